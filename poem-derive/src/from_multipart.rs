@@ -0,0 +1,135 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Result};
+
+use crate::utils::get_crate_name;
+
+/// Returns the name of the multipart field, which can be renamed with
+/// `#[multipart(rename = "...")]`.
+fn field_name(field: &syn::Field) -> Result<String> {
+    let mut name = None;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("multipart"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected `multipart(...)`")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    match nv.lit {
+                        Lit::Str(lit) => name = Some(lit.value()),
+                        lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    }
+                }
+                nested => return Err(Error::new_spanned(nested, "unknown attribute")),
+            }
+        }
+    }
+
+    Ok(name.unwrap_or_else(|| field.ident.as_ref().unwrap().to_string()))
+}
+
+pub(crate) fn generate(input: DeriveInput) -> Result<TokenStream> {
+    let crate_name = get_crate_name(false);
+    let ident = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`FromMultipart` does not support generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "`FromMultipart` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "`FromMultipart` can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut vars = Vec::new();
+    let mut parsers = Vec::new();
+    let mut values = Vec::new();
+
+    for (idx, field) in fields.iter().enumerate() {
+        let field_ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let name = field_name(field)?;
+        let var = quote::format_ident!("__field{}", idx);
+
+        vars.push(quote! {
+            let mut #var: ::std::option::Option<#ty> = ::std::option::Option::None;
+        });
+        parsers.push(quote! {
+            ::std::option::Option::Some(#name) => {
+                #var = ::std::option::Option::Some(
+                    <#ty as #crate_name::web::FromMultipartField>::from_multipart_field(
+                        field,
+                        #var.take(),
+                    )
+                    .await
+                    .map_err(|err| #crate_name::error::ParseMultipartError::InvalidField {
+                        name: ::std::string::ToString::to_string(#name),
+                        source: ::std::boxed::Box::new(err),
+                    })?,
+                );
+            }
+        });
+        values.push(quote! {
+            #field_ident: match #var
+                .or_else(<#ty as #crate_name::web::FromMultipartField>::from_missing)
+            {
+                ::std::option::Option::Some(value) => value,
+                ::std::option::Option::None => {
+                    return ::std::result::Result::Err(
+                        #crate_name::error::ParseMultipartError::MissingField(
+                            ::std::string::ToString::to_string(#name),
+                        )
+                        .into(),
+                    )
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        #[#crate_name::async_trait]
+        impl<'a> #crate_name::FromRequest<'a> for #ident {
+            async fn from_request(
+                req: &'a #crate_name::Request,
+                body: &mut #crate_name::RequestBody,
+            ) -> #crate_name::Result<Self> {
+                let mut multipart =
+                    <#crate_name::web::Multipart as #crate_name::FromRequest>::from_request(req, body)
+                        .await?;
+                #(#vars)*
+
+                while let ::std::option::Option::Some(field) = multipart.next_field().await? {
+                    match field.name() {
+                        #(#parsers)*
+                        _ => {}
+                    }
+                }
+
+                ::std::result::Result::Ok(Self {
+                    #(#values,)*
+                })
+            }
+        }
+    })
+}
@@ -7,11 +7,15 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![warn(missing_docs)]
 
+mod from_multipart;
+mod typed_path;
 mod utils;
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, AttributeArgs, FnArg, ItemFn, Member, Meta, NestedMeta, Result};
+use syn::{
+    parse_macro_input, AttributeArgs, DeriveInput, FnArg, ItemFn, Member, Meta, NestedMeta, Result,
+};
 
 /// Wrap an asynchronous function as an `Endpoint`.
 ///
@@ -93,6 +97,64 @@ fn generate_handler(internal: bool, input: TokenStream) -> Result<TokenStream> {
     Ok(expanded.into())
 }
 
+/// Define a typed path, the parameters are extracted into the fields of a
+/// struct, and the segments which can not be parsed into the types of the
+/// parameters are rejected by the router with `404 Not Found`.
+///
+/// The parameters are declared with `{name}`, `{name: Type}` or `{*name}`
+/// for the rest of the path, the default type is `String`.
+///
+/// # Example
+///
+/// ```ignore
+/// path!(pub UserPostPath = "/users/{id: u64}/posts/{slug}");
+///
+/// #[handler]
+/// fn user_post(path: UserPostPath) -> String {
+///     format!("{} {}", path.id, path.slug)
+/// }
+///
+/// let app = Route::new().at(UserPostPath::PATTERN, get(user_post));
+/// ```
+#[proc_macro]
+pub fn path(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as typed_path::TypedPathInput);
+    match typed_path::generate(input) {
+        Ok(stream) => stream.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Derive `FromRequest` for a struct which is extracted from a
+/// `multipart/form-data` body.
+///
+/// Each field is parsed with `FromMultipartField`, the field can be renamed
+/// with `#[multipart(rename = "...")]`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(FromMultipart)]
+/// struct UploadForm {
+///     title: String,
+///     description: Option<String>,
+///     files: Vec<Upload>,
+/// }
+///
+/// #[handler]
+/// async fn upload(form: UploadForm) -> String {
+///     format!("{}: {} files", form.title, form.files.len())
+/// }
+/// ```
+#[proc_macro_derive(FromMultipart, attributes(multipart))]
+pub fn derive_from_multipart(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match from_multipart::generate(input) {
+        Ok(stream) => stream.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
 #[doc(hidden)]
 #[proc_macro]
 pub fn generate_implement_middlewares(_: TokenStream) -> TokenStream {
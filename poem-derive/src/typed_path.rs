@@ -0,0 +1,156 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Attribute, Error, Ident, LitStr, Result, Token, Type, Visibility,
+};
+
+use crate::utils::get_crate_name;
+
+pub(crate) struct TypedPathInput {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    ident: Ident,
+    pattern: LitStr,
+}
+
+impl Parse for TypedPathInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let pattern = input.parse()?;
+        Ok(Self {
+            attrs,
+            vis,
+            ident,
+            pattern,
+        })
+    }
+}
+
+struct Param {
+    name: Ident,
+    ty: Type,
+}
+
+/// Returns the regex which matches the values of the type, so that the
+/// non-matching segments are rejected by the router.
+fn type_regex(ty: &Type) -> Option<&'static str> {
+    let ident = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path.segments.last()?.ident,
+        _ => return None,
+    };
+    let re = match ident.to_string().as_str() {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => r"^\+?\d+",
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => r"^[+-]?\d+",
+        "f32" | "f64" => r"^[+-]?(\d+(\.\d*)?|\.\d+)([eE][+-]?\d+)?",
+        "bool" => r"^(true|false)",
+        "Uuid" => r"^[0-9a-fA-F]{8}-?([0-9a-fA-F]{4}-?){3}[0-9a-fA-F]{12}",
+        _ => return None,
+    };
+    Some(re)
+}
+
+pub(crate) fn generate(input: TypedPathInput) -> Result<TokenStream> {
+    let crate_name = get_crate_name(false);
+    let TypedPathInput {
+        attrs,
+        vis,
+        ident,
+        pattern,
+    } = input;
+    let err = |msg: &str| Error::new(pattern.span(), msg);
+
+    let value = pattern.value();
+    let mut route_path = String::new();
+    let mut params: Vec<Param> = Vec::new();
+    let mut s = value.as_str();
+    let mut after_param = false;
+
+    while !s.is_empty() {
+        if let Some(rest) = s.strip_prefix('{') {
+            if after_param {
+                return Err(err("a parameter must be followed by `/`"));
+            }
+            let end = rest
+                .find('}')
+                .ok_or_else(|| err("missing `}` in the path"))?;
+            let (decl, catch_all) = match rest[..end].trim().strip_prefix('*') {
+                Some(decl) => (decl, true),
+                None => (rest[..end].trim(), false),
+            };
+            let (name, ty) = match decl.split_once(':') {
+                Some((name, ty)) => (name.trim(), syn::parse_str::<Type>(ty.trim())?),
+                None => (decl, syn::parse_quote!(::std::string::String)),
+            };
+            let name = syn::parse_str::<Ident>(name)
+                .map_err(|_| err(&format!("invalid parameter name `{}`", name)))?;
+            if params.iter().any(|param| param.name == name) {
+                return Err(err(&format!("duplicate parameter `{}`", name)));
+            }
+
+            if catch_all {
+                if !rest[end + 1..].is_empty() {
+                    return Err(err("the wildcard parameter must be at the end of the path"));
+                }
+                route_path.push_str(&format!("*{}", name));
+            } else {
+                match type_regex(&ty) {
+                    Some(re) => route_path.push_str(&format!(":{}<{}>", name, re)),
+                    None => route_path.push_str(&format!(":{}", name)),
+                }
+            }
+            params.push(Param { name, ty });
+            after_param = true;
+            s = &rest[end + 1..];
+        } else {
+            let end = s.find('{').unwrap_or(s.len());
+            if s[..end].contains([':', '*', '<', '>', '}']) {
+                return Err(err("invalid character in the static segment of the path"));
+            }
+            if after_param && !s.starts_with('/') {
+                return Err(err("a parameter must be followed by `/`"));
+            }
+            route_path.push_str(&s[..end]);
+            after_param = false;
+            s = &s[end..];
+        }
+    }
+
+    let fields = params.iter().map(|Param { name, ty }| quote!(#vis #name: #ty));
+    let extractors = params.iter().map(|Param { name, ty }| {
+        let name_str = name.to_string();
+        quote! {
+            #name: req
+                .raw_path_param(#name_str)
+                .and_then(|value| value.parse::<#ty>().ok())
+                .ok_or(#crate_name::error::ParsePathError)?
+        }
+    });
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #ident {
+            #(#fields,)*
+        }
+
+        impl #ident {
+            /// The path pattern to be added to the route.
+            #vis const PATTERN: &'static str = #route_path;
+        }
+
+        #[#crate_name::async_trait]
+        impl<'a> #crate_name::FromRequest<'a> for #ident {
+            async fn from_request(
+                req: &'a #crate_name::Request,
+                _body: &mut #crate_name::RequestBody,
+            ) -> #crate_name::Result<Self> {
+                ::std::result::Result::Ok(Self {
+                    #(#extractors,)*
+                })
+            }
+        }
+    })
+}
@@ -134,6 +134,12 @@ impl Body {
         size_hint.lower() == 0 && size_hint.upper() == Some(0)
     }
 
+    /// Returns the size of this body if it is known, such as the bodies
+    /// created from the bytes.
+    pub(crate) fn exact_size(&self) -> Option<u64> {
+        hyper::body::HttpBody::size_hint(&self.0).exact()
+    }
+
     /// Consumes this body object to return a [`Bytes`] that contains all data.
     pub async fn into_bytes(self) -> Result<Bytes, ReadBodyError> {
         hyper::body::to_bytes(self.0)
@@ -36,7 +36,7 @@ pub use inspect_err::InspectError;
 pub use map::Map;
 pub use map_to_response::MapToResponse;
 #[cfg(feature = "prometheus")]
-pub use prometheus_exporter::PrometheusExporter;
+pub use prometheus_exporter::{prometheus_exporter, PrometheusExporter};
 #[cfg(feature = "static-files")]
 pub use static_files::{StaticFileEndpoint, StaticFilesEndpoint};
 pub use to_response::ToResponse;
@@ -1,11 +1,63 @@
 use libopentelemetry::{sdk::Resource, KeyValue};
-use libprometheus::{Encoder, TextEncoder};
+use libprometheus::{Encoder, Registry, TextEncoder};
 
 use crate::{
     http::{Method, StatusCode},
     Endpoint, IntoEndpoint, Request, Response, Result,
 };
 
+fn encode_metrics(req: &Request, registry: &Registry) -> Result<Response, StatusCode> {
+    if req.method() != Method::GET {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into());
+    }
+
+    let encoder = TextEncoder::new();
+    let metric_families = registry.gather();
+    let mut result = Vec::new();
+    match encoder.encode(&metric_families, &mut result) {
+        Ok(()) => Ok(Response::builder().content_type("text/plain").body(result)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Create an endpoint that exports the metrics of the Prometheus registry in
+/// the text format, to be mounted at `/metrics`.
+///
+/// See also [`PrometheusMetrics`](crate::middleware::PrometheusMetrics).
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::{make_sync, prometheus_exporter},
+///     middleware::PrometheusMetrics,
+///     EndpointExt, Route,
+/// };
+///
+/// let metrics = PrometheusMetrics::new();
+/// let app = Route::new()
+///     .at("/", make_sync(|_| "hello"))
+///     .at("/metrics", prometheus_exporter(metrics.registry().clone()))
+///     .with(metrics);
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "prometheus")))]
+pub fn prometheus_exporter(registry: Registry) -> impl Endpoint<Output = Response> {
+    PrometheusRegistryEndpoint { registry }
+}
+
+struct PrometheusRegistryEndpoint {
+    registry: Registry,
+}
+
+#[async_trait::async_trait]
+impl Endpoint for PrometheusRegistryEndpoint {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        Ok(encode_metrics(&req, &self.registry)?)
+    }
+}
+
 /// An endpoint that exports metrics for Prometheus.
 #[cfg_attr(docsrs, doc(cfg(feature = "prometheus")))]
 #[derive(Default)]
@@ -49,16 +101,6 @@ impl Endpoint for PrometheusExporterEndpoint {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Result<Self::Output> {
-        if req.method() != Method::GET {
-            return Ok(StatusCode::METHOD_NOT_ALLOWED.into());
-        }
-
-        let encoder = TextEncoder::new();
-        let metric_families = self.exporter.registry().gather();
-        let mut result = Vec::new();
-        match encoder.encode(&metric_families, &mut result) {
-            Ok(()) => Ok(Response::builder().content_type("text/plain").body(result)),
-            Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
-        }
+        Ok(encode_metrics(&req, self.exporter.registry())?)
     }
 }
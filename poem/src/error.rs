@@ -8,7 +8,7 @@ use std::{
 };
 
 use headers::{ContentRange, HeaderMapExt};
-use http::{Extensions, Method};
+use http::{Extensions, HeaderMap, Method};
 
 use crate::{http::StatusCode, IntoResponse, Response};
 
@@ -612,8 +612,76 @@ define_simple_errors!(
 
     /// Error occurred in the router.
     (MethodNotAllowedError, METHOD_NOT_ALLOWED, "method not allowed");
+
+    /// The client did not present a certificate in the TLS handshake.
+    (MissingClientCertError, UNAUTHORIZED, "missing client certificate");
+
+    /// The connection is not accepted by a TLS listener.
+    (MissingTlsInfoError, BAD_REQUEST, "the connection is not secured by TLS");
+
+    /// The rate limit of [`RateLimit`](crate::middleware::RateLimit) is exceeded.
+    (TooManyRequestsError, TOO_MANY_REQUESTS, "too many requests");
+
+    /// The endpoint did not complete within the duration of [`Timeout`](crate::middleware::Timeout).
+    (TimeoutError, GATEWAY_TIMEOUT, "timeout");
+
+    /// The queue of [`ConcurrencyLimit`](crate::middleware::ConcurrencyLimit) is full.
+    (QueueFullError, SERVICE_UNAVAILABLE, "the queue is full");
+
+    /// The circuit of [`CircuitBreaker`](crate::middleware::CircuitBreaker) is open.
+    (CircuitOpenError, SERVICE_UNAVAILABLE, "the circuit is open");
+
+    /// The client IP is not allowed by [`IpFilter`](crate::middleware::IpFilter).
+    (IpNotAllowedError, FORBIDDEN, "the ip address is not allowed");
+
+    /// The credentials are missing or rejected by [`BasicAuth`](crate::middleware::BasicAuth) or [`BearerAuth`](crate::middleware::BearerAuth).
+    (UnauthorizedError, UNAUTHORIZED, "unauthorized");
+
+    /// The API key does not have the scopes required by [`ApiKey`](crate::middleware::ApiKey).
+    (InsufficientScopeError, FORBIDDEN, "insufficient scope");
+
+    /// The service is in the maintenance mode of [`Maintenance`](crate::middleware::Maintenance).
+    (MaintenanceError, SERVICE_UNAVAILABLE, "the service is under maintenance");
+
+    /// None of the supported media types is accepted by the `Accept` header of the request.
+    (NotAcceptableError, NOT_ACCEPTABLE, "not acceptable");
 );
 
+struct ResponseHeaders(HeaderMap);
+
+/// An extension trait for the errors whose responses need additional headers,
+/// such as `Retry-After` for [`TooManyRequestsError`] and
+/// [`MaintenanceError`], or `WWW-Authenticate` for [`UnauthorizedError`].
+pub(crate) trait ResponseErrorExt:
+    ResponseError + StdError + Send + Sync + Sized + 'static
+{
+    /// Creates an error whose response contains the specified headers, it can
+    /// still be downcast to the original error.
+    fn with_headers(self, headers: HeaderMap) -> Error {
+        let mut err = Error {
+            as_response: AsResponse::Fn(
+                |err| {
+                    let mut resp = err
+                        .downcast_ref::<Self>()
+                        .expect("valid error")
+                        .as_response();
+                    if let Some(ResponseHeaders(headers)) = err.data::<ResponseHeaders>() {
+                        resp.headers_mut().extend(headers.clone());
+                    }
+                    resp
+                },
+                |err| err.downcast_ref::<Self>().expect("valid error").status(),
+            ),
+            source: Some(ErrorSource::BoxedError(Box::new(self))),
+            extensions: Extensions::default(),
+        };
+        err.set_data(ResponseHeaders(headers));
+        err
+    }
+}
+
+impl<T: ResponseError + StdError + Send + Sync + 'static> ResponseErrorExt for T {}
+
 /// A possible error value when reading the body.
 #[derive(Debug, thiserror::Error)]
 pub enum ReadBodyError {
@@ -681,6 +749,34 @@ impl ResponseError for GetDataError {
     }
 }
 
+/// A possible error value when resolving a value from the
+/// [`Container`](crate::web::Container).
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum ResolveError {
+    /// The container is not added to the request.
+    #[error("the container was not found, add it with the `AddContainer` middleware")]
+    ContainerNotFound,
+
+    /// The type is not registered in the container.
+    #[error(
+        "type `{type_name}` is not registered in the container, registered types: [{}]",
+        .registered.join(", ")
+    )]
+    NotRegistered {
+        /// The name of the type
+        type_name: &'static str,
+
+        /// The names of the registered types
+        registered: Vec<&'static str>,
+    },
+}
+
+impl ResponseError for ResolveError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// A possible error value when parsing form.
 #[derive(Debug, thiserror::Error)]
 pub enum ParseFormError {
@@ -707,6 +803,24 @@ impl ResponseError for ParseFormError {
     }
 }
 
+/// A possible error value when verifying the CSRF token.
+#[derive(Debug, thiserror::Error)]
+pub enum CsrfError {
+    /// The request does not contain a CSRF token.
+    #[error("missing csrf token")]
+    MissingToken,
+
+    /// The CSRF token is invalid or expired.
+    #[error("invalid csrf token")]
+    InvalidToken,
+}
+
+impl ResponseError for CsrfError {
+    fn status(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
 /// A possible error value when parsing JSON.
 #[derive(Debug, thiserror::Error)]
 #[error("parse: {0}")]
@@ -745,7 +859,7 @@ impl ResponseError for ParseXmlError {
 /// A missing xml Content-Type error value when parsing header.
 #[cfg(feature = "xml")]
 #[derive(Debug, thiserror::Error)]
-#[error("Missing `Content-Type: application/xml`")]
+#[error("Missing `Content-Type: application/xml` or `Content-Type: text/xml`")]
 pub struct MissingXmlContentTypeError;
 
 #[cfg(feature = "xml")]
@@ -755,6 +869,180 @@ impl ResponseError for MissingXmlContentTypeError {
     }
 }
 
+/// A possible error value when parsing MessagePack.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, thiserror::Error)]
+#[error("parse: {0}")]
+pub struct ParseMsgPackError(#[from] pub rmp_serde::decode::Error);
+
+#[cfg(feature = "msgpack")]
+impl ResponseError for ParseMsgPackError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// A missing MessagePack Content-Type error value when parsing header.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, thiserror::Error)]
+#[error("Missing `Content-Type: application/msgpack`")]
+pub struct MissingMsgPackContentTypeError;
+
+#[cfg(feature = "msgpack")]
+impl ResponseError for MissingMsgPackContentTypeError {
+    fn status(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+/// A possible error value when parsing CBOR.
+#[cfg(feature = "cbor")]
+#[derive(Debug, thiserror::Error)]
+#[error("parse: {0}")]
+pub struct ParseCborError(#[from] pub ciborium::de::Error<std::io::Error>);
+
+#[cfg(feature = "cbor")]
+impl ResponseError for ParseCborError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// A missing CBOR Content-Type error value when parsing header.
+#[cfg(feature = "cbor")]
+#[derive(Debug, thiserror::Error)]
+#[error("Missing `Content-Type: application/cbor`")]
+pub struct MissingCborContentTypeError;
+
+#[cfg(feature = "cbor")]
+impl ResponseError for MissingCborContentTypeError {
+    fn status(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+/// A possible error value when decoding Protocol Buffers.
+#[cfg(feature = "protobuf")]
+#[derive(Debug, thiserror::Error)]
+#[error("parse: {0}")]
+pub struct ParseProtobufError(#[from] pub prost::DecodeError);
+
+#[cfg(feature = "protobuf")]
+impl ResponseError for ParseProtobufError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// A missing Protocol Buffers Content-Type error value when parsing header.
+#[cfg(feature = "protobuf")]
+#[derive(Debug, thiserror::Error)]
+#[error("Missing `Content-Type: application/x-protobuf`")]
+pub struct MissingProtobufContentTypeError;
+
+#[cfg(feature = "protobuf")]
+impl ResponseError for MissingProtobufContentTypeError {
+    fn status(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+/// A possible error value when validating the value extracted by
+/// [`Valid`](crate::web::Valid).
+///
+/// The response is `422 Unprocessable Entity` with a JSON body listing the
+/// errors of the fields, the fields of the nested structs and lists are
+/// separated by dots, such as `address.city` and `items[0].name`.
+///
+/// ```json
+/// {
+///     "errors": [
+///         {
+///             "field": "name",
+///             "code": "length",
+///             "message": null,
+///             "params": { "min": 1, "value": "" }
+///         }
+///     ]
+/// }
+/// ```
+#[cfg(feature = "validator")]
+#[cfg_attr(docsrs, doc(cfg(feature = "validator")))]
+#[derive(Debug, thiserror::Error)]
+#[error("validate: {0}")]
+pub struct ValidateError(#[from] pub validator::ValidationErrors);
+
+#[cfg(feature = "validator")]
+impl ValidateError {
+    fn field_errors(&self) -> Vec<serde_json::Value> {
+        fn collect(
+            prefix: &str,
+            errors: &validator::ValidationErrors,
+            res: &mut Vec<serde_json::Value>,
+        ) {
+            for (field, kind) in errors.errors() {
+                let field = if prefix.is_empty() {
+                    field.to_string()
+                } else {
+                    format!("{}.{}", prefix, field)
+                };
+                match kind {
+                    validator::ValidationErrorsKind::Field(errors) => {
+                        res.extend(errors.iter().map(|err| {
+                            serde_json::json!({
+                                "field": field,
+                                "code": err.code,
+                                "message": err.message,
+                                "params": err.params,
+                            })
+                        }));
+                    }
+                    validator::ValidationErrorsKind::Struct(errors) => collect(&field, errors, res),
+                    validator::ValidationErrorsKind::List(items) => {
+                        for (idx, errors) in items {
+                            collect(&format!("{}[{}]", field, idx), errors, res);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut res = Vec::new();
+        collect("", &self.0, &mut res);
+        res.sort_by(|a, b| a["field"].as_str().cmp(&b["field"].as_str()));
+        res
+    }
+}
+
+#[cfg(feature = "validator")]
+impl ResponseError for ValidateError {
+    fn status(&self) -> StatusCode {
+        StatusCode::UNPROCESSABLE_ENTITY
+    }
+
+    fn as_response(&self) -> Response {
+        Response::builder()
+            .status(self.status())
+            .content_type("application/json; charset=utf-8")
+            .body(serde_json::json!({ "errors": self.field_errors() }).to_string())
+    }
+}
+
+/// A possible error value when parsing the nested query string or form body,
+/// see [`UrlEncodedConfig`](crate::web::UrlEncodedConfig).
+#[cfg(feature = "serde-qs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-qs")))]
+#[derive(Debug, thiserror::Error)]
+#[error("parse: {0}")]
+pub struct ParseQsError(#[from] pub serde_qs::Error);
+
+#[cfg(feature = "serde-qs")]
+impl ResponseError for ParseQsError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 /// A possible error value when parsing query.
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
@@ -766,10 +1054,29 @@ impl ResponseError for ParseQueryError {
     }
 }
 
+/// A possible error value when parsing the pagination parameters.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum ParsePaginationError {
+    /// The page is zero.
+    #[error("the page must be greater than 0")]
+    InvalidPage,
+
+    /// The number of the items per page is zero.
+    #[error("the per_page must be greater than 0")]
+    InvalidPerPage,
+}
+
+impl ResponseError for ParsePaginationError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 /// A possible error value when parsing multipart.
 #[cfg(feature = "multipart")]
 #[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum ParseMultipartError {
     /// Invalid content type.
     #[error("invalid content type `{0}`, expect: `multipart/form-data`")]
@@ -790,6 +1097,45 @@ pub enum ParseMultipartError {
     /// Io error
     #[error("io: {0}")]
     Io(#[from] std::io::Error),
+
+    /// The number of the files exceeds the limit of
+    /// [`MultipartConfig::max_files`](crate::web::MultipartConfig::max_files).
+    #[error("the number of files exceeds the limit {0}")]
+    TooManyFiles(usize),
+
+    /// The content type of the file is not allowed by
+    /// [`MultipartConfig::allowed_content_types`](crate::web::MultipartConfig::allowed_content_types).
+    #[error("the content type `{0}` of the file is not allowed")]
+    ContentTypeNotAllowed(String),
+
+    /// The required field is missing.
+    #[error("the field `{0}` is required")]
+    MissingField(String),
+
+    /// The value of the field is invalid.
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+
+    /// Failed to parse the field.
+    #[error("failed to parse the field `{name}`: {source}")]
+    InvalidField {
+        /// The name of the field
+        name: String,
+        /// The error occurred while parsing the field
+        source: Box<ParseMultipartError>,
+    },
+}
+
+#[cfg(feature = "multipart")]
+fn is_size_exceeded(err: &multer::Error) -> bool {
+    match err {
+        multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. } => true,
+        multer::Error::StreamReadFailed(err) => matches!(
+            err.downcast_ref::<multer::Error>(),
+            Some(err) if is_size_exceeded(err)
+        ),
+        _ => false,
+    }
 }
 
 #[cfg(feature = "multipart")]
@@ -798,9 +1144,25 @@ impl ResponseError for ParseMultipartError {
         match self {
             ParseMultipartError::InvalidContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             ParseMultipartError::ContentTypeRequired => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ParseMultipartError::Multipart(err) if is_size_exceeded(err) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
             ParseMultipartError::Multipart(_) => StatusCode::BAD_REQUEST,
             ParseMultipartError::Utf8(_) => StatusCode::BAD_REQUEST,
+            ParseMultipartError::Io(err)
+                if matches!(
+                    err.get_ref().and_then(|err| err.downcast_ref::<multer::Error>()),
+                    Some(err) if is_size_exceeded(err)
+                ) =>
+            {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
             ParseMultipartError::Io(_) => StatusCode::BAD_REQUEST,
+            ParseMultipartError::TooManyFiles(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ParseMultipartError::ContentTypeNotAllowed(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ParseMultipartError::MissingField(_) => StatusCode::BAD_REQUEST,
+            ParseMultipartError::InvalidValue(_) => StatusCode::BAD_REQUEST,
+            ParseMultipartError::InvalidField { source, .. } => source.status(),
         }
     }
 }
@@ -868,6 +1230,30 @@ impl ResponseError for UpgradeError {
     }
 }
 
+/// An error returned when none of the ranges requested by the `Range` header
+/// can be satisfied, see [`Range`](crate::web::Range).
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+#[error("range not satisfiable")]
+pub struct RangeNotSatisfiableError {
+    /// Content length
+    pub size: u64,
+}
+
+impl ResponseError for RangeNotSatisfiableError {
+    fn status(&self) -> StatusCode {
+        StatusCode::RANGE_NOT_SATISFIABLE
+    }
+
+    fn as_response(&self) -> Response {
+        let mut resp = Response::builder()
+            .status(self.status())
+            .body(self.to_string());
+        resp.headers_mut()
+            .typed_insert(ContentRange::unsatisfied_bytes(self.size));
+        resp
+    }
+}
+
 /// A possible error value when processing static files.
 #[derive(Debug, thiserror::Error)]
 pub enum StaticFileError {
@@ -951,6 +1337,7 @@ impl ResponseError for SizedLimitError {
 
 /// A possible error value occurred when adding a route.
 #[derive(Debug, thiserror::Error, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum RouteError {
     /// Invalid path
     #[error("invalid path: {0}")]
@@ -960,6 +1347,28 @@ pub enum RouteError {
     #[error("duplicate path: {0}")]
     Duplicate(String),
 
+    /// Duplicate route name
+    #[error("duplicate route name: {0}")]
+    DuplicateName(String),
+
+    /// Unknown segment matcher
+    #[error("unknown segment matcher: {0}")]
+    UnknownSegmentMatcher(String),
+
+    /// The path overlaps ambiguously with an existing path, see
+    /// [`Route::strict_conflicts`](crate::Route::strict_conflicts).
+    #[error("path `{path}` conflicts with `{existing}`, the requests matching both are routed to `{matched_first}`")]
+    Conflict {
+        /// Path
+        path: String,
+
+        /// The existing path
+        existing: String,
+
+        /// The path which is matched first
+        matched_first: String,
+    },
+
     /// Invalid regex in path
     #[error("invalid regex in path: {path}")]
     InvalidRegex {
@@ -977,6 +1386,57 @@ impl ResponseError for RouteError {
     }
 }
 
+/// A possible error value occurred when generating the URL of a named route.
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum UrlForError {
+    /// Route not found
+    #[error("route not found: {0}")]
+    NotFound(String),
+
+    /// Missing path parameter
+    #[error("missing parameter `{param}` for route `{name}`")]
+    MissingParam {
+        /// Route name
+        name: String,
+
+        /// Parameter name
+        param: String,
+    },
+
+    /// The path of the route contains an unnamed regex or wildcard
+    #[error("the path of route `{0}` contains an unnamed parameter")]
+    UnnamedParam(String),
+}
+
+impl ResponseError for UrlForError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred in the
+/// [`VersionedRoute`](crate::VersionedRoute).
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum ApiVersionError {
+    /// The request does not specify the version, and there is no default
+    /// version
+    #[error("missing api version")]
+    Missing,
+
+    /// The version specified by the request is not supported
+    #[error("unsupported api version: {0}")]
+    Unsupported(String),
+}
+
+impl ResponseError for ApiVersionError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiVersionError::Missing => StatusCode::BAD_REQUEST,
+            ApiVersionError::Unsupported(_) => StatusCode::NOT_FOUND,
+        }
+    }
+}
+
 /// A possible error value occurred in the `Cors` middleware.
 #[derive(Debug, thiserror::Error, Eq, PartialEq)]
 pub enum CorsError {
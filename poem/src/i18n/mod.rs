@@ -61,6 +61,7 @@
 
 mod args;
 mod locale;
+mod preferred_languages;
 mod resources;
 
 pub use fluent_langneg::NegotiationStrategy;
@@ -69,5 +70,6 @@ pub use unic_langid;
 pub use self::{
     args::I18NArgs,
     locale::Locale,
+    preferred_languages::PreferredLanguages,
     resources::{I18NBundle, I18NResources, I18NResourcesBuilder},
 };
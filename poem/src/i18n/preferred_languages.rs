@@ -0,0 +1,274 @@
+use std::str::FromStr;
+
+use http::header;
+use smallvec::SmallVec;
+use unic_langid::LanguageIdentifier;
+
+use crate::{
+    i18n::{I18NResources, Locale},
+    FromRequest, Request, RequestBody, Result,
+};
+
+/// An extractor that parses the `Accept-Language` header into the languages
+/// in the order of preference.
+///
+/// The languages are sorted by the quality values, and the languages with the
+/// same quality keep the order in the header. The languages with `q=0` are
+/// not acceptable and removed, and the wildcard `*` means that any language is
+/// acceptable.
+///
+/// Use [`PreferredLanguages::negotiate`] to resolve the best [`Locale`]
+/// against the [`I18NResources`], the fallback chain is resolved from the
+/// preferred languages, e.g. `en-GB` falls back to `en` and `en-US`, and
+/// finally to the default language of the resources.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::header,
+///     i18n::{I18NResources, PreferredLanguages},
+///     test::TestClient,
+///     web::Data,
+///     EndpointExt, Route,
+/// };
+///
+/// let resources = I18NResources::builder()
+///     .add_ftl("en-US", "hello-world = hello world!")
+///     .add_ftl("fr", "hello-world = bonjour le monde!")
+///     .build()
+///     .unwrap();
+///
+/// #[handler]
+/// async fn index(languages: PreferredLanguages, resources: Data<&I18NResources>) -> String {
+///     let locale = languages.negotiate(&resources);
+///     format!(
+///         "{}: {}",
+///         locale.language().unwrap(),
+///         locale.text("hello-world").unwrap()
+///     )
+/// }
+///
+/// let app = Route::new().at("/", index).data(resources);
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .get("/")
+///     .header(header::ACCEPT_LANGUAGE, "de;q=0.9, fr-CA;q=0.8, en;q=0.5")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("fr: bonjour le monde!").await;
+/// # });
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct PreferredLanguages {
+    languages: SmallVec<[(LanguageIdentifier, u16); 8]>,
+    any: bool,
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for PreferredLanguages {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(PreferredLanguages::parse)
+            .unwrap_or_default())
+    }
+}
+
+impl PreferredLanguages {
+    /// Parse the value of the `Accept-Language` header, the invalid entries
+    /// are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut languages = SmallVec::<[_; 8]>::new();
+        let mut any = false;
+
+        for s in value.split(',').map(str::trim) {
+            let (name, quality) = match parse_language(s) {
+                Some(res) => res,
+                None => continue,
+            };
+            if quality == 0 {
+                continue;
+            }
+            if name == "*" {
+                any = true;
+            } else if let Ok(language) = LanguageIdentifier::from_str(name) {
+                if languages.iter().all(|(lang, _)| *lang != language) {
+                    languages.push((language, quality));
+                }
+            }
+        }
+
+        languages.sort_by(|(_, a), (_, b)| b.cmp(a));
+        Self { languages, any }
+    }
+
+    /// Returns the preferred languages in the order of preference.
+    pub fn languages(&self) -> impl Iterator<Item = &LanguageIdentifier> {
+        self.languages.iter().map(|(language, _)| language)
+    }
+
+    /// Returns the quality value of the language in the range `0.0..=1.0`, or
+    /// `None` if the language is not listed.
+    pub fn quality(&self, language: &LanguageIdentifier) -> Option<f32> {
+        self.languages
+            .iter()
+            .find(|(lang, _)| lang == language)
+            .map(|(_, quality)| *quality as f32 / 1000.0)
+    }
+
+    /// Returns `true` if no language is preferred.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+    }
+
+    /// Returns `true` if the wildcard `*` is listed, which means that any
+    /// language is acceptable.
+    #[inline]
+    pub fn accepts_any(&self) -> bool {
+        self.any
+    }
+
+    /// Negotiates the languages with the resources and returns the best
+    /// [`Locale`], see [`I18NResources::negotiate_languages`].
+    pub fn negotiate(&self, resources: &I18NResources) -> Locale {
+        let languages = self.languages().collect::<SmallVec<[_; 8]>>();
+        Locale::new(resources.negotiate_languages(&languages))
+    }
+}
+
+fn parse_language(value: &str) -> Option<(&str, u16)> {
+    let mut parts = value.split(';');
+    let name = parts.next()?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let quality = match parts.next() {
+        Some(quality) => parse_quality(quality)?,
+        None => 1000,
+    };
+    Some((name, quality))
+}
+
+/// Parse the quality value, which is in the range `0.0..=1.0` with at most
+/// three digits after the decimal point.
+fn parse_quality(value: &str) -> Option<u16> {
+    let (name, q) = value.split_once('=')?;
+    if name.trim() != "q" {
+        return None;
+    }
+    let q = q.trim();
+    let (int, frac) = q.split_once('.').unwrap_or((q, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac = format!("{:0<3}", frac).parse::<u16>().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unic_langid::{langid, langids};
+
+    use super::*;
+
+    fn parse_accept_languages(value: &str) -> Vec<LanguageIdentifier> {
+        PreferredLanguages::parse(value)
+            .languages()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_accept_languages() {
+        assert_eq!(
+            parse_accept_languages("zh-CN;q=0.5,en-US;q=0.7,fr;q=0.3"),
+            langids!("en-US", "zh-CN", "fr")
+        );
+
+        assert_eq!(
+            parse_accept_languages("zh-CN ; q=0.5,en-US;q = 0.7,   fr;q=0.3"),
+            langids!("en-US", "zh-CN", "fr")
+        );
+
+        assert_eq!(
+            parse_accept_languages("en-US;q=0.7,zh-CN,fr;q=0.3"),
+            langids!("zh-CN", "en-US", "fr")
+        );
+    }
+
+    #[test]
+    fn test_parse_quality() {
+        let languages = PreferredLanguages::parse("de;q=0, fr;q=1.0, en;q=0.05, *;q=0.1, it;q=2");
+        assert_eq!(
+            languages.languages().cloned().collect::<Vec<_>>(),
+            langids!("fr", "en")
+        );
+        assert!(languages.accepts_any());
+        assert_eq!(languages.quality(&langid!("en")), Some(0.05));
+        assert_eq!(languages.quality(&langid!("de")), None);
+
+        for value in ["q=0.1234", "q=1.5", "q=-1", "q=abc", "p=0.5", "q=.5"] {
+            assert_eq!(parse_quality(value), None, "{}", value);
+        }
+        assert_eq!(parse_quality("q=0.5"), Some(500));
+        assert_eq!(parse_quality("q=1"), Some(1000));
+
+        assert!(PreferredLanguages::parse("").is_empty());
+        let languages = PreferredLanguages::parse("*");
+        assert!(languages.is_empty());
+        assert!(languages.accepts_any());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let resources = I18NResources::builder()
+            .add_ftl("en-US", "hello = hello")
+            .add_ftl("en-GB", "hello = hullo")
+            .add_ftl("fr", "hello = bonjour")
+            .add_ftl("zh-CN", "hello = 你好")
+            .build()
+            .unwrap();
+
+        let negotiate = |value: &str| {
+            let locale = PreferredLanguages::parse(value).negotiate(&resources);
+            (
+                locale.language().unwrap().to_string(),
+                locale.text("hello").unwrap(),
+            )
+        };
+
+        assert_eq!(
+            negotiate("en-GB"),
+            ("en-GB".to_string(), "hullo".to_string())
+        );
+        assert_eq!(
+            negotiate("fr-CA, en;q=0.5"),
+            ("fr".to_string(), "bonjour".to_string())
+        );
+        assert_eq!(
+            negotiate("zh;q=0.8, de"),
+            ("zh-CN".to_string(), "你好".to_string())
+        );
+        // fallback to the default language
+        assert_eq!(
+            negotiate("de, ja"),
+            ("en-US".to_string(), "hello".to_string())
+        );
+        assert_eq!(
+            negotiate("fr;q=0, *"),
+            ("en-US".to_string(), "hello".to_string())
+        );
+    }
+}
@@ -222,6 +222,12 @@ impl I18NResources {
 pub struct I18NBundle(SmallVec<[Arc<FluentBundle>; 8]>);
 
 impl I18NBundle {
+    /// Returns the best matched language, which is the first language in the
+    /// fallback chain.
+    pub fn language(&self) -> Option<&LanguageIdentifier> {
+        self.0.first().and_then(|bundle| bundle.locales.first())
+    }
+
     fn message(&self, id: impl AsRef<str>) -> Result<(&FluentBundle, FluentMessage), I18NError> {
         let id = id.as_ref();
         for bundle in &self.0 {
@@ -231,32 +231,52 @@
 //! |Feature           |Description                     |
 //! |------------------|--------------------------------|
 //! | server | Server and listener APIs(enable by default) |
+//! |cbor              | Support for CBOR               |
 //! |compression  | Support decompress request body and compress response body |
 //! |cookie            | Support for Cookie             |
 //! |csrf | Support for Cross-Site Request Forgery (CSRF) protection |
+//! |forward-auth      | Support for ForwardAuth middleware |
+//! |jwt               | Support for JSON Web Token validation with [`jsonwebtoken`](https://crates.io/crates/jsonwebtoken) |
+//! |mirror            | Support for Mirror middleware |
+//! |msgpack           | Support for MessagePack        |
 //! |multipart         | Support for Multipart          |
+//! |negotiate         | Support for content negotiation with JSON, XML, MessagePack and CBOR |
 //! |native-tls        | Support for HTTP server over TLS with [`native-tls`](https://crates.io/crates/native-tls)  |
 //! |openssl-tls        | Support for HTTP server over TLS with [`openssl-tls`](https://crates.io/crates/openssl)  |
+//! |ocsp              | Support for OCSP stapling with [`rustls`](https://crates.io/crates/rustls) and ACME |
 //! |opentelemetry     | Support for opentelemetry    |
 //! |prometheus        | Support for Prometheus       |
+//! |protobuf          | Support for Protocol Buffers   |
+//! |redis-cache       | Support for RedisCacheStore  |
+//! |redis-rate-limit  | Support for RedisRateLimitStore |
 //! |redis-session     | Support for RedisSession     |
+//! |request-id        | Support for RequestId middleware |
 //! |rustls            | Support for HTTP server over TLS with [`rustls`](https://crates.io/crates/rustls)  |
+//! |secure-headers    | Support for SecureHeaders middleware |
+//! |sentry            | Support for Sentry middleware |
+//! |serde-qs          | Support for the nested query strings and form bodies with [`serde_qs`](https://crates.io/crates/serde_qs) |
 //! |session           | Support for session    |
+//! |socket-options    | Support for the socket options of `TcpListener` and the socket file owner of `UnixListener` |
 //! |sse               | Support Server-Sent Events (SSE)       |
 //! |tempfile          | Support for [`tempfile`](https://crates.io/crates/tempfile) |
 //! |tower-compat      | Adapters for `tower::Layer` and `tower::Service`. |
+//! |upgrade           | Support for handing over the listening sockets to a new process on Unix |
+//! |vsock             | Support for VM sockets (`AF_VSOCK`) listener on Linux |
 //! |websocket         | Support for WebSocket          |
 //! | anyhow        | Integrate with the [`anyhow`](https://crates.io/crates/anyhow) crate. |
 //! | eyre06        | Integrate with version 0.6.x of the [`eyre`](https://crates.io/crates/eyre) crate. |
+//! | validator     | Integrate with the [`validator`](https://crates.io/crates/validator) crate. |
 //! | i18n          | Support for internationalization |
 //! | acme | Support for ACME(Automatic Certificate Management Environment) |
+//! | acme-cloudflare | Support for the ACME `DNS-01` challenge with [Cloudflare](https://www.cloudflare.com/) |
+//! | acme-route53 | Support for the ACME `DNS-01` challenge with [Amazon Route 53](https://aws.amazon.com/route53/) |
 //! | tokio-metrics | Integrate with the [`tokio-metrics`](https://crates.io/crates/tokio-metrics) crate. |
 //! | embed  | Integrate with [`rust-embed`](https://crates.io/crates/rust-embed) crate. |
 //! | xml | Integrate with [`quick-xml`](https://crates.io/crates/quick-xml) crate. |
 
 #![doc(html_favicon_url = "https://raw.githubusercontent.com/poem-web/poem/master/favicon.ico")]
 #![doc(html_logo_url = "https://raw.githubusercontent.com/poem-web/poem/master/logo.png")]
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 #![deny(private_in_public, unreachable_pub)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![warn(missing_docs)]
@@ -280,6 +300,9 @@ pub mod web;
 
 #[doc(inline)]
 pub use http;
+#[cfg(feature = "jwt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+pub use jsonwebtoken;
 
 mod addr;
 mod body;
@@ -295,12 +318,16 @@ pub use body::Body;
 pub use endpoint::{Endpoint, EndpointExt, IntoEndpoint};
 pub use error::{Error, Result};
 pub use middleware::Middleware;
-pub use poem_derive::handler;
+#[cfg(feature = "multipart")]
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+pub use poem_derive::FromMultipart;
+pub use poem_derive::{handler, path};
 pub use request::{OnUpgrade, Request, RequestBuilder, RequestParts, Upgraded};
 pub use response::{Response, ResponseBuilder, ResponseParts};
 pub use route::{
-    connect, delete, get, head, options, patch, post, put, trace, Route, RouteDomain, RouteMethod,
-    RouteScheme,
+    connect, delete, get, head, options, patch, post, put, trace, CaseSensitivity, Route,
+    RouteDomain, RouteInfo, RouteMethod, RoutePredicate, RouteScheme, SegmentMatcher,
+    TrailingSlashPolicy, VersionSource, VersionedRoute,
 };
 #[cfg(feature = "server")]
 pub use server::Server;
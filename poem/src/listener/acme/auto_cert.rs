@@ -1,28 +1,50 @@
 use std::{
-    collections::HashMap,
     fmt::{self, Debug, Formatter},
-    path::PathBuf,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
     sync::Arc,
+    time::Duration,
 };
 
 use http::Uri;
-use parking_lot::RwLock;
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use tokio_rustls::rustls::RootCertStore;
 
 use crate::listener::acme::{
-    builder::AutoCertBuilder, endpoint::Http01Endpoint, keypair::KeyPair, ChallengeType,
+    builder::AutoCertBuilder, cache, client::AcmeClient, endpoint::Http01Endpoint,
+    event::EventCallback, jose::ExternalAccountKey, keypair::KeyPair, proxy::Proxy,
+    resolver::CertGroup, solver::TlsAlpn01Keys, CertEvent, CertificateCache, ChallengeSolver,
+    ChallengeType, Http01TokenStore, KeyType, RevocationReason,
 };
 
+pub(crate) type TermsOfServiceCallback = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
 /// ACME configuration
+///
+/// Cloning an `AutoCert` is cheap, and the clones share the same ACME account
+/// and certificate, so a clone can be kept to manage the certificate after
+/// the original one has been passed to the listener.
+#[derive(Clone)]
 pub struct AutoCert {
     pub(crate) directory_url: Uri,
-    pub(crate) domains: Vec<String>,
+    pub(crate) groups: Vec<CertGroup>,
     pub(crate) contacts: Vec<String>,
-    pub(crate) key_pair: Arc<KeyPair>,
     pub(crate) challenge_type: ChallengeType,
-    pub(crate) keys_for_http01: Option<Arc<RwLock<HashMap<String, String>>>>,
-    pub(crate) cache_path: Option<PathBuf>,
-    pub(crate) cache_cert: Option<Vec<u8>>,
-    pub(crate) cache_key: Option<Vec<u8>>,
+    pub(crate) key_type: KeyType,
+    pub(crate) preferred_chain: Option<String>,
+    pub(crate) solver: Arc<dyn ChallengeSolver>,
+    pub(crate) keys_for_http01: Option<Http01TokenStore>,
+    pub(crate) keys_for_tls_alpn01: TlsAlpn01Keys,
+    pub(crate) eab: Option<ExternalAccountKey>,
+    pub(crate) terms_of_service: Option<TermsOfServiceCallback>,
+    pub(crate) cache: Option<Arc<dyn CertificateCache>>,
+    pub(crate) proxy: Option<Proxy>,
+    pub(crate) directory_root_certs: Option<RootCertStore>,
+    pub(crate) on_event: Option<EventCallback>,
+    #[cfg(feature = "ocsp")]
+    pub(crate) ocsp_stapling: bool,
+    pub(crate) poll_interval: Duration,
+    pub(crate) poll_timeout: Duration,
+    pub(crate) client: Arc<Mutex<Option<AcmeClient>>>,
 }
 
 impl AutoCert {
@@ -37,24 +59,559 @@ impl AutoCert {
     ///
     /// # Panics
     ///
-    /// Panic if current challenge type is not [`ChallengeType::Http01`].
+    /// Panic if current challenge type is not [`ChallengeType::Http01`], or a
+    /// custom [`ChallengeSolver`] is used.
     pub fn http_01_endpoint(&self) -> Http01Endpoint {
         if let Some(keys_for_http01) = &self.keys_for_http01 {
             Http01Endpoint {
                 keys: keys_for_http01.clone(),
             }
         } else {
-            panic!("current challenge type is not `HTTP-01`");
+            panic!("current challenge type is not `HTTP-01` or a custom solver is used");
+        }
+    }
+
+    /// Revoke the current certificates.
+    ///
+    /// The certificates are also removed from the memory and the cache, so
+    /// the listener will obtain new ones at the next check. If no listener has
+    /// loaded a certificate yet, the certificate in the cache is revoked.
+    ///
+    /// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.6>
+    pub async fn revoke(&self, reason: Option<RevocationReason>) -> IoResult<()> {
+        let mut revoked = false;
+
+        for group in &self.groups {
+            let cert = group
+                .cert
+                .read()
+                .as_ref()
+                .and_then(|cert| cert.cert.first())
+                .map(|cert| cert.0.clone());
+            let cert = match (cert, &self.cache) {
+                (Some(cert), _) => Some(cert),
+                (None, Some(cache)) => cache
+                    .load(&group.cache_key(cache::CERT))
+                    .await?
+                    .and_then(|data| rustls_pemfile::certs(&mut data.as_slice()).ok())
+                    .and_then(|certs| certs.into_iter().next()),
+                (None, None) => None,
+            };
+            let cert = match cert {
+                Some(cert) => cert,
+                None => continue,
+            };
+
+            self.client()
+                .await?
+                .revoke_certificate(&cert, reason)
+                .await?;
+            revoked = true;
+
+            *group.cert.write() = None;
+            if let Some(cache) = &self.cache {
+                cache.remove(&group.cache_key(cache::CERT)).await?;
+                cache.remove(&group.cache_key(cache::CERT_KEY)).await?;
+            }
+        }
+
+        if !revoked {
+            return Err(IoError::new(ErrorKind::Other, "no certificate to revoke"));
+        }
+        Ok(())
+    }
+
+    /// Replace the ACME account key with a newly generated one.
+    ///
+    /// If the cache is configured, the new key is written to a pending entry
+    /// before the key is changed on the ACME server, and replaces the cached
+    /// account key after the server accepts it. If the new key cannot be
+    /// cached, the account key is not changed.
+    ///
+    /// The pending key is discarded if the server rejects the change. If the
+    /// result of the change is unknown, such as the connection is lost, or
+    /// the new key cannot replace the cached account key, the pending key is
+    /// kept, and the server is asked whether the change has been applied the
+    /// next time the ACME client is created, such as after a restart, so the
+    /// key is never lost.
+    ///
+    /// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.5>
+    pub async fn rotate_account_key(&self) -> IoResult<()> {
+        let new_key = Arc::new(KeyPair::generate()?);
+        let pem = new_key.to_pem();
+
+        let mut client = self.client.lock().await;
+        if client.is_none() {
+            *client = Some(self.new_client().await?);
+        }
+
+        if let Some(cache) = &self.cache {
+            cache
+                .store(cache::PENDING_ACCOUNT_KEY, pem.as_bytes())
+                .await?;
+        }
+
+        let res = client.as_mut().unwrap().change_key(new_key).await;
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return res.map_err(Into::into),
+        };
+        match res {
+            Ok(()) => {}
+            Err(err) if err.is_rejected() => {
+                if let Err(err) = cache.remove(cache::PENDING_ACCOUNT_KEY).await {
+                    tracing::warn!(error = %err, "failed to remove the pending account key");
+                }
+                return Err(err.into());
+            }
+            Err(err) => {
+                // the change may have been applied, the client is created again
+                // to check it with the pending key
+                *client = None;
+                return Err(err.into());
+            }
+        }
+
+        // if the new key cannot be promoted, it is kept in the pending entry and
+        // promoted when the client is created again
+        cache.store(cache::ACCOUNT_KEY, pem.as_bytes()).await?;
+        cache.remove(cache::PENDING_ACCOUNT_KEY).await
+    }
+
+    pub(crate) fn emit(&self, event: CertEvent) {
+        tracing::debug!(event = ?event, "certificate event");
+        if let Some(on_event) = &self.on_event {
+            on_event(event);
+        }
+    }
+
+    /// Returns the ACME client, creates it if it does not exist.
+    pub(crate) async fn client(&self) -> IoResult<MappedMutexGuard<'_, AcmeClient>> {
+        let mut client = self.client.lock().await;
+        if client.is_none() {
+            *client = Some(self.new_client().await?);
+        }
+        Ok(MutexGuard::map(client, |client| client.as_mut().unwrap()))
+    }
+
+    /// Create the ACME client with the cached account key.
+    ///
+    /// If a pending account key is left by [`AutoCert::rotate_account_key`],
+    /// it is promoted if the ACME server has an account for it, otherwise it
+    /// is discarded.
+    async fn new_client(&self) -> IoResult<AcmeClient> {
+        if let Some(cache) = &self.cache {
+            if let Some(key_pair) = load_pending_account_key(&**cache).await? {
+                let mut client = AcmeClient::try_new(self, Arc::new(key_pair)).await?;
+                if client.find_account().await? {
+                    tracing::debug!("the pending account key has been applied");
+                    cache
+                        .store(cache::ACCOUNT_KEY, client.key_pair().to_pem().as_bytes())
+                        .await?;
+                    cache.remove(cache::PENDING_ACCOUNT_KEY).await?;
+                    return Ok(client);
+                }
+                tracing::debug!("the pending account key has not been applied");
+                cache.remove(cache::PENDING_ACCOUNT_KEY).await?;
+            }
+        }
+
+        let key_pair = load_account_key(self.cache.as_deref()).await?;
+        Ok(AcmeClient::try_new(self, Arc::new(key_pair)).await?)
+    }
+}
+
+async fn load_pending_account_key(cache: &dyn CertificateCache) -> IoResult<Option<KeyPair>> {
+    let data = match cache.load(cache::PENDING_ACCOUNT_KEY).await? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    match KeyPair::from_pem(&data) {
+        Ok(key_pair) => Ok(Some(key_pair)),
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to parse pending account key");
+            cache.remove(cache::PENDING_ACCOUNT_KEY).await?;
+            Ok(None)
         }
     }
 }
 
+async fn load_account_key(cache: Option<&dyn CertificateCache>) -> IoResult<KeyPair> {
+    if let Some(cache) = cache {
+        if let Some(data) = cache.load(cache::ACCOUNT_KEY).await? {
+            match KeyPair::from_pem(&data) {
+                Ok(key_pair) => {
+                    tracing::debug!("using cached account key");
+                    return Ok(key_pair);
+                }
+                Err(err) => tracing::warn!(error = %err, "failed to parse cached account key"),
+            }
+        }
+    }
+
+    let key_pair = KeyPair::generate()?;
+    if let Some(cache) = cache {
+        cache
+            .store(cache::ACCOUNT_KEY, key_pair.to_pem().as_bytes())
+            .await?;
+    }
+    Ok(key_pair)
+}
+
 impl Debug for AutoCert {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("AutoCert")
             .field("directory_url", &self.directory_url)
-            .field("domains", &self.domains)
-            .field("cache_path", &self.cache_path)
+            .field(
+                "domains",
+                &self
+                    .groups
+                    .iter()
+                    .map(|group| &group.domains)
+                    .collect::<Vec<_>>(),
+            )
+            .field("contacts", &self.contacts)
+            .field("challenge_type", &self.challenge_type)
+            .field("key_type", &self.key_type)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use base64::URL_SAFE_NO_PAD;
+    use parking_lot::Mutex;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        endpoint::make,
+        http::StatusCode,
+        listener::{Acceptor, Listener, TcpListener},
+        IntoResponse, Request, Response, Server,
+    };
+
+    #[derive(Default, Clone)]
+    struct MemoryCache {
+        entries: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        fail_store: Option<&'static str>,
+    }
+
+    impl MemoryCache {
+        fn get(&self, key: &str) -> Option<Vec<u8>> {
+            self.entries.lock().get(key).cloned()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CertificateCache for MemoryCache {
+        async fn load(&self, key: &str) -> IoResult<Option<Vec<u8>>> {
+            Ok(self.get(key))
+        }
+
+        async fn store(&self, key: &str, data: &[u8]) -> IoResult<()> {
+            if self.fail_store == Some(key) {
+                return Err(IoError::new(ErrorKind::Other, "store failed"));
+            }
+            self.entries.lock().insert(key.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        async fn remove(&self, key: &str) -> IoResult<()> {
+            self.entries.lock().remove(key);
+            Ok(())
+        }
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq)]
+    enum KeyChange {
+        Accept,
+        Reject,
+        /// Apply the change, but respond with a server error.
+        ApplyAndFail,
+    }
+
+    /// An ACME server with a single account, which is identified by the `x`
+    /// coordinate of its public key.
+    #[derive(Clone)]
+    struct MockServer {
+        url: String,
+        account_key: Arc<Mutex<Option<String>>>,
+        key_change: Arc<Mutex<KeyChange>>,
+    }
+
+    fn decode_json(data: &Value) -> Value {
+        serde_json::from_slice(
+            &base64::decode_config(data.as_str().unwrap(), URL_SAFE_NO_PAD).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn public_key_id(pem: &[u8]) -> String {
+        let key_pair = KeyPair::from_pem(pem).unwrap();
+        base64::encode_config(&key_pair.public_key()[1..33], URL_SAFE_NO_PAD)
+    }
+
+    fn problem(status: StatusCode, ty: &str) -> Response {
+        (status, json!({ "type": ty, "detail": ty }).to_string()).into_response()
+    }
+
+    impl MockServer {
+        async fn start(key_change: KeyChange) -> Self {
+            let acceptor = TcpListener::bind("127.0.0.1:0")
+                .into_acceptor()
+                .await
+                .unwrap();
+            let addr = acceptor
+                .local_addr()
+                .remove(0)
+                .as_socket_addr()
+                .cloned()
+                .unwrap();
+            let server = Self {
+                url: format!("http://{}", addr),
+                account_key: Default::default(),
+                key_change: Arc::new(Mutex::new(key_change)),
+            };
+            tokio::spawn({
+                let server = server.clone();
+                async move {
+                    let _ = Server::new_with_acceptor(acceptor)
+                        .run(make(move |req| {
+                            let server = server.clone();
+                            async move { server.handle(req).await }
+                        }))
+                        .await;
+                }
+            });
+            server
+        }
+
+        fn directory_url(&self) -> String {
+            format!("{}/directory", self.url)
+        }
+
+        fn account_key(&self) -> Option<String> {
+            self.account_key.lock().clone()
+        }
+
+        async fn handle(&self, req: Request) -> Response {
+            let path = req.uri().path().to_string();
+            let jws = req
+                .into_body()
+                .into_json::<Value>()
+                .await
+                .unwrap_or_default();
+            let resp = match path.as_str() {
+                "/directory" => json!({
+                    "newNonce": format!("{}/nonce", self.url),
+                    "newAccount": format!("{}/account", self.url),
+                    "newOrder": format!("{}/order", self.url),
+                    "revokeCert": format!("{}/revoke", self.url),
+                    "keyChange": format!("{}/key-change", self.url),
+                })
+                .to_string()
+                .into_response(),
+                "/nonce" => Response::default(),
+                "/account" => {
+                    let key_id = decode_json(&jws["protected"])["jwk"]["x"]
+                        .as_str()
+                        .unwrap()
+                        .to_string();
+                    let only_return_existing = decode_json(&jws["payload"])["onlyReturnExisting"]
+                        .as_bool()
+                        .unwrap();
+                    let mut account_key = self.account_key.lock();
+                    match &*account_key {
+                        Some(account_key) if *account_key == key_id => {}
+                        _ if only_return_existing => {
+                            return problem(
+                                StatusCode::BAD_REQUEST,
+                                "urn:ietf:params:acme:error:accountDoesNotExist",
+                            )
+                            .with_header("replay-nonce", "nonce")
+                            .into_response()
+                        }
+                        _ => *account_key = Some(key_id),
+                    }
+                    Response::builder()
+                        .status(StatusCode::CREATED)
+                        .header("location", format!("{}/account/1", self.url))
+                        .body("{}")
+                }
+                "/key-change" => {
+                    let inner = decode_json(&jws["payload"]);
+                    let key_id = decode_json(&inner["protected"])["jwk"]["x"]
+                        .as_str()
+                        .unwrap()
+                        .to_string();
+                    match *self.key_change.lock() {
+                        KeyChange::Accept => {
+                            *self.account_key.lock() = Some(key_id);
+                            Response::default()
+                        }
+                        KeyChange::Reject => problem(
+                            StatusCode::FORBIDDEN,
+                            "urn:ietf:params:acme:error:unauthorized",
+                        ),
+                        KeyChange::ApplyAndFail => {
+                            *self.account_key.lock() = Some(key_id);
+                            problem(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "urn:ietf:params:acme:error:serverInternal",
+                            )
+                            .with_header("retry-after", "0")
+                            .into_response()
+                        }
+                    }
+                }
+                _ => StatusCode::NOT_FOUND.into_response(),
+            };
+            resp.with_header("replay-nonce", "nonce").into_response()
+        }
+    }
+
+    fn build(cache: MemoryCache, server: &MockServer) -> AutoCert {
+        AutoCert::builder()
+            .directory_url(server.directory_url())
+            .domain("example.com")
+            .cache(cache)
+            .build()
+            .unwrap()
+    }
+
+    /// Create an `AutoCert` with an account registered on the server.
+    async fn auto_cert(cache: MemoryCache, server: &MockServer) -> (AutoCert, Vec<u8>) {
+        let auto_cert = build(cache.clone(), server);
+        auto_cert
+            .client()
+            .await
+            .unwrap()
+            .ensure_account()
+            .await
+            .unwrap();
+        let account_key = cache.get(cache::ACCOUNT_KEY).unwrap();
+        assert_eq!(server.account_key(), Some(public_key_id(&account_key)));
+        (auto_cert, account_key)
+    }
+
+    async fn client_key(auto_cert: &AutoCert) -> Vec<u8> {
+        auto_cert
+            .client()
+            .await
+            .unwrap()
+            .key_pair()
+            .to_pem()
+            .into_bytes()
+    }
+
+    #[tokio::test]
+    async fn rotate_account_key() {
+        let server = MockServer::start(KeyChange::Accept).await;
+        let cache = MemoryCache::default();
+        let (auto_cert, account_key) = auto_cert(cache.clone(), &server).await;
+
+        auto_cert.rotate_account_key().await.unwrap();
+        let new_key = cache.get(cache::ACCOUNT_KEY).unwrap();
+        assert_ne!(new_key, account_key);
+        assert_eq!(server.account_key(), Some(public_key_id(&new_key)));
+        assert_eq!(client_key(&auto_cert).await, new_key);
+        assert!(cache.get(cache::PENDING_ACCOUNT_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn rotate_account_key_store_failed() {
+        let server = MockServer::start(KeyChange::Accept).await;
+        let cache = MemoryCache {
+            fail_store: Some(cache::PENDING_ACCOUNT_KEY),
+            ..Default::default()
+        };
+        let (auto_cert, account_key) = auto_cert(cache.clone(), &server).await;
+
+        let err = auto_cert.rotate_account_key().await.unwrap_err();
+        assert_eq!(err.to_string(), "store failed");
+        // the key is not changed on the server if it cannot be cached
+        assert_eq!(server.account_key(), Some(public_key_id(&account_key)));
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(account_key));
+        assert!(cache.get(cache::PENDING_ACCOUNT_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn rotate_account_key_rejected() {
+        let server = MockServer::start(KeyChange::Reject).await;
+        let cache = MemoryCache::default();
+        let (auto_cert, account_key) = auto_cert(cache.clone(), &server).await;
+
+        assert!(auto_cert.rotate_account_key().await.is_err());
+        assert_eq!(server.account_key(), Some(public_key_id(&account_key)));
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(account_key.clone()));
+        assert!(cache.get(cache::PENDING_ACCOUNT_KEY).is_none());
+        assert_eq!(client_key(&auto_cert).await, account_key);
+    }
+
+    #[tokio::test]
+    async fn rotate_account_key_unknown_result() {
+        let server = MockServer::start(KeyChange::ApplyAndFail).await;
+        let cache = MemoryCache::default();
+        let (auto_cert, account_key) = auto_cert(cache.clone(), &server).await;
+
+        assert!(auto_cert.rotate_account_key().await.is_err());
+        let new_key = cache.get(cache::PENDING_ACCOUNT_KEY).unwrap();
+        assert_eq!(server.account_key(), Some(public_key_id(&new_key)));
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(account_key));
+
+        // the pending key is promoted when the client is created again
+        assert_eq!(client_key(&auto_cert).await, new_key);
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(new_key));
+        assert!(cache.get(cache::PENDING_ACCOUNT_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn rotate_account_key_promote_failed() {
+        let server = MockServer::start(KeyChange::Accept).await;
+        let cache = MemoryCache {
+            fail_store: Some(cache::ACCOUNT_KEY),
+            ..Default::default()
+        };
+        cache.entries.lock().insert(
+            cache::ACCOUNT_KEY.to_string(),
+            KeyPair::generate().unwrap().to_pem().into_bytes(),
+        );
+        let (auto_cert, account_key) = auto_cert(cache.clone(), &server).await;
+
+        let err = auto_cert.rotate_account_key().await.unwrap_err();
+        assert_eq!(err.to_string(), "store failed");
+        let new_key = cache.get(cache::PENDING_ACCOUNT_KEY).unwrap();
+        assert_eq!(server.account_key(), Some(public_key_id(&new_key)));
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(account_key));
+        assert_eq!(client_key(&auto_cert).await, new_key);
+
+        // the pending key is recovered after a restart
+        let cache = MemoryCache {
+            fail_store: None,
+            ..cache
+        };
+        let auto_cert = build(cache.clone(), &server);
+        assert_eq!(client_key(&auto_cert).await, new_key);
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(new_key));
+        assert!(cache.get(cache::PENDING_ACCOUNT_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn discard_pending_account_key() {
+        let server = MockServer::start(KeyChange::Accept).await;
+        let cache = MemoryCache::default();
+        let (_, account_key) = auto_cert(cache.clone(), &server).await;
+        cache.entries.lock().insert(
+            cache::PENDING_ACCOUNT_KEY.to_string(),
+            KeyPair::generate().unwrap().to_pem().into_bytes(),
+        );
+
+        let auto_cert = build(cache.clone(), &server);
+        assert_eq!(client_key(&auto_cert).await, account_key);
+        assert_eq!(cache.get(cache::ACCOUNT_KEY), Some(account_key));
+        assert!(cache.get(cache::PENDING_ACCOUNT_KEY).is_none());
+    }
+}
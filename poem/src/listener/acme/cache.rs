@@ -0,0 +1,98 @@
+use std::{io::Result as IoResult, path::PathBuf};
+
+/// The cache key of the ACME account private key.
+pub(crate) const ACCOUNT_KEY: &str = "account_key.pem";
+
+/// The cache key of the new ACME account private key while the key is being
+/// changed.
+pub(crate) const PENDING_ACCOUNT_KEY: &str = "account_key.pending.pem";
+
+/// The cache key of the pending order.
+pub(crate) const ORDER: &str = "order.json";
+
+/// The cache key of the certificate private key.
+pub(crate) const CERT_KEY: &str = "key.pem";
+
+/// The cache key of the certificate chain.
+pub(crate) const CERT: &str = "cert.pem";
+
+/// Represents a storage that persists the ACME account key, the order state
+/// and the issued certificates, so that they can be reused after the server
+/// is restarted.
+#[async_trait::async_trait]
+pub trait CertificateCache: Send + Sync + 'static {
+    /// Load the data with the specified key, returns `None` if it does not
+    /// exist.
+    async fn load(&self, key: &str) -> IoResult<Option<Vec<u8>>>;
+
+    /// Store the data with the specified key.
+    async fn store(&self, key: &str, data: &[u8]) -> IoResult<()>;
+
+    /// Remove the data with the specified key.
+    async fn remove(&self, key: &str) -> IoResult<()>;
+}
+
+/// A [`CertificateCache`] that stores data in files under a directory.
+pub struct FileCache {
+    path: PathBuf,
+}
+
+impl FileCache {
+    /// Create a file cache with the specified directory.
+    ///
+    /// The directory will be created if it does not exist.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl CertificateCache for FileCache {
+    async fn load(&self, key: &str) -> IoResult<Option<Vec<u8>>> {
+        let path = self.path.join(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        tracing::debug!(path = %path.display(), "load from cache path");
+        std::fs::read(path).map(Some)
+    }
+
+    async fn store(&self, key: &str, data: &[u8]) -> IoResult<()> {
+        std::fs::create_dir_all(&self.path)?;
+        let path = self.path.join(key);
+        tracing::debug!(path = %path.display(), "write to cache path");
+        std::fs::write(path, data)
+    }
+
+    async fn remove(&self, key: &str) -> IoResult<()> {
+        let path = self.path.join(key);
+        if path.exists() {
+            tracing::debug!(path = %path.display(), "remove from cache path");
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_cache() {
+        let path = std::env::temp_dir().join(format!("poem-acme-cache-{}", std::process::id()));
+        let cache = FileCache::new(&path);
+
+        assert_eq!(cache.load(CERT).await.unwrap(), None);
+        cache.store(CERT, b"abc").await.unwrap();
+        assert_eq!(
+            cache.load(CERT).await.unwrap().as_deref(),
+            Some(&b"abc"[..])
+        );
+        cache.remove(CERT).await.unwrap();
+        assert_eq!(cache.load(CERT).await.unwrap(), None);
+        cache.remove(CERT).await.unwrap();
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -1,115 +1,325 @@
 use std::{
-    io::{Error as IoError, ErrorKind, Result as IoResult},
+    io::{Error as IoError, ErrorKind},
     sync::Arc,
+    time::{Duration, SystemTime},
 };
 
 use base64::URL_SAFE_NO_PAD;
-use http::{header, Uri};
-use hyper::{client::HttpConnector, Client};
+use http::{header, Method, StatusCode, Uri};
+use hyper::Client;
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use x509_parser::prelude::{FromDer, X509Certificate};
 
 use crate::{
     listener::acme::{
-        jose,
+        auto_cert::TermsOfServiceCallback,
+        error::{AcmeError, Problem},
+        jose::{self, ExternalAccountKey},
         keypair::KeyPair,
         protocol::{
             CsrRequest, Directory, FetchAuthorizationResponse, Identifier, NewAccountRequest,
-            NewOrderRequest, NewOrderResponse,
+            NewOrderRequest, NewOrderResponse, RenewalInfoResponse, RevocationReason,
+            RevokeCertRequest,
         },
-        ChallengeType,
+        proxy::{Proxy, ProxyConnector},
+        AutoCert, ChallengeType,
     },
-    Body,
+    Response,
 };
 
+/// The maximum number of retries for a request.
+const MAX_RETRIES: u32 = 4;
+
+/// Requests will not be retried if the server asks to wait longer than this.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The maximum number of nonces to keep for the subsequent requests.
+const MAX_NONCES: usize = 8;
+
+pub(crate) type HttpClient = Client<HttpsConnector<ProxyConnector>>;
+
+pub(crate) fn new_http_client(
+    proxy: Option<Proxy>,
+    root_certs: Option<RootCertStore>,
+) -> HttpClient {
+    let builder = match root_certs {
+        Some(root_certs) => HttpsConnectorBuilder::new().with_tls_config(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(root_certs)
+                .with_no_client_auth(),
+        ),
+        None => HttpsConnectorBuilder::new().with_native_roots(),
+    };
+    Client::builder().build(
+        builder
+            .https_or_http()
+            .enable_http1()
+            .wrap_connector(ProxyConnector::new(proxy)),
+    )
+}
+
 pub(crate) struct AcmeClient {
-    client: Client<HttpsConnector<HttpConnector>>,
+    client: HttpClient,
     directory: Directory,
     key_pair: Arc<KeyPair>,
     contacts: Vec<String>,
+    eab: Option<ExternalAccountKey>,
+    terms_of_service: Option<TermsOfServiceCallback>,
     kid: Option<String>,
+    nonces: Mutex<Vec<String>>,
 }
 
 impl AcmeClient {
     pub(crate) async fn try_new(
-        directory_url: &Uri,
+        auto_cert: &AutoCert,
         key_pair: Arc<KeyPair>,
-        contacts: Vec<String>,
-    ) -> IoResult<Self> {
-        let client = Client::builder().build(
-            HttpsConnectorBuilder::new()
-                .with_native_roots()
-                .https_or_http()
-                .enable_http1()
-                .build(),
+    ) -> Result<Self, AcmeError> {
+        let client = new_http_client(
+            auto_cert.proxy.clone(),
+            auto_cert.directory_root_certs.clone(),
         );
-        let directory = get_directory(&client, directory_url).await?;
+        let directory = get_directory(&client, &auto_cert.directory_url).await?;
         Ok(Self {
             client,
             directory,
             key_pair,
-            contacts,
+            contacts: auto_cert.contacts.clone(),
+            eab: auto_cert.eab.clone(),
+            terms_of_service: auto_cert.terms_of_service.clone(),
             kid: None,
+            nonces: Default::default(),
         })
     }
 
-    pub(crate) async fn new_order(&mut self, domains: &[String]) -> IoResult<NewOrderResponse> {
-        let kid = match &self.kid {
-            Some(kid) => kid,
-            None => {
-                // create account
-                let kid = create_acme_account(
-                    &self.client,
-                    &self.directory,
-                    &self.key_pair,
-                    self.contacts.clone(),
-                )
-                .await?;
-                self.kid = Some(kid);
-                self.kid.as_ref().unwrap()
+    pub(crate) fn key_pair(&self) -> &KeyPair {
+        &self.key_pair
+    }
+
+    /// Send a signed `POST` request, retries if the nonce is rejected or the
+    /// server is temporarily unavailable.
+    async fn post<T: Serialize>(
+        &self,
+        url: &Uri,
+        payload: Option<&T>,
+    ) -> Result<Response, AcmeError> {
+        let mut retries = 0;
+
+        loop {
+            let nonce = self.nonces.lock().pop();
+            let nonce = match nonce {
+                Some(nonce) => nonce,
+                None => get_nonce(&self.client, &self.directory).await?,
+            };
+            let resp = jose::request(
+                &self.client,
+                &self.key_pair,
+                self.kid.as_deref(),
+                &nonce,
+                url,
+                payload,
+            )
+            .await;
+
+            if let Ok(resp) = &resp {
+                self.save_nonce(resp);
             }
+
+            let (err, retry_after) = match resp {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let retry_after = retry_after(&resp);
+                    (error_from_response(resp).await, retry_after)
+                }
+                Err(err) => (err, None),
+            };
+
+            match retry_delay(&err, retry_after, retries) {
+                Some(delay) => {
+                    tracing::debug!(url = %url, error = %err, delay = ?delay, "retry acme request");
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                None => return Err(err),
+            }
+        }
+    }
+
+    /// Save the `Replay-Nonce` of the response for the next request, so that
+    /// the `newNonce` request can be avoided.
+    fn save_nonce(&self, resp: &Response) {
+        if let Some(nonce) = resp.header("replay-nonce") {
+            let mut nonces = self.nonces.lock();
+            if nonces.len() >= MAX_NONCES {
+                nonces.remove(0);
+            }
+            nonces.push(nonce.to_string());
+        }
+    }
+
+    async fn post_json<T: Serialize, R: DeserializeOwned>(
+        &self,
+        url: &Uri,
+        payload: Option<&T>,
+    ) -> Result<R, AcmeError> {
+        let resp = self.post(url, payload).await?;
+        read_json(resp).await
+    }
+
+    pub(crate) async fn ensure_account(&mut self) -> Result<(), AcmeError> {
+        if self.kid.is_none() {
+            let kid = self.create_account().await?;
+            self.kid = Some(kid);
+        }
+        Ok(())
+    }
+
+    async fn create_account(&self) -> Result<String, AcmeError> {
+        tracing::debug!("creating acme account");
+
+        if self.directory.meta.external_account_required && self.eab.is_none() {
+            return Err(IoError::new(
+                ErrorKind::Other,
+                "the acme server requires an external account binding",
+            )
+            .into());
+        }
+        let external_account_binding = self
+            .eab
+            .as_ref()
+            .map(|eab| {
+                jose::external_account_binding(&self.key_pair, eab, &self.directory.new_account)
+            })
+            .transpose()?;
+
+        let terms_of_service_agreed = match (
+            &self.directory.meta.terms_of_service,
+            &self.terms_of_service,
+        ) {
+            (Some(url), Some(callback)) => {
+                tracing::debug!(url = url.as_str(), "check terms of service");
+                if !callback(url) {
+                    return Err(IoError::new(
+                        ErrorKind::Other,
+                        format!("the terms of service are not agreed: {}", url),
+                    )
+                    .into());
+                }
+                true
+            }
+            _ => true,
         };
 
-        tracing::debug!(kid = kid.as_str(), "new order request");
-
-        let nonce = get_nonce(&self.client, &self.directory).await?;
-        let resp: NewOrderResponse = jose::request_json(
-            &self.client,
-            &self.key_pair,
-            Some(kid),
-            &nonce,
-            &self.directory.new_order,
-            Some(NewOrderRequest {
-                identifiers: domains
-                    .iter()
-                    .map(|domain| Identifier {
-                        ty: "dns".to_string(),
-                        value: domain.to_string(),
-                    })
-                    .collect(),
-            }),
-        )
-        .await?;
+        let resp = self
+            .post(
+                &self.directory.new_account,
+                Some(&NewAccountRequest {
+                    only_return_existing: false,
+                    terms_of_service_agreed,
+                    contact: self.contacts.clone(),
+                    external_account_binding,
+                }),
+            )
+            .await?;
+        let kid = resp
+            .header(header::LOCATION)
+            .ok_or_else(|| AcmeError::InvalidResponse("unable to get account id".to_string()))?
+            .to_string();
+
+        tracing::debug!(kid = kid.as_str(), "account created");
+        Ok(kid)
+    }
+
+    /// Look up the existing account of the key, returns `false` if the ACME
+    /// server has no account for it.
+    pub(crate) async fn find_account(&mut self) -> Result<bool, AcmeError> {
+        tracing::debug!("finding acme account");
+
+        let resp = self
+            .post(
+                &self.directory.new_account,
+                Some(&NewAccountRequest {
+                    only_return_existing: true,
+                    terms_of_service_agreed: false,
+                    contact: Vec::new(),
+                    external_account_binding: None,
+                }),
+            )
+            .await;
+        let resp = match resp {
+            Ok(resp) => resp,
+            Err(AcmeError::Problem(problem)) if problem.is_account_does_not_exist() => {
+                return Ok(false)
+            }
+            Err(err) => return Err(err),
+        };
+        let kid = resp
+            .header(header::LOCATION)
+            .ok_or_else(|| AcmeError::InvalidResponse("unable to get account id".to_string()))?
+            .to_string();
+
+        tracing::debug!(kid = kid.as_str(), "account found");
+        self.kid = Some(kid);
+        Ok(true)
+    }
 
-        tracing::debug!(status = resp.status.as_str(), "order created");
+    /// Create a new order, returns the order url and the order object.
+    pub(crate) async fn new_order(
+        &mut self,
+        domains: &[String],
+    ) -> Result<(Uri, NewOrderResponse), AcmeError> {
+        self.ensure_account().await?;
+
+        tracing::debug!(kid = self.kid.as_deref(), "new order request");
+
+        let resp = self
+            .post(
+                &self.directory.new_order,
+                Some(&NewOrderRequest {
+                    identifiers: domains
+                        .iter()
+                        .map(|domain| Identifier {
+                            ty: "dns".to_string(),
+                            value: domain.to_string(),
+                        })
+                        .collect(),
+                }),
+            )
+            .await?;
+        let order_url = resp
+            .header(header::LOCATION)
+            .and_then(|location| location.parse::<Uri>().ok())
+            .ok_or_else(|| AcmeError::InvalidResponse("unable to get order url".to_string()))?;
+        let resp: NewOrderResponse = read_json(resp).await?;
+
+        tracing::debug!(status = ?resp.status, "order created");
+        Ok((order_url, resp))
+    }
+
+    pub(crate) async fn fetch_order(
+        &mut self,
+        order_url: &Uri,
+    ) -> Result<NewOrderResponse, AcmeError> {
+        self.ensure_account().await?;
+
+        tracing::debug!(order_url = %order_url, "fetch order");
+
+        let resp: NewOrderResponse = self.post_json(order_url, None::<&()>).await?;
+
+        tracing::debug!(status = ?resp.status, "order fetched");
         Ok(resp)
     }
 
     pub(crate) async fn fetch_authorization(
         &self,
         auth_url: &Uri,
-    ) -> IoResult<FetchAuthorizationResponse> {
+    ) -> Result<FetchAuthorizationResponse, AcmeError> {
         tracing::debug!(auth_uri = %auth_url, "fetch authorization");
 
-        let nonce = get_nonce(&self.client, &self.directory).await?;
-        let resp: FetchAuthorizationResponse = jose::request_json(
-            &self.client,
-            &self.key_pair,
-            self.kid.as_deref(),
-            &nonce,
-            auth_url,
-            None::<()>,
-        )
-        .await?;
+        let resp: FetchAuthorizationResponse = self.post_json(auth_url, None::<&()>).await?;
 
         tracing::debug!(
             identifier = ?resp.identifier,
@@ -125,7 +335,7 @@ impl AcmeClient {
         domain: &str,
         challenge_type: ChallengeType,
         url: &Uri,
-    ) -> IoResult<()> {
+    ) -> Result<(), AcmeError> {
         tracing::debug!(
             auth_uri = %url,
             domain = domain,
@@ -133,89 +343,178 @@ impl AcmeClient {
             "trigger challenge",
         );
 
-        let nonce = get_nonce(&self.client, &self.directory).await?;
-        jose::request(
-            &self.client,
-            &self.key_pair,
-            self.kid.as_deref(),
-            &nonce,
-            url,
-            Some(serde_json::json!({})),
-        )
-        .await?;
-
+        self.post(url, Some(&serde_json::json!({}))).await?;
         Ok(())
     }
 
-    pub(crate) async fn send_csr(&self, url: &Uri, csr: &[u8]) -> IoResult<NewOrderResponse> {
+    pub(crate) async fn send_csr(
+        &self,
+        url: &Uri,
+        csr: &[u8],
+    ) -> Result<NewOrderResponse, AcmeError> {
         tracing::debug!(url = %url, "send certificate request");
 
-        let nonce = get_nonce(&self.client, &self.directory).await?;
-        jose::request_json(
-            &self.client,
-            &self.key_pair,
-            self.kid.as_deref(),
-            &nonce,
+        self.post_json(
             url,
-            Some(CsrRequest {
+            Some(&CsrRequest {
                 csr: base64::encode_config(csr, URL_SAFE_NO_PAD),
             }),
         )
         .await
     }
 
-    pub(crate) async fn obtain_certificate(&self, url: &Uri) -> IoResult<Vec<u8>> {
-        tracing::debug!(url = %url, "send certificate request");
+    /// Download the certificate chain.
+    ///
+    /// If the `preferred_chain` is specified, the alternate chains are also
+    /// checked, and the first one whose topmost certificate is issued by the
+    /// specified common name is returned. If no chain matches, the default
+    /// chain is returned.
+    pub(crate) async fn obtain_certificate(
+        &self,
+        url: &Uri,
+        preferred_chain: Option<&str>,
+    ) -> Result<Vec<u8>, AcmeError> {
+        let (chain, alternates) = self.download_chain(url).await?;
+        let preferred_chain = match preferred_chain {
+            Some(preferred_chain) if !chain_issued_by(&chain, preferred_chain) => preferred_chain,
+            _ => return Ok(chain),
+        };
 
-        let nonce = get_nonce(&self.client, &self.directory).await?;
-        let resp = jose::request(
-            &self.client,
-            &self.key_pair,
-            self.kid.as_deref(),
-            &nonce,
-            url,
-            None::<()>,
+        for url in alternates {
+            let (alternate_chain, _) = self.download_chain(&url).await?;
+            if chain_issued_by(&alternate_chain, preferred_chain) {
+                tracing::debug!(url = %url, "use alternate certificate chain");
+                return Ok(alternate_chain);
+            }
+        }
+
+        tracing::debug!(
+            preferred_chain = preferred_chain,
+            "no certificate chain matches the preferred chain, use the default chain"
+        );
+        Ok(chain)
+    }
+
+    /// Download the certificate chain, returns the chain and the urls of the
+    /// alternate chains.
+    async fn download_chain(&self, url: &Uri) -> Result<(Vec<u8>, Vec<Uri>), AcmeError> {
+        tracing::debug!(url = %url, "download certificate");
+
+        let resp = self.post(url, None::<&()>).await?;
+        let alternates = resp
+            .headers()
+            .get_all(header::LINK)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(alternate_links)
+            .collect();
+        let chain = resp.into_body().into_vec().await.map_err(|err| {
+            AcmeError::InvalidResponse(format!("failed to download certificate: {}", err))
+        })?;
+        Ok((chain, alternates))
+    }
+
+    /// Replace the account key with the new key.
+    pub(crate) async fn change_key(&mut self, new_key: Arc<KeyPair>) -> Result<(), AcmeError> {
+        self.ensure_account().await?;
+
+        let kid = self.kid.as_deref().unwrap_or_default();
+        tracing::debug!(kid = kid, "change account key");
+
+        let inner = jose::key_change(&new_key, &self.key_pair, kid, &self.directory.key_change)?;
+        self.post(&self.directory.key_change, Some(&inner)).await?;
+        self.key_pair = new_key;
+
+        tracing::debug!("account key changed");
+        Ok(())
+    }
+
+    /// Fetch the suggested renewal window of the certificate, returns `None`
+    /// if the server does not support ACME Renewal Information (ARI).
+    ///
+    /// The `Retry-After` header of the response indicates when the
+    /// information should be fetched again.
+    pub(crate) async fn renewal_info(
+        &self,
+        cert_id: &str,
+    ) -> Result<Option<(RenewalInfoResponse, Option<Duration>)>, AcmeError> {
+        let renewal_info = match &self.directory.renewal_info {
+            Some(renewal_info) => renewal_info,
+            None => return Ok(None),
+        };
+        let url = format!(
+            "{}/{}",
+            renewal_info.to_string().trim_end_matches('/'),
+            cert_id
         )
-        .await?;
+        .parse::<Uri>()
+        .map_err(|err| AcmeError::InvalidResponse(format!("invalid renewal info url: {}", err)))?;
 
-        resp.into_body().into_vec().await.map_err(|err| {
-            IoError::new(
-                ErrorKind::Other,
-                format!("failed to download certificate: {}", err),
-            )
-        })
+        tracing::debug!(url = %url, "fetch renewal info");
+
+        let resp = get(&self.client, &url).await?;
+        let retry_after = retry_after(&resp);
+        Ok(Some((read_json(resp).await?, retry_after)))
     }
-}
 
-async fn get_directory(
-    client: &Client<HttpsConnector<HttpConnector>>,
-    directory_url: &Uri,
-) -> IoResult<Directory> {
-    tracing::debug!("loading directory");
+    pub(crate) async fn revoke_certificate(
+        &mut self,
+        cert: &[u8],
+        reason: Option<RevocationReason>,
+    ) -> Result<(), AcmeError> {
+        self.ensure_account().await?;
 
-    let resp = client.get(directory_url.clone()).await.map_err(|err| {
-        IoError::new(
-            ErrorKind::Other,
-            format!("failed to load directory: {}", err),
+        tracing::debug!(reason = ?reason, "revoke certificate");
+
+        self.post(
+            &self.directory.revoke_cert,
+            Some(&RevokeCertRequest {
+                certificate: base64::encode_config(cert, URL_SAFE_NO_PAD),
+                reason: reason.map(|reason| reason as u8),
+            }),
         )
-    })?;
+        .await?;
 
-    if !resp.status().is_success() {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!("failed to load directory: status = {}", resp.status()),
-        ));
+        tracing::debug!("certificate revoked");
+        Ok(())
     }
+}
 
-    let directory = Body(resp.into_body())
-        .into_json::<Directory>()
-        .await
-        .map_err(|err| {
-            IoError::new(
-                ErrorKind::Other,
-                format!("failed to load directory: {}", err),
-            )
-        })?;
+/// Send a `GET` request, retries if the server is temporarily unavailable.
+async fn get(client: &HttpClient, url: &Uri) -> Result<Response, AcmeError> {
+    let mut retries = 0;
+
+    loop {
+        let req = http::Request::builder()
+            .method(Method::GET)
+            .uri(url.clone())
+            .body(hyper::Body::empty())
+            .unwrap();
+        let (err, retry_after) = match client.request(req).await {
+            Ok(resp) if resp.status().is_success() => return Ok(resp.into()),
+            Ok(resp) => {
+                let resp: Response = resp.into();
+                let retry_after = retry_after(&resp);
+                (error_from_response(resp).await, retry_after)
+            }
+            Err(err) => (AcmeError::Http(err), None),
+        };
+
+        match retry_delay(&err, retry_after, retries) {
+            Some(delay) => {
+                tracing::debug!(url = %url, error = %err, delay = ?delay, "retry acme request");
+                tokio::time::sleep(delay).await;
+                retries += 1;
+            }
+            None => return Err(err),
+        }
+    }
+}
+
+async fn get_directory(client: &HttpClient, directory_url: &Uri) -> Result<Directory, AcmeError> {
+    tracing::debug!("loading directory");
+
+    let directory: Directory = read_json(get(client, directory_url).await?).await?;
 
     tracing::debug!(
         new_nonce = ?directory.new_nonce,
@@ -226,62 +525,187 @@ async fn get_directory(
     Ok(directory)
 }
 
-async fn get_nonce(
-    client: &Client<HttpsConnector<HttpConnector>>,
-    directory: &Directory,
-) -> IoResult<String> {
+async fn get_nonce(client: &HttpClient, directory: &Directory) -> Result<String, AcmeError> {
     tracing::debug!("creating nonce");
 
-    let resp = client
-        .get(directory.new_nonce.clone())
-        .await
-        .map_err(|err| IoError::new(ErrorKind::Other, format!("failed to get nonce: {}", err)))?;
-
-    if !resp.status().is_success() {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!("failed to load directory: status = {}", resp.status()),
-        ));
-    }
-
+    let resp = get(client, &directory.new_nonce).await?;
     let nonce = resp
-        .headers()
-        .get("replay-nonce")
-        .and_then(|value| value.to_str().ok())
+        .header("replay-nonce")
         .map(ToString::to_string)
-        .unwrap_or_default();
+        .ok_or_else(|| AcmeError::InvalidResponse("missing `Replay-Nonce` header".to_string()))?;
 
     tracing::debug!(nonce = nonce.as_str(), "nonce created");
     Ok(nonce)
 }
 
-async fn create_acme_account(
-    client: &Client<HttpsConnector<HttpConnector>>,
-    directory: &Directory,
-    key_pair: &KeyPair,
-    contacts: Vec<String>,
-) -> IoResult<String> {
-    tracing::debug!("creating acme account");
-
-    let nonce = get_nonce(client, directory).await?;
-    let resp = jose::request(
-        client,
-        key_pair,
-        None,
-        &nonce,
-        &directory.new_account,
-        Some(NewAccountRequest {
-            only_return_existing: false,
-            terms_of_service_agreed: true,
-            contacts,
-        }),
-    )
-    .await?;
-    let kid = resp
-        .header(header::LOCATION)
-        .ok_or_else(|| IoError::new(ErrorKind::Other, "unable to get account id"))?
-        .to_string();
-
-    tracing::debug!(kid = kid.as_str(), "account created");
-    Ok(kid)
+async fn read_json<T: DeserializeOwned>(resp: Response) -> Result<T, AcmeError> {
+    let data = resp
+        .into_body()
+        .into_vec()
+        .await
+        .map_err(|_| AcmeError::InvalidResponse("failed to read response".to_string()))?;
+    serde_json::from_slice(&data).map_err(|err| AcmeError::InvalidResponse(err.to_string()))
+}
+
+async fn error_from_response(resp: Response) -> AcmeError {
+    let status = resp.status();
+    let data = resp.into_body().into_vec().await.unwrap_or_default();
+    match serde_json::from_slice::<Problem>(&data) {
+        Ok(mut problem) if !problem.ty.is_empty() || !problem.detail.is_empty() => {
+            problem.status.get_or_insert(status.as_u16());
+            AcmeError::Problem(problem)
+        }
+        _ => AcmeError::UnexpectedStatus(status),
+    }
+}
+
+/// Returns the urls with the `alternate` relation in the `Link` header.
+fn alternate_links(value: &str) -> Vec<Uri> {
+    value
+        .split(',')
+        .filter_map(|link| {
+            let mut parts = link.split(';').map(str::trim);
+            let url = parts.next()?.strip_prefix('<')?.strip_suffix('>')?;
+            parts
+                .any(|param| param.replace(' ', "") == "rel=\"alternate\"")
+                .then(|| url.parse().ok())
+                .flatten()
+        })
+        .collect()
+}
+
+/// Returns `true` if the topmost certificate of the chain is issued by the
+/// specified common name.
+fn chain_issued_by(chain: &[u8], common_name: &str) -> bool {
+    rustls_pemfile::certs(&mut &*chain)
+        .ok()
+        .and_then(|certs| certs.last().cloned())
+        .and_then(|cert| {
+            X509Certificate::from_der(&cert).ok().and_then(|(_, cert)| {
+                cert.issuer()
+                    .iter_common_name()
+                    .next()
+                    .and_then(|cn| cn.as_str().ok())
+                    .map(|cn| cn == common_name)
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Parse the `Retry-After` header, which is either a number of seconds or a
+/// HTTP date.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.header(header::RETRY_AFTER)?;
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Returns how long to wait before retrying the request, or `None` if the
+/// request should not be retried.
+fn retry_delay(err: &AcmeError, retry_after: Option<Duration>, retries: u32) -> Option<Duration> {
+    if retries >= MAX_RETRIES {
+        return None;
+    }
+
+    let status = match err {
+        AcmeError::Problem(problem) if problem.is_bad_nonce() => return Some(Duration::ZERO),
+        AcmeError::Problem(problem) => problem
+            .status
+            .and_then(|status| StatusCode::from_u16(status).ok()),
+        AcmeError::UnexpectedStatus(status) => Some(*status),
+        AcmeError::Http(_) => None,
+        _ => return None,
+    };
+    if let Some(status) = status {
+        if !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+    }
+
+    let delay = retry_after.unwrap_or_else(|| Duration::from_secs(1 << retries));
+    if delay > MAX_RETRY_AFTER {
+        return None;
+    }
+    Some(delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(ty: &str, status: u16) -> AcmeError {
+        AcmeError::Problem(Problem {
+            ty: ty.to_string(),
+            detail: String::new(),
+            status: Some(status),
+        })
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let bad_nonce = problem("urn:ietf:params:acme:error:badNonce", 400);
+        assert_eq!(retry_delay(&bad_nonce, None, 0), Some(Duration::ZERO));
+        assert_eq!(retry_delay(&bad_nonce, None, MAX_RETRIES), None);
+
+        let server_internal = problem("urn:ietf:params:acme:error:serverInternal", 500);
+        assert_eq!(
+            retry_delay(&server_internal, None, 0),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            retry_delay(&server_internal, None, 2),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            retry_delay(&server_internal, Some(Duration::from_secs(10)), 0),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            retry_delay(&server_internal, Some(Duration::from_secs(3600)), 0),
+            None
+        );
+
+        let unavailable = AcmeError::UnexpectedStatus(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            retry_delay(&unavailable, None, 1),
+            Some(Duration::from_secs(2))
+        );
+
+        let malformed = problem("urn:ietf:params:acme:error:malformed", 400);
+        assert_eq!(retry_delay(&malformed, None, 0), None);
+
+        let invalid = AcmeError::InvalidResponse("bad".to_string());
+        assert_eq!(retry_delay(&invalid, None, 0), None);
+    }
+
+    #[test]
+    fn test_alternate_links() {
+        assert_eq!(
+            alternate_links(
+                r#"<https://example.com/cert/1/1>;rel="alternate", <https://example.com/dir>;rel="index", <https://example.com/cert/1/2>; rel="alternate""#
+            ),
+            vec![
+                "https://example.com/cert/1/1".parse::<Uri>().unwrap(),
+                "https://example.com/cert/1/2".parse::<Uri>().unwrap(),
+            ]
+        );
+        assert!(alternate_links(r#"<https://example.com/dir>;rel="index""#).is_empty());
+    }
+
+    #[test]
+    fn test_chain_issued_by() {
+        let mut params = rcgen::CertificateParams::new(vec!["example.com".to_string()]);
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "ISRG Root X1");
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let chain = cert.serialize_pem().unwrap();
+
+        assert!(chain_issued_by(chain.as_bytes(), "ISRG Root X1"));
+        assert!(!chain_issued_by(chain.as_bytes(), "DST Root CA X3"));
+        assert!(!chain_issued_by(b"", "ISRG Root X1"));
+    }
 }
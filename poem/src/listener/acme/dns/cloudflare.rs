@@ -0,0 +1,374 @@
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+use http::{header, Method};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    listener::acme::{
+        client::{new_http_client, HttpClient},
+        dns::DnsProvider,
+    },
+    Body, Request,
+};
+
+const API_URL: &str = "https://api.cloudflare.com/client/v4";
+
+/// A [`DnsProvider`] for [Cloudflare](https://www.cloudflare.com/).
+///
+/// The API token requires the `Zone:Read` and `DNS:Edit` permissions.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::acme::{AutoCert, CloudflareProvider};
+///
+/// let auto_cert = AutoCert::builder()
+///     .domain("*.example.com")
+///     .dns_provider(CloudflareProvider::new("api token"))
+///     .build();
+/// ```
+pub struct CloudflareProvider {
+    client: HttpClient,
+    api_url: String,
+    api_token: String,
+    zone_id: Option<String>,
+}
+
+impl CloudflareProvider {
+    /// Create a Cloudflare DNS provider with the specified API token.
+    pub fn new(api_token: impl Into<String>) -> Self {
+        Self {
+            client: new_http_client(None, None),
+            api_url: API_URL.to_string(),
+            api_token: api_token.into(),
+            zone_id: None,
+        }
+    }
+
+    /// Sets the zone id.
+    ///
+    /// If not set, the zone will be looked up by the record name.
+    #[must_use]
+    pub fn zone_id(self, zone_id: impl Into<String>) -> Self {
+        Self {
+            zone_id: Some(zone_id.into()),
+            ..self
+        }
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<impl Serialize>,
+    ) -> IoResult<T> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri_str(format!("{}{}", self.api_url, path))
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.api_token));
+        let body = match body {
+            Some(body) => {
+                builder = builder.content_type("application/json");
+                serde_json::to_vec(&body).map_err(|err| {
+                    IoError::new(
+                        ErrorKind::Other,
+                        format!("failed to encode cloudflare request: {}", err),
+                    )
+                })?
+            }
+            None => Vec::new(),
+        };
+
+        let resp = self
+            .client
+            .request(builder.body(body).into())
+            .await
+            .map_err(|err| {
+                IoError::new(
+                    ErrorKind::Other,
+                    format!("failed to send cloudflare request: {}", err),
+                )
+            })?;
+        let resp = Body(resp.into_body())
+            .into_json::<ApiResponse<T>>()
+            .await
+            .map_err(|err| {
+                IoError::new(
+                    ErrorKind::Other,
+                    format!("bad cloudflare response: {}", err),
+                )
+            })?;
+
+        match resp {
+            ApiResponse {
+                success: true,
+                result: Some(result),
+                ..
+            } => Ok(result),
+            ApiResponse { errors, .. } => Err(IoError::new(
+                ErrorKind::Other,
+                format!(
+                    "cloudflare request failed: {}",
+                    errors
+                        .into_iter()
+                        .map(|err| format!("[{}] {}", err.code, err.message))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )),
+        }
+    }
+
+    async fn get_zone_id(&self, name: &str) -> IoResult<String> {
+        if let Some(zone_id) = &self.zone_id {
+            return Ok(zone_id.clone());
+        }
+
+        let mut zone = name;
+        while let Some((_, parent)) = zone.split_once('.') {
+            if !parent.contains('.') {
+                break;
+            }
+            zone = parent;
+
+            let zones: Vec<Zone> = self
+                .request(Method::GET, &format!("/zones?name={}", zone), None::<()>)
+                .await?;
+            if let Some(zone) = zones.into_iter().next() {
+                tracing::debug!(zone = zone.name.as_str(), "found cloudflare zone");
+                return Ok(zone.id);
+            }
+        }
+
+        Err(IoError::new(
+            ErrorKind::Other,
+            format!("unable to find cloudflare zone for `{}`", name),
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl DnsProvider for CloudflareProvider {
+    async fn set_txt_record(&self, name: &str, value: &str) -> IoResult<()> {
+        let zone_id = self.get_zone_id(name).await?;
+        let _: DnsRecord = self
+            .request(
+                Method::POST,
+                &format!("/zones/{}/dns_records", zone_id),
+                Some(NewDnsRecord {
+                    ty: "TXT",
+                    name,
+                    content: value,
+                    ttl: 120,
+                }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_txt_record(&self, name: &str, value: &str) -> IoResult<()> {
+        let zone_id = self.get_zone_id(name).await?;
+        let records: Vec<DnsRecord> = self
+            .request(
+                Method::GET,
+                &format!("/zones/{}/dns_records?type=TXT&name={}", zone_id, name),
+                None::<()>,
+            )
+            .await?;
+
+        for record in records.into_iter().filter(|record| record.content == value) {
+            let _: serde_json::Value = self
+                .request(
+                    Method::DELETE,
+                    &format!("/zones/{}/dns_records/{}", zone_id, record.id),
+                    None::<()>,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: i32,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<ApiError>,
+    result: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct NewDnsRecord<'a> {
+    #[serde(rename = "type")]
+    ty: &'static str,
+    name: &'a str,
+    content: &'a str,
+    ttl: u32,
+}
+
+#[derive(Deserialize)]
+struct DnsRecord {
+    id: String,
+    content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{listener::acme::dns::mock::MockServer, IntoResponse};
+
+    fn ok(result: serde_json::Value) -> crate::Response {
+        json!({ "success": true, "errors": [], "result": result })
+            .to_string()
+            .into_response()
+    }
+
+    #[tokio::test]
+    async fn set_and_remove_txt_record() {
+        let server = MockServer::start(|req| match (&req.method, req.uri.as_str()) {
+            (&Method::GET, "/zones?name=example.com") => {
+                ok(json!([{ "id": "Z1", "name": "example.com" }]))
+            }
+            (&Method::GET, uri) if uri.starts_with("/zones?") => ok(json!([])),
+            (&Method::POST, _) => ok(json!({ "id": "R1", "content": "a" })),
+            (&Method::GET, _) => ok(json!([
+                { "id": "R1", "content": "a" },
+                { "id": "R2", "content": "b" },
+            ])),
+            _ => ok(json!({ "id": "R1" })),
+        })
+        .await;
+        let provider = CloudflareProvider {
+            api_url: server.url.clone(),
+            ..CloudflareProvider::new("token")
+        };
+        let name = "_acme-challenge.www.example.com";
+
+        provider.set_txt_record(name, "a").await.unwrap();
+        provider.remove_txt_record(name, "a").await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|req| (req.method.clone(), req.uri.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Method::GET, "/zones?name=www.example.com"),
+                (Method::GET, "/zones?name=example.com"),
+                (Method::POST, "/zones/Z1/dns_records"),
+                (Method::GET, "/zones?name=www.example.com"),
+                (Method::GET, "/zones?name=example.com"),
+                (
+                    Method::GET,
+                    "/zones/Z1/dns_records?type=TXT&name=_acme-challenge.www.example.com"
+                ),
+                (Method::DELETE, "/zones/Z1/dns_records/R1"),
+            ]
+        );
+        assert!(requests
+            .iter()
+            .all(|req| req.headers.get(header::AUTHORIZATION).unwrap() == "Bearer token"));
+
+        let create = &requests[2];
+        assert_eq!(
+            create.headers.get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&create.body).unwrap(),
+            json!({
+                "type": "TXT",
+                "name": "_acme-challenge.www.example.com",
+                "content": "a",
+                "ttl": 120,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn specified_zone_id() {
+        let server = MockServer::start(|_| ok(json!({ "id": "R1", "content": "a" }))).await;
+        let provider = CloudflareProvider {
+            api_url: server.url.clone(),
+            ..CloudflareProvider::new("token").zone_id("Z2")
+        };
+
+        provider
+            .set_txt_record("_acme-challenge.example.com", "a")
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].uri, "/zones/Z2/dns_records");
+    }
+
+    #[tokio::test]
+    async fn zone_not_found() {
+        let server = MockServer::start(|_| ok(json!([]))).await;
+        let provider = CloudflareProvider {
+            api_url: server.url.clone(),
+            ..CloudflareProvider::new("token")
+        };
+
+        let err = provider
+            .set_txt_record("_acme-challenge.example.com", "a")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unable to find cloudflare zone for `_acme-challenge.example.com`"
+        );
+        assert_eq!(
+            server
+                .requests()
+                .iter()
+                .map(|req| req.uri.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/zones?name=example.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn request_failed() {
+        let server = MockServer::start(|_| {
+            json!({
+                "success": false,
+                "errors": [{ "code": 9109, "message": "Invalid access token" }],
+                "result": null,
+            })
+            .to_string()
+            .into_response()
+        })
+        .await;
+        let provider = CloudflareProvider {
+            api_url: server.url.clone(),
+            ..CloudflareProvider::new("token").zone_id("Z1")
+        };
+
+        let err = provider
+            .set_txt_record("_acme-challenge.example.com", "a")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cloudflare request failed: [9109] Invalid access token"
+        );
+    }
+}
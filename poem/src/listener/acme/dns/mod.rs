@@ -0,0 +1,132 @@
+//! DNS providers for the `DNS-01` challenge.
+
+#[cfg(feature = "acme-cloudflare")]
+mod cloudflare;
+#[cfg(feature = "acme-route53")]
+mod route53;
+
+use std::{io::Result as IoResult, time::Duration};
+
+#[cfg(feature = "acme-cloudflare")]
+pub use cloudflare::CloudflareProvider;
+#[cfg(feature = "acme-route53")]
+pub use route53::Route53Provider;
+
+/// Represents a DNS provider that can publish the `TXT` records required by
+/// the `DNS-01` challenge.
+///
+/// Reference: <https://letsencrypt.org/docs/challenge-types/#dns-01-challenge>
+#[async_trait::async_trait]
+pub trait DnsProvider: Send + Sync + 'static {
+    /// Create a `TXT` record with the specified name and value.
+    ///
+    /// The `name` is a fully qualified domain name such as
+    /// `_acme-challenge.example.com`. Note that there may be more than one
+    /// record with the same name at the same time (for example when issuing
+    /// a certificate for both `example.com` and `*.example.com`), so
+    /// implementations must not replace existing records.
+    async fn set_txt_record(&self, name: &str, value: &str) -> IoResult<()>;
+
+    /// Remove the `TXT` record previously created by
+    /// [`DnsProvider::set_txt_record`].
+    async fn remove_txt_record(&self, name: &str, value: &str) -> IoResult<()>;
+
+    /// Wait until the `TXT` record is visible to the ACME server.
+    ///
+    /// The default implementation sleeps for 30 seconds.
+    async fn wait_for_propagation(&self, name: &str, value: &str) -> IoResult<()> {
+        let _ = (name, value);
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        Ok(())
+    }
+}
+
+/// Returns the name of the `TXT` record for the specified domain.
+pub(crate) fn challenge_record_name(domain: &str) -> String {
+    format!("_acme-challenge.{}", domain.trim_start_matches("*."))
+}
+
+#[cfg(all(test, any(feature = "acme-cloudflare", feature = "acme-route53")))]
+mod mock {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use crate::{
+        endpoint::make,
+        http::{HeaderMap, Method},
+        listener::{Acceptor, Listener, TcpListener},
+        Response, Server,
+    };
+
+    /// A request received by the [`MockServer`].
+    #[derive(Clone)]
+    pub(crate) struct RecordedRequest {
+        pub(crate) method: Method,
+        pub(crate) uri: String,
+        pub(crate) headers: HeaderMap,
+        pub(crate) body: String,
+    }
+
+    /// A server that records every request and responds with the result of
+    /// the specified function.
+    pub(crate) struct MockServer {
+        pub(crate) url: String,
+        requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    }
+
+    impl MockServer {
+        pub(crate) async fn start(
+            handler: impl Fn(&RecordedRequest) -> Response + Send + Sync + 'static,
+        ) -> Self {
+            let acceptor = TcpListener::bind("127.0.0.1:0")
+                .into_acceptor()
+                .await
+                .unwrap();
+            let addr = acceptor
+                .local_addr()
+                .remove(0)
+                .as_socket_addr()
+                .cloned()
+                .unwrap();
+            let requests: Arc<Mutex<Vec<RecordedRequest>>> = Default::default();
+            let handler = Arc::new(handler);
+
+            tokio::spawn({
+                let requests = requests.clone();
+                async move {
+                    let _ = Server::new_with_acceptor(acceptor)
+                        .run(make(move |req| {
+                            let requests = requests.clone();
+                            let handler = handler.clone();
+                            async move {
+                                let method = req.method().clone();
+                                let uri = req.uri().to_string();
+                                let headers = req.headers().clone();
+                                let body = req.into_body().into_string().await.unwrap();
+                                let req = RecordedRequest {
+                                    method,
+                                    uri,
+                                    headers,
+                                    body,
+                                };
+                                let resp = handler(&req);
+                                requests.lock().push(req);
+                                resp
+                            }
+                        }))
+                        .await;
+                }
+            });
+
+            Self {
+                url: format!("http://{}", addr),
+                requests,
+            }
+        }
+
+        pub(crate) fn requests(&self) -> Vec<RecordedRequest> {
+            self.requests.lock().clone()
+        }
+    }
+}
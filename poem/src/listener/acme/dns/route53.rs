@@ -0,0 +1,513 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    time::Duration,
+};
+
+use http::{header, Method};
+use parking_lot::Mutex;
+use ring::{
+    digest::{digest, SHA256},
+    hmac,
+};
+
+use crate::{
+    listener::acme::{
+        client::{new_http_client, HttpClient},
+        dns::DnsProvider,
+    },
+    Body, Request,
+};
+
+const HOST: &str = "route53.amazonaws.com";
+const REGION: &str = "us-east-1";
+const SERVICE: &str = "route53";
+
+/// A [`DnsProvider`] for [Amazon Route 53](https://aws.amazon.com/route53/).
+///
+/// The credentials require the `route53:ChangeResourceRecordSets` and
+/// `route53:GetChange` permissions.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::acme::{AutoCert, Route53Provider};
+///
+/// let auto_cert = AutoCert::builder()
+///     .domain("*.example.com")
+///     .dns_provider(Route53Provider::new(
+///         "access key id",
+///         "secret access key",
+///         "hosted zone id",
+///     ))
+///     .build();
+/// ```
+pub struct Route53Provider {
+    client: HttpClient,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    hosted_zone_id: String,
+    endpoint: String,
+    records: Mutex<HashMap<String, Vec<String>>>,
+    changes: Mutex<HashMap<String, String>>,
+}
+
+impl Route53Provider {
+    /// Create a Route 53 DNS provider with the specified credentials and
+    /// hosted zone id.
+    pub fn new(
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+        hosted_zone_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            client: new_http_client(None, None),
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token: None,
+            hosted_zone_id: hosted_zone_id.into(),
+            endpoint: format!("https://{}", HOST),
+            records: Default::default(),
+            changes: Default::default(),
+        }
+    }
+
+    /// Sets the session token for temporary credentials.
+    #[must_use]
+    pub fn session_token(self, token: impl Into<String>) -> Self {
+        Self {
+            session_token: Some(token.into()),
+            ..self
+        }
+    }
+
+    async fn request(&self, method: Method, path: &str, body: String) -> IoResult<String> {
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut headers = vec![("host", HOST), ("x-amz-date", amz_date.as_str())];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.as_str()));
+        }
+        let (canonical_request, signed_headers) =
+            canonical_request(&method, path, &headers, body.as_bytes());
+        let scope = format!("{}/{}/{}/aws4_request", date, REGION, SERVICE);
+        let signature = hex(&hmac_sha256(
+            &signing_key(&self.secret_access_key, &date, REGION, SERVICE),
+            string_to_sign(&amz_date, &scope, &canonical_request).as_bytes(),
+        ));
+
+        let mut builder = Request::builder()
+            .method(method)
+            .uri_str(format!("{}{}", self.endpoint, path))
+            .header(header::HOST, HOST)
+            .header("x-amz-date", amz_date)
+            .header(
+                header::AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, scope, signed_headers, signature
+                ),
+            );
+        if let Some(token) = &self.session_token {
+            builder = builder.header("x-amz-security-token", token);
+        }
+        if !body.is_empty() {
+            builder = builder.content_type("text/xml");
+        }
+
+        let resp = self
+            .client
+            .request(builder.body(body).into())
+            .await
+            .map_err(|err| {
+                IoError::new(
+                    ErrorKind::Other,
+                    format!("failed to send route53 request: {}", err),
+                )
+            })?;
+        let status = resp.status();
+        let body = Body(resp.into_body())
+            .into_string()
+            .await
+            .map_err(|_| IoError::new(ErrorKind::Other, "failed to read route53 response"))?;
+
+        if !status.is_success() {
+            return Err(IoError::new(
+                ErrorKind::Other,
+                format!("route53 request failed: status = {}, {}", status, body),
+            ));
+        }
+
+        Ok(body)
+    }
+
+    async fn change_record_set(&self, action: &str, name: &str, values: &[String]) -> IoResult<()> {
+        let mut records = String::new();
+        for value in values {
+            let _ = write!(
+                records,
+                "<ResourceRecord><Value>\"{}\"</Value></ResourceRecord>",
+                value
+            );
+        }
+
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/2013-04-01/">"#,
+                "<ChangeBatch><Changes><Change><Action>{}</Action><ResourceRecordSet>",
+                "<Name>{}</Name><Type>TXT</Type><TTL>60</TTL><ResourceRecords>{}</ResourceRecords>",
+                "</ResourceRecordSet></Change></Changes></ChangeBatch>",
+                "</ChangeResourceRecordSetsRequest>"
+            ),
+            action, name, records
+        );
+
+        let resp = self
+            .request(
+                Method::POST,
+                &format!("/2013-04-01/hostedzone/{}/rrset", self.hosted_zone_id),
+                body,
+            )
+            .await?;
+
+        if let Some(change_id) = xml_element(&resp, "Id") {
+            self.changes.lock().insert(
+                name.to_string(),
+                change_id.trim_start_matches("/change/").to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DnsProvider for Route53Provider {
+    async fn set_txt_record(&self, name: &str, value: &str) -> IoResult<()> {
+        let values = {
+            let mut records = self.records.lock();
+            let values = records.entry(name.to_string()).or_default();
+            values.push(value.to_string());
+            values.clone()
+        };
+        self.change_record_set("UPSERT", name, &values).await
+    }
+
+    async fn remove_txt_record(&self, name: &str, value: &str) -> IoResult<()> {
+        let (old_values, values) = {
+            let mut records = self.records.lock();
+            let values = records.entry(name.to_string()).or_default();
+            let old_values = values.clone();
+            values.retain(|v| v != value);
+            if values.is_empty() {
+                records.remove(name);
+            }
+            (old_values, records.get(name).cloned().unwrap_or_default())
+        };
+
+        if values.is_empty() {
+            if old_values.is_empty() {
+                return Ok(());
+            }
+            self.change_record_set("DELETE", name, &old_values).await
+        } else {
+            self.change_record_set("UPSERT", name, &values).await
+        }
+    }
+
+    async fn wait_for_propagation(&self, name: &str, _value: &str) -> IoResult<()> {
+        let change_id = match self.changes.lock().get(name) {
+            Some(change_id) => change_id.clone(),
+            None => return Ok(()),
+        };
+
+        for _ in 0..60 {
+            let resp = self
+                .request(
+                    Method::GET,
+                    &format!("/2013-04-01/change/{}", change_id),
+                    String::new(),
+                )
+                .await?;
+            if xml_element(&resp, "Status") == Some("INSYNC") {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+
+        Err(IoError::new(
+            ErrorKind::Other,
+            format!("route53 change `{}` is not in sync", change_id),
+        ))
+    }
+}
+
+/// Returns the canonical request and the signed headers of the AWS Signature
+/// Version 4.
+///
+/// The `headers` must be lowercase and sorted by name, and the `path` must not
+/// contain a query string.
+///
+/// Reference: <https://docs.aws.amazon.com/general/latest/gr/create-signed-request.html>
+fn canonical_request(
+    method: &Method,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> (String, String) {
+    let mut canonical_headers = String::new();
+    for (name, value) in headers {
+        let _ = writeln!(canonical_headers, "{}:{}", name, value.trim());
+    }
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        hex(digest(&SHA256, body).as_ref()),
+    );
+    (canonical_request, signed_headers)
+}
+
+fn string_to_sign(amz_date: &str, scope: &str, canonical_request: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(digest(&SHA256, canonical_request.as_bytes()).as_ref()),
+    )
+}
+
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let mut key = format!("AWS4{}", secret_access_key).into_bytes();
+    for data in [date, region, service, "aws4_request"] {
+        key = hmac_sha256(&key, data.as_bytes());
+    }
+    key
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(&xml[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http::StatusCode, listener::acme::dns::mock::MockServer, IntoResponse};
+
+    const SECRET_ACCESS_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+
+    /// The `get-vanilla` and `post-vanilla` cases of the AWS Signature Version
+    /// 4 test suite.
+    #[test]
+    fn sign_test_suite() {
+        let headers = [
+            ("host", "example.amazonaws.com"),
+            ("x-amz-date", "20150830T123600Z"),
+        ];
+        let scope = "20150830/us-east-1/service/aws4_request";
+        let key = signing_key(SECRET_ACCESS_KEY, "20150830", "us-east-1", "service");
+
+        let (request, signed_headers) = canonical_request(&Method::GET, "/", &headers, b"");
+        assert_eq!(
+            request,
+            "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(signed_headers, "host;x-amz-date");
+        let data = string_to_sign("20150830T123600Z", scope, &request);
+        assert_eq!(
+            data,
+            "AWS4-HMAC-SHA256\n20150830T123600Z\n20150830/us-east-1/service/aws4_request\nbb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(&key, data.as_bytes())),
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+
+        let (request, _) = canonical_request(&Method::POST, "/", &headers, b"");
+        let data = string_to_sign("20150830T123600Z", scope, &request);
+        assert_eq!(
+            hex(&hmac_sha256(&key, data.as_bytes())),
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    /// The signing key examples of the AWS General Reference.
+    #[test]
+    fn derive_signing_key() {
+        assert_eq!(
+            hex(&signing_key(
+                SECRET_ACCESS_KEY,
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(
+            hex(&signing_key(
+                SECRET_ACCESS_KEY,
+                "20150830",
+                "us-east-1",
+                "iam"
+            )),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+    }
+
+    fn rrset_body(action: &str, values: &[&str]) -> String {
+        let records = values
+            .iter()
+            .map(|value| {
+                format!(
+                    "<ResourceRecord><Value>\"{}\"</Value></ResourceRecord>",
+                    value
+                )
+            })
+            .collect::<String>();
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/2013-04-01/">"#,
+                "<ChangeBatch><Changes><Change><Action>{}</Action><ResourceRecordSet>",
+                "<Name>_acme-challenge.example.com</Name><Type>TXT</Type><TTL>60</TTL>",
+                "<ResourceRecords>{}</ResourceRecords>",
+                "</ResourceRecordSet></Change></Changes></ChangeBatch>",
+                "</ChangeResourceRecordSetsRequest>"
+            ),
+            action, records
+        )
+    }
+
+    #[tokio::test]
+    async fn change_record_sets() {
+        let server = MockServer::start(|req| {
+            if req.uri.starts_with("/2013-04-01/change/") {
+                "<GetChangeResponse><ChangeInfo><Id>/change/C1</Id><Status>INSYNC</Status></ChangeInfo></GetChangeResponse>"
+                    .into_response()
+            } else {
+                "<ChangeResourceRecordSetsResponse><ChangeInfo><Id>/change/C1</Id><Status>PENDING</Status></ChangeInfo></ChangeResourceRecordSetsResponse>"
+                    .into_response()
+            }
+        })
+        .await;
+        let provider = Route53Provider {
+            endpoint: server.url.clone(),
+            ..Route53Provider::new("AKIDEXAMPLE", SECRET_ACCESS_KEY, "Z1").session_token("token")
+        };
+        let name = "_acme-challenge.example.com";
+
+        provider.set_txt_record(name, "a").await.unwrap();
+        provider.set_txt_record(name, "b").await.unwrap();
+        provider.wait_for_propagation(name, "b").await.unwrap();
+        provider.remove_txt_record(name, "a").await.unwrap();
+        provider.remove_txt_record(name, "b").await.unwrap();
+        provider.remove_txt_record(name, "b").await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|req| (req.method.clone(), req.uri.as_str(), req.body.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    Method::POST,
+                    "/2013-04-01/hostedzone/Z1/rrset",
+                    rrset_body("UPSERT", &["a"])
+                ),
+                (
+                    Method::POST,
+                    "/2013-04-01/hostedzone/Z1/rrset",
+                    rrset_body("UPSERT", &["a", "b"])
+                ),
+                (Method::GET, "/2013-04-01/change/C1", String::new()),
+                (
+                    Method::POST,
+                    "/2013-04-01/hostedzone/Z1/rrset",
+                    rrset_body("UPSERT", &["b"])
+                ),
+                (
+                    Method::POST,
+                    "/2013-04-01/hostedzone/Z1/rrset",
+                    rrset_body("DELETE", &["b"])
+                ),
+            ]
+        );
+
+        let headers = &requests[0].headers;
+        assert_eq!(headers.get(header::HOST).unwrap(), HOST);
+        assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "text/xml");
+        assert_eq!(headers.get("x-amz-security-token").unwrap(), "token");
+        let amz_date = headers.get("x-amz-date").unwrap().to_str().unwrap();
+        let date = &amz_date[..8];
+        let scope = format!("{}/us-east-1/route53/aws4_request", date);
+        let (request, _) = canonical_request(
+            &Method::POST,
+            "/2013-04-01/hostedzone/Z1/rrset",
+            &[
+                ("host", HOST),
+                ("x-amz-date", amz_date),
+                ("x-amz-security-token", "token"),
+            ],
+            requests[0].body.as_bytes(),
+        );
+        let signature = hex(&hmac_sha256(
+            &signing_key(SECRET_ACCESS_KEY, date, "us-east-1", "route53"),
+            string_to_sign(amz_date, &scope, &request).as_bytes(),
+        ));
+        assert_eq!(
+            headers.get(header::AUTHORIZATION).unwrap().to_str().unwrap(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/{}, SignedHeaders=host;x-amz-date;x-amz-security-token, Signature={}",
+                scope, signature
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn request_failed() {
+        let server =
+            MockServer::start(|_| (StatusCode::FORBIDDEN, "<ErrorResponse/>").into_response())
+                .await;
+        let provider = Route53Provider {
+            endpoint: server.url.clone(),
+            ..Route53Provider::new("AKIDEXAMPLE", SECRET_ACCESS_KEY, "Z1")
+        };
+
+        let err = provider
+            .set_txt_record("_acme-challenge.example.com", "a")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "route53 request failed: status = 403 Forbidden, <ErrorResponse/>"
+        );
+    }
+}
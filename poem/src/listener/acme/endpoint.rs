@@ -4,9 +4,72 @@ use parking_lot::RwLock;
 
 use crate::{error::NotFoundError, Endpoint, IntoResponse, Request, Response, Result};
 
+/// A store of the responses for the `HTTP-01` challenge, which is shared
+/// between the [`AutoCert`](crate::listener::acme::AutoCert) and the
+/// endpoints created with [`http01_endpoint`].
+///
+/// Cloning a `Http01TokenStore` is cheap, and the clones share the same
+/// responses.
+#[derive(Debug, Clone, Default)]
+pub struct Http01TokenStore(Arc<RwLock<HashMap<String, String>>>);
+
+impl Http01TokenStore {
+    /// Create an empty token store.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub(crate) fn insert(&self, token: impl Into<String>, key_authorization: impl Into<String>) {
+        self.0
+            .write()
+            .insert(token.into(), key_authorization.into());
+    }
+
+    pub(crate) fn remove(&self, token: &str) {
+        self.0.write().remove(token);
+    }
+
+    pub(crate) fn get(&self, token: &str) -> Option<String> {
+        self.0.read().get(token).cloned()
+    }
+}
+
+/// Create an endpoint for the `HTTP-01` challenge which serves the responses
+/// in the `token_store`.
+///
+/// This is useful if the application is behind a reverse proxy which owns the
+/// port 80, the endpoint can be mounted on the existing routes, and the same
+/// token store should be passed to
+/// [`AutoCertBuilder::http01_token_store`](crate::listener::acme::AutoCertBuilder::http01_token_store).
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     listener::acme::{http01_endpoint, AutoCert, ChallengeType, Http01TokenStore},
+///     Route,
+/// };
+///
+/// let token_store = Http01TokenStore::new();
+/// let auto_cert = AutoCert::builder()
+///     .domain("example.com")
+///     .challenge_type(ChallengeType::Http01)
+///     .http01_token_store(token_store.clone())
+///     .build()
+///     .unwrap();
+///
+/// let app = Route::new().at(
+///     "/.well-known/acme-challenge/:token",
+///     http01_endpoint(token_store),
+/// );
+/// ```
+pub fn http01_endpoint(token_store: Http01TokenStore) -> Http01Endpoint {
+    Http01Endpoint { keys: token_store }
+}
+
 /// An endpoint for `HTTP-01` challenge.
 pub struct Http01Endpoint {
-    pub(crate) keys: Arc<RwLock<HashMap<String, String>>>,
+    pub(crate) keys: Http01TokenStore,
 }
 
 #[async_trait::async_trait]
@@ -14,17 +77,47 @@ impl Endpoint for Http01Endpoint {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Result<Self::Output> {
-        if let Some(token) = req
-            .uri()
-            .path()
-            .strip_prefix("/.well-known/acme-challenge/")
-        {
-            let keys = self.keys.read();
-            if let Some(value) = keys.get(token) {
-                return Ok(value.clone().into_response());
+        // also check the original uri, in case the endpoint is nested
+        let token = [req.uri(), req.original_uri()]
+            .into_iter()
+            .find_map(|uri| uri.path().strip_prefix("/.well-known/acme-challenge/"));
+        if let Some(token) = token {
+            if let Some(value) = self.keys.get(token) {
+                return Ok(value.into_response());
             }
         }
 
         Err(NotFoundError.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{endpoint::make_sync, test::TestClient, Route};
+
+    #[tokio::test]
+    async fn mounted_endpoint() {
+        let token_store = Http01TokenStore::new();
+        token_store.insert("token", "token.thumb");
+
+        let app = Route::new().at("/", make_sync(|_| "index")).at(
+            "/.well-known/acme-challenge/:token",
+            http01_endpoint(token_store.clone()),
+        );
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/.well-known/acme-challenge/token").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("token.thumb").await;
+
+        token_store.remove("token");
+        cli.get("/.well-known/acme-challenge/token")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        cli.get("/").send().await.assert_text("index").await;
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{Error as IoError, ErrorKind},
+};
+
+use http::StatusCode;
+use serde::Deserialize;
+
+/// A problem document returned by the ACME server.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-6.7>
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Problem {
+    /// The problem type, such as `urn:ietf:params:acme:error:badNonce`.
+    #[serde(rename = "type", default)]
+    pub ty: String,
+    /// A human-readable explanation of the problem.
+    #[serde(default)]
+    pub detail: String,
+    /// The HTTP status code.
+    pub status: Option<u16>,
+}
+
+impl Problem {
+    /// Returns `true` if the nonce of the request is rejected by the server.
+    pub fn is_bad_nonce(&self) -> bool {
+        self.ty == "urn:ietf:params:acme:error:badNonce"
+    }
+
+    /// Returns `true` if the request is rejected because of rate limiting.
+    pub fn is_rate_limited(&self) -> bool {
+        self.ty == "urn:ietf:params:acme:error:rateLimited"
+    }
+
+    /// Returns `true` if the request is rejected because the account does not
+    /// exist.
+    pub fn is_account_does_not_exist(&self) -> bool {
+        self.ty == "urn:ietf:params:acme:error:accountDoesNotExist"
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.ty.is_empty() {
+            f.write_str(&self.detail)
+        } else {
+            write!(f, "{} ({})", self.detail, self.ty)
+        }
+    }
+}
+
+/// A possible error value when communicating with the ACME server.
+#[derive(Debug, thiserror::Error)]
+pub enum AcmeError {
+    /// Failed to send the HTTP request.
+    #[error("http: {0}")]
+    Http(#[from] hyper::Error),
+
+    /// The ACME server returned a problem document.
+    #[error("acme server: {0}")]
+    Problem(Problem),
+
+    /// The ACME server returned an unexpected status code.
+    #[error("unexpected status code: {0}")]
+    UnexpectedStatus(StatusCode),
+
+    /// The order failed, with the problem document returned by the ACME
+    /// server.
+    #[error("order failed: {0}")]
+    OrderInvalid(Problem),
+
+    /// The response of the ACME server is invalid.
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+
+    /// Io error.
+    #[error("io: {0}")]
+    Io(#[from] IoError),
+}
+
+impl AcmeError {
+    /// Returns the problem document if the ACME server returned one.
+    pub fn problem(&self) -> Option<&Problem> {
+        match self {
+            AcmeError::Problem(problem) | AcmeError::OrderInvalid(problem) => Some(problem),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the ACME server rejected the request, so the request
+    /// has no effect. The other errors, such as a lost connection or a server
+    /// error, leave the result of the request unknown.
+    pub(crate) fn is_rejected(&self) -> bool {
+        let status = match self {
+            AcmeError::Problem(problem) => problem.status,
+            AcmeError::UnexpectedStatus(status) => Some(status.as_u16()),
+            _ => return false,
+        };
+        matches!(status, Some(status) if (400..500).contains(&status))
+    }
+}
+
+impl From<AcmeError> for IoError {
+    fn from(err: AcmeError) -> Self {
+        match err {
+            AcmeError::Io(err) => err,
+            err => IoError::new(ErrorKind::Other, err),
+        }
+    }
+}
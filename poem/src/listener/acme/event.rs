@@ -0,0 +1,39 @@
+use std::{io::Error as IoError, sync::Arc, time::SystemTime};
+
+pub(crate) type EventCallback = Arc<dyn Fn(CertEvent) + Send + Sync>;
+
+/// An event about the certificate managed by
+/// [`AutoCert`](crate::listener::acme::AutoCert).
+///
+/// See also [`AutoCertBuilder::on_event`](crate::listener::acme::AutoCertBuilder::on_event).
+#[derive(Debug)]
+pub enum CertEvent {
+    /// A certificate has been issued, and there was no certificate before.
+    Issued {
+        /// The domains of the certificate.
+        domains: Vec<String>,
+        /// The expiration time of the new certificate.
+        expires_at: SystemTime,
+    },
+    /// The certificate has been renewed.
+    Renewed {
+        /// The domains of the certificate.
+        domains: Vec<String>,
+        /// The expiration time of the new certificate.
+        expires_at: SystemTime,
+    },
+    /// Failed to issue or renew the certificate, it will be retried later.
+    RenewalFailed {
+        /// The domains of the certificate.
+        domains: Vec<String>,
+        /// The error that caused the failure.
+        error: IoError,
+    },
+    /// The current certificate is about to expire.
+    AboutToExpire {
+        /// The domains of the certificate.
+        domains: Vec<String>,
+        /// The expiration time of the current certificate.
+        expires_at: SystemTime,
+    },
+}
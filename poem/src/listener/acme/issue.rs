@@ -0,0 +1,360 @@
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+use http::Uri;
+use rcgen::{Certificate, CertificateParams, DistinguishedName};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tokio_rustls::rustls::{
+    sign::{any_supported_type, CertifiedKey},
+    PrivateKey,
+};
+
+use crate::listener::acme::{
+    builder::validate_domain,
+    client::AcmeClient,
+    jose,
+    protocol::{NewOrderResponse, OrderStatus},
+    AcmeError, AutoCert, ChallengeType, Problem,
+};
+
+/// A certificate issued by [`issue_certificate`].
+#[derive(Debug, Clone)]
+pub struct IssuedCertificate {
+    /// The PEM-encoded certificate chain.
+    pub certificate_pem: String,
+    /// The PEM-encoded private key in PKCS#8 format.
+    pub private_key_pem: String,
+}
+
+impl IssuedCertificate {
+    pub(crate) fn certified_key(&self) -> IoResult<CertifiedKey> {
+        let cert_chain = rustls_pemfile::certs(&mut self.certificate_pem.as_bytes())
+            .map_err(|err| IoError::new(ErrorKind::Other, format!("invalid pem: {}", err)))?
+            .into_iter()
+            .map(tokio_rustls::rustls::Certificate)
+            .collect();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut self.private_key_pem.as_bytes())
+            .map_err(|err| IoError::new(ErrorKind::Other, format!("invalid pem: {}", err)))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| IoError::new(ErrorKind::Other, "missing private key"))?;
+        let key = any_supported_type(&PrivateKey(key)).map_err(|err| {
+            IoError::new(
+                ErrorKind::Other,
+                format!("failed to load private key: {}", err),
+            )
+        })?;
+        Ok(CertifiedKey::new(cert_chain, key))
+    }
+}
+
+/// Obtain a certificate for the `domains` without running a listener.
+///
+/// The ACME configuration, such as the directory, the account and the
+/// challenge solver, is taken from `auto_cert`, and the domains of
+/// `auto_cert` are ignored. Since there is no listener, the `TLS-ALPN-01`
+/// challenge can not be used, for the `HTTP-01` challenge the
+/// [`AutoCert::http_01_endpoint`] must be served on port 80.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::listener::acme::{issue_certificate, AutoCert, ChallengeType};
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let auto_cert = AutoCert::builder()
+///     .domain("example.com")
+///     .challenge_type(ChallengeType::Http01)
+///     .build()?;
+/// let issued = issue_certificate(["example.com", "www.example.com"], &auto_cert).await?;
+/// std::fs::write("cert.pem", issued.certificate_pem)?;
+/// std::fs::write("key.pem", issued.private_key_pem)?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn issue_certificate(
+    domains: impl IntoIterator<Item = impl Into<String>>,
+    auto_cert: &AutoCert,
+) -> IoResult<IssuedCertificate> {
+    if auto_cert.challenge_type == ChallengeType::TlsAlpn01 {
+        return Err(IoError::new(
+            ErrorKind::Other,
+            "the `TLS-ALPN-01` challenge requires a listener",
+        ));
+    }
+
+    let mut domains = domains.into_iter().map(Into::into).collect::<Vec<String>>();
+    domains.sort();
+    domains.dedup();
+    if domains.is_empty() {
+        return Err(IoError::new(
+            ErrorKind::Other,
+            "at least one domain name is expected",
+        ));
+    }
+    for domain in &domains {
+        validate_domain(domain, auto_cert.challenge_type)?;
+    }
+
+    issue(auto_cert, &domains, None).await
+}
+
+/// Issue a certificate, the pending order is cached with `order_cache_key`
+/// so that it can be resumed.
+pub(crate) async fn issue(
+    auto_cert: &AutoCert,
+    domains: &[String],
+    order_cache_key: Option<&str>,
+) -> IoResult<IssuedCertificate> {
+    tracing::debug!(domains = ?domains, "issue certificate");
+
+    let mut client = auto_cert.client().await?;
+    let client = &mut *client;
+
+    let mut presented = Vec::new();
+    let res = process_order(client, auto_cert, domains, order_cache_key, &mut presented).await;
+
+    for (domain, token, key_authorization) in presented {
+        if let Err(err) = auto_cert
+            .solver
+            .cleanup(&domain, &token, &key_authorization)
+            .await
+        {
+            tracing::warn!(domain = domain.as_str(), error = %err, "failed to clean up challenge");
+        }
+    }
+
+    let (cert, certificate_pem) = res?;
+    Ok(IssuedCertificate {
+        certificate_pem: String::from_utf8(certificate_pem).map_err(|_| {
+            IoError::new(
+                ErrorKind::Other,
+                "invalid response: the certificate is not valid utf-8",
+            )
+        })?,
+        private_key_pem: cert.serialize_private_key_pem(),
+    })
+}
+
+/// Drive the order through its states until the certificate is issued,
+/// returns the certificate with the private key and the downloaded
+/// certificate chain.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.1.6>
+async fn process_order(
+    client: &mut AcmeClient,
+    auto_cert: &AutoCert,
+    domains: &[String],
+    order_cache_key: Option<&str>,
+    presented: &mut Vec<(String, String, String)>,
+) -> IoResult<(Certificate, Vec<u8>)> {
+    let deadline = Instant::now() + auto_cert.poll_timeout;
+    let (order_url, mut order_resp) =
+        new_order(client, auto_cert, domains, order_cache_key).await?;
+    let mut authorized = false;
+    let mut cert = None;
+
+    loop {
+        tracing::debug!(order_url = %order_url, status = ?order_resp.status, "order status");
+
+        match order_resp.status {
+            OrderStatus::Pending => {
+                if authorized {
+                    poll_wait(auto_cert, deadline, "the order to become ready").await?;
+                } else {
+                    authorize(client, auto_cert, &order_resp, deadline, presented).await?;
+                    authorized = true;
+                }
+                order_resp = client.fetch_order(&order_url).await?;
+            }
+            OrderStatus::Ready => {
+                if cert.is_some() {
+                    return Err(IoError::new(
+                        ErrorKind::Other,
+                        "the certificate request has been sent, but the order is still ready",
+                    ));
+                }
+                let (new_cert, csr) = create_csr(auto_cert, domains)?;
+                cert = Some(new_cert);
+                order_resp = client.send_csr(&order_resp.finalize, &csr).await?;
+            }
+            OrderStatus::Processing => {
+                poll_wait(auto_cert, deadline, "the certificate to be issued").await?;
+                order_resp = client.fetch_order(&order_url).await?;
+            }
+            OrderStatus::Valid => break,
+            OrderStatus::Invalid => {
+                return Err(
+                    AcmeError::OrderInvalid(order_resp.error.unwrap_or_else(|| Problem {
+                        detail: "unknown error".to_string(),
+                        ..Default::default()
+                    }))
+                    .into(),
+                );
+            }
+        }
+    }
+
+    let cert = cert.ok_or_else(|| {
+        IoError::new(
+            ErrorKind::Other,
+            "the order is valid, but no certificate request has been sent",
+        )
+    })?;
+
+    // download certificate
+    let acme_cert_pem = client
+        .obtain_certificate(
+            order_resp.certificate.as_ref().ok_or_else(|| {
+                IoError::new(
+                    ErrorKind::Other,
+                    "invalid response: missing `certificate` url",
+                )
+            })?,
+            auto_cert.preferred_chain.as_deref(),
+        )
+        .await?;
+
+    Ok((cert, acme_cert_pem))
+}
+
+/// Generate a new private key, returns the certificate and the DER-encoded
+/// certificate request.
+fn create_csr(auto_cert: &AutoCert, domains: &[String]) -> IoResult<(Certificate, Vec<u8>)> {
+    let mut params = CertificateParams::new(domains.to_vec());
+    params.distinguished_name = DistinguishedName::new();
+    params.alg = auto_cert.key_type.algorithm();
+    params.key_pair = Some(auto_cert.key_type.generate()?);
+    let cert = Certificate::from_params(params).map_err(|err| {
+        IoError::new(
+            ErrorKind::Other,
+            format!("failed create certificate request: {}", err),
+        )
+    })?;
+    let csr = cert.serialize_request_der().map_err(|err| {
+        IoError::new(
+            ErrorKind::Other,
+            format!("failed to serialize request der {}", err),
+        )
+    })?;
+    Ok((cert, csr))
+}
+
+/// Wait for the next poll, returns an error if the deadline will be exceeded.
+async fn poll_wait(auto_cert: &AutoCert, deadline: Instant, waiting_for: &str) -> IoResult<()> {
+    if Instant::now() + auto_cert.poll_interval > deadline {
+        return Err(IoError::new(
+            ErrorKind::TimedOut,
+            format!("timed out waiting for {}", waiting_for),
+        ));
+    }
+    tokio::time::sleep(auto_cert.poll_interval).await;
+    Ok(())
+}
+
+/// Resume the cached pending order, or create a new order.
+async fn new_order(
+    client: &mut AcmeClient,
+    auto_cert: &AutoCert,
+    domains: &[String],
+    order_cache_key: Option<&str>,
+) -> IoResult<(Uri, NewOrderResponse)> {
+    #[derive(Serialize, Deserialize)]
+    struct CachedOrder {
+        url: String,
+        domains: Vec<String>,
+    }
+
+    let cache = auto_cert.cache.as_ref().zip(order_cache_key);
+
+    if let Some((cache, order_cache_key)) = cache {
+        let cached_order = cache
+            .load(order_cache_key)
+            .await?
+            .and_then(|data| serde_json::from_slice::<CachedOrder>(&data).ok())
+            .filter(|order| order.domains == domains);
+        if let Some(order_url) = cached_order.and_then(|order| order.url.parse::<Uri>().ok()) {
+            match client.fetch_order(&order_url).await {
+                Ok(order_resp)
+                    if matches!(order_resp.status, OrderStatus::Pending | OrderStatus::Ready) =>
+                {
+                    tracing::debug!(order_url = %order_url, "resume cached order");
+                    return Ok((order_url, order_resp));
+                }
+                Ok(_) => {}
+                Err(err) => tracing::debug!(error = %err, "failed to fetch cached order"),
+            }
+        }
+    }
+
+    let (order_url, order_resp) = client.new_order(domains).await?;
+    if let Some((cache, order_cache_key)) = cache {
+        let data = serde_json::to_vec(&CachedOrder {
+            url: order_url.to_string(),
+            domains: domains.to_vec(),
+        })
+        .map_err(|err| IoError::new(ErrorKind::Other, err))?;
+        cache.store(order_cache_key, &data).await?;
+    }
+    Ok((order_url, order_resp))
+}
+
+async fn authorize(
+    client: &AcmeClient,
+    auto_cert: &AutoCert,
+    order_resp: &NewOrderResponse,
+    deadline: Instant,
+    presented: &mut Vec<(String, String, String)>,
+) -> IoResult<()> {
+    loop {
+        let mut all_valid = true;
+
+        for auth_url in &order_resp.authorizations {
+            let resp = client.fetch_authorization(auth_url).await?;
+
+            if resp.status == "valid" {
+                continue;
+            }
+
+            all_valid = false;
+
+            if resp.status == "pending" {
+                let challenge = resp.find_challenge(auto_cert.challenge_type)?;
+
+                let key_authorization =
+                    jose::key_authorization(client.key_pair(), &challenge.token)?;
+                let item = (
+                    resp.identifier.value.clone(),
+                    challenge.token.clone(),
+                    key_authorization,
+                );
+                if !presented.contains(&item) {
+                    auto_cert.solver.present(&item.0, &item.1, &item.2).await?;
+                    presented.push(item);
+                }
+
+                client
+                    .trigger_challenge(&resp.domain(), auto_cert.challenge_type, &challenge.url)
+                    .await?;
+            } else if resp.status == "invalid" {
+                return Err(IoError::new(
+                    ErrorKind::Other,
+                    format!(
+                        "unable to authorize `{}`: {}",
+                        resp.domain(),
+                        resp.error
+                            .as_ref()
+                            .map(|problem| &*problem.detail)
+                            .unwrap_or("unknown")
+                    ),
+                ));
+            }
+        }
+
+        if all_valid {
+            return Ok(());
+        }
+
+        poll_wait(auto_cert, deadline, "the authorizations").await?;
+    }
+}
@@ -2,12 +2,16 @@ use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 
 use base64::URL_SAFE_NO_PAD;
 use http::{Method, Uri};
-use hyper::{client::HttpConnector, Client};
-use hyper_rustls::HttpsConnector;
-use ring::digest::{digest, Digest, SHA256};
-use serde::{de::DeserializeOwned, Serialize};
+use ring::{
+    digest::{digest, Digest, SHA256},
+    hmac,
+};
+use serde::Serialize;
 
-use crate::{listener::acme::keypair::KeyPair, Request, Response};
+use crate::{
+    listener::acme::{client::HttpClient, error::AcmeError, keypair::KeyPair},
+    Request, Response,
+};
 
 #[derive(Serialize)]
 struct Protected<'a> {
@@ -92,28 +96,139 @@ fn sha256(data: impl AsRef<[u8]>) -> Digest {
     digest(&SHA256, data.as_ref())
 }
 
+/// A JWS object in the flattened JSON serialization.
 #[derive(Serialize)]
-struct Body {
+pub(crate) struct Jws {
     protected: String,
     payload: String,
     signature: String,
 }
 
-pub(crate) async fn request(
-    cli: &Client<HttpsConnector<HttpConnector>>,
+/// The key used to bind the ACME account to an account in a non-ACME system.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.4>
+#[derive(Clone)]
+pub(crate) struct ExternalAccountKey {
+    pub(crate) kid: String,
+    pub(crate) hmac_key: Vec<u8>,
+}
+
+impl ExternalAccountKey {
+    pub(crate) fn new(kid: String, hmac_key: &str) -> IoResult<Self> {
+        let hmac_key = base64::decode_config(hmac_key.trim_end_matches('='), URL_SAFE_NO_PAD)
+            .map_err(|err| {
+                IoError::new(
+                    ErrorKind::Other,
+                    format!("invalid external account hmac key: {}", err),
+                )
+            })?;
+        Ok(Self { kid, hmac_key })
+    }
+}
+
+/// Create the `externalAccountBinding` object for the `newAccount` request.
+pub(crate) fn external_account_binding(
+    key_pair: &KeyPair,
+    eab: &ExternalAccountKey,
+    uri: &Uri,
+) -> IoResult<Jws> {
+    #[derive(Serialize)]
+    struct EabProtected<'a> {
+        alg: &'static str,
+        kid: &'a str,
+        url: &'a str,
+    }
+
+    let protected = serde_json::to_vec(&EabProtected {
+        alg: "HS256",
+        kid: &eab.kid,
+        url: &uri.to_string(),
+    })
+    .map_err(|err| IoError::new(ErrorKind::Other, format!("failed to encode jwt: {}", err)))?;
+    let protected = base64::encode_config(protected, URL_SAFE_NO_PAD);
+    let payload = serde_json::to_vec(&Jwk::new(key_pair))
+        .map_err(|err| IoError::new(ErrorKind::Other, format!("failed to encode jwk: {}", err)))?;
+    let payload = base64::encode_config(payload, URL_SAFE_NO_PAD);
+    let combined = format!("{}.{}", &protected, &payload);
+    let signature = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, &eab.hmac_key),
+        combined.as_bytes(),
+    );
+
+    Ok(Jws {
+        protected,
+        payload,
+        signature: base64::encode_config(signature.as_ref(), URL_SAFE_NO_PAD),
+    })
+}
+
+/// Create the inner JWS object for the `keyChange` request, which is signed
+/// with the new key.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.5>
+pub(crate) fn key_change(
+    new_key: &KeyPair,
+    old_key: &KeyPair,
+    kid: &str,
+    uri: &Uri,
+) -> IoResult<Jws> {
+    #[derive(Serialize)]
+    struct KeyChangeProtected<'a> {
+        alg: &'static str,
+        jwk: Jwk,
+        url: &'a str,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct KeyChangePayload<'a> {
+        account: &'a str,
+        old_key: Jwk,
+    }
+
+    let protected = serde_json::to_vec(&KeyChangeProtected {
+        alg: "ES256",
+        jwk: Jwk::new(new_key),
+        url: &uri.to_string(),
+    })
+    .map_err(|err| IoError::new(ErrorKind::Other, format!("failed to encode jwt: {}", err)))?;
+    let protected = base64::encode_config(protected, URL_SAFE_NO_PAD);
+    let payload = serde_json::to_vec(&KeyChangePayload {
+        account: kid,
+        old_key: Jwk::new(old_key),
+    })
+    .map_err(|err| {
+        IoError::new(
+            ErrorKind::Other,
+            format!("failed to encode payload: {}", err),
+        )
+    })?;
+    let payload = base64::encode_config(payload, URL_SAFE_NO_PAD);
+    let combined = format!("{}.{}", &protected, &payload);
+    let signature = base64::encode_config(new_key.sign(combined.as_bytes())?, URL_SAFE_NO_PAD);
+
+    Ok(Jws {
+        protected,
+        payload,
+        signature,
+    })
+}
+
+pub(crate) async fn request<T: Serialize>(
+    cli: &HttpClient,
     key_pair: &KeyPair,
     kid: Option<&str>,
     nonce: &str,
     uri: &Uri,
-    payload: Option<impl Serialize>,
-) -> IoResult<Response> {
+    payload: Option<&T>,
+) -> Result<Response, AcmeError> {
     let jwk = match kid {
         None => Some(Jwk::new(key_pair)),
         Some(_) => None,
     };
     let protected = Protected::base64(jwk, kid, nonce, &uri.to_string())?;
     let payload = match payload {
-        Some(payload) => serde_json::to_vec(&payload).map_err(|err| {
+        Some(payload) => serde_json::to_vec(payload).map_err(|err| {
             IoError::new(
                 ErrorKind::Other,
                 format!("failed to encode payload: {}", err),
@@ -124,7 +239,7 @@ pub(crate) async fn request(
     let payload = base64::encode_config(payload, URL_SAFE_NO_PAD);
     let combined = format!("{}.{}", &protected, &payload);
     let signature = base64::encode_config(key_pair.sign(combined.as_bytes())?, URL_SAFE_NO_PAD);
-    let body = serde_json::to_vec(&Body {
+    let body = serde_json::to_vec(&Jws {
         protected,
         payload,
         signature,
@@ -138,50 +253,12 @@ pub(crate) async fn request(
 
     tracing::debug!(uri = %uri, "http request");
 
-    let resp = cli.request(req.into()).await.map_err(|err| {
-        IoError::new(
-            ErrorKind::Other,
-            format!("failed to send http request: {}", err),
-        )
-    })?;
-    if !resp.status().is_success() {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!("unexpected status code: status = {}", resp.status()),
-        ));
-    }
+    let resp = cli.request(req.into()).await?;
     Ok(resp.into())
 }
 
-pub(crate) async fn request_json<T, R>(
-    cli: &Client<HttpsConnector<HttpConnector>>,
-    key_pair: &KeyPair,
-    kid: Option<&str>,
-    nonce: &str,
-    uri: &Uri,
-    payload: Option<T>,
-) -> IoResult<R>
-where
-    T: Serialize,
-    R: DeserializeOwned,
-{
-    let resp = request(cli, key_pair, kid, nonce, uri, payload).await?;
-
-    let data = resp
-        .into_body()
-        .into_string()
-        .await
-        .map_err(|_| IoError::new(ErrorKind::Other, "failed to read response"))?;
-    serde_json::from_str(&data)
-        .map_err(|err| IoError::new(ErrorKind::Other, format!("bad response: {}", err)))
-}
-
 pub(crate) fn key_authorization(key: &KeyPair, token: &str) -> IoResult<String> {
     let jwk = Jwk::new(key);
     let key_authorization = format!("{}.{}", token, jwk.thumb_sha256_base64()?);
     Ok(key_authorization)
 }
-
-pub(crate) fn key_authorization_sha256(key: &KeyPair, token: &str) -> IoResult<impl AsRef<[u8]>> {
-    Ok(sha256(key_authorization(key, token)?.as_bytes()))
-}
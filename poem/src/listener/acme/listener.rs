@@ -1,32 +1,30 @@
 use std::{
-    io::{Error as IoError, ErrorKind, Result as IoResult},
+    future::Future,
+    io::Result as IoResult,
     sync::{Arc, Weak},
-    time::{Duration, UNIX_EPOCH},
+    time::{Duration, SystemTime},
 };
 
+use futures_util::TryFutureExt;
 use http::uri::Scheme;
-use rcgen::{
-    Certificate, CertificateParams, CustomExtension, DistinguishedName, PKCS_ECDSA_P256_SHA256,
-};
 use tokio_rustls::{
     rustls::{
-        sign::{any_ecdsa_type, CertifiedKey},
+        sign::{any_supported_type, CertifiedKey},
         PrivateKey, ServerConfig,
     },
     server::TlsStream,
     TlsAcceptor,
 };
-use x509_parser::prelude::{FromDer, X509Certificate};
 
 use crate::{
     listener::{
         acme::{
-            client::AcmeClient,
-            jose,
-            resolver::{ResolveServerCert, ACME_TLS_ALPN_NAME},
-            AutoCert, ChallengeType,
+            cache, issue, renewal,
+            renewal::RenewalSchedule,
+            resolver::{cert_expires_at, CertGroup, ResolveServerCert, ACME_TLS_ALPN_NAME},
+            AutoCert, CertEvent, ChallengeType,
         },
-        Acceptor, HandshakeStream, Listener,
+        rustls, Acceptor, HandshakeStream, Listener, TlsInfoSlot,
     },
     web::{LocalAddr, RemoteAddr},
 };
@@ -48,66 +46,21 @@ impl<T: Listener> Listener for AutoCertListener<T> {
     type Acceptor = AutoCertAcceptor<T::Acceptor>;
 
     async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
-        let mut client = AcmeClient::try_new(
-            &self.auto_cert.directory_url,
-            self.auto_cert.key_pair.clone(),
-            self.auto_cert.contacts.clone(),
-        )
-        .await?;
-
-        let (cache_certs, cert_key) = {
-            let mut certs = None;
-            let mut key = None;
-
-            if let Some(cache_cert) = &self.auto_cert.cache_cert {
-                match rustls_pemfile::certs(&mut cache_cert.as_slice()) {
-                    Ok(c) => certs = Some(c),
-                    Err(err) => {
-                        tracing::warn!("failed to parse cached tls certificates: {}", err)
-                    }
-                };
-            }
+        drop(self.auto_cert.client().await?);
 
-            if let Some(cache_key) = &self.auto_cert.cache_key {
-                match rustls_pemfile::pkcs8_private_keys(&mut cache_key.as_slice()) {
-                    Ok(k) => key = k.into_iter().next(),
-                    Err(err) => {
-                        tracing::warn!("failed to parse cached private key: {}", err)
-                    }
-                };
+        for group in &self.auto_cert.groups {
+            if group.cert.read().is_none() {
+                if let Some(cert_key) = load_cached_cert(&self.auto_cert, group).await? {
+                    *group.cert.write() = Some(Arc::new(cert_key));
+                }
             }
-
-            (certs, key)
-        };
-
-        let cert_resolver = Arc::new(ResolveServerCert::default());
-
-        if let (Some(certs), Some(key)) = (cache_certs, cert_key) {
-            let certs = certs
-                .into_iter()
-                .map(tokio_rustls::rustls::Certificate)
-                .collect::<Vec<_>>();
-
-            let expires_at = match certs
-                .first()
-                .and_then(|cert| X509Certificate::from_der(cert.as_ref()).ok())
-                .map(|(_, cert)| cert.validity().not_after.timestamp())
-                .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp as u64))
-            {
-                Some(expires_at) => chrono::DateTime::<chrono::Utc>::from(expires_at).to_string(),
-                None => "unknown".to_string(),
-            };
-
-            tracing::debug!(
-                expires_at = expires_at.as_str(),
-                "using cached tls certificates"
-            );
-            *cert_resolver.cert.write() = Some(Arc::new(CertifiedKey::new(
-                certs,
-                any_ecdsa_type(&PrivateKey(key)).unwrap(),
-            )));
         }
 
+        let cert_resolver = Arc::new(ResolveServerCert::new(
+            self.auto_cert.groups.clone(),
+            self.auto_cert.keys_for_tls_alpn01.clone(),
+        ));
+
         let weak_cert_resolver = Arc::downgrade(&cert_resolver);
         let mut server_config = ServerConfig::builder()
             .with_safe_defaults()
@@ -126,10 +79,17 @@ impl<T: Listener> Listener for AutoCertListener<T> {
         let auto_cert = self.auto_cert;
 
         tokio::spawn(async move {
-            while let Some(cert_resolver) = Weak::upgrade(&weak_cert_resolver) {
-                if cert_resolver.is_expired() {
-                    if let Err(err) = issue_cert(&mut client, &auto_cert, &cert_resolver).await {
-                        tracing::error!(error = %err, "failed to issue certificate");
+            let mut states = auto_cert
+                .groups
+                .iter()
+                .map(|_| RenewalState::default())
+                .collect::<Vec<_>>();
+            while Weak::upgrade(&weak_cert_resolver).is_some() {
+                for (group, state) in auto_cert.groups.iter().zip(&mut states) {
+                    check_renewal(&auto_cert, group, state, || issue_cert(&auto_cert, group)).await;
+                    #[cfg(feature = "ocsp")]
+                    if auto_cert.ocsp_stapling {
+                        refresh_ocsp(group, state).await;
                     }
                 }
                 tokio::time::sleep(Duration::from_secs(60 * 5)).await;
@@ -159,190 +119,306 @@ impl<T: Acceptor> Acceptor for AutoCertAcceptor<T> {
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
         let (stream, local_addr, remote_addr, _) = self.inner.accept().await?;
-        let stream = HandshakeStream::new(self.acceptor.accept(stream));
+        let tls_info = TlsInfoSlot::default();
+        let stream = HandshakeStream::new(self.acceptor.accept(stream).map_ok({
+            let tls_info = tls_info.clone();
+            move |stream| {
+                tls_info.set_info(rustls::connection_info(stream.get_ref().1));
+                stream
+            }
+        }))
+        .with_tls_info(tls_info);
         return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        io.tls_info()
+    }
 }
 
-fn gen_acme_cert(domain: &str, acme_hash: &[u8]) -> IoResult<CertifiedKey> {
-    let mut params = CertificateParams::new(vec![domain.to_string()]);
-    params.alg = &PKCS_ECDSA_P256_SHA256;
-    params.custom_extensions = vec![CustomExtension::new_acme_identifier(acme_hash)];
-    let cert = Certificate::from_params(params)
-        .map_err(|_| IoError::new(ErrorKind::Other, "failed to generate acme certificate"))?;
-    let key = any_ecdsa_type(&PrivateKey(cert.serialize_private_key_der())).unwrap();
-    Ok(CertifiedKey::new(
-        vec![tokio_rustls::rustls::Certificate(
-            cert.serialize_der().map_err(|_| {
-                IoError::new(ErrorKind::Other, "failed to serialize acme certificate")
-            })?,
-        )],
+async fn load_cached_cert(
+    auto_cert: &AutoCert,
+    group: &CertGroup,
+) -> IoResult<Option<CertifiedKey>> {
+    let cache = match &auto_cert.cache {
+        Some(cache) => cache,
+        None => return Ok(None),
+    };
+    let (cache_cert, cache_key) = (
+        cache.load(&group.cache_key(cache::CERT)).await?,
+        cache.load(&group.cache_key(cache::CERT_KEY)).await?,
+    );
+
+    let certs = match cache_cert.map(|cache_cert| rustls_pemfile::certs(&mut cache_cert.as_slice()))
+    {
+        Some(Ok(certs)) => certs,
+        Some(Err(err)) => {
+            tracing::warn!("failed to parse cached tls certificates: {}", err);
+            return Ok(None);
+        }
+        None => return Ok(None),
+    };
+    let key = match cache_key
+        .map(|cache_key| rustls_pemfile::pkcs8_private_keys(&mut cache_key.as_slice()))
+    {
+        Some(Ok(keys)) => match keys.into_iter().next() {
+            Some(key) => key,
+            None => return Ok(None),
+        },
+        Some(Err(err)) => {
+            tracing::warn!("failed to parse cached private key: {}", err);
+            return Ok(None);
+        }
+        None => return Ok(None),
+    };
+
+    let key = match any_supported_type(&PrivateKey(key)) {
+        Ok(key) => key,
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to load cached private key");
+            return Ok(None);
+        }
+    };
+    let cert_key = CertifiedKey::new(
+        certs
+            .into_iter()
+            .map(tokio_rustls::rustls::Certificate)
+            .collect(),
         key,
-    ))
+    );
+
+    let expires_at = match cert_expires_at(&cert_key) {
+        Some(expires_at) => chrono::DateTime::<chrono::Utc>::from(expires_at).to_string(),
+        None => "unknown".to_string(),
+    };
+    tracing::debug!(
+        domains = ?group.domains,
+        expires_at = expires_at.as_str(),
+        "using cached tls certificates"
+    );
+    Ok(Some(cert_key))
+}
+
+#[derive(Default)]
+struct RenewalState {
+    schedule: Option<RenewalSchedule>,
+    notified_expires_at: Option<SystemTime>,
+    #[cfg(feature = "ocsp")]
+    ocsp_refresh_at: Option<SystemTime>,
 }
 
-async fn issue_cert(
-    client: &mut AcmeClient,
+/// Renew the certificate of the group with `issue` if needed, and emit the
+/// events.
+async fn check_renewal<F, Fut>(
     auto_cert: &AutoCert,
-    resolver: &ResolveServerCert,
-) -> IoResult<()> {
-    tracing::debug!("issue certificate");
+    group: &CertGroup,
+    state: &mut RenewalState,
+    issue: F,
+) where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = IoResult<()>>,
+{
+    let expires_at = group.expires_at();
+    if let Some(expires_at) = expires_at {
+        if group.is_expired() && state.notified_expires_at != Some(expires_at) {
+            state.notified_expires_at = Some(expires_at);
+            auto_cert.emit(CertEvent::AboutToExpire {
+                domains: group.domains.clone(),
+                expires_at,
+            });
+        }
+    }
+
+    if !renewal::should_renew(auto_cert, group, &mut state.schedule).await {
+        return;
+    }
 
-    let order_resp = client.new_order(&auto_cert.domains).await?;
+    match issue().await {
+        Ok(()) => {
+            #[cfg(feature = "ocsp")]
+            {
+                state.ocsp_refresh_at = None;
+            }
+            if let Some(new_expires_at) = group.expires_at() {
+                let domains = group.domains.clone();
+                auto_cert.emit(match expires_at {
+                    Some(_) => CertEvent::Renewed {
+                        domains,
+                        expires_at: new_expires_at,
+                    },
+                    None => CertEvent::Issued {
+                        domains,
+                        expires_at: new_expires_at,
+                    },
+                });
+            }
+        }
+        Err(err) => {
+            tracing::error!(domains = ?group.domains, error = %err, "failed to issue certificate");
+            auto_cert.emit(CertEvent::RenewalFailed {
+                domains: group.domains.clone(),
+                error: err,
+            });
+        }
+    }
+}
 
-    // trigger challenge
-    let mut valid = false;
+/// Fetch the OCSP response of the certificate of the group if the current
+/// one is about to expire, and staple it to the certificate.
+#[cfg(feature = "ocsp")]
+async fn refresh_ocsp(group: &CertGroup, state: &mut RenewalState) {
+    use crate::listener::ocsp;
 
-    for i in 1..5 {
-        let mut all_valid = true;
+    let now = SystemTime::now();
+    if matches!(state.ocsp_refresh_at, Some(refresh_at) if refresh_at > now) {
+        return;
+    }
+    let cert_key = match group.cert.read().clone() {
+        Some(cert_key) => cert_key,
+        None => return,
+    };
+
+    state.ocsp_refresh_at = match ocsp::fetch(&cert_key.cert).await {
+        Ok(Some(staple)) => {
+            tracing::debug!(domains = ?group.domains, "ocsp response stapled");
+            *group.cert.write() = Some(ocsp::staple(&cert_key, staple.response));
+            Some(staple.refresh_at)
+        }
+        // no OCSP responder, check again after the certificate is renewed
+        Ok(None) => group.expires_at(),
+        Err(err) => {
+            tracing::warn!(domains = ?group.domains, error = %err, "failed to fetch ocsp response");
+            Some(now + ocsp::RETRY_INTERVAL)
+        }
+    };
+}
 
-        for auth_url in &order_resp.authorizations {
-            let resp = client.fetch_authorization(auth_url).await?;
+async fn issue_cert(auto_cert: &AutoCert, group: &CertGroup) -> IoResult<()> {
+    let issued = issue::issue(
+        auto_cert,
+        &group.domains,
+        Some(&group.cache_key(cache::ORDER)),
+    )
+    .await?;
+    let cert_key = issued.certified_key()?;
 
-            if resp.status == "valid" {
-                continue;
-            }
+    *group.cert.write() = Some(Arc::new(cert_key));
 
-            all_valid = false;
+    tracing::debug!(domains = ?group.domains, "certificate obtained");
 
-            if resp.status == "pending" {
-                let challenge = resp.find_challenge(auto_cert.challenge_type)?;
+    if let Some(cache) = &auto_cert.cache {
+        cache
+            .store(
+                &group.cache_key(cache::CERT_KEY),
+                issued.private_key_pem.as_bytes(),
+            )
+            .await?;
+        cache
+            .store(
+                &group.cache_key(cache::CERT),
+                issued.certificate_pem.as_bytes(),
+            )
+            .await?;
+        cache.remove(&group.cache_key(cache::ORDER)).await?;
+    }
 
-                match auto_cert.challenge_type {
-                    ChallengeType::Http01 => {
-                        if let Some(keys) = &auto_cert.keys_for_http01 {
-                            let mut keys = keys.write();
-                            let key_authorization =
-                                jose::key_authorization(&auto_cert.key_pair, &challenge.token)?;
-                            keys.insert(challenge.token.to_string(), key_authorization);
-                        }
-                    }
-                    ChallengeType::TlsAlpn01 => {
-                        let key_authorization_sha256 =
-                            jose::key_authorization_sha256(&auto_cert.key_pair, &challenge.token)?;
-                        let auth_key = gen_acme_cert(
-                            &resp.identifier.value,
-                            key_authorization_sha256.as_ref(),
-                        )?;
-
-                        resolver
-                            .acme_keys
-                            .write()
-                            .insert(resp.identifier.value.to_string(), Arc::new(auth_key));
-                    }
-                }
+    Ok(())
+}
 
-                client
-                    .trigger_challenge(
-                        &resp.identifier.value,
-                        auto_cert.challenge_type,
-                        &challenge.url,
-                    )
-                    .await?;
-            } else if resp.status == "invalid" {
-                return Err(IoError::new(
-                    ErrorKind::Other,
-                    format!(
-                        "unable to authorize `{}`: {}",
-                        resp.identifier.value,
-                        resp.error
-                            .as_ref()
-                            .map(|problem| &*problem.detail)
-                            .unwrap_or("unknown")
-                    ),
-                ));
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use std::io::{Error as IoError, ErrorKind};
 
-        if all_valid {
-            valid = true;
-            break;
-        }
+    use parking_lot::Mutex;
+    use tokio_rustls::rustls::Certificate;
 
-        tokio::time::sleep(Duration::from_secs(i * 10)).await;
-    }
+    use super::*;
 
-    if !valid {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            "authorization failed too many times",
-        ));
+    fn cert_key(expires_year: i32) -> Arc<CertifiedKey> {
+        let mut params = rcgen::CertificateParams::new(vec!["example.com".to_string()]);
+        params.not_after = rcgen::date_time_ymd(expires_year, 1, 1);
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        Arc::new(CertifiedKey::new(
+            vec![Certificate(cert.serialize_der().unwrap())],
+            any_supported_type(&PrivateKey(cert.serialize_private_key_der())).unwrap(),
+        ))
     }
 
-    // send csr
-    let mut params = CertificateParams::new(auto_cert.domains.clone());
-    params.distinguished_name = DistinguishedName::new();
-    params.alg = &PKCS_ECDSA_P256_SHA256;
-    let cert = Certificate::from_params(params).map_err(|err| {
-        IoError::new(
-            ErrorKind::Other,
-            format!("failed create certificate request: {}", err),
-        )
-    })?;
-    let pk = any_ecdsa_type(&PrivateKey(cert.serialize_private_key_der())).unwrap();
-    let csr = cert.serialize_request_der().map_err(|err| {
-        IoError::new(
-            ErrorKind::Other,
-            format!("failed to serialize request der {}", err),
-        )
-    })?;
-
-    let order_resp = client.send_csr(&order_resp.finalize, &csr).await?;
-
-    if order_resp.status == "invalid" {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!(
-                "failed to request certificate: {}",
-                order_resp
-                    .error
-                    .as_ref()
-                    .map(|problem| &*problem.detail)
-                    .unwrap_or("unknown")
-            ),
-        ));
+    async fn issue_ok(group: &CertGroup) -> IoResult<()> {
+        *group.cert.write() = Some(cert_key(2100));
+        Ok(())
     }
 
-    if order_resp.status != "valid" {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!(
-                "failed to request certificate: unexpected status `{}`",
-                order_resp.status
-            ),
-        ));
+    async fn issue_failed() -> IoResult<()> {
+        Err(IoError::new(ErrorKind::Other, "issue failed"))
     }
 
-    // download certificate
-    let acme_cert_pem = client
-        .obtain_certificate(order_resp.certificate.as_ref().ok_or_else(|| {
-            IoError::new(
-                ErrorKind::Other,
-                "invalid response: missing `certificate` url",
-            )
-        })?)
-        .await?;
-    let pkey_pem = cert.serialize_private_key_pem();
-    let cert_chain = rustls_pemfile::certs(&mut acme_cert_pem.as_slice())
-        .map_err(|err| IoError::new(ErrorKind::Other, format!("invalid pem: {}", err)))?
-        .into_iter()
-        .map(tokio_rustls::rustls::Certificate)
-        .collect();
-    let cert_key = CertifiedKey::new(cert_chain, pk);
-
-    *resolver.cert.write() = Some(Arc::new(cert_key));
-
-    tracing::debug!("certificate obtained");
-
-    if let Some(cache_path) = &auto_cert.cache_path {
-        let pkey_path = cache_path.join("key.pem");
-        tracing::debug!(path = %pkey_path.display(), "write private key to cache path");
-        std::fs::write(pkey_path, pkey_pem)?;
-
-        let cert_path = cache_path.join("cert.pem");
-        tracing::debug!(path = %cert_path.display(), "write certificate to cache path");
-        std::fs::write(cert_path, acme_cert_pem)?;
+    #[tokio::test]
+    async fn renewal_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let auto_cert = AutoCert::builder()
+            .domain("example.com")
+            .on_event({
+                let events = events.clone();
+                move |event| {
+                    events.lock().push(match event {
+                        CertEvent::Issued { expires_at, .. } => format!("issued {:?}", expires_at),
+                        CertEvent::Renewed { expires_at, .. } => {
+                            format!("renewed {:?}", expires_at)
+                        }
+                        CertEvent::RenewalFailed { error, .. } => format!("failed {}", error),
+                        CertEvent::AboutToExpire { expires_at, .. } => {
+                            format!("about to expire {:?}", expires_at)
+                        }
+                    })
+                }
+            })
+            .build()
+            .unwrap();
+        let group = &auto_cert.groups[0];
+        let mut state = RenewalState::default();
+        let take_events = || std::mem::take(&mut *events.lock());
+        let expires_at = |cert_key: &CertifiedKey| cert_expires_at(cert_key).unwrap();
+        let (expired, expired2, renewed) = (cert_key(2000), cert_key(2001), cert_key(2100));
+
+        check_renewal(&auto_cert, group, &mut state, || issue_ok(group)).await;
+        assert_eq!(
+            take_events(),
+            vec![format!("issued {:?}", expires_at(&renewed))]
+        );
+
+        // the certificate is not about to expire
+        check_renewal(&auto_cert, group, &mut state, issue_failed).await;
+        assert!(take_events().is_empty());
+
+        *group.cert.write() = Some(expired.clone());
+        check_renewal(&auto_cert, group, &mut state, issue_failed).await;
+        assert_eq!(
+            take_events(),
+            vec![
+                format!("about to expire {:?}", expires_at(&expired)),
+                "failed issue failed".to_string()
+            ]
+        );
+
+        // `AboutToExpire` is only emitted once for the same certificate
+        check_renewal(&auto_cert, group, &mut state, issue_failed).await;
+        assert_eq!(take_events(), vec!["failed issue failed".to_string()]);
+
+        *group.cert.write() = Some(expired2.clone());
+        check_renewal(&auto_cert, group, &mut state, issue_failed).await;
+        assert_eq!(
+            take_events(),
+            vec![
+                format!("about to expire {:?}", expires_at(&expired2)),
+                "failed issue failed".to_string()
+            ]
+        );
+
+        check_renewal(&auto_cert, group, &mut state, || issue_ok(group)).await;
+        assert_eq!(
+            take_events(),
+            vec![format!("renewed {:?}", expires_at(&renewed))]
+        );
     }
-
-    Ok(())
 }
@@ -5,19 +5,41 @@
 
 mod auto_cert;
 mod builder;
+mod cache;
 mod client;
+mod dns;
 mod endpoint;
+mod error;
+mod event;
+mod issue;
 mod jose;
 mod keypair;
 mod listener;
 mod protocol;
+mod proxy;
+mod renewal;
 mod resolver;
 mod serde;
+mod solver;
 
 pub use auto_cert::AutoCert;
 pub use builder::AutoCertBuilder;
+pub use cache::{CertificateCache, FileCache};
+#[cfg(feature = "acme-cloudflare")]
+#[cfg_attr(docsrs, doc(cfg(feature = "acme-cloudflare")))]
+pub use dns::CloudflareProvider;
+pub use dns::DnsProvider;
+#[cfg(feature = "acme-route53")]
+#[cfg_attr(docsrs, doc(cfg(feature = "acme-route53")))]
+pub use dns::Route53Provider;
+pub use endpoint::{http01_endpoint, Http01Endpoint, Http01TokenStore};
+pub use error::{AcmeError, Problem};
+pub use event::CertEvent;
+pub use issue::{issue_certificate, IssuedCertificate};
+pub use keypair::KeyType;
 pub use listener::{AutoCertAcceptor, AutoCertListener};
-pub use protocol::ChallengeType;
+pub use protocol::{ChallengeType, RevocationReason};
+pub use solver::ChallengeSolver;
 
 /// Let's Encrypt production directory url
 pub const LETS_ENCRYPT_PRODUCTION: &str = "https://acme-v02.api.letsencrypt.org/directory";
@@ -5,7 +5,7 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::listener::acme::serde::SerdeUri;
+use crate::listener::acme::{error::Problem, jose::Jws, serde::SerdeUri};
 
 /// HTTP-01 challenge
 const CHALLENGE_TYPE_HTTP_01: &str = "http-01";
@@ -13,6 +13,9 @@ const CHALLENGE_TYPE_HTTP_01: &str = "http-01";
 /// TLS-ALPN-01 challenge
 const CHALLENGE_TYPE_TLS_ALPN_01: &str = "tls-alpn-01";
 
+/// DNS-01 challenge
+const CHALLENGE_TYPE_DNS_01: &str = "dns-01";
+
 /// Challenge type
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ChallengeType {
@@ -24,6 +27,10 @@ pub enum ChallengeType {
     ///
     /// Reference: <https://letsencrypt.org/docs/challenge-types/#tls-alpn-01>
     TlsAlpn01,
+    /// DNS-01
+    ///
+    /// Reference: <https://letsencrypt.org/docs/challenge-types/#dns-01-challenge>
+    Dns01,
 }
 
 impl Display for ChallengeType {
@@ -31,16 +38,47 @@ impl Display for ChallengeType {
         match self {
             ChallengeType::Http01 => f.write_str(CHALLENGE_TYPE_HTTP_01),
             ChallengeType::TlsAlpn01 => f.write_str(CHALLENGE_TYPE_TLS_ALPN_01),
+            ChallengeType::Dns01 => f.write_str(CHALLENGE_TYPE_DNS_01),
         }
     }
 }
 
+/// The reason for revoking a certificate.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc5280#section-5.3.1>
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RevocationReason {
+    /// The reason is not specified.
+    Unspecified = 0,
+    /// The private key of the certificate has been compromised.
+    KeyCompromise = 1,
+    /// The domain names of the certificate have changed.
+    AffiliationChanged = 3,
+    /// The certificate has been replaced by a new one.
+    Superseded = 4,
+    /// The certificate is no longer needed.
+    CessationOfOperation = 5,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Directory {
     pub(crate) new_nonce: SerdeUri,
     pub(crate) new_account: SerdeUri,
     pub(crate) new_order: SerdeUri,
+    pub(crate) revoke_cert: SerdeUri,
+    pub(crate) key_change: SerdeUri,
+    pub(crate) renewal_info: Option<SerdeUri>,
+    #[serde(default)]
+    pub(crate) meta: DirectoryMeta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DirectoryMeta {
+    #[serde(default)]
+    pub(crate) external_account_required: bool,
+    pub(crate) terms_of_service: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -48,7 +86,9 @@ pub(crate) struct Directory {
 pub(crate) struct NewAccountRequest {
     pub(crate) only_return_existing: bool,
     pub(crate) terms_of_service_agreed: bool,
-    pub(crate) contacts: Vec<String>,
+    pub(crate) contact: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) external_account_binding: Option<Jws>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,16 +105,28 @@ pub(crate) struct NewOrderRequest {
     pub(crate) identifiers: Vec<Identifier>,
 }
 
-#[derive(Debug, Deserialize)]
+/// The status of an order.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.1.6>
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct Problem {
-    pub(crate) detail: String,
+pub(crate) enum OrderStatus {
+    /// The authorizations of the order have not been completed.
+    Pending,
+    /// All the authorizations are valid, the CSR can be submitted.
+    Ready,
+    /// The certificate is being issued.
+    Processing,
+    /// The certificate has been issued.
+    Valid,
+    /// The order failed, this is a terminal state.
+    Invalid,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct NewOrderResponse {
-    pub(crate) status: String,
+    pub(crate) status: OrderStatus,
     pub(crate) authorizations: Vec<SerdeUri>,
     pub(crate) error: Option<Problem>,
     pub(crate) finalize: SerdeUri,
@@ -94,11 +146,23 @@ pub(crate) struct Challenge {
 pub(crate) struct FetchAuthorizationResponse {
     pub(crate) identifier: Identifier,
     pub(crate) status: String,
+    #[serde(default)]
+    pub(crate) wildcard: bool,
     pub(crate) challenges: Vec<Challenge>,
     pub(crate) error: Option<Problem>,
 }
 
 impl FetchAuthorizationResponse {
+    /// Returns the domain name of this authorization, including the `*.`
+    /// prefix for wildcard authorizations.
+    pub(crate) fn domain(&self) -> String {
+        if self.wildcard {
+            format!("*.{}", self.identifier.value)
+        } else {
+            self.identifier.value.clone()
+        }
+    }
+
     pub(crate) fn find_challenge(&self, ty: ChallengeType) -> IoResult<&Challenge> {
         self.challenges
             .iter()
@@ -117,3 +181,58 @@ impl FetchAuthorizationResponse {
 pub(crate) struct CsrRequest {
     pub(crate) csr: String,
 }
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RevokeCertRequest {
+    pub(crate) certificate: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) reason: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RenewalInfoResponse {
+    pub(crate) suggested_window: SuggestedWindow,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SuggestedWindow {
+    pub(crate) start: String,
+    pub(crate) end: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_order() {
+        let order: NewOrderResponse = serde_json::from_str(
+            r#"{
+                "status": "invalid",
+                "authorizations": ["https://example.com/acme/authz/1"],
+                "finalize": "https://example.com/acme/order/1/finalize",
+                "error": {
+                    "type": "urn:ietf:params:acme:error:unauthorized",
+                    "detail": "no valid authorizations"
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(order.status, OrderStatus::Invalid);
+        assert_eq!(order.error.unwrap().detail, "no valid authorizations");
+        assert!(order.certificate.is_none());
+
+        for (value, status) in [
+            ("pending", OrderStatus::Pending),
+            ("ready", OrderStatus::Ready),
+            ("processing", OrderStatus::Processing),
+            ("valid", OrderStatus::Valid),
+        ] {
+            assert_eq!(
+                serde_json::from_value::<OrderStatus>(serde_json::json!(value)).unwrap(),
+                status
+            );
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{Duration, SystemTime};
+
+use base64::URL_SAFE_NO_PAD;
+use rand::Rng;
+use x509_parser::{
+    extensions::ParsedExtension,
+    prelude::{FromDer, X509Certificate},
+};
+
+use crate::listener::acme::{protocol::SuggestedWindow, resolver::CertGroup, AcmeError, AutoCert};
+
+/// How long to wait before fetching the renewal information again if the
+/// server does not specify the `Retry-After` header.
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The renewal time of a certificate selected from the window suggested by
+/// the ACME server.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari>
+pub(crate) struct RenewalSchedule {
+    cert_id: String,
+    window: (SystemTime, SystemTime),
+    renew_at: SystemTime,
+    next_update: SystemTime,
+}
+
+/// Returns `true` if the current certificate should be renewed.
+///
+/// If the ACME server supports ACME Renewal Information (ARI), the
+/// certificate is renewed at a random time inside the suggested window, so
+/// that the renewals of a fleet of servers are spread out. Otherwise, it is
+/// renewed when it is about to expire.
+pub(crate) async fn should_renew(
+    auto_cert: &AutoCert,
+    group: &CertGroup,
+    schedule: &mut Option<RenewalSchedule>,
+) -> bool {
+    if group.is_expired() {
+        return true;
+    }
+
+    let cert_id = match group
+        .cert
+        .read()
+        .as_ref()
+        .and_then(|cert| cert.cert.first())
+        .and_then(|cert| cert_id(cert.as_ref()))
+    {
+        Some(cert_id) => cert_id,
+        None => return false,
+    };
+
+    let now = SystemTime::now();
+    let outdated = match schedule {
+        Some(schedule) => schedule.cert_id != cert_id || schedule.next_update <= now,
+        None => true,
+    };
+
+    if outdated {
+        match fetch_schedule(auto_cert, cert_id, schedule.take()).await {
+            Ok(new_schedule) => *schedule = new_schedule,
+            Err(err) => tracing::warn!(error = %err, "failed to fetch renewal info"),
+        }
+    }
+
+    matches!(schedule, Some(schedule) if schedule.renew_at <= now)
+}
+
+async fn fetch_schedule(
+    auto_cert: &AutoCert,
+    cert_id: String,
+    prev: Option<RenewalSchedule>,
+) -> Result<Option<RenewalSchedule>, AcmeError> {
+    let (resp, retry_after) = match auto_cert.client().await?.renewal_info(&cert_id).await? {
+        Some(renewal_info) => renewal_info,
+        None => return Ok(None),
+    };
+    let window = parse_window(&resp.suggested_window).ok_or_else(|| {
+        AcmeError::InvalidResponse(format!(
+            "invalid suggested window: {:?}",
+            resp.suggested_window
+        ))
+    })?;
+
+    // keep the selected time if the suggested window has not changed
+    let renew_at = match prev {
+        Some(prev) if prev.cert_id == cert_id && prev.window == window => prev.renew_at,
+        _ => random_time(window),
+    };
+
+    tracing::debug!(
+        cert_id = cert_id.as_str(),
+        renew_at = %chrono::DateTime::<chrono::Utc>::from(renew_at),
+        "certificate renewal scheduled"
+    );
+
+    Ok(Some(RenewalSchedule {
+        cert_id,
+        window,
+        renew_at,
+        next_update: SystemTime::now() + retry_after.unwrap_or(DEFAULT_UPDATE_INTERVAL),
+    }))
+}
+
+/// Returns the ARI certificate identifier, which is composed of the key
+/// identifier of the issuer and the serial number.
+fn cert_id(cert: &[u8]) -> Option<String> {
+    let (_, cert) = X509Certificate::from_der(cert).ok()?;
+    let key_identifier = cert
+        .extensions()
+        .iter()
+        .find_map(|ext| match ext.parsed_extension() {
+            ParsedExtension::AuthorityKeyIdentifier(aki) => aki.key_identifier.as_ref(),
+            _ => None,
+        })?;
+    Some(format_cert_id(
+        key_identifier.0,
+        cert.tbs_certificate.raw_serial(),
+    ))
+}
+
+fn format_cert_id(key_identifier: &[u8], serial: &[u8]) -> String {
+    format!(
+        "{}.{}",
+        base64::encode_config(key_identifier, URL_SAFE_NO_PAD),
+        base64::encode_config(serial, URL_SAFE_NO_PAD)
+    )
+}
+
+fn parse_window(window: &SuggestedWindow) -> Option<(SystemTime, SystemTime)> {
+    let start = chrono::DateTime::parse_from_rfc3339(&window.start).ok()?;
+    let end = chrono::DateTime::parse_from_rfc3339(&window.end).ok()?;
+    if end < start {
+        return None;
+    }
+    Some((start.into(), end.into()))
+}
+
+/// Select a uniform random time inside the window.
+fn random_time((start, end): (SystemTime, SystemTime)) -> SystemTime {
+    let len = end.duration_since(start).unwrap_or_default();
+    start + len.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_cert_id() {
+        assert_eq!(
+            format_cert_id(
+                &[
+                    0x69, 0x88, 0x5b, 0x6b, 0x87, 0x46, 0x40, 0x41, 0xe1, 0xb3, 0x7b, 0x84, 0x7b,
+                    0xa0, 0xae, 0x2c, 0xde, 0x01, 0xc8, 0xd4
+                ],
+                &[0x00, 0x87, 0x65, 0x43, 0x21]
+            ),
+            "aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE"
+        );
+    }
+
+    #[test]
+    fn test_random_time() {
+        let window = parse_window(&SuggestedWindow {
+            start: "2021-01-03T00:00:00Z".to_string(),
+            end: "2021-01-07T00:00:00Z".to_string(),
+        })
+        .unwrap();
+        for _ in 0..100 {
+            let time = random_time(window);
+            assert!(time >= window.0 && time <= window.1);
+        }
+
+        assert!(parse_window(&SuggestedWindow {
+            start: "2021-01-07T00:00:00Z".to_string(),
+            end: "2021-01-03T00:00:00Z".to_string(),
+        })
+        .is_none());
+    }
+}
@@ -1,7 +1,6 @@
 use std::{
-    collections::HashMap,
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use parking_lot::RwLock;
@@ -11,33 +10,94 @@ use tokio_rustls::rustls::{
 };
 use x509_parser::prelude::{FromDer, X509Certificate};
 
+use crate::listener::acme::solver::TlsAlpn01Keys;
+
 pub(crate) const ACME_TLS_ALPN_NAME: &[u8] = b"acme-tls/1";
 
-#[derive(Default)]
-pub(crate) struct ResolveServerCert {
-    pub(crate) cert: RwLock<Option<Arc<CertifiedKey>>>,
-    pub(crate) acme_keys: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+pub(crate) type CurrentCert = Arc<RwLock<Option<Arc<CertifiedKey>>>>;
+
+/// Returns `true` if the server name matches the domain pattern.
+///
+/// A wildcard pattern such as `*.example.com` matches exactly one label, so it
+/// matches `www.example.com` but not `example.com` or `a.b.example.com`.
+pub(crate) fn domain_matches(pattern: &str, server_name: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => match server_name.split_once('.') {
+            Some((label, rest)) => !label.is_empty() && rest.eq_ignore_ascii_case(suffix),
+            None => false,
+        },
+        None => pattern.eq_ignore_ascii_case(server_name),
+    }
 }
 
-impl ResolveServerCert {
+/// Returns the expiration time of the certificate.
+pub(crate) fn cert_expires_at(cert: &CertifiedKey) -> Option<SystemTime> {
+    cert.cert
+        .first()
+        .and_then(|cert| X509Certificate::from_der(cert.as_ref()).ok())
+        .map(|(_, cert)| cert.validity().not_after.timestamp())
+        .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp as u64))
+}
+
+/// A group of domains that share the same certificate.
+#[derive(Clone)]
+pub(crate) struct CertGroup {
+    pub(crate) domains: Vec<String>,
+    pub(crate) cert: CurrentCert,
+    cache_prefix: String,
+}
+
+impl CertGroup {
+    /// Create a certificate group, the `index` is the position of the group
+    /// in the [`AutoCert`](crate::listener::acme::AutoCert).
+    pub(crate) fn new(index: usize, domains: Vec<String>) -> Self {
+        // the first group uses the cache keys without prefix, so that the
+        // existing caches can still be loaded
+        let cache_prefix = match index {
+            0 => String::new(),
+            _ => format!("{}_", domains[0].replace('*', "_")),
+        };
+        Self {
+            domains,
+            cert: Default::default(),
+            cache_prefix,
+        }
+    }
+
+    /// Returns the key of the cache entry for this group.
+    pub(crate) fn cache_key(&self, key: &str) -> String {
+        format!("{}{}", self.cache_prefix, key)
+    }
+
+    pub(crate) fn matches(&self, server_name: &str) -> bool {
+        self.domains
+            .iter()
+            .any(|domain| domain_matches(domain, server_name))
+    }
+
+    pub(crate) fn expires_at(&self) -> Option<SystemTime> {
+        self.cert.read().as_deref().and_then(cert_expires_at)
+    }
+
     pub(crate) fn is_expired(&self) -> bool {
-        let cert = self.cert.read();
-        match cert
-            .as_ref()
-            .and_then(|cert| cert.cert.first())
-            .and_then(|cert| X509Certificate::from_der(cert.as_ref()).ok())
-            .map(|(_, cert)| cert.validity().not_after.timestamp())
-        {
-            Some(valid_until) => {
-                let now = SystemTime::now();
-                let now = now.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-                now + 60 * 60 * 12 > valid_until
-            }
+        match self.expires_at() {
+            Some(expires_at) => SystemTime::now() + Duration::from_secs(60 * 60 * 12) > expires_at,
             None => true,
         }
     }
 }
 
+pub(crate) struct ResolveServerCert {
+    pub(crate) groups: Vec<CertGroup>,
+    pub(crate) acme_keys: TlsAlpn01Keys,
+}
+
+impl ResolveServerCert {
+    pub(crate) fn new(groups: Vec<CertGroup>, acme_keys: TlsAlpn01Keys) -> Self {
+        Self { groups, acme_keys }
+    }
+}
+
 impl ResolvesServerCert for ResolveServerCert {
     fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
         if client_hello
@@ -60,6 +120,56 @@ impl ResolvesServerCert for ResolveServerCert {
             };
         };
 
-        self.cert.read().as_ref().cloned()
+        let group = client_hello
+            .server_name()
+            .and_then(|server_name| {
+                let group = self.groups.iter().find(|group| group.matches(server_name));
+                if group.is_none() {
+                    tracing::debug!(
+                        server_name = server_name,
+                        "no certificate matches the server name, use the default certificate"
+                    );
+                }
+                group
+            })
+            .or_else(|| self.groups.first())?;
+        let cert = group.cert.read().clone();
+        cert
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_matches() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("example.com", "EXAMPLE.com"));
+        assert!(!domain_matches("example.com", "www.example.com"));
+
+        assert!(domain_matches("*.example.com", "www.example.com"));
+        assert!(domain_matches("*.example.com", "api.EXAMPLE.com"));
+        assert!(!domain_matches("*.example.com", "example.com"));
+        assert!(!domain_matches("*.example.com", "a.b.example.com"));
+        assert!(!domain_matches("*.example.com", ".example.com"));
+        assert!(!domain_matches("*.example.com", "www.example.org"));
+    }
+
+    #[test]
+    fn test_cert_group() {
+        let group = CertGroup::new(0, vec!["example.com".to_string()]);
+        assert_eq!(group.cache_key("cert.pem"), "cert.pem");
+        assert!(group.matches("example.com"));
+        assert!(!group.matches("www.example.com"));
+        assert!(group.is_expired());
+
+        let group = CertGroup::new(
+            1,
+            vec!["*.example.org".to_string(), "example.org".to_string()],
+        );
+        assert_eq!(group.cache_key("cert.pem"), "_.example.org_cert.pem");
+        assert!(group.matches("example.org"));
+        assert!(group.matches("www.example.org"));
     }
 }
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    sync::Arc,
+};
+
+use base64::URL_SAFE_NO_PAD;
+use parking_lot::RwLock;
+use rcgen::{Certificate, CertificateParams, CustomExtension, PKCS_ECDSA_P256_SHA256};
+use ring::digest::{digest, SHA256};
+use tokio_rustls::rustls::{
+    sign::{any_ecdsa_type, CertifiedKey},
+    PrivateKey,
+};
+
+use crate::listener::acme::{
+    dns::challenge_record_name, ChallengeType, DnsProvider, Http01TokenStore,
+};
+
+pub(crate) type TlsAlpn01Keys = Arc<RwLock<HashMap<String, Arc<CertifiedKey>>>>;
+
+/// Represents a solver that fulfills an ACME challenge.
+///
+/// Implement this trait to handle the challenges in a custom way, for example
+/// writing the `HTTP-01` token files to a storage shared by several servers,
+/// and pass it to
+/// [`AutoCertBuilder::solver`](crate::listener::acme::AutoCertBuilder::solver).
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-8>
+#[async_trait::async_trait]
+pub trait ChallengeSolver: Send + Sync + 'static {
+    /// Returns the type of challenge this solver handles.
+    fn challenge_type(&self) -> ChallengeType;
+
+    /// Make the response of the challenge available to the ACME server.
+    ///
+    /// The `domain` is the identifier being validated (without the `*.`
+    /// prefix for wildcard domains), and the `key_authorization` is the
+    /// string `{token}.{account key thumbprint}` derived from the `token`.
+    async fn present(&self, domain: &str, token: &str, key_authorization: &str) -> IoResult<()>;
+
+    /// Remove the response created by [`ChallengeSolver::present`] after the
+    /// challenge has been completed.
+    ///
+    /// The default implementation does nothing.
+    async fn cleanup(&self, domain: &str, token: &str, key_authorization: &str) -> IoResult<()> {
+        let _ = (domain, token, key_authorization);
+        Ok(())
+    }
+}
+
+/// The built-in solver for the `HTTP-01` challenge, the responses are served
+/// by the endpoint created with
+/// [`AutoCert::http_01_endpoint`](crate::listener::acme::AutoCert::http_01_endpoint).
+pub(crate) struct Http01Solver {
+    pub(crate) keys: Http01TokenStore,
+}
+
+#[async_trait::async_trait]
+impl ChallengeSolver for Http01Solver {
+    fn challenge_type(&self) -> ChallengeType {
+        ChallengeType::Http01
+    }
+
+    async fn present(&self, _domain: &str, token: &str, key_authorization: &str) -> IoResult<()> {
+        self.keys.insert(token, key_authorization);
+        Ok(())
+    }
+
+    async fn cleanup(&self, _domain: &str, token: &str, _key_authorization: &str) -> IoResult<()> {
+        self.keys.remove(token);
+        Ok(())
+    }
+}
+
+/// The built-in solver for the `TLS-ALPN-01` challenge, the validation
+/// certificates are served by the certificate resolver of the listener.
+pub(crate) struct TlsAlpn01Solver {
+    pub(crate) keys: TlsAlpn01Keys,
+}
+
+#[async_trait::async_trait]
+impl ChallengeSolver for TlsAlpn01Solver {
+    fn challenge_type(&self) -> ChallengeType {
+        ChallengeType::TlsAlpn01
+    }
+
+    async fn present(&self, domain: &str, _token: &str, key_authorization: &str) -> IoResult<()> {
+        let acme_hash = digest(&SHA256, key_authorization.as_bytes());
+        let auth_key = gen_acme_cert(domain, acme_hash.as_ref())?;
+        self.keys
+            .write()
+            .insert(domain.to_string(), Arc::new(auth_key));
+        Ok(())
+    }
+
+    async fn cleanup(&self, domain: &str, _token: &str, _key_authorization: &str) -> IoResult<()> {
+        self.keys.write().remove(domain);
+        Ok(())
+    }
+}
+
+/// The built-in solver for the `DNS-01` challenge, the `TXT` records are
+/// published by a [`DnsProvider`].
+pub(crate) struct Dns01Solver {
+    pub(crate) provider: Arc<dyn DnsProvider>,
+}
+
+#[async_trait::async_trait]
+impl ChallengeSolver for Dns01Solver {
+    fn challenge_type(&self) -> ChallengeType {
+        ChallengeType::Dns01
+    }
+
+    async fn present(&self, domain: &str, _token: &str, key_authorization: &str) -> IoResult<()> {
+        let name = challenge_record_name(domain);
+        let value = dns_txt_value(key_authorization);
+        self.provider.set_txt_record(&name, &value).await?;
+        self.provider.wait_for_propagation(&name, &value).await
+    }
+
+    async fn cleanup(&self, domain: &str, _token: &str, key_authorization: &str) -> IoResult<()> {
+        self.provider
+            .remove_txt_record(
+                &challenge_record_name(domain),
+                &dns_txt_value(key_authorization),
+            )
+            .await
+    }
+}
+
+/// Returns the value of the `TXT` record for the `DNS-01` challenge.
+fn dns_txt_value(key_authorization: &str) -> String {
+    base64::encode_config(
+        digest(&SHA256, key_authorization.as_bytes()),
+        URL_SAFE_NO_PAD,
+    )
+}
+
+fn gen_acme_cert(domain: &str, acme_hash: &[u8]) -> IoResult<CertifiedKey> {
+    let mut params = CertificateParams::new(vec![domain.to_string()]);
+    params.alg = &PKCS_ECDSA_P256_SHA256;
+    params.custom_extensions = vec![CustomExtension::new_acme_identifier(acme_hash)];
+    let cert = Certificate::from_params(params)
+        .map_err(|_| IoError::new(ErrorKind::Other, "failed to generate acme certificate"))?;
+    let key = any_ecdsa_type(&PrivateKey(cert.serialize_private_key_der())).unwrap();
+    Ok(CertifiedKey::new(
+        vec![tokio_rustls::rustls::Certificate(
+            cert.serialize_der().map_err(|_| {
+                IoError::new(ErrorKind::Other, "failed to serialize acme certificate")
+            })?,
+        )],
+        key,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dns_txt_value() {
+        assert_eq!(
+            dns_txt_value("evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA.9jg46WB3rR_AHD-EBXdN7cBkH1WOu0tA3M9fm21mqTI"),
+            "lCM7cZyQXcVHK2nnW3jjAhNT3Fvm18UN-kWZZknKoYM"
+        );
+    }
+
+    #[tokio::test]
+    async fn http01_solver() {
+        let solver = Http01Solver {
+            keys: Default::default(),
+        };
+        solver
+            .present("example.com", "token", "token.thumb")
+            .await
+            .unwrap();
+        assert_eq!(solver.keys.get("token").as_deref(), Some("token.thumb"));
+        solver
+            .cleanup("example.com", "token", "token.thumb")
+            .await
+            .unwrap();
+        assert!(solver.keys.get("token").is_none());
+    }
+}
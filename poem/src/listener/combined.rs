@@ -7,7 +7,7 @@ use http::uri::Scheme;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
 
 use crate::{
-    listener::{Acceptor, Listener},
+    listener::{Acceptor, Listener, TlsInfoSlot},
     web::{LocalAddr, RemoteAddr},
 };
 
@@ -60,6 +60,13 @@ impl<A: Acceptor, B: Acceptor> Acceptor for Combined<A, B> {
             }
         }
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        match io {
+            CombinedStream::A(a) => self.a.tls_info(a),
+            CombinedStream::B(b) => self.b.tls_info(b),
+        }
+    }
 }
 
 /// A IO stream for CombinedAcceptor.
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+};
+
+use http::uri::Scheme;
+use parking_lot::Mutex;
+use tokio::{
+    io::Result as IoResult,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+use crate::{
+    listener::{Acceptor, AcceptorExt, BoxIo, Listener, TlsInfoSlot},
+    web::{LocalAddr, RemoteAddr},
+};
+
+type AcceptResult = IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme)>;
+
+/// The identifier of a listener added to the [`DynamicAcceptor`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ListenerId(u64);
+
+struct ListenerEntry {
+    local_addr: Vec<LocalAddr>,
+    task: JoinHandle<()>,
+}
+
+struct Inner {
+    tx: UnboundedSender<AcceptResult>,
+    next_id: AtomicU64,
+    listeners: Mutex<HashMap<ListenerId, ListenerEntry>>,
+}
+
+/// An acceptor that accepts the connections from the listeners which can be
+/// added or removed while the server is running.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::{
+///     handler,
+///     listener::{DynamicAcceptor, TcpListener},
+///     Server,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let (acceptor, handle) = DynamicAcceptor::new();
+/// let id = handle.add(TcpListener::bind("127.0.0.1:3000")).await?;
+/// tokio::spawn(Server::new_with_acceptor(acceptor).run(index));
+///
+/// // bind to a new port, and unbind the old one
+/// handle.add(TcpListener::bind("127.0.0.1:3001")).await?;
+/// handle.remove(id);
+/// # Ok::<_, std::io::Error>(())
+/// # });
+/// ```
+pub struct DynamicAcceptor {
+    inner: Arc<Inner>,
+    rx: UnboundedReceiver<AcceptResult>,
+}
+
+impl DynamicAcceptor {
+    /// Create a new `DynamicAcceptor` without any listener, and returns a
+    /// handle to add or remove the listeners.
+    pub fn new() -> (Self, DynamicAcceptorHandle) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let inner = Arc::new(Inner {
+            tx,
+            next_id: AtomicU64::new(0),
+            listeners: Default::default(),
+        });
+        let handle = DynamicAcceptorHandle {
+            inner: Arc::downgrade(&inner),
+        };
+        (Self { inner, rx }, handle)
+    }
+}
+
+impl Drop for DynamicAcceptor {
+    fn drop(&mut self) {
+        for (_, entry) in self.inner.listeners.lock().drain() {
+            entry.task.abort();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Acceptor for DynamicAcceptor {
+    type Io = BoxIo;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner
+            .listeners
+            .lock()
+            .values()
+            .flat_map(|entry| entry.local_addr.iter().cloned())
+            .collect()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        // the sender is owned by `self.inner`, so the channel is never closed
+        self.rx.recv().await.unwrap()
+    }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        io.tls_info.clone()
+    }
+}
+
+/// A handle to add or remove the listeners of a [`DynamicAcceptor`].
+#[derive(Clone)]
+pub struct DynamicAcceptorHandle {
+    inner: Weak<Inner>,
+}
+
+impl DynamicAcceptorHandle {
+    /// Binds the listener and starts accepting connections from it.
+    ///
+    /// Returns an error if the listener can not be bound, or the
+    /// [`DynamicAcceptor`] has been dropped.
+    pub async fn add<L>(&self, listener: L) -> IoResult<ListenerId>
+    where
+        L: Listener,
+        L::Acceptor: 'static,
+    {
+        let mut acceptor = listener.into_acceptor().await?.boxed();
+        let local_addr = acceptor.local_addr();
+        let inner = self.inner.upgrade().ok_or_else(|| {
+            IoError::new(ErrorKind::Other, "the dynamic acceptor has been dropped")
+        })?;
+
+        let tx = inner.tx.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let res = acceptor.accept().await;
+                if tx.send(res).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let id = ListenerId(inner.next_id.fetch_add(1, Ordering::Relaxed));
+        for addr in &local_addr {
+            tracing::info!(addr = %addr, "listening");
+        }
+        inner
+            .listeners
+            .lock()
+            .insert(id, ListenerEntry { local_addr, task });
+        Ok(id)
+    }
+
+    /// Stops accepting connections from the listener and unbinds it, the
+    /// connections that have been accepted are not affected.
+    ///
+    /// Returns `false` if the listener does not exist.
+    pub fn remove(&self, id: ListenerId) -> bool {
+        let entry = match self.inner.upgrade() {
+            Some(inner) => inner.listeners.lock().remove(&id),
+            None => None,
+        };
+        match entry {
+            Some(entry) => {
+                for addr in &entry.local_addr {
+                    tracing::info!(addr = %addr, "stop listening");
+                }
+                entry.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the local addresses of the listener.
+    pub fn local_addr(&self, id: ListenerId) -> Option<Vec<LocalAddr>> {
+        let inner = self.inner.upgrade()?;
+        let listeners = inner.listeners.lock();
+        listeners.get(&id).map(|entry| entry.local_addr.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::listener::TcpListener;
+
+    #[tokio::test]
+    async fn add_and_remove() {
+        let (mut acceptor, handle) = DynamicAcceptor::new();
+        assert!(acceptor.local_addr().is_empty());
+
+        let a = handle.add(TcpListener::bind("127.0.0.1:0")).await.unwrap();
+        let b = handle.add(TcpListener::bind("127.0.0.1:0")).await.unwrap();
+        assert_eq!(acceptor.local_addr().len(), 2);
+        let addr_a = *handle.local_addr(a).unwrap()[0].as_socket_addr().unwrap();
+        let addr_b = *handle.local_addr(b).unwrap()[0].as_socket_addr().unwrap();
+
+        for addr in [addr_a, addr_b] {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_i32(10).await.unwrap();
+            let (mut stream, local_addr, _, _) = acceptor.accept().await.unwrap();
+            assert_eq!(*local_addr.as_socket_addr().unwrap(), addr);
+            assert_eq!(stream.read_i32().await.unwrap(), 10);
+        }
+
+        assert!(handle.remove(a));
+        assert!(!handle.remove(a));
+        assert_eq!(acceptor.local_addr().len(), 1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(TcpStream::connect(addr_a).await.is_err());
+
+        drop(acceptor);
+        assert!(handle.add(TcpListener::bind("127.0.0.1:0")).await.is_err());
+    }
+}
@@ -3,11 +3,14 @@ use std::{
     io::{Error, ErrorKind},
     pin::Pin,
     task::{Context, Poll},
+    time::Instant,
 };
 
 use futures_util::{future::BoxFuture, FutureExt};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result};
 
+use crate::listener::TlsInfoSlot;
+
 enum State<S> {
     Handshaking(BoxFuture<'static, Result<S>>),
     Ready(S),
@@ -17,6 +20,8 @@ enum State<S> {
 /// A handshake stream for tls.
 pub struct HandshakeStream<S> {
     state: State<S>,
+    tls_info: Option<TlsInfoSlot>,
+    started_at: Instant,
 }
 
 impl<S> HandshakeStream<S> {
@@ -26,6 +31,27 @@ impl<S> HandshakeStream<S> {
     {
         Self {
             state: State::Handshaking(handshake.boxed()),
+            tls_info: None,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Sets the slot which the handshake future fills with the TLS
+    /// information.
+    pub(crate) fn with_tls_info(self, tls_info: TlsInfoSlot) -> Self {
+        Self {
+            tls_info: Some(tls_info),
+            ..self
+        }
+    }
+
+    pub(crate) fn tls_info(&self) -> Option<TlsInfoSlot> {
+        self.tls_info.clone()
+    }
+
+    fn handshake_completed(&self, res: std::result::Result<(), &Error>) {
+        if let Some(tls_info) = &self.tls_info {
+            tls_info.handshake_completed(self.started_at.elapsed(), res);
         }
     }
 }
@@ -44,9 +70,13 @@ where
         loop {
             match &mut this.state {
                 State::Handshaking(fut) => match fut.poll_unpin(cx) {
-                    Poll::Ready(Ok(s)) => this.state = State::Ready(s),
+                    Poll::Ready(Ok(s)) => {
+                        this.state = State::Ready(s);
+                        this.handshake_completed(Ok(()));
+                    }
                     Poll::Ready(Err(err)) => {
                         this.state = State::Error;
+                        this.handshake_completed(Err(&err));
                         return Poll::Ready(Err(err));
                     }
                     Poll::Pending => return Poll::Pending,
@@ -72,9 +102,13 @@ where
         loop {
             match &mut this.state {
                 State::Handshaking(fut) => match fut.poll_unpin(cx) {
-                    Poll::Ready(Ok(s)) => this.state = State::Ready(s),
+                    Poll::Ready(Ok(s)) => {
+                        this.state = State::Ready(s);
+                        this.handshake_completed(Ok(()));
+                    }
                     Poll::Ready(Err(err)) => {
                         this.state = State::Error;
+                        this.handshake_completed(Err(&err));
                         return Poll::Ready(Err(err));
                     }
                     Poll::Pending => return Poll::Pending,
@@ -94,9 +128,13 @@ where
         loop {
             match &mut this.state {
                 State::Handshaking(fut) => match fut.poll_unpin(cx) {
-                    Poll::Ready(Ok(s)) => this.state = State::Ready(s),
+                    Poll::Ready(Ok(s)) => {
+                        this.state = State::Ready(s);
+                        this.handshake_completed(Ok(()));
+                    }
                     Poll::Ready(Err(err)) => {
                         this.state = State::Error;
+                        this.handshake_completed(Err(&err));
                         return Poll::Ready(Err(err));
                     }
                     Poll::Pending => return Poll::Pending,
@@ -116,9 +154,13 @@ where
         loop {
             match &mut this.state {
                 State::Handshaking(fut) => match fut.poll_unpin(cx) {
-                    Poll::Ready(Ok(s)) => this.state = State::Ready(s),
+                    Poll::Ready(Ok(s)) => {
+                        this.state = State::Ready(s);
+                        this.handshake_completed(Ok(()));
+                    }
                     Poll::Ready(Err(err)) => {
                         this.state = State::Error;
+                        this.handshake_completed(Err(&err));
                         return Poll::Ready(Err(err));
                     }
                     Poll::Pending => return Poll::Pending,
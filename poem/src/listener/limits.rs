@@ -0,0 +1,350 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use http::uri::Scheme;
+use parking_lot::Mutex;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
+
+use crate::{
+    listener::{Acceptor, Listener, TlsInfoSlot},
+    web::{LocalAddr, RemoteAddr},
+    Addr,
+};
+
+/// The connection limits of a listener.
+///
+/// The connections exceeding the limits are closed as soon as they are
+/// accepted, before reading any data from them.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::listener::{Limits, Listener, TcpListener};
+///
+/// let listener = TcpListener::bind("0.0.0.0:3000").limits(
+///     Limits::new()
+///         .max_connections(10000)
+///         .max_connections_per_ip(100)
+///         .accept_rate(1000, Duration::from_secs(1)),
+/// );
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Limits {
+    max_connections: Option<usize>,
+    max_connections_per_ip: Option<usize>,
+    accept_rate: Option<(u32, Duration)>,
+}
+
+impl Limits {
+    /// Create a new `Limits` without any limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of concurrent connections.
+    #[must_use]
+    pub fn max_connections(self, max: usize) -> Self {
+        Self {
+            max_connections: Some(max),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of concurrent connections from the same IP
+    /// address.
+    #[must_use]
+    pub fn max_connections_per_ip(self, max: usize) -> Self {
+        Self {
+            max_connections_per_ip: Some(max),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of connections accepted per `period`.
+    ///
+    /// Up to `count` connections can be accepted in a burst, and then the
+    /// connections are accepted at the average rate.
+    #[must_use]
+    pub fn accept_rate(self, count: u32, period: Duration) -> Self {
+        Self {
+            accept_rate: Some((count, period)),
+            ..self
+        }
+    }
+}
+
+/// A wrapper around an underlying listener which enforces the [`Limits`].
+pub struct LimitsListener<T> {
+    inner: T,
+    limits: Limits,
+}
+
+impl<T> LimitsListener<T> {
+    pub(crate) fn new(inner: T, limits: Limits) -> Self {
+        Self { inner, limits }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Listener> Listener for LimitsListener<T> {
+    type Acceptor = LimitsAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        Ok(LimitsAcceptor {
+            inner: self.inner.into_acceptor().await?,
+            limits: self.limits,
+            counts: Default::default(),
+            rate_limiter: self
+                .limits
+                .accept_rate
+                .map(|(count, period)| RateLimiter::new(count, period)),
+        })
+    }
+}
+
+#[derive(Default)]
+struct ConnectionCounts {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// A acceptor that enforces the [`Limits`].
+pub struct LimitsAcceptor<T> {
+    inner: T,
+    limits: Limits,
+    counts: Arc<Mutex<ConnectionCounts>>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl<T> LimitsAcceptor<T> {
+    /// Returns a guard of the connection, or `None` if the connection exceeds
+    /// the limits.
+    fn try_acquire(&mut self, remote_addr: &RemoteAddr) -> Option<ConnectionGuard> {
+        if let Some(rate_limiter) = &mut self.rate_limiter {
+            if !rate_limiter.try_acquire(Instant::now()) {
+                tracing::debug!(remote_addr = %remote_addr, "accept rate limit exceeded");
+                return None;
+            }
+        }
+
+        let ip = match &remote_addr.0 {
+            Addr::SocketAddr(addr) => Some(addr.ip()),
+            _ => None,
+        };
+        let mut counts = self.counts.lock();
+
+        if matches!(self.limits.max_connections, Some(max) if counts.total >= max) {
+            tracing::debug!(remote_addr = %remote_addr, "too many connections");
+            return None;
+        }
+        if let (Some(max), Some(ip)) = (self.limits.max_connections_per_ip, ip) {
+            if counts.per_ip.get(&ip).copied().unwrap_or_default() >= max {
+                tracing::debug!(remote_addr = %remote_addr, "too many connections from the ip");
+                return None;
+            }
+        }
+
+        counts.total += 1;
+        if let Some(ip) = ip {
+            *counts.per_ip.entry(ip).or_default() += 1;
+        }
+        Some(ConnectionGuard {
+            counts: self.counts.clone(),
+            ip,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Acceptor> Acceptor for LimitsAcceptor<T> {
+    type Io = LimitsStream<T::Io>;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        loop {
+            let (stream, local_addr, remote_addr, scheme) = self.inner.accept().await?;
+            if let Some(guard) = self.try_acquire(&remote_addr) {
+                let stream = LimitsStream {
+                    inner: stream,
+                    _guard: guard,
+                };
+                return Ok((stream, local_addr, remote_addr, scheme));
+            }
+        }
+    }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        self.inner.tls_info(&io.inner)
+    }
+}
+
+/// Decreases the connection counts when the connection is closed.
+struct ConnectionGuard {
+    counts: Arc<Mutex<ConnectionCounts>>,
+    ip: Option<IpAddr>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock();
+        counts.total -= 1;
+        if let Some(ip) = self.ip {
+            if let Some(count) = counts.per_ip.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+/// A token bucket which allows `capacity` connections in a burst, and refills
+/// at the rate of `capacity` tokens per `period`.
+struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    tokens_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(count: u32, period: Duration) -> Self {
+        Self {
+            capacity: count as f64,
+            tokens: count as f64,
+            tokens_per_sec: count as f64 / period.as_secs_f64(),
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.tokens_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A IO stream for LimitsAcceptor.
+pub struct LimitsStream<S> {
+    inner: S,
+    _guard: ConnectionGuard,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for LimitsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for LimitsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::listener::TcpListener;
+
+    async fn accept_timeout<T: Acceptor>(acceptor: &mut T) -> Option<T::Io> {
+        tokio::time::timeout(Duration::from_millis(200), acceptor.accept())
+            .await
+            .ok()
+            .map(|res| res.unwrap().0)
+    }
+
+    #[tokio::test]
+    async fn max_connections() {
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .limits(Limits::new().max_connections(1))
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr().remove(0).as_socket_addr().unwrap();
+
+        let _a = TcpStream::connect(addr).await.unwrap();
+        let _b = TcpStream::connect(addr).await.unwrap();
+        let stream = accept_timeout(&mut acceptor).await.unwrap();
+        assert!(accept_timeout(&mut acceptor).await.is_none());
+
+        drop(stream);
+        let _c = TcpStream::connect(addr).await.unwrap();
+        assert!(accept_timeout(&mut acceptor).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn max_connections_per_ip() {
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .limits(Limits::new().max_connections_per_ip(2))
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr().remove(0).as_socket_addr().unwrap();
+
+        let mut clients = Vec::new();
+        for _ in 0..3 {
+            clients.push(TcpStream::connect(addr).await.unwrap());
+        }
+        let a = accept_timeout(&mut acceptor).await.unwrap();
+        let _b = accept_timeout(&mut acceptor).await.unwrap();
+        assert!(accept_timeout(&mut acceptor).await.is_none());
+        assert_eq!(acceptor.counts.lock().per_ip.len(), 1);
+
+        drop(a);
+        clients.push(TcpStream::connect(addr).await.unwrap());
+        assert!(accept_timeout(&mut acceptor).await.is_some());
+    }
+
+    #[test]
+    fn rate_limiter() {
+        let mut rate_limiter = RateLimiter::new(2, Duration::from_secs(1));
+        let now = Instant::now();
+        assert!(rate_limiter.try_acquire(now));
+        assert!(rate_limiter.try_acquire(now));
+        assert!(!rate_limiter.try_acquire(now));
+        assert!(!rate_limiter.try_acquire(now + Duration::from_millis(400)));
+        assert!(rate_limiter.try_acquire(now + Duration::from_millis(500)));
+        assert!(rate_limiter.try_acquire(now + Duration::from_secs(10)));
+        assert!(rate_limiter.try_acquire(now + Duration::from_secs(10)));
+        assert!(!rate_limiter.try_acquire(now + Duration::from_secs(10)));
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    io::Error as IoError,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use http::uri::Scheme;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
+
+use crate::{
+    listener::{Acceptor, Listener, TlsInfoSlot},
+    web::{LocalAddr, RemoteAddr},
+};
+
+/// Receives the connection events of a listener, such as accepted and closed
+/// connections, and the results of the TLS handshakes.
+///
+/// All methods do nothing by default, so only the interested events need to
+/// be implemented.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use poem::{
+///     listener::{Listener, ListenerMetrics, TcpListener},
+///     web::{LocalAddr, RemoteAddr},
+/// };
+///
+/// #[derive(Default)]
+/// struct ActiveConnections(AtomicUsize);
+///
+/// impl ListenerMetrics for ActiveConnections {
+///     fn accepted(&self, _local_addr: &LocalAddr, _remote_addr: &RemoteAddr) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn closed(&self, _local_addr: &LocalAddr, _remote_addr: &RemoteAddr) {
+///         self.0.fetch_sub(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let listener = TcpListener::bind("0.0.0.0:3000").metrics(ActiveConnections::default());
+/// ```
+pub trait ListenerMetrics: Send + Sync + 'static {
+    /// Called when a connection is accepted.
+    fn accepted(&self, _local_addr: &LocalAddr, _remote_addr: &RemoteAddr) {}
+
+    /// Called when the listener fails to accept a connection.
+    fn accept_failed(&self, _err: &IoError) {}
+
+    /// Called when an accepted connection is closed.
+    fn closed(&self, _local_addr: &LocalAddr, _remote_addr: &RemoteAddr) {}
+
+    /// Called when the TLS handshake of a connection is completed, `latency`
+    /// is the time elapsed since the connection was accepted.
+    fn handshake_succeeded(&self, _latency: Duration) {}
+
+    /// Called when the TLS handshake of a connection fails.
+    fn handshake_failed(&self, _latency: Duration, _err: &IoError) {}
+}
+
+/// A wrapper around an underlying listener which reports the connection
+/// events to the [`ListenerMetrics`].
+///
+/// The TLS handshakes are only reported if this listener wraps a TLS
+/// listener, for example `TcpListener::bind(..).rustls(..).metrics(..)`.
+pub struct MetricsListener<T> {
+    inner: T,
+    metrics: Arc<dyn ListenerMetrics>,
+}
+
+impl<T> MetricsListener<T> {
+    pub(crate) fn new(inner: T, metrics: impl ListenerMetrics) -> Self {
+        Self {
+            inner,
+            metrics: Arc::new(metrics),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Listener> Listener for MetricsListener<T> {
+    type Acceptor = MetricsAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        Ok(MetricsAcceptor {
+            inner: self.inner.into_acceptor().await?,
+            metrics: self.metrics,
+        })
+    }
+}
+
+/// A acceptor that reports the connection events to the [`ListenerMetrics`].
+pub struct MetricsAcceptor<T> {
+    inner: T,
+    metrics: Arc<dyn ListenerMetrics>,
+}
+
+#[async_trait::async_trait]
+impl<T: Acceptor> Acceptor for MetricsAcceptor<T> {
+    type Io = MetricsStream<T::Io>;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme) = match self.inner.accept().await {
+            Ok(res) => res,
+            Err(err) => {
+                self.metrics.accept_failed(&err);
+                return Err(err);
+            }
+        };
+
+        self.metrics.accepted(&local_addr, &remote_addr);
+        if let Some(tls_info) = self.inner.tls_info(&stream) {
+            tls_info.set_metrics(self.metrics.clone());
+        }
+
+        let stream = MetricsStream {
+            inner: stream,
+            metrics: self.metrics.clone(),
+            local_addr: local_addr.clone(),
+            remote_addr: remote_addr.clone(),
+        };
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        self.inner.tls_info(&io.inner)
+    }
+}
+
+/// A IO stream for MetricsAcceptor.
+pub struct MetricsStream<S> {
+    inner: S,
+    metrics: Arc<dyn ListenerMetrics>,
+    local_addr: LocalAddr,
+    remote_addr: RemoteAddr,
+}
+
+impl<S> Drop for MetricsStream<S> {
+    fn drop(&mut self) {
+        self.metrics.closed(&self.local_addr, &self.remote_addr);
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for MetricsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for MetricsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::listener::TcpListener;
+
+    #[derive(Default, Clone)]
+    struct TestMetrics(Arc<Mutex<Vec<&'static str>>>);
+
+    impl ListenerMetrics for TestMetrics {
+        fn accepted(&self, _local_addr: &LocalAddr, _remote_addr: &RemoteAddr) {
+            self.0.lock().push("accepted");
+        }
+
+        fn closed(&self, _local_addr: &LocalAddr, _remote_addr: &RemoteAddr) {
+            self.0.lock().push("closed");
+        }
+
+        fn handshake_succeeded(&self, _latency: Duration) {
+            self.0.lock().push("handshake_succeeded");
+        }
+
+        fn handshake_failed(&self, _latency: Duration, _err: &IoError) {
+            self.0.lock().push("handshake_failed");
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_events() {
+        let metrics = TestMetrics::default();
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .metrics(metrics.clone())
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr().remove(0).as_socket_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_i32(10).await.unwrap();
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+        assert_eq!(*metrics.0.lock(), ["accepted"]);
+
+        drop(stream);
+        assert_eq!(*metrics.0.lock(), ["accepted", "closed"]);
+    }
+
+    #[cfg(feature = "rustls")]
+    #[tokio::test]
+    async fn handshake_events() {
+        use crate::listener::{RustlsCertificate, RustlsConfig};
+
+        let metrics = TestMetrics::default();
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .rustls(
+                RustlsConfig::new().fallback(
+                    RustlsCertificate::new()
+                        .cert(include_bytes!("certs/cert1.pem").as_ref())
+                        .key(include_bytes!("certs/key1.pem").as_ref()),
+                ),
+            )
+            .metrics(metrics.clone())
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr().remove(0).as_socket_addr().unwrap();
+
+        tokio::spawn(async move {
+            // not a tls client hello
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+            client.read_u8().await.ok();
+        });
+
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert!(stream.read_i32().await.is_err());
+        drop(stream);
+
+        assert_eq!(
+            *metrics.0.lock(),
+            ["accepted", "handshake_failed", "closed"]
+        );
+    }
+}
@@ -4,50 +4,144 @@
 #[cfg_attr(docsrs, doc(cfg(feature = "acme")))]
 pub mod acme;
 mod combined;
+mod dynamic;
 #[cfg(any(feature = "native-tls", feature = "rustls", feature = "openssl-tls"))]
 mod handshake_stream;
+mod limits;
+mod metrics;
+#[cfg(windows)]
+mod named_pipe;
 #[cfg(feature = "native-tls")]
 mod native_tls;
+#[cfg(feature = "ocsp")]
+mod ocsp;
 #[cfg(feature = "openssl-tls")]
 mod openssl_tls;
+mod proxy_protocol;
 #[cfg(feature = "rustls")]
 mod rustls;
+#[cfg(feature = "rustls")]
+mod session_ticket;
 mod tcp;
 #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
 mod tls;
 #[cfg(unix)]
 mod unix;
+#[cfg(all(unix, feature = "upgrade"))]
+mod upgrade;
+#[cfg(all(feature = "vsock", any(target_os = "android", target_os = "linux")))]
+mod vsock;
 
 use std::{
     convert::Infallible,
+    fmt::{self, Debug, Formatter},
     io::Error,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures_util::{future::BoxFuture, FutureExt, TryFutureExt};
 use http::uri::Scheme;
-use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult},
+    sync::OnceCell,
+};
 
 #[cfg(feature = "acme")]
 use self::acme::{AutoCert, AutoCertListener};
 #[cfg(any(feature = "native-tls", feature = "rustls", feature = "openssl-tls"))]
 pub use self::handshake_stream::HandshakeStream;
+#[cfg(windows)]
+pub use self::named_pipe::{NamedPipeAcceptor, NamedPipeListener};
 #[cfg(feature = "native-tls")]
 pub use self::native_tls::{NativeTlsAcceptor, NativeTlsConfig, NativeTlsListener};
 #[cfg(feature = "openssl-tls")]
 pub use self::openssl_tls::{OpensslTlsAcceptor, OpensslTlsConfig, OpensslTlsListener};
 #[cfg(feature = "rustls")]
-pub use self::rustls::{RustlsAcceptor, RustlsCertificate, RustlsConfig, RustlsListener};
+pub use self::rustls::{
+    RustlsAcceptor, RustlsCertificate, RustlsCertificateResolver, RustlsConfig, RustlsListener,
+};
+#[cfg(feature = "rustls")]
+pub use self::session_ticket::SessionTicketKeyProvider;
 #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
 pub use self::tls::IntoTlsConfigStream;
 #[cfg(unix)]
 pub use self::unix::{UnixAcceptor, UnixListener};
+#[cfg(all(unix, feature = "upgrade"))]
+pub use self::upgrade::{UpgradeAcceptor, UpgradeHandle, UpgradeListener};
+#[cfg(all(feature = "vsock", any(target_os = "android", target_os = "linux")))]
+pub use self::vsock::{VsockAcceptor, VsockListener, VsockStream};
 pub use self::{
     combined::{Combined, CombinedStream},
+    dynamic::{DynamicAcceptor, DynamicAcceptorHandle, ListenerId},
+    limits::{Limits, LimitsAcceptor, LimitsListener, LimitsStream},
+    metrics::{ListenerMetrics, MetricsAcceptor, MetricsListener, MetricsStream},
+    proxy_protocol::{ProxyProtocolAcceptor, ProxyProtocolListener},
     tcp::{TcpAcceptor, TcpListener},
 };
-use crate::web::{LocalAddr, RemoteAddr};
+use crate::web::{AlpnProtocol, ClientCert, LocalAddr, RemoteAddr, TlsInfo};
+
+/// A slot of the TLS information of a connection, such as the client
+/// certificate and the negotiated ALPN protocol, which is filled after the TLS
+/// handshake is completed.
+#[derive(Clone, Default)]
+pub struct TlsInfoSlot(Arc<TlsInfoSlotInner>);
+
+#[derive(Default)]
+struct TlsInfoSlotInner {
+    info: OnceCell<TlsInfo>,
+    metrics: OnceCell<Arc<dyn ListenerMetrics>>,
+}
+
+impl Debug for TlsInfoSlot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsInfoSlot")
+            .field("info", &self.0.info.get())
+            .finish()
+    }
+}
+
+impl TlsInfoSlot {
+    #[cfg_attr(
+        not(any(feature = "native-tls", feature = "rustls", feature = "openssl-tls")),
+        allow(dead_code)
+    )]
+    pub(crate) fn set_info(&self, info: TlsInfo) {
+        let _ = self.0.info.set(info);
+    }
+
+    pub(crate) fn info(&self) -> Option<&TlsInfo> {
+        self.0.info.get()
+    }
+
+    pub(crate) fn client_cert(&self) -> Option<&ClientCert> {
+        self.info()?.peer_cert()
+    }
+
+    pub(crate) fn alpn_protocol(&self) -> Option<&AlpnProtocol> {
+        self.info()?.alpn_protocol()
+    }
+
+    /// Sets the metrics which the result of the TLS handshake is reported to.
+    pub(crate) fn set_metrics(&self, metrics: Arc<dyn ListenerMetrics>) {
+        let _ = self.0.metrics.set(metrics);
+    }
+
+    #[cfg_attr(
+        not(any(feature = "native-tls", feature = "rustls", feature = "openssl-tls")),
+        allow(dead_code)
+    )]
+    pub(crate) fn handshake_completed(&self, latency: Duration, res: Result<(), &Error>) {
+        if let Some(metrics) = self.0.metrics.get() {
+            match res {
+                Ok(()) => metrics.handshake_succeeded(latency),
+                Err(err) => metrics.handshake_failed(latency, err),
+            }
+        }
+    }
+}
 
 /// Represents a acceptor type.
 #[async_trait::async_trait]
@@ -64,6 +158,12 @@ pub trait Acceptor: Send {
     /// established, the corresponding IO stream and the remote peer’s
     /// address will be returned.
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)>;
+
+    /// Returns the slot of the TLS information of an IO stream returned by
+    /// [`Acceptor::accept`], or `None` if this acceptor does not provide it.
+    fn tls_info(&self, _io: &Self::Io) -> Option<TlsInfoSlot> {
+        None
+    }
 }
 
 /// An owned dynamically typed Acceptor for use in cases where you can’t
@@ -153,6 +253,64 @@ pub trait Listener: Send {
         Combined::new(self, other)
     }
 
+    /// Consume this listener and return a new listener which enforces the
+    /// connection limits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::listener::{Limits, Listener, TcpListener};
+    ///
+    /// let listener = TcpListener::bind("0.0.0.0:80").limits(Limits::new().max_connections(1000));
+    /// ```
+    #[must_use]
+    fn limits(self, limits: Limits) -> LimitsListener<Self>
+    where
+        Self: Sized,
+    {
+        LimitsListener::new(self, limits)
+    }
+
+    /// Consume this listener and return a new listener which reports the
+    /// connection events to the [`ListenerMetrics`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::listener::{Listener, ListenerMetrics, TcpListener};
+    ///
+    /// struct Metrics;
+    ///
+    /// impl ListenerMetrics for Metrics {}
+    ///
+    /// let listener = TcpListener::bind("0.0.0.0:80").metrics(Metrics);
+    /// ```
+    #[must_use]
+    fn metrics(self, metrics: impl ListenerMetrics) -> MetricsListener<Self>
+    where
+        Self: Sized,
+    {
+        MetricsListener::new(self, metrics)
+    }
+
+    /// Consume this listener and return a new listener which parses the
+    /// PROXY protocol header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::listener::{Listener, TcpListener};
+    ///
+    /// let listener = TcpListener::bind("0.0.0.0:80").proxy_protocol();
+    /// ```
+    #[must_use]
+    fn proxy_protocol(self) -> ProxyProtocolListener<Self>
+    where
+        Self: Sized,
+    {
+        ProxyProtocolListener::new(self)
+    }
+
     /// Consume this listener and return a new TLS listener with [`rustls`](https://crates.io/crates/rustls).
     #[cfg(feature = "rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
@@ -261,6 +419,10 @@ impl<T: Acceptor + ?Sized> Acceptor for Box<T> {
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
         self.as_mut().accept().await
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        self.as_ref().tls_info(io)
+    }
 }
 
 #[async_trait::async_trait]
@@ -280,14 +442,19 @@ impl Acceptor for Infallible {
 pub struct BoxIo {
     reader: Box<dyn AsyncRead + Send + Unpin + 'static>,
     writer: Box<dyn AsyncWrite + Send + Unpin + 'static>,
+    tls_info: Option<TlsInfoSlot>,
 }
 
 impl BoxIo {
-    fn new(io: impl AsyncRead + AsyncWrite + Send + Unpin + 'static) -> Self {
+    fn new(
+        io: impl AsyncRead + AsyncWrite + Send + Unpin + 'static,
+        tls_info: Option<TlsInfoSlot>,
+    ) -> Self {
         let (reader, writer) = tokio::io::split(io);
         Self {
             reader: Box::new(reader),
             writer: Box::new(writer),
+            tls_info,
         }
     }
 }
@@ -358,9 +525,14 @@ impl<T: Acceptor> Acceptor for WrappedAcceptor<T> {
             .accept()
             .await
             .map(|(io, local_addr, remote_addr, scheme)| {
-                (BoxIo::new(io), local_addr, remote_addr, scheme)
+                let tls_info = self.0.tls_info(&io);
+                (BoxIo::new(io, tls_info), local_addr, remote_addr, scheme)
             })
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        io.tls_info.clone()
+    }
 }
 
 #[cfg(test)]
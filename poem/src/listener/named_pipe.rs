@@ -0,0 +1,115 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io::Result,
+};
+
+use http::uri::Scheme;
+use tokio::{
+    io::Result as IoResult,
+    net::windows::named_pipe::{NamedPipeServer, ServerOptions},
+};
+
+use crate::{
+    listener::{Acceptor, Listener},
+    web::{LocalAddr, RemoteAddr},
+    Addr,
+};
+
+/// A Windows named pipe listener.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::listener::NamedPipeListener;
+///
+/// let listener = NamedPipeListener::bind(r"\\.\pipe\poem");
+/// ```
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+pub struct NamedPipeListener<T> {
+    name: T,
+}
+
+impl<T> NamedPipeListener<T> {
+    /// Binds to the provided pipe name, and returns a
+    /// [`NamedPipeListener<T>`].
+    pub fn bind(name: T) -> Self {
+        Self { name }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: AsRef<OsStr> + Send> Listener for NamedPipeListener<T> {
+    type Acceptor = NamedPipeAcceptor;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        let name = self.name.as_ref().to_os_string();
+        // fails if the pipe has been created by another process
+        let server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&name)?;
+        let local_addr = LocalAddr(Addr::custom("pipe", name.to_string_lossy().into_owned()));
+        Ok(NamedPipeAcceptor {
+            name,
+            local_addr,
+            server,
+        })
+    }
+}
+
+/// A acceptor that accepts named pipe connections.
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+pub struct NamedPipeAcceptor {
+    name: OsString,
+    local_addr: LocalAddr,
+    server: NamedPipeServer,
+}
+
+#[async_trait::async_trait]
+impl Acceptor for NamedPipeAcceptor {
+    type Io = NamedPipeServer;
+
+    #[inline]
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        vec![self.local_addr.clone()]
+    }
+
+    async fn accept(&mut self) -> Result<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        self.server.connect().await?;
+
+        // create the next instance of the pipe before handing out the
+        // connected one, so that there is always an instance for the clients
+        let server = ServerOptions::new().create(&self.name)?;
+        let stream = std::mem::replace(&mut self.server, server);
+        Ok((
+            stream,
+            self.local_addr.clone(),
+            RemoteAddr(Addr::custom("pipe", "unknown")),
+            Scheme::HTTP,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::windows::named_pipe::ClientOptions,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn named_pipe_listener() {
+        let name = format!(r"\\.\pipe\poem-test-{}", std::process::id());
+        let listener = NamedPipeListener::bind(name.clone());
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+
+        tokio::spawn(async move {
+            let mut stream = ClientOptions::new().open(&name).unwrap();
+            stream.write_i32(10).await.unwrap();
+        });
+
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+    }
+}
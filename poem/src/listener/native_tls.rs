@@ -7,8 +7,8 @@ use tokio::io::{Error as IoError, ErrorKind, Result as IoResult};
 use tokio_native_tls::{native_tls::Identity, TlsStream};
 
 use crate::{
-    listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener},
-    web::{LocalAddr, RemoteAddr},
+    listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener, TlsInfoSlot},
+    web::{ClientCert, LocalAddr, RemoteAddr, TlsInfo},
 };
 
 /// Native TLS Config.
@@ -174,13 +174,32 @@ where
                         Some(tls_acceptor) => tls_acceptor.clone(),
                         None => return Err(IoError::new(ErrorKind::Other, "no valid tls config.")),
                     };
-                    let fut = async move { tls_acceptor.accept(stream).map_err(|err| IoError::new(ErrorKind::Other, err.to_string())).await };
-                    let stream = HandshakeStream::new(fut);
+                    let tls_info = TlsInfoSlot::default();
+                    let fut = {
+                        let tls_info = tls_info.clone();
+                        async move {
+                            let stream = tls_acceptor.accept(stream).map_err(|err| IoError::new(ErrorKind::Other, err.to_string())).await?;
+                            // native-tls only reports the peer certificate
+                            let mut info = TlsInfo::new();
+                            if let Ok(Some(cert)) = stream.get_ref().peer_certificate() {
+                                if let Ok(cert) = cert.to_der() {
+                                    info = info.with_peer_cert(ClientCert::new(vec![cert]));
+                                }
+                            }
+                            tls_info.set_info(info);
+                            Ok(stream)
+                        }
+                    };
+                    let stream = HandshakeStream::new(fut).with_tls_info(tls_info);
                     return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
                 }
             }
         }
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        io.tls_info()
+    }
 }
 
 #[cfg(test)]
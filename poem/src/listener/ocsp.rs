@@ -0,0 +1,421 @@
+//! Fetch OCSP responses for stapling.
+//!
+//! Reference: <https://datatracker.ietf.org/doc/html/rfc6960>
+
+use std::{
+    fmt::Display,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use http::{header, Method, Request, StatusCode, Uri};
+use hyper::Body;
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use tokio_rustls::rustls::{sign::CertifiedKey, Certificate};
+use x509_parser::{
+    der_parser::asn1_rs::{
+        oid, Any, Class, Enumerated, FromDer, GeneralizedTime, Integer, Null, OctetString, Oid,
+        OptTaggedParser, ParseResult, Sequence, SerializeResult, Tag, ToDer,
+    },
+    extensions::{GeneralName, ParsedExtension},
+    oid_registry::{OID_HASH_SHA1, OID_PKIX_ACCESS_DESCRIPTOR_OCSP},
+    parse_x509_certificate,
+    prelude::X509Certificate,
+};
+
+/// How long to wait before fetching the OCSP response again after a failure.
+pub(crate) const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long to wait before fetching the OCSP response again if the responder
+/// does not specify the `nextUpdate` field.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// `id-pkix-ocsp-basic`
+const OID_OCSP_BASIC: Oid<'static> = oid!(1.3.6 .1 .5 .5 .7 .48 .1 .1);
+
+/// A DER-encoded OCSP response which can be stapled to the certificate.
+pub(crate) struct OcspStaple {
+    pub(crate) response: Vec<u8>,
+    pub(crate) refresh_at: SystemTime,
+}
+
+/// Returns a copy of the certificate with the OCSP response stapled.
+pub(crate) fn staple(cert_key: &CertifiedKey, response: Vec<u8>) -> Arc<CertifiedKey> {
+    Arc::new(CertifiedKey {
+        ocsp: Some(response),
+        ..cert_key.clone()
+    })
+}
+
+/// Fetch the OCSP response for the first certificate of the chain, the second
+/// certificate must be its issuer.
+///
+/// Returns `None` if the certificate does not specify an OCSP responder.
+pub(crate) async fn fetch(certs: &[Certificate]) -> IoResult<Option<OcspStaple>> {
+    let (cert, issuer) = match certs {
+        [cert, issuer, ..] => (cert, issuer),
+        _ => return Ok(None),
+    };
+    let (_, cert) = parse_x509_certificate(&cert.0)
+        .map_err(|err| IoError::new(ErrorKind::Other, format!("invalid certificate: {}", err)))?;
+    let (_, issuer) = parse_x509_certificate(&issuer.0)
+        .map_err(|err| IoError::new(ErrorKind::Other, format!("invalid certificate: {}", err)))?;
+
+    let responder = match responder_url(&cert) {
+        Some(responder) => responder,
+        None => return Ok(None),
+    };
+    let serial = cert.tbs_certificate.raw_serial();
+    let request = encode_request(
+        issuer.subject().as_raw(),
+        issuer.public_key().subject_public_key.data,
+        serial,
+    )
+    .map_err(|err| {
+        IoError::new(
+            ErrorKind::Other,
+            format!("failed to encode ocsp request: {}", err),
+        )
+    })?;
+
+    tracing::debug!(responder = %responder, "fetch ocsp response");
+
+    let cli = hyper::Client::new();
+    let resp = cli
+        .request(
+            Request::builder()
+                .method(Method::POST)
+                .uri(responder)
+                .header(header::CONTENT_TYPE, "application/ocsp-request")
+                .body(Body::from(request))
+                .map_err(|err| IoError::new(ErrorKind::Other, err))?,
+        )
+        .await
+        .map_err(|err| IoError::new(ErrorKind::Other, err))?;
+    if resp.status() != StatusCode::OK {
+        return Err(IoError::new(
+            ErrorKind::Other,
+            format!("unexpected ocsp responder status: {}", resp.status()),
+        ));
+    }
+    let response = hyper::body::to_bytes(resp.into_body())
+        .await
+        .map_err(|err| IoError::new(ErrorKind::Other, err))?
+        .to_vec();
+
+    let (this_update, next_update) = parse_response(&response, serial)?;
+    let refresh_at = match next_update {
+        Some(next_update) => {
+            this_update + next_update.duration_since(this_update).unwrap_or_default() / 2
+        }
+        None => SystemTime::now() + DEFAULT_REFRESH_INTERVAL,
+    };
+
+    Ok(Some(OcspStaple {
+        response,
+        refresh_at,
+    }))
+}
+
+fn responder_url(cert: &X509Certificate<'_>) -> Option<Uri> {
+    cert.extensions()
+        .iter()
+        .find_map(|ext| match ext.parsed_extension() {
+            ParsedExtension::AuthorityInfoAccess(aia) => aia
+                .accessdescs
+                .iter()
+                .filter(|desc| desc.access_method == OID_PKIX_ACCESS_DESCRIPTOR_OCSP)
+                .find_map(|desc| match desc.access_location {
+                    GeneralName::URI(uri) => uri.parse().ok(),
+                    _ => None,
+                }),
+            _ => None,
+        })
+}
+
+/// Encode a `SEQUENCE` with the specified elements.
+fn sequence(items: &[&dyn ToDer]) -> SerializeResult<Sequence<'static>> {
+    let mut content = Vec::new();
+    for item in items {
+        item.write_der(&mut content)?;
+    }
+    Ok(Sequence::new(content.into()))
+}
+
+/// Encode the `OCSPRequest` with a single `CertID` hashed with SHA-1.
+fn encode_request(
+    issuer_name: &[u8],
+    issuer_key: &[u8],
+    serial: &[u8],
+) -> SerializeResult<Vec<u8>> {
+    let issuer_name_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, issuer_name);
+    let issuer_key_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, issuer_key);
+    let cert_id = sequence(&[
+        &sequence(&[&OID_HASH_SHA1, &Null::new()])?,
+        &OctetString::new(issuer_name_hash.as_ref()),
+        &OctetString::new(issuer_key_hash.as_ref()),
+        &Integer::new(serial),
+    ])?;
+    let request = sequence(&[&cert_id])?;
+    let request_list = sequence(&[&request])?;
+    let tbs_request = sequence(&[&request_list])?;
+    sequence(&[&tbs_request])?.to_der_vec()
+}
+
+fn invalid_response(msg: impl Display) -> IoError {
+    IoError::new(ErrorKind::Other, format!("invalid ocsp response: {}", msg))
+}
+
+struct SingleResponse<'a> {
+    serial: Integer<'a>,
+    good: bool,
+    this_update: GeneralizedTime,
+    next_update: Option<GeneralizedTime>,
+}
+
+/// Parse the `SingleResponse`, the `certID` is only checked by the serial
+/// number.
+fn parse_single_response(data: &[u8]) -> ParseResult<'_, SingleResponse<'_>> {
+    Sequence::from_der_and_then(data, |data| {
+        let (data, serial) = Sequence::from_der_and_then(data, |data| {
+            let (data, _hash_algorithm) = Any::from_der(data)?;
+            let (data, _issuer_name_hash) = <&[u8]>::from_der(data)?;
+            let (data, _issuer_key_hash) = <&[u8]>::from_der(data)?;
+            Integer::from_der(data)
+        })?;
+        // good [0] IMPLICIT NULL
+        let (data, cert_status) = Any::from_der(data)?;
+        let (data, this_update) = GeneralizedTime::from_der(data)?;
+        let (data, next_update) =
+            OptTaggedParser::from(0).parse_der(data, |_, data| GeneralizedTime::from_der(data))?;
+        Ok((
+            data,
+            SingleResponse {
+                serial,
+                good: cert_status.class() == Class::ContextSpecific && cert_status.tag() == Tag(0),
+                this_update,
+                next_update,
+            },
+        ))
+    })
+}
+
+fn to_system_time(time: &GeneralizedTime) -> IoResult<SystemTime> {
+    time.utc_datetime()
+        .map(Into::into)
+        .map_err(|_| invalid_response("invalid time"))
+}
+
+/// Parse the `OCSPResponse`, returns the `thisUpdate` and `nextUpdate` fields
+/// of the response for the certificate with the `serial`.
+///
+/// The signature is not verified, since the response is only stapled for the
+/// clients which will verify it.
+fn parse_response(data: &[u8], serial: &[u8]) -> IoResult<(SystemTime, Option<SystemTime>)> {
+    let (_, (status, response_bytes)) = Sequence::from_der_and_then(data, |data| {
+        let (data, status) = Enumerated::from_der(data)?;
+        let (data, response_bytes) = OptTaggedParser::from(0).parse_der(data, |_, data| {
+            Sequence::from_der_and_then(data, |data| {
+                let (data, response_type) = Oid::from_der(data)?;
+                let (data, response) = <&[u8]>::from_der(data)?;
+                Ok((data, (response_type, response)))
+            })
+        })?;
+        Ok((data, (status, response_bytes)))
+    })
+    .map_err(invalid_response)?;
+
+    if status.0 != 0 {
+        return Err(IoError::new(
+            ErrorKind::Other,
+            format!("ocsp responder returned status {}", status.0),
+        ));
+    }
+    let response = match response_bytes {
+        Some((response_type, response)) if response_type == OID_OCSP_BASIC => response,
+        Some(_) => return Err(invalid_response("unsupported response type")),
+        None => return Err(invalid_response("missing response bytes")),
+    };
+
+    // BasicOCSPResponse -> ResponseData
+    let (_, responses) = Sequence::from_der_and_then(response, |data| {
+        Sequence::from_der_and_then(data, |data| {
+            // skip the version, the responder id and the `producedAt` field
+            let (data, _version) =
+                OptTaggedParser::from(0).parse_der(data, |_, data| Ok((data, ())))?;
+            let (data, _responder_id) = Any::from_der(data)?;
+            let (data, _produced_at) = GeneralizedTime::from_der(data)?;
+            Sequence::from_der_and_then(data, |mut data| {
+                let mut responses = Vec::new();
+                while !data.is_empty() {
+                    let (remaining, response) = parse_single_response(data)?;
+                    responses.push(response);
+                    data = remaining;
+                }
+                Ok((data, responses))
+            })
+        })
+    })
+    .map_err(invalid_response)?;
+
+    let response = responses
+        .into_iter()
+        .find(|response| response.serial.as_ref() == serial)
+        .ok_or_else(|| invalid_response("no response for the certificate"))?;
+    if !response.good {
+        return Err(IoError::new(
+            ErrorKind::Other,
+            "the certificate status is not good",
+        ));
+    }
+    Ok((
+        to_system_time(&response.this_update)?,
+        response
+            .next_update
+            .as_ref()
+            .map(to_system_time)
+            .transpose()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use x509_parser::der_parser::asn1_rs::{Header, Length};
+
+    use super::*;
+
+    fn der(item: &dyn ToDer) -> Vec<u8> {
+        item.to_der_vec().unwrap()
+    }
+
+    fn seq(items: &[Vec<u8>]) -> Vec<u8> {
+        der(&Sequence::new(items.concat().into()))
+    }
+
+    /// Encode a context-specific element, the `ToDer` implementations of
+    /// `Enumerated` and `TaggedExplicit` in `asn1-rs` 0.3 write wrong lengths.
+    fn tagged(constructed: bool, tag: u32, data: &[u8]) -> Vec<u8> {
+        der(&Any::new(
+            Header::new(
+                Class::ContextSpecific,
+                constructed,
+                Tag(tag),
+                Length::Definite(0),
+            ),
+            data,
+        ))
+    }
+
+    fn response_status(status: u8) -> Vec<u8> {
+        der(&Any::from_tag_and_data(Tag::Enumerated, &[status]))
+    }
+
+    fn generalized_time(time: &str) -> Vec<u8> {
+        der(&GeneralizedTime::from_bytes(time.as_bytes()).unwrap())
+    }
+
+    fn single_response(serial: &[u8], status: u32, next_update: Option<&str>) -> Vec<u8> {
+        let cert_id = seq(&[
+            seq(&[der(&OID_HASH_SHA1), der(&Null::new())]),
+            der(&OctetString::new(&[0; 20])),
+            der(&OctetString::new(&[0; 20])),
+            der(&Integer::new(serial)),
+        ]);
+        let mut items = vec![
+            cert_id,
+            tagged(false, status, &[]),
+            generalized_time("20220101000000Z"),
+        ];
+        if let Some(next_update) = next_update {
+            items.push(tagged(true, 0, &generalized_time(next_update)));
+        }
+        seq(&items)
+    }
+
+    fn ocsp_response(response_type: &Oid<'_>, responses: &[Vec<u8>]) -> Vec<u8> {
+        let response_data = seq(&[
+            tagged(true, 2, &der(&OctetString::new(&[1; 20]))),
+            generalized_time("20220101000000Z"),
+            seq(responses),
+        ]);
+        let basic_response = seq(&[response_data]);
+        let response_bytes = seq(&[der(response_type), der(&OctetString::new(&basic_response))]);
+        seq(&[response_status(0), tagged(true, 0, &response_bytes)])
+    }
+
+    #[test]
+    fn test_encode_request() {
+        let issuer_name_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, b"name");
+        let issuer_key_hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, b"key");
+        assert_eq!(
+            encode_request(b"name", b"key", &[1]).unwrap(),
+            [
+                // OCSPRequest -> TBSRequest -> requestList -> Request -> CertID
+                &[0x30, 0x42, 0x30, 0x40, 0x30, 0x3e, 0x30, 0x3c, 0x30, 0x3a][..],
+                // hashAlgorithm
+                &[0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00],
+                &[0x04, 0x14],
+                issuer_name_hash.as_ref(),
+                &[0x04, 0x14],
+                issuer_key_hash.as_ref(),
+                // serialNumber
+                &[0x02, 0x01, 0x01],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(OID_OCSP_BASIC.to_id_string(), "1.3.6.1.5.5.7.48.1.1");
+
+        let this_update = UNIX_EPOCH + Duration::from_secs(1640995200);
+        let next_update = UNIX_EPOCH + Duration::from_secs(1641600000);
+
+        let data = ocsp_response(
+            &OID_OCSP_BASIC,
+            &[
+                single_response(&[2], 0, None),
+                single_response(&[1], 0, Some("20220108000000Z")),
+                single_response(&[3], 2, None),
+            ],
+        );
+        assert_eq!(
+            parse_response(&data, &[1]).unwrap(),
+            (this_update, Some(next_update))
+        );
+        assert_eq!(parse_response(&data, &[2]).unwrap(), (this_update, None));
+        assert_eq!(
+            parse_response(&data, &[3]).unwrap_err().to_string(),
+            "the certificate status is not good"
+        );
+        assert_eq!(
+            parse_response(&data, &[4]).unwrap_err().to_string(),
+            "invalid ocsp response: no response for the certificate"
+        );
+
+        let data = ocsp_response(&OID_HASH_SHA1, &[single_response(&[1], 0, None)]);
+        assert_eq!(
+            parse_response(&data, &[1]).unwrap_err().to_string(),
+            "invalid ocsp response: unsupported response type"
+        );
+
+        // unauthorized
+        let data = seq(&[response_status(6)]);
+        assert_eq!(
+            parse_response(&data, &[1]).unwrap_err().to_string(),
+            "ocsp responder returned status 6"
+        );
+
+        let data = seq(&[response_status(0)]);
+        assert_eq!(
+            parse_response(&data, &[1]).unwrap_err().to_string(),
+            "invalid ocsp response: missing response bytes"
+        );
+
+        assert!(parse_response(&[0x30, 0x03, 0x0a, 0x01], &[1]).is_err());
+    }
+}
@@ -10,7 +10,7 @@ use futures_util::{
 use http::uri::Scheme;
 use openssl::{
     pkey::PKey,
-    ssl::{Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslRef},
+    ssl::{NameType, Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslRef},
     x509::X509,
 };
 use tokio::io::{Error as IoError, ErrorKind, Result as IoResult};
@@ -18,14 +18,15 @@ use tokio_openssl::SslStream;
 use tokio_util::either::Either;
 
 use crate::{
-    listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener},
-    web::{LocalAddr, RemoteAddr},
+    listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener, TlsInfoSlot},
+    web::{AlpnProtocol, ClientCert, LocalAddr, RemoteAddr, TlsInfo, TlsVersion},
 };
 
 /// Openssl configuration contains certificate's chain and private key.
 pub struct OpensslTlsConfig {
     cert: Either<Vec<u8>, PathBuf>,
     key: Either<Vec<u8>, PathBuf>,
+    alpn_protocols: Vec<Vec<u8>>,
 }
 
 impl Default for OpensslTlsConfig {
@@ -33,6 +34,7 @@ impl Default for OpensslTlsConfig {
         Self {
             cert: Either::Left(vec![]),
             key: Either::Left(vec![]),
+            alpn_protocols: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
         }
     }
 }
@@ -67,6 +69,34 @@ impl OpensslTlsConfig {
         self
     }
 
+    /// Sets the protocols advertised with ALPN in order of preference, default
+    /// is `h2` and `http/1.1`.
+    ///
+    /// The negotiated protocol is available to the handlers as
+    /// [`AlpnProtocol`](crate::web::AlpnProtocol) in the request extensions.
+    pub fn alpn_protocols<I, P>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Vec<u8>>,
+    {
+        self.alpn_protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Enable or disable HTTP/2, default is `true`.
+    ///
+    /// If disabled, `h2` is not advertised with ALPN, so the clients use
+    /// HTTP/1.1.
+    pub fn http2(mut self, enabled: bool) -> Self {
+        let is_h2 = |protocol: &Vec<u8>| protocol == b"h2";
+        if !enabled {
+            self.alpn_protocols.retain(|protocol| !is_h2(protocol));
+        } else if !self.alpn_protocols.iter().any(is_h2) {
+            self.alpn_protocols.insert(0, b"h2".to_vec());
+        }
+        self
+    }
+
     fn create_acceptor_builder(&self) -> IoResult<SslAcceptorBuilder> {
         let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
         match &self.cert {
@@ -90,17 +120,45 @@ impl OpensslTlsConfig {
             Either::Right(path) => builder.set_private_key_file(path, SslFiletype::PEM)?,
         }
 
-        // set ALPN protocols
-        static PROTOS: &[u8] = b"\x02h2\x08http/1.1";
-        builder.set_alpn_protos(PROTOS)?;
-        // set uo ALPN selection routine - as select_next_proto
+        // set ALPN protocols in the wire format
+        let mut protos = Vec::new();
+        for protocol in &self.alpn_protocols {
+            let len = u8::try_from(protocol.len())
+                .map_err(|_| IoError::new(ErrorKind::Other, "alpn protocol too long"))?;
+            protos.push(len);
+            protos.extend_from_slice(protocol);
+        }
+        builder.set_alpn_protos(&protos)?;
+        // set up ALPN selection routine
+        let alpn_protocols = self.alpn_protocols.clone();
         builder.set_alpn_select_callback(move |_: &mut SslRef, list: &[u8]| {
-            openssl::ssl::select_next_proto(PROTOS, list).ok_or(openssl::ssl::AlpnError::NOACK)
+            select_alpn_protocol(&alpn_protocols, list).ok_or(openssl::ssl::AlpnError::NOACK)
         });
         Ok(builder)
     }
 }
 
+/// Selects the most preferred protocol of the server which is also in the
+/// list sent by the client in the wire format.
+fn select_alpn_protocol<'a>(protocols: &[Vec<u8>], mut list: &'a [u8]) -> Option<&'a [u8]> {
+    let mut client_protocols = Vec::new();
+    while let Some((&len, rest)) = list.split_first() {
+        if rest.len() < len as usize {
+            break;
+        }
+        let (protocol, rest) = rest.split_at(len as usize);
+        client_protocols.push(protocol);
+        list = rest;
+    }
+
+    protocols.iter().find_map(|protocol| {
+        client_protocols
+            .iter()
+            .find(|client_protocol| **client_protocol == protocol.as_slice())
+            .copied()
+    })
+}
+
 impl<T> IntoTlsConfigStream<OpensslTlsConfig> for T
 where
     T: Stream<Item = OpensslTlsConfig> + Send + 'static,
@@ -219,21 +277,73 @@ where
                         Some(tls_acceptor) => tls_acceptor.clone(),
                         None => return Err(IoError::new(ErrorKind::Other, "no valid tls config.")),
                     };
-                    let fut = async move {
-                        let ssl = Ssl::new(tls_acceptor.context()).map_err(|err|
-                            IoError::new(ErrorKind::Other, err.to_string()))?;
-                        let mut tls_stream = SslStream::new(ssl, stream).map_err(|err|
-                            IoError::new(ErrorKind::Other, err.to_string()))?;
-                        use std::pin::Pin;
-                        Pin::new(&mut tls_stream).accept().await.map_err(|err|
-                            IoError::new(ErrorKind::Other, err.to_string()))?;
-                        Ok(tls_stream) };
-                    let stream = HandshakeStream::new(fut);
+                    let tls_info = TlsInfoSlot::default();
+                    let fut = {
+                        let tls_info = tls_info.clone();
+                        async move {
+                            let ssl = Ssl::new(tls_acceptor.context()).map_err(|err|
+                                IoError::new(ErrorKind::Other, err.to_string()))?;
+                            let mut tls_stream = SslStream::new(ssl, stream).map_err(|err|
+                                IoError::new(ErrorKind::Other, err.to_string()))?;
+                            use std::pin::Pin;
+                            Pin::new(&mut tls_stream).accept().await.map_err(|err|
+                                IoError::new(ErrorKind::Other, err.to_string()))?;
+                            tls_info.set_info(connection_info(tls_stream.ssl()));
+                            Ok(tls_stream)
+                        }
+                    };
+                    let stream = HandshakeStream::new(fut).with_tls_info(tls_info);
                     return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
                 }
             }
         }
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        io.tls_info()
+    }
+}
+
+/// Returns the parameters negotiated in the handshake of the connection.
+fn connection_info(ssl: &SslRef) -> TlsInfo {
+    let mut info = TlsInfo::new();
+    let version = match ssl.version_str() {
+        "TLSv1" => Some(TlsVersion::Tls1_0),
+        "TLSv1.1" => Some(TlsVersion::Tls1_1),
+        "TLSv1.2" => Some(TlsVersion::Tls1_2),
+        "TLSv1.3" => Some(TlsVersion::Tls1_3),
+        _ => None,
+    };
+    if let Some(version) = version {
+        info = info.with_version(version);
+    }
+    if let Some(name) = ssl
+        .current_cipher()
+        .map(|cipher| cipher.standard_name().unwrap_or_else(|| cipher.name()))
+    {
+        info = info.with_cipher_suite(name);
+    }
+    if let Some(server_name) = ssl.servername(NameType::HOST_NAME) {
+        info = info.with_server_name(server_name);
+    }
+    if let Some(protocol) = ssl.selected_alpn_protocol() {
+        info = info.with_alpn_protocol(AlpnProtocol(protocol.to_vec()));
+    }
+    if let Some(cert) = ssl.peer_certificate() {
+        // the chain of the server side does not contain the peer certificate
+        let chain = std::iter::once(cert.to_der())
+            .chain(
+                ssl.peer_cert_chain()
+                    .into_iter()
+                    .flatten()
+                    .map(|cert| cert.to_der()),
+            )
+            .collect::<Result<Vec<_>, _>>();
+        if let Ok(chain) = chain {
+            info = info.with_peer_cert(ClientCert::new(chain));
+        }
+    }
+    info
 }
 
 #[cfg(test)]
@@ -0,0 +1,299 @@
+use std::{
+    io::{Error as IoError, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use http::uri::Scheme;
+use tokio::{
+    io::{AsyncReadExt, BufReader, Result as IoResult},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::{
+    listener::{Acceptor, Listener, TlsInfoSlot},
+    web::{LocalAddr, RemoteAddr},
+};
+
+/// The signature of the PROXY protocol version 2 header.
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The maximum length of the PROXY protocol version 1 header.
+const V1_MAX_LENGTH: usize = 107;
+
+/// How long to wait for the PROXY protocol header by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A wrapper around an underlying listener which parses the [PROXY protocol](https://www.haproxy.org/download/2.6/doc/proxy-protocol.txt)
+/// header sent by the proxy, such as HAProxy or AWS Network Load Balancer,
+/// and uses the original client address as the [`RemoteAddr`].
+///
+/// Both version 1 and version 2 of the PROXY protocol are supported. The
+/// connections without a valid header are closed, so the proxy must be
+/// configured to send the header.
+pub struct ProxyProtocolListener<T> {
+    inner: T,
+    timeout: Duration,
+}
+
+impl<T> ProxyProtocolListener<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self {
+            inner,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the timeout for reading the PROXY protocol header, default is 5
+    /// seconds.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Listener> Listener for ProxyProtocolListener<T> {
+    type Acceptor = ProxyProtocolAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Ok(ProxyProtocolAcceptor {
+            inner: self.inner.into_acceptor().await?,
+            timeout: self.timeout,
+            tx,
+            rx,
+        })
+    }
+}
+
+type Accepted<Io> = (BufReader<Io>, LocalAddr, RemoteAddr, Scheme);
+
+/// A acceptor that parses the PROXY protocol header.
+pub struct ProxyProtocolAcceptor<T: Acceptor> {
+    inner: T,
+    timeout: Duration,
+    tx: UnboundedSender<Accepted<T::Io>>,
+    rx: UnboundedReceiver<Accepted<T::Io>>,
+}
+
+#[async_trait::async_trait]
+impl<T: Acceptor> Acceptor for ProxyProtocolAcceptor<T> {
+    type Io = BufReader<T::Io>;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        loop {
+            tokio::select! {
+                res = self.inner.accept() => {
+                    let (stream, local_addr, remote_addr, scheme) = res?;
+                    let timeout = self.timeout;
+                    let tx = self.tx.clone();
+
+                    // read the header in a separate task, so that a slow client
+                    // does not block the other connections
+                    tokio::spawn(async move {
+                        let mut stream = BufReader::new(stream);
+                        match tokio::time::timeout(timeout, read_header(&mut stream)).await {
+                            Ok(Ok(addr)) => {
+                                let remote_addr = addr
+                                    .map(|addr| RemoteAddr(addr.into()))
+                                    .unwrap_or(remote_addr);
+                                let _ = tx.send((stream, local_addr, remote_addr, scheme));
+                            }
+                            Ok(Err(err)) => {
+                                tracing::debug!(
+                                    remote_addr = %remote_addr,
+                                    error = %err,
+                                    "invalid proxy protocol header"
+                                );
+                            }
+                            Err(_) => {
+                                tracing::debug!(
+                                    remote_addr = %remote_addr,
+                                    "timeout reading proxy protocol header"
+                                );
+                            }
+                        }
+                    });
+                }
+                Some(accepted) = self.rx.recv() => return Ok(accepted),
+            }
+        }
+    }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        self.inner.tls_info(io.get_ref())
+    }
+}
+
+fn invalid_header(msg: &'static str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg)
+}
+
+/// Reads the PROXY protocol header, returns the source address, or `None` if
+/// the header does not contain the address of the client.
+async fn read_header<R: AsyncReadExt + Unpin>(reader: &mut R) -> IoResult<Option<SocketAddr>> {
+    // the shortest header `PROXY UNKNOWN\r\n` is longer than the signature of
+    // version 2, so this does not read the data after the header
+    let mut signature = [0; 12];
+    reader.read_exact(&mut signature).await?;
+
+    if &signature == V2_SIGNATURE {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header).await?;
+        let mut data = vec![0; u16::from_be_bytes([header[2], header[3]]) as usize];
+        reader.read_exact(&mut data).await?;
+        parse_v2(header[0], header[1], &data)
+    } else if signature.starts_with(b"PROXY ") {
+        let mut line = signature.to_vec();
+        while !line.ends_with(b"\r\n") {
+            if line.len() >= V1_MAX_LENGTH {
+                return Err(invalid_header("proxy protocol header too long"));
+            }
+            line.push(reader.read_u8().await?);
+        }
+        parse_v1(&line[..line.len() - 2])
+    } else {
+        Err(invalid_header("missing proxy protocol header"))
+    }
+}
+
+/// Parses the version 1 header without the trailing CRLF.
+fn parse_v1(line: &[u8]) -> IoResult<Option<SocketAddr>> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid_header("invalid header"))?;
+    let mut parts = line.split(' ').skip(1);
+
+    match parts.next() {
+        Some("TCP4" | "TCP6") => {}
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid_header("unsupported protocol")),
+    }
+
+    let src_ip = parts
+        .next()
+        .and_then(|ip| ip.parse::<IpAddr>().ok())
+        .ok_or_else(|| invalid_header("invalid source address"))?;
+    let _dst_ip = parts.next();
+    let src_port = parts
+        .next()
+        .and_then(|port| port.parse::<u16>().ok())
+        .ok_or_else(|| invalid_header("invalid source port"))?;
+    Ok(Some(SocketAddr::new(src_ip, src_port)))
+}
+
+/// Parses the version 2 header.
+fn parse_v2(version_command: u8, family: u8, data: &[u8]) -> IoResult<Option<SocketAddr>> {
+    if version_command >> 4 != 2 {
+        return Err(invalid_header("unsupported version"));
+    }
+    match version_command & 0x0f {
+        // LOCAL, the connection was established by the proxy itself
+        0x0 => return Ok(None),
+        // PROXY
+        0x1 => {}
+        _ => return Err(invalid_header("unsupported command")),
+    }
+
+    let read_port = |data: &[u8]| u16::from_be_bytes([data[0], data[1]]);
+    match family >> 4 {
+        // AF_INET
+        0x1 if data.len() >= 12 => {
+            let ip = Ipv4Addr::new(data[0], data[1], data[2], data[3]);
+            Ok(Some(SocketAddr::new(ip.into(), read_port(&data[8..]))))
+        }
+        // AF_INET6
+        0x2 if data.len() >= 36 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&data[..16]);
+            let ip = Ipv6Addr::from(octets);
+            Ok(Some(SocketAddr::new(ip.into(), read_port(&data[32..]))))
+        }
+        0x1 | 0x2 => Err(invalid_header("invalid address length")),
+        // AF_UNSPEC or AF_UNIX
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::listener::TcpListener;
+
+    #[tokio::test]
+    async fn header_v1() {
+        let addr = read_header(&mut &b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET"[..])
+            .await
+            .unwrap();
+        assert_eq!(addr, Some("192.168.0.1:56324".parse().unwrap()));
+
+        let addr = read_header(&mut &b"PROXY TCP6 ::1 ::2 56324 443\r\n"[..])
+            .await
+            .unwrap();
+        assert_eq!(addr, Some("[::1]:56324".parse().unwrap()));
+
+        let addr = read_header(&mut &b"PROXY UNKNOWN\r\n"[..]).await.unwrap();
+        assert_eq!(addr, None);
+
+        assert!(read_header(&mut &b"GET / HTTP/1.1\r\n"[..]).await.is_err());
+        assert!(read_header(&mut &b"PROXY TCP4 a b c d\r\n"[..])
+            .await
+            .is_err());
+        assert!(read_header(&mut &[b'P'; 200][..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn header_v2() {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x21, 0x11, 0x00, 0x0c]);
+        data.extend_from_slice(&[192, 168, 0, 1, 192, 168, 0, 11, 0xdc, 0x04, 0x01, 0xbb]);
+        let addr = read_header(&mut data.as_slice()).await.unwrap();
+        assert_eq!(addr, Some("192.168.0.1:56324".parse().unwrap()));
+
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        assert_eq!(read_header(&mut data.as_slice()).await.unwrap(), None);
+
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x21, 0x11, 0x00, 0x04, 0, 0, 0, 0]);
+        assert!(read_header(&mut data.as_slice()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn proxy_protocol_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").proxy_protocol();
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().remove(0);
+
+        tokio::spawn(async move {
+            let addr = *local_addr.as_socket_addr().unwrap();
+
+            // a connection without the header is closed
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n")
+                .await
+                .unwrap();
+            stream.write_i32(10).await.unwrap();
+            stream.read_u8().await.ok();
+        });
+
+        let (mut stream, _, remote_addr, _) = acceptor.accept().await.unwrap();
+        assert_eq!(
+            remote_addr,
+            RemoteAddr(SocketAddr::from(([192, 168, 0, 1], 56324)).into())
+        );
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+    }
+}
@@ -1,11 +1,19 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use futures_util::{
     stream::{BoxStream, Chain, Pending},
-    Stream, StreamExt,
+    Stream, StreamExt, TryFutureExt,
 };
 use http::uri::Scheme;
-use tokio::io::{Error as IoError, ErrorKind, Result as IoResult};
+use parking_lot::{Mutex, RwLock};
+use tokio::io::{AsyncRead, AsyncWrite, Error as IoError, ErrorKind, Result as IoResult};
 use tokio_rustls::{
     rustls::{
         server::{
@@ -13,17 +21,27 @@ use tokio_rustls::{
             NoClientAuth, ResolvesServerCert,
         },
         sign::{self, CertifiedKey},
-        Certificate, PrivateKey, RootCertStore, ServerConfig,
+        Certificate, KeyLog, KeyLogFile, PrivateKey, ProtocolVersion, RootCertStore, ServerConfig,
+        ServerConnection,
     },
     server::TlsStream,
+    LazyConfigAcceptor,
 };
 
 use crate::{
-    listener::{Acceptor, HandshakeStream, IntoTlsConfigStream, Listener},
-    web::{LocalAddr, RemoteAddr},
+    listener::{
+        session_ticket::{RotatingKeys, Ticketer, DEFAULT_ROTATION_INTERVAL},
+        Acceptor, HandshakeStream, IntoTlsConfigStream, Listener, SessionTicketKeyProvider,
+        TlsInfoSlot,
+    },
+    web::{AlpnProtocol, ClientCert, LocalAddr, RemoteAddr, TlsInfo, TlsVersion},
 };
 
+/// How often the files are checked by [`RustlsConfig::watch`].
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
 #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+#[derive(Clone)]
 enum TlsClientAuth {
     Off,
     Optional(Vec<u8>),
@@ -32,7 +50,7 @@ enum TlsClientAuth {
 
 /// Rustls certificate
 #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct RustlsCertificate {
     cert: Vec<u8>,
     key: Vec<u8>,
@@ -112,12 +130,42 @@ impl RustlsCertificate {
     }
 }
 
+/// A resolver that loads the certificate for the SNI name on demand.
+///
+/// This is useful if there are too many certificates to be configured up
+/// front, for example the certificates of the custom domains stored in a
+/// database.
+#[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+#[async_trait::async_trait]
+pub trait RustlsCertificateResolver: Send + Sync + 'static {
+    /// Returns the certificate for the SNI name sent by the client.
+    ///
+    /// This method is called for each TLS handshake with a SNI name, so the
+    /// implementation should cache the certificates if loading them is
+    /// expensive. If `None` is returned, the certificates in the
+    /// [`RustlsConfig`] are used.
+    async fn resolve(&self, server_name: &str) -> IoResult<Option<RustlsCertificate>>;
+}
+
 /// Rustls Config.
 #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+#[derive(Clone)]
 pub struct RustlsConfig {
     certificates: HashMap<String, RustlsCertificate>,
     fallback: Option<RustlsCertificate>,
+    resolver: Option<Arc<dyn RustlsCertificateResolver>>,
     client_auth: TlsClientAuth,
+    session_ticket_keys: Option<Arc<dyn SessionTicketKeyProvider>>,
+    alpn_protocols: Vec<Vec<u8>>,
+    key_log: Option<KeyLogTarget>,
+    #[cfg(feature = "ocsp")]
+    ocsp_stapling: bool,
+}
+
+#[derive(Clone)]
+enum KeyLogTarget {
+    Env,
+    File(PathBuf),
 }
 
 impl Default for RustlsConfig {
@@ -132,7 +180,13 @@ impl RustlsConfig {
         Self {
             certificates: HashMap::new(),
             fallback: Default::default(),
+            resolver: None,
             client_auth: TlsClientAuth::Off,
+            session_ticket_keys: None,
+            alpn_protocols: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            key_log: None,
+            #[cfg(feature = "ocsp")]
+            ocsp_stapling: false,
         }
     }
 
@@ -210,7 +264,41 @@ impl RustlsConfig {
         self
     }
 
+    /// Sets the resolver to load the certificate for the SNI name on demand.
+    ///
+    /// The resolver takes precedence over the certificates added by
+    /// [`RustlsConfig::certificate`] and [`RustlsConfig::fallback`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use poem::listener::{
+    ///     Listener, RustlsCertificate, RustlsCertificateResolver, RustlsConfig, TcpListener,
+    /// };
+    ///
+    /// struct DatabaseResolver;
+    ///
+    /// #[poem::async_trait]
+    /// impl RustlsCertificateResolver for DatabaseResolver {
+    ///     async fn resolve(&self, server_name: &str) -> std::io::Result<Option<RustlsCertificate>> {
+    ///         // query the certificate of `server_name` from the database
+    ///         # todo!()
+    ///     }
+    /// }
+    ///
+    /// let listener =
+    ///     TcpListener::bind("127.0.0.1:3000").rustls(RustlsConfig::new().resolver(DatabaseResolver));
+    /// ```
+    #[must_use]
+    pub fn resolver(mut self, resolver: impl RustlsCertificateResolver) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
     /// Sets the trust anchor for optional client authentication.
+    ///
+    /// The verified certificate chain of the client can be obtained with the
+    /// `Option<ClientCert>` extractor.
     #[must_use]
     pub fn client_auth_optional(mut self, trust_anchor: impl Into<Vec<u8>>) -> Self {
         self.client_auth = TlsClientAuth::Optional(trust_anchor.into());
@@ -218,25 +306,235 @@ impl RustlsConfig {
     }
 
     /// Sets the trust anchor for required client authentication.
+    ///
+    /// The verified certificate chain of the client can be obtained with the
+    /// [`ClientCert`] extractor.
     #[must_use]
     pub fn client_auth_required(mut self, trust_anchor: impl Into<Vec<u8>>) -> Self {
         self.client_auth = TlsClientAuth::Required(trust_anchor.into());
         self
     }
 
-    fn create_server_config(&self) -> IoResult<ServerConfig> {
+    /// Sets the protocols advertised with ALPN in order of preference, default
+    /// is `h2` and `http/1.1`.
+    ///
+    /// The negotiated protocol is available to the handlers as
+    /// [`AlpnProtocol`](crate::web::AlpnProtocol) in the request extensions.
+    #[must_use]
+    pub fn alpn_protocols<I, P>(self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Vec<u8>>,
+    {
+        Self {
+            alpn_protocols: protocols.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Enable or disable HTTP/2, default is `true`.
+    ///
+    /// If disabled, `h2` is not advertised with ALPN, so the clients use
+    /// HTTP/1.1.
+    #[must_use]
+    pub fn http2(mut self, enabled: bool) -> Self {
+        let is_h2 = |protocol: &Vec<u8>| protocol == b"h2";
+        if !enabled {
+            self.alpn_protocols.retain(|protocol| !is_h2(protocol));
+        } else if !self.alpn_protocols.iter().any(is_h2) {
+            self.alpn_protocols.insert(0, b"h2".to_vec());
+        }
+        self
+    }
+
+    /// Write the TLS secrets to the file specified by the `SSLKEYLOGFILE`
+    /// environment variable, so that the traffic can be decrypted by tools
+    /// such as Wireshark. Does nothing if the environment variable is not set.
+    ///
+    /// **NOTE**: This compromises the security of the connections, and should
+    /// only be used for debugging.
+    #[must_use]
+    pub fn key_log_from_env(self) -> Self {
+        Self {
+            key_log: Some(KeyLogTarget::Env),
+            ..self
+        }
+    }
+
+    /// Write the TLS secrets to the specified file in the [NSS key log format](https://developer.mozilla.org/en-US/docs/Mozilla/Projects/NSS/Key_Log_Format),
+    /// so that the traffic can be decrypted by tools such as Wireshark.
+    ///
+    /// **NOTE**: This compromises the security of the connections, and should
+    /// only be used for debugging.
+    #[must_use]
+    pub fn key_log_file(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            key_log: Some(KeyLogTarget::File(path.into())),
+            ..self
+        }
+    }
+
+    /// Enable or disable the TLS session tickets, default is `false`.
+    ///
+    /// If enabled, the clients can resume the sessions with the tickets
+    /// issued by the server. The ticket keys are generated randomly and
+    /// rotated every 6 hours.
+    #[must_use]
+    pub fn session_tickets(self, enabled: bool) -> Self {
+        if enabled {
+            self.session_ticket_rotation(DEFAULT_ROTATION_INTERVAL)
+        } else {
+            Self {
+                session_ticket_keys: None,
+                ..self
+            }
+        }
+    }
+
+    /// Enable the TLS session tickets, and rotate the ticket keys every
+    /// `interval`.
+    ///
+    /// The tickets can be used to resume the sessions for up to twice the
+    /// interval.
+    #[must_use]
+    pub fn session_ticket_rotation(self, interval: Duration) -> Self {
+        Self {
+            session_ticket_keys: Some(Arc::new(RotatingKeys::new(interval))),
+            ..self
+        }
+    }
+
+    /// Enable the TLS session tickets with the keys from the provider.
+    ///
+    /// This allows the sessions to be resumed across the instances which
+    /// share the same keys.
+    #[must_use]
+    pub fn session_ticket_keys(self, provider: impl SessionTicketKeyProvider) -> Self {
+        Self {
+            session_ticket_keys: Some(Arc::new(provider)),
+            ..self
+        }
+    }
+
+    /// Enable or disable OCSP stapling, default is `false`.
+    ///
+    /// If enabled, the OCSP responses of the certificates are fetched from
+    /// the responders specified in the certificates, and refreshed in the
+    /// background before they expire. The certificate chain must include the
+    /// issuer certificate.
+    #[cfg(feature = "ocsp")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ocsp")))]
+    #[must_use]
+    pub fn ocsp_stapling(mut self, enabled: bool) -> Self {
+        self.ocsp_stapling = enabled;
+        self
+    }
+
+    /// Watch the certificate and private key files, and reload the fallback
+    /// certificate when they are changed.
+    ///
+    /// The files are checked every 5 seconds, and a changed certificate is
+    /// loaded after the files have not been modified for one check, so that a
+    /// certificate and a key which are being written are not mixed up. The
+    /// existing connections are not affected by the reloading.
+    ///
+    /// This is useful if the certificates are managed by an external tool such
+    /// as `certbot`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use poem::listener::{Listener, RustlsConfig, TcpListener};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:3000").rustls(RustlsConfig::new().watch(
+    ///     "/etc/letsencrypt/live/example.com/fullchain.pem",
+    ///     "/etc/letsencrypt/live/example.com/privkey.pem",
+    /// ));
+    /// ```
+    pub fn watch(
+        self,
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+    ) -> impl Stream<Item = RustlsConfig> + Send + 'static {
+        self.watch_with_interval(cert_path.into(), key_path.into(), WATCH_INTERVAL)
+    }
+
+    fn watch_with_interval(
+        self,
+        cert_path: PathBuf,
+        key_path: PathBuf,
+        interval: Duration,
+    ) -> impl Stream<Item = RustlsConfig> + Send + 'static {
+        struct WatchState {
+            config: RustlsConfig,
+            paths: Arc<(PathBuf, PathBuf)>,
+            loaded: Option<FileStamps>,
+            seen: Option<FileStamps>,
+        }
+
+        let state = WatchState {
+            config: self,
+            paths: Arc::new((cert_path, key_path)),
+            loaded: None,
+            seen: None,
+        };
+
+        futures_util::stream::unfold(state, move |mut state| async move {
+            loop {
+                if state.loaded.is_some() {
+                    tokio::time::sleep(interval).await;
+                }
+
+                let paths = state.paths.clone();
+                let (loaded, seen) = (state.loaded, state.seen);
+                let res = tokio::task::spawn_blocking(move || {
+                    let stamps = (file_stamp(&paths.0)?, file_stamp(&paths.1)?);
+                    // wait until the files are not being modified
+                    if loaded == Some(stamps) || (loaded.is_some() && seen != Some(stamps)) {
+                        return Ok((stamps, None));
+                    }
+                    let data = (std::fs::read(&paths.0)?, std::fs::read(&paths.1)?);
+                    Ok::<_, IoError>((stamps, Some(data)))
+                })
+                .await
+                .map_err(|err| IoError::new(ErrorKind::Other, err))
+                .and_then(|res| res);
+
+                match res {
+                    Ok((stamps, None)) => state.seen = Some(stamps),
+                    Ok((stamps, Some((cert, key)))) => {
+                        state.loaded = Some(stamps);
+                        state.seen = Some(stamps);
+                        let config = state
+                            .config
+                            .clone()
+                            .fallback(RustlsCertificate::new().cert(cert).key(key));
+                        return Some((config, state));
+                    }
+                    Err(err) => {
+                        tracing::error!(error = %err, "failed to read the tls certificate files");
+                        if state.loaded.is_none() {
+                            tokio::time::sleep(interval).await;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn create_server_config(&self) -> IoResult<(ServerConfig, Arc<ResolveServerCert>)> {
         let fallback = self
             .fallback
             .as_ref()
             .map(|fallback| fallback.create_certificate_key())
             .transpose()?
-            .map(Arc::new);
+            .map(|cert_key| Arc::new(RwLock::new(Arc::new(cert_key))));
         let mut certifcate_keys = HashMap::new();
 
         for (name, certificate) in &self.certificates {
             certifcate_keys.insert(
                 name.clone(),
-                Arc::new(certificate.create_certificate_key()?),
+                Arc::new(RwLock::new(Arc::new(certificate.create_certificate_key()?))),
             );
         }
 
@@ -250,16 +548,58 @@ impl RustlsConfig {
             }
         };
 
+        let cert_resolver = Arc::new(ResolveServerCert {
+            certifcate_keys,
+            fallback,
+        });
         let mut server_config = ServerConfig::builder()
             .with_safe_defaults()
             .with_client_cert_verifier(client_auth)
-            .with_cert_resolver(Arc::new(ResolveServerCert {
-                certifcate_keys,
-                fallback,
-            }));
-        server_config.alpn_protocols = vec!["h2".into(), "http/1.1".into()];
+            .with_cert_resolver(cert_resolver.clone());
+        server_config.alpn_protocols = self.alpn_protocols.clone();
+        if let Some(keys) = &self.session_ticket_keys {
+            server_config.ticketer = Arc::new(Ticketer(keys.clone()));
+        }
+        match &self.key_log {
+            Some(KeyLogTarget::Env) => server_config.key_log = Arc::new(KeyLogFile::new()),
+            Some(KeyLogTarget::File(path)) => {
+                server_config.key_log = Arc::new(KeyLogWriter::open(path)?)
+            }
+            None => {}
+        }
+
+        Ok((server_config, cert_resolver))
+    }
+}
+
+type FileStamps = ((SystemTime, u64), (SystemTime, u64));
+
+fn file_stamp(path: &Path) -> IoResult<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
 
-        Ok(server_config)
+/// Appends the TLS secrets to a file in the NSS key log format.
+struct KeyLogWriter(Mutex<File>);
+
+impl KeyLogWriter {
+    fn open(path: &Path) -> IoResult<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(Self(Mutex::new(file)))
+    }
+}
+
+impl KeyLog for KeyLogWriter {
+    fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
+        let to_hex = |data: &[u8]| {
+            data.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        let line = format!("{} {} {}\n", label, to_hex(client_random), to_hex(secret));
+        if let Err(err) = self.0.lock().write_all(line.as_bytes()) {
+            tracing::warn!(error = %err, "failed to write the tls key log");
+        }
     }
 }
 
@@ -338,7 +678,8 @@ impl<T: Listener, S: IntoTlsConfigStream<RustlsConfig>> Listener for RustlsListe
 pub struct RustlsAcceptor<T, S> {
     inner: T,
     config_stream: Chain<S, Pending<RustlsConfig>>,
-    current_tls_acceptor: Option<tokio_rustls::TlsAcceptor>,
+    current_server_config: Option<Arc<ServerConfig>>,
+    current_resolver: Option<Arc<dyn RustlsCertificateResolver>>,
 }
 
 impl<T, S> RustlsAcceptor<T, S>
@@ -349,7 +690,8 @@ where
         RustlsAcceptor {
             inner,
             config_stream: config_stream.chain(futures_util::stream::pending()),
-            current_tls_acceptor: None,
+            current_server_config: None,
+            current_resolver: None,
         }
     }
 }
@@ -372,13 +714,18 @@ where
                 res = self.config_stream.next() => {
                     if let Some(tls_config) = res {
                         match tls_config.create_server_config() {
-                            Ok(server_config) => {
-                                if self.current_tls_acceptor.is_some() {
+                            Ok((server_config, _cert_resolver)) => {
+                                #[cfg(feature = "ocsp")]
+                                if tls_config.ocsp_stapling {
+                                    spawn_ocsp_refresh(&_cert_resolver);
+                                }
+                                if self.current_server_config.is_some() {
                                     tracing::info!("tls config changed.");
                                 } else {
                                     tracing::info!("tls config loaded.");
                                 }
-                                self.current_tls_acceptor = Some(tokio_rustls::TlsAcceptor::from(Arc::new(server_config)));
+                                self.current_server_config = Some(Arc::new(server_config));
+                                self.current_resolver = tls_config.resolver;
 
                             },
                             Err(err) => tracing::error!(error = %err, "invalid tls config."),
@@ -389,33 +736,182 @@ where
                 }
                 res = self.inner.accept() => {
                     let (stream, local_addr, remote_addr, _) = res?;
-                    let tls_acceptor = match &self.current_tls_acceptor {
-                        Some(tls_acceptor) => tls_acceptor,
+                    let server_config = match &self.current_server_config {
+                        Some(server_config) => server_config.clone(),
                         None => return Err(IoError::new(ErrorKind::Other, "no valid tls config.")),
                     };
+                    let resolver = self.current_resolver.clone();
+                    let handshake = async move {
+                        match resolver {
+                            Some(resolver) => accept_with_resolver(stream, server_config, &*resolver).await,
+                            None => tokio_rustls::TlsAcceptor::from(server_config).accept(stream).await,
+                        }
+                    };
 
-                    let stream = HandshakeStream::new(tls_acceptor.accept(stream));
+                    let tls_info = TlsInfoSlot::default();
+                    let stream = HandshakeStream::new(handshake.map_ok({
+                        let tls_info = tls_info.clone();
+                        move |stream| {
+                            tls_info.set_info(connection_info(stream.get_ref().1));
+                            stream
+                        }
+                    }))
+                    .with_tls_info(tls_info);
                     return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
                 }
             }
         }
     }
+
+    fn tls_info(&self, io: &Self::Io) -> Option<TlsInfoSlot> {
+        io.tls_info()
+    }
+}
+
+/// Returns the parameters negotiated in the handshake of the connection.
+pub(crate) fn connection_info(conn: &ServerConnection) -> TlsInfo {
+    let mut info = TlsInfo::new();
+    let version = match conn.protocol_version() {
+        Some(ProtocolVersion::TLSv1_0) => Some(TlsVersion::Tls1_0),
+        Some(ProtocolVersion::TLSv1_1) => Some(TlsVersion::Tls1_1),
+        Some(ProtocolVersion::TLSv1_2) => Some(TlsVersion::Tls1_2),
+        Some(ProtocolVersion::TLSv1_3) => Some(TlsVersion::Tls1_3),
+        _ => None,
+    };
+    if let Some(version) = version {
+        info = info.with_version(version);
+    }
+    if let Some(name) = conn
+        .negotiated_cipher_suite()
+        .and_then(|suite| suite.suite().as_str())
+    {
+        // rustls prefixes the names of the TLS 1.3 suites with `TLS13_`
+        info = info.with_cipher_suite(match name.strip_prefix("TLS13_") {
+            Some(name) => format!("TLS_{}", name),
+            None => name.to_string(),
+        });
+    }
+    if let Some(server_name) = conn.sni_hostname() {
+        info = info.with_server_name(server_name);
+    }
+    if let Some(protocol) = conn.alpn_protocol() {
+        info = info.with_alpn_protocol(AlpnProtocol(protocol.to_vec()));
+    }
+    if let Some(certs) = conn.peer_certificates().filter(|certs| !certs.is_empty()) {
+        info = info.with_peer_cert(ClientCert::new(
+            certs.iter().map(|cert| cert.0.clone()).collect(),
+        ));
+    }
+    info
+}
+
+/// Reads the client hello, and completes the handshake with the certificate
+/// returned by the resolver if there is one.
+async fn accept_with_resolver<IO>(
+    stream: IO,
+    server_config: Arc<ServerConfig>,
+    resolver: &dyn RustlsCertificateResolver,
+) -> IoResult<TlsStream<IO>>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    let start = LazyConfigAcceptor::new(Default::default(), stream).await?;
+    let server_name = start.client_hello().server_name().map(ToString::to_string);
+    let certificate = match server_name {
+        Some(server_name) => resolver.resolve(&server_name).await?,
+        None => None,
+    };
+
+    let server_config = match certificate {
+        Some(certificate) => {
+            let cert_resolver = ResolveServerCert {
+                certifcate_keys: HashMap::new(),
+                fallback: Some(Arc::new(RwLock::new(Arc::new(
+                    certificate.create_certificate_key()?,
+                )))),
+            };
+            let mut server_config = (*server_config).clone();
+            server_config.cert_resolver = Arc::new(cert_resolver);
+            Arc::new(server_config)
+        }
+        None => server_config,
+    };
+    start.into_stream(server_config).await
 }
 
+type SharedCertifiedKey = Arc<RwLock<Arc<CertifiedKey>>>;
+
 struct ResolveServerCert {
-    certifcate_keys: HashMap<String, Arc<CertifiedKey>>,
-    fallback: Option<Arc<CertifiedKey>>,
+    certifcate_keys: HashMap<String, SharedCertifiedKey>,
+    fallback: Option<SharedCertifiedKey>,
+}
+
+impl ResolveServerCert {
+    #[cfg(feature = "ocsp")]
+    fn all_certificate_keys(&self) -> impl Iterator<Item = &SharedCertifiedKey> {
+        self.fallback.iter().chain(self.certifcate_keys.values())
+    }
 }
 
 impl ResolvesServerCert for ResolveServerCert {
     fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
         client_hello
             .server_name()
-            .and_then(|name| self.certifcate_keys.get(name).map(Arc::clone))
-            .or_else(|| self.fallback.clone())
+            .and_then(|name| self.certifcate_keys.get(name))
+            .or(self.fallback.as_ref())
+            .map(|cert_key| cert_key.read().clone())
     }
 }
 
+/// Fetch the OCSP responses of the certificates, and refresh them in the
+/// background until the resolver is dropped.
+#[cfg(feature = "ocsp")]
+fn spawn_ocsp_refresh(cert_resolver: &Arc<ResolveServerCert>) {
+    use std::time::SystemTime;
+
+    use crate::listener::ocsp;
+
+    let mut refresh_at = cert_resolver
+        .all_certificate_keys()
+        .map(|_| Some(SystemTime::now()))
+        .collect::<Vec<_>>();
+    let weak_cert_resolver = Arc::downgrade(cert_resolver);
+
+    tokio::spawn(async move {
+        while let Some(cert_resolver) = weak_cert_resolver.upgrade() {
+            let now = SystemTime::now();
+            for (cert_key, refresh_at) in cert_resolver.all_certificate_keys().zip(&mut refresh_at)
+            {
+                if !matches!(refresh_at, Some(refresh_at) if *refresh_at <= now) {
+                    continue;
+                }
+
+                let current = cert_key.read().clone();
+                *refresh_at = match ocsp::fetch(&current.cert).await {
+                    Ok(Some(staple)) => {
+                        *cert_key.write() = ocsp::staple(&current, staple.response);
+                        Some(staple.refresh_at)
+                    }
+                    Ok(None) => None,
+                    Err(err) => {
+                        tracing::warn!(error = %err, "failed to fetch ocsp response");
+                        Some(now + ocsp::RETRY_INTERVAL)
+                    }
+                };
+            }
+            drop(cert_resolver);
+
+            match refresh_at.iter().flatten().min() {
+                Some(next) => {
+                    let delay = next.duration_since(SystemTime::now()).unwrap_or_default();
+                    tokio::time::sleep(delay).await;
+                }
+                None => break,
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use tokio::{
@@ -458,5 +954,189 @@ mod tests {
 
         let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
         assert_eq!(stream.read_i32().await.unwrap(), 10);
+        let tls_info = acceptor.tls_info(&stream).unwrap();
+        assert!(tls_info.client_cert().is_none());
+        let info = tls_info.info().unwrap();
+        assert_eq!(info.version(), Some(TlsVersion::Tls1_3));
+        assert!(info.cipher_suite().unwrap().starts_with("TLS_"));
+        assert_eq!(info.server_name(), Some("testserver.com"));
+    }
+
+    #[tokio::test]
+    async fn alpn_protocols() {
+        for (config, expected) in [
+            (RustlsConfig::new(), Some(b"h2".as_ref())),
+            (RustlsConfig::new().http2(false), Some(b"http/1.1".as_ref())),
+            (RustlsConfig::new().alpn_protocols(["foo"]), None),
+        ] {
+            let listener = TcpListener::bind("127.0.0.1:0").rustls(
+                config.fallback(
+                    RustlsCertificate::new()
+                        .cert(include_bytes!("certs/cert1.pem").as_ref())
+                        .key(include_bytes!("certs/key1.pem").as_ref()),
+                ),
+            );
+            let mut acceptor = listener.into_acceptor().await.unwrap();
+            let local_addr = acceptor.local_addr().pop().unwrap();
+
+            tokio::spawn(async move {
+                let mut config = ClientConfig::builder()
+                    .with_safe_defaults()
+                    .with_root_certificates(
+                        read_trust_anchor(include_bytes!("certs/chain1.pem")).unwrap(),
+                    )
+                    .with_no_client_auth();
+                config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+                let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+                let domain = ServerName::try_from("testserver.com").unwrap();
+                let stream = TcpStream::connect(*local_addr.as_socket_addr().unwrap())
+                    .await
+                    .unwrap();
+                if let Ok(mut stream) = connector.connect(domain, stream).await {
+                    let _ = stream.write_i32(10).await;
+                }
+            });
+
+            let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+            let tls_info = acceptor.tls_info(&stream).unwrap();
+            match expected {
+                Some(expected) => {
+                    assert_eq!(stream.read_i32().await.unwrap(), 10);
+                    assert_eq!(tls_info.alpn_protocol().unwrap().as_bytes(), expected);
+                }
+                None => {
+                    // no protocol in common, the handshake fails
+                    assert!(stream.read_i32().await.is_err());
+                    assert!(tls_info.alpn_protocol().is_none());
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn key_log_file() {
+        let path = std::env::temp_dir().join(format!("poem-keylog-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let listener = TcpListener::bind("127.0.0.1:0").rustls(
+            RustlsConfig::new()
+                .fallback(
+                    RustlsCertificate::new()
+                        .cert(include_bytes!("certs/cert1.pem").as_ref())
+                        .key(include_bytes!("certs/key1.pem").as_ref()),
+                )
+                .key_log_file(&path),
+        );
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().pop().unwrap();
+
+        tokio::spawn(async move {
+            let config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(
+                    read_trust_anchor(include_bytes!("certs/chain1.pem")).unwrap(),
+                )
+                .with_no_client_auth();
+
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+            let domain = ServerName::try_from("testserver.com").unwrap();
+            let stream = TcpStream::connect(*local_addr.as_socket_addr().unwrap())
+                .await
+                .unwrap();
+            let mut stream = connector.connect(domain, stream).await.unwrap();
+            stream.write_i32(10).await.unwrap();
+        });
+
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+
+        let key_log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(key_log
+            .lines()
+            .any(|line| line.starts_with("CLIENT_HANDSHAKE_TRAFFIC_SECRET ")));
+    }
+
+    #[tokio::test]
+    async fn resolver() {
+        struct TestResolver;
+
+        #[async_trait::async_trait]
+        impl RustlsCertificateResolver for TestResolver {
+            async fn resolve(&self, server_name: &str) -> IoResult<Option<RustlsCertificate>> {
+                Ok((server_name == "testserver.com").then(|| {
+                    RustlsCertificate::new()
+                        .cert(include_bytes!("certs/cert1.pem").as_ref())
+                        .key(include_bytes!("certs/key1.pem").as_ref())
+                }))
+            }
+        }
+
+        let listener =
+            TcpListener::bind("127.0.0.1:0").rustls(RustlsConfig::new().resolver(TestResolver));
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().pop().unwrap();
+
+        tokio::spawn(async move {
+            let config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(
+                    read_trust_anchor(include_bytes!("certs/chain1.pem")).unwrap(),
+                )
+                .with_no_client_auth();
+
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+            for name in ["unknown.com", "testserver.com"] {
+                let domain = ServerName::try_from(name).unwrap();
+                let stream = TcpStream::connect(*local_addr.as_socket_addr().unwrap())
+                    .await
+                    .unwrap();
+                if let Ok(mut stream) = connector.connect(domain, stream).await {
+                    stream.write_i32(10).await.unwrap();
+                }
+            }
+        });
+
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert!(stream.read_i32().await.is_err());
+
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn watch_files() {
+        let dir = std::env::temp_dir().join(format!("poem-rustls-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, include_bytes!("certs/cert1.pem")).unwrap();
+        std::fs::write(&key_path, include_bytes!("certs/key1.pem")).unwrap();
+
+        let mut stream = Box::pin(RustlsConfig::new().watch_with_interval(
+            cert_path.clone(),
+            key_path.clone(),
+            Duration::from_millis(50),
+        ));
+        let config = stream.next().await.unwrap();
+        assert_eq!(
+            config.fallback.as_ref().unwrap().cert,
+            include_bytes!("certs/cert1.pem")
+        );
+        assert!(config.create_server_config().is_ok());
+
+        std::fs::write(&cert_path, b"new cert").unwrap();
+        let config = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.fallback.as_ref().unwrap().cert, b"new cert");
+        assert_eq!(
+            config.fallback.as_ref().unwrap().key,
+            include_bytes!("certs/key1.pem")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,205 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use tokio_rustls::rustls::server::ProducesTickets;
+
+/// The default rotation interval of the session ticket keys.
+pub(crate) const DEFAULT_ROTATION_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Provides the keys to encrypt and decrypt the TLS session tickets.
+///
+/// The instances behind a load balancer can share the same keys, so that a
+/// session established with one instance can be resumed with the others.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::{RustlsConfig, SessionTicketKeyProvider};
+///
+/// struct SharedKeys;
+///
+/// impl SessionTicketKeyProvider for SharedKeys {
+///     fn keys(&self) -> Vec<[u8; 32]> {
+///         // load the keys from the shared storage, the newest one first
+///         vec![[1; 32], [2; 32]]
+///     }
+/// }
+///
+/// let config = RustlsConfig::new().session_ticket_keys(SharedKeys);
+/// ```
+pub trait SessionTicketKeyProvider: Send + Sync + 'static {
+    /// Returns the keys, the first key is used to encrypt the new tickets,
+    /// and all of the keys are used to decrypt the tickets.
+    ///
+    /// If no key is returned, no ticket is issued and the sessions can not
+    /// be resumed.
+    fn keys(&self) -> Vec<[u8; 32]>;
+
+    /// Returns the lifetime of the tickets which is sent to the clients as a
+    /// hint, default is 12 hours.
+    ///
+    /// The keys should be rotated so that the tickets can not be decrypted
+    /// after the lifetime.
+    fn lifetime(&self) -> Duration {
+        Duration::from_secs(12 * 60 * 60)
+    }
+}
+
+/// Generates a random key every interval, and keeps the previous key to
+/// decrypt the tickets issued before the rotation.
+pub(crate) struct RotatingKeys {
+    interval: Duration,
+    keys: Mutex<Option<(Instant, Vec<[u8; 32]>)>>,
+}
+
+impl RotatingKeys {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            keys: Mutex::new(None),
+        }
+    }
+}
+
+impl SessionTicketKeyProvider for RotatingKeys {
+    fn keys(&self) -> Vec<[u8; 32]> {
+        let mut keys = self.keys.lock();
+        let now = Instant::now();
+
+        match &mut *keys {
+            Some((rotated_at, keys)) if now.duration_since(*rotated_at) < self.interval => {
+                keys.clone()
+            }
+            _ => {
+                let mut key = [0; 32];
+                if SystemRandom::new().fill(&mut key).is_err() {
+                    return keys
+                        .as_ref()
+                        .map(|(_, keys)| keys.clone())
+                        .unwrap_or_default();
+                }
+                let mut new_keys = vec![key];
+                new_keys.extend(keys.take().and_then(|(_, keys)| keys.into_iter().next()));
+                *keys = Some((now, new_keys.clone()));
+                new_keys
+            }
+        }
+    }
+
+    fn lifetime(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// Encrypts the session tickets with ChaCha20-Poly1305, the ticket is the
+/// random nonce followed by the ciphertext.
+pub(crate) struct Ticketer(pub(crate) Arc<dyn SessionTicketKeyProvider>);
+
+fn create_key(key: &[u8; 32]) -> Option<LessSafeKey> {
+    UnboundKey::new(&CHACHA20_POLY1305, key)
+        .ok()
+        .map(LessSafeKey::new)
+}
+
+impl ProducesTickets for Ticketer {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn lifetime(&self) -> u32 {
+        self.0.lifetime().as_secs().min(u32::MAX as u64) as u32
+    }
+
+    fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+        let key = create_key(self.0.keys().first()?)?;
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).ok()?;
+
+        let mut data = plain.to_vec();
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .ok()?;
+
+        let mut ticket = nonce.to_vec();
+        ticket.extend(data);
+        Some(ticket)
+    }
+
+    fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
+        if cipher.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, data) = cipher.split_at(NONCE_LEN);
+
+        self.0.keys().iter().find_map(|key| {
+            let key = create_key(key)?;
+            let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+            let mut data = data.to_vec();
+            let len = key
+                .open_in_place(nonce, Aad::empty(), &mut data)
+                .ok()?
+                .len();
+            data.truncate(len);
+            Some(data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedKeys(Vec<[u8; 32]>);
+
+    impl SessionTicketKeyProvider for FixedKeys {
+        fn keys(&self) -> Vec<[u8; 32]> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let old = Ticketer(Arc::new(FixedKeys(vec![[1; 32]])));
+        let new = Ticketer(Arc::new(FixedKeys(vec![[2; 32], [1; 32]])));
+        let other = Ticketer(Arc::new(FixedKeys(vec![[3; 32]])));
+
+        let ticket = old.encrypt(b"session").unwrap();
+        assert_eq!(old.decrypt(&ticket).unwrap(), b"session");
+        assert_eq!(new.decrypt(&ticket).unwrap(), b"session");
+        assert!(other.decrypt(&ticket).is_none());
+        assert!(old.decrypt(&ticket[..ticket.len() - 1]).is_none());
+        assert!(old.decrypt(&[]).is_none());
+
+        let ticket = new.encrypt(b"session").unwrap();
+        assert!(old.decrypt(&ticket).is_none());
+
+        assert!(Ticketer(Arc::new(FixedKeys(vec![])))
+            .encrypt(b"session")
+            .is_none());
+    }
+
+    #[test]
+    fn rotating_keys() {
+        let keys = RotatingKeys::new(Duration::from_millis(50));
+        let first = keys.keys();
+        assert_eq!(first.len(), 1);
+        assert_eq!(keys.keys(), first);
+
+        std::thread::sleep(Duration::from_millis(60));
+        let second = keys.keys();
+        assert_eq!(second.len(), 2);
+        assert_ne!(second[0], first[0]);
+        assert_eq!(second[1], first[0]);
+
+        std::thread::sleep(Duration::from_millis(60));
+        let third = keys.keys();
+        assert_eq!(third.len(), 2);
+        assert_eq!(third[1], second[0]);
+    }
+}
@@ -1,6 +1,14 @@
 use std::io::Result;
+#[cfg(feature = "socket-options")]
+use std::{
+    io::{Error as IoError, ErrorKind},
+    net::SocketAddr,
+    time::Duration,
+};
 
 use http::uri::Scheme;
+#[cfg(feature = "socket-options")]
+use socket2::{Domain, Protocol, SockRef, Socket, TcpKeepalive, Type};
 use tokio::{
     io::Result as IoResult,
     net::{TcpListener as TokioTcpListener, TcpStream, ToSocketAddrs},
@@ -11,15 +19,130 @@ use crate::{
     web::{LocalAddr, RemoteAddr},
 };
 
+/// The default backlog of the listening socket, same as the one used by
+/// `tokio::net::TcpListener::bind`.
+#[cfg(feature = "socket-options")]
+const DEFAULT_BACKLOG: u32 = 1024;
+
 /// A TCP listener.
+///
+/// The socket options other than [`TcpListener::nodelay`] require the
+/// `socket-options` feature.
+///
+/// # Example
+///
+/// ```
+/// use poem::listener::TcpListener;
+///
+/// let listener = TcpListener::bind("0.0.0.0:3000").nodelay(true);
+/// ```
 pub struct TcpListener<T> {
     addr: T,
+    #[cfg(feature = "socket-options")]
+    socket_options: SocketOptions,
+    stream_options: StreamOptions,
 }
 
 impl<T> TcpListener<T> {
     /// Binds to the provided address, and returns a [`TcpListener<T>`].
     pub fn bind(addr: T) -> Self {
-        Self { addr }
+        Self {
+            addr,
+            #[cfg(feature = "socket-options")]
+            socket_options: SocketOptions::default(),
+            stream_options: StreamOptions::default(),
+        }
+    }
+
+    /// Sets the `SO_REUSEPORT` option on the listening socket, so that
+    /// multiple processes can bind to the same address, default is `false`.
+    #[cfg(all(unix, feature = "socket-options"))]
+    #[cfg_attr(docsrs, doc(cfg(all(unix, feature = "socket-options"))))]
+    #[must_use]
+    pub fn reuse_port(mut self, reuse_port: bool) -> Self {
+        self.socket_options.reuse_port = reuse_port;
+        self
+    }
+
+    /// Sets the maximum number of pending connections, default is `1024`.
+    #[cfg(feature = "socket-options")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.socket_options.backlog = backlog;
+        self
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`) of the sockets.
+    #[cfg(feature = "socket-options")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.socket_options.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`) of the sockets.
+    #[cfg(feature = "socket-options")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.socket_options.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the `TCP_NODELAY` option on the accepted connections, default is
+    /// `false`.
+    #[must_use]
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.stream_options.nodelay = nodelay;
+        self
+    }
+
+    /// Enables TCP keepalive on the accepted connections, and sets the idle
+    /// time before the first keepalive probe is sent.
+    #[cfg(feature = "socket-options")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.stream_options.keepalive_time = Some(time);
+        self
+    }
+
+    /// Sets the interval between the keepalive probes, only takes effect if
+    /// the keepalive is enabled by [`TcpListener::keepalive`].
+    #[cfg(all(
+        feature = "socket-options",
+        any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "windows",
+        )
+    ))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.stream_options.keepalive_interval = Some(interval);
+        self
+    }
+}
+
+impl<T> TcpListener<T> {
+    /// Creates a [`TcpAcceptor`] from a listening socket which is inherited
+    /// from another process, instead of binding to the address.
+    #[cfg(all(unix, feature = "upgrade"))]
+    pub(crate) fn inherit(&self, listener: socket2::Socket) -> Result<TcpAcceptor> {
+        listener.set_nonblocking(true)?;
+        let listener = TokioTcpListener::from_std(listener.into())?;
+        let local_addr = listener.local_addr().map(|addr| LocalAddr(addr.into()))?;
+        Ok(TcpAcceptor {
+            local_addr,
+            listener,
+            stream_options: self.stream_options,
+        })
     }
 }
 
@@ -28,19 +151,133 @@ impl<T: ToSocketAddrs + Send> Listener for TcpListener<T> {
     type Acceptor = TcpAcceptor;
 
     async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        #[cfg(feature = "socket-options")]
+        let listener = self.socket_options.bind(self.addr).await?;
+        #[cfg(not(feature = "socket-options"))]
         let listener = TokioTcpListener::bind(self.addr).await?;
         let local_addr = listener.local_addr().map(|addr| LocalAddr(addr.into()))?;
         Ok(TcpAcceptor {
             local_addr,
             listener,
+            stream_options: self.stream_options,
         })
     }
 }
 
+/// The options of the listening socket.
+#[cfg(feature = "socket-options")]
+#[derive(Debug, Copy, Clone)]
+struct SocketOptions {
+    #[cfg(unix)]
+    reuse_port: bool,
+    backlog: u32,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+}
+
+#[cfg(feature = "socket-options")]
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(unix)]
+            reuse_port: false,
+            backlog: DEFAULT_BACKLOG,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+        }
+    }
+}
+
+#[cfg(feature = "socket-options")]
+impl SocketOptions {
+    async fn bind(&self, addr: impl ToSocketAddrs) -> Result<TokioTcpListener> {
+        let mut last_err = None;
+
+        for addr in tokio::net::lookup_host(addr).await? {
+            match self.bind_addr(addr) {
+                Ok(listener) => return Ok(listener),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| {
+            IoError::new(ErrorKind::InvalidInput, "could not resolve to any address")
+        }))
+    }
+
+    fn bind_addr(&self, addr: SocketAddr) -> Result<TokioTcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        #[cfg(unix)]
+        {
+            socket.set_reuse_address(true)?;
+            if self.reuse_port {
+                socket.set_reuse_port(true)?;
+            }
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        socket.bind(&addr.into())?;
+        socket.listen(self.backlog.min(i32::MAX as u32) as i32)?;
+        socket.set_nonblocking(true)?;
+        TokioTcpListener::from_std(socket.into())
+    }
+}
+
+/// The options applied to the accepted connections.
+#[derive(Debug, Default, Copy, Clone)]
+struct StreamOptions {
+    nodelay: bool,
+    #[cfg(feature = "socket-options")]
+    keepalive_time: Option<Duration>,
+    #[cfg(all(
+        feature = "socket-options",
+        any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "windows",
+        )
+    ))]
+    keepalive_interval: Option<Duration>,
+}
+
+impl StreamOptions {
+    fn apply(&self, stream: &TcpStream) -> Result<()> {
+        if self.nodelay {
+            stream.set_nodelay(true)?;
+        }
+        #[cfg(feature = "socket-options")]
+        if let Some(time) = self.keepalive_time {
+            #[allow(unused_mut)]
+            let mut keepalive = TcpKeepalive::new().with_time(time);
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "ios",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "windows",
+            ))]
+            if let Some(interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(interval);
+            }
+            SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+        }
+        Ok(())
+    }
+}
+
 /// A acceptor that accepts TCP connections.
 pub struct TcpAcceptor {
     local_addr: LocalAddr,
     listener: TokioTcpListener,
+    stream_options: StreamOptions,
 }
 
 impl TcpAcceptor {
@@ -50,6 +287,7 @@ impl TcpAcceptor {
         Ok(Self {
             local_addr,
             listener: TokioTcpListener::from_std(listener)?,
+            stream_options: StreamOptions::default(),
         })
     }
 
@@ -59,10 +297,18 @@ impl TcpAcceptor {
         Ok(Self {
             local_addr,
             listener,
+            stream_options: StreamOptions::default(),
         })
     }
 }
 
+#[cfg(all(unix, feature = "upgrade"))]
+impl std::os::unix::io::AsRawFd for TcpAcceptor {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.listener.as_raw_fd()
+    }
+}
+
 #[async_trait::async_trait]
 impl Acceptor for TcpAcceptor {
     type Io = TcpStream;
@@ -74,14 +320,16 @@ impl Acceptor for TcpAcceptor {
 
     #[inline]
     async fn accept(&mut self) -> Result<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
-        self.listener.accept().await.map(|(io, addr)| {
-            (
-                io,
-                self.local_addr.clone(),
-                RemoteAddr(addr.into()),
-                Scheme::HTTP,
-            )
-        })
+        let (io, addr) = self.listener.accept().await?;
+        if let Err(err) = self.stream_options.apply(&io) {
+            tracing::warn!(error = %err, "failed to set the tcp socket options");
+        }
+        Ok((
+            io,
+            self.local_addr.clone(),
+            RemoteAddr(addr.into()),
+            Scheme::HTTP,
+        ))
     }
 }
 
@@ -107,4 +355,47 @@ mod tests {
         let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
         assert_eq!(stream.read_i32().await.unwrap(), 10);
     }
+
+    #[cfg(feature = "socket-options")]
+    #[tokio::test]
+    async fn socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .nodelay(true)
+            .keepalive(Duration::from_secs(30))
+            .backlog(16)
+            .recv_buffer_size(64 * 1024);
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().remove(0);
+
+        tokio::spawn(async move {
+            let mut stream = TcpStream::connect(*local_addr.as_socket_addr().unwrap())
+                .await
+                .unwrap();
+            stream.write_i32(10).await.unwrap();
+        });
+
+        let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert!(SockRef::from(&stream).keepalive().unwrap());
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+    }
+
+    #[cfg(all(unix, feature = "socket-options"))]
+    #[tokio::test]
+    async fn reuse_port() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .reuse_port(true)
+            .into_acceptor()
+            .await
+            .unwrap();
+        let local_addr = acceptor.local_addr().remove(0);
+        let addr = *local_addr.as_socket_addr().unwrap();
+
+        assert!(TcpListener::bind(addr)
+            .reuse_port(true)
+            .into_acceptor()
+            .await
+            .is_ok());
+        assert!(TcpListener::bind(addr).into_acceptor().await.is_err());
+    }
 }
@@ -1,6 +1,13 @@
-use std::{io::Result, path::Path};
+use std::{
+    fs::Permissions,
+    io::{Error as IoError, ErrorKind, Result},
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::Path,
+};
 
 use http::uri::Scheme;
+#[cfg(feature = "socket-options")]
+use nix::unistd::{Gid, Uid};
 use tokio::{
     io::Result as IoResult,
     net::{UnixListener as TokioUnixListener, UnixStream},
@@ -12,15 +19,107 @@ use crate::{
 };
 
 /// A Unix domain socket listener.
+///
+/// Changing the owner of the socket file requires the `socket-options`
+/// feature.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::listener::UnixListener;
+///
+/// let listener = UnixListener::bind("/run/poem/poem.sock")
+///     .permissions(0o660)
+///     .remove_stale_socket(true);
+/// ```
 #[cfg_attr(docsrs, doc(cfg(unix)))]
 pub struct UnixListener<T> {
     path: T,
+    permissions: Option<u32>,
+    #[cfg(feature = "socket-options")]
+    owner: Option<u32>,
+    #[cfg(feature = "socket-options")]
+    group: Option<u32>,
+    remove_stale_socket: bool,
 }
 
 impl<T> UnixListener<T> {
     /// Binds to the provided address, and returns a [`UnixListener<T>`].
     pub fn bind(path: T) -> Self {
-        Self { path }
+        Self {
+            path,
+            permissions: None,
+            #[cfg(feature = "socket-options")]
+            owner: None,
+            #[cfg(feature = "socket-options")]
+            group: None,
+            remove_stale_socket: false,
+        }
+    }
+
+    /// Sets the permission bits of the socket file, such as `0o660`.
+    #[must_use]
+    pub fn permissions(self, mode: u32) -> Self {
+        Self {
+            permissions: Some(mode),
+            ..self
+        }
+    }
+
+    /// Sets the user id of the owner of the socket file.
+    #[cfg(feature = "socket-options")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn owner(self, uid: u32) -> Self {
+        Self {
+            owner: Some(uid),
+            ..self
+        }
+    }
+
+    /// Sets the group id of the socket file.
+    #[cfg(feature = "socket-options")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "socket-options")))]
+    #[must_use]
+    pub fn group(self, gid: u32) -> Self {
+        Self {
+            group: Some(gid),
+            ..self
+        }
+    }
+
+    /// If enabled, an existing socket file at the path is removed before
+    /// binding if no process is listening on it, default is `false`.
+    ///
+    /// Binding fails if another process is still listening on the socket.
+    #[must_use]
+    pub fn remove_stale_socket(self, enabled: bool) -> Self {
+        Self {
+            remove_stale_socket: enabled,
+            ..self
+        }
+    }
+}
+
+/// Removes the socket file at the path if no process is listening on it.
+fn remove_stale_socket(path: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {}
+        Ok(_) => return Ok(()),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    }
+
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(IoError::new(
+            ErrorKind::AddrInUse,
+            format!("another process is listening on `{}`", path.display()),
+        )),
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+            tracing::debug!(path = %path.display(), "remove stale socket");
+            std::fs::remove_file(path)
+        }
+        Err(err) => Err(err),
     }
 }
 
@@ -29,7 +128,24 @@ impl<T: AsRef<Path> + Send> Listener for UnixListener<T> {
     type Acceptor = UnixAcceptor;
 
     async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
-        let listener = TokioUnixListener::bind(self.path)?;
+        let path = self.path.as_ref();
+        if self.remove_stale_socket {
+            remove_stale_socket(path)?;
+        }
+
+        let listener = TokioUnixListener::bind(path)?;
+        if let Some(mode) = self.permissions {
+            std::fs::set_permissions(path, Permissions::from_mode(mode))?;
+        }
+        #[cfg(feature = "socket-options")]
+        if self.owner.is_some() || self.group.is_some() {
+            nix::unistd::chown(
+                path,
+                self.owner.map(Uid::from_raw),
+                self.group.map(Gid::from_raw),
+            )?;
+        }
+
         let local_addr = listener.local_addr().map(|addr| LocalAddr(addr.into()))?;
         Ok(UnixAcceptor {
             local_addr,
@@ -104,4 +220,37 @@ mod tests {
         drop(acceptor);
         std::fs::remove_file("test-socket").unwrap();
     }
+
+    #[tokio::test]
+    async fn socket_options() {
+        let path = std::env::temp_dir().join(format!("poem-unix-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let acceptor = UnixListener::bind(&path)
+            .permissions(0o600)
+            .into_acceptor()
+            .await
+            .unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        // the socket is in use
+        assert!(UnixListener::bind(&path)
+            .remove_stale_socket(true)
+            .into_acceptor()
+            .await
+            .is_err());
+
+        // the socket is stale
+        drop(acceptor);
+        assert!(UnixListener::bind(&path).into_acceptor().await.is_err());
+        let acceptor = UnixListener::bind(&path)
+            .remove_stale_socket(true)
+            .into_acceptor()
+            .await
+            .unwrap();
+
+        drop(acceptor);
+        std::fs::remove_file(&path).unwrap();
+    }
 }
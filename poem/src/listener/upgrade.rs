@@ -0,0 +1,384 @@
+use std::{
+    io::{Error as IoError, ErrorKind, IoSlice, IoSliceMut, Result},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use http::uri::Scheme;
+use nix::{
+    fcntl::{fcntl, FcntlArg},
+    sys::socket::{recvmsg, sendmsg, ControlMessage, ControlMessageOwned, MsgFlags},
+};
+use socket2::Socket;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, Interest, Result as IoResult},
+    net::{TcpStream, ToSocketAddrs, UnixListener as TokioUnixListener, UnixStream},
+    sync::watch,
+};
+
+use crate::{
+    listener::{Acceptor, Listener, TcpAcceptor, TcpListener},
+    web::{LocalAddr, RemoteAddr},
+};
+
+/// A TCP listener which can hand over its listening socket to a new process,
+/// so that the binary can be upgraded without refusing any connection.
+///
+/// The listener listens on a control socket at the provided path. When it is
+/// converted to an acceptor, it first tries to take over the listening socket
+/// from the process which is listening on the control socket, and only binds
+/// to the address of the [`TcpListener`] if there is no such process.
+///
+/// After the listening socket is handed over, the old process stops accepting
+/// connections, and [`UpgradeHandle::upgraded`] is resolved so that it can
+/// drain the remaining connections and exit.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use poem::{
+///     handler,
+///     listener::{TcpListener, UpgradeListener},
+///     Server,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let listener =
+///     UpgradeListener::new("/run/poem/upgrade.sock", TcpListener::bind("0.0.0.0:3000"));
+/// let handle = listener.handle();
+///
+/// // start the new binary to take over the listening socket, and this
+/// // process exits after the remaining connections are closed
+/// Server::new(listener)
+///     .run_with_graceful_shutdown(
+///         index,
+///         async move { handle.upgraded().await },
+///         Some(Duration::from_secs(30)),
+///     )
+///     .await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "upgrade"))))]
+pub struct UpgradeListener<T> {
+    path: PathBuf,
+    inner: TcpListener<T>,
+    upgraded: Arc<watch::Sender<bool>>,
+}
+
+impl<T> UpgradeListener<T> {
+    /// Creates an [`UpgradeListener<T>`] with the path of the control socket
+    /// and the listener to bind if the listening socket can not be inherited.
+    pub fn new(path: impl Into<PathBuf>, inner: TcpListener<T>) -> Self {
+        Self {
+            path: path.into(),
+            inner,
+            upgraded: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Returns a handle to wait for the listening socket to be handed over to
+    /// a new process.
+    pub fn handle(&self) -> UpgradeHandle {
+        UpgradeHandle {
+            rx: self.upgraded.subscribe(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ToSocketAddrs + Send> Listener for UpgradeListener<T> {
+    type Acceptor = UpgradeAcceptor;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        let (inner, confirm) = match UnixStream::connect(&self.path).await {
+            Ok(stream) => {
+                let socket = receive_socket(&stream).await?;
+                (self.inner.inherit(socket)?, Some(stream))
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::NotFound | ErrorKind::ConnectionRefused
+                ) =>
+            {
+                (self.inner.into_acceptor().await?, None)
+            }
+            Err(err) => return Err(err),
+        };
+
+        // the control socket of the old process is replaced
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        let control = TokioUnixListener::bind(&self.path)?;
+
+        if let Some(mut stream) = confirm {
+            stream.write_all(&[1]).await?;
+            tracing::info!(path = %self.path.display(), "inherited the listening socket");
+        }
+
+        Ok(UpgradeAcceptor {
+            path: self.path,
+            inner,
+            control,
+            rx: self.upgraded.subscribe(),
+            upgraded: self.upgraded,
+        })
+    }
+}
+
+/// A acceptor that can hand over its listening socket to a new process.
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "upgrade"))))]
+pub struct UpgradeAcceptor {
+    path: PathBuf,
+    inner: TcpAcceptor,
+    control: TokioUnixListener,
+    upgraded: Arc<watch::Sender<bool>>,
+    rx: watch::Receiver<bool>,
+}
+
+impl UpgradeAcceptor {
+    /// Returns a handle to wait for the listening socket to be handed over to
+    /// a new process.
+    pub fn handle(&self) -> UpgradeHandle {
+        UpgradeHandle {
+            rx: self.upgraded.subscribe(),
+        }
+    }
+}
+
+impl Drop for UpgradeAcceptor {
+    fn drop(&mut self) {
+        // the control socket belongs to the new process after upgraded
+        if !*self.upgraded.borrow() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Acceptor for UpgradeAcceptor {
+    type Io = TcpStream;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> Result<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        loop {
+            if *self.rx.borrow_and_update() {
+                // the new process accepts the connections
+                futures_util::future::pending::<()>().await;
+            }
+
+            tokio::select! {
+                res = self.inner.accept() => return res,
+                res = self.control.accept() => match res {
+                    Ok((stream, _)) => {
+                        let socket = fcntl(self.inner.as_raw_fd(), FcntlArg::F_DUPFD_CLOEXEC(0))
+                            .map(owned_socket)?;
+                        let upgraded = self.upgraded.clone();
+                        let path = self.path.clone();
+                        tokio::spawn(async move {
+                            match hand_over(stream, socket).await {
+                                Ok(()) => {
+                                    tracing::info!(
+                                        path = %path.display(),
+                                        "the listening socket is handed over"
+                                    );
+                                    upgraded.send_replace(true);
+                                }
+                                Err(err) => {
+                                    tracing::warn!(
+                                        error = %err,
+                                        "failed to hand over the listening socket"
+                                    );
+                                }
+                            }
+                        });
+                    }
+                    Err(err) => {
+                        tracing::warn!(error = %err, "failed to accept the control connection");
+                    }
+                },
+                _ = self.rx.changed() => {}
+            }
+        }
+    }
+}
+
+/// A handle to wait for the listening socket of an [`UpgradeListener`] to be
+/// handed over to a new process.
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "upgrade"))))]
+pub struct UpgradeHandle {
+    rx: watch::Receiver<bool>,
+}
+
+impl UpgradeHandle {
+    /// Returns `true` if the listening socket has been handed over.
+    pub fn is_upgraded(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Waits until the listening socket has been handed over.
+    pub async fn upgraded(&self) {
+        let mut rx = self.rx.clone();
+        while !*rx.borrow_and_update() {
+            if rx.changed().await.is_err() {
+                // the acceptor is dropped without being upgraded
+                futures_util::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// Sends the listening socket to the new process, and waits for the new
+/// process to confirm that it is ready to accept the connections.
+async fn hand_over(mut stream: UnixStream, socket: Socket) -> Result<()> {
+    loop {
+        stream.writable().await?;
+        match stream.try_io(Interest::WRITABLE, || send_fd(&stream, socket.as_raw_fd())) {
+            Ok(()) => break,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    let mut buf = [0; 1];
+    stream.read_exact(&mut buf).await?;
+    Ok(())
+}
+
+fn send_fd(stream: &UnixStream, fd: RawFd) -> Result<()> {
+    sendmsg::<()>(
+        stream.as_raw_fd(),
+        &[IoSlice::new(&[0])],
+        &[ControlMessage::ScmRights(&[fd])],
+        MsgFlags::empty(),
+        None,
+    )?;
+    Ok(())
+}
+
+/// Receives the listening socket from the old process.
+async fn receive_socket(stream: &UnixStream) -> Result<Socket> {
+    loop {
+        stream.readable().await?;
+        match stream.try_io(Interest::READABLE, || receive_fd(stream)) {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => continue,
+            res => return res,
+        }
+    }
+}
+
+fn receive_fd(stream: &UnixStream) -> Result<Socket> {
+    // newer versions of libc re-export `c_uint` from `core::ffi`, which is
+    // not used on the older compilers
+    #[allow(clippy::incompatible_msrv)]
+    let mut space = nix::cmsg_space!(RawFd);
+    let mut buf = [0; 1];
+    let mut iov = [IoSliceMut::new(&mut buf)];
+    let msg = recvmsg::<()>(
+        stream.as_raw_fd(),
+        &mut iov,
+        Some(&mut space),
+        MsgFlags::empty(),
+    )?;
+
+    let socket = msg
+        .cmsgs()
+        .find_map(|msg| match msg {
+            ControlMessageOwned::ScmRights(fds) => fds.first().copied(),
+            _ => None,
+        })
+        .map(owned_socket)
+        .ok_or_else(|| {
+            IoError::new(
+                ErrorKind::Other,
+                "the listening socket is not received from the old process",
+            )
+        })?;
+    socket.set_cloexec(true)?;
+    Ok(socket)
+}
+
+/// Takes the ownership of a file descriptor returned by `fcntl` or `recvmsg`.
+#[allow(unsafe_code)]
+fn owned_socket(fd: RawFd) -> Socket {
+    // SAFETY: the file descriptor is newly created and is not owned by anything
+    // else.
+    unsafe { Socket::from_raw_fd(fd) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn control_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("poem-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn bind_without_old_process() {
+        let path = control_path("upgrade-bind");
+        let _ = std::fs::remove_file(&path);
+
+        let acceptor = UpgradeListener::new(&path, TcpListener::bind("127.0.0.1:0"))
+            .into_acceptor()
+            .await
+            .unwrap();
+        assert!(path.exists());
+
+        drop(acceptor);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn hand_over_listening_socket() {
+        let path = control_path("upgrade");
+        let _ = std::fs::remove_file(&path);
+
+        let old = UpgradeListener::new(&path, TcpListener::bind("127.0.0.1:0"));
+        let handle = old.handle();
+        let mut old = old.into_acceptor().await.unwrap();
+        let addr = old.local_addr().remove(0);
+        let old_task = tokio::spawn(async move {
+            let _ = old.accept().await;
+        });
+
+        let mut new = UpgradeListener::new(&path, TcpListener::bind("127.0.0.1:0"))
+            .into_acceptor()
+            .await
+            .unwrap();
+        assert_eq!(new.local_addr(), vec![addr.clone()]);
+
+        tokio::time::timeout(Duration::from_secs(5), handle.upgraded())
+            .await
+            .unwrap();
+        assert!(handle.is_upgraded());
+
+        let mut client = TcpStream::connect(*addr.as_socket_addr().unwrap())
+            .await
+            .unwrap();
+        client.write_i32(10).await.unwrap();
+        let (mut stream, _, _, _) = new.accept().await.unwrap();
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+
+        old_task.abort();
+        drop(new);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    io::{Read, Write},
+    net::Shutdown,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::ready;
+use http::uri::Scheme;
+use socket2::{Domain, SockAddr, Socket, Type};
+use tokio::io::{unix::AsyncFd, AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
+
+use crate::{
+    listener::{Acceptor, Listener},
+    web::{LocalAddr, RemoteAddr},
+    Addr,
+};
+
+/// A VM socket (`AF_VSOCK`) listener, which accepts the connections from the
+/// host or the other virtual machines, for example when running inside a
+/// Firecracker microVM or an AWS Nitro Enclave.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::listener::VsockListener;
+///
+/// let listener = VsockListener::bind(VsockListener::CID_ANY, 3000);
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "vsock")))]
+pub struct VsockListener {
+    cid: u32,
+    port: u32,
+    backlog: u32,
+}
+
+impl VsockListener {
+    /// The CID to accept the connections to any CID of this machine
+    /// (`VMADDR_CID_ANY`).
+    pub const CID_ANY: u32 = u32::MAX;
+
+    /// The port to bind to a random unused port (`VMADDR_PORT_ANY`).
+    pub const PORT_ANY: u32 = u32::MAX;
+
+    /// Binds to the provided CID and port, and returns a [`VsockListener`].
+    pub fn bind(cid: u32, port: u32) -> Self {
+        Self {
+            cid,
+            port,
+            backlog: 1024,
+        }
+    }
+
+    /// Sets the maximum number of pending connections, default is `1024`.
+    #[must_use]
+    pub fn backlog(self, backlog: u32) -> Self {
+        Self { backlog, ..self }
+    }
+}
+
+fn vsock_addr(addr: &SockAddr) -> Addr {
+    match addr.vsock_address() {
+        Some((cid, port)) => Addr::custom("vsock", format!("{}:{}", cid, port)),
+        None => Addr::default(),
+    }
+}
+
+#[async_trait::async_trait]
+impl Listener for VsockListener {
+    type Acceptor = VsockAcceptor;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        let socket = Socket::new(Domain::VSOCK, Type::STREAM, None)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SockAddr::vsock(self.cid, self.port)?)?;
+        socket.listen(self.backlog.min(i32::MAX as u32) as i32)?;
+        let local_addr = LocalAddr(vsock_addr(&socket.local_addr()?));
+
+        Ok(VsockAcceptor {
+            local_addr,
+            listener: AsyncFd::new(socket)?,
+        })
+    }
+}
+
+/// A acceptor that accepts VM socket connections.
+#[cfg_attr(docsrs, doc(cfg(feature = "vsock")))]
+pub struct VsockAcceptor {
+    local_addr: LocalAddr,
+    listener: AsyncFd<Socket>,
+}
+
+#[async_trait::async_trait]
+impl Acceptor for VsockAcceptor {
+    type Io = VsockStream;
+
+    #[inline]
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        vec![self.local_addr.clone()]
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        loop {
+            let mut guard = self.listener.readable().await?;
+            let (socket, addr) = match guard.try_io(|listener| listener.get_ref().accept()) {
+                Ok(res) => res?,
+                Err(_would_block) => continue,
+            };
+            socket.set_nonblocking(true)?;
+            let stream = VsockStream(AsyncFd::new(socket)?);
+            return Ok((
+                stream,
+                self.local_addr.clone(),
+                RemoteAddr(vsock_addr(&addr)),
+                Scheme::HTTP,
+            ));
+        }
+    }
+}
+
+/// A IO stream for VsockAcceptor.
+#[cfg_attr(docsrs, doc(cfg(feature = "vsock")))]
+pub struct VsockStream(AsyncFd<Socket>);
+
+impl AsyncRead for VsockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|socket| socket.get_ref().read(unfilled)) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(err)) => return Poll::Ready(Err(err)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for VsockStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        loop {
+            let mut guard = ready!(self.0.poll_write_ready(cx))?;
+            match guard.try_io(|socket| socket.get_ref().write(buf)) {
+                Ok(res) => return Poll::Ready(res),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Poll::Ready(self.0.get_ref().shutdown(Shutdown::Write))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bind() {
+        let acceptor = match VsockListener::bind(VsockListener::CID_ANY, VsockListener::PORT_ANY)
+            .into_acceptor()
+            .await
+        {
+            Ok(acceptor) => acceptor,
+            // the vsock is not supported on this machine
+            Err(_) => return,
+        };
+
+        let local_addr = acceptor.local_addr().remove(0);
+        match &local_addr.0 {
+            Addr::Custom("vsock", addr) => {
+                let (cid, port) = addr.split_once(':').unwrap();
+                assert_eq!(cid.parse::<u32>().unwrap(), VsockListener::CID_ANY);
+                assert_ne!(port.parse::<u32>().unwrap(), VsockListener::PORT_ANY);
+            }
+            _ => panic!("unexpected address: {}", local_addr),
+        }
+    }
+}
@@ -0,0 +1,593 @@
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{Result as IoResult, Write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+
+use crate::{
+    http::{header, HeaderMap, HeaderName, Method, StatusCode, Uri, Version},
+    web::{MatchedPath, RealIp},
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, Response, Result,
+};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The format of the access log lines.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AccessLogFormat {
+    /// The [Common Log Format](https://httpd.apache.org/docs/current/logs.html#common).
+    Common,
+
+    /// The [Combined Log Format](https://httpd.apache.org/docs/current/logs.html#combined),
+    /// which appends the `Referer` and `User-Agent` headers to the Common Log
+    /// Format.
+    Combined,
+
+    /// A JSON object per line.
+    Json,
+}
+
+impl Default for AccessLogFormat {
+    fn default() -> Self {
+        Self::Combined
+    }
+}
+
+/// The optional fields of the access log lines.
+///
+/// In the Common and Combined Log Formats, the response size is always
+/// logged, and the other fields are appended as `key=value` pairs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AccessLogField {
+    /// The time taken to produce the response, in milliseconds.
+    Latency,
+
+    /// The size of the response body in bytes, it is unknown for the
+    /// streaming bodies.
+    Bytes,
+
+    /// The path pattern of the matched route, see [`MatchedPath`].
+    RoutePattern,
+
+    /// The request ID, see [`AccessLog::request_id_header`].
+    RequestId,
+}
+
+/// The information of a request and its response.
+#[derive(Debug, Clone)]
+pub struct AccessLogRecord {
+    /// The time when the request is received.
+    pub time: SystemTime,
+
+    /// The IP address of the client.
+    pub remote_addr: Option<String>,
+
+    /// The method of the request.
+    pub method: Method,
+
+    /// The URI of the request.
+    pub uri: Uri,
+
+    /// The version of the request.
+    pub version: Version,
+
+    /// The status of the response.
+    pub status: StatusCode,
+
+    /// The size of the response body in bytes.
+    pub bytes: Option<u64>,
+
+    /// The time taken to produce the response.
+    pub latency: Duration,
+
+    /// The `Referer` header of the request.
+    pub referer: Option<String>,
+
+    /// The `User-Agent` header of the request.
+    pub user_agent: Option<String>,
+
+    /// The path pattern of the matched route.
+    pub route_pattern: Option<String>,
+
+    /// The request ID.
+    pub request_id: Option<String>,
+}
+
+impl AccessLogRecord {
+    /// Formats the record to a line without the trailing newline.
+    pub fn format(&self, format: AccessLogFormat, fields: &[AccessLogField]) -> String {
+        match format {
+            AccessLogFormat::Common => self.format_text(false, fields),
+            AccessLogFormat::Combined => self.format_text(true, fields),
+            AccessLogFormat::Json => self.format_json(fields),
+        }
+    }
+
+    fn format_text(&self, combined: bool, fields: &[AccessLogField]) -> String {
+        let mut line = format!(
+            "{} - - [{}] \"{} {} {:?}\" {} {}",
+            self.remote_addr.as_deref().unwrap_or("-"),
+            format_clf_time(self.time),
+            self.method,
+            escape(&self.uri.to_string()),
+            self.version,
+            self.status.as_u16(),
+            self.bytes
+                .map(|bytes| bytes.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        );
+        if combined {
+            let _ = write!(
+                line,
+                " \"{}\" \"{}\"",
+                escape(self.referer.as_deref().unwrap_or("-")),
+                escape(self.user_agent.as_deref().unwrap_or("-")),
+            );
+        }
+        for field in fields {
+            let _ = match field {
+                AccessLogField::Latency => write!(line, " latency_ms={:.3}", self.latency_ms()),
+                AccessLogField::Bytes => Ok(()),
+                AccessLogField::RoutePattern => write!(
+                    line,
+                    " route=\"{}\"",
+                    escape(self.route_pattern.as_deref().unwrap_or("-"))
+                ),
+                AccessLogField::RequestId => write!(
+                    line,
+                    " request_id=\"{}\"",
+                    escape(self.request_id.as_deref().unwrap_or("-"))
+                ),
+            };
+        }
+        line
+    }
+
+    fn format_json(&self, fields: &[AccessLogField]) -> String {
+        let mut obj = Map::new();
+        obj.insert("time".to_string(), format_rfc3339_time(self.time).into());
+        obj.insert("remote_addr".to_string(), self.remote_addr.clone().into());
+        obj.insert("method".to_string(), self.method.as_str().into());
+        obj.insert("uri".to_string(), self.uri.to_string().into());
+        obj.insert("version".to_string(), format!("{:?}", self.version).into());
+        obj.insert("status".to_string(), self.status.as_u16().into());
+        obj.insert("referer".to_string(), self.referer.clone().into());
+        obj.insert("user_agent".to_string(), self.user_agent.clone().into());
+        for field in fields {
+            let (name, value) = match field {
+                AccessLogField::Latency => ("latency_ms", self.latency_ms().into()),
+                AccessLogField::Bytes => ("bytes", self.bytes.into()),
+                AccessLogField::RoutePattern => ("route", self.route_pattern.clone().into()),
+                AccessLogField::RequestId => ("request_id", self.request_id.clone().into()),
+            };
+            obj.insert(name.to_string(), value);
+        }
+        Value::Object(obj).to_string()
+    }
+
+    fn latency_ms(&self) -> f64 {
+        self.latency.as_secs_f64() * 1000.0
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Converts the time to the UTC date and time, the date is calculated with
+/// the algorithm in <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn utc_datetime(time: SystemTime) -> (i64, u32, u32, u64, u64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn format_clf_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc_datetime(time);
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        hour,
+        minute,
+        second
+    )
+}
+
+fn format_rfc3339_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc_datetime(time);
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Represents a destination of the access log lines.
+pub trait AccessLogSink: Send + Sync {
+    /// Writes a line, which does not contain the trailing newline.
+    fn write(&self, record: &AccessLogRecord, line: String);
+}
+
+/// An access log sink which writes the lines to the standard output.
+#[derive(Debug, Default)]
+pub struct StdoutAccessLogSink;
+
+impl AccessLogSink for StdoutAccessLogSink {
+    fn write(&self, _record: &AccessLogRecord, line: String) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+    }
+}
+
+/// An access log sink which appends the lines to a file.
+pub struct FileAccessLogSink {
+    file: Mutex<File>,
+}
+
+impl FileAccessLogSink {
+    /// Opens the file in the append mode, the file is created if it does not
+    /// exist.
+    pub fn open(path: impl AsRef<Path>) -> IoResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AccessLogSink for FileAccessLogSink {
+    fn write(&self, _record: &AccessLogRecord, mut line: String) {
+        line.push('\n');
+        let _ = self.file.lock().write_all(line.as_bytes());
+    }
+}
+
+impl AccessLogSink for tokio::sync::mpsc::UnboundedSender<String> {
+    fn write(&self, _record: &AccessLogRecord, line: String) {
+        let _ = self.send(line);
+    }
+}
+
+impl AccessLogSink for tokio::sync::mpsc::UnboundedSender<AccessLogRecord> {
+    fn write(&self, record: &AccessLogRecord, _line: String) {
+        let _ = self.send(record.clone());
+    }
+}
+
+/// Middleware for writing the access logs.
+///
+/// A line is written to the sink after the inner endpoint returns the
+/// response, the Combined Log Format and the standard output are used by
+/// default.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     middleware::{AccessLog, AccessLogField, AccessLogFormat},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn hello() -> &'static str {
+///     "hello"
+/// }
+///
+/// let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+/// let app = Route::new().at("/users/:id", get(hello)).with(
+///     AccessLog::new()
+///         .format(AccessLogFormat::Json)
+///         .fields([AccessLogField::RoutePattern, AccessLogField::Bytes])
+///         .sink(tx),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// TestClient::new(app)
+///     .get("/users/1")
+///     .send()
+///     .await
+///     .assert_status_is_ok();
+/// let line: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+/// assert_eq!(line["route"], "/users/:id");
+/// assert_eq!(line["bytes"], 5);
+/// # });
+/// ```
+pub struct AccessLog {
+    format: AccessLogFormat,
+    fields: Vec<AccessLogField>,
+    request_id_header: HeaderName,
+    sink: Arc<dyn AccessLogSink>,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self {
+            format: AccessLogFormat::default(),
+            fields: Vec::new(),
+            request_id_header: HeaderName::from_static("x-request-id"),
+            sink: Arc::new(StdoutAccessLogSink),
+        }
+    }
+}
+
+impl AccessLog {
+    /// Create `AccessLog` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the format of the lines.
+    ///
+    /// Default is [`AccessLogFormat::Combined`].
+    #[must_use]
+    pub fn format(self, format: AccessLogFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Sets the optional fields to be logged.
+    #[must_use]
+    pub fn fields(self, fields: impl IntoIterator<Item = AccessLogField>) -> Self {
+        Self {
+            fields: fields.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the header which the request ID is read from, the response header
+    /// is used first, then the request header.
+    ///
+    /// Default is `X-Request-Id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header name is invalid.
+    #[must_use]
+    pub fn request_id_header<K: TryInto<HeaderName>>(self, name: K) -> Self {
+        Self {
+            request_id_header: name
+                .try_into()
+                .unwrap_or_else(|_| panic!("invalid header name")),
+            ..self
+        }
+    }
+
+    /// Sets the sink which the lines are written to.
+    ///
+    /// Default is [`StdoutAccessLogSink`].
+    #[must_use]
+    pub fn sink(self, sink: impl AccessLogSink + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for AccessLog {
+    type Output = AccessLogEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        AccessLogEndpoint {
+            inner: ep,
+            format: self.format,
+            fields: self.fields.clone(),
+            request_id_header: self.request_id_header.clone(),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+/// Endpoint for AccessLog middleware.
+pub struct AccessLogEndpoint<E> {
+    inner: E,
+    format: AccessLogFormat,
+    fields: Vec<AccessLogField>,
+    request_id_header: HeaderName,
+    sink: Arc<dyn AccessLogSink>,
+}
+
+fn header_value(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string)
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for AccessLogEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let time = SystemTime::now();
+        let now = Instant::now();
+        let remote_addr = RealIp::from_request_without_body(&req)
+            .await
+            .ok()
+            .and_then(|real_ip| real_ip.0)
+            .map(|addr| addr.to_string())
+            .or_else(|| {
+                req.remote_addr()
+                    .as_socket_addr()
+                    .map(|addr| addr.ip().to_string())
+            });
+        let mut record = AccessLogRecord {
+            time,
+            remote_addr,
+            method: req.method().clone(),
+            uri: req.original_uri().clone(),
+            version: req.version(),
+            status: StatusCode::OK,
+            bytes: None,
+            latency: Duration::ZERO,
+            referer: header_value(req.headers(), &header::REFERER),
+            user_agent: header_value(req.headers(), &header::USER_AGENT),
+            route_pattern: None,
+            request_id: header_value(req.headers(), &self.request_id_header),
+        };
+
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        record.latency = now.elapsed();
+        match &res {
+            Ok(resp) => {
+                record.status = resp.status();
+                record.bytes = resp
+                    .headers()
+                    .get(header::CONTENT_LENGTH)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok())
+                    .or_else(|| resp.body_size());
+                record.route_pattern = resp
+                    .data::<MatchedPath>()
+                    .map(|path| path.as_str().to_string());
+                if let Some(request_id) = header_value(resp.headers(), &self.request_id_header) {
+                    record.request_id = Some(request_id);
+                }
+            }
+            Err(err) => {
+                record.status = err.status();
+                record.route_pattern = err
+                    .data::<MatchedPath>()
+                    .map(|path| path.as_str().to_string());
+            }
+        }
+
+        let line = record.format(self.format, &self.fields);
+        self.sink.write(&record, line);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt, Route};
+
+    #[test]
+    fn format_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(971_186_136);
+        assert_eq!(format_clf_time(time), "10/Oct/2000:13:55:36 +0000");
+        assert_eq!(format_rfc3339_time(time), "2000-10-10T13:55:36Z");
+        assert_eq!(format_rfc3339_time(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+        assert_eq!(format_rfc3339_time(time), "2024-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn format_record() {
+        let record = AccessLogRecord {
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+            remote_addr: Some("127.0.0.1".to_string()),
+            method: Method::GET,
+            uri: Uri::from_static("/apache_pb.gif"),
+            version: Version::HTTP_10,
+            status: StatusCode::OK,
+            bytes: Some(2326),
+            latency: Duration::from_millis(5),
+            referer: None,
+            user_agent: Some("Mozilla/4.08 \"test\"".to_string()),
+            route_pattern: Some("/:file".to_string()),
+            request_id: None,
+        };
+
+        assert_eq!(
+            record.format(AccessLogFormat::Common, &[]),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /apache_pb.gif HTTP/1.0\" 200 2326"
+        );
+        assert_eq!(
+            record.format(
+                AccessLogFormat::Combined,
+                &[
+                    AccessLogField::Latency,
+                    AccessLogField::RoutePattern,
+                    AccessLogField::RequestId
+                ]
+            ),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /apache_pb.gif HTTP/1.0\" 200 2326 \
+             \"-\" \"Mozilla/4.08 \\\"test\\\"\" latency_ms=5.000 route=\"/:file\" request_id=\"-\""
+        );
+
+        let value: Value = serde_json::from_str(&record.format(
+            AccessLogFormat::Json,
+            &[AccessLogField::Bytes, AccessLogField::RequestId],
+        ))
+        .unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "time": "2000-10-10T13:55:36Z",
+                "remote_addr": "127.0.0.1",
+                "method": "GET",
+                "uri": "/apache_pb.gif",
+                "version": "HTTP/1.0",
+                "status": 200,
+                "referer": null,
+                "user_agent": "Mozilla/4.08 \"test\"",
+                "bytes": 2326,
+                "request_id": null,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn access_log() {
+        #[handler(internal)]
+        fn index() -> &'static str {
+            "hello"
+        }
+
+        let (tx, mut rx) = unbounded_channel::<AccessLogRecord>();
+        let cli = TestClient::new(
+            Route::new()
+                .at("/a/:id", index)
+                .with(AccessLog::new().sink(tx)),
+        );
+
+        cli.get("/a/1")
+            .header("x-request-id", "abc")
+            .header("x-real-ip", "10.0.0.1")
+            .send()
+            .await
+            .assert_status_is_ok();
+        let record = rx.recv().await.unwrap();
+        assert_eq!(record.remote_addr.as_deref(), Some("10.0.0.1"));
+        assert_eq!(record.status, StatusCode::OK);
+        assert_eq!(record.bytes, Some(5));
+        assert_eq!(record.route_pattern.as_deref(), Some("/a/:id"));
+        assert_eq!(record.request_id.as_deref(), Some("abc"));
+
+        cli.get("/b")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        let record = rx.recv().await.unwrap();
+        assert_eq!(record.status, StatusCode::NOT_FOUND);
+        assert_eq!(record.route_pattern, None);
+    }
+}
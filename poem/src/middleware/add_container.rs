@@ -0,0 +1,49 @@
+use crate::{
+    web::{container::RequestScope, Container},
+    Endpoint, Middleware, Request, Result,
+};
+
+/// Middleware for adding the dependency injection [`Container`] to the
+/// request, so that the values can be extracted with
+/// [`Inject`](crate::web::Inject).
+///
+/// Each request gets a new scope for the values registered with
+/// [`Container::scoped`].
+pub struct AddContainer {
+    container: Container,
+}
+
+impl AddContainer {
+    /// Create new `AddContainer` middleware with the container.
+    pub fn new(container: Container) -> Self {
+        Self { container }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for AddContainer {
+    type Output = AddContainerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        AddContainerEndpoint {
+            inner: ep,
+            container: self.container.clone(),
+        }
+    }
+}
+
+/// Endpoint for AddContainer middleware.
+pub struct AddContainerEndpoint<E> {
+    inner: E,
+    container: Container,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for AddContainerEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        req.extensions_mut()
+            .insert(RequestScope::new(self.container.clone()));
+        self.inner.call(req).await
+    }
+}
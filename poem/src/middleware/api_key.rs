@@ -0,0 +1,423 @@
+use std::{collections::HashMap, convert::TryInto, sync::Arc};
+
+use crate::{
+    error::{GetDataError, InsufficientScopeError, UnauthorizedError},
+    http::{header, header::HeaderName},
+    middleware::RateLimitQuota,
+    Endpoint, FromRequest, Middleware, Request, RequestBody, Result,
+};
+
+/// The metadata of an API key, which is attached to the request by
+/// [`ApiKey`] middleware.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ApiKeyInfo {
+    /// The identifier of the key, which is used as the rate limit key, so it
+    /// should not be the secret key itself.
+    pub id: String,
+
+    /// The owner of the key.
+    pub owner: String,
+
+    /// The scopes granted to the key.
+    pub scopes: Vec<String>,
+
+    /// The quota of the key, which overrides the quota of
+    /// [`RateLimit`](crate::middleware::RateLimit) if it uses
+    /// [`RateLimit::key_by_api_key`](crate::middleware::RateLimit::key_by_api_key).
+    pub quota: Option<RateLimitQuota>,
+}
+
+impl ApiKeyInfo {
+    /// Create an `ApiKeyInfo` without scopes and quota.
+    pub fn new(id: impl Into<String>, owner: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            owner: owner.into(),
+            scopes: Vec::new(),
+            quota: None,
+        }
+    }
+
+    /// Sets the scopes granted to the key.
+    #[must_use]
+    pub fn scopes<I, T>(self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets the quota of the key.
+    #[must_use]
+    pub fn quota(self, quota: RateLimitQuota) -> Self {
+        Self {
+            quota: Some(quota),
+            ..self
+        }
+    }
+
+    /// Returns `true` if the key has the specified scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ApiKeyInfo {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<ApiKeyInfo>()
+            .cloned()
+            .ok_or_else(|| GetDataError(std::any::type_name::<ApiKeyInfo>()))?)
+    }
+}
+
+/// Represents a store of the API keys.
+#[async_trait::async_trait]
+pub trait ApiKeyStore: Send + Sync {
+    /// Returns the metadata of the key, or `None` if the key is invalid.
+    async fn verify(&self, key: &str) -> Result<Option<ApiKeyInfo>>;
+}
+
+/// An API key store using memory.
+#[derive(Default)]
+pub struct MemoryApiKeyStore {
+    keys: HashMap<String, ApiKeyInfo>,
+}
+
+impl MemoryApiKeyStore {
+    /// Create an empty `MemoryApiKeyStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a key with its metadata.
+    #[must_use]
+    pub fn key(mut self, key: impl Into<String>, info: ApiKeyInfo) -> Self {
+        self.keys.insert(key.into(), info);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for MemoryApiKeyStore {
+    async fn verify(&self, key: &str) -> Result<Option<ApiKeyInfo>> {
+        Ok(self.keys.get(key).cloned())
+    }
+}
+
+/// Where [`ApiKey`] middleware extracts the key from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ApiKeySource {
+    /// The request header.
+    Header(HeaderName),
+
+    /// The query parameter.
+    Query(String),
+
+    /// The cookie.
+    Cookie(String),
+}
+
+impl ApiKeySource {
+    fn extract(&self, req: &Request) -> Option<String> {
+        match self {
+            ApiKeySource::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+            ApiKeySource::Query(name) => {
+                serde_urlencoded::from_str::<Vec<(String, String)>>(req.uri().query()?)
+                    .ok()?
+                    .into_iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, value)| value)
+            }
+            ApiKeySource::Cookie(name) => req
+                .headers()
+                .get_all(header::COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.to_string()),
+        }
+        .filter(|key| !key.is_empty())
+    }
+}
+
+/// Middleware for authenticating the requests with the API keys.
+///
+/// The key is extracted from the sources in order, which is the `X-API-Key`
+/// header by default, and verified by the [`ApiKeyStore`]. The metadata of
+/// the key is inserted into the request extensions as [`ApiKeyInfo`], so the
+/// downstream endpoints can get it, and
+/// [`RateLimit::key_by_api_key`](crate::middleware::RateLimit::key_by_api_key)
+/// can limit the requests per key.
+///
+/// # Errors
+///
+/// - [`UnauthorizedError`] if the key is missing or invalid
+/// - [`InsufficientScopeError`] if the key does not have the required scopes
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     handler,
+///     middleware::{ApiKey, ApiKeyInfo, ApiKeySource, MemoryApiKeyStore, RateLimit},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(info: ApiKeyInfo) -> String {
+///     format!("hello {}", info.owner)
+/// }
+///
+/// let store = MemoryApiKeyStore::new().key(
+///     "secret-key",
+///     ApiKeyInfo::new("key-1", "sunli").scopes(["read"]),
+/// );
+/// let app = index
+///     .with(RateLimit::new(100, Duration::from_secs(60)).key_by_api_key())
+///     .with(
+///         ApiKey::new(store)
+///             .source(ApiKeySource::Query("api_key".to_string()))
+///             .required_scopes(["read"]),
+///     );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(app)
+///     .get("/")
+///     .query("api_key", &"secret-key")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("hello sunli").await;
+/// # });
+/// ```
+pub struct ApiKey {
+    store: Arc<dyn ApiKeyStore>,
+    sources: Vec<ApiKeySource>,
+    required_scopes: Vec<String>,
+}
+
+impl ApiKey {
+    /// Create `ApiKey` middleware with the key store.
+    pub fn new(store: impl ApiKeyStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            sources: Vec::new(),
+            required_scopes: Vec::new(),
+        }
+    }
+
+    /// Appends a source to extract the key from.
+    ///
+    /// Default is the `X-API-Key` header.
+    #[must_use]
+    pub fn source(mut self, source: ApiKeySource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Appends a header to extract the key from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header name is invalid.
+    #[must_use]
+    pub fn header<K: TryInto<HeaderName>>(self, name: K) -> Self {
+        let name = name
+            .try_into()
+            .unwrap_or_else(|_| panic!("invalid header name"));
+        self.source(ApiKeySource::Header(name))
+    }
+
+    /// Sets the scopes which the key must have.
+    #[must_use]
+    pub fn required_scopes<I, T>(self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            required_scopes: scopes.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ApiKey {
+    type Output = ApiKeyEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let sources = if self.sources.is_empty() {
+            vec![ApiKeySource::Header(HeaderName::from_static("x-api-key"))]
+        } else {
+            self.sources.clone()
+        };
+
+        ApiKeyEndpoint {
+            inner: ep,
+            store: self.store.clone(),
+            sources,
+            required_scopes: self.required_scopes.clone(),
+        }
+    }
+}
+
+/// Endpoint for ApiKey middleware.
+pub struct ApiKeyEndpoint<E> {
+    inner: E,
+    store: Arc<dyn ApiKeyStore>,
+    sources: Vec<ApiKeySource>,
+    required_scopes: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for ApiKeyEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let key = self
+            .sources
+            .iter()
+            .find_map(|source| source.extract(&req))
+            .ok_or(UnauthorizedError)?;
+        let info = self.store.verify(&key).await?.ok_or(UnauthorizedError)?;
+        if !self
+            .required_scopes
+            .iter()
+            .all(|scope| info.has_scope(scope))
+        {
+            return Err(InsufficientScopeError.into());
+        }
+
+        req.extensions_mut().insert(info);
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        handler,
+        middleware::{RateLimit, RateLimitAlgorithm},
+        test::TestClient,
+        EndpointExt,
+    };
+
+    #[handler(internal)]
+    fn index(info: ApiKeyInfo) -> String {
+        info.owner
+    }
+
+    fn store() -> MemoryApiKeyStore {
+        MemoryApiKeyStore::new()
+            .key("k1", ApiKeyInfo::new("1", "a").scopes(["read", "write"]))
+            .key(
+                "k2",
+                ApiKeyInfo::new("2", "b")
+                    .scopes(["read"])
+                    .quota(RateLimitQuota {
+                        algorithm: RateLimitAlgorithm::TokenBucket,
+                        limit: 1,
+                        period: Duration::from_secs(60),
+                    }),
+            )
+    }
+
+    #[tokio::test]
+    async fn sources() {
+        let cli = TestClient::new(
+            index.with(
+                ApiKey::new(store())
+                    .header("x-api-key")
+                    .source(ApiKeySource::Query("key".to_string()))
+                    .source(ApiKeySource::Cookie("key".to_string())),
+            ),
+        );
+
+        let resp = cli.get("/").header("x-api-key", "k1").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("a").await;
+
+        let resp = cli.get("/").query("key", &"k2").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("b").await;
+
+        let resp = cli.get("/").header("cookie", "a=1; key=k1").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("a").await;
+
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        cli.get("/")
+            .header("x-api-key", "k3")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn required_scopes() {
+        let cli = TestClient::new(index.with(ApiKey::new(store()).required_scopes(["write"])));
+        cli.get("/")
+            .header("x-api-key", "k1")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .header("x-api-key", "k2")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_per_key() {
+        let cli = TestClient::new(
+            index
+                .with(RateLimit::new(2, Duration::from_secs(60)).key_by_api_key())
+                .with(ApiKey::new(store())),
+        );
+
+        for _ in 0..2 {
+            let resp = cli.get("/").header("x-api-key", "k1").send().await;
+            resp.assert_status_is_ok();
+            resp.assert_header("x-ratelimit-limit", "2");
+        }
+        cli.get("/")
+            .header("x-api-key", "k1")
+            .send()
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+        let resp = cli.get("/").header("x-api-key", "k2").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-ratelimit-limit", "1");
+        cli.get("/")
+            .header("x-api-key", "k2")
+            .send()
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    }
+}
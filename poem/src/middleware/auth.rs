@@ -0,0 +1,363 @@
+use std::{future::Future, sync::Arc};
+
+use headers::{
+    authorization::{Basic, Bearer},
+    Authorization, HeaderMapExt,
+};
+
+use crate::{
+    error::{ResponseErrorExt, UnauthorizedError},
+    http::{header, HeaderMap, HeaderValue},
+    Endpoint, Error, Middleware, Request, Result,
+};
+
+pub(crate) fn unauthorized(challenge: &HeaderValue) -> Error {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::WWW_AUTHENTICATE, challenge.clone());
+    UnauthorizedError.with_headers(headers)
+}
+
+fn challenge(scheme: &str, realm: &str, params: &str) -> HeaderValue {
+    let realm = realm.replace('\\', "\\\\").replace('"', "\\\"");
+    HeaderValue::from_str(&format!("{} realm=\"{}\"{}", scheme, realm, params))
+        .unwrap_or_else(|_| HeaderValue::from_str(scheme).expect("valid header value"))
+}
+
+/// Middleware for the HTTP Basic authentication.
+///
+/// The verifier takes the username and password, and returns the
+/// authenticated principal, which is inserted into the request extensions,
+/// so the downstream endpoints can get it with [`Data`](crate::web::Data).
+/// If the credentials are missing or the verifier returns `None`,
+/// [`UnauthorizedError`] with the `WWW-Authenticate` header is returned.
+///
+/// # Errors
+///
+/// - [`UnauthorizedError`]
+/// - The errors returned by the verifier
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler, http::StatusCode, middleware::BasicAuth, test::TestClient, web::Data, EndpointExt,
+///     Result,
+/// };
+///
+/// #[derive(Clone)]
+/// struct User(String);
+///
+/// #[handler]
+/// fn index(Data(user): Data<&User>) -> String {
+///     format!("hello {}", user.0)
+/// }
+///
+/// let app = index.with(BasicAuth::new(|username, password| async move {
+///     // check the credentials against the database
+///     Ok::<_, poem::Error>((password == "123456").then(|| User(username)))
+/// }));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+///
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::UNAUTHORIZED);
+/// resp.assert_header(
+///     "www-authenticate",
+///     "Basic realm=\"Restricted\", charset=\"UTF-8\"",
+/// );
+///
+/// let resp = cli
+///     .get("/")
+///     .header("authorization", "Basic c3VubGk6MTIzNDU2")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("hello sunli").await;
+/// # });
+/// ```
+pub struct BasicAuth<F> {
+    verifier: Arc<F>,
+    realm: String,
+}
+
+impl<F> BasicAuth<F> {
+    /// Create `BasicAuth` middleware with the verifier.
+    pub fn new(verifier: F) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            realm: "Restricted".to_string(),
+        }
+    }
+
+    /// Sets the realm of the `WWW-Authenticate` header.
+    ///
+    /// Default is `Restricted`.
+    #[must_use]
+    pub fn realm(self, realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            ..self
+        }
+    }
+}
+
+impl<E, F, Fut, P> Middleware<E> for BasicAuth<F>
+where
+    E: Endpoint,
+    F: Fn(String, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<P>>> + Send,
+    P: Send + Sync + 'static,
+{
+    type Output = BasicAuthEndpoint<E, F>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        BasicAuthEndpoint {
+            inner: ep,
+            verifier: self.verifier.clone(),
+            challenge: challenge("Basic", &self.realm, ", charset=\"UTF-8\""),
+        }
+    }
+}
+
+/// Endpoint for BasicAuth middleware.
+pub struct BasicAuthEndpoint<E, F> {
+    inner: E,
+    verifier: Arc<F>,
+    challenge: HeaderValue,
+}
+
+#[async_trait::async_trait]
+impl<E, F, Fut, P> Endpoint for BasicAuthEndpoint<E, F>
+where
+    E: Endpoint,
+    F: Fn(String, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<P>>> + Send,
+    P: Send + Sync + 'static,
+{
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let credentials = req
+            .headers()
+            .typed_get::<Authorization<Basic>>()
+            .ok_or_else(|| unauthorized(&self.challenge))?;
+        let principal = (self.verifier)(
+            credentials.username().to_string(),
+            credentials.password().to_string(),
+        )
+        .await?
+        .ok_or_else(|| unauthorized(&self.challenge))?;
+        req.extensions_mut().insert(principal);
+        self.inner.call(req).await
+    }
+}
+
+/// Middleware for the Bearer token authentication.
+///
+/// The verifier takes the token, and returns the authenticated principal,
+/// which is inserted into the request extensions, so the downstream endpoints
+/// can get it with [`Data`](crate::web::Data). If the token is missing or the
+/// verifier returns `None`, [`UnauthorizedError`] with the `WWW-Authenticate`
+/// header is returned.
+///
+/// # Errors
+///
+/// - [`UnauthorizedError`]
+/// - The errors returned by the verifier
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler, http::StatusCode, middleware::BearerAuth, test::TestClient, web::Data,
+///     EndpointExt, Result,
+/// };
+///
+/// #[derive(Clone)]
+/// struct UserId(i64);
+///
+/// #[handler]
+/// fn index(Data(user_id): Data<&UserId>) -> String {
+///     format!("user {}", user_id.0)
+/// }
+///
+/// let app = index.with(BearerAuth::new(|token| async move {
+///     // look up the token in the database
+///     Ok::<_, poem::Error>((token == "abc").then(|| UserId(1)))
+/// }));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+///
+/// let resp = cli
+///     .get("/")
+///     .header("authorization", "Bearer abc")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("user 1").await;
+/// # });
+/// ```
+pub struct BearerAuth<F> {
+    verifier: Arc<F>,
+    realm: String,
+}
+
+impl<F> BearerAuth<F> {
+    /// Create `BearerAuth` middleware with the verifier.
+    pub fn new(verifier: F) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            realm: "Restricted".to_string(),
+        }
+    }
+
+    /// Sets the realm of the `WWW-Authenticate` header.
+    ///
+    /// Default is `Restricted`.
+    #[must_use]
+    pub fn realm(self, realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            ..self
+        }
+    }
+}
+
+impl<E, F, Fut, P> Middleware<E> for BearerAuth<F>
+where
+    E: Endpoint,
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<P>>> + Send,
+    P: Send + Sync + 'static,
+{
+    type Output = BearerAuthEndpoint<E, F>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        BearerAuthEndpoint {
+            inner: ep,
+            verifier: self.verifier.clone(),
+            challenge: challenge("Bearer", &self.realm, ""),
+            invalid_token_challenge: challenge("Bearer", &self.realm, ", error=\"invalid_token\""),
+        }
+    }
+}
+
+/// Endpoint for BearerAuth middleware.
+pub struct BearerAuthEndpoint<E, F> {
+    inner: E,
+    verifier: Arc<F>,
+    challenge: HeaderValue,
+    invalid_token_challenge: HeaderValue,
+}
+
+#[async_trait::async_trait]
+impl<E, F, Fut, P> Endpoint for BearerAuthEndpoint<E, F>
+where
+    E: Endpoint,
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<P>>> + Send,
+    P: Send + Sync + 'static,
+{
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let token = req
+            .headers()
+            .typed_get::<Authorization<Bearer>>()
+            .ok_or_else(|| unauthorized(&self.challenge))?;
+        let principal = (self.verifier)(token.token().to_string())
+            .await?
+            .ok_or_else(|| unauthorized(&self.invalid_token_challenge))?;
+        req.extensions_mut().insert(principal);
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, web::Data, EndpointExt};
+
+    #[handler(internal)]
+    fn index(Data(user): Data<&String>) -> String {
+        user.clone()
+    }
+
+    #[tokio::test]
+    async fn basic_auth() {
+        let cli = TestClient::new(
+            index.with(
+                BasicAuth::new(|username: String, password| async move {
+                    if username == "error" {
+                        return Err(Error::from_status(StatusCode::SERVICE_UNAVAILABLE));
+                    }
+                    Ok((password == "123456").then(|| username))
+                })
+                .realm("My \"Realm\""),
+            ),
+        );
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        resp.assert_header(
+            header::WWW_AUTHENTICATE,
+            r#"Basic realm="My \"Realm\"", charset="UTF-8""#,
+        );
+
+        let resp = cli
+            .get("/")
+            .typed_header(Authorization::basic("sunli", "123"))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+
+        let resp = cli
+            .get("/")
+            .typed_header(Authorization::basic("error", "123456"))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+
+        let resp = cli
+            .get("/")
+            .typed_header(Authorization::basic("sunli", "123456"))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("sunli").await;
+    }
+
+    #[tokio::test]
+    async fn bearer_auth() {
+        let cli = TestClient::new(index.with(BearerAuth::new(|token: String| async move {
+            Ok(token.strip_prefix("user-").map(ToString::to_string))
+        })));
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        resp.assert_header(header::WWW_AUTHENTICATE, r#"Bearer realm="Restricted""#);
+
+        let resp = cli
+            .get("/")
+            .header(header::AUTHORIZATION, "Bearer abc")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        resp.assert_header(
+            header::WWW_AUTHENTICATE,
+            r#"Bearer realm="Restricted", error="invalid_token""#,
+        );
+
+        let resp = cli
+            .get("/")
+            .header(header::AUTHORIZATION, "Bearer user-sunli")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("sunli").await;
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    middleware::{CacheStore, CachedResponse},
+    Result,
+};
+
+struct Entry {
+    resp: CachedResponse,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct InnerStore {
+    entries: HashMap<String, Entry>,
+    /// The keys ordered by the last used time.
+    lru: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl InnerStore {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.lru.remove(&entry.last_used);
+            entry.last_used = self.clock;
+            self.lru.insert(self.clock, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+        }
+    }
+}
+
+/// A cache store using memory, the least recently used responses are evicted
+/// when the capacity is reached.
+pub struct MemoryCacheStore {
+    capacity: usize,
+    inner: Mutex<InnerStore>,
+}
+
+impl Default for MemoryCacheStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryCacheStore {
+    /// Create a `MemoryCacheStore` which stores at most 1000 responses.
+    pub fn new() -> Self {
+        Self::with_capacity(1000)
+    }
+
+    /// Create a `MemoryCacheStore` which stores at most `capacity` responses.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Default::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheStore for MemoryCacheStore {
+    async fn get(&self, key: &str) -> Result<Option<CachedResponse>> {
+        let mut inner = self.inner.lock();
+        match inner.entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                let resp = entry.resp.clone();
+                inner.touch(key);
+                Ok(Some(resp))
+            }
+            Some(_) => {
+                inner.remove(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, resp: &CachedResponse, ttl: Duration) -> Result<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        let mut inner = self.inner.lock();
+        inner.remove(key);
+        while inner.entries.len() >= self.capacity {
+            match inner.lru.values().next().cloned() {
+                Some(key) => inner.remove(&key),
+                None => break,
+            }
+        }
+
+        inner.entries.insert(
+            key.to_string(),
+            Entry {
+                resp: resp.clone(),
+                expires_at: Instant::now() + ttl,
+                last_used: 0,
+            },
+        );
+        inner.touch(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use http::{HeaderMap, StatusCode};
+
+    use super::*;
+
+    fn response(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: body.into(),
+            stored_at: SystemTime::now(),
+        }
+    }
+
+    async fn get_body(store: &MemoryCacheStore, key: &str) -> Option<String> {
+        store
+            .get(key)
+            .await
+            .unwrap()
+            .map(|resp| String::from_utf8(resp.body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn lru() {
+        let store = MemoryCacheStore::with_capacity(2);
+        let ttl = Duration::from_secs(60);
+
+        store.set("a", &response("a"), ttl).await.unwrap();
+        store.set("b", &response("b"), ttl).await.unwrap();
+        assert_eq!(get_body(&store, "a").await.as_deref(), Some("a"));
+
+        // `b` is the least recently used
+        store.set("c", &response("c"), ttl).await.unwrap();
+        assert_eq!(get_body(&store, "b").await, None);
+        assert_eq!(get_body(&store, "a").await.as_deref(), Some("a"));
+        assert_eq!(get_body(&store, "c").await.as_deref(), Some("c"));
+
+        store.set("c", &response("d"), ttl).await.unwrap();
+        assert_eq!(get_body(&store, "c").await.as_deref(), Some("d"));
+        assert_eq!(get_body(&store, "a").await.as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn expires() {
+        let store = MemoryCacheStore::new();
+        store
+            .set("a", &response("a"), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(get_body(&store, "a").await.as_deref(), Some("a"));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(get_body(&store, "a").await, None);
+    }
+}
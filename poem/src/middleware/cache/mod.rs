@@ -0,0 +1,435 @@
+mod memory_store;
+#[cfg(feature = "redis-cache")]
+mod redis_store;
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use headers::{CacheControl, ETag, HeaderMapExt, IfNoneMatch};
+pub use memory_store::MemoryCacheStore;
+#[cfg(feature = "redis-cache")]
+pub use redis_store::RedisCacheStore;
+
+use crate::{
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    web::generate_etag,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A response stored in a [`CacheStore`].
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The status code.
+    pub status: StatusCode,
+    /// The headers, including the `ETag` header.
+    pub headers: HeaderMap,
+    /// The body.
+    pub body: Bytes,
+    /// The time when the response is stored.
+    pub stored_at: SystemTime,
+}
+
+impl CachedResponse {
+    fn etag(&self) -> Option<ETag> {
+        self.headers.typed_get::<ETag>()
+    }
+
+    fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.stored_at)
+            .unwrap_or_default()
+    }
+
+    /// Creates a `304 Not Modified` response with the headers which would have
+    /// been sent in the `200 OK` response.
+    fn not_modified(&self) -> Response {
+        let mut resp = StatusCode::NOT_MODIFIED.into_response();
+        for name in [
+            header::CACHE_CONTROL,
+            header::CONTENT_LOCATION,
+            header::DATE,
+            header::ETAG,
+            header::EXPIRES,
+            header::VARY,
+        ] {
+            for value in self.headers.get_all(&name) {
+                resp.headers_mut().append(name.clone(), value.clone());
+            }
+        }
+        resp.headers_mut()
+            .insert(header::AGE, HeaderValue::from(self.age().as_secs()));
+        resp
+    }
+}
+
+impl IntoResponse for CachedResponse {
+    fn into_response(self) -> Response {
+        let age = self.age();
+        let mut resp = Response::builder().status(self.status).body(self.body);
+        *resp.headers_mut() = self.headers;
+        resp.headers_mut()
+            .insert(header::AGE, HeaderValue::from(age.as_secs()));
+        resp
+    }
+}
+
+/// Represents a back-end storage of the cached responses.
+#[async_trait::async_trait]
+pub trait CacheStore: Send + Sync {
+    /// Returns the response stored with the key if it is not expired.
+    async fn get(&self, key: &str) -> Result<Option<CachedResponse>>;
+
+    /// Stores the response with the key, it expires after `ttl`.
+    async fn set(&self, key: &str, resp: &CachedResponse, ttl: Duration) -> Result<()>;
+}
+
+/// Middleware for caching the responses.
+///
+/// The responses of the `GET` and `HEAD` requests are stored with the method,
+/// the URI and the values of the request headers specified by
+/// [`Cache::vary`]. A response is stored if:
+///
+/// - its status code is `200 OK`
+/// - its `Cache-Control` header does not contain `no-store`, `no-cache` or
+///   `private`, and specifies the `max-age` or `s-maxage` directive, or
+///   [`Cache::default_ttl`] is set
+/// - it does not have the `Set-Cookie` header
+/// - all headers in its `Vary` header are specified by [`Cache::vary`]
+///
+/// A strong `ETag` is generated for the stored responses without one, and
+/// `304 Not Modified` is returned if it matches the `If-None-Match` header of
+/// the request.
+///
+/// The responses are stored in a [`MemoryCacheStore`] by default, use
+/// [`RedisCacheStore`] to share them between the servers.
+///
+/// NOTE: The bodies of the stored responses are read into memory, so do not
+/// use it for the streaming responses.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::{header, StatusCode},
+///     middleware::Cache,
+///     test::TestClient,
+///     EndpointExt, IntoResponse, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> impl IntoResponse {
+///     "hello".with_header(header::CACHE_CONTROL, "max-age=60")
+/// }
+///
+/// let app = Route::new().at("/", get(index)).with(Cache::new());
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// let etag = resp.0.headers().get(header::ETAG).unwrap().clone();
+///
+/// cli.get("/")
+///     .header(header::IF_NONE_MATCH, etag)
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_MODIFIED);
+/// # });
+/// ```
+pub struct Cache {
+    store: Arc<dyn CacheStore>,
+    vary: Vec<HeaderName>,
+    default_ttl: Option<Duration>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(MemoryCacheStore::new()),
+            vary: vec![header::ACCEPT_ENCODING],
+            default_ttl: None,
+        }
+    }
+}
+
+impl Cache {
+    /// Create `Cache` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the store of the cached responses.
+    #[must_use]
+    pub fn store(self, store: impl CacheStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            ..self
+        }
+    }
+
+    /// Sets the request headers whose values are a part of the cache key, the
+    /// responses varying on the other headers are not stored.
+    ///
+    /// Default is `[Accept-Encoding]`.
+    #[must_use]
+    pub fn vary(self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        Self {
+            vary: headers.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the time to live of the responses whose `Cache-Control` header
+    /// does not specify `max-age` or `s-maxage`.
+    ///
+    /// Default is `None`, such responses are not stored.
+    #[must_use]
+    pub fn default_ttl(self, ttl: Duration) -> Self {
+        Self {
+            default_ttl: Some(ttl),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Cache {
+    type Output = CacheEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CacheEndpoint {
+            inner: ep,
+            store: self.store.clone(),
+            vary: self.vary.clone(),
+            default_ttl: self.default_ttl,
+        }
+    }
+}
+
+/// Endpoint for Cache middleware.
+pub struct CacheEndpoint<E> {
+    inner: E,
+    store: Arc<dyn CacheStore>,
+    vary: Vec<HeaderName>,
+    default_ttl: Option<Duration>,
+}
+
+impl<E> CacheEndpoint<E> {
+    fn cache_key(&self, req: &Request) -> String {
+        let mut key = format!("{} {}", req.method(), req.original_uri());
+        for name in &self.vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            for value in req.headers().get_all(name) {
+                key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+                key.push(',');
+            }
+        }
+        key
+    }
+
+    /// Returns the time to live of the response if it can be stored.
+    fn ttl(&self, resp: &Response) -> Option<Duration> {
+        if resp.status() != StatusCode::OK || resp.headers().contains_key(header::SET_COOKIE) {
+            return None;
+        }
+
+        let vary_covered = resp
+            .headers()
+            .get_all(header::VARY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .all(|name| {
+                self.vary
+                    .iter()
+                    .any(|vary| vary.as_str().eq_ignore_ascii_case(name))
+            });
+        if !vary_covered {
+            return None;
+        }
+
+        match resp.headers().typed_get::<CacheControl>() {
+            Some(cache_control)
+                if cache_control.no_store()
+                    || cache_control.no_cache()
+                    || cache_control.private() =>
+            {
+                None
+            }
+            Some(cache_control) => cache_control
+                .s_max_age()
+                .or_else(|| cache_control.max_age())
+                .or(self.default_ttl),
+            None => self.default_ttl,
+        }
+        .filter(|ttl| !ttl.is_zero())
+    }
+}
+
+fn is_not_modified(if_none_match: Option<&IfNoneMatch>, cached: &CachedResponse) -> bool {
+    match (if_none_match, cached.etag()) {
+        (Some(if_none_match), Some(etag)) => !if_none_match.precondition_passes(&etag),
+        _ => false,
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for CacheEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let key = self.cache_key(&req);
+        let if_none_match = req.headers().typed_get::<IfNoneMatch>();
+        if let Some(cached) = self.store.get(&key).await? {
+            if is_not_modified(if_none_match.as_ref(), &cached) {
+                return Ok(cached.not_modified());
+            }
+            return Ok(cached.into_response());
+        }
+
+        let resp = self.inner.call(req).await?.into_response();
+        let ttl = match self.ttl(&resp) {
+            Some(ttl) => ttl,
+            None => return Ok(resp),
+        };
+
+        let (parts, body) = resp.into_parts();
+        let body = body.into_bytes().await?;
+        let mut cached = CachedResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+            stored_at: SystemTime::now(),
+        };
+        if !cached.headers.contains_key(header::ETAG) {
+            cached.headers.typed_insert(generate_etag(&cached.body));
+        }
+        self.store.set(&key, &cached, ttl).await?;
+
+        if is_not_modified(if_none_match.as_ref(), &cached) {
+            return Ok(cached.not_modified());
+        }
+        Ok(cached.into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{endpoint::make_sync, test::TestClient, EndpointExt};
+
+    fn counter(
+        cache_control: &'static str,
+    ) -> (Arc<AtomicUsize>, impl Endpoint<Output = impl IntoResponse>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let ep = make_sync({
+            let count = count.clone();
+            move |req| {
+                let n = count.fetch_add(1, Ordering::SeqCst);
+                format!("{} {}", req.uri(), n).with_header(header::CACHE_CONTROL, cache_control)
+            }
+        });
+        (count, ep)
+    }
+
+    #[tokio::test]
+    async fn cache() {
+        let (count, ep) = counter("max-age=60");
+        let cli = TestClient::new(ep.with(Cache::new()));
+
+        let resp = cli.get("/a").send().await;
+        resp.assert_header(header::AGE, "0");
+        let etag = resp.0.headers().get(header::ETAG).unwrap().clone();
+        resp.assert_text("/a 0").await;
+        cli.get("/a").send().await.assert_text("/a 0").await;
+        cli.get("/b").send().await.assert_text("/b 1").await;
+        cli.get("/a")
+            .query("q", &1)
+            .send()
+            .await
+            .assert_text("/a?q=1 2")
+            .await;
+
+        let resp = cli
+            .get("/a")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .send()
+            .await;
+        resp.assert_status(StatusCode::NOT_MODIFIED);
+        resp.assert_header(header::ETAG, etag.to_str().unwrap());
+        resp.assert_header(header::CACHE_CONTROL, "max-age=60");
+        cli.get("/a")
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .send()
+            .await
+            .assert_text("/a 0")
+            .await;
+
+        // the other methods are not cached
+        cli.post("/a").send().await.assert_text("/a 3").await;
+        cli.post("/a").send().await.assert_text("/a 4").await;
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn vary() {
+        let (_, ep) = counter("max-age=60");
+        let cli = TestClient::new(ep.with(Cache::new()));
+
+        cli.get("/").send().await.assert_text("/ 0").await;
+        cli.get("/")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .assert_text("/ 1")
+            .await;
+        cli.get("/")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .assert_text("/ 1")
+            .await;
+
+        let (_, ep) = counter("max-age=60");
+        let cli = TestClient::new(
+            ep.map(|resp| async move { resp.with_header(header::VARY, "Accept-Language") })
+                .with(Cache::new()),
+        );
+        cli.get("/").send().await.assert_text("/ 0").await;
+        cli.get("/").send().await.assert_text("/ 1").await;
+    }
+
+    #[tokio::test]
+    async fn cache_control() {
+        for cache_control in [
+            "no-store",
+            "no-cache",
+            "private, max-age=60",
+            "max-age=0",
+            "",
+        ] {
+            let (_, ep) = counter(cache_control);
+            let cli = TestClient::new(ep.with(Cache::new()));
+            cli.get("/").send().await.assert_text("/ 0").await;
+            cli.get("/").send().await.assert_text("/ 1").await;
+        }
+
+        let (_, ep) = counter("public");
+        let cli = TestClient::new(ep.with(Cache::new().default_ttl(Duration::from_secs(60))));
+        cli.get("/").send().await.assert_text("/ 0").await;
+        cli.get("/").send().await.assert_text("/ 0").await;
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use redis::{aio::ConnectionLike, Cmd};
+
+use crate::{
+    error::InternalServerError,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::{CacheStore, CachedResponse},
+    Result,
+};
+
+/// Encodes the headers as the lines of `name:value`, the header values never
+/// contain the newlines.
+fn encode_headers(headers: &HeaderMap) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (name, value) in headers {
+        data.extend_from_slice(name.as_str().as_bytes());
+        data.push(b':');
+        data.extend_from_slice(value.as_bytes());
+        data.push(b'\n');
+    }
+    data
+}
+
+fn decode_headers(data: &[u8]) -> Option<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for line in data.split(|c| *c == b'\n').filter(|line| !line.is_empty()) {
+        let pos = line.iter().position(|c| *c == b':')?;
+        headers.append(
+            HeaderName::from_bytes(&line[..pos]).ok()?,
+            HeaderValue::from_bytes(&line[pos + 1..]).ok()?,
+        );
+    }
+    Some(headers)
+}
+
+/// A cache store using redis.
+///
+/// # Errors
+///
+/// - [`redis::RedisError`]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis-cache")))]
+pub struct RedisCacheStore<T> {
+    connection: T,
+    prefix: String,
+}
+
+impl<T> RedisCacheStore<T> {
+    /// Create a `RedisCacheStore`.
+    pub fn new(connection: T) -> Self {
+        Self {
+            connection,
+            prefix: "poem-cache:".to_string(),
+        }
+    }
+
+    /// Sets the prefix of the redis keys.
+    ///
+    /// Default is `poem-cache:`.
+    #[must_use]
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ConnectionLike + Clone + Sync + Send> CacheStore for RedisCacheStore<T> {
+    async fn get(&self, key: &str) -> Result<Option<CachedResponse>> {
+        let (status, headers, body, stored_at): (
+            Option<u16>,
+            Option<Vec<u8>>,
+            Option<Vec<u8>>,
+            Option<u64>,
+        ) = Cmd::hget(
+            format!("{}{}", self.prefix, key),
+            &["status", "headers", "body", "stored_at"],
+        )
+        .query_async(&mut self.connection.clone())
+        .await
+        .map_err(InternalServerError)?;
+
+        Ok((|| {
+            Some(CachedResponse {
+                status: StatusCode::from_u16(status?).ok()?,
+                headers: decode_headers(&headers?)?,
+                body: body?.into(),
+                stored_at: UNIX_EPOCH + Duration::from_millis(stored_at?),
+            })
+        })())
+    }
+
+    async fn set(&self, key: &str, resp: &CachedResponse, ttl: Duration) -> Result<()> {
+        let key = format!("{}{}", self.prefix, key);
+        let stored_at = resp
+            .stored_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        redis::pipe()
+            .atomic()
+            .del(&key)
+            .ignore()
+            .hset_multiple(
+                &key,
+                &[
+                    ("status", resp.status.as_u16().to_string().into_bytes()),
+                    ("headers", encode_headers(&resp.headers)),
+                    ("body", resp.body.to_vec()),
+                    ("stored_at", stored_at.to_string().into_bytes()),
+                ],
+            )
+            .ignore()
+            .pexpire(&key, ttl.as_millis().max(1) as usize)
+            .ignore()
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .map_err(InternalServerError)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use redis::{aio::ConnectionManager, Client, ConnectionLike};
+
+    use super::*;
+
+    #[test]
+    fn test_encode_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        headers.append("x-a", HeaderValue::from_static("1:2"));
+        headers.append("x-a", HeaderValue::from_static("3"));
+        assert_eq!(decode_headers(&encode_headers(&headers)), Some(headers));
+        assert_eq!(decode_headers(b""), Some(HeaderMap::new()));
+        assert_eq!(decode_headers(b"invalid"), None);
+    }
+
+    #[tokio::test]
+    async fn redis_cache_store() {
+        let mut client = match Client::open("redis://127.0.0.1/") {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+        if !client.check_connection() {
+            return;
+        }
+
+        let store =
+            RedisCacheStore::new(ConnectionManager::new(client).await.unwrap()).prefix(format!(
+                "poem-cache-test-{}:",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos()
+            ));
+        let mut headers = HeaderMap::new();
+        headers.insert("etag", HeaderValue::from_static("\"1\""));
+        let resp = CachedResponse {
+            status: StatusCode::OK,
+            headers: headers.clone(),
+            body: "hello".into(),
+            stored_at: SystemTime::now(),
+        };
+
+        assert!(store.get("a").await.unwrap().is_none());
+        store
+            .set("a", &resp, Duration::from_millis(100))
+            .await
+            .unwrap();
+        let cached = store.get("a").await.unwrap().unwrap();
+        assert_eq!(cached.status, StatusCode::OK);
+        assert_eq!(cached.headers, headers);
+        assert_eq!(cached.body, "hello");
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(store.get("a").await.unwrap().is_none());
+    }
+}
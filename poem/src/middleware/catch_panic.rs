@@ -1,9 +1,9 @@
-use std::{any::Any, panic::AssertUnwindSafe};
+use std::{any::Any, future::Future, panic::AssertUnwindSafe, sync::Arc};
 
-use futures_util::FutureExt;
-use http::StatusCode;
+use futures_util::{future::BoxFuture, FutureExt};
+use http::{HeaderMap, Method, StatusCode, Uri, Version};
 
-use crate::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use crate::{web::RemoteAddr, Endpoint, IntoResponse, Middleware, Request, Response, Result};
 
 /// Panics handler
 pub trait PanicHandler: Clone + Sync + Send + 'static {
@@ -34,6 +34,53 @@ where
     }
 }
 
+/// The panic payload and the metadata of the request which caused the panic,
+/// which is passed to the handler specified by
+/// [`CatchPanic::with_async_handler`].
+pub struct PanicContext {
+    /// The panic payload.
+    pub payload: Box<dyn Any + Send + 'static>,
+
+    /// The method of the request.
+    pub method: Method,
+
+    /// The URI of the request.
+    pub uri: Uri,
+
+    /// The version of the request.
+    pub version: Version,
+
+    /// The headers of the request.
+    pub headers: HeaderMap,
+
+    /// The remote address of the request.
+    pub remote_addr: RemoteAddr,
+}
+
+impl PanicContext {
+    fn new(req: &Request) -> Self {
+        Self {
+            payload: Box::new(()),
+            method: req.method().clone(),
+            uri: req.original_uri().clone(),
+            version: req.version(),
+            headers: req.headers().clone(),
+            remote_addr: req.remote_addr().clone(),
+        }
+    }
+
+    /// Returns the panic message if the payload is a string, such as the
+    /// payload of `panic!("message")`.
+    pub fn message(&self) -> Option<&str> {
+        self.payload
+            .downcast_ref::<&'static str>()
+            .copied()
+            .or_else(|| self.payload.downcast_ref::<String>().map(String::as_str))
+    }
+}
+
+type AsyncPanicHandler = Arc<dyn Fn(PanicContext) -> BoxFuture<'static, Response> + Send + Sync>;
+
 /// Middleware for catches panics and converts them into `500 INTERNAL SERVER
 /// ERROR` responses.
 ///
@@ -58,13 +105,17 @@ where
 /// ```
 pub struct CatchPanic<H> {
     panic_handler: H,
+    async_handler: Option<AsyncPanicHandler>,
 }
 
 impl CatchPanic<()> {
     /// Create new `CatchPanic` middleware.
     #[inline]
     pub fn new() -> Self {
-        CatchPanic { panic_handler: () }
+        CatchPanic {
+            panic_handler: (),
+            async_handler: None,
+        }
     }
 }
 
@@ -107,6 +158,68 @@ impl<H> CatchPanic<H> {
     pub fn with_handler<T: PanicHandler>(self, handler: T) -> CatchPanic<T> {
         CatchPanic {
             panic_handler: handler,
+            async_handler: None,
+        }
+    }
+
+    /// Specifies an async panic handler which takes the panic payload and the
+    /// metadata of the request, it can be used to create a custom response,
+    /// such as a JSON error with the request ID, and to report the panic.
+    ///
+    /// It takes precedence over the handler specified by
+    /// [`CatchPanic::with_handler`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use http::StatusCode;
+    /// use poem::{
+    ///     handler,
+    ///     middleware::{CatchPanic, PanicContext},
+    ///     test::TestClient,
+    ///     web::Json,
+    ///     EndpointExt, IntoResponse, Route,
+    /// };
+    /// use serde_json::json;
+    ///
+    /// #[handler]
+    /// async fn index() {
+    ///     panic!("oops")
+    /// }
+    ///
+    /// let app = Route::new().at("/", index).with(CatchPanic::new().with_async_handler(
+    ///     |ctx: PanicContext| async move {
+    ///         tracing::error!(uri = %ctx.uri, message = ?ctx.message(), "panic");
+    ///         let request_id = ctx
+    ///             .headers
+    ///             .get("x-request-id")
+    ///             .and_then(|value| value.to_str().ok())
+    ///             .map(ToString::to_string);
+    ///         Json(json!({ "error": "internal server error", "request_id": request_id }))
+    ///             .with_status(StatusCode::INTERNAL_SERVER_ERROR)
+    ///     },
+    /// ));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// let resp = cli.get("/").header("x-request-id", "abc").send().await;
+    /// resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    /// resp.assert_json(json!({ "error": "internal server error", "request_id": "abc" }))
+    ///     .await;
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn with_async_handler<F, Fut, R>(self, handler: F) -> Self
+    where
+        F: Fn(PanicContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse + 'static,
+    {
+        Self {
+            async_handler: Some(Arc::new(move |ctx| {
+                handler(ctx).map(IntoResponse::into_response).boxed()
+            })),
+            ..self
         }
     }
 }
@@ -118,6 +231,7 @@ impl<E: Endpoint, H: PanicHandler> Middleware<E> for CatchPanic<H> {
         CatchPanicEndpoint {
             inner: ep,
             panic_handler: self.panic_handler.clone(),
+            async_handler: self.async_handler.clone(),
         }
     }
 }
@@ -126,6 +240,7 @@ impl<E: Endpoint, H: PanicHandler> Middleware<E> for CatchPanic<H> {
 pub struct CatchPanicEndpoint<E, H> {
     inner: E,
     panic_handler: H,
+    async_handler: Option<AsyncPanicHandler>,
 }
 
 #[async_trait::async_trait]
@@ -133,9 +248,68 @@ impl<E: Endpoint, H: PanicHandler> Endpoint for CatchPanicEndpoint<E, H> {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Result<Self::Output> {
+        let ctx = self.async_handler.as_ref().map(|_| PanicContext::new(&req));
         match AssertUnwindSafe(self.inner.call(req)).catch_unwind().await {
             Ok(resp) => resp.map(IntoResponse::into_response),
-            Err(err) => Ok(self.panic_handler.get_response(err).into_response()),
+            Err(err) => match (&self.async_handler, ctx) {
+                (Some(async_handler), Some(mut ctx)) => {
+                    ctx.payload = err;
+                    Ok(async_handler(ctx).await)
+                }
+                _ => Ok(self.panic_handler.get_response(err).into_response()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn index(req: &Request) -> &'static str {
+        if req.uri().path() == "/panic" {
+            panic!("oops");
         }
+        "hello"
+    }
+
+    #[tokio::test]
+    async fn catch_panic() {
+        let cli = TestClient::new(index.with(CatchPanic::new()));
+        cli.get("/").send().await.assert_text("hello").await;
+        let resp = cli.get("/panic").send().await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        resp.assert_text("internal server error").await;
+    }
+
+    #[tokio::test]
+    async fn async_handler() {
+        let cli = TestClient::new(index.with(CatchPanic::new().with_async_handler(
+            |ctx: PanicContext| async move {
+                tokio::task::yield_now().await;
+                format!(
+                    "{:?} {} {} {}",
+                    ctx.message(),
+                    ctx.method,
+                    ctx.uri,
+                    ctx.headers["x-id"].to_str().unwrap()
+                )
+                .with_status(StatusCode::SERVICE_UNAVAILABLE)
+            },
+        )));
+
+        cli.get("/").send().await.assert_text("hello").await;
+
+        let resp = cli
+            .post("/panic")
+            .query("a", &1)
+            .header("x-id", "abc")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.assert_text(r#"Some("oops") POST /panic?a=1 abc"#)
+            .await;
     }
 }
@@ -0,0 +1,469 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    error::{CircuitOpenError, TimeoutError},
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CircuitState {
+    /// The requests are passed to the endpoint and the failures are counted.
+    Closed,
+    /// The requests are rejected until the cooldown period elapses.
+    Open,
+    /// A limited number of the trial requests are passed to the endpoint, the
+    /// circuit is closed if they succeed, otherwise it is opened again.
+    HalfOpen,
+}
+
+type StateChangeHook = Arc<dyn Fn(CircuitState, CircuitState) + Send + Sync>;
+
+/// Middleware for stopping calling a failing endpoint for a cooldown period.
+///
+/// The outcomes of the recent requests are recorded, a request fails if the
+/// endpoint returns a response or an error with a `5xx` status code, or it
+/// does not complete within the [`CircuitBreaker::call_timeout`]. When the
+/// failure rate reaches the threshold, the circuit is opened and the requests
+/// are rejected with [`CircuitOpenError`] immediately. After the cooldown
+/// period, some trial requests are passed to the endpoint to decide whether to
+/// close the circuit.
+///
+/// Each endpoint transformed by this middleware has its own circuit.
+///
+/// # Errors
+///
+/// - [`CircuitOpenError`]
+/// - [`TimeoutError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     middleware::{CircuitBreaker, CircuitState},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn upstream() -> StatusCode {
+///     StatusCode::BAD_GATEWAY
+/// }
+///
+/// let app = Route::new().at(
+///     "/",
+///     get(upstream).with(
+///         CircuitBreaker::new()
+///             .failure_rate_threshold(0.5)
+///             .minimum_requests(2)
+///             .cooldown(Duration::from_secs(30))
+///             .on_state_change(|from: CircuitState, to: CircuitState| {
+///                 println!("circuit breaker: {:?} -> {:?}", from, to);
+///             }),
+///     ),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::BAD_GATEWAY);
+/// cli.get("/")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::BAD_GATEWAY);
+/// cli.get("/")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    failure_rate_threshold: f64,
+    minimum_requests: usize,
+    window_size: usize,
+    cooldown: Duration,
+    half_open_requests: usize,
+    call_timeout: Option<Duration>,
+    on_state_change: Option<StateChangeHook>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            minimum_requests: 10,
+            window_size: 100,
+            cooldown: Duration::from_secs(30),
+            half_open_requests: 1,
+            call_timeout: None,
+            on_state_change: None,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Create `CircuitBreaker` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the failure rate, between `0.0` and `1.0`, at which the circuit is
+    /// opened.
+    ///
+    /// Default is `0.5`.
+    #[must_use]
+    pub fn failure_rate_threshold(self, threshold: f64) -> Self {
+        Self {
+            failure_rate_threshold: threshold,
+            ..self
+        }
+    }
+
+    /// Sets the minimum number of the recorded requests before the failure
+    /// rate is checked.
+    ///
+    /// Default is `10`.
+    #[must_use]
+    pub fn minimum_requests(self, minimum_requests: usize) -> Self {
+        Self {
+            minimum_requests,
+            ..self
+        }
+    }
+
+    /// Sets the number of the recent requests whose outcomes are recorded.
+    ///
+    /// Default is `100`.
+    #[must_use]
+    pub fn window_size(self, window_size: usize) -> Self {
+        Self {
+            window_size,
+            ..self
+        }
+    }
+
+    /// Sets how long the circuit stays open before the trial requests.
+    ///
+    /// Default is `30s`.
+    #[must_use]
+    pub fn cooldown(self, cooldown: Duration) -> Self {
+        Self { cooldown, ..self }
+    }
+
+    /// Sets the maximum number of the concurrent trial requests when the
+    /// circuit is half-open.
+    ///
+    /// Default is `1`.
+    #[must_use]
+    pub fn half_open_requests(self, half_open_requests: usize) -> Self {
+        Self {
+            half_open_requests,
+            ..self
+        }
+    }
+
+    /// Sets the timeout of the endpoint, the requests exceeding it are
+    /// cancelled and counted as the failures.
+    ///
+    /// Default is `None`.
+    #[must_use]
+    pub fn call_timeout(self, timeout: Duration) -> Self {
+        Self {
+            call_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets a function which is called with the previous and the new states
+    /// when the state of the circuit changes.
+    #[must_use]
+    pub fn on_state_change<F>(self, f: F) -> Self
+    where
+        F: Fn(CircuitState, CircuitState) + Send + Sync + 'static,
+    {
+        Self {
+            on_state_change: Some(Arc::new(f)),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for CircuitBreaker {
+    type Output = CircuitBreakerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CircuitBreakerEndpoint {
+            inner: ep,
+            config: self.clone(),
+            circuit: Mutex::new(Circuit {
+                state: State::Closed,
+                outcomes: VecDeque::new(),
+                failures: 0,
+            }),
+        }
+    }
+}
+
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { in_flight: usize },
+}
+
+impl State {
+    fn kind(&self) -> CircuitState {
+        match self {
+            State::Closed => CircuitState::Closed,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+struct Circuit {
+    state: State,
+    outcomes: VecDeque<bool>,
+    failures: usize,
+}
+
+impl Circuit {
+    fn open(&mut self, cooldown: Duration) {
+        self.state = State::Open {
+            until: Instant::now() + cooldown,
+        };
+        self.outcomes.clear();
+        self.failures = 0;
+    }
+}
+
+/// Endpoint for CircuitBreaker middleware.
+pub struct CircuitBreakerEndpoint<E> {
+    inner: E,
+    config: CircuitBreaker,
+    circuit: Mutex<Circuit>,
+}
+
+impl<E> CircuitBreakerEndpoint<E> {
+    /// Returns the current state of the circuit.
+    pub fn state(&self) -> CircuitState {
+        self.circuit.lock().state.kind()
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut Circuit) -> R) -> R {
+        let (res, from, to) = {
+            let mut circuit = self.circuit.lock();
+            let from = circuit.state.kind();
+            let res = f(&mut circuit);
+            (res, from, circuit.state.kind())
+        };
+        if from != to {
+            if let Some(on_state_change) = &self.config.on_state_change {
+                on_state_change(from, to);
+            }
+        }
+        res
+    }
+
+    /// Returns `Some(is_trial)` if the request is allowed.
+    fn acquire(&self) -> Option<bool> {
+        self.update(|circuit| match &mut circuit.state {
+            State::Closed => Some(false),
+            State::Open { until } if Instant::now() >= *until => {
+                circuit.state = State::HalfOpen { in_flight: 1 };
+                Some(true)
+            }
+            State::Open { .. } => None,
+            State::HalfOpen { in_flight } if *in_flight < self.config.half_open_requests => {
+                *in_flight += 1;
+                Some(true)
+            }
+            State::HalfOpen { .. } => None,
+        })
+    }
+
+    /// Records the outcome of a request, `None` means the request is cancelled.
+    fn record(&self, trial: bool, success: Option<bool>) {
+        let config = &self.config;
+        self.update(|circuit| match (&mut circuit.state, success) {
+            (State::HalfOpen { in_flight }, None) if trial => *in_flight -= 1,
+            (State::HalfOpen { .. }, Some(true)) if trial => circuit.state = State::Closed,
+            (State::HalfOpen { .. }, Some(false)) if trial => circuit.open(config.cooldown),
+            (State::Closed, Some(success)) if !trial => {
+                circuit.outcomes.push_back(success);
+                circuit.failures += usize::from(!success);
+                if circuit.outcomes.len() > config.window_size {
+                    if let Some(false) = circuit.outcomes.pop_front() {
+                        circuit.failures -= 1;
+                    }
+                }
+
+                let total = circuit.outcomes.len();
+                if total >= config.minimum_requests.max(1)
+                    && circuit.failures as f64 / total as f64 >= config.failure_rate_threshold
+                {
+                    circuit.open(config.cooldown);
+                }
+            }
+            // the state has been changed by the other requests
+            _ => {}
+        })
+    }
+}
+
+/// Records the request as cancelled if it is dropped before completion.
+struct Permit<'a, E> {
+    ep: &'a CircuitBreakerEndpoint<E>,
+    trial: bool,
+    completed: bool,
+}
+
+impl<E> Permit<'_, E> {
+    fn complete(mut self, success: bool) {
+        self.completed = true;
+        self.ep.record(self.trial, Some(success));
+    }
+}
+
+impl<E> Drop for Permit<'_, E> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.ep.record(self.trial, None);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for CircuitBreakerEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let permit = Permit {
+            ep: self,
+            trial: self.acquire().ok_or(CircuitOpenError)?,
+            completed: false,
+        };
+
+        let res = match self.config.call_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, self.inner.call(req)).await {
+                Ok(res) => res,
+                Err(_) => Err(TimeoutError.into()),
+            },
+            None => self.inner.call(req).await,
+        }
+        .map(IntoResponse::into_response);
+
+        let status = match &res {
+            Ok(resp) => resp.status(),
+            Err(err) => err.status(),
+        };
+        permit.complete(!status.is_server_error());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, web::Query, EndpointExt};
+
+    #[derive(serde::Deserialize)]
+    struct Params {
+        status: u16,
+    }
+
+    #[handler(internal)]
+    async fn index(Query(params): Query<Params>) -> StatusCode {
+        StatusCode::from_u16(params.status).unwrap()
+    }
+
+    #[tokio::test]
+    async fn circuit_breaker() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let cli = TestClient::new(
+            index.with(
+                CircuitBreaker::new()
+                    .minimum_requests(4)
+                    .window_size(4)
+                    .cooldown(Duration::from_millis(50))
+                    .on_state_change({
+                        let changes = changes.clone();
+                        move |from, to| changes.lock().push((from, to))
+                    }),
+            ),
+        );
+        let call = |status: u16| {
+            let cli = &cli;
+            async move {
+                cli.get("/")
+                    .query("status", &status)
+                    .send()
+                    .await
+                    .0
+                    .status()
+                    .as_u16()
+            }
+        };
+
+        assert_eq!(call(200).await, 200);
+        assert_eq!(call(500).await, 500);
+        assert_eq!(call(200).await, 200);
+        // the failure rate reaches 0.5
+        assert_eq!(call(503).await, 503);
+        assert_eq!(call(200).await, 503);
+
+        // the trial request fails
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(call(500).await, 500);
+        assert_eq!(call(200).await, 503);
+
+        // the trial request succeeds
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(call(404).await, 404);
+        assert_eq!(call(500).await, 500);
+
+        assert_eq!(
+            *changes.lock(),
+            vec![
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn call_timeout() {
+        #[handler(internal)]
+        async fn slow() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let ep = slow.with(
+            CircuitBreaker::new()
+                .minimum_requests(1)
+                .call_timeout(Duration::from_millis(10)),
+        );
+        let err = ep.call(Request::default()).await.unwrap_err();
+        assert!(err.is::<TimeoutError>());
+        assert_eq!(ep.state(), CircuitState::Open);
+        let err = ep.call(Request::default()).await.unwrap_err();
+        assert!(err.is::<CircuitOpenError>());
+    }
+}
@@ -1,16 +1,18 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use headers::HeaderMap;
 
 use crate::{
-    http::header,
-    web::{Compress, CompressionAlgo},
+    http::{header, HeaderValue, StatusCode},
+    web::{Compress, CompressionAlgo, CompressionLevel},
     Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
 };
 
 enum ContentCoding {
+    Brotli,
     Deflate,
     Gzip,
+    Zstd,
     Star,
 }
 
@@ -22,6 +24,10 @@ impl FromStr for ContentCoding {
             Ok(ContentCoding::Deflate)
         } else if s.eq_ignore_ascii_case("gzip") {
             Ok(ContentCoding::Gzip)
+        } else if s.eq_ignore_ascii_case("br") {
+            Ok(ContentCoding::Brotli)
+        } else if s.eq_ignore_ascii_case("zstd") {
+            Ok(ContentCoding::Zstd)
         } else if s == "*" {
             Ok(ContentCoding::Star)
         } else {
@@ -30,7 +36,10 @@ impl FromStr for ContentCoding {
     }
 }
 
-fn parse_accept_encoding(headers: &HeaderMap) -> Option<ContentCoding> {
+fn parse_accept_encoding(
+    headers: &HeaderMap,
+    enabled: &[CompressionAlgo],
+) -> Option<CompressionAlgo> {
     headers
         .get_all(header::ACCEPT_ENCODING)
         .iter()
@@ -41,11 +50,22 @@ fn parse_accept_encoding(headers: &HeaderMap) -> Option<ContentCoding> {
                 Some((e, q)) => (e, (q.parse::<f32>().ok()? * 1000.0) as i32),
                 None => (v, 1000),
             };
-            let coding: ContentCoding = e.parse().ok()?;
-            Some((coding, q))
+            let algo = match e.trim().parse().ok()? {
+                ContentCoding::Brotli => CompressionAlgo::BR,
+                ContentCoding::Deflate => CompressionAlgo::DEFLATE,
+                ContentCoding::Gzip => CompressionAlgo::GZIP,
+                ContentCoding::Zstd => CompressionAlgo::ZSTD,
+                ContentCoding::Star if enabled.contains(&CompressionAlgo::GZIP) => {
+                    CompressionAlgo::GZIP
+                }
+                ContentCoding::Star => {
+                    *enabled.iter().max_by_key(|algo| coding_priority(**algo))?
+                }
+            };
+            (q > 0 && enabled.contains(&algo)).then(|| (algo, q))
         })
-        .max_by_key(|(coding, q)| (*q, coding_priority(coding)))
-        .map(|(coding, _)| coding)
+        .max_by_key(|(algo, q)| (*q, coding_priority(*algo)))
+        .map(|(algo, _)| algo)
 }
 
 /// Middleware for decompress request body and compress response body.
@@ -53,9 +73,57 @@ fn parse_accept_encoding(headers: &HeaderMap) -> Option<ContentCoding> {
 /// It selects the decompression algorithm according to the request
 /// `Content-Encoding` header, and selects the compression algorithm according
 /// to the request `Accept-Encoding` header.
+///
+/// The response bodies are compressed as streams, so the large responses are
+/// not buffered. The responses which already have the `Content-Encoding`
+/// header are not compressed.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::Compression,
+///     web::{CompressionAlgo, CompressionLevel},
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = index.with(
+///     Compression::new()
+///         .algorithms([CompressionAlgo::ZSTD, CompressionAlgo::GZIP])
+///         .with_quality(CompressionAlgo::ZSTD, CompressionLevel::Precise(6))
+///         .min_size(1024)
+///         .content_types(["text/*", "application/json"]),
+/// );
+/// ```
 #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
-#[derive(Default)]
-pub struct Compression;
+pub struct Compression {
+    algorithms: Vec<CompressionAlgo>,
+    levels: HashMap<CompressionAlgo, CompressionLevel>,
+    min_size: u64,
+    content_types: Option<Vec<String>>,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            algorithms: vec![
+                CompressionAlgo::BR,
+                CompressionAlgo::DEFLATE,
+                CompressionAlgo::GZIP,
+                CompressionAlgo::ZSTD,
+            ],
+            levels: HashMap::new(),
+            min_size: 0,
+            content_types: None,
+        }
+    }
+}
 
 impl Compression {
     /// Creates a new `Compression` middleware.
@@ -63,13 +131,112 @@ impl Compression {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Sets the algorithms used to compress the response bodies.
+    ///
+    /// Default is all algorithms.
+    #[must_use]
+    pub fn algorithms(self, algorithms: impl IntoIterator<Item = CompressionAlgo>) -> Self {
+        Self {
+            algorithms: algorithms.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the quality of the specified algorithm.
+    ///
+    /// Default is [`CompressionLevel::Default`].
+    #[must_use]
+    pub fn with_quality(mut self, algo: CompressionAlgo, level: CompressionLevel) -> Self {
+        self.levels.insert(algo, level);
+        self
+    }
+
+    /// Sets the minimum size of the response bodies to compress, the responses
+    /// whose size is unknown, such as the streaming responses, are always
+    /// compressed.
+    ///
+    /// Default is `0`.
+    #[must_use]
+    pub fn min_size(self, min_size: u64) -> Self {
+        Self { min_size, ..self }
+    }
+
+    /// Sets the content types of the responses to compress, such as
+    /// `text/html` or `text/*`.
+    ///
+    /// Default is all content types.
+    #[must_use]
+    pub fn content_types<I, T>(self, content_types: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            content_types: Some(
+                content_types
+                    .into_iter()
+                    .map(|content_type| content_type.into().to_ascii_lowercase())
+                    .collect(),
+            ),
+            ..self
+        }
+    }
+
+    fn should_compress(&self, resp: &Response, body_size: Option<u64>) -> bool {
+        if resp.status() == StatusCode::NO_CONTENT
+            || resp.status() == StatusCode::NOT_MODIFIED
+            || resp.headers().contains_key(header::CONTENT_ENCODING)
+        {
+            return false;
+        }
+
+        let body_size = body_size.or_else(|| {
+            resp.headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        });
+        if matches!(body_size, Some(size) if size < self.min_size) {
+            return false;
+        }
+
+        match &self.content_types {
+            Some(content_types) => {
+                let essence = match resp.content_type() {
+                    Some(content_type) => content_type
+                        .split(';')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_ascii_lowercase(),
+                    None => return false,
+                };
+                content_types
+                    .iter()
+                    .any(|content_type| match content_type.strip_suffix('*') {
+                        Some(prefix) => essence.starts_with(prefix),
+                        None => essence == *content_type,
+                    })
+            }
+            None => true,
+        }
+    }
 }
 
 impl<E: Endpoint> Middleware<E> for Compression {
     type Output = CompressionEndpoint<E>;
 
     fn transform(&self, ep: E) -> Self::Output {
-        CompressionEndpoint { ep }
+        CompressionEndpoint {
+            ep,
+            config: Compression {
+                algorithms: self.algorithms.clone(),
+                levels: self.levels.clone(),
+                min_size: self.min_size,
+                content_types: self.content_types.clone(),
+            },
+        }
     }
 }
 
@@ -77,15 +244,16 @@ impl<E: Endpoint> Middleware<E> for Compression {
 #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
 pub struct CompressionEndpoint<E: Endpoint> {
     ep: E,
+    config: Compression,
 }
 
 #[inline]
-fn coding_priority(c: &ContentCoding) -> u8 {
-    match *c {
-        ContentCoding::Deflate => 1,
-        ContentCoding::Gzip => 2,
-        // ContentCoding::BROTLI => 3,
-        _ => 0,
+fn coding_priority(algo: CompressionAlgo) -> u8 {
+    match algo {
+        CompressionAlgo::DEFLATE => 1,
+        CompressionAlgo::GZIP => 2,
+        CompressionAlgo::BR => 3,
+        CompressionAlgo::ZSTD => 4,
     }
 }
 
@@ -106,16 +274,24 @@ impl<E: Endpoint> Endpoint for CompressionEndpoint<E> {
         }
 
         // negotiate content-encoding
-        let compress_algo = parse_accept_encoding(req.headers()).map(|coding| match coding {
-            ContentCoding::Gzip => CompressionAlgo::GZIP,
-            ContentCoding::Deflate => CompressionAlgo::DEFLATE,
-            // ContentCoding::STAR | ContentCoding::BROTLI => Some(CompressionAlgo::BR),
-            ContentCoding::Star => CompressionAlgo::GZIP,
-        });
+        let compress_algo = parse_accept_encoding(req.headers(), &self.config.algorithms);
+
+        let mut resp = self.ep.call(req).await?.into_response();
+        let body = resp.take_body();
+        let body_size = body.exact_size();
+        resp.set_body(body);
 
         match compress_algo {
-            Some(algo) => Ok(Compress::new(self.ep.call(req).await?, algo).into_response()),
-            None => Ok(self.ep.call(req).await?.into_response()),
+            Some(algo) if self.config.should_compress(&resp, body_size) => {
+                let level = self.config.levels.get(&algo).copied().unwrap_or_default();
+                let mut resp = Compress::new(resp, algo)
+                    .with_quality(level)
+                    .into_response();
+                resp.headers_mut()
+                    .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+                Ok(resp)
+            }
+            _ => Ok(resp),
         }
     }
 }
@@ -136,7 +312,7 @@ mod tests {
     }
 
     async fn test_algo(algo: CompressionAlgo) {
-        let ep = index.with(Compression);
+        let ep = index.with(Compression::new());
         let cli = TestClient::new(ep);
 
         let resp = cli
@@ -158,14 +334,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_compression() {
-        // test_algo(CompressionAlgo::BR).await;
+        test_algo(CompressionAlgo::BR).await;
         test_algo(CompressionAlgo::DEFLATE).await;
         test_algo(CompressionAlgo::GZIP).await;
+        test_algo(CompressionAlgo::ZSTD).await;
     }
 
     #[tokio::test]
     async fn test_negotiate() {
-        let ep = index.with(Compression);
+        let ep = index.with(Compression::new());
         let cli = TestClient::new(ep);
 
         let resp = cli
@@ -185,7 +362,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_star() {
-        let ep = index.with(Compression);
+        let ep = index.with(Compression::new());
         let cli = TestClient::new(ep);
 
         let resp = cli
@@ -205,7 +382,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_coding_priority() {
-        let ep = index.with(Compression);
+        let ep = index.with(Compression::new());
         let cli = TestClient::new(ep);
 
         let resp = cli
@@ -215,11 +392,119 @@ mod tests {
             .send()
             .await;
         resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "br");
+
+        let mut data = Vec::new();
+        let mut reader = CompressionAlgo::BR.decompress(resp.0.into_body().into_async_read());
+        reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, DATA_REV.as_bytes());
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "gzip, deflate")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
         resp.assert_header("Content-Encoding", "gzip");
+    }
+
+    #[tokio::test]
+    async fn test_algorithms_and_quality() {
+        let ep = index.with(
+            Compression::new()
+                .algorithms([CompressionAlgo::GZIP, CompressionAlgo::ZSTD])
+                .with_quality(CompressionAlgo::ZSTD, CompressionLevel::Best),
+        );
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "br, zstd;q=0.5, gzip;q=0.1")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "zstd");
+        resp.assert_header("Vary", "accept-encoding");
 
         let mut data = Vec::new();
-        let mut reader = CompressionAlgo::GZIP.decompress(resp.0.into_body().into_async_read());
+        let mut reader = CompressionAlgo::ZSTD.decompress(resp.0.into_body().into_async_read());
         reader.read_to_end(&mut data).await.unwrap();
         assert_eq!(data, DATA_REV.as_bytes());
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "br, zstd;q=0")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("Content-Encoding");
+        resp.assert_text(DATA_REV).await;
+    }
+
+    #[tokio::test]
+    async fn test_min_size() {
+        let ep = index.with(Compression::new().min_size(64));
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "gzip")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("Content-Encoding");
+        resp.assert_text(DATA_REV).await;
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "gzip")
+            .body(DATA.repeat(2))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "gzip");
+    }
+
+    #[tokio::test]
+    async fn test_content_types() {
+        #[handler(internal)]
+        fn json() -> Response {
+            Response::builder()
+                .content_type("application/json; charset=utf-8")
+                .body("{}")
+        }
+
+        #[handler(internal)]
+        fn png() -> Response {
+            Response::builder().content_type("image/png").body("png")
+        }
+
+        let compression = Compression::new().content_types(["text/*", "application/json"]);
+        let cli = TestClient::new(
+            crate::Route::new()
+                .at("/text", index)
+                .at("/json", json)
+                .at("/png", png)
+                .with(compression),
+        );
+
+        for (path, compressed) in [("/text", true), ("/json", true), ("/png", false)] {
+            let resp = cli
+                .post(path)
+                .header("Accept-Encoding", "gzip")
+                .body(DATA)
+                .send()
+                .await;
+            resp.assert_status_is_ok();
+            if compressed {
+                resp.assert_header("Content-Encoding", "gzip");
+            } else {
+                resp.assert_header_is_not_exist("Content-Encoding");
+            }
+        }
     }
 }
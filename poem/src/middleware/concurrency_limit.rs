@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::Semaphore;
+
+use crate::{error::QueueFullError, Endpoint, Middleware, Request, Result};
+
+/// Middleware for limiting the number of the concurrent executions of an
+/// endpoint, such as an expensive report generation.
+///
+/// When the limit is reached, the requests wait in a bounded queue, and
+/// [`QueueFullError`] is returned if the queue is full. The queue is empty by
+/// default, so the requests are rejected immediately.
+///
+/// Each endpoint transformed by this middleware has its own limit.
+///
+/// # Errors
+///
+/// - [`QueueFullError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, middleware::ConcurrencyLimit, EndpointExt, Route};
+///
+/// #[handler]
+/// async fn report() -> &'static str {
+///     // generate the report
+///     "report"
+/// }
+///
+/// let app = Route::new().at(
+///     "/report",
+///     get(report).with(ConcurrencyLimit::new(4).queue_size(16)),
+/// );
+/// ```
+pub struct ConcurrencyLimit {
+    max_concurrency: usize,
+    queue_size: usize,
+}
+
+impl ConcurrencyLimit {
+    /// Create `ConcurrencyLimit` middleware which allows at most
+    /// `max_concurrency` concurrent executions.
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            max_concurrency,
+            queue_size: 0,
+        }
+    }
+
+    /// Sets the maximum number of the requests waiting for the executions.
+    ///
+    /// Default is `0`.
+    #[must_use]
+    pub fn queue_size(self, queue_size: usize) -> Self {
+        Self { queue_size, ..self }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ConcurrencyLimit {
+    type Output = ConcurrencyLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ConcurrencyLimitEndpoint {
+            inner: ep,
+            semaphore: Semaphore::new(self.max_concurrency),
+            queue_size: self.queue_size,
+            queued: Default::default(),
+        }
+    }
+}
+
+/// Endpoint for ConcurrencyLimit middleware.
+pub struct ConcurrencyLimitEndpoint<E> {
+    inner: E,
+    semaphore: Semaphore,
+    queue_size: usize,
+    queued: AtomicUsize,
+}
+
+/// Removes a request from the queue when it is dropped, even if the request
+/// is cancelled while waiting.
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for ConcurrencyLimitEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let _permit = match self.semaphore.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if self
+                    .queued
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                        (queued < self.queue_size).then(|| queued + 1)
+                    })
+                    .is_err()
+                {
+                    return Err(QueueFullError.into());
+                }
+                let _guard = QueueGuard(&self.queued);
+                self.semaphore
+                    .acquire()
+                    .await
+                    .expect("the semaphore is never closed")
+            }
+        };
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        "done"
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() {
+        let cli = TestClient::new(slow.with(ConcurrencyLimit::new(2).queue_size(1)));
+
+        let statuses =
+            join_all((0..4).map(|_| async { cli.get("/").send().await.0.status() })).await;
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|status| **status == StatusCode::OK)
+                .count(),
+            3
+        );
+        assert!(statuses.contains(&StatusCode::SERVICE_UNAVAILABLE));
+
+        // the permits and the queue are released
+        let statuses =
+            join_all((0..3).map(|_| async { cli.get("/").send().await.0.status() })).await;
+        assert!(statuses.iter().all(|status| *status == StatusCode::OK));
+    }
+}
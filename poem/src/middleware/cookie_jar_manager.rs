@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    web::cookie::{CookieJar, CookieKey},
+    web::cookie::{CookieConfig, CookieJar, CookieKey},
     Endpoint, IntoResponse, Middleware, Request, Response, Result,
 };
 
@@ -10,6 +10,8 @@ use crate::{
 #[derive(Default)]
 pub struct CookieJarManager {
     key: Option<Arc<CookieKey>>,
+    fallback_keys: Arc<Vec<CookieKey>>,
+    config: Option<Arc<CookieConfig>>,
 }
 
 impl CookieJarManager {
@@ -24,6 +26,30 @@ impl CookieJarManager {
     pub fn with_key(key: CookieKey) -> Self {
         Self {
             key: Some(Arc::new(key)),
+            ..Default::default()
+        }
+    }
+
+    /// Specify the keys that are used to verify and decrypt the cookies when
+    /// the `CookieKey` fails, so that the key can be rotated without
+    /// invalidating the existing cookies.
+    ///
+    /// The cookies are always signed and encrypted with the `CookieKey`.
+    #[must_use]
+    pub fn fallback_keys(self, keys: impl IntoIterator<Item = CookieKey>) -> Self {
+        Self {
+            fallback_keys: Arc::new(keys.into_iter().collect()),
+            ..self
+        }
+    }
+
+    /// Specify the default attributes of the cookies added to the
+    /// `CookieJar`.
+    #[must_use]
+    pub fn config(self, config: CookieConfig) -> Self {
+        Self {
+            config: Some(Arc::new(config)),
+            ..self
         }
     }
 }
@@ -38,6 +64,8 @@ where
         CookieJarManagerEndpoint {
             inner: ep,
             key: self.key.clone(),
+            fallback_keys: self.fallback_keys.clone(),
+            config: self.config.clone(),
         }
     }
 }
@@ -47,6 +75,8 @@ where
 pub struct CookieJarManagerEndpoint<E> {
     inner: E,
     key: Option<Arc<CookieKey>>,
+    fallback_keys: Arc<Vec<CookieKey>>,
+    config: Option<Arc<CookieConfig>>,
 }
 
 #[async_trait::async_trait]
@@ -57,6 +87,8 @@ impl<E: Endpoint> Endpoint for CookieJarManagerEndpoint<E> {
         if req.state().cookie_jar.is_none() {
             let mut cookie_jar = CookieJar::extract_from_headers(req.headers());
             cookie_jar.key = self.key.clone();
+            cookie_jar.fallback_keys = self.fallback_keys.clone();
+            cookie_jar.config = self.config.clone();
             req.state_mut().cookie_jar = Some(cookie_jar.clone());
             let mut resp = self.inner.call(req).await?.into_response();
             cookie_jar.append_delta_to_headers(resp.headers_mut());
@@ -123,4 +155,52 @@ mod tests {
             .await
             .assert_status_is_ok();
     }
+
+    #[tokio::test]
+    async fn test_cookie_jar_manager_key_rotation() {
+        #[handler(internal)]
+        async fn index(cookie_jar: &CookieJar) -> String {
+            let value = cookie_jar.signed().get_value::<i32>("value").unwrap();
+            cookie_jar.signed().add_value("value", value + 1);
+            value.to_string()
+        }
+
+        let old_key = CookieKey::generate();
+        let key = CookieKey::generate();
+        let cli = TestClient::new(
+            index.with(
+                CookieJarManager::with_key(key.clone())
+                    .fallback_keys([old_key.clone()])
+                    .config(CookieConfig::new().http_only(true).path("/")),
+            ),
+        );
+
+        let cookie_jar = CookieJar::default();
+        cookie_jar.signed_with_key(&old_key).add_value("value", 1);
+        let resp = cli
+            .get("/")
+            .header("Cookie", cookie_jar.get("value").unwrap().to_string())
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+
+        let cookie = Cookie::parse(
+            resp.0.headers()[crate::http::header::SET_COOKIE]
+                .to_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(cookie.http_only());
+        assert_eq!(cookie.path(), Some("/"));
+        resp.assert_text("1").await;
+
+        // the new cookie is signed with the current key
+        let cookie_jar = CookieJar::default();
+        cookie_jar.add(cookie);
+        assert_eq!(
+            cookie_jar.signed_with_key(&key).get_value::<i32>("value"),
+            Some(2)
+        );
+        assert!(cookie_jar.signed_with_key(&old_key).get("value").is_none());
+    }
 }
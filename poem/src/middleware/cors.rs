@@ -1,5 +1,6 @@
-use std::{collections::HashSet, str::FromStr, sync::Arc};
+use std::{collections::HashSet, future::Future, str::FromStr, sync::Arc};
 
+use futures_util::{future::BoxFuture, FutureExt};
 use headers::{
     AccessControlAllowHeaders, AccessControlAllowMethods, AccessControlExposeHeaders, HeaderMapExt,
 };
@@ -10,7 +11,7 @@ use crate::{
     http::{
         header,
         header::{HeaderName, HeaderValue},
-        Method,
+        HeaderMap, Method,
     },
     middleware::Middleware,
     request::Request,
@@ -18,8 +19,24 @@ use crate::{
     IntoResponse, Result,
 };
 
+type AllowOriginsFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+type AllowOriginsAsyncFn = Arc<dyn Fn(String) -> BoxFuture<'static, Result<bool>> + Send + Sync>;
+
 /// Middleware for CORS
 ///
+/// The `Vary` header of the responses always contains `Origin`, because the
+/// `Access-Control-Allow-Origin` header is set to the origin of the request,
+/// and the preflight responses also vary on the
+/// `Access-Control-Request-Method` and `Access-Control-Request-Headers`
+/// headers.
+///
+/// # Per-route configuration
+///
+/// The configuration can be overridden for the paths with
+/// [`Cors::override_path`]. A `Cors` applied to a single route also takes
+/// precedence over the outer ones for the non-preflight requests, since the
+/// headers set by it are not overwritten.
+///
 /// # Errors
 ///
 /// - [`CorsError`]
@@ -34,16 +51,17 @@ use crate::{
 ///     .allow_method(Method::POST)
 ///     .allow_credentials(false);
 /// ```
-#[derive(Default)]
-#[allow(clippy::type_complexity)]
+#[derive(Default, Clone)]
 pub struct Cors {
     allow_credentials: bool,
     allow_origins: HashSet<HeaderValue>,
-    allow_origins_fn: Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>,
+    allow_origins_fn: Option<AllowOriginsFn>,
+    allow_origins_async_fn: Option<AllowOriginsAsyncFn>,
     allow_headers: HashSet<HeaderName>,
     allow_methods: HashSet<Method>,
     expose_headers: HashSet<HeaderName>,
     max_age: i32,
+    overrides: Vec<(String, Cors)>,
 }
 
 impl Cors {
@@ -161,6 +179,59 @@ impl Cors {
         self
     }
 
+    /// Determinate allowed origins asynchronously by processing requests
+    /// which didn’t match any origins specified in the `allow_origin` and the
+    /// `allow_origins_fn`, such as looking up the origins of the tenants in
+    /// the database.
+    ///
+    /// This function will receive the `Origin` header, the errors returned by
+    /// it are returned as the responses.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     handler, http::StatusCode, middleware::Cors, test::TestClient, EndpointExt, Result,
+    /// };
+    ///
+    /// async fn is_tenant_origin(origin: &str) -> Result<bool> {
+    ///     // look up the origin in the database
+    ///     Ok(origin.ends_with(".tenants.example.com"))
+    /// }
+    ///
+    /// #[handler]
+    /// fn index() -> &'static str {
+    ///     "hello"
+    /// }
+    ///
+    /// let app = index.with(
+    ///     Cors::new().allow_origins_async_fn(|origin| async move { is_tenant_origin(&origin).await }),
+    /// );
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/")
+    ///     .header("origin", "https://a.tenants.example.com")
+    ///     .send()
+    ///     .await
+    ///     .assert_status_is_ok();
+    /// cli.get("/")
+    ///     .header("origin", "https://example.org")
+    ///     .send()
+    ///     .await
+    ///     .assert_status(StatusCode::FORBIDDEN);
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn allow_origins_async_fn<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<bool>> + Send + 'static,
+    {
+        self.allow_origins_async_fn = Some(Arc::new(move |origin| f(origin).boxed()));
+        self
+    }
+
     /// Add an expose header.
     #[must_use]
     pub fn expose_header<T>(mut self, header: T) -> Self
@@ -193,6 +264,50 @@ impl Cors {
         self.max_age = max_age;
         self
     }
+
+    /// Uses another configuration for the requests to the path, including
+    /// the preflight requests.
+    ///
+    /// The path is matched against the original path of the request, a path
+    /// ending with `/*` matches all paths under it. The overrides are checked
+    /// in the order they are added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::make_sync, http::StatusCode, middleware::Cors, test::TestClient, EndpointExt,
+    ///     Route,
+    /// };
+    ///
+    /// let app = Route::new()
+    ///     .at("/api", make_sync(|_| "api"))
+    ///     .at("/public", make_sync(|_| "public"))
+    ///     .with(
+    ///         Cors::new()
+    ///             .allow_origin("https://app.example.com")
+    ///             .override_path("/public", Cors::new()),
+    ///     );
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/api")
+    ///     .header("origin", "https://example.org")
+    ///     .send()
+    ///     .await
+    ///     .assert_status(StatusCode::FORBIDDEN);
+    /// cli.get("/public")
+    ///     .header("origin", "https://example.org")
+    ///     .send()
+    ///     .await
+    ///     .assert_status_is_ok();
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn override_path(mut self, path: impl Into<String>, cors: Cors) -> Self {
+        self.overrides.push((path.into(), cors));
+        self
+    }
 }
 
 impl<E: Endpoint> Middleware<E> for Cors {
@@ -201,27 +316,21 @@ impl<E: Endpoint> Middleware<E> for Cors {
     fn transform(&self, ep: E) -> Self::Output {
         CorsEndpoint {
             inner: ep,
-            allow_credentials: self.allow_credentials,
-            allow_origins: self.allow_origins.clone(),
-            allow_origins_fn: self.allow_origins_fn.clone(),
-            allow_headers: self.allow_headers.clone(),
-            allow_methods: self.allow_methods.clone(),
-            expose_headers: self.expose_headers.clone(),
-            allow_headers_header: self.allow_headers.clone().into_iter().collect(),
-            allow_methods_header: self.allow_methods.clone().into_iter().collect(),
-            expose_headers_header: self.expose_headers.clone().into_iter().collect(),
-            max_age: self.max_age,
+            config: CorsConfig::new(self),
+            overrides: self
+                .overrides
+                .iter()
+                .map(|(path, cors)| (path.clone(), CorsConfig::new(cors)))
+                .collect(),
         }
     }
 }
 
-/// Endpoint for Cors middleware.
-#[allow(clippy::type_complexity)]
-pub struct CorsEndpoint<E> {
-    inner: E,
+struct CorsConfig {
     allow_credentials: bool,
     allow_origins: HashSet<HeaderValue>,
-    allow_origins_fn: Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>,
+    allow_origins_fn: Option<AllowOriginsFn>,
+    allow_origins_async_fn: Option<AllowOriginsAsyncFn>,
     allow_headers: HashSet<HeaderName>,
     allow_methods: HashSet<Method>,
     expose_headers: HashSet<HeaderName>,
@@ -231,24 +340,45 @@ pub struct CorsEndpoint<E> {
     max_age: i32,
 }
 
-impl<E: Endpoint> CorsEndpoint<E> {
-    fn is_valid_origin(&self, origin: &HeaderValue) -> (bool, bool) {
+impl CorsConfig {
+    fn new(cors: &Cors) -> Self {
+        Self {
+            allow_credentials: cors.allow_credentials,
+            allow_origins: cors.allow_origins.clone(),
+            allow_origins_fn: cors.allow_origins_fn.clone(),
+            allow_origins_async_fn: cors.allow_origins_async_fn.clone(),
+            allow_headers: cors.allow_headers.clone(),
+            allow_methods: cors.allow_methods.clone(),
+            expose_headers: cors.expose_headers.clone(),
+            allow_headers_header: cors.allow_headers.clone().into_iter().collect(),
+            allow_methods_header: cors.allow_methods.clone().into_iter().collect(),
+            expose_headers_header: cors.expose_headers.clone().into_iter().collect(),
+            max_age: cors.max_age,
+        }
+    }
+
+    async fn is_valid_origin(&self, origin: &HeaderValue) -> Result<bool> {
         if self.allow_origins.contains(origin) {
-            return (true, false);
+            return Ok(true);
         }
 
-        if let Some(allow_origins_fn) = &self.allow_origins_fn {
-            if let Ok(origin) = origin.to_str() {
+        if let Ok(origin) = origin.to_str() {
+            if let Some(allow_origins_fn) = &self.allow_origins_fn {
                 if allow_origins_fn(origin) {
-                    return (true, true);
+                    return Ok(true);
+                }
+            }
+
+            if let Some(allow_origins_async_fn) = &self.allow_origins_async_fn {
+                if allow_origins_async_fn(origin.to_string()).await? {
+                    return Ok(true);
                 }
             }
         }
 
-        (
-            self.allow_origins.is_empty() && self.allow_origins_fn.is_none(),
-            true,
-        )
+        Ok(self.allow_origins.is_empty()
+            && self.allow_origins_fn.is_none()
+            && self.allow_origins_async_fn.is_none())
     }
 
     fn build_preflight_response(
@@ -296,7 +426,15 @@ impl<E: Endpoint> CorsEndpoint<E> {
             builder = builder.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
         }
 
-        builder.body(())
+        let mut resp = builder.body(());
+        for name in [
+            "Origin",
+            "Access-Control-Request-Method",
+            "Access-Control-Request-Headers",
+        ] {
+            append_vary(resp.headers_mut(), name);
+        }
+        resp
     }
 
     fn check_allow_headers<'a>(&self, req: &'a Request) -> (bool, Option<&'a HeaderValue>) {
@@ -325,23 +463,19 @@ impl<E: Endpoint> CorsEndpoint<E> {
 
         (allow_headers, request_headers)
     }
-}
-
-#[async_trait::async_trait]
-impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
-    type Output = Response;
 
-    async fn call(&self, req: Request) -> Result<Self::Output> {
+    async fn call<E: Endpoint>(&self, inner: &E, req: Request) -> Result<Response> {
         let origin = match req.headers().get(header::ORIGIN) {
             Some(origin) => origin.clone(),
             None => {
                 // This is not a CORS request if there is no Origin header
-                return self.inner.call(req).await.map(IntoResponse::into_response);
+                let mut resp = inner.call(req).await?.into_response();
+                append_vary(resp.headers_mut(), "Origin");
+                return Ok(resp);
             }
         };
 
-        let (origin_is_allow, vary_header) = self.is_valid_origin(&origin);
-        if !origin_is_allow {
+        if !self.is_valid_origin(&origin).await? {
             return Err(CorsError::OriginNotAllowed.into());
         }
 
@@ -371,29 +505,81 @@ impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
             return Ok(self.build_preflight_response(&origin, request_headers));
         }
 
-        let mut resp = self.inner.get_response(req).await;
+        let mut resp = inner.get_response(req).await;
 
-        resp.headers_mut()
-            .insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        // the headers set by an inner `Cors` take precedence
+        if !resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        {
+            resp.headers_mut()
+                .insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
 
-        if self.allow_credentials {
-            resp.headers_mut().insert(
-                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
-                HeaderValue::from_static("true"),
-            );
-        }
+            if self.allow_credentials {
+                resp.headers_mut().insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
 
-        if !self.expose_headers.is_empty() {
-            resp.headers_mut()
-                .typed_insert(self.expose_headers_header.clone());
+            if !self.expose_headers.is_empty() {
+                resp.headers_mut()
+                    .typed_insert(self.expose_headers_header.clone());
+            }
         }
 
-        if vary_header {
-            resp.headers_mut()
-                .insert(header::VARY, HeaderValue::from_static("Origin"));
+        append_vary(resp.headers_mut(), "Origin");
+        Ok(resp)
+    }
+}
+
+/// Appends the header name to the `Vary` header if it is not already
+/// contained.
+fn append_vary(headers: &mut HeaderMap, name: &'static str) {
+    let contained = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|value| value == "*" || value.eq_ignore_ascii_case(name));
+    if !contained {
+        headers.append(header::VARY, HeaderValue::from_static(name));
+    }
+}
+
+/// Endpoint for Cors middleware.
+pub struct CorsEndpoint<E> {
+    inner: E,
+    config: CorsConfig,
+    overrides: Vec<(String, CorsConfig)>,
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => {
+            path == prefix
+                || path
+                    .strip_prefix(prefix)
+                    .map(|rest| rest.starts_with('/'))
+                    .unwrap_or_default()
         }
+        None => path == pattern,
+    }
+}
 
-        Ok(resp)
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for CorsEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let config = self
+            .overrides
+            .iter()
+            .find(|(path, _)| path_matches(path, req.original_uri().path()))
+            .map(|(_, config)| config)
+            .unwrap_or(&self.config);
+        config.call(&self.inner, req).await
     }
 }
 
@@ -516,7 +702,7 @@ mod tests {
             .await;
         resp.assert_status_is_ok();
         resp.assert_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, ALLOW_ORIGIN);
-        resp.assert_header_is_not_exist(header::VARY);
+        resp.assert_header(header::VARY, "Origin");
 
         let resp = cli
             .get("/")
@@ -668,4 +854,119 @@ mod tests {
         resp.assert_status_is_ok();
         resp.assert_header(header::ACCESS_CONTROL_ALLOW_HEADERS, "content-type");
     }
+
+    #[tokio::test]
+    async fn allow_origins_async_fn() {
+        let ep = make_sync(|_| "hello").with(
+            Cors::new()
+                .allow_origin(ALLOW_ORIGIN)
+                .allow_origins_async_fn(|origin| async move {
+                    match origin.as_str() {
+                        "https://error.com" => Err(Error::from_status(StatusCode::BAD_GATEWAY)),
+                        origin => Ok(origin.ends_with(".tenant.com")),
+                    }
+                }),
+        );
+        let cli = TestClient::new(ep);
+
+        for origin in [ALLOW_ORIGIN, "https://a.tenant.com"] {
+            let resp = cli.get("/").header(header::ORIGIN, origin).send().await;
+            resp.assert_status_is_ok();
+            resp.assert_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        }
+        cli.get("/")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        cli.get("/")
+            .header(header::ORIGIN, "https://error.com")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn vary() {
+        let ep = make_sync(|_| "hello".with_header(header::VARY, "Accept-Encoding")).with(cors());
+        let cli = TestClient::new(ep);
+
+        let resp = get_request(&cli).send().await;
+        resp.assert_header_all(header::VARY, ["Accept-Encoding", "Origin"]);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_header_is_not_exist(header::ACCESS_CONTROL_ALLOW_ORIGIN);
+        resp.assert_header_all(header::VARY, ["Accept-Encoding", "Origin"]);
+
+        let resp = opt_request(&cli).send().await;
+        resp.assert_header_all(
+            header::VARY,
+            [
+                "Origin",
+                "Access-Control-Request-Method",
+                "Access-Control-Request-Headers",
+            ],
+        );
+
+        let ep = make_sync(|_| "hello".with_header(header::VARY, "*")).with(cors());
+        let resp = get_request(&TestClient::new(ep)).send().await;
+        resp.assert_header_all(header::VARY, ["*"]);
+    }
+
+    #[tokio::test]
+    async fn per_route() {
+        let ep = crate::Route::new()
+            .at("/a", make_sync(|_| "a"))
+            .at("/public/b", make_sync(|_| "b"))
+            .at(
+                "/c",
+                make_sync(|_| "c").with(
+                    Cors::new()
+                        .allow_origin("https://c.com")
+                        .expose_header("x-c"),
+                ),
+            )
+            .with(cors().override_path("/public/*", Cors::new().allow_method(Method::GET)));
+        let cli = TestClient::new(ep);
+
+        cli.get("/a")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        let resp = cli
+            .get("/public/b")
+            .header(header::ORIGIN, "https://abc.com")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "https://abc.com");
+        resp.assert_header_is_not_exist(header::ACCESS_CONTROL_ALLOW_CREDENTIALS);
+
+        let resp = cli
+            .options("/public/b")
+            .header(header::ORIGIN, "https://abc.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET");
+
+        // the inner middleware rejects the origin allowed by the outer one
+        let resp = cli
+            .get("/c")
+            .header(header::ORIGIN, ALLOW_ORIGIN)
+            .send()
+            .await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+        let ep = make_sync(|_| "c")
+            .with(Cors::new().expose_header("x-c"))
+            .with(cors());
+        let resp = get_request(&TestClient::new(ep)).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "x-c");
+        resp.assert_header_is_not_exist(header::ACCESS_CONTROL_ALLOW_CREDENTIALS);
+        resp.assert_header_all(header::VARY, ["Origin"]);
+    }
 }
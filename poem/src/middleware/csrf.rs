@@ -6,16 +6,44 @@ use libcsrf::{
 };
 
 use crate::{
+    http::HeaderName,
     middleware::{CookieJarManager, CookieJarManagerEndpoint},
     web::{
         cookie::{Cookie, SameSite},
+        csrf::{
+            generate_double_submit_token, is_valid_double_submit_token, CsrfCookie, CsrfTokenSource,
+        },
         CsrfToken, CsrfVerifier,
     },
     Endpoint, Middleware, Request, Result,
 };
 
+/// The strategy of [`Csrf`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CsrfMode {
+    /// The token is paired with the cookie and both are encrypted with the
+    /// key of the middleware, so the token can not be forged without the key.
+    ///
+    /// This is the default mode.
+    Encrypted,
+
+    /// The token is a random value which is also stored in the cookie, and
+    /// the request is valid if it submits the same value as the cookie.
+    ///
+    /// It does not need a shared key between the servers, and relies on the
+    /// fact that other sites can neither read nor write the cookie, so the
+    /// cookie should be protected by `SameSite` and `Secure`.
+    DoubleSubmit,
+}
+
 /// Middleware for Cross-Site Request Forgery (CSRF) protection.
 ///
+/// The token for the next request can be extracted with [`CsrfToken`], and
+/// the token of the request can be verified with [`CsrfVerifier`] or by the
+/// [`VerifiedCsrfToken`](crate::web::VerifiedCsrfToken) extractor, which
+/// reads it from the header or the form field.
+///
 /// # Example
 ///
 /// ```
@@ -76,23 +104,31 @@ use crate::{
 /// ```
 #[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
 pub struct Csrf {
+    mode: CsrfMode,
     cookie_name: String,
     key: [u8; 32],
     secure: bool,
     http_only: bool,
     same_site: Option<SameSite>,
     ttl: Duration,
+    rotate: bool,
+    header_name: HeaderName,
+    form_field: String,
 }
 
 impl Default for Csrf {
     fn default() -> Self {
         Self {
+            mode: CsrfMode::Encrypted,
             cookie_name: "poem-csrf-token".to_string(),
             key: Default::default(),
             secure: true,
             http_only: true,
             same_site: Some(SameSite::Strict),
             ttl: Duration::from_secs(24 * 60 * 60),
+            rotate: false,
+            header_name: HeaderName::from_static("x-csrf-token"),
+            form_field: "csrf_token".to_string(),
         }
     }
 }
@@ -103,6 +139,22 @@ impl Csrf {
         Default::default()
     }
 
+    /// Sets the strategy of the protection. Default is
+    /// [`CsrfMode::Encrypted`].
+    #[must_use]
+    pub fn mode(self, mode: CsrfMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Sets the name of the csrf cookie. Default is `poem-csrf-token`.
+    #[must_use]
+    pub fn cookie_name(self, name: impl Into<String>) -> Self {
+        Self {
+            cookie_name: name.into(),
+            ..self
+        }
+    }
+
     /// Sets AES256 key to provide signed, encrypted CSRF tokens and cookies.
     #[must_use]
     pub fn key(self, key: [u8; 32]) -> Self {
@@ -146,6 +198,43 @@ impl Csrf {
     pub fn ttl(self, ttl: Duration) -> Self {
         Self { ttl, ..self }
     }
+
+    /// Issues a new cookie for each request, so the token can only be used
+    /// by the next request, such as the submission of the form containing
+    /// it, and the tokens of the previous forms are invalidated.
+    ///
+    /// NOTE: The forms opened in the other tabs are also invalidated.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn rotate_per_request(self, enable: bool) -> Self {
+        Self {
+            rotate: enable,
+            ..self
+        }
+    }
+
+    /// Sets the header from which
+    /// [`VerifiedCsrfToken`](crate::web::VerifiedCsrfToken) reads the token.
+    /// Default is `X-CSRF-Token`.
+    #[must_use]
+    pub fn header_name(self, name: HeaderName) -> Self {
+        Self {
+            header_name: name,
+            ..self
+        }
+    }
+
+    /// Sets the form field from which
+    /// [`VerifiedCsrfToken`](crate::web::VerifiedCsrfToken) reads the token.
+    /// Default is `csrf_token`.
+    #[must_use]
+    pub fn form_field(self, name: impl Into<String>) -> Self {
+        Self {
+            form_field: name.into(),
+            ..self
+        }
+    }
 }
 
 impl<E: Endpoint> Middleware<E> for Csrf {
@@ -154,12 +243,18 @@ impl<E: Endpoint> Middleware<E> for Csrf {
     fn transform(&self, ep: E) -> Self::Output {
         CookieJarManager::new().transform(CsrfEndpoint {
             inner: ep,
+            mode: self.mode,
             protect: Arc::new(AesGcmCsrfProtection::from_key(self.key)),
             cookie_name: self.cookie_name.clone(),
             secure: self.secure,
             http_only: self.http_only,
             same_site: self.same_site,
             ttl: self.ttl,
+            rotate: self.rotate,
+            source: Arc::new(CsrfTokenSource {
+                header_name: self.header_name.clone(),
+                form_field: self.form_field.clone(),
+            }),
         })
     }
 }
@@ -168,12 +263,15 @@ impl<E: Endpoint> Middleware<E> for Csrf {
 #[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
 pub struct CsrfEndpoint<E> {
     inner: E,
+    mode: CsrfMode,
     protect: Arc<AesGcmCsrfProtection>,
     cookie_name: String,
     secure: bool,
     http_only: bool,
     same_site: Option<SameSite>,
     ttl: Duration,
+    rotate: bool,
+    source: Arc<CsrfTokenSource>,
 }
 
 impl<E> CsrfEndpoint<E> {
@@ -196,6 +294,42 @@ impl<E> CsrfEndpoint<E> {
             .generate_token_pair(existing_cookie_bytes.as_ref(), self.ttl.as_secs() as i64)
             .expect("couldn't generate token/cookie pair")
     }
+
+    /// Returns the existing cookie and the token and the cookie value for
+    /// the next request.
+    fn issue_token(&self, existing_cookie: Option<&str>) -> (Option<CsrfCookie>, String, String) {
+        match self.mode {
+            CsrfMode::Encrypted => {
+                let existing_cookie = existing_cookie
+                    .and_then(|cookie| base64::decode(cookie).ok())
+                    .and_then(|value| self.protect.parse_cookie(&value).ok());
+                let (token, cookie) = self.generate_token(if self.rotate {
+                    None
+                } else {
+                    existing_cookie.as_ref()
+                });
+                (
+                    existing_cookie.map(CsrfCookie::Encrypted),
+                    base64::encode(token.value()),
+                    base64::encode(cookie.value()),
+                )
+            }
+            CsrfMode::DoubleSubmit => {
+                let existing_cookie = existing_cookie.map(ToString::to_string);
+                let token = match &existing_cookie {
+                    Some(cookie) if !self.rotate && is_valid_double_submit_token(cookie) => {
+                        cookie.clone()
+                    }
+                    _ => generate_double_submit_token(self.ttl.as_secs()),
+                };
+                (
+                    existing_cookie.map(CsrfCookie::DoubleSubmit),
+                    token.clone(),
+                    token,
+                )
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -206,13 +340,11 @@ impl<E: Endpoint> Endpoint for CsrfEndpoint<E> {
         let existing_cookie = req
             .cookie()
             .get(&self.cookie_name)
-            .and_then(|cookie| base64::decode(cookie.value_str()).ok())
-            .and_then(|value| self.protect.parse_cookie(&value).ok());
+            .map(|cookie| cookie.value_str().to_string());
 
-        let (token, cookie) = self.generate_token(existing_cookie.as_ref());
+        let (existing_cookie, token, cookie) = self.issue_token(existing_cookie.as_deref());
         let csrf_cookie = {
-            let mut cookie =
-                Cookie::new_with_str(&self.cookie_name, base64::encode(cookie.value()));
+            let mut cookie = Cookie::new_with_str(&self.cookie_name, cookie);
             cookie.set_secure(self.secure);
             cookie.set_http_only(self.http_only);
             cookie.set_same_site(self.same_site);
@@ -221,10 +353,12 @@ impl<E: Endpoint> Endpoint for CsrfEndpoint<E> {
         };
 
         req.cookie().add(csrf_cookie);
-        req.extensions_mut()
-            .insert(CsrfToken(base64::encode(token.value())));
-        req.extensions_mut()
-            .insert(CsrfVerifier::new(existing_cookie, self.protect.clone()));
+        req.extensions_mut().insert(CsrfToken(token));
+        req.extensions_mut().insert(CsrfVerifier::new(
+            existing_cookie,
+            self.protect.clone(),
+            self.source.clone(),
+        ));
 
         self.inner.call(req).await
     }
@@ -235,7 +369,12 @@ mod tests {
     use http::{header, Method, StatusCode};
 
     use super::*;
-    use crate::{get, handler, EndpointExt, Error, IntoResponse, Result};
+    use crate::{
+        error::CsrfError,
+        get, handler,
+        web::{Form, VerifiedCsrfToken},
+        EndpointExt, Error, IntoResponse, Result,
+    };
 
     const CSRF_TOKEN_NAME: &str = "X-CSRF-Token";
 
@@ -308,4 +447,190 @@ mod tests {
             "invalid token"
         );
     }
+
+    async fn get_token<T: Endpoint>(app: &T) -> (String, String) {
+        let resp = app.call(Request::default()).await.unwrap().into_response();
+        let cookie = resp
+            .header(header::SET_COOKIE)
+            .map(|cookie| Cookie::parse(cookie).unwrap())
+            .map(|cookie| format!("{}={}", cookie.name(), cookie.value_str()))
+            .unwrap();
+        let token = resp.into_body().into_string().await.unwrap();
+        (token, cookie)
+    }
+
+    #[handler(internal)]
+    fn token_ui(token: &CsrfToken) -> String {
+        token.0.clone()
+    }
+
+    #[handler(internal)]
+    fn submit(_: VerifiedCsrfToken, Form(form): Form<Vec<(String, String)>>) -> String {
+        form.len().to_string()
+    }
+
+    #[tokio::test]
+    async fn double_submit() {
+        let app = get(token_ui)
+            .post(submit)
+            .with(Csrf::new().mode(CsrfMode::DoubleSubmit));
+
+        let (token, cookie) = get_token(&app).await;
+        assert_eq!(cookie, format!("poem-csrf-token={}", token));
+
+        // the cookie is reused
+        let resp = app
+            .call(Request::builder().header(header::COOKIE, &cookie).finish())
+            .await
+            .unwrap();
+        assert_eq!(resp.into_body().into_string().await.unwrap(), token);
+
+        let resp = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .header("x-csrf-token", &token)
+                    .header(header::COOKIE, &cookie)
+                    .content_type("application/x-www-form-urlencoded")
+                    .body("a=1"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "1");
+
+        let (other_token, _) = get_token(&app).await;
+        let err = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .header("x-csrf-token", &other_token)
+                    .header(header::COOKIE, &cookie)
+                    .finish(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CsrfError>(),
+            Some(CsrfError::InvalidToken)
+        ));
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+
+        // expired
+        let token = {
+            let mut data = base64::decode(&token).unwrap();
+            data[32..].copy_from_slice(&1u64.to_be_bytes());
+            base64::encode(data)
+        };
+        let err = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .header("x-csrf-token", &token)
+                    .header(header::COOKIE, format!("poem-csrf-token={}", token))
+                    .finish(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CsrfError>(),
+            Some(CsrfError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn verified_token_from_form() {
+        let app = get(token_ui).post(submit).with(
+            Csrf::new()
+                .header_name(HeaderName::from_static("x-token"))
+                .form_field("token"),
+        );
+        let (token, cookie) = get_token(&app).await;
+
+        let resp = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .header(header::COOKIE, &cookie)
+                    .content_type("application/x-www-form-urlencoded")
+                    .body(serde_urlencoded::to_string([("a", "1"), ("token", &token)]).unwrap()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "2");
+
+        let resp = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .header("x-token", &token)
+                    .header(header::COOKIE, &cookie)
+                    .content_type("application/x-www-form-urlencoded")
+                    .body("a=1"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "1");
+
+        let err = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .header("x-csrf-token", &token)
+                    .header(header::COOKIE, &cookie)
+                    .content_type("application/x-www-form-urlencoded")
+                    .body("a=1"),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CsrfError>(),
+            Some(CsrfError::MissingToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rotate_per_request() {
+        for mode in [CsrfMode::Encrypted, CsrfMode::DoubleSubmit] {
+            let app = get(token_ui)
+                .post(submit)
+                .with(Csrf::new().mode(mode).rotate_per_request(true));
+            let (token, cookie) = get_token(&app).await;
+
+            let resp = app
+                .call(
+                    Request::builder()
+                        .method(Method::POST)
+                        .header("x-csrf-token", &token)
+                        .header(header::COOKIE, &cookie)
+                        .content_type("application/x-www-form-urlencoded")
+                        .body("a=1"),
+                )
+                .await
+                .unwrap()
+                .into_response();
+            let new_cookie = resp
+                .header(header::SET_COOKIE)
+                .map(|cookie| Cookie::parse(cookie).unwrap())
+                .map(|cookie| format!("{}={}", cookie.name(), cookie.value_str()))
+                .unwrap();
+            assert_ne!(new_cookie, cookie);
+            assert_eq!(resp.into_body().into_string().await.unwrap(), "1");
+
+            // the previous token is invalidated by the new cookie
+            let err = app
+                .call(
+                    Request::builder()
+                        .method(Method::POST)
+                        .header("x-csrf-token", &token)
+                        .header(header::COOKIE, &new_cookie)
+                        .finish(),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CsrfError>(),
+                Some(CsrfError::InvalidToken)
+            ));
+        }
+    }
 }
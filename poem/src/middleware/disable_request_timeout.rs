@@ -0,0 +1,112 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{Endpoint, Middleware, Request, Result};
+
+/// A flag in the request extensions which is set to disable the request
+/// timeout of the server and the [`Timeout`](crate::middleware::Timeout)
+/// middlewares, disabling a flag also disables the flags of the outer timeouts.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequestTimeoutFlag {
+    disabled: Arc<AtomicBool>,
+    parent: Option<Arc<RequestTimeoutFlag>>,
+}
+
+impl RequestTimeoutFlag {
+    /// Creates a flag for an inner timeout.
+    pub(crate) fn child(&self) -> Self {
+        Self {
+            disabled: Default::default(),
+            parent: Some(Arc::new(self.clone())),
+        }
+    }
+
+    pub(crate) fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn disable(&self) {
+        self.disabled.store(true, Ordering::SeqCst);
+        if let Some(parent) = &self.parent {
+            parent.disable();
+        }
+    }
+}
+
+/// Middleware for disable the request timeout set by
+/// [`Server::request_timeout`](crate::Server::request_timeout) and the outer
+/// [`Timeout`](crate::middleware::Timeout) middlewares, for the long-running
+/// endpoints such as long polling.
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, middleware::DisableRequestTimeout, EndpointExt, Route};
+///
+/// #[handler]
+/// async fn poll() -> &'static str {
+///     // wait for the events
+///     "event"
+/// }
+///
+/// let app = Route::new().at("/poll", get(poll).with(DisableRequestTimeout));
+/// ```
+#[derive(Default)]
+pub struct DisableRequestTimeout;
+
+impl<E: Endpoint> Middleware<E> for DisableRequestTimeout {
+    type Output = DisableRequestTimeoutEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        DisableRequestTimeoutEndpoint { inner: ep }
+    }
+}
+
+/// Endpoint for DisableRequestTimeout middleware.
+pub struct DisableRequestTimeoutEndpoint<E> {
+    inner: E,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for DisableRequestTimeoutEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if let Some(flag) = req.extensions().get::<RequestTimeoutFlag>() {
+            flag.disable();
+        }
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[tokio::test]
+    async fn disable_request_timeout() {
+        #[handler(internal)]
+        fn index() {}
+
+        let flag = RequestTimeoutFlag::default();
+        let cli = TestClient::new(index.with(DisableRequestTimeout));
+        cli.get("/")
+            .data(flag.clone())
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert!(flag.is_disabled());
+    }
+
+    #[test]
+    fn disable_parent_flags() {
+        let root = RequestTimeoutFlag::default();
+        let child = root.child();
+        child.child().disable();
+        assert!(child.is_disabled());
+        assert!(root.is_disabled());
+    }
+}
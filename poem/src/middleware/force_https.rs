@@ -1,16 +1,143 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
-use http::{header, uri::Scheme, Uri};
+use http::{header, uri::Scheme, HeaderValue, Uri};
 
 use crate::{web::Redirect, Endpoint, IntoResponse, Middleware, Request, Response, Result};
 
 type FilterFn = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
 
+const ACME_CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+/// The `Strict-Transport-Security` header.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Default for Hsts {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(365 * 24 * 60 * 60),
+            include_subdomains: true,
+            preload: false,
+        }
+    }
+}
+
+impl Hsts {
+    /// Create a `Strict-Transport-Security` header with the specified max
+    /// age.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            ..Default::default()
+        }
+    }
+
+    /// Sets whether the policy applies to the subdomains.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn include_subdomains(self, include_subdomains: bool) -> Self {
+        Self {
+            include_subdomains,
+            ..self
+        }
+    }
+
+    /// Sets whether the domain should be included in the
+    /// [HSTS preload list](https://hstspreload.org), which requires the
+    /// subdomains to be included and the max age to be at least one year.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn preload(self, preload: bool) -> Self {
+        Self { preload, ..self }
+    }
+
+    pub(crate) fn header_value(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        HeaderValue::from_str(&value).expect("valid header value")
+    }
+}
+
 /// Middleware for force redirect to HTTPS uri.
-#[derive(Default)]
+///
+/// The request is considered as HTTPS if the `X-Forwarded-Proto` or
+/// `Forwarded` header set by the reverse proxy says so. The requests for the
+/// ACME `HTTP-01` challenges (`/.well-known/acme-challenge/`) are not
+/// redirected.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     middleware::{ForceHttps, Hsts},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = index.with(
+///     ForceHttps::new()
+///         .preserve_method(false)
+///         .hsts(Hsts::default().preload(true)),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+///
+/// let resp = cli.get("/a").header("host", "example.com").send().await;
+/// resp.assert_status(StatusCode::MOVED_PERMANENTLY);
+/// resp.assert_header("location", "https://example.com/a");
+///
+/// let resp = cli
+///     .get("/a")
+///     .header("host", "example.com")
+///     .header("x-forwarded-proto", "https")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header(
+///     "strict-transport-security",
+///     "max-age=31536000; includeSubDomains; preload",
+/// );
+/// # });
+/// ```
 pub struct ForceHttps {
     https_port: Option<u16>,
     filter_fn: Option<FilterFn>,
+    preserve_method: bool,
+    trust_forwarded_proto: bool,
+    exclude_acme_challenge: bool,
+    hsts: Option<Hsts>,
+}
+
+impl Default for ForceHttps {
+    fn default() -> Self {
+        Self {
+            https_port: None,
+            filter_fn: None,
+            preserve_method: true,
+            trust_forwarded_proto: true,
+            exclude_acme_challenge: true,
+            hsts: None,
+        }
+    }
 }
 
 impl ForceHttps {
@@ -36,6 +163,52 @@ impl ForceHttps {
             ..self
         }
     }
+
+    /// Sets whether to redirect with `308 Permanent Redirect`, which preserves
+    /// the method and body, or `301 Moved Permanently`.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn preserve_method(self, preserve_method: bool) -> Self {
+        Self {
+            preserve_method,
+            ..self
+        }
+    }
+
+    /// Sets whether to trust the `X-Forwarded-Proto` and `Forwarded` headers,
+    /// it should be disabled if the server is not behind a reverse proxy.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn trust_forwarded_proto(self, trust_forwarded_proto: bool) -> Self {
+        Self {
+            trust_forwarded_proto,
+            ..self
+        }
+    }
+
+    /// Sets whether to exclude the ACME `HTTP-01` challenge path
+    /// (`/.well-known/acme-challenge/`) from the redirection.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn exclude_acme_challenge(self, exclude_acme_challenge: bool) -> Self {
+        Self {
+            exclude_acme_challenge,
+            ..self
+        }
+    }
+
+    /// Sets the `Strict-Transport-Security` header to the responses of the
+    /// HTTPS requests.
+    #[must_use]
+    pub fn hsts(self, hsts: Hsts) -> Self {
+        Self {
+            hsts: Some(hsts),
+            ..self
+        }
+    }
 }
 
 impl<E> Middleware<E> for ForceHttps
@@ -49,6 +222,10 @@ where
             inner: ep,
             https_port: self.https_port,
             filter_fn: self.filter_fn.clone(),
+            preserve_method: self.preserve_method,
+            trust_forwarded_proto: self.trust_forwarded_proto,
+            exclude_acme_challenge: self.exclude_acme_challenge,
+            hsts: self.hsts.as_ref().map(Hsts::header_value),
         }
     }
 }
@@ -58,6 +235,37 @@ pub struct ForceHttpsEndpoint<E> {
     inner: E,
     https_port: Option<u16>,
     filter_fn: Option<FilterFn>,
+    preserve_method: bool,
+    trust_forwarded_proto: bool,
+    exclude_acme_challenge: bool,
+    hsts: Option<HeaderValue>,
+}
+
+impl<E> ForceHttpsEndpoint<E> {
+    fn is_https(&self, req: &Request) -> bool {
+        if req.scheme() == &Scheme::HTTPS {
+            return true;
+        }
+        if !self.trust_forwarded_proto {
+            return false;
+        }
+
+        if let Some(proto) = req.header("x-forwarded-proto") {
+            return proto
+                .split(',')
+                .next()
+                .map(|proto| proto.trim().eq_ignore_ascii_case("https"))
+                .unwrap_or_default();
+        }
+        req.header(header::FORWARDED)
+            .and_then(|value| {
+                rfc7239::parse(value)
+                    .next()
+                    .and_then(|item| item.ok()?.protocol)
+                    .map(|proto| proto.eq_ignore_ascii_case("https"))
+            })
+            .unwrap_or_default()
+    }
 }
 
 #[async_trait::async_trait]
@@ -68,7 +276,17 @@ where
     type Output = Response;
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
-        if req.scheme() == &Scheme::HTTP && self.filter_fn.as_ref().map(|f| f(&req)).unwrap_or(true)
+        if self.is_https(&req) {
+            let mut resp = self.inner.call(req).await?.into_response();
+            if let Some(hsts) = &self.hsts {
+                resp.headers_mut()
+                    .insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+            }
+            return Ok(resp);
+        }
+
+        if !(self.exclude_acme_challenge && req.uri().path().starts_with(ACME_CHALLENGE_PATH))
+            && self.filter_fn.as_ref().map(|f| f(&req)).unwrap_or(true)
         {
             if let Some(host) = req.headers().get(header::HOST).cloned() {
                 if let Ok(host) = host.to_str() {
@@ -79,7 +297,11 @@ where
                         builder = builder.path_and_query(path_and_query);
                     }
                     if let Ok(uri) = builder.build() {
-                        return Ok(Redirect::permanent(uri).into_response());
+                        return Ok(if self.preserve_method {
+                            Redirect::permanent(uri).into_response()
+                        } else {
+                            Redirect::moved_permanent(uri).into_response()
+                        });
                     }
                 }
             }
@@ -99,7 +321,10 @@ fn redirect_host(host: &str, https_port: Option<u16>) -> Cow<'_, str> {
 
 #[cfg(test)]
 mod tests {
+    use http::StatusCode;
+
     use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
 
     #[test]
     fn test_redirect_host() {
@@ -112,4 +337,69 @@ mod tests {
         assert_eq!(redirect_host("example.com:1234", None), "example.com:1234");
         assert_eq!(redirect_host("example.com", None), "example.com");
     }
+
+    #[handler(internal)]
+    fn index() -> &'static str {
+        "hello"
+    }
+
+    #[tokio::test]
+    async fn redirect() {
+        let cli = TestClient::new(index.with(ForceHttps::new().https_port(8443)));
+
+        let resp = cli
+            .post("/a?b=1")
+            .header("host", "example.com:8080")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::PERMANENT_REDIRECT);
+        resp.assert_header("location", "https://example.com:8443/a?b=1");
+
+        let resp = cli
+            .get("/.well-known/acme-challenge/token")
+            .header("host", "example.com")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("strict-transport-security");
+    }
+
+    #[tokio::test]
+    async fn forwarded_proto() {
+        let cli = TestClient::new(index.with(ForceHttps::new().hsts(Hsts::default())));
+
+        for (name, value) in [
+            ("x-forwarded-proto", "https"),
+            ("forwarded", "for=192.0.2.43;proto=https"),
+        ] {
+            let resp = cli
+                .get("/")
+                .header("host", "example.com")
+                .header(name, value)
+                .send()
+                .await;
+            resp.assert_status_is_ok();
+            resp.assert_header(
+                "strict-transport-security",
+                "max-age=31536000; includeSubDomains",
+            );
+        }
+
+        let resp = cli
+            .get("/")
+            .header("host", "example.com")
+            .header("x-forwarded-proto", "http")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::PERMANENT_REDIRECT);
+
+        let cli = TestClient::new(index.with(ForceHttps::new().trust_forwarded_proto(false)));
+        let resp = cli
+            .get("/")
+            .header("host", "example.com")
+            .header("x-forwarded-proto", "https")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::PERMANENT_REDIRECT);
+    }
 }
@@ -0,0 +1,353 @@
+use std::{convert::TryInto, sync::Arc, time::Duration};
+
+use hyper::{client::HttpConnector, Client};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+
+use crate::{
+    http::{header, HeaderName, HeaderValue, StatusCode, Uri},
+    Body, Endpoint, Error, Middleware, Request, Response, Result,
+};
+
+const X_FORWARDED_METHOD: &str = "x-forwarded-method";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+const X_FORWARDED_URI: &str = "x-forwarded-uri";
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// The headers of the auth response which are returned to the client when the
+/// request is denied.
+const DENIED_RESPONSE_HEADERS: [HeaderName; 4] = [
+    header::LOCATION,
+    header::WWW_AUTHENTICATE,
+    header::SET_COOKIE,
+    header::CONTENT_TYPE,
+];
+
+fn parse_header_name<K: TryInto<HeaderName>>(name: K) -> HeaderName {
+    name.try_into()
+        .unwrap_or_else(|_| panic!("invalid header name"))
+}
+
+#[derive(Clone)]
+struct ForwardAuthConfig {
+    address: Uri,
+    request_headers: Vec<HeaderName>,
+    response_headers: Vec<HeaderName>,
+    timeout: Duration,
+}
+
+/// Middleware for delegating the authentication to an external auth service,
+/// like the `ForwardAuth` middleware of Traefik or the `auth_request` module
+/// of Nginx used with oauth2-proxy.
+///
+/// For each request, a `GET` sub-request is sent to the auth service with the
+/// selected headers of the original request, and the `X-Forwarded-Method`,
+/// `X-Forwarded-Proto`, `X-Forwarded-Host`, `X-Forwarded-Uri` and
+/// `X-Forwarded-For` headers describing it.
+///
+/// - If the auth service responds with a `2xx` status, the request is allowed,
+///   and the configured response headers, such as `X-Auth-User`, are copied
+///   into the request.
+/// - Otherwise, the status, the body and the `Location`, `WWW-Authenticate`,
+///   `Set-Cookie` and `Content-Type` headers of the auth response are returned
+///   to the client, so the auth service can respond with `401 Unauthorized` or
+///   redirect to the sign-in page with `302 Found`.
+///
+/// If the auth service is unreachable, `502 Bad Gateway` is returned.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, http::HeaderMap, middleware::ForwardAuth, EndpointExt};
+///
+/// #[handler]
+/// fn index(headers: &HeaderMap) -> String {
+///     format!("hello {:?}", headers.get("x-auth-user"))
+/// }
+///
+/// let app = index.with(
+///     ForwardAuth::new("http://127.0.0.1:4180/oauth2/auth")
+///         .request_header("x-api-token")
+///         .response_header("x-auth-user"),
+/// );
+/// ```
+pub struct ForwardAuth {
+    config: ForwardAuthConfig,
+}
+
+impl ForwardAuth {
+    /// Create `ForwardAuth` middleware with the address of the auth service.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address is not a valid absolute URI.
+    pub fn new(address: impl AsRef<str>) -> Self {
+        let address = address.as_ref();
+        let address: Uri = address
+            .parse()
+            .ok()
+            .filter(|uri: &Uri| uri.scheme().is_some() && uri.authority().is_some())
+            .unwrap_or_else(|| panic!("invalid auth service address `{}`", address));
+
+        Self {
+            config: ForwardAuthConfig {
+                address,
+                request_headers: vec![header::AUTHORIZATION, header::COOKIE],
+                response_headers: Vec::new(),
+                timeout: Duration::from_secs(10),
+            },
+        }
+    }
+
+    /// Appends a header of the original request to be sent to the auth
+    /// service.
+    ///
+    /// Default is `Authorization` and `Cookie`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header name is invalid.
+    #[must_use]
+    pub fn request_header<K: TryInto<HeaderName>>(mut self, name: K) -> Self {
+        self.config.request_headers.push(parse_header_name(name));
+        self
+    }
+
+    /// Appends a header of the auth response to be copied into the original
+    /// request when it is allowed, the existing values of the header in the
+    /// request are replaced, so the clients cannot forge it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header name is invalid.
+    #[must_use]
+    pub fn response_header<K: TryInto<HeaderName>>(mut self, name: K) -> Self {
+        self.config.response_headers.push(parse_header_name(name));
+        self
+    }
+
+    /// Sets the timeout of the sub-requests.
+    ///
+    /// Default is `10s`.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ForwardAuth {
+    type Output = ForwardAuthEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ForwardAuthEndpoint {
+            inner: ep,
+            config: Arc::new(self.config.clone()),
+            client: Client::builder().build(
+                HttpsConnectorBuilder::new()
+                    .with_native_roots()
+                    .https_or_http()
+                    .enable_http1()
+                    .build(),
+            ),
+        }
+    }
+}
+
+/// Endpoint for ForwardAuth middleware.
+pub struct ForwardAuthEndpoint<E> {
+    inner: E,
+    config: Arc<ForwardAuthConfig>,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+impl<E> ForwardAuthEndpoint<E> {
+    fn auth_request(&self, req: &Request) -> hyper::Request<hyper::Body> {
+        let mut auth_req = hyper::Request::new(hyper::Body::empty());
+        *auth_req.uri_mut() = self.config.address.clone();
+
+        let headers = auth_req.headers_mut();
+        for name in &self.config.request_headers {
+            for value in req.headers().get_all(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+
+        let host = req.headers().get(header::HOST).cloned().or_else(|| {
+            req.uri()
+                .authority()
+                .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok())
+        });
+        let forwarded = [
+            (
+                X_FORWARDED_METHOD,
+                HeaderValue::from_str(req.method().as_str()).ok(),
+            ),
+            (
+                X_FORWARDED_PROTO,
+                HeaderValue::from_str(req.scheme().as_str()).ok(),
+            ),
+            (X_FORWARDED_HOST, host),
+            (
+                X_FORWARDED_URI,
+                req.original_uri()
+                    .path_and_query()
+                    .and_then(|path| HeaderValue::from_str(path.as_str()).ok()),
+            ),
+            (
+                X_FORWARDED_FOR,
+                req.remote_addr()
+                    .as_socket_addr()
+                    .and_then(|addr| HeaderValue::from_str(&addr.ip().to_string()).ok()),
+            ),
+        ];
+        for (name, value) in forwarded {
+            if let Some(value) = value {
+                headers.insert(name, value);
+            }
+        }
+
+        auth_req
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for ForwardAuthEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let auth_resp = tokio::time::timeout(
+            self.config.timeout,
+            self.client.request(self.auth_request(&req)),
+        )
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .ok_or_else(|| Error::from_status(StatusCode::BAD_GATEWAY))?;
+
+        if !auth_resp.status().is_success() {
+            let (parts, body) = auth_resp.into_parts();
+            let mut resp = Response::builder().status(parts.status);
+            for name in &DENIED_RESPONSE_HEADERS {
+                for value in parts.headers.get_all(name) {
+                    resp = resp.header(name.clone(), value.clone());
+                }
+            }
+            return Err(Error::from_response(resp.body(Body::from(body))));
+        }
+
+        for name in &self.config.response_headers {
+            req.headers_mut().remove(name);
+            for value in auth_resp.headers().get_all(name) {
+                req.headers_mut().append(name.clone(), value.clone());
+            }
+        }
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handler,
+        http::HeaderMap,
+        listener::{Acceptor, Listener, TcpListener},
+        test::TestClient,
+        EndpointExt, Server,
+    };
+
+    #[handler(internal)]
+    fn auth(headers: &HeaderMap) -> Response {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        match header("authorization").as_str() {
+            "Bearer ok" => Response::builder()
+                .header("x-auth-user", "sunli")
+                .header("x-auth-uri", header(X_FORWARDED_URI))
+                .header("x-auth-method", header(X_FORWARDED_METHOD))
+                .finish(),
+            "Bearer redirect" => Response::builder()
+                .status(StatusCode::FOUND)
+                .header(header::LOCATION, "/sign_in")
+                .header("x-secret", "1")
+                .finish(),
+            _ => Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body("denied"),
+        }
+    }
+
+    #[handler(internal)]
+    fn index(headers: &HeaderMap) -> String {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        format!(
+            "{:?} {:?} {:?}",
+            header("x-auth-user"),
+            header("x-auth-uri"),
+            header("x-auth-method"),
+        )
+    }
+
+    #[tokio::test]
+    async fn forward_auth() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let handle = tokio::spawn(async move {
+            let _ = Server::new_with_acceptor(acceptor).run(auth).await;
+        });
+
+        let cli = TestClient::new(
+            index.with(
+                ForwardAuth::new(format!("http://{}/auth", addr))
+                    .response_header("x-auth-user")
+                    .response_header("x-auth-uri"),
+            ),
+        );
+
+        let resp = cli
+            .post("/a?b=1")
+            .header("authorization", "Bearer ok")
+            .header("x-auth-user", "forged")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text(r#"Some("sunli") Some("/a?b=1") None"#)
+            .await;
+
+        let resp = cli.get("/").header("x-auth-user", "forged").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        resp.assert_text("denied").await;
+
+        let resp = cli
+            .get("/")
+            .header("authorization", "Bearer redirect")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::FOUND);
+        resp.assert_header(header::LOCATION, "/sign_in");
+        resp.assert_header_is_not_exist("x-secret");
+
+        handle.abort();
+        let _ = handle.await;
+
+        let cli = TestClient::new(index.with(ForwardAuth::new(format!("http://{}", addr))));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{net::IpAddr, sync::Arc};
+
+use crate::{
+    error::IpNotAllowedError,
+    web::{
+        client_ip::{parse_network, IpNet},
+        ClientIp,
+    },
+    Endpoint, Error, IntoResponse, Middleware, Request, Response, Result,
+};
+
+type ForbiddenHandler = Arc<dyn Fn(Option<IpAddr>) -> Response + Send + Sync>;
+
+/// Middleware for allowing or denying the requests by the client IP
+/// addresses.
+///
+/// The networks are in the CIDR notation, such as `10.0.0.0/8` and
+/// `2001:db8::/32`, or the single addresses. The denied networks are checked
+/// first, then the request is allowed if it matches any allowed networks, or
+/// if no allowed networks are specified.
+///
+/// The client IP is extracted by [`ClientIp`], so the forwarding header is
+/// only respected if it is set by the
+/// [`TrustedProxies`](crate::web::TrustedProxies) of the request.
+/// If the client IP is unknown, the request is only allowed if no allowed
+/// networks are specified.
+///
+/// # Errors
+///
+/// - [`IpNotAllowedError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::IpFilter, web::TrustedProxies, EndpointExt};
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = index
+///     .with(
+///         IpFilter::new()
+///             .allow("10.0.0.0/8")
+///             .allow("192.168.0.0/16")
+///             .deny("10.0.0.1"),
+///     )
+///     // the load balancer
+///     .data(TrustedProxies::new().trust("172.16.0.1"));
+/// ```
+#[derive(Default)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    on_forbidden: Option<ForbiddenHandler>,
+}
+
+impl IpFilter {
+    /// Create new `IpFilter` middleware.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Allows the requests from the specified network.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the network is invalid.
+    #[must_use]
+    pub fn allow(mut self, network: impl AsRef<str>) -> Self {
+        self.allow.push(parse_network(network.as_ref()));
+        self
+    }
+
+    /// Denies the requests from the specified network.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the network is invalid.
+    #[must_use]
+    pub fn deny(mut self, network: impl AsRef<str>) -> Self {
+        self.deny.push(parse_network(network.as_ref()));
+        self
+    }
+
+    /// Sets a function to create the response for the forbidden requests,
+    /// which takes the client IP.
+    ///
+    /// Default is the response of [`IpNotAllowedError`].
+    #[must_use]
+    pub fn on_forbidden<F, R>(self, f: F) -> Self
+    where
+        F: Fn(Option<IpAddr>) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        Self {
+            on_forbidden: Some(Arc::new(move |ip| f(ip).into_response())),
+            ..self
+        }
+    }
+
+    fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => {
+                !self.deny.iter().any(|net| net.contains(&ip))
+                    && (self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip)))
+            }
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for IpFilter {
+    type Output = IpFilterEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        IpFilterEndpoint {
+            inner: ep,
+            filter: IpFilter {
+                allow: self.allow.clone(),
+                deny: self.deny.clone(),
+                on_forbidden: self.on_forbidden.clone(),
+            },
+        }
+    }
+}
+
+/// Endpoint for IpFilter middleware.
+pub struct IpFilterEndpoint<E> {
+    inner: E,
+    filter: IpFilter,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for IpFilterEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let ip = ClientIp::resolve(&req);
+        if !self.filter.is_allowed(ip) {
+            return Err(match &self.filter.on_forbidden {
+                Some(on_forbidden) => Error::from_response(on_forbidden(ip)),
+                None => IpNotAllowedError.into(),
+            });
+        }
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        handler,
+        test::TestClient,
+        web::{RemoteAddr, TrustedProxies},
+        Addr, EndpointExt,
+    };
+
+    #[handler(internal)]
+    fn index() -> &'static str {
+        "hello"
+    }
+
+    fn behind_proxy(ep: impl Endpoint + 'static) -> impl Endpoint {
+        ep.before(|mut req| async move {
+            req.state_mut().remote_addr =
+                RemoteAddr(Addr::SocketAddr("127.0.0.1:8000".parse().unwrap()));
+            Ok(req)
+        })
+        .data(TrustedProxies::new().trust("127.0.0.1"))
+    }
+
+    #[tokio::test]
+    async fn ip_filter() {
+        let cli = TestClient::new(behind_proxy(index.with(IpFilter::new().deny("10.0.0.0/8"))));
+        cli.get("/")
+            .header("x-forwarded-for", "10.0.0.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        // the client is the nearest untrusted address
+        cli.get("/")
+            .header("x-forwarded-for", "10.0.0.1, 192.168.0.1")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .header("x-forwarded-for", "192.168.0.1, 10.0.0.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        // the headers from the untrusted peers are ignored
+        let cli = TestClient::new(index.with(IpFilter::new().allow("10.0.0.0/8")));
+        cli.get("/")
+            .header("x-forwarded-for", "10.0.0.1")
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        let cli = TestClient::new(behind_proxy(
+            index.with(IpFilter::new().allow("192.168.0.0/16").on_forbidden(
+                |ip: Option<IpAddr>| (StatusCode::FORBIDDEN, format!("{:?} is forbidden", ip)),
+            )),
+        ));
+        cli.get("/")
+            .header("x-forwarded-for", "192.168.3.4")
+            .send()
+            .await
+            .assert_status_is_ok();
+        let resp = cli
+            .get("/")
+            .header("x-forwarded-for", "10.0.0.1")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+        resp.assert_text("Some(10.0.0.1) is forbidden").await;
+    }
+}
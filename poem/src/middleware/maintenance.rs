@@ -0,0 +1,297 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{
+    error::{MaintenanceError, ResponseErrorExt},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Endpoint, Error, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A shared flag which toggles the maintenance mode of [`Maintenance`]
+/// middleware at runtime.
+#[derive(Debug, Default, Clone)]
+pub struct MaintenanceFlag(Arc<AtomicBool>);
+
+impl MaintenanceFlag {
+    /// Create a disabled `MaintenanceFlag`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Enables the maintenance mode.
+    pub fn enable(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Disables the maintenance mode.
+    pub fn disable(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the maintenance mode is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+type MaintenanceResponseFn = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// Middleware for returning `503 Service Unavailable` for all requests when
+/// the maintenance mode is enabled by the [`MaintenanceFlag`].
+///
+/// The paths in the allowlist, such as the health checks and the admin
+/// endpoints, are still served in the maintenance mode. They are matched
+/// against the original path of the request, a path ending with `/*` matches
+/// all paths under it.
+///
+/// # Errors
+///
+/// - [`MaintenanceError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     middleware::{Maintenance, MaintenanceFlag},
+///     test::TestClient,
+///     web::{Data, Json},
+///     EndpointExt, Route,
+/// };
+/// use serde_json::json;
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// #[handler]
+/// fn toggle(Data(flag): Data<&MaintenanceFlag>) {
+///     if flag.is_enabled() {
+///         flag.disable();
+///     } else {
+///         flag.enable();
+///     }
+/// }
+///
+/// let flag = MaintenanceFlag::new();
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .at("/admin/maintenance", get(toggle))
+///     .with(
+///         Maintenance::new(flag.clone())
+///             .allow("/admin/*")
+///             .retry_after(Duration::from_secs(600))
+///             .response(|_| Json(json!({ "error": "under maintenance" }))),
+///     )
+///     .data(flag);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/").send().await.assert_status_is_ok();
+///
+/// cli.get("/admin/maintenance").send().await.assert_status_is_ok();
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+/// resp.assert_header("retry-after", "600");
+/// resp.assert_json(json!({ "error": "under maintenance" })).await;
+///
+/// cli.get("/admin/maintenance").send().await.assert_status_is_ok();
+/// cli.get("/").send().await.assert_status_is_ok();
+/// # });
+/// ```
+pub struct Maintenance {
+    flag: MaintenanceFlag,
+    allowlist: Vec<String>,
+    retry_after: Option<Duration>,
+    response: Option<MaintenanceResponseFn>,
+}
+
+impl Maintenance {
+    /// Create `Maintenance` middleware with the flag.
+    pub fn new(flag: MaintenanceFlag) -> Self {
+        Self {
+            flag,
+            allowlist: Vec::new(),
+            retry_after: None,
+            response: None,
+        }
+    }
+
+    /// Allows the requests to the path in the maintenance mode.
+    #[must_use]
+    pub fn allow(mut self, path: impl Into<String>) -> Self {
+        self.allowlist.push(path.into());
+        self
+    }
+
+    /// Sets the value of the `Retry-After` header.
+    #[must_use]
+    pub fn retry_after(self, retry_after: Duration) -> Self {
+        Self {
+            retry_after: Some(retry_after),
+            ..self
+        }
+    }
+
+    /// Sets a function to create the response in the maintenance mode, such
+    /// as an HTML page or a JSON object, its status is always `503 Service
+    /// Unavailable`.
+    ///
+    /// Default is the response of [`MaintenanceError`].
+    #[must_use]
+    pub fn response<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        Self {
+            response: Some(Arc::new(move |req| f(req).into_response())),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Maintenance {
+    type Output = MaintenanceEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let mut headers = HeaderMap::new();
+        if let Some(retry_after) = self.retry_after {
+            headers.insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs()),
+            );
+        }
+
+        MaintenanceEndpoint {
+            inner: ep,
+            flag: self.flag.clone(),
+            allowlist: self.allowlist.clone(),
+            headers,
+            response: self.response.clone(),
+        }
+    }
+}
+
+/// Endpoint for Maintenance middleware.
+pub struct MaintenanceEndpoint<E> {
+    inner: E,
+    flag: MaintenanceFlag,
+    allowlist: Vec<String>,
+    headers: HeaderMap,
+    response: Option<MaintenanceResponseFn>,
+}
+
+impl<E> MaintenanceEndpoint<E> {
+    fn is_allowed(&self, path: &str) -> bool {
+        self.allowlist
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(prefix) => {
+                    path == prefix
+                        || path
+                            .strip_prefix(prefix)
+                            .map(|rest| rest.starts_with('/'))
+                            .unwrap_or_default()
+                }
+                None => path == allowed,
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for MaintenanceEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if !self.flag.is_enabled() || self.is_allowed(req.original_uri().path()) {
+            return self.inner.call(req).await;
+        }
+
+        Err(match &self.response {
+            Some(response) => {
+                let mut resp = response(&req);
+                resp.set_status(StatusCode::SERVICE_UNAVAILABLE);
+                resp.headers_mut().extend(self.headers.clone());
+                Error::from_response(resp)
+            }
+            None => MaintenanceError.with_headers(self.headers.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make_sync, test::TestClient, EndpointExt};
+
+    #[tokio::test]
+    async fn maintenance() {
+        let flag = MaintenanceFlag::new();
+        let cli = TestClient::new(
+            make_sync(|_| "hello").with(
+                Maintenance::new(flag.clone())
+                    .allow("/health")
+                    .allow("/admin/*"),
+            ),
+        );
+
+        cli.get("/").send().await.assert_status_is_ok();
+
+        flag.enable();
+        assert!(flag.is_enabled());
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.assert_header_is_not_exist(header::RETRY_AFTER);
+        resp.assert_text("the service is under maintenance").await;
+
+        for path in ["/health", "/admin", "/admin/a/b"] {
+            cli.get(path).send().await.assert_status_is_ok();
+        }
+        for path in ["/health/a", "/administrator", "/a/admin"] {
+            cli.get(path)
+                .send()
+                .await
+                .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        flag.disable();
+        cli.get("/").send().await.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn retry_after() {
+        let flag = MaintenanceFlag::new();
+        flag.enable();
+        let ep = make_sync(|_| "hello").with(
+            Maintenance::new(flag.clone())
+                .retry_after(Duration::from_secs(120))
+                .response(|req: &Request| format!("{} is under maintenance", req.uri().path())),
+        );
+
+        let resp = TestClient::new(&ep).get("/a").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.assert_header(header::RETRY_AFTER, "120");
+        resp.assert_text("/a is under maintenance").await;
+
+        let ep = make_sync(|_| "hello")
+            .with(Maintenance::new(flag).retry_after(Duration::from_secs(120)));
+        let err = ep.call(Request::default()).await.unwrap_err();
+        assert!(err.is::<MaintenanceError>());
+        let resp = err.into_response();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            resp.headers().get(header::RETRY_AFTER),
+            Some(&HeaderValue::from_static("120"))
+        );
+    }
+}
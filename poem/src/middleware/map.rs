@@ -0,0 +1,276 @@
+use std::{future::Future, sync::Arc};
+
+use crate::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+
+/// Middleware for transforming the requests with an async function before
+/// they are passed to the inner endpoint.
+///
+/// The function takes the ownership of the request, so it can modify the
+/// headers and the extensions, or replace the body. Returns an error to
+/// reject the request.
+///
+/// Unlike [`EndpointExt::before`](crate::EndpointExt::before), it can be
+/// created once and cloned to be applied to multiple endpoints, the
+/// function is shared between them.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::TryStreamExt;
+/// use poem::{
+///     handler, http::StatusCode, middleware::MapRequest, test::TestClient, Body, EndpointExt,
+///     Error, Request,
+/// };
+///
+/// #[handler]
+/// fn echo(body: String) -> String {
+///     body
+/// }
+///
+/// let ep = echo.with(MapRequest::new(|mut req: Request| async move {
+///     if req.header("x-api-version") != Some("2") {
+///         return Err(Error::from_status(StatusCode::BAD_REQUEST));
+///     }
+///
+///     // transform the body chunk by chunk, without buffering it
+///     let stream = req
+///         .take_body()
+///         .into_bytes_stream()
+///         .map_ok(|data| data.to_ascii_uppercase());
+///     req.set_body(Body::from_bytes_stream(stream));
+///     Ok(req)
+/// }));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(ep);
+/// let resp = cli
+///     .post("/")
+///     .header("x-api-version", "2")
+///     .body("hello")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("HELLO").await;
+///
+/// let resp = cli.post("/").body("hello").send().await;
+/// resp.assert_status(StatusCode::BAD_REQUEST);
+/// # });
+/// ```
+pub struct MapRequest<F> {
+    f: Arc<F>,
+}
+
+impl<F> MapRequest<F> {
+    /// Create `MapRequest` middleware with the function.
+    pub fn new(f: F) -> Self {
+        Self { f: Arc::new(f) }
+    }
+}
+
+impl<F> Clone for MapRequest<F> {
+    fn clone(&self) -> Self {
+        Self { f: self.f.clone() }
+    }
+}
+
+impl<E, F, Fut> Middleware<E> for MapRequest<F>
+where
+    E: Endpoint,
+    F: Fn(Request) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Request>> + Send,
+{
+    type Output = MapRequestEndpoint<E, F>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        MapRequestEndpoint {
+            inner: ep,
+            f: self.f.clone(),
+        }
+    }
+}
+
+/// Endpoint for MapRequest middleware.
+pub struct MapRequestEndpoint<E, F> {
+    inner: E,
+    f: Arc<F>,
+}
+
+#[async_trait::async_trait]
+impl<E, F, Fut> Endpoint for MapRequestEndpoint<E, F>
+where
+    E: Endpoint,
+    F: Fn(Request) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Request>> + Send,
+{
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        self.inner.call((self.f)(req).await?).await
+    }
+}
+
+/// Middleware for transforming the responses of the inner endpoint with an
+/// async function.
+///
+/// The function takes the ownership of the response, so it can modify the
+/// status, the headers and the extensions, or replace the body. The errors
+/// returned by the inner endpoint are not passed to the function.
+///
+/// Unlike [`EndpointExt::map`](crate::EndpointExt::map), it can be created
+/// once and applied to multiple endpoints.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::TryStreamExt;
+/// use poem::{
+///     endpoint::make_sync, middleware::MapResponse, test::TestClient, Body, EndpointExt, Response,
+/// };
+///
+/// let ep = make_sync(|_| "hello").with(MapResponse::new(|mut resp: Response| async move {
+///     resp.headers_mut()
+///         .insert("x-transformed", "1".parse().unwrap());
+///
+///     // transform the body chunk by chunk, without buffering it
+///     let stream = resp
+///         .take_body()
+///         .into_bytes_stream()
+///         .map_ok(|data| data.to_ascii_uppercase());
+///     resp.set_body(Body::from_bytes_stream(stream));
+///     Ok(resp)
+/// }));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(ep).get("/").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-transformed", "1");
+/// resp.assert_text("HELLO").await;
+/// # });
+/// ```
+pub struct MapResponse<F> {
+    f: Arc<F>,
+}
+
+impl<F> MapResponse<F> {
+    /// Create `MapResponse` middleware with the function.
+    pub fn new(f: F) -> Self {
+        Self { f: Arc::new(f) }
+    }
+}
+
+impl<F> Clone for MapResponse<F> {
+    fn clone(&self) -> Self {
+        Self { f: self.f.clone() }
+    }
+}
+
+impl<E, F, Fut> Middleware<E> for MapResponse<F>
+where
+    E: Endpoint,
+    F: Fn(Response) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response>> + Send,
+{
+    type Output = MapResponseEndpoint<E, F>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        MapResponseEndpoint {
+            inner: ep,
+            f: self.f.clone(),
+        }
+    }
+}
+
+/// Endpoint for MapResponse middleware.
+pub struct MapResponseEndpoint<E, F> {
+    inner: E,
+    f: Arc<F>,
+}
+
+#[async_trait::async_trait]
+impl<E, F, Fut> Endpoint for MapResponseEndpoint<E, F>
+where
+    E: Endpoint,
+    F: Fn(Response) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response>> + Send,
+{
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let resp = self.inner.call(req).await?.into_response();
+        (self.f)(resp).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        endpoint::make_sync,
+        http::StatusCode,
+        test::TestClient,
+        web::{Data, Path},
+        EndpointExt, Error, Route,
+    };
+
+    #[tokio::test]
+    async fn map_request() {
+        let middleware = MapRequest::new(|mut req: Request| async move {
+            let user = req
+                .header("x-user")
+                .ok_or_else(|| Error::from_status(StatusCode::UNAUTHORIZED))?
+                .to_string();
+            req.extensions_mut().insert(user);
+            Ok(req)
+        });
+
+        #[crate::handler(internal)]
+        fn hello(Path(name): Path<String>, Data(user): Data<&String>) -> String {
+            format!("{} {}", name, user)
+        }
+
+        let cli = TestClient::new(
+            Route::new()
+                .at("/a/:name", hello.with(middleware.clone()))
+                .at("/b/:name", hello.with(middleware)),
+        );
+
+        for path in ["/a/x", "/b/x"] {
+            let resp = cli.get(path).header("x-user", "sunli").send().await;
+            resp.assert_status_is_ok();
+            resp.assert_text("x sunli").await;
+
+            cli.get(path)
+                .send()
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn map_response() {
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", make_sync(|_| "hello"))
+                .at(
+                    "/error",
+                    make_sync(|_| ())
+                        .before(|_| async { Err(Error::from_status(StatusCode::BAD_REQUEST)) }),
+                )
+                .with(MapResponse::new(|mut resp: Response| async move {
+                    resp.set_status(StatusCode::CREATED);
+                    let body = resp.take_body().into_string().await?;
+                    resp.set_body(format!("{}!", body));
+                    Ok(resp)
+                })),
+        );
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::CREATED);
+        resp.assert_text("hello!").await;
+
+        cli.get("/error")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{sync::Arc, time::Duration};
+
+use hyper::{client::HttpConnector, Client};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use rand::Rng;
+
+use crate::{
+    http::{header, HeaderMap, Uri},
+    Endpoint, Middleware, Request, Result,
+};
+
+/// The hop-by-hop headers which are not forwarded to the shadow upstream.
+const HOP_BY_HOP_HEADERS: [header::HeaderName; 6] = [
+    header::HOST,
+    header::CONNECTION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+#[derive(Clone)]
+struct MirrorConfig {
+    upstream: Uri,
+    percentage: f64,
+    max_body_size: usize,
+    timeout: Duration,
+}
+
+/// Middleware for mirroring the requests to a shadow upstream, for testing a
+/// new backend with the production traffic safely.
+///
+/// The method, the headers and the body of the sampled requests are sent to
+/// the upstream in the background, the path and the query are appended to
+/// the upstream address. The mirrored responses are discarded, and the
+/// failures of the upstream never affect the primary responses.
+///
+/// The body is buffered to be sent twice, so the requests whose bodies are
+/// larger than [`Mirror::max_body_size`] or whose sizes are unknown are not
+/// mirrored.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::Mirror, EndpointExt};
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = index.with(Mirror::new("http://shadow.internal:3000").percentage(10.0));
+/// ```
+pub struct Mirror {
+    config: MirrorConfig,
+}
+
+impl Mirror {
+    /// Create `Mirror` middleware with the address of the shadow upstream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address is not a valid absolute URI.
+    pub fn new(upstream: impl AsRef<str>) -> Self {
+        let upstream = upstream.as_ref();
+        let upstream: Uri = upstream
+            .parse()
+            .ok()
+            .filter(|uri: &Uri| uri.scheme().is_some() && uri.authority().is_some())
+            .unwrap_or_else(|| panic!("invalid upstream address `{}`", upstream));
+
+        Self {
+            config: MirrorConfig {
+                upstream,
+                percentage: 100.0,
+                max_body_size: 1024 * 1024,
+                timeout: Duration::from_secs(10),
+            },
+        }
+    }
+
+    /// Sets the percentage of the requests to be mirrored, between `0.0` and
+    /// `100.0`.
+    ///
+    /// Default is `100.0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the percentage is out of range.
+    #[must_use]
+    pub fn percentage(mut self, percentage: f64) -> Self {
+        assert!(
+            (0.0..=100.0).contains(&percentage),
+            "the percentage must be between 0 and 100"
+        );
+        self.config.percentage = percentage;
+        self
+    }
+
+    /// Sets the maximum size of the bodies to be buffered.
+    ///
+    /// Default is `1MB`.
+    #[must_use]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.config.max_body_size = size;
+        self
+    }
+
+    /// Sets the timeout of the mirrored requests.
+    ///
+    /// Default is `10s`.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Mirror {
+    type Output = MirrorEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        MirrorEndpoint {
+            inner: ep,
+            config: Arc::new(self.config.clone()),
+            client: Client::builder().build(
+                HttpsConnectorBuilder::new()
+                    .with_native_roots()
+                    .https_or_http()
+                    .enable_http1()
+                    .build(),
+            ),
+        }
+    }
+}
+
+/// Endpoint for Mirror middleware.
+pub struct MirrorEndpoint<E> {
+    inner: E,
+    config: Arc<MirrorConfig>,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+impl<E> MirrorEndpoint<E> {
+    fn should_mirror(&self) -> bool {
+        let percentage = self.config.percentage;
+        percentage >= 100.0
+            || (percentage > 0.0 && rand::thread_rng().gen_range(0.0..100.0) < percentage)
+    }
+
+    fn upstream_uri(&self, req: &Request) -> Option<Uri> {
+        let upstream = &self.config.upstream;
+        let path_and_query = req.original_uri().path_and_query()?.as_str();
+        let base = upstream
+            .path_and_query()
+            .map(|path| path.path().trim_end_matches('/'))
+            .unwrap_or_default();
+        Uri::builder()
+            .scheme(upstream.scheme()?.clone())
+            .authority(upstream.authority()?.clone())
+            .path_and_query(format!("{}{}", base, path_and_query))
+            .build()
+            .ok()
+    }
+}
+
+fn mirrored_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in &HOP_BY_HOP_HEADERS {
+        headers.remove(name);
+    }
+    headers
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for MirrorEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if !self.should_mirror() {
+            return self.inner.call(req).await;
+        }
+        let uri = match self.upstream_uri(&req) {
+            Some(uri) => uri,
+            None => return self.inner.call(req).await,
+        };
+
+        let body = req.take_body();
+        match body.exact_size() {
+            Some(size) if size <= self.config.max_body_size as u64 => {}
+            _ => {
+                req.set_body(body);
+                return self.inner.call(req).await;
+            }
+        }
+        let data = body.into_bytes().await?;
+        req.set_body(data.clone());
+
+        let mut mirrored_req = hyper::Request::new(hyper::Body::from(data));
+        *mirrored_req.method_mut() = req.method().clone();
+        *mirrored_req.uri_mut() = uri;
+        *mirrored_req.headers_mut() = mirrored_headers(req.headers());
+
+        let client = self.client.clone();
+        let timeout = self.config.timeout;
+        tokio::spawn(async move {
+            match tokio::time::timeout(timeout, client.request(mirrored_req)).await {
+                Ok(Ok(resp)) => {
+                    let _ = hyper::body::to_bytes(resp.into_body()).await;
+                }
+                Ok(Err(err)) => tracing::debug!(error = %err, "failed to mirror the request"),
+                Err(_) => tracing::debug!("mirroring the request timed out"),
+            }
+        });
+
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+    use super::*;
+    use crate::{
+        handler,
+        http::Method,
+        listener::{Acceptor, Listener, TcpListener},
+        test::TestClient,
+        web::Data,
+        EndpointExt, Server,
+    };
+
+    type Mirrored = (Method, String, Option<String>, String);
+
+    #[handler(internal)]
+    async fn shadow(req: &Request, body: String, Data(tx): Data<&UnboundedSender<Mirrored>>) {
+        let header = req
+            .headers()
+            .get("x-test")
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        let _ = tx.send((req.method().clone(), req.uri().to_string(), header, body));
+    }
+
+    #[handler(internal)]
+    fn index(body: String) -> String {
+        format!("primary {}", body)
+    }
+
+    #[tokio::test]
+    async fn mirror() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let (tx, mut rx) = unbounded_channel::<Mirrored>();
+        let handle = tokio::spawn(async move {
+            let _ = Server::new_with_acceptor(acceptor)
+                .run(shadow.data(tx))
+                .await;
+        });
+
+        let cli = TestClient::new(
+            index.with(Mirror::new(format!("http://{}/shadow/", addr)).max_body_size(8)),
+        );
+        let resp = cli
+            .post("/a?b=1")
+            .header("x-test", "1")
+            .body("hello")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("primary hello").await;
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            (
+                Method::POST,
+                "/shadow/a?b=1".to_string(),
+                Some("1".to_string()),
+                "hello".to_string()
+            )
+        );
+
+        // the body is too large to be mirrored
+        let resp = cli.post("/").body("hello world").send().await;
+        resp.assert_text("primary hello world").await;
+
+        cli.get("/c").send().await.assert_status_is_ok();
+        assert_eq!(rx.recv().await.unwrap().1, "/shadow/c");
+
+        let cli =
+            TestClient::new(index.with(Mirror::new(format!("http://{}", addr)).percentage(0.0)));
+        cli.get("/d").send().await.assert_status_is_ok();
+        let cli = TestClient::new(index.with(Mirror::new(format!("http://{}", addr))));
+        cli.get("/e").send().await.assert_status_is_ok();
+        assert_eq!(rx.recv().await.unwrap().1, "/e");
+
+        handle.abort();
+        let _ = handle.await;
+
+        // the primary response is not affected by the failures of the upstream
+        let cli = TestClient::new(index.with(Mirror::new(format!("http://{}", addr))));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("primary ").await;
+    }
+}
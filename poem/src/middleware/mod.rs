@@ -1,54 +1,127 @@
 //! Commonly used middleware.
 
+mod access_log;
+mod add_container;
 mod add_data;
+mod api_key;
+mod auth;
+mod cache;
 mod catch_panic;
+mod circuit_breaker;
 #[cfg(feature = "compression")]
 mod compression;
+mod concurrency_limit;
 #[cfg(feature = "cookie")]
 mod cookie_jar_manager;
 mod cors;
 #[cfg(feature = "csrf")]
 mod csrf;
+mod disable_request_timeout;
 mod force_https;
+#[cfg(feature = "forward-auth")]
+mod forward_auth;
+mod ip_filter;
+#[cfg(feature = "jwt")]
+mod jwt;
+mod maintenance;
+mod map;
+#[cfg(feature = "mirror")]
+mod mirror;
 mod normalize_path;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_metrics;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_tracing;
+#[cfg(feature = "prometheus")]
+mod prometheus_metrics;
 mod propagate_header;
+mod rate_limit;
+#[cfg(feature = "request-id")]
+mod request_id;
+#[cfg(feature = "secure-headers")]
+mod secure_headers;
 mod sensitive_header;
+#[cfg(feature = "sentry")]
+mod sentry;
 mod set_header;
+mod single_flight;
 mod size_limit;
+mod slow_request;
+mod timeout;
 #[cfg(feature = "tokio-metrics")]
 mod tokio_metrics_mw;
 #[cfg(feature = "tower-compat")]
 mod tower_compat;
 mod tracing_mw;
 
+#[cfg(feature = "redis-cache")]
+pub use self::cache::RedisCacheStore;
 #[cfg(feature = "compression")]
 pub use self::compression::{Compression, CompressionEndpoint};
 #[cfg(feature = "cookie")]
 pub use self::cookie_jar_manager::{CookieJarManager, CookieJarManagerEndpoint};
 #[cfg(feature = "csrf")]
-pub use self::csrf::{Csrf, CsrfEndpoint};
+pub use self::csrf::{Csrf, CsrfEndpoint, CsrfMode};
+pub(crate) use self::disable_request_timeout::RequestTimeoutFlag;
+#[cfg(feature = "forward-auth")]
+pub use self::forward_auth::{ForwardAuth, ForwardAuthEndpoint};
+#[cfg(feature = "jwt")]
+pub use self::jwt::{Claims, Jwt, JwtEndpoint};
+#[cfg(feature = "mirror")]
+pub use self::mirror::{Mirror, MirrorEndpoint};
 #[cfg(feature = "opentelemetry")]
 pub use self::opentelemetry_metrics::{OpenTelemetryMetrics, OpenTelemetryMetricsEndpoint};
 #[cfg(feature = "opentelemetry")]
-pub use self::opentelemetry_tracing::{OpenTelemetryTracing, OpenTelemetryTracingEndpoint};
+pub use self::opentelemetry_tracing::{
+    OpenTelemetryContext, OpenTelemetryTracing, OpenTelemetryTracingEndpoint,
+};
+#[cfg(feature = "prometheus")]
+pub use self::prometheus_metrics::{PrometheusMetrics, PrometheusMetricsEndpoint};
+#[cfg(feature = "redis-rate-limit")]
+pub use self::rate_limit::RedisRateLimitStore;
+#[cfg(feature = "request-id")]
+pub use self::request_id::{ReqId, RequestId, RequestIdEndpoint, RequestIdFormat};
+#[cfg(feature = "secure-headers")]
+pub use self::secure_headers::{
+    ContentSecurityPolicy, CspNonce, FrameOptions, SecureHeaders, SecureHeadersEndpoint,
+};
+#[cfg(feature = "sentry")]
+pub use self::sentry::{Sentry, SentryEndpoint};
 #[cfg(feature = "tokio-metrics")]
 pub use self::tokio_metrics_mw::{TokioMetrics, TokioMetricsEndpoint};
 #[cfg(feature = "tower-compat")]
 pub use self::tower_compat::TowerLayerCompatExt;
 pub use self::{
+    access_log::{
+        AccessLog, AccessLogEndpoint, AccessLogField, AccessLogFormat, AccessLogRecord,
+        AccessLogSink, FileAccessLogSink, StdoutAccessLogSink,
+    },
+    add_container::{AddContainer, AddContainerEndpoint},
     add_data::{AddData, AddDataEndpoint},
-    catch_panic::{CatchPanic, CatchPanicEndpoint, PanicHandler},
+    api_key::{ApiKey, ApiKeyEndpoint, ApiKeyInfo, ApiKeySource, ApiKeyStore, MemoryApiKeyStore},
+    auth::{BasicAuth, BasicAuthEndpoint, BearerAuth, BearerAuthEndpoint},
+    cache::{Cache, CacheEndpoint, CacheStore, CachedResponse, MemoryCacheStore},
+    catch_panic::{CatchPanic, CatchPanicEndpoint, PanicContext, PanicHandler},
+    circuit_breaker::{CircuitBreaker, CircuitBreakerEndpoint, CircuitState},
+    concurrency_limit::{ConcurrencyLimit, ConcurrencyLimitEndpoint},
     cors::{Cors, CorsEndpoint},
-    force_https::ForceHttps,
+    disable_request_timeout::{DisableRequestTimeout, DisableRequestTimeoutEndpoint},
+    force_https::{ForceHttps, ForceHttpsEndpoint, Hsts},
+    ip_filter::{IpFilter, IpFilterEndpoint},
+    maintenance::{Maintenance, MaintenanceEndpoint, MaintenanceFlag},
+    map::{MapRequest, MapRequestEndpoint, MapResponse, MapResponseEndpoint},
     normalize_path::{NormalizePath, NormalizePathEndpoint, TrailingSlash},
     propagate_header::{PropagateHeader, PropagateHeaderEndpoint},
+    rate_limit::{
+        MemoryRateLimitStore, RateLimit, RateLimitAlgorithm, RateLimitDecision, RateLimitEndpoint,
+        RateLimitQuota, RateLimitStore,
+    },
     sensitive_header::{SensitiveHeader, SensitiveHeaderEndpoint},
     set_header::{SetHeader, SetHeaderEndpoint},
+    single_flight::{SingleFlight, SingleFlightEndpoint},
     size_limit::{SizeLimit, SizeLimitEndpoint},
+    slow_request::{SlowRequestDetector, SlowRequestDetectorEndpoint, SlowRequestEvent},
+    timeout::{Timeout, TimeoutEndpoint, TimeoutExt},
     tracing_mw::{Tracing, TracingEndpoint},
 };
 use crate::endpoint::Endpoint;
@@ -1,9 +1,8 @@
 use std::str::FromStr;
 
 use http::{uri::PathAndQuery, Uri};
-use regex::Regex;
 
-use crate::{Endpoint, Middleware, Request, Result};
+use crate::{web::Redirect, Endpoint, IntoResponse, Middleware, Request, Response, Result};
 
 /// Determines the behavior of the [`NormalizePath`] middleware.
 #[derive(Debug, Clone, Copy)]
@@ -27,6 +26,16 @@ impl Default for TrailingSlash {
 /// Middleware for normalizing a request's path so that routes can be matched
 /// more flexibly.
 ///
+/// The duplicate slashes are merged, the `.` and `..` segments are resolved,
+/// and the trailing slash is handled according to the [`TrailingSlash`]
+/// style. The path can also be converted to lowercase with
+/// [`NormalizePath::lowercase`].
+///
+/// By default the path is rewritten before it is passed to the inner
+/// endpoint, use [`NormalizePath::redirect`] to redirect the clients to the
+/// canonical path instead, so that the search engines only index one URL for
+/// each page.
+///
 /// # Example
 ///
 /// ```
@@ -52,15 +61,74 @@ impl Default for TrailingSlash {
 /// let resp = cli.get("/foo/bar/").send().await;
 /// resp.assert_status_is_ok();
 /// resp.assert_text("hello").await;
+///
+/// let resp = cli.get("/foo/./baz/../bar").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("hello").await;
 /// # });
 /// ```
-pub struct NormalizePath(TrailingSlash);
+pub struct NormalizePath {
+    style: TrailingSlash,
+    lowercase: bool,
+    redirect: bool,
+}
 
 impl NormalizePath {
     /// Create new `NormalizePath` middleware with the specified trailing slash
     /// style.
     pub fn new(style: TrailingSlash) -> Self {
-        Self(style)
+        Self {
+            style,
+            lowercase: false,
+            redirect: false,
+        }
+    }
+
+    /// Converts the path to lowercase. Default is `false`.
+    #[must_use]
+    pub fn lowercase(self, enable: bool) -> Self {
+        Self {
+            lowercase: enable,
+            ..self
+        }
+    }
+
+    /// Responds with a `308 Permanent Redirect` to the normalized path
+    /// instead of rewriting the path, if the path is not normalized. Default
+    /// is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::make_sync,
+    ///     http::{header, StatusCode},
+    ///     middleware::{NormalizePath, TrailingSlash},
+    ///     test::TestClient,
+    ///     EndpointExt, Route,
+    /// };
+    ///
+    /// let app = Route::new().at("/foo/bar", make_sync(|_| "hello")).with(
+    ///     NormalizePath::new(TrailingSlash::Trim)
+    ///         .lowercase(true)
+    ///         .redirect(true),
+    /// );
+    /// let cli = TestClient::new(app);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = cli.get("//Foo/Bar/?a=1").send().await;
+    /// resp.assert_status(StatusCode::PERMANENT_REDIRECT);
+    /// resp.assert_header(header::LOCATION, "/foo/bar?a=1");
+    ///
+    /// cli.get("/foo/bar").send().await.assert_status_is_ok();
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn redirect(self, enable: bool) -> Self {
+        Self {
+            redirect: enable,
+            ..self
+        }
     }
 }
 
@@ -70,8 +138,9 @@ impl<E: Endpoint> Middleware<E> for NormalizePath {
     fn transform(&self, ep: E) -> Self::Output {
         NormalizePathEndpoint {
             inner: ep,
-            merge_slash: Regex::new("//+").unwrap(),
-            style: self.0,
+            style: self.style,
+            lowercase: self.lowercase,
+            redirect: self.redirect,
         }
     }
 }
@@ -79,40 +148,81 @@ impl<E: Endpoint> Middleware<E> for NormalizePath {
 /// Endpoint for NormalizePath middleware.
 pub struct NormalizePathEndpoint<E> {
     inner: E,
-    merge_slash: Regex,
     style: TrailingSlash,
+    lowercase: bool,
+    redirect: bool,
+}
+
+impl<E> NormalizePathEndpoint<E> {
+    fn normalize(&self, path: &str) -> String {
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop();
+                }
+                segment => segments.push(segment),
+            }
+        }
+
+        let trailing_slash = match self.style {
+            TrailingSlash::Trim => false,
+            TrailingSlash::Always => true,
+            TrailingSlash::MergeOnly => {
+                path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..")
+            }
+        };
+
+        let mut normalized = String::with_capacity(path.len() + 1);
+        for segment in segments {
+            normalized.push('/');
+            normalized.push_str(segment);
+        }
+        if normalized.is_empty() || trailing_slash {
+            normalized.push('/');
+        }
+        if self.lowercase {
+            normalized.make_ascii_lowercase();
+        }
+        normalized
+    }
+}
+
+fn with_query(path: &str, uri: &Uri) -> String {
+    match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    }
 }
 
 #[async_trait::async_trait]
 impl<E: Endpoint> Endpoint for NormalizePathEndpoint<E> {
-    type Output = E::Output;
+    type Output = Response;
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
-        let original_path = req
-            .uri()
-            .path_and_query()
-            .map(|x| x.path())
-            .unwrap_or_default();
-
-        if !original_path.is_empty() {
-            let path = match self.style {
-                TrailingSlash::Always => format!("{}/", original_path),
-                TrailingSlash::MergeOnly => original_path.to_string(),
-                TrailingSlash::Trim => original_path.trim_end_matches('/').to_string(),
-            };
-
-            let path = self.merge_slash.replace_all(&path, "/");
-            let path = if path.is_empty() { "/" } else { path.as_ref() };
+        if self.redirect {
+            let original_path = req.original_uri().path();
+            if original_path.starts_with('/') {
+                let path = self.normalize(original_path);
+                if path != original_path {
+                    return Ok(
+                        Redirect::permanent(with_query(&path, req.original_uri())).into_response()
+                    );
+                }
+            }
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let original_path = req.uri().path();
+        if original_path.starts_with('/') {
+            let path = self.normalize(original_path);
 
             if path != original_path {
                 let (mut parts, body) = req.into_parts();
-                let mut uri_parts = parts.uri.into_parts();
-                let query = uri_parts.path_and_query.as_ref().and_then(|pq| pq.query());
-                let path = match query {
-                    Some(query) => format!("{}?{}", path, query),
-                    None => path.to_string(),
-                };
-                uri_parts.path_and_query = Some(PathAndQuery::from_str(&path).unwrap());
+                let mut uri_parts = parts.uri.clone().into_parts();
+                uri_parts.path_and_query =
+                    Some(PathAndQuery::from_str(&with_query(&path, &parts.uri)).unwrap());
 
                 let new_uri = Uri::from_parts(uri_parts).unwrap();
                 parts.uri = new_uri;
@@ -121,7 +231,7 @@ impl<E: Endpoint> Endpoint for NormalizePathEndpoint<E> {
             }
         }
 
-        self.inner.call(req).await
+        self.inner.call(req).await.map(IntoResponse::into_response)
     }
 }
 
@@ -302,4 +412,75 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn resolve_dot_segments() {
+        let ep = Route::new()
+            .at("/a/b", make_sync(|req| req.uri().to_string()))
+            .with(NormalizePath::new(TrailingSlash::Trim));
+        let cli = TestClient::new(ep);
+
+        for uri in [
+            "/a/b",
+            "/a/./b",
+            "/./a/b/.",
+            "/a/c/../b",
+            "/../../a/b",
+            "//a/c//..//b/",
+        ] {
+            let resp = cli.get(uri).send().await;
+            resp.assert_status_is_ok();
+            resp.assert_text("/a/b").await;
+        }
+
+        let resp = cli.get("/a/c/../b?x=1").send().await;
+        resp.assert_text("/a/b?x=1").await;
+        cli.get("/A/B")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn lowercase() {
+        let ep = Route::new()
+            .at("/a/b/", make_sync(|req| req.uri().to_string()))
+            .with(NormalizePath::new(TrailingSlash::MergeOnly).lowercase(true));
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/A//B/.?X=1").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("/a/b/?X=1").await;
+        cli.get("/A/B")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn redirect() {
+        let ep = Route::new()
+            .nest(
+                "/api",
+                Route::new()
+                    .at("/a/b/", make_sync(|_| "hello"))
+                    .with(NormalizePath::new(TrailingSlash::Always).redirect(true)),
+            )
+            .at("/", make_sync(|_| "root"));
+        let cli = TestClient::new(ep);
+
+        for (uri, location) in [
+            ("/api/a/b", "/api/a/b/"),
+            ("/api//a/./b?x=1", "/api/a/b/?x=1"),
+            ("/api/a/c/../b//", "/api/a/b/"),
+        ] {
+            let resp = cli.get(uri).send().await;
+            resp.assert_status(StatusCode::PERMANENT_REDIRECT);
+            resp.assert_header(http::header::LOCATION, location);
+        }
+
+        let resp = cli.get("/api/a/b/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("hello").await;
+    }
 }
@@ -2,21 +2,93 @@ use std::sync::Arc;
 
 use libopentelemetry::{
     global,
+    propagation::TextMapPropagator,
     trace::{FutureExt, Span, SpanKind, TraceContextExt, Tracer},
     Context,
 };
-use opentelemetry_http::HeaderExtractor;
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
 use opentelemetry_semantic_conventions::{resource, trace};
 
 use crate::{
+    http::HeaderMap,
     web::{headers::HeaderMapExt, RealIp},
-    Endpoint, FromRequest, IntoResponse, Middleware, Request, Response, Result,
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, RequestBody, Response, Result,
 };
 
+type SharedPropagator = Arc<dyn TextMapPropagator + Send + Sync>;
+
+/// The OpenTelemetry context of the request, which contains the span created
+/// by [`OpenTelemetryTracing`] middleware and the baggage of the incoming
+/// request.
+///
+/// Use [`OpenTelemetryContext::inject`] to propagate the context to the
+/// outgoing requests, so that the traces of the downstream services are
+/// stitched together with the current one.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, http::HeaderMap, middleware::OpenTelemetryContext};
+///
+/// #[handler]
+/// async fn index(cx: &OpenTelemetryContext) {
+///     let mut headers = HeaderMap::new();
+///     cx.inject(&mut headers);
+///     // send a request to the downstream service with the headers
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
+#[derive(Clone)]
+pub struct OpenTelemetryContext {
+    cx: Context,
+    propagator: Option<SharedPropagator>,
+}
+
+impl OpenTelemetryContext {
+    /// Returns the OpenTelemetry context.
+    pub fn context(&self) -> &Context {
+        &self.cx
+    }
+
+    /// Injects the context into the headers of an outgoing request, with the
+    /// propagator of the middleware.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        let mut injector = HeaderInjector(headers);
+        match &self.propagator {
+            Some(propagator) => propagator.inject_context(&self.cx, &mut injector),
+            None => global::get_text_map_propagator(|propagator| {
+                propagator.inject_context(&self.cx, &mut injector)
+            }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for &'a OpenTelemetryContext {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.extensions().get::<OpenTelemetryContext>().expect(
+            "To use the `OpenTelemetryContext` extractor, the `OpenTelemetryTracing` middleware is required.",
+        ))
+    }
+}
+
 /// Middleware for tracing with OpenTelemetry.
+///
+/// The parent context is extracted from the headers of the incoming request
+/// with the global propagator by default, use
+/// [`OpenTelemetryTracing::propagator`] to specify another one, such as the
+/// W3C `traceparent` and `baggage` propagators:
+///
+/// ```text
+/// TextMapCompositePropagator::new(vec![
+///     Box::new(TraceContextPropagator::new()),
+///     Box::new(BaggagePropagator::new()),
+/// ])
+/// ```
 #[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
 pub struct OpenTelemetryTracing<T> {
     tracer: Arc<T>,
+    propagator: Option<SharedPropagator>,
 }
 
 impl<T> OpenTelemetryTracing<T> {
@@ -24,6 +96,20 @@ impl<T> OpenTelemetryTracing<T> {
     pub fn new(tracer: T) -> Self {
         Self {
             tracer: Arc::new(tracer),
+            propagator: None,
+        }
+    }
+
+    /// Sets the propagator to extract the parent context from the incoming
+    /// requests, and to inject the context into the outgoing requests with
+    /// [`OpenTelemetryContext::inject`].
+    ///
+    /// Default is the global propagator.
+    #[must_use]
+    pub fn propagator(self, propagator: impl TextMapPropagator + Send + Sync + 'static) -> Self {
+        Self {
+            propagator: Some(Arc::new(propagator)),
+            ..self
         }
     }
 }
@@ -39,6 +125,7 @@ where
     fn transform(&self, ep: E) -> Self::Output {
         OpenTelemetryTracingEndpoint {
             tracer: self.tracer.clone(),
+            propagator: self.propagator.clone(),
             inner: ep,
         }
     }
@@ -48,6 +135,7 @@ where
 #[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
 pub struct OpenTelemetryTracingEndpoint<T, E> {
     tracer: Arc<T>,
+    propagator: Option<SharedPropagator>,
     inner: E,
 }
 
@@ -60,7 +148,7 @@ where
 {
     type Output = Response;
 
-    async fn call(&self, req: Request) -> Result<Self::Output> {
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
         let remote_addr = RealIp::from_request_without_body(&req)
             .await
             .ok()
@@ -68,9 +156,11 @@ where
             .map(|addr| addr.to_string())
             .unwrap_or_else(|| req.remote_addr().to_string());
 
-        let parent_cx = global::get_text_map_propagator(|propagator| {
-            propagator.extract(&HeaderExtractor(req.headers()))
-        });
+        let extractor = HeaderExtractor(req.headers());
+        let parent_cx = match &self.propagator {
+            Some(propagator) => propagator.extract(&extractor),
+            None => global::get_text_map_propagator(|propagator| propagator.extract(&extractor)),
+        };
 
         let mut attributes = Vec::new();
         attributes.push(resource::TELEMETRY_SDK_NAME.string(env!("CARGO_CRATE_NAME")));
@@ -90,6 +180,12 @@ where
 
         span.add_event("request.started".to_string(), vec![]);
 
+        let cx = parent_cx.with_span(span);
+        req.extensions_mut().insert(OpenTelemetryContext {
+            cx: cx.clone(),
+            propagator: self.propagator.clone(),
+        });
+
         async move {
             let res = self.inner.call(req).await;
             let cx = Context::current();
@@ -119,7 +215,89 @@ where
                 }
             }
         }
-        .with_context(Context::current_with_span(span))
+        .with_context(cx)
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use libopentelemetry::{
+        baggage::BaggageExt,
+        sdk::{
+            propagation::{BaggagePropagator, TextMapCompositePropagator, TraceContextPropagator},
+            trace::TracerProvider,
+        },
+        trace::TracerProvider as _,
+    };
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    #[handler(internal)]
+    fn index(cx: &OpenTelemetryContext) -> String {
+        let mut headers = HeaderMap::new();
+        cx.inject(&mut headers);
+        let baggage = cx
+            .context()
+            .baggage()
+            .get("user")
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        format!(
+            "{} {}",
+            headers
+                .get("traceparent")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default(),
+            baggage
+        )
+    }
+
+    #[tokio::test]
+    async fn propagation() {
+        let provider = TracerProvider::builder().build();
+        let tracer = provider.tracer("poem");
+        let cli = TestClient::new(index.with(OpenTelemetryTracing::new(tracer).propagator(
+            TextMapCompositePropagator::new(vec![
+                Box::new(TraceContextPropagator::new()),
+                Box::new(BaggagePropagator::new()),
+            ]),
+        )));
+
+        let resp = cli
+            .get("/")
+            .header(
+                "traceparent",
+                format!("00-{}-00f067aa0ba902b7-01", TRACE_ID),
+            )
+            .header("baggage", "user=sunli")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let text = resp.0.into_body().into_string().await.unwrap();
+        let (traceparent, baggage) = text.split_once(' ').unwrap();
+        let parts = traceparent.split('-').collect::<Vec<_>>();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[1], TRACE_ID);
+        assert_ne!(parts[2], "00f067aa0ba902b7");
+        assert_eq!(baggage, "sunli");
+
+        // a new trace is started without the parent context
+        let text = cli
+            .get("/")
+            .send()
+            .await
+            .0
+            .into_body()
+            .into_string()
+            .await
+            .unwrap();
+        let (traceparent, baggage) = text.split_once(' ').unwrap();
+        assert!(!traceparent.is_empty());
+        assert!(!traceparent.contains(TRACE_ID));
+        assert_eq!(baggage, "");
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::Instant;
+
+use libprometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+};
+
+use crate::{
+    endpoint::prometheus_exporter, web::MatchedPath, Endpoint, IntoResponse, Middleware, Request,
+    Response, Result,
+};
+
+const LABELS: [&str; 3] = ["method", "route", "status"];
+
+/// The value of the `route` label for the requests which do not match any
+/// route, so that the raw paths never become labels.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Clone)]
+struct Metrics {
+    requests: IntCounterVec,
+    duration: HistogramVec,
+    in_flight: IntGauge,
+    response_size: HistogramVec,
+}
+
+impl Metrics {
+    fn new(registry: &Registry) -> libprometheus::Result<Self> {
+        let requests = IntCounterVec::new(
+            Opts::new("poem_http_requests_total", "total number of requests"),
+            &LABELS,
+        )?;
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                "poem_http_request_duration_seconds",
+                "request duration in seconds",
+            ),
+            &LABELS,
+        )?;
+        let in_flight = IntGauge::new(
+            "poem_http_requests_in_flight",
+            "number of requests in progress",
+        )?;
+        let response_size = HistogramVec::new(
+            HistogramOpts::new(
+                "poem_http_response_size_bytes",
+                "response body size in bytes",
+            )
+            .buckets(exponential_buckets(64.0, 4.0, 10)?),
+            &LABELS,
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(duration.clone()))?;
+        registry.register(Box::new(in_flight.clone()))?;
+        registry.register(Box::new(response_size.clone()))?;
+
+        Ok(Self {
+            requests,
+            duration,
+            in_flight,
+            response_size,
+        })
+    }
+}
+
+/// Decrements the in-flight gauge when the request completes or is cancelled.
+struct InFlightGuard<'a>(&'a IntGauge);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(gauge: &'a IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Middleware for recording the metrics of the requests with Prometheus.
+///
+/// The following metrics are labeled by `method`, `route` and `status`, the
+/// `route` is the path pattern of the matched route, see [`MatchedPath`], or
+/// `unmatched`:
+///
+/// - `poem_http_requests_total`
+/// - `poem_http_request_duration_seconds`
+/// - `poem_http_response_size_bytes`, only if the size of the body is known
+///
+/// And `poem_http_requests_in_flight` is the number of requests in progress.
+///
+/// Use [`prometheus_exporter`] or [`PrometheusMetrics::exporter`] to export
+/// the metrics.
+///
+/// NOTE: The middleware should be applied to the [`Route`](crate::Route) to
+/// get the route patterns.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::make_sync, middleware::PrometheusMetrics, test::TestClient, EndpointExt, Route,
+/// };
+///
+/// let metrics = PrometheusMetrics::new();
+/// let app = Route::new()
+///     .at("/users/:id", make_sync(|_| "user"))
+///     .at("/metrics", metrics.exporter())
+///     .with(metrics);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/users/1").send().await.assert_status_is_ok();
+///
+/// let resp = cli.get("/metrics").send().await;
+/// resp.assert_status_is_ok();
+/// let text = resp.0.into_body().into_string().await.unwrap();
+/// assert!(text
+///     .contains(r#"poem_http_requests_total{method="GET",route="/users/:id",status="200"} 1"#));
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "prometheus")))]
+pub struct PrometheusMetrics {
+    registry: Registry,
+    metrics: Metrics,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrometheusMetrics {
+    /// Create `PrometheusMetrics` middleware with a new registry.
+    pub fn new() -> Self {
+        Self::with_registry(Registry::new())
+    }
+
+    /// Create `PrometheusMetrics` middleware which registers the metrics to
+    /// `registry`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the metrics have already been registered to the registry.
+    pub fn with_registry(registry: Registry) -> Self {
+        let metrics = Metrics::new(&registry).expect("failed to register the metrics");
+        Self { registry, metrics }
+    }
+
+    /// Returns the registry of the metrics.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Create an endpoint that exports the metrics of the registry, see
+    /// [`prometheus_exporter`].
+    pub fn exporter(&self) -> impl Endpoint<Output = Response> {
+        prometheus_exporter(self.registry.clone())
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for PrometheusMetrics {
+    type Output = PrometheusMetricsEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        PrometheusMetricsEndpoint {
+            inner: ep,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Endpoint for PrometheusMetrics middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "prometheus")))]
+pub struct PrometheusMetricsEndpoint<E> {
+    inner: E,
+    metrics: Metrics,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for PrometheusMetricsEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let method = req.method().clone();
+        let now = Instant::now();
+
+        let in_flight = InFlightGuard::new(&self.metrics.in_flight);
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        drop(in_flight);
+
+        let elapsed = now.elapsed();
+        let (status, matched_path, size) = match &res {
+            Ok(resp) => (resp.status(), resp.data::<MatchedPath>(), resp.body_size()),
+            Err(err) => (err.status(), err.data::<MatchedPath>(), None),
+        };
+        let status = status.as_u16().to_string();
+        let labels = [
+            method.as_str(),
+            matched_path
+                .map(MatchedPath::as_str)
+                .unwrap_or(UNMATCHED_ROUTE),
+            status.as_str(),
+        ];
+
+        self.metrics.requests.with_label_values(&labels).inc();
+        self.metrics
+            .duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+        if let Some(size) = size {
+            self.metrics
+                .response_size
+                .with_label_values(&labels)
+                .observe(size as f64);
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make_sync, http::StatusCode, test::TestClient, EndpointExt, Route};
+
+    #[tokio::test]
+    async fn prometheus_metrics() {
+        let metrics = PrometheusMetrics::new();
+        let cli = TestClient::new(
+            Route::new()
+                .at("/users/:id", make_sync(|_| "user"))
+                .at("/metrics", metrics.exporter())
+                .with(metrics),
+        );
+
+        cli.get("/users/1").send().await.assert_status_is_ok();
+        cli.get("/users/2").send().await.assert_status_is_ok();
+        cli.get("/a/b")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+
+        let resp = cli.get("/metrics").send().await;
+        resp.assert_status_is_ok();
+        let text = resp.0.into_body().into_string().await.unwrap();
+        for line in [
+            r#"poem_http_requests_total{method="GET",route="/users/:id",status="200"} 2"#,
+            r#"poem_http_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+            r#"poem_http_request_duration_seconds_count{method="GET",route="/users/:id",status="200"} 2"#,
+            r#"poem_http_response_size_bytes_sum{method="GET",route="/users/:id",status="200"} 8"#,
+            // the request to the exporter is in progress
+            "poem_http_requests_in_flight 1",
+        ] {
+            assert!(text.contains(line), "missing `{}` in {}", line, text);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn register_twice() {
+        let metrics = PrometheusMetrics::new();
+        let _ = PrometheusMetrics::with_registry(metrics.registry().clone());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    middleware::{RateLimitAlgorithm, RateLimitDecision, RateLimitQuota, RateLimitStore},
+    Result,
+};
+
+/// The interval of removing the expired states.
+const CLEANUP_INTERVAL: f64 = 1.0;
+
+enum State {
+    TokenBucket { tokens: f64, updated: f64 },
+    SlidingWindow { start: f64, prev: u64, curr: u64 },
+}
+
+impl State {
+    fn new(algorithm: RateLimitAlgorithm, limit: f64, now: f64) -> Self {
+        match algorithm {
+            RateLimitAlgorithm::TokenBucket => State::TokenBucket {
+                tokens: limit,
+                updated: now,
+            },
+            RateLimitAlgorithm::SlidingWindow => State::SlidingWindow {
+                start: now,
+                prev: 0,
+                curr: 0,
+            },
+        }
+    }
+}
+
+struct Entry {
+    state: State,
+    expires_at: f64,
+}
+
+struct InnerStore {
+    entries: HashMap<String, Entry>,
+    last_cleanup: f64,
+}
+
+/// A rate limit store using memory.
+///
+/// The states are not shared between the processes.
+pub struct MemoryRateLimitStore {
+    epoch: Instant,
+    inner: Mutex<InnerStore>,
+}
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            inner: Mutex::new(InnerStore {
+                entries: HashMap::new(),
+                last_cleanup: 0.0,
+            }),
+        }
+    }
+}
+
+impl MemoryRateLimitStore {
+    /// Create a `MemoryRateLimitStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+fn token_bucket(
+    tokens: &mut f64,
+    updated: &mut f64,
+    limit: f64,
+    period: f64,
+    now: f64,
+) -> RateLimitDecision {
+    let rate = limit / period;
+    *tokens = (*tokens + (now - *updated).max(0.0) * rate).min(limit);
+    *updated = now;
+
+    let allowed = *tokens >= 1.0;
+    if allowed {
+        *tokens -= 1.0;
+    }
+    RateLimitDecision {
+        allowed,
+        remaining: tokens.floor() as u64,
+        reset: Duration::from_secs_f64((limit - *tokens) / rate),
+        retry_after: if allowed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - *tokens) / rate)
+        },
+    }
+}
+
+fn sliding_window(
+    start: &mut f64,
+    prev: &mut u64,
+    curr: &mut u64,
+    limit: f64,
+    period: f64,
+    now: f64,
+) -> RateLimitDecision {
+    let windows = ((now - *start) / period).floor();
+    if windows >= 1.0 {
+        *prev = if windows == 1.0 { *curr } else { 0 };
+        *curr = 0;
+        *start += windows * period;
+    }
+
+    let elapsed = (now - *start).max(0.0);
+    let mut count = *prev as f64 * (period - elapsed) / period + *curr as f64;
+    let allowed = count + 1.0 <= limit;
+    if allowed {
+        *curr += 1;
+        count += 1.0;
+    }
+
+    let reset = if *curr > 0 {
+        2.0 * period - elapsed
+    } else if *prev > 0 {
+        period - elapsed
+    } else {
+        0.0
+    };
+    let retry_after = if allowed {
+        0.0
+    } else if *curr as f64 <= limit - 1.0 {
+        // wait until the weighted count of the previous window decreases enough
+        period - elapsed - period * (limit - 1.0 - *curr as f64) / *prev as f64
+    } else {
+        // wait until the current window becomes the previous window
+        period - elapsed + period * (1.0 - (limit - 1.0) / *curr as f64)
+    };
+
+    RateLimitDecision {
+        allowed,
+        remaining: (limit - count).floor().max(0.0) as u64,
+        reset: Duration::from_secs_f64(reset),
+        retry_after: Duration::from_secs_f64(retry_after.max(0.0)),
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn acquire(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision> {
+        let now = self.epoch.elapsed().as_secs_f64();
+        let limit = quota.limit as f64;
+        let period = quota.period.as_secs_f64();
+        let mut inner = self.inner.lock();
+
+        if now - inner.last_cleanup >= CLEANUP_INTERVAL {
+            inner.entries.retain(|_, entry| entry.expires_at > now);
+            inner.last_cleanup = now;
+        }
+
+        let entry = inner
+            .entries
+            .entry(key.to_string())
+            .or_insert_with(|| Entry {
+                state: State::new(quota.algorithm, limit, now),
+                expires_at: now,
+            });
+
+        let (decision, ttl) = loop {
+            match (&mut entry.state, quota.algorithm) {
+                (State::TokenBucket { tokens, updated }, RateLimitAlgorithm::TokenBucket) => {
+                    break (token_bucket(tokens, updated, limit, period, now), period)
+                }
+                (State::SlidingWindow { start, prev, curr }, RateLimitAlgorithm::SlidingWindow) => {
+                    break (
+                        sliding_window(start, prev, curr, limit, period, now),
+                        2.0 * period,
+                    )
+                }
+                // the store is shared by the middlewares with different algorithms
+                _ => entry.state = State::new(quota.algorithm, limit, now),
+            }
+        };
+        entry.expires_at = now + ttl;
+        Ok(decision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let (mut tokens, mut updated) = (3.0, 0.0);
+
+        for remaining in [2, 1, 0] {
+            let decision = token_bucket(&mut tokens, &mut updated, 3.0, 3.0, 0.0);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+
+        let decision = token_bucket(&mut tokens, &mut updated, 3.0, 3.0, 0.5);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Duration::from_secs_f64(0.5));
+        assert_eq!(decision.reset, Duration::from_secs_f64(2.5));
+
+        // refilled a token
+        let decision = token_bucket(&mut tokens, &mut updated, 3.0, 3.0, 1.0);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+
+        // never exceeds the limit
+        let decision = token_bucket(&mut tokens, &mut updated, 3.0, 3.0, 100.0);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 2);
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let (mut start, mut prev, mut curr) = (0.0, 0, 0);
+
+        for remaining in [1, 0] {
+            let decision = sliding_window(&mut start, &mut prev, &mut curr, 2.0, 10.0, 0.0);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+
+        let decision = sliding_window(&mut start, &mut prev, &mut curr, 2.0, 10.0, 5.0);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Duration::from_secs(10));
+        assert_eq!(decision.reset, Duration::from_secs(15));
+
+        // the previous window is weighted by 0.5
+        let decision = sliding_window(&mut start, &mut prev, &mut curr, 2.0, 10.0, 15.0);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!((start, prev, curr), (10.0, 2, 1));
+
+        let decision = sliding_window(&mut start, &mut prev, &mut curr, 2.0, 10.0, 15.0);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Duration::from_secs(5));
+        assert_eq!(decision.reset, Duration::from_secs(15));
+
+        // the previous window is skipped
+        let decision = sliding_window(&mut start, &mut prev, &mut curr, 2.0, 10.0, 35.0);
+        assert!(decision.allowed);
+        assert_eq!((start, prev, curr), (30.0, 0, 1));
+    }
+}
@@ -0,0 +1,429 @@
+mod memory_store;
+#[cfg(feature = "redis-rate-limit")]
+mod redis_store;
+
+use std::{convert::TryInto, sync::Arc, time::Duration};
+
+pub use memory_store::MemoryRateLimitStore;
+#[cfg(feature = "redis-rate-limit")]
+pub use redis_store::RedisRateLimitStore;
+
+use crate::{
+    error::{ResponseErrorExt, TooManyRequestsError},
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    middleware::ApiKeyInfo,
+    web::ClientIp,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+const X_RATELIMIT_LIMIT: &str = "x-ratelimit-limit";
+const X_RATELIMIT_REMAINING: &str = "x-ratelimit-remaining";
+const X_RATELIMIT_RESET: &str = "x-ratelimit-reset";
+
+/// The algorithm used to count the requests.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RateLimitAlgorithm {
+    /// A bucket holds at most `limit` tokens and is refilled continuously at
+    /// the rate of `limit` tokens per `period`, each request takes a token.
+    ///
+    /// It allows the bursts of up to `limit` requests.
+    TokenBucket,
+
+    /// The requests in the current and the previous windows of `period` are
+    /// counted, the count of the previous window is weighted by how much of it
+    /// overlaps the sliding window ending now.
+    ///
+    /// It smooths out the bursts at the boundaries of the fixed windows.
+    SlidingWindow,
+}
+
+impl Default for RateLimitAlgorithm {
+    fn default() -> Self {
+        Self::TokenBucket
+    }
+}
+
+/// How many requests are allowed in a period.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RateLimitQuota {
+    /// The algorithm used to count the requests.
+    pub algorithm: RateLimitAlgorithm,
+    /// The maximum number of the requests in a period.
+    pub limit: u64,
+    /// The length of the period.
+    pub period: Duration,
+}
+
+/// The state of a key after acquiring a permit from a [`RateLimitStore`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RateLimitDecision {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+    /// The number of the requests which are still allowed now.
+    pub remaining: u64,
+    /// The time until the limit of the key is fully reset.
+    pub reset: Duration,
+    /// The time until the next request is allowed, it is zero if the request
+    /// is allowed.
+    pub retry_after: Duration,
+}
+
+/// Represents a back-end storage of the rate limit states.
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Acquires a permit for the key, the state of the key must be updated
+    /// atomically.
+    async fn acquire(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision>;
+}
+
+type KeyFn = dyn Fn(&Request) -> Option<String> + Send + Sync;
+
+enum RateLimitKey {
+    ClientIp,
+    Header(HeaderName),
+    Custom(Box<KeyFn>),
+    ApiKey,
+}
+
+impl RateLimitKey {
+    fn extract(&self, req: &Request) -> Option<String> {
+        match self {
+            RateLimitKey::ClientIp => ClientIp::resolve(req).map(|ip| ip.to_string()),
+            RateLimitKey::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+            RateLimitKey::Custom(f) => f(req),
+            RateLimitKey::ApiKey => req.data::<ApiKeyInfo>().map(|info| info.id.clone()),
+        }
+    }
+
+    fn quota(&self, req: &Request, default: RateLimitQuota) -> RateLimitQuota {
+        match self {
+            RateLimitKey::ApiKey => req
+                .data::<ApiKeyInfo>()
+                .and_then(|info| info.quota)
+                .unwrap_or(default),
+            _ => default,
+        }
+    }
+}
+
+/// Middleware for limiting the rate of the requests.
+///
+/// The requests are keyed by the client IP by default, which is resolved with
+/// the [`TrustedProxies`](crate::web::TrustedProxies) of the request, see
+/// [`ClientIp`]. The requests without a key are not limited. When the limit is
+/// exceeded, [`TooManyRequestsError`] is returned with the `Retry-After`
+/// header, and the `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+/// `X-RateLimit-Reset` (in seconds) headers are added to all responses.
+///
+/// The states are stored in a [`MemoryRateLimitStore`] by default, use
+/// [`RedisRateLimitStore`] to share them between the servers.
+///
+/// # Errors
+///
+/// - [`TooManyRequestsError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     endpoint::make_sync,
+///     http::StatusCode,
+///     middleware::{RateLimit, RateLimitAlgorithm},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// let app = make_sync(|_| "hello").with(
+///     RateLimit::new(2, Duration::from_secs(60))
+///         .algorithm(RateLimitAlgorithm::SlidingWindow)
+///         .key_by_header("x-api-key"),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// for _ in 0..2 {
+///     cli.get("/")
+///         .header("x-api-key", "a")
+///         .send()
+///         .await
+///         .assert_status_is_ok();
+/// }
+/// let resp = cli.get("/").header("x-api-key", "a").send().await;
+/// resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+/// resp.assert_header("x-ratelimit-remaining", "0");
+/// # });
+/// ```
+pub struct RateLimit {
+    quota: RateLimitQuota,
+    key: Arc<RateLimitKey>,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimit {
+    /// Create a `RateLimit` middleware which allows `limit` requests per
+    /// `period` for each key.
+    ///
+    /// # Panics
+    ///
+    /// Panic when `limit` or `period` is zero.
+    pub fn new(limit: u64, period: Duration) -> Self {
+        assert!(limit > 0, "the limit must be greater than zero");
+        assert!(!period.is_zero(), "the period must be greater than zero");
+        Self {
+            quota: RateLimitQuota {
+                algorithm: RateLimitAlgorithm::default(),
+                limit,
+                period,
+            },
+            key: Arc::new(RateLimitKey::ClientIp),
+            store: Arc::new(MemoryRateLimitStore::new()),
+        }
+    }
+
+    /// Sets the algorithm used to count the requests.
+    ///
+    /// Default is [`RateLimitAlgorithm::TokenBucket`].
+    #[must_use]
+    pub fn algorithm(mut self, algorithm: RateLimitAlgorithm) -> Self {
+        self.quota.algorithm = algorithm;
+        self
+    }
+
+    /// Keys the requests by the value of the specified header, such as an API
+    /// key.
+    ///
+    /// # Panics
+    ///
+    /// Panic when the header name is invalid.
+    #[must_use]
+    pub fn key_by_header<K: TryInto<HeaderName>>(self, name: K) -> Self {
+        let name = name
+            .try_into()
+            .unwrap_or_else(|_| panic!("invalid header name"));
+        Self {
+            key: Arc::new(RateLimitKey::Header(name)),
+            ..self
+        }
+    }
+
+    /// Keys the requests by the return value of the function, the requests
+    /// for which it returns `None` are not limited.
+    #[must_use]
+    pub fn key_fn<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            key: Arc::new(RateLimitKey::Custom(Box::new(f))),
+            ..self
+        }
+    }
+
+    /// Keys the requests by the API key authenticated by
+    /// [`ApiKey`](crate::middleware::ApiKey) middleware, which must wrap this
+    /// middleware. The [`ApiKeyInfo::quota`] of the key overrides the quota of
+    /// this middleware.
+    #[must_use]
+    pub fn key_by_api_key(self) -> Self {
+        Self {
+            key: Arc::new(RateLimitKey::ApiKey),
+            ..self
+        }
+    }
+
+    /// Sets the store of the rate limit states.
+    #[must_use]
+    pub fn store(self, store: impl RateLimitStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RateLimit {
+    type Output = RateLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint {
+            inner: ep,
+            quota: self.quota,
+            key: self.key.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Endpoint for RateLimit middleware.
+pub struct RateLimitEndpoint<E> {
+    inner: E,
+    quota: RateLimitQuota,
+    key: Arc<RateLimitKey>,
+    store: Arc<dyn RateLimitStore>,
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+fn rate_limit_headers(quota: &RateLimitQuota, decision: &RateLimitDecision) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(quota.limit));
+    headers.insert(X_RATELIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(
+        X_RATELIMIT_RESET,
+        HeaderValue::from(ceil_secs(decision.reset)),
+    );
+    headers
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for RateLimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let key = match self.key.extract(&req) {
+            Some(key) => key,
+            None => return self.inner.call(req).await.map(IntoResponse::into_response),
+        };
+
+        let quota = self.key.quota(&req, self.quota);
+        let decision = self.store.acquire(&key, &quota).await?;
+        let mut headers = rate_limit_headers(&quota, &decision);
+        if !decision.allowed {
+            headers.insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(ceil_secs(decision.retry_after).max(1)),
+            );
+            return Err(TooManyRequestsError.with_headers(headers));
+        }
+
+        let mut resp = self.inner.call(req).await?.into_response();
+        resp.headers_mut().extend(headers);
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{
+        endpoint::make_sync,
+        test::TestClient,
+        web::{RemoteAddr, TrustedProxies},
+        Addr, EndpointExt,
+    };
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let cli = TestClient::new(
+            make_sync(|_| "hello")
+                .with(RateLimit::new(2, Duration::from_secs(10)).key_by_header("x-api-key")),
+        );
+
+        let resp = cli.get("/").header("x-api-key", "a").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-ratelimit-limit", "2");
+        resp.assert_header("x-ratelimit-remaining", "1");
+        resp.assert_header("x-ratelimit-reset", "5");
+
+        let resp = cli.get("/").header("x-api-key", "a").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-ratelimit-remaining", "0");
+
+        let resp = cli.get("/").header("x-api-key", "a").send().await;
+        resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        resp.assert_header("x-ratelimit-remaining", "0");
+        resp.assert_header("retry-after", "5");
+
+        // the other keys have their own limits
+        cli.get("/")
+            .header("x-api-key", "b")
+            .send()
+            .await
+            .assert_status_is_ok();
+
+        // the requests without a key are not limited
+        for _ in 0..3 {
+            cli.get("/").send().await.assert_status_is_ok();
+        }
+    }
+
+    #[tokio::test]
+    async fn key_by_client_ip() {
+        let cli = TestClient::new(
+            make_sync(|_| "hello")
+                .with(RateLimit::new(1, Duration::from_secs(10)))
+                .before(|mut req| async move {
+                    req.state_mut().remote_addr =
+                        RemoteAddr(Addr::SocketAddr("10.0.0.1:8000".parse().unwrap()));
+                    Ok(req)
+                })
+                .data(TrustedProxies::new().trust("10.0.0.0/8")),
+        );
+
+        for ip in ["1.2.3.4", "5.6.7.8"] {
+            cli.get("/")
+                .header("x-forwarded-for", ip)
+                .send()
+                .await
+                .assert_status_is_ok();
+        }
+        cli.get("/")
+            .header("x-forwarded-for", "1.2.3.4")
+            .send()
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn key_fn() {
+        let cli = TestClient::new(
+            make_sync(|_| "hello").with(
+                RateLimit::new(1, Duration::from_secs(10))
+                    .algorithm(RateLimitAlgorithm::SlidingWindow)
+                    .key_fn(|req| req.uri().query().map(ToString::to_string)),
+            ),
+        );
+
+        cli.get("/")
+            .query("a", &1)
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .query("a", &1)
+            .send()
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+        cli.get("/")
+            .query("a", &2)
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn downcast_too_many_requests() {
+        let ep = make_sync(|_| "hello")
+            .with(RateLimit::new(1, Duration::from_secs(10)).key_fn(|_| Some("key".to_string())));
+
+        ep.call(Request::default()).await.unwrap();
+        let err = ep.call(Request::default()).await.unwrap_err();
+        assert!(err.is::<TooManyRequestsError>());
+        let resp = err.into_response();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.headers().get(header::RETRY_AFTER),
+            Some(&HeaderValue::from_static("10"))
+        );
+    }
+}
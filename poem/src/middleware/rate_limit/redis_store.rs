@@ -0,0 +1,199 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use redis::{aio::ConnectionLike, Script};
+
+use crate::{
+    error::InternalServerError,
+    middleware::{RateLimitAlgorithm, RateLimitDecision, RateLimitQuota, RateLimitStore},
+    Result,
+};
+
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local limit = tonumber(ARGV[1])
+local period = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(state[1]) or limit
+local updated = tonumber(state[2]) or now
+local rate = limit / period
+
+tokens = math.min(limit, tokens + math.max(0, now - updated) * rate)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HMSET', KEYS[1], 'tokens', tostring(tokens), 'updated', now)
+redis.call('PEXPIRE', KEYS[1], period)
+
+local retry_after = 0
+if allowed == 0 then
+    retry_after = math.ceil((1 - tokens) / rate)
+end
+return {allowed, math.floor(tokens), math.ceil((limit - tokens) / rate), retry_after}
+"#;
+
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local limit = tonumber(ARGV[1])
+local period = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local state = redis.call('HMGET', KEYS[1], 'start', 'prev', 'curr')
+local start = tonumber(state[1]) or now
+local prev = tonumber(state[2]) or 0
+local curr = tonumber(state[3]) or 0
+
+local windows = math.floor((now - start) / period)
+if windows >= 1 then
+    if windows == 1 then prev = curr else prev = 0 end
+    curr = 0
+    start = start + windows * period
+end
+
+local elapsed = math.max(0, now - start)
+local count = prev * (period - elapsed) / period + curr
+local allowed = 0
+if count + 1 <= limit then
+    curr = curr + 1
+    count = count + 1
+    allowed = 1
+end
+redis.call('HMSET', KEYS[1], 'start', start, 'prev', prev, 'curr', curr)
+redis.call('PEXPIRE', KEYS[1], 2 * period)
+
+local reset = 0
+if curr > 0 then
+    reset = 2 * period - elapsed
+elseif prev > 0 then
+    reset = period - elapsed
+end
+local retry_after = 0
+if allowed == 0 then
+    if curr <= limit - 1 then
+        retry_after = period - elapsed - period * (limit - 1 - curr) / prev
+    else
+        retry_after = period - elapsed + period * (1 - (limit - 1) / curr)
+    end
+end
+return {allowed, math.max(0, math.floor(limit - count)), math.ceil(reset), math.ceil(math.max(0, retry_after))}
+"#;
+
+/// A rate limit store using redis, the states are updated atomically with Lua
+/// scripts, so they can be shared between the servers.
+///
+/// The times are read from the clocks of the servers, which should be
+/// synchronized.
+///
+/// # Errors
+///
+/// - [`redis::RedisError`]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis-rate-limit")))]
+pub struct RedisRateLimitStore<T> {
+    connection: T,
+    prefix: String,
+    token_bucket: Script,
+    sliding_window: Script,
+}
+
+impl<T> RedisRateLimitStore<T> {
+    /// Create a `RedisRateLimitStore`.
+    pub fn new(connection: T) -> Self {
+        Self {
+            connection,
+            prefix: "poem-rate-limit:".to_string(),
+            token_bucket: Script::new(TOKEN_BUCKET_SCRIPT),
+            sliding_window: Script::new(SLIDING_WINDOW_SCRIPT),
+        }
+    }
+
+    /// Sets the prefix of the redis keys.
+    ///
+    /// Default is `poem-rate-limit:`.
+    #[must_use]
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ConnectionLike + Clone + Sync + Send> RateLimitStore for RedisRateLimitStore<T> {
+    async fn acquire(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision> {
+        let (script, algorithm) = match quota.algorithm {
+            RateLimitAlgorithm::TokenBucket => (&self.token_bucket, "token-bucket"),
+            RateLimitAlgorithm::SlidingWindow => (&self.sliding_window, "sliding-window"),
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let (allowed, remaining, reset, retry_after): (u64, u64, u64, u64) = script
+            .key(format!("{}{}:{}", self.prefix, algorithm, key))
+            .arg(quota.limit)
+            .arg(quota.period.as_millis() as u64)
+            .arg(now)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(InternalServerError)?;
+
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            remaining,
+            reset: Duration::from_millis(reset),
+            retry_after: Duration::from_millis(retry_after),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::{aio::ConnectionManager, Client, ConnectionLike};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn redis_rate_limit_store() {
+        let mut client = match Client::open("redis://127.0.0.1/") {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+        if !client.check_connection() {
+            return;
+        }
+
+        let store = RedisRateLimitStore::new(ConnectionManager::new(client).await.unwrap()).prefix(
+            format!(
+                "poem-rate-limit-test-{}:",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos()
+            ),
+        );
+
+        for algorithm in [
+            RateLimitAlgorithm::TokenBucket,
+            RateLimitAlgorithm::SlidingWindow,
+        ] {
+            let quota = RateLimitQuota {
+                algorithm,
+                limit: 2,
+                period: Duration::from_secs(10),
+            };
+
+            for remaining in [1, 0] {
+                let decision = store.acquire("a", &quota).await.unwrap();
+                assert!(decision.allowed);
+                assert_eq!(decision.remaining, remaining);
+            }
+
+            let decision = store.acquire("a", &quota).await.unwrap();
+            assert!(!decision.allowed);
+            assert!(decision.retry_after > Duration::ZERO);
+
+            assert!(store.acquire("b", &quota).await.unwrap().allowed);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    convert::TryInto,
+    fmt::{self, Display, Formatter},
+    ops::Deref,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing::Instrument;
+
+use crate::{
+    error::GetDataError,
+    http::{header::HeaderName, HeaderValue},
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, RequestBody, Response, Result,
+};
+
+/// The format of the request IDs generated by [`RequestId`] middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RequestIdFormat {
+    /// Random [UUID](https://datatracker.ietf.org/doc/html/rfc4122) version 4,
+    /// such as `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    Uuid,
+
+    /// [ULID](https://github.com/ulid/spec), which is sortable by the
+    /// generation time, such as `01ARZ3NDEKTSV4RRFFQ69G5FAV`.
+    Ulid,
+}
+
+impl Default for RequestIdFormat {
+    fn default() -> Self {
+        Self::Uuid
+    }
+}
+
+impl RequestIdFormat {
+    fn generate(&self) -> String {
+        match self {
+            RequestIdFormat::Uuid => uuid::Uuid::new_v4().to_string(),
+            RequestIdFormat::Ulid => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                encode_ulid(timestamp, rand::random())
+            }
+        }
+    }
+}
+
+/// Encodes the 48 bits timestamp and the 80 bits randomness with Crockford's
+/// base32.
+fn encode_ulid(timestamp: u64, random: [u8; 10]) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+    let value = random
+        .iter()
+        .fold(u128::from(timestamp & 0xffff_ffff_ffff), |acc, b| {
+            (acc << 8) | u128::from(*b)
+        });
+    (0..26)
+        .rev()
+        .map(|i| ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// An extractor that gets the request ID generated or reused by
+/// [`RequestId`] middleware.
+///
+/// # Errors
+///
+/// - [`GetDataError`]
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ReqId(String);
+
+impl ReqId {
+    /// Returns the request ID as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for ReqId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for ReqId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ReqId {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<ReqId>()
+            .cloned()
+            .ok_or_else(|| GetDataError(std::any::type_name::<ReqId>()))?)
+    }
+}
+
+/// Middleware for assigning an ID to each request.
+///
+/// The ID is read from the request header, or generated if the header does
+/// not exist or is invalid. It is stored in the request extensions as
+/// [`ReqId`], recorded as the `request_id` field of a `tracing` span around
+/// the inner endpoint, and echoed in the response header.
+///
+/// Apply it inside [`Tracing`](crate::middleware::Tracing) to make the span a
+/// child of the request span.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{ReqId, RequestId, RequestIdFormat},
+///     test::TestClient,
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(req_id: ReqId) -> String {
+///     req_id.to_string()
+/// }
+///
+/// let app = index.with(RequestId::new().format(RequestIdFormat::Ulid));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(app)
+///     .get("/")
+///     .header("x-request-id", "abc")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-request-id", "abc");
+/// resp.assert_text("abc").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+pub struct RequestId {
+    header_name: HeaderName,
+    format: RequestIdFormat,
+    reuse_incoming: bool,
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self {
+            header_name: HeaderName::from_static("x-request-id"),
+            format: RequestIdFormat::default(),
+            reuse_incoming: true,
+        }
+    }
+}
+
+impl RequestId {
+    /// Create new `RequestId` middleware.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the header used to read the request ID from the request and echo
+    /// it in the response, it is ignored if the name is invalid.
+    ///
+    /// Default is `x-request-id`.
+    #[must_use]
+    pub fn header_name<K>(self, header_name: K) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        match header_name.try_into() {
+            Ok(header_name) => Self {
+                header_name,
+                ..self
+            },
+            Err(_) => self,
+        }
+    }
+
+    /// Sets the format of the generated request IDs.
+    ///
+    /// Default is [`RequestIdFormat::Uuid`].
+    #[must_use]
+    pub fn format(self, format: RequestIdFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Sets whether to reuse the request ID from the request header, it should
+    /// be disabled if the clients are not trusted.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn reuse_incoming(self, reuse_incoming: bool) -> Self {
+        Self {
+            reuse_incoming,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RequestId {
+    type Output = RequestIdEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RequestIdEndpoint {
+            inner: ep,
+            header_name: self.header_name.clone(),
+            format: self.format,
+            reuse_incoming: self.reuse_incoming,
+        }
+    }
+}
+
+/// Endpoint for RequestId middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+pub struct RequestIdEndpoint<E> {
+    inner: E,
+    header_name: HeaderName,
+    format: RequestIdFormat,
+    reuse_incoming: bool,
+}
+
+/// The incoming request IDs are limited to the printable ASCII characters, so
+/// they are safe to log and echo.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 128 && id.bytes().all(|c| c.is_ascii_graphic())
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for RequestIdEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let id = self
+            .reuse_incoming
+            .then(|| {
+                req.headers()
+                    .get(&self.header_name)
+                    .and_then(|value| value.to_str().ok())
+                    .filter(|id| is_valid_id(id))
+                    .map(ToString::to_string)
+            })
+            .flatten()
+            .unwrap_or_else(|| self.format.generate());
+        let header_value = HeaderValue::from_str(&id).ok();
+
+        if let Some(value) = &header_value {
+            req.headers_mut()
+                .insert(self.header_name.clone(), value.clone());
+        }
+        req.extensions_mut().insert(ReqId(id.clone()));
+
+        let span = tracing::info_span!("request_id", request_id = %id);
+        let mut resp = self.inner.call(req).instrument(span).await?.into_response();
+
+        if let Some(value) = header_value {
+            resp.headers_mut().insert(self.header_name.clone(), value);
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn index(req_id: ReqId) -> String {
+        req_id.to_string()
+    }
+
+    #[test]
+    fn test_encode_ulid() {
+        assert_eq!(encode_ulid(0, [0; 10]), "00000000000000000000000000");
+        assert_eq!(
+            encode_ulid(u64::MAX, [0xff; 10]),
+            "7ZZZZZZZZZZZZZZZZZZZZZZZZZ"
+        );
+        assert_eq!(
+            encode_ulid(1469918176385, [0; 10]),
+            "01ARYZ6S410000000000000000"
+        );
+    }
+
+    #[tokio::test]
+    async fn generate() {
+        for (format, len) in [(RequestIdFormat::Uuid, 36), (RequestIdFormat::Ulid, 26)] {
+            let cli = TestClient::new(index.with(RequestId::new().format(format)));
+            let resp = cli.get("/").send().await;
+            resp.assert_status_is_ok();
+            let id = resp.0.header("x-request-id").unwrap().to_string();
+            assert_eq!(id.len(), len);
+            resp.assert_text(id).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn reuse_incoming() {
+        let cli = TestClient::new(index.with(RequestId::new().header_name("x-trace-id")));
+        let resp = cli.get("/").header("x-trace-id", "abc").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-trace-id", "abc");
+        resp.assert_text("abc").await;
+
+        let resp = cli.get("/").header("x-trace-id", "a b").send().await;
+        resp.assert_status_is_ok();
+        assert_eq!(resp.0.header("x-trace-id").unwrap().len(), 36);
+
+        let cli = TestClient::new(index.with(RequestId::new().reuse_incoming(false)));
+        let resp = cli.get("/").header("x-request-id", "abc").send().await;
+        resp.assert_status_is_ok();
+        assert_ne!(resp.0.header("x-request-id"), Some("abc"));
+    }
+}
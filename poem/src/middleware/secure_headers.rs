@@ -0,0 +1,541 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Deref,
+};
+
+use crate::{
+    error::GetDataError,
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Hsts,
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, RequestBody, Response, Result,
+};
+
+/// The value of the `X-Frame-Options` header.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FrameOptions {
+    /// The page cannot be displayed in a frame.
+    Deny,
+
+    /// The page can only be displayed in a frame on the same origin.
+    SameOrigin,
+}
+
+impl FrameOptions {
+    fn header_value(&self) -> HeaderValue {
+        match self {
+            FrameOptions::Deny => HeaderValue::from_static("DENY"),
+            FrameOptions::SameOrigin => HeaderValue::from_static("SAMEORIGIN"),
+        }
+    }
+}
+
+/// A builder for the `Content-Security-Policy` header.
+///
+/// If the nonce is enabled, [`SecureHeaders`] generates a nonce for each
+/// request, adds it to the `script-src` and `style-src` directives, and
+/// stores it in the request extensions as [`CspNonce`], so the templates can
+/// use it in the `nonce` attributes.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<String>)>,
+    nonce: bool,
+    report_only: bool,
+}
+
+impl ContentSecurityPolicy {
+    /// Create an empty `ContentSecurityPolicy`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends the sources to the specified directive, such as
+    /// `.directive("img-src", ["'self'", "data:"])`.
+    #[must_use]
+    pub fn directive<I, T>(mut self, name: impl Into<String>, sources: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let name = name.into().to_ascii_lowercase();
+        let sources = sources.into_iter().map(Into::into);
+        match self.directives.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, values)) => values.extend(sources),
+            None => self.directives.push((name, sources.collect())),
+        }
+        self
+    }
+
+    /// Appends the sources to the `default-src` directive.
+    #[must_use]
+    pub fn default_src<I, T>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.directive("default-src", sources)
+    }
+
+    /// Appends the sources to the `script-src` directive.
+    #[must_use]
+    pub fn script_src<I, T>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.directive("script-src", sources)
+    }
+
+    /// Appends the sources to the `style-src` directive.
+    #[must_use]
+    pub fn style_src<I, T>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.directive("style-src", sources)
+    }
+
+    /// Sets whether to generate a nonce for each request.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn nonce(self, nonce: bool) -> Self {
+        Self { nonce, ..self }
+    }
+
+    /// Sets whether to use the `Content-Security-Policy-Report-Only` header,
+    /// so the violations are only reported.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn report_only(self, report_only: bool) -> Self {
+        Self {
+            report_only,
+            ..self
+        }
+    }
+
+    fn header_value(&self, nonce: Option<&CspNonce>) -> Option<HeaderValue> {
+        let mut directives = self.directives.clone();
+        if let Some(nonce) = nonce {
+            for name in ["script-src", "style-src"] {
+                let source = format!("'nonce-{}'", nonce);
+                match directives.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, values)) => values.push(source),
+                    None => directives.push((name.to_string(), vec![source])),
+                }
+            }
+        }
+
+        let value = directives
+            .iter()
+            .map(|(name, values)| {
+                let mut directive = name.clone();
+                for value in values {
+                    directive.push(' ');
+                    directive.push_str(value);
+                }
+                directive
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+/// An extractor that gets the nonce of the `Content-Security-Policy` header
+/// generated by [`SecureHeaders`] middleware.
+///
+/// # Errors
+///
+/// - [`GetDataError`]
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CspNonce(String);
+
+impl CspNonce {
+    fn generate() -> Self {
+        Self(base64::encode(rand::random::<[u8; 16]>()))
+    }
+
+    /// Returns the nonce as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for CspNonce {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for CspNonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for CspNonce {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<CspNonce>()
+            .cloned()
+            .ok_or_else(|| GetDataError(std::any::type_name::<CspNonce>()))?)
+    }
+}
+
+/// Middleware for setting the security related headers.
+///
+/// By default, it sets the following headers:
+///
+/// - `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// - `X-Content-Type-Options: nosniff`
+/// - `X-Frame-Options: DENY`
+/// - `Referrer-Policy: strict-origin-when-cross-origin`
+///
+/// The `Permissions-Policy` and `Content-Security-Policy` headers are only set
+/// if they are specified.
+///
+/// The headers which already exist in the response are not overridden, so a
+/// route can override them by applying another `SecureHeaders` middleware or
+/// setting the headers in the handler.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     middleware::{ContentSecurityPolicy, CspNonce, FrameOptions, SecureHeaders},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index(nonce: CspNonce) -> String {
+///     format!("<script nonce=\"{}\">alert(1)</script>", nonce)
+/// }
+///
+/// #[handler]
+/// fn embed() -> &'static str {
+///     "embed"
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .at(
+///         "/embed",
+///         get(embed).with(SecureHeaders::new().frame_options(Some(FrameOptions::SameOrigin))),
+///     )
+///     .with(
+///         SecureHeaders::new().content_security_policy(Some(
+///             ContentSecurityPolicy::new()
+///                 .default_src(["'self'"])
+///                 .nonce(true),
+///         )),
+///     );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+///
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-frame-options", "DENY");
+///
+/// let resp = cli.get("/embed").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-frame-options", "SAMEORIGIN");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+#[derive(Debug, Clone)]
+pub struct SecureHeaders {
+    hsts: Option<Hsts>,
+    content_type_options: bool,
+    frame_options: Option<FrameOptions>,
+    referrer_policy: Option<HeaderValue>,
+    permissions_policy: Option<HeaderValue>,
+    csp: Option<ContentSecurityPolicy>,
+}
+
+impl Default for SecureHeaders {
+    fn default() -> Self {
+        Self {
+            hsts: Some(Hsts::default()),
+            content_type_options: true,
+            frame_options: Some(FrameOptions::Deny),
+            referrer_policy: Some(HeaderValue::from_static("strict-origin-when-cross-origin")),
+            permissions_policy: None,
+            csp: None,
+        }
+    }
+}
+
+impl SecureHeaders {
+    /// Create new `SecureHeaders` middleware.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the `Strict-Transport-Security` header, `None` to disable it.
+    #[must_use]
+    pub fn hsts(self, hsts: Option<Hsts>) -> Self {
+        Self { hsts, ..self }
+    }
+
+    /// Sets whether to set the `X-Content-Type-Options: nosniff` header.
+    #[must_use]
+    pub fn content_type_options(self, enabled: bool) -> Self {
+        Self {
+            content_type_options: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the `X-Frame-Options` header, `None` to disable it.
+    #[must_use]
+    pub fn frame_options(self, frame_options: Option<FrameOptions>) -> Self {
+        Self {
+            frame_options,
+            ..self
+        }
+    }
+
+    /// Sets the `Referrer-Policy` header, such as `no-referrer`, `None` to
+    /// disable it, it is ignored if the value is invalid.
+    #[must_use]
+    pub fn referrer_policy(self, policy: Option<&str>) -> Self {
+        match policy.map(HeaderValue::from_str) {
+            Some(Err(_)) => self,
+            Some(Ok(value)) => Self {
+                referrer_policy: Some(value),
+                ..self
+            },
+            None => Self {
+                referrer_policy: None,
+                ..self
+            },
+        }
+    }
+
+    /// Sets the `Permissions-Policy` header, such as `camera=(),
+    /// geolocation=(self)`, it is ignored if the value is invalid.
+    #[must_use]
+    pub fn permissions_policy(self, policy: &str) -> Self {
+        match HeaderValue::from_str(policy) {
+            Ok(value) => Self {
+                permissions_policy: Some(value),
+                ..self
+            },
+            Err(_) => self,
+        }
+    }
+
+    /// Sets the `Content-Security-Policy` header, `None` to disable it.
+    #[must_use]
+    pub fn content_security_policy(self, csp: Option<ContentSecurityPolicy>) -> Self {
+        Self { csp, ..self }
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, nonce: Option<&CspNonce>) {
+        let mut set_default = |name, value| {
+            headers.entry(name).or_insert(value);
+        };
+
+        if let Some(hsts) = &self.hsts {
+            set_default(header::STRICT_TRANSPORT_SECURITY, hsts.header_value());
+        }
+        if self.content_type_options {
+            set_default(
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            );
+        }
+        if let Some(frame_options) = &self.frame_options {
+            set_default(header::X_FRAME_OPTIONS, frame_options.header_value());
+        }
+        if let Some(policy) = &self.referrer_policy {
+            set_default(header::REFERRER_POLICY, policy.clone());
+        }
+        if let Some(policy) = &self.permissions_policy {
+            set_default(
+                header::HeaderName::from_static("permissions-policy"),
+                policy.clone(),
+            );
+        }
+        if let Some(csp) = &self.csp {
+            let name = if csp.report_only {
+                header::CONTENT_SECURITY_POLICY_REPORT_ONLY
+            } else {
+                header::CONTENT_SECURITY_POLICY
+            };
+            if !headers.contains_key(&name) {
+                if let Some(value) = csp.header_value(nonce) {
+                    headers.insert(name, value);
+                }
+            }
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SecureHeaders {
+    type Output = SecureHeadersEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SecureHeadersEndpoint {
+            inner: ep,
+            config: self.clone(),
+        }
+    }
+}
+
+/// Endpoint for SecureHeaders middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "secure-headers")))]
+pub struct SecureHeadersEndpoint<E> {
+    inner: E,
+    config: SecureHeaders,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for SecureHeadersEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let nonce = match &self.config.csp {
+            Some(csp) if csp.nonce => {
+                let nonce = CspNonce::generate();
+                req.extensions_mut().insert(nonce.clone());
+                Some(nonce)
+            }
+            _ => None,
+        };
+
+        let mut resp = self.inner.call(req).await?.into_response();
+        self.config.apply(resp.headers_mut(), nonce.as_ref());
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn index(nonce: Option<CspNonce>) -> String {
+        nonce.map(|nonce| nonce.to_string()).unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn default_headers() {
+        let cli = TestClient::new(index.with(SecureHeaders::new()));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header(
+            header::STRICT_TRANSPORT_SECURITY,
+            "max-age=31536000; includeSubDomains",
+        );
+        resp.assert_header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+        resp.assert_header(header::X_FRAME_OPTIONS, "DENY");
+        resp.assert_header(header::REFERRER_POLICY, "strict-origin-when-cross-origin");
+        resp.assert_header_is_not_exist("permissions-policy");
+        resp.assert_header_is_not_exist(header::CONTENT_SECURITY_POLICY);
+    }
+
+    #[tokio::test]
+    async fn custom_headers() {
+        let cli = TestClient::new(
+            index.with(
+                SecureHeaders::new()
+                    .hsts(Some(Hsts::new(Duration::from_secs(60)).preload(true)))
+                    .content_type_options(false)
+                    .frame_options(None)
+                    .referrer_policy(Some("no-referrer"))
+                    .permissions_policy("camera=()"),
+            ),
+        );
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header(
+            header::STRICT_TRANSPORT_SECURITY,
+            "max-age=60; includeSubDomains; preload",
+        );
+        resp.assert_header_is_not_exist(header::X_CONTENT_TYPE_OPTIONS);
+        resp.assert_header_is_not_exist(header::X_FRAME_OPTIONS);
+        resp.assert_header(header::REFERRER_POLICY, "no-referrer");
+        resp.assert_header("permissions-policy", "camera=()");
+    }
+
+    #[tokio::test]
+    async fn csp_nonce() {
+        let csp = ContentSecurityPolicy::new()
+            .default_src(["'self'"])
+            .script_src(["'self'"])
+            .directive("img-src", ["'self'", "data:"])
+            .nonce(true);
+        let cli = TestClient::new(
+            index.with(SecureHeaders::new().content_security_policy(Some(csp.clone()))),
+        );
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        let value = resp
+            .0
+            .header(header::CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .to_string();
+        let nonce = resp.0.into_body().into_string().await.unwrap();
+        assert!(!nonce.is_empty());
+        assert_eq!(
+            value,
+            format!(
+                "default-src 'self'; script-src 'self' 'nonce-{0}'; img-src 'self' data:; \
+                 style-src 'nonce-{0}'",
+                nonce
+            )
+        );
+
+        let cli = TestClient::new(index.with(
+            SecureHeaders::new().content_security_policy(Some(csp.nonce(false).report_only(true))),
+        ));
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist(header::CONTENT_SECURITY_POLICY);
+        resp.assert_header(
+            header::CONTENT_SECURITY_POLICY_REPORT_ONLY,
+            "default-src 'self'; script-src 'self'; img-src 'self' data:",
+        );
+        resp.assert_text("").await;
+    }
+
+    #[tokio::test]
+    async fn override_per_route() {
+        let csp = ContentSecurityPolicy::new().default_src(["'self'"]);
+        let ep = index
+            .with(
+                SecureHeaders::new()
+                    .frame_options(Some(FrameOptions::SameOrigin))
+                    .content_security_policy(Some(csp.clone().nonce(true))),
+            )
+            .with(SecureHeaders::new().content_security_policy(Some(csp.nonce(true))));
+        let cli = TestClient::new(ep);
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::X_FRAME_OPTIONS, "SAMEORIGIN");
+        let value = resp
+            .0
+            .header(header::CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .to_string();
+        let nonce = resp.0.into_body().into_string().await.unwrap();
+        assert!(value.contains(&format!("'nonce-{}'", nonce)));
+    }
+}
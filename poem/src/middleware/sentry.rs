@@ -0,0 +1,403 @@
+use std::{any::Any, panic::AssertUnwindSafe, sync::Arc};
+
+use futures_util::FutureExt;
+use sentry_core::{
+    protocol::{self, Event, Exception, Level, Mechanism, SpanStatus},
+    Hub, SentryFutureExt, TransactionContext, User,
+};
+
+use crate::{
+    http::{header, HeaderMap, StatusCode},
+    web::MatchedPath,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The request headers which are only sent if `send_default_pii` is enabled.
+const SENSITIVE_HEADERS: [header::HeaderName; 4] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::PROXY_AUTHORIZATION,
+    header::SET_COOKIE,
+];
+
+type UserFn = Arc<dyn Fn(&Request) -> Option<User> + Send + Sync>;
+
+/// Middleware for reporting the panics and the server errors to
+/// [Sentry](https://sentry.io).
+///
+/// Each request is processed in a new [`Hub`] with the request attached to
+/// its scope, so the events captured by the endpoints also contain the URL,
+/// the method and the headers of the request. The `Cookie` and the
+/// `Authorization` headers are only sent if the `send_default_pii` option of
+/// the client is enabled.
+///
+/// A performance transaction is started for each request, and continues the
+/// trace of the `sentry-trace` header. It is set as the span of the scope, so
+/// the events, and the spans created with
+/// [`sentry-tracing`](https://crates.io/crates/sentry-tracing) during the
+/// request are tied to it.
+///
+/// The panics are captured and resumed, so they can still be caught by
+/// [`CatchPanic`](crate::middleware::CatchPanic) applied outside of this
+/// middleware.
+///
+/// NOTE: The Sentry client must be initialized with `sentry::init`.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     middleware::{CatchPanic, Sentry},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .with(Sentry::new())
+///     .with(CatchPanic::new());
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "sentry")))]
+pub struct Sentry {
+    capture_server_errors: bool,
+    user: UserFn,
+}
+
+impl Default for Sentry {
+    fn default() -> Self {
+        Self {
+            capture_server_errors: true,
+            user: Arc::new(|req| req.extensions().get::<User>().cloned()),
+        }
+    }
+}
+
+impl Sentry {
+    /// Create `Sentry` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Captures the responses whose status is `5xx`. Default is `true`.
+    #[must_use]
+    pub fn capture_server_errors(self, enable: bool) -> Self {
+        Self {
+            capture_server_errors: enable,
+            ..self
+        }
+    }
+
+    /// Sets a function to get the user of the request, which is attached to
+    /// the events.
+    ///
+    /// Default is the [`User`] in the extensions of the request, which is
+    /// inserted by the outer middlewares, such as the authentication.
+    #[must_use]
+    pub fn user<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Option<User> + Send + Sync + 'static,
+    {
+        Self {
+            user: Arc::new(f),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Sentry {
+    type Output = SentryEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SentryEndpoint {
+            inner: ep,
+            capture_server_errors: self.capture_server_errors,
+            user: self.user.clone(),
+        }
+    }
+}
+
+/// Endpoint for Sentry middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "sentry")))]
+pub struct SentryEndpoint<E> {
+    inner: E,
+    capture_server_errors: bool,
+    user: UserFn,
+}
+
+fn sentry_request(req: &Request, send_default_pii: bool) -> protocol::Request {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            req.original_uri()
+                .authority()
+                .map(|authority| authority.as_str())
+        })
+        .unwrap_or("localhost");
+    let url = format!("{}://{}{}", req.scheme(), host, req.original_uri().path());
+
+    protocol::Request {
+        url: url.parse().ok(),
+        method: Some(req.method().to_string()),
+        query_string: req.original_uri().query().map(ToString::to_string),
+        cookies: if send_default_pii {
+            req.header(header::COOKIE).map(ToString::to_string)
+        } else {
+            None
+        },
+        headers: sentry_headers(req.headers(), send_default_pii),
+        ..Default::default()
+    }
+}
+
+fn sentry_headers(headers: &HeaderMap, send_default_pii: bool) -> protocol::Map<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| send_default_pii || !SENSITIVE_HEADERS.contains(name))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn panic_event(payload: &(dyn Any + Send)) -> Event<'static> {
+    let message = payload
+        .downcast_ref::<&'static str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+
+    Event {
+        exception: vec![Exception {
+            ty: "panic".to_string(),
+            value: Some(message),
+            mechanism: Some(Mechanism {
+                ty: "panic".to_string(),
+                handled: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Fatal,
+        ..Default::default()
+    }
+}
+
+fn span_status(status: StatusCode) -> SpanStatus {
+    match status {
+        StatusCode::UNAUTHORIZED => SpanStatus::Unauthenticated,
+        StatusCode::FORBIDDEN => SpanStatus::PermissionDenied,
+        StatusCode::NOT_FOUND => SpanStatus::NotFound,
+        StatusCode::CONFLICT => SpanStatus::AlreadyExists,
+        StatusCode::TOO_MANY_REQUESTS => SpanStatus::ResourceExhausted,
+        StatusCode::NOT_IMPLEMENTED => SpanStatus::Unimplemented,
+        StatusCode::SERVICE_UNAVAILABLE => SpanStatus::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => SpanStatus::DeadlineExceeded,
+        _ if status.is_client_error() => SpanStatus::InvalidArgument,
+        _ if status.is_server_error() => SpanStatus::InternalError,
+        _ => SpanStatus::Ok,
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for SentryEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        let send_default_pii = hub
+            .client()
+            .map(|client| client.options().send_default_pii)
+            .unwrap_or_default();
+
+        let sentry_req = sentry_request(&req, send_default_pii);
+        let user = (self.user)(&req);
+        let name = format!("{} {}", req.method(), req.original_uri().path());
+        let headers = req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)));
+        let transaction = hub.start_transaction(TransactionContext::continue_from_headers(
+            &name,
+            "http.server",
+            headers,
+        ));
+        transaction.set_request(sentry_req.clone());
+
+        hub.configure_scope(|scope| {
+            scope.set_span(Some(transaction.clone().into()));
+            scope.set_user(user);
+            scope.add_event_processor(move |mut event| {
+                if event.request.is_none() {
+                    event.request = Some(sentry_req.clone());
+                }
+                Some(event)
+            });
+        });
+
+        let res = AssertUnwindSafe(self.inner.call(req))
+            .catch_unwind()
+            .bind_hub(hub.clone())
+            .await;
+        let res = match res {
+            Ok(res) => res.map(IntoResponse::into_response),
+            Err(payload) => {
+                hub.capture_event(panic_event(&*payload));
+                transaction.set_status(SpanStatus::InternalError);
+                transaction.finish();
+                std::panic::resume_unwind(payload);
+            }
+        };
+
+        let (status, matched_path, message) = match &res {
+            Ok(resp) => (resp.status(), resp.data::<MatchedPath>(), None),
+            Err(err) => (
+                err.status(),
+                err.data::<MatchedPath>(),
+                Some(err.to_string()),
+            ),
+        };
+        if let Some(matched_path) = matched_path {
+            let route = matched_path.as_str().to_string();
+            hub.configure_scope(|scope| scope.set_transaction(Some(&route)));
+        }
+        if self.capture_server_errors && status.is_server_error() {
+            let message = message.unwrap_or_else(|| status.to_string());
+            hub.capture_message(&message, Level::Error);
+        }
+
+        transaction.set_status(span_status(status));
+        transaction.finish();
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+    use sentry_core::{protocol::Envelope, ClientOptions, Transport};
+
+    use super::*;
+    use crate::{
+        endpoint::make_sync, middleware::CatchPanic, test::TestClient, EndpointExt, Error, Route,
+    };
+
+    #[derive(Default)]
+    struct TestTransport(Mutex<Vec<Event<'static>>>);
+
+    impl Transport for TestTransport {
+        fn send_envelope(&self, envelope: Envelope) {
+            if let Some(event) = envelope.event() {
+                self.0.lock().push(event.clone());
+            }
+        }
+    }
+
+    fn init_hub(send_default_pii: bool) -> (Arc<Hub>, Arc<TestTransport>) {
+        let transport = Arc::new(TestTransport::default());
+        let client = sentry_core::Client::with_options(ClientOptions {
+            dsn: Some("https://public@sentry.invalid/1".parse().unwrap()),
+            transport: Some(Arc::new(transport.clone())),
+            send_default_pii,
+            ..Default::default()
+        });
+        let hub = Arc::new(Hub::new(Some(Arc::new(client)), Default::default()));
+        (hub, transport)
+    }
+
+    #[tokio::test]
+    async fn capture_server_errors() {
+        let (hub, transport) = init_hub(false);
+        let cli = TestClient::new(
+            Route::new()
+                .at("/ok", make_sync(|_| "hello"))
+                .at(
+                    "/error/:id",
+                    make_sync(|_| ()).before(|_| async {
+                        Err(Error::from_string(
+                            "database is down",
+                            StatusCode::SERVICE_UNAVAILABLE,
+                        ))
+                    }),
+                )
+                .at("/status", make_sync(|_| StatusCode::BAD_GATEWAY))
+                .with(Sentry::new().user(|req| {
+                    Some(User {
+                        id: req.header("x-user-id").map(ToString::to_string),
+                        ..Default::default()
+                    })
+                })),
+        );
+
+        async {
+            cli.get("/ok").send().await.assert_status_is_ok();
+            cli.get("/error/1?a=1")
+                .header(header::AUTHORIZATION, "Bearer abc")
+                .header("x-user-id", "1")
+                .send()
+                .await
+                .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+            cli.get("/status")
+                .send()
+                .await
+                .assert_status(StatusCode::BAD_GATEWAY);
+        }
+        .bind_hub(hub)
+        .await;
+
+        let events = std::mem::take(&mut *transport.0.lock());
+        assert_eq!(events.len(), 2);
+
+        let event = &events[0];
+        assert_eq!(event.message.as_deref(), Some("database is down"));
+        assert_eq!(event.level, Level::Error);
+        assert_eq!(event.transaction.as_deref(), Some("/error/:id"));
+        assert_eq!(
+            event.user.as_ref().and_then(|user| user.id.as_deref()),
+            Some("1")
+        );
+        let req = event.request.as_ref().unwrap();
+        assert_eq!(
+            req.url.as_ref().map(ToString::to_string).as_deref(),
+            Some("http://localhost/error/1")
+        );
+        assert_eq!(req.method.as_deref(), Some("GET"));
+        assert_eq!(req.query_string.as_deref(), Some("a=1"));
+        assert_eq!(req.headers.get("x-user-id").map(String::as_str), Some("1"));
+        assert!(!req.headers.contains_key("authorization"));
+        assert!(event.contexts.contains_key("trace"));
+
+        assert_eq!(events[1].message.as_deref(), Some("502 Bad Gateway"));
+    }
+
+    #[tokio::test]
+    async fn capture_panics() {
+        let (hub, transport) = init_hub(true);
+        let ep = make_sync(|_| -> () { panic!("boom") })
+            .with(Sentry::new())
+            .with(CatchPanic::new());
+
+        let resp = async {
+            TestClient::new(ep)
+                .get("/")
+                .header(header::COOKIE, "a=1")
+                .send()
+                .await
+        }
+        .bind_hub(hub)
+        .await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let events = std::mem::take(&mut *transport.0.lock());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, Level::Fatal);
+        assert_eq!(events[0].exception[0].value.as_deref(), Some("boom"));
+        let req = events[0].request.as_ref().unwrap();
+        assert_eq!(req.cookies.as_deref(), Some("a=1"));
+    }
+}
@@ -0,0 +1,307 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use crate::{
+    http::{header, HeaderMap, HeaderName, Method, StatusCode, Version},
+    Endpoint, Error, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// A buffered response which is shared with the waiting requests.
+struct SharedResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+    is_error: bool,
+}
+
+impl SharedResponse {
+    fn to_response(&self) -> Response {
+        let mut resp = Response::builder()
+            .status(self.status)
+            .version(self.version)
+            .body(self.body.clone());
+        *resp.headers_mut() = self.headers.clone();
+        resp
+    }
+}
+
+type InFlight = Mutex<HashMap<String, broadcast::Sender<Arc<SharedResponse>>>>;
+
+/// Removes the key from the in-flight requests when the leading request
+/// completes or is cancelled, the waiting requests are woken up by dropping
+/// or sending to the channel.
+struct InFlightGuard<'a> {
+    in_flight: &'a InFlight,
+    key: &'a str,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.lock().remove(self.key);
+    }
+}
+
+/// Middleware for coalescing the concurrent identical `GET` and `HEAD`
+/// requests.
+///
+/// The first request with a key is sent to the inner endpoint, the requests
+/// with the same key arriving before it completes wait for it, and receive a
+/// copy of its response, so the handler is executed once. If the first
+/// request is cancelled, the waiting requests are sent to the inner endpoint.
+///
+/// The key consists of the method, the URI and the values of the request
+/// headers specified by [`SingleFlight::vary`], which should include all
+/// headers affecting the response, so that the responses are never shared
+/// between the different users.
+///
+/// NOTE: The bodies of the coalesced responses are read into memory, so do
+/// not use it for the streaming responses.
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, middleware::SingleFlight, EndpointExt, Route};
+///
+/// #[handler]
+/// async fn report() -> String {
+///     // an expensive query
+///     "report".to_string()
+/// }
+///
+/// let app = Route::new()
+///     .at("/report", get(report))
+///     .with(SingleFlight::new());
+/// ```
+pub struct SingleFlight {
+    vary: Vec<HeaderName>,
+}
+
+impl Default for SingleFlight {
+    fn default() -> Self {
+        Self {
+            vary: vec![
+                header::ACCEPT,
+                header::ACCEPT_ENCODING,
+                header::ACCEPT_LANGUAGE,
+                header::AUTHORIZATION,
+                header::COOKIE,
+            ],
+        }
+    }
+}
+
+impl SingleFlight {
+    /// Create `SingleFlight` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the request headers whose values are a part of the key.
+    ///
+    /// Default is `[Accept, Accept-Encoding, Accept-Language, Authorization,
+    /// Cookie]`.
+    #[must_use]
+    pub fn vary(self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        Self {
+            vary: headers.into_iter().collect(),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SingleFlight {
+    type Output = SingleFlightEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SingleFlightEndpoint {
+            inner: ep,
+            vary: self.vary.clone(),
+            in_flight: Default::default(),
+        }
+    }
+}
+
+/// Endpoint for SingleFlight middleware.
+pub struct SingleFlightEndpoint<E> {
+    inner: E,
+    vary: Vec<HeaderName>,
+    in_flight: InFlight,
+}
+
+impl<E> SingleFlightEndpoint<E> {
+    fn key(&self, req: &Request) -> String {
+        let mut key = format!("{} {}", req.method(), req.original_uri());
+        for name in &self.vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            for value in req.headers().get_all(name) {
+                key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+                key.push(',');
+            }
+        }
+        key
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for SingleFlightEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let key = self.key(&req);
+        let tx = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get(&key) {
+                Some(tx) => Err(tx.subscribe()),
+                None => {
+                    let (tx, _) = broadcast::channel(1);
+                    in_flight.insert(key.clone(), tx.clone());
+                    Ok(tx)
+                }
+            }
+        };
+
+        let tx = match tx {
+            Ok(tx) => tx,
+            Err(mut rx) => {
+                return match rx.recv().await {
+                    Ok(shared) if shared.is_error => {
+                        Err(Error::from_response(shared.to_response()))
+                    }
+                    Ok(shared) => Ok(shared.to_response()),
+                    // the leading request is cancelled
+                    Err(_) => self.inner.call(req).await.map(IntoResponse::into_response),
+                };
+            }
+        };
+
+        let guard = InFlightGuard {
+            in_flight: &self.in_flight,
+            key: &key,
+        };
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        let (res, resp) = match res {
+            Ok(resp) => {
+                let (parts, body) = resp.into_parts();
+                let body = body.into_bytes().await?;
+                let shared = SharedResponse {
+                    status: parts.status,
+                    version: parts.version,
+                    headers: parts.headers.clone(),
+                    body: body.clone(),
+                    is_error: false,
+                };
+                (Ok(Response::from_parts(parts, body.into())), shared)
+            }
+            Err(err) => {
+                let status = err.status();
+                let (parts, body) = err.into_response().into_parts();
+                let body = body.into_bytes().await.unwrap_or_default();
+                let shared = SharedResponse {
+                    status,
+                    version: parts.version,
+                    headers: parts.headers,
+                    body,
+                    is_error: true,
+                };
+                (Err(Error::from_response(shared.to_response())), shared)
+            }
+        };
+
+        drop(guard);
+        let _ = tx.send(Arc::new(resp));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use futures_util::future::join_all;
+
+    use super::*;
+    use crate::{handler, test::TestClient, web::Data, EndpointExt};
+
+    #[handler(internal)]
+    async fn index(req: &Request, Data(count): Data<&Arc<AtomicUsize>>) -> Result<String> {
+        let n = count.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        if req.uri().path() == "/error" {
+            return Err(Error::from_string("error", StatusCode::BAD_GATEWAY));
+        }
+        Ok(format!("{} {}", req.uri(), n))
+    }
+
+    #[tokio::test]
+    async fn single_flight() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let ep = index.with(SingleFlight::new()).data(count.clone());
+        let cli = TestClient::new(ep);
+
+        let resps = join_all((0..5).map(|_| cli.get("/a").send())).await;
+        for resp in resps {
+            resp.assert_status_is_ok();
+            resp.assert_text("/a 0").await;
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // the different keys are not coalesced
+        let resps = join_all([
+            cli.get("/b").send(),
+            cli.get("/b")
+                .header(header::AUTHORIZATION, "Bearer a")
+                .send(),
+            cli.post("/b").send(),
+            cli.post("/b").send(),
+        ])
+        .await;
+        for resp in resps {
+            resp.assert_status_is_ok();
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+
+        // the completed requests are not shared
+        cli.get("/a").send().await.assert_text("/a 5").await;
+
+        let resps = join_all((0..3).map(|_| cli.get("/error").send())).await;
+        for resp in resps {
+            resp.assert_status(StatusCode::BAD_GATEWAY);
+            resp.assert_text("error").await;
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 7);
+    }
+
+    #[tokio::test]
+    async fn leader_cancelled() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let ep = Arc::new(index.with(SingleFlight::new()).data(count.clone()));
+
+        let leader = tokio::spawn({
+            let ep = ep.clone();
+            async move { ep.call(Request::builder().uri_str("/a").finish()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let follower = tokio::spawn({
+            let ep = ep.clone();
+            async move { ep.call(Request::builder().uri_str("/a").finish()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+
+        let resp = follower.await.unwrap().unwrap();
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "/a 1");
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
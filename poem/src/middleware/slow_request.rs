@@ -0,0 +1,192 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    http::{Method, StatusCode, Uri},
+    web::MatchedPath,
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+};
+
+/// The event emitted by [`SlowRequestDetector`] when a request exceeds the
+/// threshold.
+#[derive(Debug)]
+pub struct SlowRequestEvent {
+    /// The method of the request.
+    pub method: Method,
+
+    /// The URI of the request.
+    pub uri: Uri,
+
+    /// The path pattern of the matched route, see [`MatchedPath`].
+    pub route_pattern: Option<String>,
+
+    /// The status of the response.
+    pub status: StatusCode,
+
+    /// The time taken to produce the response.
+    pub duration: Duration,
+}
+
+type SlowRequestHandler = Arc<dyn Fn(&SlowRequestEvent) + Send + Sync>;
+
+fn log_slow_request(event: &SlowRequestEvent) {
+    tracing::warn!(
+        method = %event.method,
+        uri = %event.uri,
+        route = ?event.route_pattern,
+        status = %event.status,
+        duration = ?event.duration,
+        "slow request"
+    );
+}
+
+/// Middleware for detecting the requests which take longer than a threshold
+/// to produce the responses.
+///
+/// The slow requests are logged with [`tracing`](https://crates.io/crates/tracing)
+/// at the `WARN` level by default, use
+/// [`SlowRequestDetector::on_slow_request`] to emit the events elsewhere,
+/// such as to a metrics system.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{handler, middleware::SlowRequestDetector, EndpointExt};
+///
+/// #[handler]
+/// async fn index() -> &'static str {
+///     tokio::time::sleep(Duration::from_secs(2)).await;
+///     "hello"
+/// }
+///
+/// let app = index.with(
+///     SlowRequestDetector::new(Duration::from_secs(1)).on_slow_request(|event| {
+///         eprintln!("slow request: {} took {:?}", event.uri, event.duration);
+///     }),
+/// );
+/// ```
+pub struct SlowRequestDetector {
+    threshold: Duration,
+    handler: SlowRequestHandler,
+}
+
+impl SlowRequestDetector {
+    /// Create `SlowRequestDetector` middleware which detects the requests
+    /// taking longer than `threshold`.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            handler: Arc::new(log_slow_request),
+        }
+    }
+
+    /// Sets a function to be called for each slow request, instead of
+    /// logging it.
+    #[must_use]
+    pub fn on_slow_request<F>(self, f: F) -> Self
+    where
+        F: Fn(&SlowRequestEvent) + Send + Sync + 'static,
+    {
+        Self {
+            handler: Arc::new(f),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SlowRequestDetector {
+    type Output = SlowRequestDetectorEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SlowRequestDetectorEndpoint {
+            inner: ep,
+            threshold: self.threshold,
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+/// Endpoint for SlowRequestDetector middleware.
+pub struct SlowRequestDetectorEndpoint<E> {
+    inner: E,
+    threshold: Duration,
+    handler: SlowRequestHandler,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for SlowRequestDetectorEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let method = req.method().clone();
+        let uri = req.original_uri().clone();
+        let now = Instant::now();
+
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        let duration = now.elapsed();
+
+        if duration >= self.threshold {
+            let (status, matched_path) = match &res {
+                Ok(resp) => (resp.status(), resp.data::<MatchedPath>()),
+                Err(err) => (err.status(), err.data::<MatchedPath>()),
+            };
+            (self.handler)(&SlowRequestEvent {
+                method,
+                uri,
+                route_pattern: matched_path.map(|path| path.as_str().to_string()),
+                status,
+                duration,
+            });
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::{handler, test::TestClient, web::Path, EndpointExt, Route};
+
+    #[handler(internal)]
+    async fn index(Path(ms): Path<u64>) -> &'static str {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+        "hello"
+    }
+
+    #[tokio::test]
+    async fn slow_request() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let cli = TestClient::new(Route::new().at("/sleep/:ms", index).with(
+            SlowRequestDetector::new(Duration::from_millis(50)).on_slow_request({
+                let events = events.clone();
+                move |event| {
+                    events.lock().push((
+                        event.uri.to_string(),
+                        event.route_pattern.clone(),
+                        event.status,
+                        event.duration,
+                    ))
+                }
+            }),
+        ));
+
+        cli.get("/sleep/0").send().await.assert_status_is_ok();
+        assert!(events.lock().is_empty());
+
+        cli.get("/sleep/100").send().await.assert_status_is_ok();
+        let events = std::mem::take(&mut *events.lock());
+        assert_eq!(events.len(), 1);
+        let (uri, route_pattern, status, duration) = &events[0];
+        assert_eq!(uri, "/sleep/100");
+        assert_eq!(route_pattern.as_deref(), Some("/sleep/:ms"));
+        assert_eq!(*status, StatusCode::OK);
+        assert!(*duration >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use crate::{
+    error::TimeoutError,
+    middleware::{DisableRequestTimeout, DisableRequestTimeoutEndpoint, RequestTimeoutFlag},
+    Endpoint, EndpointExt, IntoEndpoint, Middleware, Request, Result,
+};
+
+/// Middleware for cancelling the endpoint if it does not complete within the
+/// specified duration.
+///
+/// The timeout can be changed for the specific routes with [`TimeoutExt`].
+///
+/// # Errors
+///
+/// - [`TimeoutError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     middleware::{Timeout, TimeoutExt},
+///     test::TestClient,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// async fn slow() -> &'static str {
+///     tokio::time::sleep(Duration::from_millis(200)).await;
+///     "done"
+/// }
+///
+/// let app = Route::new()
+///     .at("/slow", get(slow))
+///     .at("/report", get(slow).timeout(Duration::from_secs(10)))
+///     .with(Timeout::new(Duration::from_millis(50)));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/slow")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::GATEWAY_TIMEOUT);
+/// cli.get("/report").send().await.assert_text("done").await;
+/// # });
+/// ```
+pub struct Timeout {
+    timeout: Duration,
+    replace_outer: bool,
+}
+
+impl Timeout {
+    /// Create `Timeout` middleware.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            replace_outer: false,
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Timeout {
+    type Output = TimeoutEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TimeoutEndpoint {
+            inner: ep,
+            timeout: self.timeout,
+            replace_outer: self.replace_outer,
+        }
+    }
+}
+
+/// Endpoint for Timeout middleware.
+pub struct TimeoutEndpoint<E> {
+    inner: E,
+    timeout: Duration,
+    replace_outer: bool,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for TimeoutEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let outer = req.extensions().get::<RequestTimeoutFlag>().cloned();
+        let flag = match outer {
+            Some(outer) if self.replace_outer => {
+                outer.disable();
+                RequestTimeoutFlag::default()
+            }
+            Some(outer) => outer.child(),
+            None => RequestTimeoutFlag::default(),
+        };
+        req.extensions_mut().insert(flag.clone());
+
+        let resp = self.inner.call(req);
+        tokio::pin!(resp);
+        tokio::select! {
+            resp = &mut resp => resp,
+            _ = tokio::time::sleep(self.timeout) => {
+                if flag.is_disabled() {
+                    resp.await
+                } else {
+                    Err(TimeoutError.into())
+                }
+            }
+        }
+    }
+}
+
+/// Extension trait for changing the timeouts of the specific routes.
+pub trait TimeoutExt: IntoEndpoint {
+    /// Replaces the timeouts of the outer [`Timeout`] middlewares and
+    /// [`Server::request_timeout`](crate::Server::request_timeout) with the
+    /// specified duration, which starts when this endpoint is called. It can
+    /// be used to lengthen the timeout of a slow route.
+    fn timeout(self, timeout: Duration) -> TimeoutEndpoint<Self::Endpoint>
+    where
+        Self: Sized,
+    {
+        self.with(Timeout {
+            timeout,
+            replace_outer: true,
+        })
+    }
+
+    /// Disables the timeouts of the outer [`Timeout`] middlewares and
+    /// [`Server::request_timeout`](crate::Server::request_timeout).
+    fn disable_timeout(self) -> DisableRequestTimeoutEndpoint<Self::Endpoint>
+    where
+        Self: Sized,
+    {
+        self.with(DisableRequestTimeout)
+    }
+}
+
+impl<T: IntoEndpoint> TimeoutExt for T {}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, Route};
+
+    #[handler(internal)]
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        "done"
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let cli = TestClient::new(
+            Route::new()
+                .at("/slow", slow)
+                .at("/longer", slow.timeout(Duration::from_secs(10)))
+                .at("/shorter", slow.timeout(Duration::from_millis(10)))
+                .at("/disabled", slow.disable_timeout())
+                .with(Timeout::new(Duration::from_millis(50))),
+        );
+
+        cli.get("/slow")
+            .send()
+            .await
+            .assert_status(StatusCode::GATEWAY_TIMEOUT);
+        cli.get("/longer").send().await.assert_text("done").await;
+        cli.get("/shorter")
+            .send()
+            .await
+            .assert_status(StatusCode::GATEWAY_TIMEOUT);
+        cli.get("/disabled").send().await.assert_text("done").await;
+    }
+
+    #[tokio::test]
+    async fn nested_timeouts() {
+        let flag = RequestTimeoutFlag::default();
+        let cli = TestClient::new(
+            slow.disable_timeout()
+                .with(Timeout::new(Duration::from_millis(10)))
+                .with(Timeout::new(Duration::from_millis(20))),
+        );
+
+        cli.get("/")
+            .data(flag.clone())
+            .send()
+            .await
+            .assert_text("done")
+            .await;
+        assert!(flag.is_disabled());
+    }
+}
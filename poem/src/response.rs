@@ -232,6 +232,11 @@ impl Response {
         std::mem::take(&mut self.body)
     }
 
+    /// Returns the size of the body if it is known.
+    pub(crate) fn body_size(&self) -> Option<u64> {
+        self.body.exact_size()
+    }
+
     /// Consume this response and return its inner body.
     #[inline]
     pub fn into_body(self) -> Body {
@@ -0,0 +1,138 @@
+use std::cmp::Ordering;
+
+use regex::Regex;
+
+#[derive(Debug, Eq, PartialEq)]
+enum Segment<'a> {
+    Static(&'a str),
+    Regex(&'a str),
+    Param,
+    CatchAll,
+}
+
+impl Segment<'_> {
+    /// The lower rank is matched first by the router.
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Static(_) => 0,
+            Segment::Regex(_) => 1,
+            Segment::Param => 2,
+            Segment::CatchAll => 3,
+        }
+    }
+}
+
+fn parse_segment(segment: &str) -> Segment<'_> {
+    if segment.starts_with('*') {
+        return Segment::CatchAll;
+    }
+    if !segment.contains([':', '<', '*']) {
+        return Segment::Static(segment);
+    }
+
+    let re = match segment.strip_prefix(':') {
+        Some(param) => match param.find('<') {
+            Some(pos) => &param[pos..],
+            None => return Segment::Param,
+        },
+        None => segment,
+    };
+    match re
+        .strip_prefix('<')
+        .and_then(|re| re.strip_suffix('>'))
+        .filter(|re| !re.contains(['<', '>']) && !re.starts_with('@'))
+    {
+        Some(re) => Segment::Regex(re),
+        // the segment matchers and the segments mixed with the static text are
+        // treated as the parameters
+        None => Segment::Param,
+    }
+}
+
+fn parse_path(path: &str) -> Vec<Segment<'_>> {
+    path.split('/').map(parse_segment).collect()
+}
+
+fn regex_matches(re: &str, value: &str) -> bool {
+    Regex::new(&format!("^(?:{})$", re))
+        .map(|re| re.is_match(value))
+        .unwrap_or(true)
+}
+
+fn segments_overlap(a: &Segment<'_>, b: &Segment<'_>) -> bool {
+    match (a, b) {
+        (Segment::Static(a), Segment::Static(b)) => a == b,
+        (Segment::Static(value), Segment::Regex(re))
+        | (Segment::Regex(re), Segment::Static(value)) => regex_matches(re, value),
+        // the different regexes are assumed to be disjoint
+        (Segment::Regex(a), Segment::Regex(b)) => a == b,
+        _ => true,
+    }
+}
+
+/// Returns `true` if there are request paths which match both paths, the
+/// regexes in the paths are assumed to be disjoint unless they are the same.
+pub(crate) fn is_conflict(a: &str, b: &str) -> bool {
+    let a = parse_path(a);
+    let b = parse_path(b);
+
+    for i in 0..a.len().max(b.len()) {
+        match (a.get(i), b.get(i)) {
+            (Some(Segment::CatchAll), Some(_)) | (Some(_), Some(Segment::CatchAll)) => return true,
+            (Some(a), Some(b)) if segments_overlap(a, b) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Returns the ordering of the priorities of the conflicting paths, `Less`
+/// means the path `a` is matched first.
+pub(crate) fn priority(a: &str, b: &str) -> Ordering {
+    parse_path(a)
+        .iter()
+        .zip(parse_path(b).iter())
+        .map(|(a, b)| a.rank().cmp(&b.rank()))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_segment() {
+        assert_eq!(parse_segment("users"), Segment::Static("users"));
+        assert_eq!(parse_segment(":id"), Segment::Param);
+        assert_eq!(parse_segment(":id<\\d+>"), Segment::Regex("\\d+"));
+        assert_eq!(parse_segment("<\\d+>"), Segment::Regex("\\d+"));
+        assert_eq!(parse_segment(":id<@slug>"), Segment::Param);
+        assert_eq!(parse_segment("a-:id"), Segment::Param);
+        assert_eq!(parse_segment("*path"), Segment::CatchAll);
+    }
+
+    #[test]
+    fn test_is_conflict() {
+        assert!(is_conflict("/users/:id", "/users/new"));
+        assert!(is_conflict("/users/:id", "/users/:name"));
+        assert!(is_conflict("/users/*path", "/users/a/b"));
+        assert!(is_conflict("/users/:id<\\d+>", "/users/123"));
+        assert!(is_conflict("/:a/b", "/a/:b"));
+
+        assert!(!is_conflict("/users/:id", "/users/:id/posts"));
+        assert!(!is_conflict("/users/:id<\\d+>", "/users/new"));
+        assert!(!is_conflict("/users/:id<\\d+>", "/users/:name<[a-z]+>"));
+        assert!(!is_conflict("/:id1/a", "/:id2/b"));
+        assert!(!is_conflict("/users/*path", "/users"));
+    }
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(priority("/users/new", "/users/:id"), Ordering::Less);
+        assert_eq!(priority("/users/*path", "/users/:id"), Ordering::Greater);
+        assert_eq!(priority("/users/:id<\\d+>", "/users/:id"), Ordering::Less);
+        assert_eq!(priority("/:a/b", "/a/:b"), Ordering::Greater);
+        assert_eq!(priority("/users/:id", "/users/:name"), Ordering::Equal);
+    }
+}
@@ -1,2 +1,3 @@
+pub(crate) mod conflict;
 pub(crate) mod radix_tree;
 pub(crate) mod trie;
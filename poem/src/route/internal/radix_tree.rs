@@ -1,9 +1,17 @@
-use std::fmt::{self, Debug, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
 
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use regex::bytes::Regex;
 use smallvec::SmallVec;
 
-use crate::error::RouteError;
+use crate::{
+    error::{RouteError, UrlForError},
+    route::SegmentMatcher,
+};
 
 fn longest_common_prefix(a: &[u8], b: &[u8]) -> usize {
     a.iter().zip(b).take_while(|(a, b)| **a == **b).count()
@@ -120,6 +128,65 @@ fn parse_path_segments(path: &[u8]) -> Result<Vec<RawSegment<'_>>, ()> {
     Ok(segments)
 }
 
+/// The characters to be percent-encoded in a path parameter.
+const PARAM_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Builds the path of the route `name` by replacing the parameters in the
+/// `pattern` with the values in `params`.
+pub(crate) fn build_path(
+    name: &str,
+    pattern: &str,
+    params: &[(&str, &str)],
+) -> Result<String, UrlForError> {
+    let segments = parse_path_segments(pattern.as_bytes())
+        .map_err(|_| UrlForError::UnnamedParam(name.to_string()))?;
+    let get_param = |param: &[u8]| {
+        let param = String::from_utf8_lossy(param);
+        params
+            .iter()
+            .find(|(key, _)| *key == param)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| UrlForError::MissingParam {
+                name: name.to_string(),
+                param: param.into_owned(),
+            })
+    };
+
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            RawSegment::Static(s) => path.push_str(&String::from_utf8_lossy(s)),
+            RawSegment::Param(param) | RawSegment::Regex(Some(param), _) => {
+                path.extend(utf8_percent_encode(get_param(param)?, PARAM_ENCODE_SET));
+            }
+            RawSegment::CatchAll(Some(param)) => {
+                // the slashes are kept in the rest of the path
+                for (i, s) in get_param(param)?.split('/').enumerate() {
+                    if i > 0 {
+                        path.push('/');
+                    }
+                    path.extend(utf8_percent_encode(s, PARAM_ENCODE_SET));
+                }
+            }
+            RawSegment::CatchAll(None) | RawSegment::Regex(None, _) => {
+                return Err(UrlForError::UnnamedParam(name.to_string()))
+            }
+        }
+    }
+    Ok(path)
+}
+
 #[derive(Debug, Eq, PartialEq)]
 enum NodeType {
     Root,
@@ -129,9 +196,14 @@ enum NodeType {
     Regex,
 }
 
+enum SegmentPattern {
+    Regex(Regex),
+    Matcher(Arc<dyn SegmentMatcher>),
+}
+
 struct PathRegex {
     re_str: String,
-    re: Regex,
+    pattern: SegmentPattern,
 }
 
 impl PathRegex {
@@ -139,9 +211,31 @@ impl PathRegex {
         let re_str = std::str::from_utf8(re_bytes).ok()?;
         Some(PathRegex {
             re_str: re_str.to_string(),
-            re: Regex::new(re_str).ok()?,
+            // the value must start at the beginning of the path
+            pattern: SegmentPattern::Regex(Regex::new(&format!("^(?:{})", re_str)).ok()?),
         })
     }
+
+    fn with_matcher(name: &[u8], matcher: Arc<dyn SegmentMatcher>) -> Self {
+        PathRegex {
+            re_str: String::from_utf8_lossy(name).into_owned(),
+            pattern: SegmentPattern::Matcher(matcher),
+        }
+    }
+
+    /// Returns the length of the value matched at the beginning of the path.
+    fn match_len(&self, path: &[u8]) -> Option<usize> {
+        match &self.pattern {
+            SegmentPattern::Regex(re) => re.find(path).map(|m| m.end()),
+            SegmentPattern::Matcher(matcher) => {
+                let segment = &path[..find_slash(path).unwrap_or(path.len())];
+                let value = percent_encoding::percent_decode(segment)
+                    .decode_utf8()
+                    .ok()?;
+                matcher.matches(&value).then(|| segment.len())
+            }
+        }
+    }
 }
 
 impl Debug for PathRegex {
@@ -210,7 +304,7 @@ impl<T> Node<T> {
                         re: None,
                         param_children: ::std::mem::take(&mut child.param_children),
                         catch_all_child: child.catch_all_child.take(),
-                        regex_children: vec![],
+                        regex_children: std::mem::take(&mut child.regex_children),
                         data: child.data.take(),
                     };
 
@@ -343,10 +437,14 @@ impl<T> Node<T> {
         child.insert_child(segments, data)
     }
 
+    /// Matches the path, the static segments are compared ASCII
+    /// case-insensitively if `canonical` is `Some`, and the matched static
+    /// segments are written into it.
     fn matches<'a: 'b, 'b>(
         &'a self,
         path: &'b [u8],
         params: &mut SmallVec<[(&'b [u8], &'b [u8]); 8]>,
+        mut canonical: Option<&mut [u8]>,
     ) -> Option<&'a T> {
         if path.is_empty() {
             return if let Some(catch_all_child) = &self.catch_all_child {
@@ -361,11 +459,32 @@ impl<T> Node<T> {
 
         let num_params = params.len();
 
-        if let Some(pos) = self.find_static_child(path[0]) {
-            let child = &self.children[pos];
-            if let Some(tail_path) = path.strip_prefix(child.name.as_slice()) {
-                if let Some(data) = child.matches(tail_path, params) {
-                    return Some(data);
+        match canonical.as_deref_mut() {
+            None => {
+                if let Some(pos) = self.find_static_child(path[0]) {
+                    let child = &self.children[pos];
+                    if let Some(tail_path) = path.strip_prefix(child.name.as_slice()) {
+                        if let Some(data) = child.matches(tail_path, params, None) {
+                            return Some(data);
+                        }
+                    }
+                }
+            }
+            Some(canonical) => {
+                let pos = canonical.len() - path.len();
+                for child in self
+                    .children
+                    .iter()
+                    .filter(|child| child.name[0].eq_ignore_ascii_case(&path[0]))
+                {
+                    let len = child.name.len();
+                    if path.len() < len || !path[..len].eq_ignore_ascii_case(&child.name) {
+                        continue;
+                    }
+                    if let Some(data) = child.matches(&path[len..], params, Some(&mut *canonical)) {
+                        canonical[pos..pos + len].copy_from_slice(&child.name);
+                        return Some(data);
+                    }
                 }
             }
         }
@@ -373,12 +492,14 @@ impl<T> Node<T> {
         for regex_children in &self.regex_children {
             params.truncate(num_params);
 
-            if let Some(captures) = regex_children.re.as_ref().unwrap().re.captures(path) {
-                let value = &path[..captures[0].len()];
+            if let Some(len) = regex_children.re.as_ref().unwrap().match_len(path) {
+                let value = &path[..len];
                 if !regex_children.name.is_empty() {
                     params.push((&regex_children.name, value));
                 }
-                if let Some(data) = regex_children.matches(&path[value.len()..], params) {
+                if let Some(data) =
+                    regex_children.matches(&path[value.len()..], params, canonical.as_deref_mut())
+                {
                     return Some(data);
                 }
             }
@@ -392,7 +513,9 @@ impl<T> Node<T> {
                 None => path,
             };
             params.push((&param_children.name, value));
-            if let Some(data) = param_children.matches(&path[value.len()..], params) {
+            if let Some(data) =
+                param_children.matches(&path[value.len()..], params, canonical.as_deref_mut())
+            {
                 return Some(data);
             }
         }
@@ -439,7 +562,19 @@ impl<T> Default for RadixTree<T> {
 }
 
 impl<T> RadixTree<T> {
+    #[cfg(test)]
     pub(crate) fn add(&mut self, path: &str, data: T) -> Result<(), RouteError> {
+        self.add_with_matchers(path, data, &HashMap::new())
+    }
+
+    /// Adds a path, the segment matchers are referenced by `<@name>` in the
+    /// path.
+    pub(crate) fn add_with_matchers(
+        &mut self,
+        path: &str,
+        data: T,
+        matchers: &HashMap<String, Arc<dyn SegmentMatcher>>,
+    ) -> Result<(), RouteError> {
         let raw_segments = match parse_path_segments(path.as_bytes()) {
             Ok(raw_segments) => raw_segments,
             Err(_) => return Err(RouteError::InvalidPath(path.to_string())),
@@ -451,6 +586,19 @@ impl<T> RadixTree<T> {
                 RawSegment::Static(value) => Segment::Static(value),
                 RawSegment::Param(name) => Segment::Param(name),
                 RawSegment::CatchAll(name) => Segment::CatchAll(name),
+                RawSegment::Regex(name, re_bytes) if re_bytes.starts_with(b"@") => {
+                    let matcher_name = String::from_utf8_lossy(&re_bytes[1..]);
+                    match matchers.get(&*matcher_name) {
+                        Some(matcher) => {
+                            Segment::Regex(name, PathRegex::with_matcher(re_bytes, matcher.clone()))
+                        }
+                        None => {
+                            return Err(RouteError::UnknownSegmentMatcher(
+                                matcher_name.into_owned(),
+                            ))
+                        }
+                    }
+                }
                 RawSegment::Regex(name, re_bytes) => {
                     if let Some(re) = PathRegex::new(re_bytes) {
                         Segment::Regex(name, re)
@@ -474,13 +622,26 @@ impl<T> RadixTree<T> {
     }
 
     pub(crate) fn matches(&self, path: &str) -> Option<Matches<T>> {
+        self.matches_inner(path, None)
+    }
+
+    /// Matches the path ASCII case-insensitively, returns the matches and the
+    /// path with the static segments in the registered case.
+    pub(crate) fn matches_ignore_case(&self, path: &str) -> Option<(Matches<'_, T>, String)> {
+        let mut canonical = path.as_bytes().to_vec();
+        let matches = self.matches_inner(path, Some(&mut canonical))?;
+        let canonical = String::from_utf8(canonical).ok()?;
+        Some((matches, canonical))
+    }
+
+    fn matches_inner(&self, path: &str, canonical: Option<&mut [u8]>) -> Option<Matches<'_, T>> {
         if path.is_empty() {
             return None;
         }
 
         let mut params = SmallVec::default();
 
-        match self.root.matches(path.as_bytes(), &mut params) {
+        match self.root.matches(path.as_bytes(), &mut params, canonical) {
             Some(data) => {
                 let mut params2 = Vec::with_capacity(params.len());
                 for (name, value) in params {
@@ -1217,4 +1378,36 @@ mod tests {
         assert_eq!(matches.params[0].0, "id");
         assert_eq!(matches.params[0].1, "你好");
     }
+
+    #[test]
+    fn test_matches_ignore_case() {
+        let mut tree = RadixTree::default();
+        tree.add("/Users/:name/Posts", 1).unwrap();
+        tree.add("/users/:name/avatar", 2).unwrap();
+        tree.add("/files/*path", 3).unwrap();
+
+        assert!(tree.matches("/users/Alice/posts").is_none());
+
+        let (matches, canonical) = tree.matches_ignore_case("/users/Alice/posts").unwrap();
+        assert_eq!(matches.data, &1);
+        assert_eq!(
+            matches.params,
+            vec![("name".to_string(), "Alice".to_string())]
+        );
+        assert_eq!(canonical, "/Users/Alice/Posts");
+
+        let (matches, canonical) = tree.matches_ignore_case("/USERS/Bob/AVATAR").unwrap();
+        assert_eq!(matches.data, &2);
+        assert_eq!(canonical, "/users/Bob/avatar");
+
+        let (matches, canonical) = tree.matches_ignore_case("/Files/A/b").unwrap();
+        assert_eq!(matches.data, &3);
+        assert_eq!(
+            matches.params,
+            vec![("path".to_string(), "A/b".to_string())]
+        );
+        assert_eq!(canonical, "/files/A/b");
+
+        assert!(tree.matches_ignore_case("/users/Alice").is_none());
+    }
 }
@@ -4,11 +4,14 @@ mod internal;
 mod router;
 mod router_domain;
 mod router_method;
+mod router_predicate;
 mod router_scheme;
+mod router_version;
+mod segment_matcher;
 
-pub(crate) use internal::radix_tree::PathParams;
+pub(crate) use internal::radix_tree::{build_path, PathParams};
 #[allow(unreachable_pub)]
-pub use router::Route;
+pub use router::{CaseSensitivity, Route, RouteInfo, TrailingSlashPolicy};
 #[allow(unreachable_pub)]
 pub use router_domain::RouteDomain;
 #[allow(unreachable_pub)]
@@ -16,17 +19,49 @@ pub use router_method::{
     connect, delete, get, head, options, patch, post, put, trace, RouteMethod,
 };
 #[allow(unreachable_pub)]
+pub use router_predicate::RoutePredicate;
+#[allow(unreachable_pub)]
 pub use router_scheme::RouteScheme;
+#[allow(unreachable_pub)]
+pub use router_version::{VersionSource, VersionedRoute};
+#[allow(unreachable_pub)]
+pub use segment_matcher::SegmentMatcher;
 
-use crate::error::RouteError;
+use crate::{
+    error::RouteError,
+    http::{header, uri::Authority},
+    Request,
+};
 
 pub(crate) fn check_result<T>(res: Result<T, RouteError>) -> T {
     match res {
         Ok(value) => value,
         Err(RouteError::InvalidPath(path)) => panic!("invalid path: {}", path),
         Err(RouteError::Duplicate(path)) => panic!("duplicate path: {}", path),
+        Err(RouteError::DuplicateName(name)) => panic!("duplicate route name: {}", name),
+        Err(err @ RouteError::Conflict { .. }) => panic!("{}", err),
+        Err(RouteError::UnknownSegmentMatcher(name)) => {
+            panic!("unknown segment matcher: {}", name)
+        }
         Err(RouteError::InvalidRegex { path, regex }) => {
             panic!("invalid regex in path: {} `{}`", path, regex)
         }
     }
 }
+
+/// Returns the host of the request without the port.
+pub(crate) fn request_host(req: &Request) -> &str {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or_default();
+    match Authority::try_from(host)
+        .ok()
+        .and_then(|authority| authority.port_u16())
+        .and_then(|_| host.rfind(':'))
+    {
+        Some(idx) => &host[..idx],
+        None => host,
+    }
+}
@@ -1,13 +1,23 @@
-use std::{str::FromStr, sync::Arc};
+use std::{any::Any, cmp::Ordering, collections::HashMap, str::FromStr, sync::Arc};
 
 use regex::Regex;
 
 use crate::{
     endpoint::BoxEndpoint,
-    error::{NotFoundError, RouteError},
-    http::{uri::PathAndQuery, Uri},
-    route::{check_result, internal::radix_tree::RadixTree},
-    Endpoint, EndpointExt, IntoEndpoint, IntoResponse, Request, Response, Result,
+    error::{NotFoundError, RouteError, UrlForError},
+    http::{uri::PathAndQuery, Method, Uri},
+    middleware::make,
+    route::{
+        check_result,
+        internal::{
+            conflict::{is_conflict, priority},
+            radix_tree::{Matches, RadixTree},
+            trie::Trie,
+        },
+        request_host, RouteMethod, SegmentMatcher,
+    },
+    web::{MatchedPath, Redirect, UrlFor},
+    Endpoint, EndpointExt, IntoEndpoint, IntoResponse, Middleware, Request, Response, Result,
 };
 
 /// Routing object
@@ -76,6 +86,19 @@ use crate::{
 /// # });
 /// ```
 ///
+/// # Priority
+///
+/// When a request path matches multiple paths, the segments of the paths are
+/// compared from left to right, and the path is matched first in the following
+/// order of the first different segment:
+///
+/// 1. Static segment, such as `/users/new`
+/// 2. Regex, such as `/users/:id<\d+>`, in the order they are added
+/// 3. Parameter, such as `/users/:id`
+/// 4. Wildcard, such as `/users/*path`
+///
+/// Use [`Route::strict_conflicts`] to reject the paths that overlap.
+///
 /// # Nested
 ///
 /// ```
@@ -127,7 +150,86 @@ use crate::{
 /// ```
 #[derive(Default)]
 pub struct Route {
-    tree: RadixTree<BoxEndpoint<'static>>,
+    tree: RadixTree<RouteEntry>,
+    names: Arc<HashMap<String, String>>,
+    routes: Vec<RouteInfo>,
+    matchers: HashMap<String, Arc<dyn SegmentMatcher>>,
+    trailing_slash: TrailingSlashPolicy,
+    case_sensitivity: CaseSensitivity,
+    hosts: Option<Trie<BoxEndpoint<'static>>>,
+    fallback: Option<BoxEndpoint<'static>>,
+    strict_conflicts: bool,
+    paths: Vec<String>,
+}
+
+/// How a [`Route`] handles the requests whose paths only differ from the
+/// registered paths by a trailing slash, such as `/users/` for `/users`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TrailingSlashPolicy {
+    /// The paths must match exactly, otherwise [`NotFoundError`] is returned.
+    Strict,
+
+    /// Responds `301 Moved Permanently` that redirects to the registered
+    /// path.
+    RedirectToCanonical,
+
+    /// Calls the endpoint of the registered path as if the request path
+    /// matches it.
+    MergeSlash,
+}
+
+impl Default for TrailingSlashPolicy {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
+/// How a [`Route`] compares the static segments of the request paths with the
+/// registered paths.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CaseSensitivity {
+    /// The static segments must match exactly.
+    Sensitive,
+
+    /// The static segments are compared ASCII case-insensitively, the path
+    /// parameters keep the case of the request path.
+    Insensitive,
+
+    /// Same as [`CaseSensitivity::Insensitive`], but responds `301 Moved
+    /// Permanently` that redirects to the path in the registered case if the
+    /// case is different.
+    RedirectToCanonical,
+}
+
+impl Default for CaseSensitivity {
+    fn default() -> Self {
+        Self::Sensitive
+    }
+}
+
+struct RouteEntry {
+    ep: BoxEndpoint<'static>,
+    /// The path pattern of the route, see [`MatchedPath`].
+    pattern: String,
+    /// The prefix of the path patterns in the nested endpoint.
+    nest_prefix: Option<String>,
+}
+
+/// The prefix of the path patterns of the nested route.
+pub(crate) struct NestPrefix(pub(crate) String);
+
+/// The information of a route registered in a [`Route`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RouteInfo {
+    /// The HTTP method of the route, or `None` if the endpoint is not a
+    /// [`RouteMethod`] and handles all methods.
+    pub method: Option<Method>,
+
+    /// The path pattern of the route, such as `/users/:id`.
+    pub path: String,
+
+    /// The name of the route, see [`Route::at_named`].
+    pub name: Option<String>,
 }
 
 impl Route {
@@ -136,6 +238,130 @@ impl Route {
         Default::default()
     }
 
+    /// Registers a [`SegmentMatcher`] with a name, which can be referenced by
+    /// the paths added after it with `:name<@matcher>` or `<@matcher>`.
+    #[must_use]
+    pub fn segment_matcher(
+        mut self,
+        name: impl Into<String>,
+        matcher: impl SegmentMatcher,
+    ) -> Self {
+        self.matchers.insert(name.into(), Arc::new(matcher));
+        self
+    }
+
+    /// Sets the [`TrailingSlashPolicy`] of this route, the routes nested in it
+    /// have their own policies.
+    ///
+    /// Default is [`TrailingSlashPolicy::Strict`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{handler, http::StatusCode, test::TestClient, Route, TrailingSlashPolicy};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let app = Route::new()
+    ///     .at("/users", index)
+    ///     .nest(
+    ///         "/api",
+    ///         Route::new()
+    ///             .at("/users/", index)
+    ///             .trailing_slash(TrailingSlashPolicy::MergeSlash),
+    ///     )
+    ///     .trailing_slash(TrailingSlashPolicy::RedirectToCanonical);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    ///
+    /// let resp = cli.get("/users/").query("page", &1).send().await;
+    /// resp.assert_status(StatusCode::MOVED_PERMANENTLY);
+    /// resp.assert_header("location", "/users?page=1");
+    ///
+    /// cli.get("/api/users").send().await.assert_status_is_ok();
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn trailing_slash(self, policy: TrailingSlashPolicy) -> Self {
+        Self {
+            trailing_slash: policy,
+            ..self
+        }
+    }
+
+    /// Sets the [`CaseSensitivity`] of this route, it is useful when migrating
+    /// from the systems which never treat the case of the URLs as significant.
+    /// The routes nested in it have their own settings.
+    ///
+    /// Default is [`CaseSensitivity::Sensitive`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{handler, http::StatusCode, test::TestClient, web::Path, CaseSensitivity, Route};
+    ///
+    /// #[handler]
+    /// fn user(Path(name): Path<String>) -> String {
+    ///     name
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .at("/Users/:name", user)
+    ///     .case_sensitivity(CaseSensitivity::Insensitive);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/USERS/Alice")
+    ///     .send()
+    ///     .await
+    ///     .assert_text("Alice")
+    ///     .await;
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn case_sensitivity(self, case_sensitivity: CaseSensitivity) -> Self {
+        Self {
+            case_sensitivity,
+            ..self
+        }
+    }
+
+    /// Sets whether to reject the paths which overlap ambiguously with the
+    /// existing paths, such as `/users/:id` and `/users/new`. The paths added
+    /// after it panic, or return [`RouteError::Conflict`] for the `try_*`
+    /// methods.
+    ///
+    /// The different regexes in the same position are assumed not to overlap.
+    ///
+    /// Default is `false`, the overlapping paths are matched by priority.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{error::RouteError, handler, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let res = Route::new()
+    ///     .strict_conflicts(true)
+    ///     .at("/users/new", index)
+    ///     .try_at("/users/:id", index);
+    /// assert!(matches!(
+    ///     res,
+    ///     Err(RouteError::Conflict { matched_first, .. }) if matched_first == "/users/new"
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn strict_conflicts(self, enable: bool) -> Self {
+        Self {
+            strict_conflicts: enable,
+            ..self
+        }
+    }
+
     /// Add an [Endpoint] to the specified path.
     ///
     /// # Panics
@@ -151,16 +377,273 @@ impl Route {
     }
 
     /// Attempts to add an [Endpoint] to the specified path.
-    pub fn try_at<E>(mut self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
+    pub fn try_at<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.internal_at(&normalize_path(path.as_ref()), ep, None)
+    }
+
+    /// Add an [Endpoint] to the specified path with a name, the URL of the
+    /// route can be generated with the [`UrlFor`] extractor.
+    ///
+    /// # Panics
+    ///
+    /// Panic when there are duplicates in the routing table, or the name is
+    /// already used.
+    #[must_use]
+    pub fn at_named<E>(self, path: impl AsRef<str>, ep: E, name: impl Into<String>) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        check_result(self.try_at_named(path, ep, name))
+    }
+
+    /// Attempts to add an [Endpoint] to the specified path with a name.
+    pub fn try_at_named<E>(
+        self,
+        path: impl AsRef<str>,
+        ep: E,
+        name: impl Into<String>,
+    ) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.internal_at(&normalize_path(path.as_ref()), ep, Some(name.into()))
+    }
+
+    fn internal_at<E>(mut self, path: &str, ep: E, name: Option<String>) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.tree
-            .add(&normalize_path(path.as_ref()), ep.map_to_response().boxed())?;
+        let ep = ep.into_endpoint();
+        if let Some(name) = &name {
+            self.add_name(name.clone(), path.to_string())?;
+        }
+        match (&ep as &dyn Any).downcast_ref::<RouteMethod>() {
+            Some(route_method) => {
+                self.routes
+                    .extend(route_method.methods().map(|method| RouteInfo {
+                        method: Some(method.clone()),
+                        path: path.to_string(),
+                        name: name.clone(),
+                    }))
+            }
+            None => self.routes.push(RouteInfo {
+                method: None,
+                path: path.to_string(),
+                name,
+            }),
+        }
+        let entry = RouteEntry {
+            ep: ep.map_to_response().boxed(),
+            pattern: path.to_string(),
+            nest_prefix: None,
+        };
+        self.add_path(path, entry)?;
         Ok(self)
     }
 
+    fn add_path(&mut self, path: &str, entry: RouteEntry) -> Result<(), RouteError> {
+        if self.strict_conflicts {
+            if let Some(existing) = self
+                .paths
+                .iter()
+                .find(|existing| *existing != path && is_conflict(existing, path))
+            {
+                let display = |path: &str| path.replace("*--poem-rest", "*");
+                let matched_first = match priority(path, existing) {
+                    Ordering::Less => path,
+                    _ => existing,
+                };
+                return Err(RouteError::Conflict {
+                    path: display(path),
+                    existing: display(existing),
+                    matched_first: display(matched_first),
+                });
+            }
+        }
+        self.tree.add_with_matchers(path, entry, &self.matchers)?;
+        self.paths.push(path.to_string());
+        Ok(())
+    }
+
+    /// Returns an iterator over the routes registered in this route, including
+    /// the routes which are nested directly with [`Route::nest`] or
+    /// [`Route::nest_no_strip`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{get, handler, http::Method, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let app = Route::new()
+    ///     .at("/a", get(index).post(index))
+    ///     .nest("/b", Route::new().at_named("/c", index, "c"));
+    ///
+    /// for route in app.routes() {
+    ///     println!("{:?} {}", route.method, route.path);
+    /// }
+    ///
+    /// let routes = app.routes().collect::<Vec<_>>();
+    /// assert_eq!(routes.len(), 3);
+    /// assert_eq!(routes[0].method, Some(Method::GET));
+    /// assert_eq!(routes[2].path, "/b/c");
+    /// assert_eq!(routes[2].name.as_deref(), Some("c"));
+    /// ```
+    pub fn routes(&self) -> impl Iterator<Item = &RouteInfo> {
+        self.routes.iter()
+    }
+
+    /// Returns the URL of the route with the specified name, the parameters
+    /// in the path are replaced with the values in `params`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{handler, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let app = Route::new().at_named("/users/:id", index, "user_detail");
+    /// assert_eq!(
+    ///     app.url_for("user_detail", &[("id", "1")]).unwrap(),
+    ///     "/users/1"
+    /// );
+    /// ```
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlForError> {
+        UrlFor(self.names.clone()).url(name, params)
+    }
+
+    fn add_name(&mut self, name: String, path: String) -> Result<(), RouteError> {
+        let names = Arc::make_mut(&mut self.names);
+        if names.contains_key(&name) {
+            return Err(RouteError::DuplicateName(name));
+        }
+        names.insert(name, path);
+        Ok(())
+    }
+
+    /// Add an [Endpoint] for the requests whose `Host` header matches the
+    /// pattern, the pattern syntax is the same as
+    /// [`RouteDomain`](crate::RouteDomain), and the port of the host is
+    /// ignored.
+    ///
+    /// The requests are dispatched by the hosts before the paths, the requests
+    /// with other hosts are routed by the paths.
+    ///
+    /// # Panics
+    ///
+    /// Panic when there are duplicates in the hosts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::make_sync, test::TestClient, Route};
+    ///
+    /// let app = Route::new()
+    ///     .host("api.example.com", make_sync(|_| "api"))
+    ///     .host("*.tenants.example.com", make_sync(|_| "tenant"))
+    ///     .at("/", make_sync(|_| "index"));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/")
+    ///     .header("host", "api.example.com:8080")
+    ///     .send()
+    ///     .await
+    ///     .assert_text("api")
+    ///     .await;
+    /// cli.get("/")
+    ///     .header("host", "a.tenants.example.com")
+    ///     .send()
+    ///     .await
+    ///     .assert_text("tenant")
+    ///     .await;
+    /// cli.get("/")
+    ///     .header("host", "example.com")
+    ///     .send()
+    ///     .await
+    ///     .assert_text("index")
+    ///     .await;
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn host<E>(self, pattern: impl AsRef<str>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        check_result(self.try_host(pattern, ep))
+    }
+
+    /// Attempts to add an [Endpoint] for the requests whose `Host` header
+    /// matches the pattern.
+    pub fn try_host<E>(mut self, pattern: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.hosts.get_or_insert_with(Default::default).add(
+            &pattern.as_ref().to_ascii_lowercase(),
+            ep.into_endpoint().map_to_response().boxed(),
+        )?;
+        Ok(self)
+    }
+
+    /// Sets the endpoint for the requests which do not match any routes,
+    /// otherwise [`NotFoundError`] is returned.
+    ///
+    /// The routes nested in this route have their own fallback endpoints, the
+    /// unmatched requests under the nested prefixes are handled by them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::make_sync, handler, http::StatusCode, test::TestClient, Route};
+    ///
+    /// #[handler]
+    /// fn index() -> &'static str {
+    ///     "index"
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .at("/", index)
+    ///     .nest(
+    ///         "/api",
+    ///         Route::new()
+    ///             .at("/users", index)
+    ///             .fallback(make_sync(|_| StatusCode::NOT_IMPLEMENTED)),
+    ///     )
+    ///     .fallback(index);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/a/b").send().await.assert_text("index").await;
+    /// cli.get("/api/a")
+    ///     .send()
+    ///     .await
+    ///     .assert_status(StatusCode::NOT_IMPLEMENTED);
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn fallback<E>(mut self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.fallback = Some(ep.into_endpoint().map_to_response().boxed());
+        self
+    }
+
     /// Nest a `Endpoint` to the specified path and strip the prefix.
     ///
     /// # Panics
@@ -182,7 +665,70 @@ impl Route {
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.internal_nest(&normalize_path(path.as_ref()), ep, true)
+        self.internal_nest(&normalize_path(path.as_ref()), ep, true, make(|ep| ep))
+    }
+
+    /// Nest a `Endpoint` to the specified path and strip the prefix, the
+    /// middleware only wraps the nested endpoint.
+    ///
+    /// Unlike nesting `ep.with(middleware)`, the names and the routes of the
+    /// nested [`Route`] are still available in this route. The middleware is
+    /// called after the middlewares of this route, and the request path has
+    /// been stripped when it is called.
+    ///
+    /// # Panics
+    ///
+    /// Panic when there are duplicates in the routing table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{handler, http::StatusCode, middleware::SetHeader, test::TestClient, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let app = Route::new().at("/a", index).nest_with(
+    ///     "/api",
+    ///     Route::new().at("/b", index),
+    ///     SetHeader::new().overriding("x-api", "1"),
+    /// );
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/a")
+    ///     .send()
+    ///     .await
+    ///     .assert_header_is_not_exist("x-api");
+    /// cli.get("/api/b").send().await.assert_header("x-api", "1");
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn nest_with<E, M>(self, path: impl AsRef<str>, ep: E, middleware: M) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+        M: Middleware<E::Endpoint>,
+        M::Output: 'static,
+    {
+        check_result(self.try_nest_with(path, ep, middleware))
+    }
+
+    /// Attempts to nest a `Endpoint` to the specified path and strip the
+    /// prefix, the middleware only wraps the nested endpoint.
+    pub fn try_nest_with<E, M>(
+        self,
+        path: impl AsRef<str>,
+        ep: E,
+        middleware: M,
+    ) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+        M: Middleware<E::Endpoint>,
+        M::Output: 'static,
+    {
+        self.internal_nest(&normalize_path(path.as_ref()), ep, true, middleware)
     }
 
     /// Nest a `Endpoint` to the specified path, but do not strip the prefix.
@@ -206,20 +752,90 @@ impl Route {
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.internal_nest(&normalize_path(path.as_ref()), ep, false)
+        self.internal_nest(&normalize_path(path.as_ref()), ep, false, make(|ep| ep))
     }
 
-    fn internal_nest<E>(mut self, path: &str, ep: E, strip: bool) -> Result<Self, RouteError>
+    /// Nest a `Endpoint` to the specified path, but do not strip the prefix,
+    /// the middleware only wraps the nested endpoint.
+    ///
+    /// See also [`Route::nest_with`].
+    ///
+    /// # Panics
+    ///
+    /// Panic when there are duplicates in the routing table.
+    #[must_use]
+    pub fn nest_no_strip_with<E, M>(self, path: impl AsRef<str>, ep: E, middleware: M) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
+        M: Middleware<E::Endpoint>,
+        M::Output: 'static,
     {
-        let ep = Arc::new(ep.into_endpoint());
+        check_result(self.try_nest_no_strip_with(path, ep, middleware))
+    }
+
+    /// Attempts to nest a `Endpoint` to the specified path, but do not strip
+    /// the prefix, the middleware only wraps the nested endpoint.
+    pub fn try_nest_no_strip_with<E, M>(
+        self,
+        path: impl AsRef<str>,
+        ep: E,
+        middleware: M,
+    ) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+        M: Middleware<E::Endpoint>,
+        M::Output: 'static,
+    {
+        self.internal_nest(&normalize_path(path.as_ref()), ep, false, middleware)
+    }
+
+    fn internal_nest<E, M>(
+        mut self,
+        path: &str,
+        ep: E,
+        strip: bool,
+        middleware: M,
+    ) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+        M: Middleware<E::Endpoint>,
+        M::Output: 'static,
+    {
+        let ep = ep.into_endpoint();
         let mut path = path.to_string();
         if !path.ends_with('/') {
             path.push('/');
         }
 
+        // the names and the routes of the nested route are available in this
+        // route
+        let prefix = match strip {
+            true => &path[..path.len() - 1],
+            false => "",
+        };
+        let nested_path = |route_path: &str| join_path(prefix, route_path);
+        match (&ep as &dyn Any).downcast_ref::<Route>() {
+            Some(route) => {
+                for (name, route_path) in route.names.iter() {
+                    self.add_name(name.clone(), nested_path(route_path))?;
+                }
+                self.routes
+                    .extend(route.routes.iter().map(|route| RouteInfo {
+                        path: nested_path(&route.path),
+                        ..route.clone()
+                    }));
+            }
+            None => self.routes.push(RouteInfo {
+                method: None,
+                path: format!("{}*", path),
+                name: None,
+            }),
+        }
+        let ep = Arc::new(middleware.transform(ep));
+
         struct Nest<T> {
             inner: T,
             root: bool,
@@ -265,40 +881,170 @@ impl Route {
             true => path.len() - 1,
         };
 
-        self.tree.add(
+        let pattern = format!("{}*", path);
+        let nest_prefix = prefix.to_string();
+
+        self.add_path(
             &format!("{}*--poem-rest", path),
-            Box::new(Nest {
-                inner: ep.clone(),
-                root: false,
-                prefix_len,
-            }),
+            RouteEntry {
+                ep: Box::new(Nest {
+                    inner: ep.clone(),
+                    root: false,
+                    prefix_len,
+                }),
+                pattern: pattern.clone(),
+                nest_prefix: Some(nest_prefix.clone()),
+            },
         )?;
 
-        self.tree.add(
+        self.add_path(
             &path[..path.len() - 1],
-            Box::new(Nest {
-                inner: ep,
-                root: true,
-                prefix_len,
-            }),
+            RouteEntry {
+                ep: Box::new(Nest {
+                    inner: ep,
+                    root: true,
+                    prefix_len,
+                }),
+                pattern,
+                nest_prefix: Some(nest_prefix),
+            },
         )?;
 
         Ok(self)
     }
 }
 
+impl Route {
+    /// Returns the matches of the path, and the canonical path to redirect to
+    /// if the path only matches with the [`TrailingSlashPolicy`] or
+    /// [`CaseSensitivity`] policy.
+    fn find(&self, path: &str) -> Option<(Matches<'_, RouteEntry>, Option<String>)> {
+        if let Some(matches) = self.tree.matches(path) {
+            return Some((matches, None));
+        }
+
+        let ignore_case = self.case_sensitivity != CaseSensitivity::Sensitive;
+        let case_redirect = self.case_sensitivity == CaseSensitivity::RedirectToCanonical;
+        let slash_redirect = self.trailing_slash == TrailingSlashPolicy::RedirectToCanonical;
+
+        if ignore_case {
+            if let Some((matches, canonical)) = self.tree.matches_ignore_case(path) {
+                return Some((matches, case_redirect.then(|| canonical)));
+            }
+        }
+
+        if self.trailing_slash != TrailingSlashPolicy::Strict && path != "/" {
+            let path = toggle_trailing_slash(path);
+            if let Some(matches) = self.tree.matches(&path) {
+                return Some((matches, slash_redirect.then(|| path)));
+            }
+            if ignore_case {
+                if let Some((matches, canonical)) = self.tree.matches_ignore_case(&path) {
+                    return Some((
+                        matches,
+                        (case_redirect || slash_redirect).then(|| canonical),
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+}
+
 #[async_trait::async_trait]
 impl Endpoint for Route {
     type Output = Response;
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
-        match self.tree.matches(req.uri().path()) {
-            Some(matches) => {
-                req.state_mut().match_params.extend(matches.params);
-                matches.data.call(req).await
+        if let Some(ep) = self
+            .hosts
+            .as_ref()
+            .and_then(|hosts| hosts.matches(&request_host(&req).to_ascii_lowercase()))
+        {
+            return ep.call(req).await;
+        }
+
+        if !self.names.is_empty() && req.extensions().get::<UrlFor>().is_none() {
+            req.extensions_mut().insert(UrlFor(self.names.clone()));
+        }
+
+        let (matches, canonical) = match self.find(req.uri().path()) {
+            Some(res) => res,
+            None => {
+                return match &self.fallback {
+                    Some(ep) => ep.call(req).await,
+                    None => Err(NotFoundError.into()),
+                }
             }
-            None => Err(NotFoundError.into()),
+        };
+
+        if let Some(canonical) = canonical {
+            // the request path may be stripped by the nesting, so the prefix is taken
+            // from the original path
+            let original_uri = req.original_uri();
+            let original_path = original_uri.path();
+            let prefix = original_path
+                .len()
+                .checked_sub(req.uri().path().len())
+                .and_then(|len| original_path.get(..len))
+                .unwrap_or_default();
+            let mut location = format!("{}{}", prefix, canonical);
+            if let Some(query) = original_uri.query() {
+                location.push('?');
+                location.push_str(query);
+            }
+            return Ok(Redirect::moved_permanent(location).into_response());
+        }
+
+        let entry = matches.data;
+        let base = req
+            .extensions()
+            .get::<NestPrefix>()
+            .map(|prefix| prefix.0.as_str())
+            .unwrap_or_default();
+        let matched_path = MatchedPath(join_path(base, &entry.pattern));
+        let nest_prefix = entry
+            .nest_prefix
+            .as_deref()
+            .map(|prefix| NestPrefix(join_path(base, prefix)));
+
+        if let Some(nest_prefix) = nest_prefix {
+            req.extensions_mut().insert(nest_prefix);
         }
+        req.extensions_mut().insert(matched_path.clone());
+        req.state_mut().match_params.extend(matches.params);
+
+        // the nested routes have inserted the more specific patterns
+        match entry.ep.call(req).await {
+            Ok(mut resp) => {
+                if resp.data::<MatchedPath>().is_none() {
+                    resp.set_data(matched_path);
+                }
+                Ok(resp)
+            }
+            Err(mut err) => {
+                if err.data::<MatchedPath>().is_none() {
+                    err.set_data(matched_path);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Joins the prefix of the nesting and the path of the nested route.
+fn join_path(prefix: &str, path: &str) -> String {
+    match path {
+        "/" if !prefix.is_empty() => prefix.to_string(),
+        _ => format!("{}{}", prefix, path),
+    }
+}
+
+fn toggle_trailing_slash(path: &str) -> String {
+    match path.strip_suffix('/') {
+        Some(path) => path.to_string(),
+        None => format!("{}/", path),
     }
 }
 
@@ -313,10 +1059,15 @@ fn normalize_path(path: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use http::{StatusCode, Uri};
+    use http::{header, StatusCode, Uri};
 
     use super::*;
-    use crate::{endpoint::make_sync, handler};
+    use crate::{
+        endpoint::make_sync,
+        handler,
+        test::TestClient,
+        web::{MatchedPath, UrlFor},
+    };
 
     #[test]
     fn test_normalize_path() {
@@ -453,6 +1204,352 @@ mod tests {
         let _ = Route::new().at("/a/*:v", h).at("/a/*", h);
     }
 
+    #[tokio::test]
+    async fn named_routes() {
+        #[handler(internal)]
+        fn url(url_for: UrlFor) -> String {
+            url_for.url("c", &[("id", "1")]).unwrap()
+        }
+
+        let app = Route::new()
+            .at_named("/a", h, "a")
+            .nest(
+                "/api",
+                Route::new()
+                    .at_named("/", h, "root")
+                    .at_named("/c/:id", h, "c")
+                    .at("/url", url),
+            )
+            .nest_no_strip("/b", Route::new().at_named("/b/d", h, "d"));
+
+        assert_eq!(app.url_for("a", &[]).unwrap(), "/a");
+        assert_eq!(app.url_for("root", &[]).unwrap(), "/api");
+        assert_eq!(app.url_for("c", &[("id", "1")]).unwrap(), "/api/c/1");
+        assert_eq!(app.url_for("d", &[]).unwrap(), "/b/d");
+        assert_eq!(get(&app, "/api/url").await, "/api/c/1");
+    }
+
+    #[tokio::test]
+    async fn segment_matcher() {
+        let r = Route::new()
+            .segment_matcher("even", |segment: &str| {
+                segment.parse::<u32>().map(|n| n % 2 == 0).unwrap_or(false)
+            })
+            .at("/a/:n<@even>", h)
+            .at("/a/:n", make_sync(|_| "odd"))
+            .at("/b/<@even>/c", h);
+
+        assert_eq!(get(&r, "/a/2").await, "/a/2");
+        assert_eq!(get(&r, "/a/3").await, "odd");
+        assert_eq!(get(&r, "/b/4/c").await, "/b/4/c");
+        assert_eq!(
+            r.get_response(Request::builder().uri(Uri::from_static("/b/5/c")).finish())
+                .await
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn regex_matches_from_start() {
+        let r = Route::new().at("/a/:n<\\d+>", h);
+        assert_eq!(get(&r, "/a/12").await, "/a/12");
+        assert_eq!(
+            r.get_response(Request::builder().uri(Uri::from_static("/a/b12")).finish())
+                .await
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn trailing_slash() {
+        async fn status(route: &Route, path: &'static str) -> StatusCode {
+            route
+                .get_response(Request::builder().uri(Uri::from_static(path)).finish())
+                .await
+                .status()
+        }
+
+        let r = Route::new().at("/a", h).at("/b/", h);
+        assert_eq!(status(&r, "/a/").await, StatusCode::NOT_FOUND);
+        assert_eq!(status(&r, "/b").await, StatusCode::NOT_FOUND);
+
+        let r = Route::new()
+            .at("/a", h)
+            .at("/b/", h)
+            .trailing_slash(TrailingSlashPolicy::MergeSlash);
+        assert_eq!(get(&r, "/a").await, "/a");
+        assert_eq!(get(&r, "/a/").await, "/a/");
+        assert_eq!(get(&r, "/b").await, "/b");
+        assert_eq!(status(&r, "/c/").await, StatusCode::NOT_FOUND);
+
+        let r = Route::new()
+            .nest(
+                "/api",
+                Route::new()
+                    .at("/a", h)
+                    .trailing_slash(TrailingSlashPolicy::RedirectToCanonical),
+            )
+            .at("/b/", h);
+        let resp = r
+            .get_response(
+                Request::builder()
+                    .uri(Uri::from_static("/api/a/?x=1"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers()["location"], "/api/a?x=1");
+        assert_eq!(get(&r, "/api/a").await, "/a");
+        assert_eq!(status(&r, "/b").await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn matched_path() {
+        #[handler(internal)]
+        fn matched_path(matched_path: MatchedPath) -> String {
+            matched_path.to_string()
+        }
+
+        let r = Route::new()
+            .at("/a/:id", matched_path)
+            .nest(
+                "/b",
+                Route::new()
+                    .at("/", matched_path)
+                    .at("/c/*path", matched_path)
+                    .nest("/d", Route::new().at("/:id/e", matched_path)),
+            )
+            .nest_no_strip("/f", Route::new().at("/f/g", matched_path))
+            .nest("/h", make_sync(|_| ()));
+
+        assert_eq!(get(&r, "/a/1").await, "/a/:id");
+        assert_eq!(get(&r, "/b").await, "/b");
+        assert_eq!(get(&r, "/b/c/1/2").await, "/b/c/*path");
+        assert_eq!(get(&r, "/b/d/1/e").await, "/b/d/:id/e");
+        assert_eq!(get(&r, "/f/g").await, "/f/g");
+
+        let resp = r
+            .get_response(Request::builder().uri(Uri::from_static("/h/1")).finish())
+            .await;
+        assert_eq!(resp.data::<MatchedPath>().unwrap().as_str(), "/h/*");
+        let resp = r
+            .get_response(
+                Request::builder()
+                    .uri(Uri::from_static("/b/d/1/x"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.data::<MatchedPath>().unwrap().as_str(), "/b/d/*");
+        assert!(r
+            .get_response(Request::builder().uri(Uri::from_static("/x")).finish())
+            .await
+            .data::<MatchedPath>()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn nest_with_middleware() {
+        fn append(
+            value: &'static str,
+        ) -> impl Middleware<BoxEndpoint<'static>, Output = BoxEndpoint<'static>> {
+            make(move |ep: BoxEndpoint<'static>| {
+                ep.after(move |res| async move {
+                    let mut resp = res?;
+                    let body = resp.take_body().into_string().await?;
+                    Ok(Response::builder().body(format!("{}{}", body, value)))
+                })
+                .boxed()
+            })
+        }
+
+        let r = Route::new()
+            .at("/a", h)
+            .nest_with(
+                "/b",
+                Route::new().at_named("/c", h, "c").boxed(),
+                append("+inner"),
+            )
+            .nest_no_strip_with("/d", Route::new().at("/d/e", h).boxed(), append("+d"))
+            .boxed()
+            .with(append("+outer"));
+
+        assert_eq!(get(&r, "/a").await, "/a+outer");
+        assert_eq!(get(&r, "/b/c").await, "/c+inner+outer");
+        assert_eq!(get(&r, "/d/e").await, "/d/e+d+outer");
+
+        let r = Route::new().nest_with("/b", Route::new().at_named("/c", h, "c"), make(|ep| ep));
+        assert_eq!(r.url_for("c", &[]).unwrap(), "/b/c");
+        assert_eq!(r.routes().next().unwrap().path, "/b/c");
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        let r = Route::new()
+            .at("/a", h)
+            .nest(
+                "/b",
+                Route::new()
+                    .at("/c", h)
+                    .fallback(make_sync(|_| "b fallback")),
+            )
+            .nest("/d", Route::new().at("/e", h))
+            .fallback(make_sync(|_| "fallback"));
+
+        assert_eq!(get(&r, "/a").await, "/a");
+        assert_eq!(get(&r, "/x/y").await, "fallback");
+        assert_eq!(get(&r, "/b/c").await, "/c");
+        assert_eq!(get(&r, "/b/x").await, "b fallback");
+        assert_eq!(
+            r.get_response(Request::builder().uri(Uri::from_static("/d/x")).finish())
+                .await
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn strict_conflicts() {
+        let r = Route::new()
+            .strict_conflicts(true)
+            .at("/a/:id<\\d+>", h)
+            .at("/a/:name<[a-z]+>", h)
+            .at("/a/:id/b", h)
+            .nest("/c", Route::new());
+
+        assert_eq!(
+            r.try_at("/c/d", h).err(),
+            Some(RouteError::Conflict {
+                path: "/c/d".to_string(),
+                existing: "/c/*".to_string(),
+                matched_first: "/c/d".to_string(),
+            })
+        );
+
+        let r = Route::new().strict_conflicts(true).at("/a/:id<\\d+>", h);
+        assert_eq!(
+            r.try_at("/a/:id", h).err(),
+            Some(RouteError::Conflict {
+                path: "/a/:id".to_string(),
+                existing: "/a/:id<\\d+>".to_string(),
+                matched_first: "/a/:id<\\d+>".to_string(),
+            })
+        );
+
+        let r = Route::new().strict_conflicts(true).at("/a/:id", h);
+        assert_eq!(
+            r.try_at("/a/:id", h).err(),
+            Some(RouteError::Duplicate("/a/:id".to_string()))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn strict_conflicts_panic() {
+        let _ = Route::new()
+            .strict_conflicts(true)
+            .at("/users/:id", h)
+            .at("/users/new", h);
+    }
+
+    #[tokio::test]
+    async fn hosts() {
+        let r = Route::new()
+            .host("api.example.com", make_sync(|_| "api"))
+            .at("/a", make_sync(|_| "a"));
+        let cli = TestClient::new(r);
+
+        cli.get("/b")
+            .header(header::HOST, "API.example.com:3000")
+            .send()
+            .await
+            .assert_text("api")
+            .await;
+        cli.get("/a")
+            .header(header::HOST, "www.example.com")
+            .send()
+            .await
+            .assert_text("a")
+            .await;
+        cli.get("/a").send().await.assert_text("a").await;
+    }
+
+    #[tokio::test]
+    async fn case_sensitivity() {
+        let r = Route::new().at("/Abc/:name", h);
+        assert_eq!(
+            r.get_response(Request::builder().uri(Uri::from_static("/abc/x")).finish())
+                .await
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let r = Route::new()
+            .at("/Abc/:name", h)
+            .case_sensitivity(CaseSensitivity::Insensitive);
+        assert_eq!(get(&r, "/ABC/Xy").await, "/ABC/Xy");
+
+        let r = Route::new().nest(
+            "/api",
+            Route::new()
+                .at("/Abc/:name", h)
+                .case_sensitivity(CaseSensitivity::RedirectToCanonical)
+                .trailing_slash(TrailingSlashPolicy::MergeSlash),
+        );
+        assert_eq!(get(&r, "/api/Abc/Xy").await, "/Abc/Xy");
+        let resp = r
+            .get_response(
+                Request::builder()
+                    .uri(Uri::from_static("/api/abc/Xy/?a=1"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers()["location"], "/api/Abc/Xy?a=1");
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_segment_matcher() {
+        let _ = Route::new().at("/a/:n<@even>", h);
+    }
+
+    #[test]
+    fn routes() {
+        let app = Route::new()
+            .at_named("/a", RouteMethod::new().get(h).post(h), "a")
+            .nest("/b", Route::new().at("/", h).at("/c/:id", h))
+            .nest_no_strip("/d", Route::new().at("/d/e", h))
+            .nest("/f", h);
+
+        let route = |method: Option<Method>, path: &str, name: Option<&str>| RouteInfo {
+            method,
+            path: path.to_string(),
+            name: name.map(ToString::to_string),
+        };
+        assert_eq!(
+            app.routes().cloned().collect::<Vec<_>>(),
+            vec![
+                route(Some(Method::GET), "/a", Some("a")),
+                route(Some(Method::POST), "/a", Some("a")),
+                route(None, "/b", None),
+                route(None, "/b/c/:id", None),
+                route(None, "/d/e", None),
+                route(None, "/f/*", None),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_name() {
+        let _ = Route::new()
+            .at_named("/a", h, "a")
+            .nest("/b", Route::new().at_named("/a", h, "a"));
+    }
+
     #[tokio::test]
     async fn issue_174() {
         let app = Route::new().nest("/", make_sync(|_| "hello"));
@@ -1,6 +1,8 @@
 use crate::{
-    endpoint::BoxEndpoint, error::MethodNotAllowedError, http::Method, Endpoint, EndpointExt,
-    IntoEndpoint, Request, Response, Result,
+    endpoint::BoxEndpoint,
+    error::{MethodNotAllowedError, ResponseErrorExt},
+    http::{header, HeaderMap, HeaderValue, Method},
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response, Result,
 };
 
 /// Routing object for HTTP methods
@@ -47,11 +49,21 @@ use crate::{
 ///     .get_response(Request::builder().method(Method::PUT).finish())
 ///     .await;
 /// assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+/// assert_eq!(resp.headers()["allow"], "GET, HEAD, POST");
 /// # });
 /// ```
-#[derive(Default)]
 pub struct RouteMethod {
     methods: Vec<(Method, BoxEndpoint<'static>)>,
+    allow_header: bool,
+}
+
+impl Default for RouteMethod {
+    fn default() -> Self {
+        Self {
+            methods: Vec::new(),
+            allow_header: true,
+        }
+    }
 }
 
 impl RouteMethod {
@@ -60,6 +72,18 @@ impl RouteMethod {
         Default::default()
     }
 
+    /// Sets whether to add the `Allow` header, which lists the methods
+    /// supported by this object, to the `405 Method Not Allowed` responses.
+    ///
+    /// Default is `true`.
+    #[must_use]
+    pub fn allow_header(self, enable: bool) -> Self {
+        Self {
+            allow_header: enable,
+            ..self
+        }
+    }
+
     /// Sets the endpoint for the specified `method`.
     #[must_use]
     pub fn method<E>(mut self, method: Method, ep: E) -> Self
@@ -72,6 +96,11 @@ impl RouteMethod {
         self
     }
 
+    /// Returns an iterator over the methods which have an endpoint.
+    pub fn methods(&self) -> impl Iterator<Item = &Method> {
+        self.methods.iter().map(|(method, _)| method)
+    }
+
     /// Sets the endpoint for `GET`.
     #[must_use]
     pub fn get<E>(self, ep: E) -> Self
@@ -161,6 +190,19 @@ impl RouteMethod {
     {
         self.method(Method::TRACE, ep)
     }
+
+    fn allow_header_value(&self) -> HeaderValue {
+        let mut methods: Vec<&str> = Vec::new();
+        for (method, _) in &self.methods {
+            if !methods.contains(&method.as_str()) {
+                methods.push(method.as_str());
+            }
+            if method == Method::GET && !methods.contains(&Method::HEAD.as_str()) {
+                methods.push(Method::HEAD.as_str());
+            }
+        }
+        HeaderValue::from_str(&methods.join(", ")).expect("valid header value")
+    }
 }
 
 #[async_trait::async_trait]
@@ -182,7 +224,12 @@ impl Endpoint for RouteMethod {
                     resp.set_body(());
                     return Ok(resp);
                 }
-                Err(MethodNotAllowedError.into())
+                if !self.allow_header {
+                    return Err(MethodNotAllowedError.into());
+                }
+                let mut headers = HeaderMap::new();
+                headers.insert(header::ALLOW, self.allow_header_value());
+                Err(MethodNotAllowedError.with_headers(headers))
             }
         }
     }
@@ -284,6 +331,41 @@ mod tests {
         resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
     }
 
+    #[tokio::test]
+    async fn allow_header() {
+        #[handler(internal)]
+        fn index() {}
+
+        let route = RouteMethod::new().get(index).post(index).head(index);
+        let resp = TestClient::new(route).put("/").send().await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header("allow", "GET, HEAD, POST");
+
+        let route = RouteMethod::new().post(index);
+        let resp = TestClient::new(route).head("/").send().await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header("allow", "POST");
+
+        let route = RouteMethod::new().post(index).allow_header(false);
+        let resp = TestClient::new(route).get("/").send().await;
+        resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        resp.assert_header_is_not_exist("allow");
+    }
+
+    #[tokio::test]
+    async fn downcast_method_not_allowed() {
+        #[handler(internal)]
+        fn index() {}
+
+        let err = RouteMethod::new()
+            .post(index)
+            .call(Request::default())
+            .await
+            .unwrap_err();
+        assert!(err.is::<MethodNotAllowedError>());
+        assert_eq!(err.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
     #[tokio::test]
     async fn route_method() {
         #[handler(internal)]
@@ -0,0 +1,213 @@
+use std::convert::TryInto;
+
+use http::uri::Scheme;
+
+use crate::{
+    endpoint::BoxEndpoint,
+    error::NotFoundError,
+    http::{HeaderName, HeaderValue},
+    route::request_host,
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response, Result,
+};
+
+type Predicate = Box<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// Routing object which dispatches the requests with predicates, such as the
+/// `Host` header, any other headers or the scheme.
+///
+/// The predicates are checked in the order they are added, the first matched
+/// endpoint is called.
+///
+/// # Errors
+///
+/// - [`NotFoundError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{endpoint::make_sync, test::TestClient, RoutePredicate};
+///
+/// let app = RoutePredicate::new()
+///     .host("api.example.com", make_sync(|_| "api"))
+///     .header("x-tenant", "a", make_sync(|_| "tenant a"))
+///     .at(
+///         |req: &poem::Request| req.uri().query().is_some(),
+///         make_sync(|_| "query"),
+///     )
+///     .fallback(make_sync(|_| "fallback"));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/")
+///     .header("host", "api.example.com:8080")
+///     .send()
+///     .await
+///     .assert_text("api")
+///     .await;
+/// cli.get("/")
+///     .header("x-tenant", "a")
+///     .send()
+///     .await
+///     .assert_text("tenant a")
+///     .await;
+/// cli.get("/")
+///     .query("a", &1)
+///     .send()
+///     .await
+///     .assert_text("query")
+///     .await;
+/// cli.get("/").send().await.assert_text("fallback").await;
+/// # });
+/// ```
+#[derive(Default)]
+pub struct RoutePredicate {
+    predicates: Vec<(Predicate, BoxEndpoint<'static>)>,
+    fallback: Option<BoxEndpoint<'static>>,
+}
+
+impl RoutePredicate {
+    /// Create a `RoutePredicate` object.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the endpoint for the requests which match the predicate.
+    #[must_use]
+    pub fn at<F, E>(mut self, predicate: F, ep: E) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.predicates.push((
+            Box::new(predicate),
+            ep.into_endpoint().map_to_response().boxed(),
+        ));
+        self
+    }
+
+    /// Sets the endpoint for the requests whose `Host` header, without the
+    /// port, equals to `host` case-insensitively.
+    ///
+    /// Use [`RouteDomain`](crate::RouteDomain) to match the hosts with
+    /// wildcards.
+    #[must_use]
+    pub fn host<E>(self, host: impl Into<String>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let host = host.into();
+        self.at(move |req| request_host(req).eq_ignore_ascii_case(&host), ep)
+    }
+
+    /// Sets the endpoint for the requests which have a header with the
+    /// specified value.
+    ///
+    /// # Panics
+    ///
+    /// Panic when the header name or value is invalid.
+    #[must_use]
+    pub fn header<K, V, E>(self, key: K, value: V, ep: E) -> Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let key = key
+            .try_into()
+            .unwrap_or_else(|_| panic!("invalid header name"));
+        let value = value
+            .try_into()
+            .unwrap_or_else(|_| panic!("invalid header value"));
+        self.at(
+            move |req| req.headers().get_all(&key).iter().any(|v| v == value),
+            ep,
+        )
+    }
+
+    /// Sets the endpoint for the requests with the specified scheme.
+    #[must_use]
+    pub fn scheme<E>(self, scheme: Scheme, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.at(move |req| req.scheme() == &scheme, ep)
+    }
+
+    /// Sets the endpoint for the requests which do not match any predicates.
+    #[must_use]
+    pub fn fallback<E>(mut self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.fallback = Some(ep.into_endpoint().map_to_response().boxed());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for RoutePredicate {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        match self
+            .predicates
+            .iter()
+            .find(|(predicate, _)| predicate(&req))
+            .map(|(_, ep)| ep)
+        {
+            Some(ep) => ep.call(req).await,
+            None => match &self.fallback {
+                Some(ep) => ep.call(req).await,
+                None => Err(NotFoundError.into()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{endpoint::make_sync, test::TestClient};
+
+    #[tokio::test]
+    async fn route_predicate() {
+        let cli = TestClient::new(
+            RoutePredicate::new()
+                .host("example.com", make_sync(|_| "1"))
+                .header("x-tenant", "a", make_sync(|_| "2"))
+                .scheme(Scheme::HTTPS, make_sync(|_| "3")),
+        );
+
+        cli.get("/")
+            .header("host", "EXAMPLE.com")
+            .send()
+            .await
+            .assert_text("1")
+            .await;
+        cli.get("/")
+            .header("host", "example.com:3000")
+            .send()
+            .await
+            .assert_text("1")
+            .await;
+        cli.get("/")
+            .header("host", "www.example.com")
+            .header("x-tenant", "a")
+            .send()
+            .await
+            .assert_text("2")
+            .await;
+        cli.get("/")
+            .header("x-tenant", "b")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
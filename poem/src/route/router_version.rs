@@ -0,0 +1,327 @@
+use std::str::FromStr;
+
+use crate::{
+    endpoint::BoxEndpoint,
+    error::ApiVersionError,
+    http::{header, uri::PathAndQuery, HeaderName, Uri},
+    route::router::NestPrefix,
+    web::ApiVersion,
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response, Result,
+};
+
+/// Where [`VersionedRoute`] reads the API version from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VersionSource {
+    /// The first segment of the path with a prefix, such as `/v1/users` with
+    /// the prefix `v`. The segment is stripped from the path before calling the
+    /// endpoint.
+    ///
+    /// The segment is only treated as a version if the version is registered.
+    PathSegment(String),
+
+    /// A header which contains the version, such as `Accept-Version: 1`.
+    Header(HeaderName),
+
+    /// A parameter of the media types in the `Accept` header, such as
+    /// `Accept: application/json; version=1`.
+    MediaTypeParam(String),
+}
+
+/// Routing object for API versions
+///
+/// The version is read from the [`VersionSource`]s in order, the default
+/// sources are `/v{version}/...`, the `Accept-Version` header and the
+/// `version` parameter of the media types in the `Accept` header. The resolved
+/// version is available to the endpoints with the
+/// [`ApiVersion`](crate::web::ApiVersion) extractor.
+///
+/// # Errors
+///
+/// - [`ApiVersionError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler, http::StatusCode, test::TestClient, web::ApiVersion, Route, VersionedRoute,
+/// };
+///
+/// #[handler]
+/// fn users(version: ApiVersion) -> String {
+///     format!("users v{}", version.as_str())
+/// }
+///
+/// let app = VersionedRoute::new()
+///     .version("1", Route::new().at("/users", users))
+///     .version("2", Route::new().at("/users", users))
+///     .default_version("2");
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/v1/users")
+///     .send()
+///     .await
+///     .assert_text("users v1")
+///     .await;
+/// cli.get("/users")
+///     .header("accept-version", "1")
+///     .send()
+///     .await
+///     .assert_text("users v1")
+///     .await;
+/// cli.get("/users")
+///     .header("accept", "application/json; version=1")
+///     .send()
+///     .await
+///     .assert_text("users v1")
+///     .await;
+/// cli.get("/users").send().await.assert_text("users v2").await;
+/// cli.get("/users")
+///     .header("accept-version", "3")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_FOUND);
+/// # });
+/// ```
+pub struct VersionedRoute {
+    versions: Vec<(String, BoxEndpoint<'static>)>,
+    default_version: Option<String>,
+    sources: Vec<VersionSource>,
+}
+
+impl Default for VersionedRoute {
+    fn default() -> Self {
+        Self {
+            versions: Vec::new(),
+            default_version: None,
+            sources: vec![
+                VersionSource::PathSegment("v".to_string()),
+                VersionSource::Header(HeaderName::from_static("accept-version")),
+                VersionSource::MediaTypeParam("version".to_string()),
+            ],
+        }
+    }
+}
+
+impl VersionedRoute {
+    /// Create a `VersionedRoute` object.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the endpoint for the specified version.
+    #[must_use]
+    pub fn version<E>(mut self, version: impl Into<String>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.versions
+            .push((version.into(), ep.into_endpoint().map_to_response().boxed()));
+        self
+    }
+
+    /// Sets the version used when the request does not specify the version.
+    #[must_use]
+    pub fn default_version(mut self, version: impl Into<String>) -> Self {
+        self.default_version = Some(version.into());
+        self
+    }
+
+    /// Sets the sources of the version, they are checked in order.
+    #[must_use]
+    pub fn sources(mut self, sources: impl IntoIterator<Item = VersionSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    fn find(&self, version: &str) -> Option<(&str, &BoxEndpoint<'static>)> {
+        self.versions
+            .iter()
+            .find(|(v, _)| v == version)
+            .map(|(v, ep)| (v.as_str(), ep))
+    }
+}
+
+/// Returns the value of the parameter in the media types of the `Accept`
+/// header.
+fn media_type_param<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .flat_map(|media_type| media_type.split(';').skip(1))
+        .find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then(|| value.trim().trim_matches('"'))
+        })
+}
+
+#[async_trait::async_trait]
+impl Endpoint for VersionedRoute {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let mut resolved = None;
+
+        for source in &self.sources {
+            match source {
+                VersionSource::PathSegment(prefix) => {
+                    let path = req.uri().path();
+                    let segment = path
+                        .trim_start_matches('/')
+                        .split('/')
+                        .next()
+                        .unwrap_or_default();
+                    if let Some((version, ep)) = segment
+                        .strip_prefix(prefix.as_str())
+                        .and_then(|version| self.find(version))
+                    {
+                        resolved = Some((version, ep, Some(segment.len() + 1)));
+                        break;
+                    }
+                }
+                VersionSource::Header(name) => {
+                    if let Some(version) = req
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                    {
+                        let (version, ep) = self
+                            .find(version.trim())
+                            .ok_or_else(|| ApiVersionError::Unsupported(version.to_string()))?;
+                        resolved = Some((version, ep, None));
+                        break;
+                    }
+                }
+                VersionSource::MediaTypeParam(name) => {
+                    if let Some(version) = media_type_param(&req, name) {
+                        let (version, ep) = self
+                            .find(version)
+                            .ok_or_else(|| ApiVersionError::Unsupported(version.to_string()))?;
+                        resolved = Some((version, ep, None));
+                        break;
+                    }
+                }
+            }
+        }
+
+        let (version, ep, strip_len) = match resolved {
+            Some(resolved) => resolved,
+            None => {
+                let version = self
+                    .default_version
+                    .as_deref()
+                    .ok_or(ApiVersionError::Missing)?;
+                let (version, ep) = self
+                    .find(version)
+                    .ok_or_else(|| ApiVersionError::Unsupported(version.to_string()))?;
+                (version, ep, None)
+            }
+        };
+
+        if let Some(strip_len) = strip_len {
+            // the version segment is a part of the patterns of the matched routes
+            let segment = &req.uri().path()[..strip_len];
+            let prefix = match req.extensions().get::<NestPrefix>() {
+                Some(prefix) => format!("{}{}", prefix.0, segment),
+                None => segment.to_string(),
+            };
+            req.extensions_mut().insert(NestPrefix(prefix));
+
+            let mut uri_parts = std::mem::take(req.uri_mut()).into_parts();
+            let path = &uri_parts.path_and_query.as_ref().unwrap().as_str()[strip_len..];
+            uri_parts.path_and_query = Some(if !path.starts_with('/') {
+                PathAndQuery::from_str(&format!("/{}", path)).unwrap()
+            } else {
+                PathAndQuery::from_str(path).unwrap()
+            });
+            *req.uri_mut() = Uri::from_parts(uri_parts).unwrap();
+        }
+
+        req.extensions_mut().insert(ApiVersion(version.to_string()));
+        ep.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{endpoint::make_sync, handler, test::TestClient, web::MatchedPath, Route};
+
+    #[handler(internal)]
+    fn index(version: ApiVersion, matched_path: MatchedPath, uri: &Uri) -> String {
+        format!("{} {} {}", version.as_str(), matched_path.as_str(), uri)
+    }
+
+    #[tokio::test]
+    async fn versioned_route() {
+        let app = Route::new().nest(
+            "/api",
+            VersionedRoute::new()
+                .version("1", Route::new().at("/users/:id", index))
+                .version("2", Route::new().at("/users/:id", index)),
+        );
+        let cli = TestClient::new(app);
+
+        cli.get("/api/v1/users/1")
+            .query("a", &1)
+            .send()
+            .await
+            .assert_text("1 /api/v1/users/:id /users/1?a=1")
+            .await;
+        cli.get("/api/users/1")
+            .header("accept-version", "2")
+            .send()
+            .await
+            .assert_text("2 /api/users/:id /users/1")
+            .await;
+        cli.get("/api/users/1")
+            .header(
+                "accept",
+                "text/html, application/json; q=0.9; version=\"1\"",
+            )
+            .send()
+            .await
+            .assert_text("1 /api/users/:id /users/1")
+            .await;
+        cli.get("/api/users/1")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/api/v3/users/1")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/api/users/1")
+            .header("accept-version", "3")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn custom_sources() {
+        let app = VersionedRoute::new()
+            .version("2022-01", make_sync(|_| "old"))
+            .version("2023-01", make_sync(|_| "new"))
+            .sources([VersionSource::Header(HeaderName::from_static(
+                "x-api-version",
+            ))])
+            .default_version("2023-01");
+        let cli = TestClient::new(app);
+
+        cli.get("/v2022-01").send().await.assert_text("new").await;
+        cli.get("/")
+            .header("x-api-version", "2022-01")
+            .send()
+            .await
+            .assert_text("old")
+            .await;
+    }
+}
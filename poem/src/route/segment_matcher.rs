@@ -0,0 +1,52 @@
+/// Matches a segment of the request path, it can be registered with
+/// [`Route::segment_matcher`](crate::Route::segment_matcher) and referenced in
+/// the paths with `:name<@matcher>` or `<@matcher>`.
+///
+/// The requests which do not match are rejected by the router, so the invalid
+/// values never reach the handlers.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, http::StatusCode, test::TestClient, web::Path, Route};
+///
+/// #[handler]
+/// fn file(Path(name): Path<String>) -> String {
+///     name
+/// }
+///
+/// let app = Route::new()
+///     .segment_matcher("slug", |segment: &str| {
+///         !segment.is_empty()
+///             && segment
+///                 .chars()
+///                 .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+///     })
+///     .at("/files/:name<@slug>", file);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/files/hello-world")
+///     .send()
+///     .await
+///     .assert_text("hello-world")
+///     .await;
+/// cli.get("/files/Hello")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_FOUND);
+/// # });
+/// ```
+pub trait SegmentMatcher: Send + Sync + 'static {
+    /// Returns `true` if the percent-decoded segment matches.
+    fn matches(&self, segment: &str) -> bool;
+}
+
+impl<F> SegmentMatcher for F
+where
+    F: Fn(&str) -> bool + Send + Sync + 'static,
+{
+    fn matches(&self, segment: &str) -> bool {
+        (self)(segment)
+    }
+}
@@ -1,26 +1,32 @@
 use std::{
     convert::Infallible,
     future::Future,
+    pin::Pin,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
+    task::{Context, Poll},
 };
 
-use http::uri::Scheme;
+use http::{header, uri::Scheme, StatusCode};
 use hyper::server::conn::Http;
 use tokio::{
-    io::{AsyncRead, AsyncWrite, Result as IoResult},
-    sync::Notify,
-    time::Duration,
+    io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult},
+    sync::{watch, Notify},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    listener::{Acceptor, AcceptorExt, Listener},
-    web::{LocalAddr, RemoteAddr},
-    Endpoint, EndpointExt, IntoEndpoint, Response,
+    listener::{Acceptor, AcceptorExt, Listener, TlsInfoSlot},
+    middleware::RequestTimeoutFlag,
+    web::{connection_info::ConnectionMeta, LocalAddr, RemoteAddr},
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response,
 };
 
+/// The ID of the next accepted connection.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
 enum Either<L, A> {
     Listener(L),
     Acceptor(A),
@@ -31,6 +37,22 @@ enum Either<L, A> {
 pub struct Server<L, A> {
     listener: Either<L, A>,
     name: Option<String>,
+    on_draining: Option<DrainingCallback>,
+    options: ConnectionOptions,
+}
+
+type DrainingCallback = Arc<dyn Fn(usize) + Send + Sync>;
+
+type TimeoutResponseFn = Arc<dyn Fn() -> Response + Send + Sync>;
+
+#[derive(Default, Clone)]
+struct ConnectionOptions {
+    idle_timeout: Option<Duration>,
+    header_read_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    request_timeout_response: Option<TimeoutResponseFn>,
+    in_flight_limit: Option<Arc<InFlightLimit>>,
+    retry_after: Option<Duration>,
 }
 
 impl<L: Listener> Server<L, Infallible> {
@@ -39,6 +61,8 @@ impl<L: Listener> Server<L, Infallible> {
         Self {
             listener: Either::Listener(listener),
             name: None,
+            on_draining: None,
+            options: Default::default(),
         }
     }
 }
@@ -49,6 +73,8 @@ impl<A: Acceptor> Server<Infallible, A> {
         Self {
             listener: Either::Acceptor(acceptor),
             name: None,
+            on_draining: None,
+            options: Default::default(),
         }
     }
 }
@@ -68,6 +94,136 @@ where
         }
     }
 
+    /// Sets a callback which is called with the number of the connections
+    /// that are still open during the graceful shutdown.
+    ///
+    /// The callback is called when the graceful shutdown is initiated, and
+    /// each time a connection is closed after that.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{listener::TcpListener, Server};
+    ///
+    /// let server = Server::new(TcpListener::bind("127.0.0.1:3000")).on_draining(|remaining| {
+    ///     tracing::info!(remaining, "draining connections");
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_draining(self, callback: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        Self {
+            on_draining: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    /// Sets the timeout for the idle connections, default is no timeout.
+    ///
+    /// A connection is idle if there is no request in progress, and no data
+    /// is received or sent. The connections which are idle for longer than
+    /// the timeout are closed, which releases the resources held by the
+    /// keep-alive connections that are no longer used.
+    #[must_use]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.options.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for reading the headers of a HTTP/1 request, default
+    /// is no timeout.
+    ///
+    /// If a client does not send the entire headers within the timeout, the
+    /// connection is closed. This protects the server from the slowloris
+    /// attacks, which keep many connections open by sending the headers very
+    /// slowly.
+    #[must_use]
+    pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+        self.options.header_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for handling a request, default is no timeout.
+    ///
+    /// If an endpoint does not return the response within the timeout, the
+    /// handler is canceled and the client receives the response returned by
+    /// [`Server::request_timeout_response`], or `503 Service Unavailable` by
+    /// default. The streaming response body, such as Server-Sent Events, is
+    /// not limited by the timeout.
+    ///
+    /// Use the [`DisableRequestTimeout`](crate::middleware::DisableRequestTimeout)
+    /// middleware to disable the timeout for the long-running endpoints.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use poem::{listener::TcpListener, Server};
+    ///
+    /// let server =
+    ///     Server::new(TcpListener::bind("127.0.0.1:3000")).request_timeout(Duration::from_secs(30));
+    /// ```
+    #[must_use]
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.options.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a function to create the response for the requests which exceed
+    /// the [`Server::request_timeout`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use poem::{http::StatusCode, listener::TcpListener, IntoResponse, Server};
+    ///
+    /// let server = Server::new(TcpListener::bind("127.0.0.1:3000"))
+    ///     .request_timeout(Duration::from_secs(30))
+    ///     .request_timeout_response(|| StatusCode::GATEWAY_TIMEOUT.into_response());
+    /// ```
+    #[must_use]
+    pub fn request_timeout_response(
+        mut self,
+        f: impl Fn() -> Response + Send + Sync + 'static,
+    ) -> Self {
+        self.options.request_timeout_response = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the maximum number of the requests in progress across all
+    /// connections, default is no limit.
+    ///
+    /// The requests exceeding the limit are rejected immediately with `503
+    /// Service Unavailable` and a `Retry-After` header, instead of being
+    /// queued, which keeps the latency bounded when the server is overloaded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use poem::{listener::TcpListener, Server};
+    ///
+    /// let server = Server::new(TcpListener::bind("127.0.0.1:3000"))
+    ///     .max_in_flight_requests(1024)
+    ///     .load_shedding_retry_after(Duration::from_secs(5));
+    /// ```
+    #[must_use]
+    pub fn max_in_flight_requests(mut self, max: usize) -> Self {
+        self.options.in_flight_limit = Some(Arc::new(InFlightLimit::new(max)));
+        self
+    }
+
+    /// Sets the value of the `Retry-After` header of the requests rejected by
+    /// [`Server::max_in_flight_requests`], default is `1` second.
+    #[must_use]
+    pub fn load_shedding_retry_after(mut self, retry_after: Duration) -> Self {
+        self.options.retry_after = Some(retry_after);
+        self
+    }
+
     /// Run this server.
     pub async fn run<E>(self, ep: E) -> IoResult<()>
     where
@@ -79,6 +235,11 @@ where
     }
 
     /// Run this server and a signal to initiate graceful shutdown.
+    ///
+    /// When the signal is received, the server stops accepting new
+    /// connections, closes the idle connections, and waits for the in-flight
+    /// requests to complete. If `timeout` is specified, the remaining
+    /// connections are closed forcibly after the timeout.
     pub async fn run_with_graceful_shutdown<E>(
         self,
         ep: E,
@@ -90,11 +251,17 @@ where
         E::Endpoint: 'static,
     {
         let ep = Arc::new(ep.into_endpoint().map_to_response());
-        let Server { listener, name } = self;
+        let Server {
+            listener,
+            name,
+            on_draining,
+            options,
+        } = self;
         let name = name.as_deref();
         let alive_connections = Arc::new(AtomicUsize::new(0));
         let notify = Arc::new(Notify::new());
-        let timeout_notify = Arc::new(Notify::new());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (force_close_tx, force_close_rx) = watch::channel(false);
 
         let mut acceptor = match listener {
             Either::Listener(listener) => listener.into_acceptor().await?.boxed(),
@@ -118,10 +285,9 @@ where
                             "initiate graceful shutdown",
                         );
 
-                        let timeout_notify = timeout_notify.clone();
                         tokio::spawn(async move {
                             tokio::time::sleep(timeout).await;
-                            timeout_notify.notify_waiters();
+                            let _ = force_close_tx.send(true);
                         });
                     } else {
                         tracing::info!(name = name, "initiate graceful shutdown");
@@ -130,24 +296,38 @@ where
                 },
                 res = acceptor.accept() => {
                     if let Ok((socket, local_addr, remote_addr, scheme)) = res {
+                        let tls_info = acceptor.tls_info(&socket);
                         let ep = ep.clone();
                         let alive_connections = alive_connections.clone();
                         let notify = notify.clone();
-                        let timeout_notify = timeout_notify.clone();
+                        let shutdown_rx = shutdown_rx.clone();
+                        let force_close_rx = force_close_rx.clone();
+                        let on_draining = on_draining.clone();
+                        let options = options.clone();
 
+                        alive_connections.fetch_add(1, Ordering::SeqCst);
                         tokio::spawn(async move {
-                            alive_connections.fetch_add(1, Ordering::SeqCst);
+                            tokio::select! {
+                                _ = serve_connection(
+                                    socket,
+                                    local_addr,
+                                    remote_addr,
+                                    scheme,
+                                    tls_info,
+                                    shutdown_rx.clone(),
+                                    ep,
+                                    options,
+                                ) => {}
+                                _ = wait_for(force_close_rx) => {}
+                            }
 
-                            if timeout.is_some() {
-                                tokio::select! {
-                                    _ = serve_connection(socket, local_addr, remote_addr, scheme, ep) => {}
-                                    _ = timeout_notify.notified() => {}
+                            let remaining = alive_connections.fetch_sub(1, Ordering::SeqCst) - 1;
+                            if *shutdown_rx.borrow() {
+                                if let Some(on_draining) = &on_draining {
+                                    on_draining(remaining);
                                 }
-                            } else {
-                                serve_connection(socket, local_addr, remote_addr, scheme, ep).await;
                             }
-
-                            if alive_connections.fetch_sub(1, Ordering::SeqCst) == 1 {
+                            if remaining == 0 {
                                 notify.notify_one();
                             }
                         });
@@ -157,8 +337,17 @@ where
         }
 
         drop(acceptor);
-        if alive_connections.load(Ordering::SeqCst) > 0 {
-            tracing::info!(name = name, "wait for all connections to close.");
+        let _ = shutdown_tx.send(true);
+        let remaining = alive_connections.load(Ordering::SeqCst);
+        if let Some(on_draining) = &on_draining {
+            on_draining(remaining);
+        }
+        if remaining > 0 {
+            tracing::info!(
+                name = name,
+                remaining = remaining,
+                "wait for all connections to close."
+            );
             notify.notified().await;
         }
 
@@ -167,31 +356,523 @@ where
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn serve_connection(
     socket: impl AsyncRead + AsyncWrite + Send + Unpin + 'static,
     local_addr: LocalAddr,
     remote_addr: RemoteAddr,
     scheme: Scheme,
+    tls_info: Option<TlsInfoSlot>,
+    shutdown_rx: watch::Receiver<bool>,
     ep: Arc<dyn Endpoint<Output = Response>>,
+    options: ConnectionOptions,
 ) {
+    let activity = Arc::new(Activity::new());
+    let socket = ActivityStream {
+        inner: socket,
+        activity: activity.clone(),
+    };
+
+    let idle_timeout = options.idle_timeout;
+    let header_read_timeout = options.header_read_timeout;
+    let connection_id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+    let requests = AtomicU64::new(0);
     let service = hyper::service::service_fn({
+        let activity = activity.clone();
         move |req: hyper::Request<hyper::Body>| {
+            let connection = ConnectionMeta {
+                id: connection_id,
+                requests: requests.fetch_add(1, Ordering::Relaxed) + 1,
+            };
             let ep = ep.clone();
             let local_addr = local_addr.clone();
             let remote_addr = remote_addr.clone();
             let scheme = scheme.clone();
+            let tls_info = tls_info.clone();
+            let options = options.clone();
+            // the request is in progress until the response is returned
+            let request_guard = activity.start_request();
+            let in_flight_guard = options
+                .in_flight_limit
+                .as_ref()
+                .map(|limit| limit.try_acquire());
             async move {
-                Ok::<http::Response<_>, Infallible>(
-                    ep.get_response((req, local_addr, remote_addr, scheme).into())
-                        .await
-                        .into(),
-                )
+                let _request_guard = request_guard;
+                if let Some(None) = in_flight_guard {
+                    let retry_after = options.retry_after.unwrap_or(Duration::from_secs(1));
+                    let resp = Response::builder()
+                        .status(StatusCode::SERVICE_UNAVAILABLE)
+                        .header(header::RETRY_AFTER, retry_after.as_secs())
+                        .finish();
+                    return Ok(resp.into());
+                }
+                let _in_flight_guard = in_flight_guard;
+                let mut req: Request = (req, local_addr, remote_addr, scheme).into();
+                req.extensions_mut().insert(connection);
+                if let Some(tls_info) = &tls_info {
+                    if let Some(info) = tls_info.info() {
+                        req.extensions_mut().insert(info.clone());
+                    }
+                    if let Some(client_cert) = tls_info.client_cert() {
+                        req.extensions_mut().insert(client_cert.clone());
+                    }
+                    if let Some(alpn_protocol) = tls_info.alpn_protocol() {
+                        req.extensions_mut().insert(alpn_protocol.clone());
+                    }
+                }
+                let resp = match options.request_timeout {
+                    Some(timeout) => {
+                        let flag = RequestTimeoutFlag::default();
+                        req.extensions_mut().insert(flag.clone());
+                        let resp = ep.get_response(req);
+                        tokio::pin!(resp);
+                        tokio::select! {
+                            resp = &mut resp => resp,
+                            _ = tokio::time::sleep(timeout) => {
+                                if flag.is_disabled() {
+                                    resp.await
+                                } else {
+                                    match &options.request_timeout_response {
+                                        Some(f) => f(),
+                                        None => StatusCode::SERVICE_UNAVAILABLE.into(),
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    None => ep.get_response(req).await,
+                };
+                Ok::<http::Response<_>, Infallible>(resp.into())
             }
         }
     });
 
-    let conn = Http::new()
-        .serve_connection(socket, service)
-        .with_upgrades();
+    let mut http = Http::new();
+    if let Some(timeout) = header_read_timeout {
+        http.http1_header_read_timeout(timeout);
+    }
+    let conn = http.serve_connection(socket, service).with_upgrades();
+    tokio::pin!(conn);
+
+    tokio::select! {
+        _ = &mut conn => return,
+        _ = wait_for(shutdown_rx) => {}
+        _ = wait_idle(&activity, idle_timeout) => {}
+    }
+
+    // finish the in-flight request, and close the connection
+    conn.as_mut().graceful_shutdown();
     let _ = conn.await;
 }
+
+/// Tracks the requests in progress and the last time data was received or
+/// sent on a connection.
+struct Activity {
+    started_at: Instant,
+    // milliseconds since `started_at`
+    last_active: AtomicU64,
+    requests: AtomicUsize,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            last_active: AtomicU64::new(0),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        let elapsed = self.started_at.elapsed().as_millis() as u64;
+        self.last_active.store(elapsed, Ordering::Relaxed);
+    }
+
+    fn start_request(self: &Arc<Self>) -> RequestGuard {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        RequestGuard(self.clone())
+    }
+
+    /// Returns how long the connection has been idle, or `None` if there are
+    /// requests in progress.
+    fn idle_duration(&self) -> Option<Duration> {
+        if self.requests.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        let last_active = Duration::from_millis(self.last_active.load(Ordering::Relaxed));
+        Some(self.started_at.elapsed().saturating_sub(last_active))
+    }
+}
+
+/// Limits the number of the requests in progress across all connections.
+struct InFlightLimit {
+    max: usize,
+    current: AtomicUsize,
+}
+
+impl InFlightLimit {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns `None` if the limit is reached.
+    fn try_acquire(self: &Arc<Self>) -> Option<InFlightGuard> {
+        if self.current.fetch_add(1, Ordering::SeqCst) >= self.max {
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(InFlightGuard(self.clone()))
+    }
+}
+
+/// Releases an in-flight request when dropped.
+struct InFlightGuard(Arc<InFlightLimit>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Marks the end of a request when dropped.
+struct RequestGuard(Arc<Activity>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.touch();
+        self.0.requests.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Waits until the connection has been idle for `timeout`, or forever if the
+/// timeout is not set.
+async fn wait_idle(activity: &Activity, timeout: Option<Duration>) {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return futures_util::future::pending().await,
+    };
+
+    loop {
+        match activity.idle_duration() {
+            Some(idle) if idle >= timeout => return,
+            Some(idle) => tokio::time::sleep(timeout - idle).await,
+            None => tokio::time::sleep(timeout).await,
+        }
+    }
+}
+
+/// A IO stream which records the activity of the connection.
+struct ActivityStream<S> {
+    inner: S,
+    activity: Arc<Activity>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ActivityStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if res.is_ready() {
+            self.activity.touch();
+        }
+        res
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ActivityStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if res.is_ready() {
+            self.activity.touch();
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Waits until the value of the channel becomes `true`.
+async fn wait_for(mut rx: watch::Receiver<bool>) {
+    while !*rx.borrow_and_update() {
+        if rx.changed().await.is_err() {
+            futures_util::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::oneshot,
+    };
+
+    use super::*;
+    use crate::{
+        handler,
+        listener::{TcpAcceptor, TcpListener},
+        middleware::DisableRequestTimeout,
+        IntoResponse, Route,
+    };
+
+    async fn start_server(
+        timeout: Option<Duration>,
+    ) -> (
+        std::net::SocketAddr,
+        oneshot::Sender<()>,
+        tokio::task::JoinHandle<()>,
+        Arc<Mutex<Vec<usize>>>,
+    ) {
+        #[handler(internal)]
+        async fn index(req: &Request) -> &'static str {
+            if req.uri().path() == "/slow" {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            "hello"
+        }
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        let (tx, rx) = oneshot::channel();
+        let draining = Arc::new(Mutex::new(Vec::new()));
+
+        let server = Server::new_with_acceptor(acceptor).on_draining({
+            let draining = draining.clone();
+            move |remaining| draining.lock().push(remaining)
+        });
+        let handle = tokio::spawn(async move {
+            server
+                .run_with_graceful_shutdown(
+                    index,
+                    async move {
+                        let _ = rx.await;
+                    },
+                    timeout,
+                )
+                .await
+                .unwrap();
+        });
+        (addr, tx, handle, draining)
+    }
+
+    #[tokio::test]
+    async fn close_idle_connections() {
+        let (addr, tx, handle, draining) = start_server(None).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(String::from_utf8_lossy(&buf[..n]).ends_with("hello"));
+
+        // the keep-alive connection is idle now
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*draining.lock(), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn force_close_after_timeout() {
+        let (addr, tx, handle, draining) = start_server(Some(Duration::from_millis(100))).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*draining.lock(), vec![1, 0]);
+    }
+
+    async fn start_server_with_options(
+        f: impl FnOnce(Server<Infallible, TcpAcceptor>) -> Server<Infallible, TcpAcceptor>,
+    ) -> std::net::SocketAddr {
+        #[handler(internal)]
+        fn index() -> &'static str {
+            "hello"
+        }
+
+        #[handler(internal)]
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            "hello"
+        }
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        let server = f(Server::new_with_acceptor(acceptor));
+        let app = Route::new()
+            .at("/", index)
+            .at("/slow", slow)
+            .at("/poll", slow.with(DisableRequestTimeout));
+        tokio::spawn(server.run(app));
+        addr
+    }
+
+    async fn send_request(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nhost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn idle_timeout() {
+        let addr =
+            start_server_with_options(|server| server.idle_timeout(Duration::from_millis(200)))
+                .await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(String::from_utf8_lossy(&buf[..n]).ends_with("hello"));
+        }
+
+        let mut buf = [0; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(n, 0);
+    }
+
+    #[tokio::test]
+    async fn header_read_timeout() {
+        let addr = start_server_with_options(|server| {
+            server.header_read_timeout(Duration::from_millis(100))
+        })
+        .await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let mut buf = [0; 1024];
+        let res = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .unwrap();
+        assert!(matches!(res, Ok(0) | Err(_)));
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        let addr =
+            start_server_with_options(|server| server.request_timeout(Duration::from_millis(100)))
+                .await;
+
+        assert!(send_request(addr, "/").await.ends_with("hello"));
+        assert!(send_request(addr, "/slow")
+            .await
+            .starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(send_request(addr, "/poll").await.ends_with("hello"));
+
+        let addr = start_server_with_options(|server| {
+            server
+                .request_timeout(Duration::from_millis(100))
+                .request_timeout_response(|| StatusCode::GATEWAY_TIMEOUT.into_response())
+        })
+        .await;
+        assert!(send_request(addr, "/slow")
+            .await
+            .starts_with("HTTP/1.1 504 Gateway Timeout"));
+    }
+
+    #[tokio::test]
+    async fn max_in_flight_requests() {
+        let addr = start_server_with_options(|server| {
+            server
+                .max_in_flight_requests(1)
+                .load_shedding_retry_after(Duration::from_secs(5))
+        })
+        .await;
+
+        let slow = tokio::spawn(send_request(addr, "/slow"));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let resp = send_request(addr, "/").await;
+        assert!(resp.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(resp.contains("retry-after: 5\r\n"));
+
+        assert!(slow.await.unwrap().ends_with("hello"));
+        assert!(send_request(addr, "/").await.ends_with("hello"));
+    }
+
+    #[tokio::test]
+    async fn connection_info() {
+        #[handler(internal)]
+        fn index(conn: crate::web::ConnectionInfo) -> String {
+            format!("{}:{}", conn.id(), conn.requests())
+        }
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(index));
+
+        async fn send(stream: &mut TcpStream) -> (u64, u64) {
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            let resp = String::from_utf8_lossy(&buf[..n]).into_owned();
+            let (id, requests) = resp.rsplit("\r\n").next().unwrap().split_once(':').unwrap();
+            (id.parse().unwrap(), requests.parse().unwrap())
+        }
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let (id, requests) = send(&mut stream).await;
+        assert_eq!(requests, 1);
+        assert_eq!(send(&mut stream).await, (id, 2));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let (other_id, requests) = send(&mut stream).await;
+        assert_ne!(other_id, id);
+        assert_eq!(requests, 1);
+    }
+}
@@ -128,6 +128,34 @@ impl<'a, E> TestRequestBuilder<'a, E> {
             .body(quick_xml::se::to_string(&body).expect("valid xml"))
     }
 
+    /// Sets the MessagePack body for this request with `application/msgpack`
+    /// content type.
+    #[cfg(feature = "msgpack")]
+    #[must_use]
+    pub fn body_msgpack(self, body: &impl Serialize) -> Self {
+        self.content_type("application/msgpack")
+            .body(rmp_serde::to_vec_named(&body).expect("valid msgpack"))
+    }
+
+    /// Sets the CBOR body for this request with `application/cbor` content
+    /// type.
+    #[cfg(feature = "cbor")]
+    #[must_use]
+    pub fn body_cbor(self, body: &impl Serialize) -> Self {
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&body, &mut data).expect("valid cbor");
+        self.content_type("application/cbor").body(data)
+    }
+
+    /// Sets the Protocol Buffers body for this request with
+    /// `application/x-protobuf` content type.
+    #[cfg(feature = "protobuf")]
+    #[must_use]
+    pub fn body_protobuf(self, body: &impl prost::Message) -> Self {
+        self.content_type("application/x-protobuf")
+            .body(body.encode_to_vec())
+    }
+
     /// Sets the form data for this request with
     /// `application/x-www-form-urlencoded` content type.
     #[must_use]
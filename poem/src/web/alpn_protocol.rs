@@ -0,0 +1,43 @@
+use std::fmt::{self, Display, Formatter};
+
+/// The application protocol negotiated with ALPN in the TLS handshake, such
+/// as `h2` or `http/1.1`.
+///
+/// It is added to the request extensions if the connection is accepted by a
+/// TLS listener and the client has negotiated a protocol.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, web::AlpnProtocol, Request};
+///
+/// #[handler]
+/// fn index(req: &Request) -> String {
+///     match req.extensions().get::<AlpnProtocol>() {
+///         Some(protocol) => format!("negotiated {}", protocol),
+///         None => "not negotiated".to_string(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AlpnProtocol(pub Vec<u8>);
+
+impl AlpnProtocol {
+    /// Returns `true` if the negotiated protocol is HTTP/2.
+    #[inline]
+    pub fn is_h2(&self) -> bool {
+        self.0 == b"h2"
+    }
+
+    /// Returns the protocol name.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Display for AlpnProtocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
@@ -0,0 +1,38 @@
+use std::ops::Deref;
+
+use crate::{error::GetDataError, FromRequest, Request, RequestBody, Result};
+
+/// An extractor that gets the API version resolved by
+/// [`VersionedRoute`](crate::VersionedRoute).
+///
+/// # Errors
+///
+/// - [`GetDataError`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ApiVersion(pub(crate) String);
+
+impl ApiVersion {
+    /// Returns the version as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for ApiVersion {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ApiVersion {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<ApiVersion>()
+            .cloned()
+            .ok_or_else(|| GetDataError(std::any::type_name::<ApiVersion>()))?)
+    }
+}
@@ -0,0 +1,34 @@
+use crate::Request;
+
+/// The maximum size of the request body read by the binary extractors, such
+/// as `MsgPack`, `Cbor` and `Protobuf`, the default is 2 MiB.
+///
+/// Add it to the requests with [`EndpointExt::data`](crate::EndpointExt::data)
+/// to change the limit, the extractors return
+/// [`ReadBodyError::PayloadTooLarge`](crate::error::ReadBodyError::PayloadTooLarge)
+/// if the body exceeds it.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, web::BodyLimit, EndpointExt, Route};
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let app = Route::new().at("/", index).data(BodyLimit(64 * 1024));
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BodyLimit(pub usize);
+
+impl Default for BodyLimit {
+    fn default() -> Self {
+        Self(2 * 1024 * 1024)
+    }
+}
+
+impl BodyLimit {
+    pub(crate) fn of(req: &Request) -> usize {
+        req.data::<BodyLimit>().copied().unwrap_or_default().0
+    }
+}
@@ -0,0 +1,212 @@
+use std::ops::{Deref, DerefMut};
+
+use http::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::{MissingCborContentTypeError, ParseCborError},
+    http::header,
+    web::{BodyLimit, RequestBody},
+    FromRequest, IntoResponse, Request, Response, Result,
+};
+
+/// CBOR extractor and response.
+///
+/// To extract the specified type of CBOR from the body, `T` must
+/// implement [`serde::Deserialize`]. The `Content-Type` of the request must
+/// be `application/cbor` or `application/*+cbor`, and the size of the body
+/// is limited by [`BodyLimit`].
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`MissingCborContentTypeError`]
+/// - [`ParseCborError`]
+///
+/// ```
+/// use poem::{handler, post, test::TestClient, web::Cbor, Route};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[handler]
+/// async fn index(Cbor(user): Cbor<User>) -> String {
+///     format!("welcome {}!", user.name)
+/// }
+///
+/// let app = Route::new().at("/", post(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .body_cbor(&User {
+///         name: "foo".to_string(),
+///     })
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("welcome foo!").await;
+/// # });
+/// ```
+///
+/// # Response
+///
+/// To serialize the specified type to CBOR, `T` must implement
+/// [`serde::Serialize`].
+///
+/// ```
+/// use poem::{get, handler, test::TestClient, web::Cbor, Route};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[handler]
+/// async fn index() -> Cbor<User> {
+///     Cbor(User {
+///         name: "foo".to_string(),
+///     })
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_content_type("application/cbor");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Cbor<T>(pub T);
+
+impl<T> Deref for Cbor<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Cbor<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, T: DeserializeOwned> FromRequest<'a> for Cbor<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        if is_cbor_content_type(req) {
+            let data = body.take()?.into_bytes_limit(BodyLimit::of(req)).await?;
+            Ok(Self(
+                ciborium::de::from_reader(&*data).map_err(ParseCborError)?,
+            ))
+        } else {
+            Err(MissingCborContentTypeError.into())
+        }
+    }
+}
+
+fn is_cbor_content_type(req: &Request) -> bool {
+    matches!(
+        req
+            .header(header::CONTENT_TYPE)
+            .and_then(|value| value.parse::<mime::Mime>().ok()),
+        Some(content_type)
+            if content_type.type_() == "application"
+                && (content_type.subtype() == "cbor"
+                    || content_type.suffix().map(|v| v.as_str()) == Some("cbor")))
+}
+
+impl<T: Serialize + Send> IntoResponse for Cbor<T> {
+    fn into_response(self) -> Response {
+        let mut data = Vec::new();
+        if let Err(err) = ciborium::ser::into_writer(&self.0, &mut data) {
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(err.to_string());
+        }
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/cbor")
+            .body(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[derive(Deserialize, Serialize, Debug, Eq, PartialEq)]
+    struct CreateResource {
+        name: String,
+        value: i32,
+    }
+
+    fn resource() -> CreateResource {
+        CreateResource {
+            name: "abc".to_string(),
+            value: 100,
+        }
+    }
+
+    #[handler(internal)]
+    async fn echo(data: Cbor<CreateResource>) -> Cbor<CreateResource> {
+        data
+    }
+
+    #[tokio::test]
+    async fn test_cbor() {
+        let cli = TestClient::new(echo);
+        let resp = cli.post("/").body_cbor(&resource()).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("application/cbor");
+        let data = resp.0.into_body().into_vec().await.unwrap();
+        assert_eq!(
+            ciborium::de::from_reader::<CreateResource, _>(&*data).unwrap(),
+            resource()
+        );
+
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&resource(), &mut data).unwrap();
+        let resp = cli
+            .post("/")
+            .content_type("application/example+cbor")
+            .body(data)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn test_cbor_extractor_fail() {
+        let cli = TestClient::new(echo.data(BodyLimit(8)));
+        cli.post("/")
+            .content_type("application/json")
+            .body(r#"{"name":"abc","value":100}"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        cli.post("/")
+            .content_type("application/cbor")
+            .body(vec![0xff, 0x00])
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.post("/")
+            .body_cbor(&resource())
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
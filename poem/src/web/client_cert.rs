@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use crate::{error::MissingClientCertError, FromRequest, Request, RequestBody, Result};
+
+/// An extractor that can extracts the certificate chain presented by the
+/// client in the TLS handshake.
+///
+/// The certificate chain is only available if the client authentication is
+/// enabled in the TLS config, for example with
+/// [`RustlsConfig::client_auth_required`](crate::listener::RustlsConfig::client_auth_required),
+/// and the client has sent a certificate that passed the verification. Use
+/// `Option<ClientCert>` if the client authentication is optional.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, web::ClientCert};
+///
+/// #[handler]
+/// fn index(client_cert: ClientCert) -> String {
+///     format!("{} bytes", client_cert.leaf().len())
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClientCert(Arc<Vec<Vec<u8>>>);
+
+impl ClientCert {
+    /// Create a client certificate from a DER-encoded certificate chain, the
+    /// first certificate is the end-entity certificate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chain is empty.
+    pub fn new(chain: Vec<Vec<u8>>) -> Self {
+        assert!(!chain.is_empty(), "the certificate chain is empty");
+        Self(Arc::new(chain))
+    }
+
+    /// Returns the DER-encoded certificate chain, the first certificate is
+    /// the end-entity certificate.
+    #[inline]
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.0
+    }
+
+    /// Returns the DER-encoded end-entity certificate.
+    #[inline]
+    pub fn leaf(&self) -> &[u8] {
+        &self.0[0]
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ClientCert {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        req.extensions()
+            .get::<ClientCert>()
+            .cloned()
+            .ok_or_else(|| MissingClientCertError.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient};
+
+    #[tokio::test]
+    async fn extract_client_cert() {
+        #[handler(internal)]
+        fn index(client_cert: ClientCert) -> String {
+            format!("{:?}", client_cert.chain())
+        }
+
+        let cli = TestClient::new(index);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        let client_cert = ClientCert::new(vec![vec![1, 2], vec![3]]);
+        assert_eq!(client_cert.leaf(), &[1, 2]);
+        cli.get("/")
+            .data(client_cert)
+            .send()
+            .await
+            .assert_text("[[1, 2], [3]]")
+            .await;
+    }
+}
@@ -0,0 +1,470 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+};
+
+use rfc7239::{NodeIdentifier, NodeName};
+
+use crate::{Addr, FromRequest, Request, RequestBody, Result};
+
+/// An IP network in the CIDR notation, such as `10.0.0.0/8`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl FromStr for IpNet {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.trim().split_once('/') {
+            Some((addr, prefix_len)) => {
+                let addr = addr.parse::<IpAddr>().map_err(|_| ())?;
+                (addr, prefix_len.parse::<u8>().map_err(|_| ())?)
+            }
+            None => {
+                let addr = s.trim().parse::<IpAddr>().map_err(|_| ())?;
+                (addr, max_prefix_len(&addr))
+            }
+        };
+        if prefix_len > max_prefix_len(&addr) {
+            return Err(());
+        }
+        match normalize(addr) {
+            IpAddr::V4(v4) if addr.is_ipv6() && prefix_len >= 96 => Ok(IpNet {
+                addr: IpAddr::V4(v4),
+                prefix_len: prefix_len - 96,
+            }),
+            _ => Ok(IpNet { addr, prefix_len }),
+        }
+    }
+}
+
+impl IpNet {
+    pub(crate) fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, normalize(*addr)) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                prefix_eq(&net.octets(), &addr.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                prefix_eq(&net.octets(), &addr.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn max_prefix_len(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Converts the IPv4-mapped IPv6 addresses, such as `::ffff:10.0.0.1`, to
+/// IPv4 addresses.
+fn normalize(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match v6.octets() {
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => {
+                IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+            }
+            _ => addr,
+        },
+        IpAddr::V4(_) => addr,
+    }
+}
+
+fn prefix_eq(a: &[u8], b: &[u8], prefix_len: u8) -> bool {
+    let bytes = (prefix_len / 8) as usize;
+    let bits = prefix_len % 8;
+    if a[..bytes] != b[..bytes] {
+        return false;
+    }
+    bits == 0 || {
+        let mask = 0xffu8 << (8 - bits);
+        a[bytes] & mask == b[bytes] & mask
+    }
+}
+
+/// Parses the network in the CIDR notation.
+///
+/// # Panics
+///
+/// Panics if the network is invalid.
+pub(crate) fn parse_network(network: &str) -> IpNet {
+    network
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid network `{}`", network))
+}
+
+/// The header which the trusted reverse proxies forward the client addresses
+/// with, see [`TrustedProxies::forwarded_header`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ForwardedHeader {
+    /// The `Forwarded` header defined by
+    /// [RFC 7239](https://datatracker.ietf.org/doc/html/rfc7239).
+    Forwarded,
+
+    /// The `X-Forwarded-For` header.
+    XForwardedFor,
+
+    /// The `X-Real-IP` header, which contains a single address.
+    XRealIp,
+}
+
+impl Default for ForwardedHeader {
+    fn default() -> Self {
+        Self::XForwardedFor
+    }
+}
+
+/// The networks of the trusted reverse proxies, which are used by
+/// [`ClientIp`] to resolve the client IP.
+///
+/// Add it to the requests with [`EndpointExt::data`](crate::EndpointExt::data)
+/// to configure the [`ClientIp`] extractor, and the
+/// [`IpFilter`](crate::middleware::IpFilter) and
+/// [`RateLimit`](crate::middleware::RateLimit) middlewares.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     web::{ForwardedHeader, TrustedProxies},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = Route::new().at("/", index).data(
+///     TrustedProxies::new()
+///         .trust("10.0.0.0/8")
+///         .trust("2001:db8::/32")
+///         .forwarded_header(ForwardedHeader::Forwarded),
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+    header: ForwardedHeader,
+}
+
+impl TrustedProxies {
+    /// Create an empty `TrustedProxies`, which trusts no proxies.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Trusts the proxies in the specified network, which is in the CIDR
+    /// notation, such as `10.0.0.0/8`, or a single address.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the network is invalid.
+    #[must_use]
+    pub fn trust(mut self, network: impl AsRef<str>) -> Self {
+        self.networks.push(parse_network(network.as_ref()));
+        self
+    }
+
+    /// Sets the header which the trusted proxies forward the client addresses
+    /// with, default is [`ForwardedHeader::XForwardedFor`].
+    ///
+    /// The other forwarding headers are ignored, because the proxies usually
+    /// pass them through unchanged, so the clients can forge them.
+    #[must_use]
+    pub fn forwarded_header(self, header: ForwardedHeader) -> Self {
+        Self { header, ..self }
+    }
+
+    /// Returns `true` if the address is a trusted proxy.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(addr))
+    }
+}
+
+/// An extractor that resolves the client IP, and is aware of the trusted
+/// reverse proxies.
+///
+/// If the remote peer is a proxy in the [`TrustedProxies`] of the request,
+/// the addresses forwarded by the header set with
+/// [`TrustedProxies::forwarded_header`] are walked from the nearest one, and
+/// the first address which is not a trusted proxy is the client IP. If all
+/// of them are trusted proxies, the farthest one is the client IP. Otherwise,
+/// the client IP is the address of the remote peer, and the headers are
+/// ignored, since they can be forged by the clients.
+///
+/// It is `None` if the remote peer is not an internet socket, such as a Unix
+/// domain socket.
+///
+/// Unlike [`RealIp`](crate::web::RealIp), it is safe to be used for the
+/// access control.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     test::TestClient,
+///     web::{ClientIp, TrustedProxies},
+///     EndpointExt,
+/// };
+///
+/// #[handler]
+/// fn index(ClientIp(ip): ClientIp) -> String {
+///     format!("{:?}", ip)
+/// }
+///
+/// let app = index.data(TrustedProxies::new().trust("10.0.0.0/8"));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// // the remote address of the test client is unknown, so the header is ignored
+/// let resp = TestClient::new(app)
+///     .get("/")
+///     .header("x-forwarded-for", "203.0.113.195")
+///     .send()
+///     .await;
+/// resp.assert_text("None").await;
+/// # });
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl ClientIp {
+    pub(crate) fn resolve(req: &Request) -> Option<IpAddr> {
+        let peer = match &req.remote_addr().0 {
+            Addr::SocketAddr(addr) => normalize(addr.ip()),
+            _ => return None,
+        };
+        let proxies = match req.data::<TrustedProxies>() {
+            Some(proxies) if proxies.contains(&peer) => proxies,
+            _ => return Some(peer),
+        };
+
+        let mut client = peer;
+        for hop in forwarded_chain(req, proxies.header).into_iter().rev() {
+            match hop {
+                Some(ip) => {
+                    client = normalize(ip);
+                    if !proxies.contains(&client) {
+                        break;
+                    }
+                }
+                // an obfuscated or unknown address, the nearest known one is used
+                None => break,
+            }
+        }
+        Some(client)
+    }
+}
+
+/// Returns the addresses forwarded by the header, from the farthest to the
+/// nearest one.
+fn forwarded_chain(req: &Request, header: ForwardedHeader) -> Vec<Option<IpAddr>> {
+    match header {
+        ForwardedHeader::Forwarded => req
+            .headers()
+            .get_all("forwarded")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(rfc7239::parse)
+            // a malformed element is treated as an unknown address
+            .map(|item| match item {
+                Ok(item) => item.forwarded_for,
+                Err(_) => None,
+            })
+            .map(|node| match node {
+                Some(NodeIdentifier {
+                    name: NodeName::Ip(ip),
+                    ..
+                }) => Some(ip),
+                _ => None,
+            })
+            .collect(),
+        ForwardedHeader::XForwardedFor => req
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|value| value.trim().parse::<IpAddr>().ok())
+            .collect(),
+        ForwardedHeader::XRealIp => req
+            .headers()
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| vec![value.trim().parse::<IpAddr>().ok()])
+            .unwrap_or_default(),
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ClientIp {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(ClientIp(Self::resolve(req)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::*;
+    use crate::web::RemoteAddr;
+
+    fn resolve(peer: &str, headers: &[(&str, &str)]) -> Option<IpAddr> {
+        resolve_with(ForwardedHeader::XForwardedFor, peer, headers)
+    }
+
+    fn resolve_with(
+        header: ForwardedHeader,
+        peer: &str,
+        headers: &[(&str, &str)],
+    ) -> Option<IpAddr> {
+        let mut req = Request::builder();
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let mut req = req.finish();
+        req.state_mut().remote_addr = RemoteAddr(Addr::SocketAddr(SocketAddr::new(
+            peer.parse().unwrap(),
+            8000,
+        )));
+        req.set_data(
+            TrustedProxies::new()
+                .trust("10.0.0.0/8")
+                .trust("2001:db8::/32")
+                .forwarded_header(header),
+        );
+        ClientIp::resolve(&req)
+    }
+
+    #[test]
+    fn test_ip_net() {
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains(&"10.1.2.3".parse().unwrap()));
+        assert!(net.contains(&"::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!net.contains(&"11.0.0.0".parse().unwrap()));
+
+        let net: IpNet = "192.168.1.128/25".parse().unwrap();
+        assert!(net.contains(&"192.168.1.200".parse().unwrap()));
+        assert!(!net.contains(&"192.168.1.127".parse().unwrap()));
+
+        let net: IpNet = "2001:db8::/32".parse().unwrap();
+        assert!(net.contains(&"2001:db8:1::1".parse().unwrap()));
+        assert!(!net.contains(&"2001:db9::1".parse().unwrap()));
+        assert!(!net.contains(&"10.0.0.1".parse().unwrap()));
+
+        let net: IpNet = "::ffff:10.0.0.0/104".parse().unwrap();
+        assert!(net.contains(&"10.2.3.4".parse().unwrap()));
+
+        let net: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(net.contains(&"1.2.3.4".parse().unwrap()));
+
+        let net: IpNet = "1.2.3.4".parse().unwrap();
+        assert!(net.contains(&"1.2.3.4".parse().unwrap()));
+        assert!(!net.contains(&"1.2.3.5".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("10.0.0/8".parse::<IpNet>().is_err());
+        assert!("10.0.0.0/a".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        assert_eq!(
+            resolve("1.2.3.4", &[("x-forwarded-for", "5.6.7.8")]),
+            Some("1.2.3.4".parse().unwrap())
+        );
+        assert_eq!(
+            resolve("::ffff:1.2.3.4", &[("x-real-ip", "5.6.7.8")]),
+            Some("1.2.3.4".parse().unwrap())
+        );
+
+        // no trusted proxies
+        let mut req = Request::builder().header("x-real-ip", "5.6.7.8").finish();
+        req.state_mut().remote_addr =
+            RemoteAddr(Addr::SocketAddr("10.0.0.1:8000".parse().unwrap()));
+        assert_eq!(ClientIp::resolve(&req), Some("10.0.0.1".parse().unwrap()));
+
+        assert_eq!(ClientIp::resolve(&Request::default()), None);
+    }
+
+    #[test]
+    fn trusted_peer() {
+        // the spoofed address on the left is ignored
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[("x-forwarded-for", "9.9.9.9, 1.2.3.4, 10.0.0.2")]
+            ),
+            Some("1.2.3.4".parse().unwrap())
+        );
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[
+                    ("x-forwarded-for", "9.9.9.9"),
+                    ("x-forwarded-for", "1.2.3.4, 10.0.0.2")
+                ]
+            ),
+            Some("1.2.3.4".parse().unwrap())
+        );
+        assert_eq!(
+            resolve_with(
+                ForwardedHeader::Forwarded,
+                "2001:db8::1",
+                &[(
+                    "forwarded",
+                    r#"for=9.9.9.9, for="[2001:db9:cafe::17]:4711", for=10.0.0.2"#
+                )]
+            ),
+            Some("2001:db9:cafe::17".parse().unwrap())
+        );
+        assert_eq!(
+            resolve_with(
+                ForwardedHeader::XRealIp,
+                "10.0.0.1",
+                &[("x-real-ip", "1.2.3.4")]
+            ),
+            Some("1.2.3.4".parse().unwrap())
+        );
+        // only the configured header is trusted
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[
+                    ("forwarded", "for=9.9.9.9"),
+                    ("x-real-ip", "9.9.9.9"),
+                    ("x-forwarded-for", "1.2.3.4")
+                ]
+            ),
+            Some("1.2.3.4".parse().unwrap())
+        );
+        assert_eq!(
+            resolve("10.0.0.1", &[("forwarded", "for=9.9.9.9")]),
+            Some("10.0.0.1".parse().unwrap())
+        );
+
+        // all of them are trusted proxies
+        assert_eq!(
+            resolve("10.0.0.1", &[("x-forwarded-for", "10.0.0.3, 10.0.0.2")]),
+            Some("10.0.0.3".parse().unwrap())
+        );
+        // an unknown address
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[("x-forwarded-for", "1.2.3.4, unknown, 10.0.0.2")]
+            ),
+            Some("10.0.0.2".parse().unwrap())
+        );
+        assert_eq!(resolve("10.0.0.1", &[]), Some("10.0.0.1".parse().unwrap()));
+    }
+}
@@ -13,15 +13,19 @@ use crate::{
 
 /// The compression algorithms.
 #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum CompressionAlgo {
-    // /// brotli
-    // BR,
+    /// brotli
+    BR,
+
     /// deflate
     DEFLATE,
 
     /// gzip
     GZIP,
+
+    /// zstd
+    ZSTD,
 }
 
 impl FromStr for CompressionAlgo {
@@ -29,38 +33,89 @@ impl FromStr for CompressionAlgo {
 
     fn from_str(s: &str) -> std::prelude::rust_2015::Result<Self, Self::Err> {
         Ok(match s {
-            // "br" => CompressionAlgo::BR,
+            "br" => CompressionAlgo::BR,
             "deflate" => CompressionAlgo::DEFLATE,
             "gzip" => CompressionAlgo::GZIP,
+            "zstd" => CompressionAlgo::ZSTD,
             _ => return Err(()),
         })
     }
 }
 
+/// The quality of the compression.
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CompressionLevel {
+    /// The fastest compression, usually produces the bigger size.
+    Fastest,
+
+    /// The best compression, usually produces the smallest size.
+    Best,
+
+    /// The default quality of the algorithm.
+    Default,
+
+    /// The precise quality of the algorithm, such as `0-11` for brotli, `1-9`
+    /// for gzip and deflate, and `1-22` for zstd. It is clamped to the maximum
+    /// of the algorithm.
+    Precise(u32),
+}
+
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+impl From<CompressionLevel> for async_compression::Level {
+    fn from(level: CompressionLevel) -> Self {
+        match level {
+            CompressionLevel::Fastest => async_compression::Level::Fastest,
+            CompressionLevel::Best => async_compression::Level::Best,
+            CompressionLevel::Default => async_compression::Level::Default,
+            CompressionLevel::Precise(quality) => async_compression::Level::Precise(quality),
+        }
+    }
+}
+
 impl CompressionAlgo {
     pub(crate) fn as_str(&self) -> &'static str {
         match self {
-            // CompressionAlgo::BR => "br",
+            CompressionAlgo::BR => "br",
             CompressionAlgo::DEFLATE => "deflate",
             CompressionAlgo::GZIP => "gzip",
+            CompressionAlgo::ZSTD => "zstd",
         }
     }
 
+    #[cfg(test)]
     pub(crate) fn compress<'a>(
         &self,
         reader: impl AsyncRead + Send + Unpin + 'a,
     ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        self.compress_with_quality(reader, CompressionLevel::Default)
+    }
+
+    pub(crate) fn compress_with_quality<'a>(
+        &self,
+        reader: impl AsyncRead + Send + Unpin + 'a,
+        level: CompressionLevel,
+    ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        let reader = BufReader::new(reader);
+        let level = level.into();
         match self {
-            // CompressionAlgo::BR =>
-            // Box::pin(async_compression::tokio::bufread::BrotliEncoder::new(
-            //     BufReader::new(reader),
-            // )),
+            CompressionAlgo::BR => Box::pin(
+                async_compression::tokio::bufread::BrotliEncoder::with_quality(reader, level),
+            ),
             CompressionAlgo::DEFLATE => Box::pin(
-                async_compression::tokio::bufread::DeflateEncoder::new(BufReader::new(reader)),
+                async_compression::tokio::bufread::DeflateEncoder::with_quality(reader, level),
+            ),
+            CompressionAlgo::GZIP => Box::pin(
+                async_compression::tokio::bufread::GzipEncoder::with_quality(reader, level),
+            ),
+            CompressionAlgo::ZSTD => Box::pin(
+                async_compression::tokio::bufread::ZstdEncoder::with_quality(reader, level),
             ),
-            CompressionAlgo::GZIP => Box::pin(async_compression::tokio::bufread::GzipEncoder::new(
-                BufReader::new(reader),
-            )),
         }
     }
 
@@ -68,17 +123,20 @@ impl CompressionAlgo {
         &self,
         reader: impl AsyncRead + Send + Unpin + 'a,
     ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        let reader = BufReader::new(reader);
         match self {
-            // CompressionAlgo::BR =>
-            // Box::pin(async_compression::tokio::bufread::BrotliDecoder::new(
-            //     BufReader::new(reader),
-            // )),
+            CompressionAlgo::BR => Box::pin(async_compression::tokio::bufread::BrotliDecoder::new(
+                reader,
+            )),
             CompressionAlgo::DEFLATE => Box::pin(
-                async_compression::tokio::bufread::DeflateDecoder::new(BufReader::new(reader)),
+                async_compression::tokio::bufread::DeflateDecoder::new(reader),
             ),
-            CompressionAlgo::GZIP => Box::pin(async_compression::tokio::bufread::GzipDecoder::new(
-                BufReader::new(reader),
-            )),
+            CompressionAlgo::GZIP => {
+                Box::pin(async_compression::tokio::bufread::GzipDecoder::new(reader))
+            }
+            CompressionAlgo::ZSTD => {
+                Box::pin(async_compression::tokio::bufread::ZstdDecoder::new(reader))
+            }
         }
     }
 }
@@ -109,12 +167,23 @@ impl Display for CompressionAlgo {
 pub struct Compress<T> {
     inner: T,
     algo: CompressionAlgo,
+    level: CompressionLevel,
 }
 
 impl<T> Compress<T> {
-    /// /// Create a compressed response using the specified algorithm.
+    /// Create a compressed response using the specified algorithm.
     pub fn new(inner: T, algo: CompressionAlgo) -> Self {
-        Self { inner, algo }
+        Self {
+            inner,
+            algo,
+            level: CompressionLevel::Default,
+        }
+    }
+
+    /// Specify the quality of the compression.
+    #[must_use]
+    pub fn with_quality(self, level: CompressionLevel) -> Self {
+        Self { level, ..self }
     }
 }
 
@@ -130,7 +199,8 @@ impl<T: IntoResponse> IntoResponse for Compress<T> {
         resp.headers_mut().remove(header::CONTENT_LENGTH);
 
         resp.set_body(Body::from_async_read(
-            self.algo.compress(body.into_async_read()),
+            self.algo
+                .compress_with_quality(body.into_async_read(), self.level),
         ));
         resp
     }
@@ -176,8 +246,33 @@ mod tests {
 
     #[tokio::test]
     async fn test_compress() {
-        // test_algo(CompressionAlgo::BR).await;
+        test_algo(CompressionAlgo::BR).await;
         test_algo(CompressionAlgo::DEFLATE).await;
         test_algo(CompressionAlgo::GZIP).await;
+        test_algo(CompressionAlgo::ZSTD).await;
+    }
+
+    #[tokio::test]
+    async fn test_compress_with_quality() {
+        let data = "abcdefghijklmnopqrstuvwxyz1234567890".repeat(100);
+        for algo in [
+            CompressionAlgo::BR,
+            CompressionAlgo::DEFLATE,
+            CompressionAlgo::GZIP,
+            CompressionAlgo::ZSTD,
+        ] {
+            for level in [
+                CompressionLevel::Fastest,
+                CompressionLevel::Best,
+                CompressionLevel::Precise(3),
+            ] {
+                let resp = Compress::new(data.clone(), algo)
+                    .with_quality(level)
+                    .into_response();
+                let compressed = resp.into_body().into_bytes().await.unwrap();
+                assert!(compressed.len() < data.len());
+                assert_eq!(decompress_data(algo, &compressed).await, data);
+            }
+        }
     }
 }
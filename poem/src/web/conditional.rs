@@ -0,0 +1,412 @@
+use std::{collections::hash_map::DefaultHasher, hash::Hasher, str::FromStr, time::SystemTime};
+
+use headers::{
+    ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince, LastModified,
+};
+
+use crate::{
+    http::{header, Method, StatusCode},
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+/// Generates a strong `ETag` from the content.
+pub(crate) fn generate_etag(body: &[u8]) -> ETag {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(body);
+    ETag::from_str(&format!("\"{:x}-{:x}\"", body.len(), hasher.finish())).unwrap()
+}
+
+/// The result of evaluating the conditional headers, see
+/// [`ConditionalHeaders::evaluate`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Precondition {
+    /// All the preconditions are passed, the request should be processed as
+    /// usual.
+    Passed,
+    /// The representation is not modified, respond with `304 Not Modified`.
+    NotModified,
+    /// The preconditions are failed, respond with `412 Precondition Failed`.
+    Failed,
+}
+
+/// An extractor for the conditional headers `If-Match`, `If-None-Match`,
+/// `If-Modified-Since` and `If-Unmodified-Since`.
+///
+/// Use [`ConditionalHeaders::evaluate`] to evaluate the preconditions against
+/// the current representation, or respond with [`Conditional`].
+#[derive(Debug, Clone)]
+pub struct ConditionalHeaders {
+    method: Method,
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+    if_unmodified_since: Option<IfUnmodifiedSince>,
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ConditionalHeaders {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(Self {
+            method: req.method().clone(),
+            if_match: req.headers().typed_get(),
+            if_none_match: req.headers().typed_get(),
+            if_modified_since: req.headers().typed_get(),
+            if_unmodified_since: req.headers().typed_get(),
+        })
+    }
+}
+
+impl ConditionalHeaders {
+    /// Returns `true` if any conditional header is present.
+    pub fn is_conditional(&self) -> bool {
+        self.if_match.is_some()
+            || self.if_none_match.is_some()
+            || self.if_modified_since.is_some()
+            || self.if_unmodified_since.is_some()
+    }
+
+    /// Evaluates the preconditions against the `ETag` and the last modified
+    /// time of the current representation in the order defined by
+    /// [RFC 7232](https://www.rfc-editor.org/rfc/rfc7232#section-6).
+    ///
+    /// `If-Unmodified-Since` and `If-Modified-Since` are ignored if
+    /// `If-Match` and `If-None-Match` are present respectively, and a
+    /// matching `If-None-Match` results in [`Precondition::NotModified`] only
+    /// for the `GET` and `HEAD` requests.
+    pub fn evaluate(&self, etag: Option<&ETag>, last_modified: Option<SystemTime>) -> Precondition {
+        if let Some(if_match) = &self.if_match {
+            let passed = match etag {
+                Some(etag) => if_match.precondition_passes(etag),
+                None => *if_match == IfMatch::any(),
+            };
+            if !passed {
+                return Precondition::Failed;
+            }
+        } else if let (Some(if_unmodified_since), Some(last_modified)) =
+            (&self.if_unmodified_since, last_modified)
+        {
+            if !if_unmodified_since.precondition_passes(last_modified) {
+                return Precondition::Failed;
+            }
+        }
+
+        let is_get_or_head = self.method == Method::GET || self.method == Method::HEAD;
+        if let Some(if_none_match) = &self.if_none_match {
+            let passed = match etag {
+                Some(etag) => if_none_match.precondition_passes(etag),
+                None => *if_none_match != IfNoneMatch::any(),
+            };
+            if !passed {
+                return if is_get_or_head {
+                    Precondition::NotModified
+                } else {
+                    Precondition::Failed
+                };
+            }
+        } else if let (Some(if_modified_since), Some(last_modified), true) =
+            (&self.if_modified_since, last_modified, is_get_or_head)
+        {
+            if !if_modified_since.is_modified(last_modified) {
+                return Precondition::NotModified;
+            }
+        }
+
+        Precondition::Passed
+    }
+}
+
+/// A response that evaluates the [`ConditionalHeaders`] against the `ETag`
+/// and the last modified time of the inner response.
+///
+/// - Responds with `304 Not Modified` if the representation is not modified,
+///   the headers that would be sent with `200 OK` such as `Cache-Control` and
+///   `Vary` are preserved.
+/// - Responds with `412 Precondition Failed` if the preconditions are failed.
+/// - Otherwise responds with the inner response and the `ETag` and
+///   `Last-Modified` headers.
+///
+/// The preconditions are only evaluated if the inner response is successful.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::{header, StatusCode},
+///     test::TestClient,
+///     web::{Conditional, ConditionalHeaders},
+///     Route,
+/// };
+///
+/// #[handler]
+/// fn index(conditions: ConditionalHeaders) -> Conditional<&'static str> {
+///     let body = "hello";
+///     Conditional::new(conditions, body).etag_from_bytes(body)
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// let etag = resp.0.headers().get(header::ETAG).unwrap().clone();
+///
+/// cli.get("/")
+///     .header(header::IF_NONE_MATCH, etag)
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_MODIFIED);
+/// # });
+/// ```
+pub struct Conditional<T> {
+    conditions: ConditionalHeaders,
+    inner: T,
+    etag: Option<ETag>,
+    last_modified: Option<SystemTime>,
+}
+
+impl<T> Conditional<T> {
+    /// Create a `Conditional` response.
+    pub fn new(conditions: ConditionalHeaders, inner: T) -> Self {
+        Self {
+            conditions,
+            inner,
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    /// Sets the `ETag` of the representation.
+    #[must_use]
+    pub fn etag(self, etag: ETag) -> Self {
+        Self {
+            etag: Some(etag),
+            ..self
+        }
+    }
+
+    /// Sets the `ETag` of the representation to a strong `ETag` computed from
+    /// `data`.
+    #[must_use]
+    pub fn etag_from_bytes(self, data: impl AsRef<[u8]>) -> Self {
+        self.etag(generate_etag(data.as_ref()))
+    }
+
+    /// Sets the last modified time of the representation.
+    #[must_use]
+    pub fn last_modified(self, last_modified: SystemTime) -> Self {
+        Self {
+            last_modified: Some(last_modified),
+            ..self
+        }
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for Conditional<T> {
+    fn into_response(self) -> Response {
+        let mut resp = self.inner.into_response();
+        if !resp.status().is_success() {
+            return resp;
+        }
+
+        let etag = self.etag.or_else(|| resp.headers().typed_get::<ETag>());
+        let last_modified = self.last_modified.or_else(|| {
+            resp.headers()
+                .typed_get::<LastModified>()
+                .map(SystemTime::from)
+        });
+        if let Some(etag) = &etag {
+            resp.headers_mut().typed_insert(etag.clone());
+        }
+        if let Some(last_modified) = last_modified {
+            resp.headers_mut()
+                .typed_insert(LastModified::from(last_modified));
+        }
+
+        match self.conditions.evaluate(etag.as_ref(), last_modified) {
+            Precondition::Passed => resp,
+            Precondition::NotModified => {
+                let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+                for name in [
+                    header::CACHE_CONTROL,
+                    header::CONTENT_LOCATION,
+                    header::DATE,
+                    header::ETAG,
+                    header::EXPIRES,
+                    header::LAST_MODIFIED,
+                    header::VARY,
+                ] {
+                    for value in resp.headers().get_all(&name) {
+                        not_modified
+                            .headers_mut()
+                            .append(name.clone(), value.clone());
+                    }
+                }
+                not_modified
+            }
+            Precondition::Failed => StatusCode::PRECONDITION_FAILED.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{handler, test::TestClient};
+
+    fn conditions(headers: &[(&str, &str)], method: Method) -> ConditionalHeaders {
+        let mut req = Request::builder().method(method);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        futures_util::FutureExt::now_or_never(ConditionalHeaders::from_request_without_body(
+            &req.finish(),
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn evaluate() {
+        let etag = ETag::from_str("\"abc\"").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let before = "Mon, 12 Jan 1970 13:46:39 GMT";
+        let after = "Mon, 12 Jan 1970 13:46:41 GMT";
+        let evaluate = |headers: &[(&str, &str)], method: Method| {
+            conditions(headers, method).evaluate(Some(&etag), Some(modified))
+        };
+
+        assert!(!conditions(&[], Method::GET).is_conditional());
+        assert_eq!(evaluate(&[], Method::GET), Precondition::Passed);
+
+        assert_eq!(
+            evaluate(&[("if-match", "\"abc\"")], Method::PUT),
+            Precondition::Passed
+        );
+        assert_eq!(
+            evaluate(&[("if-match", "\"xyz\"")], Method::PUT),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate(&[("if-match", "W/\"abc\"")], Method::PUT),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate(&[("if-unmodified-since", before)], Method::PUT),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate(&[("if-unmodified-since", after)], Method::PUT),
+            Precondition::Passed
+        );
+        // `If-Unmodified-Since` is ignored if `If-Match` is present
+        assert_eq!(
+            evaluate(
+                &[("if-match", "\"abc\""), ("if-unmodified-since", before)],
+                Method::PUT
+            ),
+            Precondition::Passed
+        );
+
+        assert_eq!(
+            evaluate(&[("if-none-match", "W/\"abc\"")], Method::GET),
+            Precondition::NotModified
+        );
+        assert_eq!(
+            evaluate(&[("if-none-match", "\"abc\"")], Method::POST),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate(&[("if-none-match", "\"xyz\"")], Method::GET),
+            Precondition::Passed
+        );
+        assert_eq!(
+            evaluate(&[("if-modified-since", after)], Method::GET),
+            Precondition::NotModified
+        );
+        assert_eq!(
+            evaluate(&[("if-modified-since", before)], Method::GET),
+            Precondition::Passed
+        );
+        assert_eq!(
+            evaluate(&[("if-modified-since", after)], Method::POST),
+            Precondition::Passed
+        );
+        // `If-Modified-Since` is ignored if `If-None-Match` is present
+        assert_eq!(
+            evaluate(
+                &[("if-none-match", "\"xyz\""), ("if-modified-since", after)],
+                Method::GET
+            ),
+            Precondition::Passed
+        );
+
+        // the representation does not exist
+        let conditions = conditions(&[("if-match", "*")], Method::PUT);
+        assert_eq!(conditions.evaluate(None, None), Precondition::Passed);
+    }
+
+    #[tokio::test]
+    async fn conditional() {
+        #[handler(internal)]
+        fn index(conditions: ConditionalHeaders) -> impl IntoResponse {
+            Conditional::new(
+                conditions,
+                "hello"
+                    .with_header(header::CACHE_CONTROL, "max-age=60")
+                    .with_header(header::CONTENT_TYPE, "text/plain"),
+            )
+            .etag_from_bytes("hello")
+            .last_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000))
+        }
+
+        let cli = TestClient::new(index);
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::LAST_MODIFIED, "Mon, 12 Jan 1970 13:46:40 GMT");
+        let etag = resp
+            .0
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        resp.assert_text("hello").await;
+
+        let resp = cli
+            .get("/")
+            .header(header::IF_NONE_MATCH, &etag)
+            .send()
+            .await;
+        resp.assert_status(StatusCode::NOT_MODIFIED);
+        resp.assert_header(header::ETAG, &etag);
+        resp.assert_header(header::CACHE_CONTROL, "max-age=60");
+        resp.assert_header_is_not_exist(header::CONTENT_TYPE);
+        resp.assert_text("").await;
+
+        cli.put("/")
+            .header(header::IF_MATCH, "\"other\"")
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn conditional_error_response() {
+        #[handler(internal)]
+        fn index(conditions: ConditionalHeaders) -> impl IntoResponse {
+            Conditional::new(conditions, StatusCode::NOT_FOUND).etag_from_bytes("hello")
+        }
+
+        TestClient::new(index)
+            .get("/")
+            .header(header::IF_NONE_MATCH, "*")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    http::Version,
+    web::{LocalAddr, RemoteAddr},
+    FromRequest, Request, RequestBody, Result,
+};
+
+/// The connection which the request is received from, which is added to the
+/// request extensions by the server.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ConnectionMeta {
+    /// The id of the connection.
+    pub(crate) id: u64,
+    /// The number of the requests received from the connection, including
+    /// this one.
+    pub(crate) requests: u64,
+}
+
+/// An extractor that can extracts the information of the client connection
+/// which the request is received from.
+///
+/// The connection ID is unique within the process, so it can be used to
+/// correlate the requests received from one connection. If the request is not
+/// received by the [`Server`](crate::Server), such as the requests sent by the
+/// [`TestClient`](crate::test::TestClient), the connection ID is `0`.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, web::ConnectionInfo};
+///
+/// #[handler]
+/// fn index(conn: ConnectionInfo) -> String {
+///     format!(
+///         "connection #{} from {}, {} request(s), reused: {}",
+///         conn.id(),
+///         conn.remote_addr(),
+///         conn.requests(),
+///         conn.is_reused()
+///     )
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    id: u64,
+    requests: u64,
+    local_addr: LocalAddr,
+    remote_addr: RemoteAddr,
+    version: Version,
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for ConnectionInfo {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let meta = req
+            .extensions()
+            .get::<ConnectionMeta>()
+            .copied()
+            .unwrap_or(ConnectionMeta { id: 0, requests: 1 });
+        Ok(Self {
+            id: meta.id,
+            requests: meta.requests,
+            local_addr: req.local_addr().clone(),
+            remote_addr: req.remote_addr().clone(),
+            version: req.version(),
+        })
+    }
+}
+
+impl ConnectionInfo {
+    /// Returns the ID of the connection.
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the number of the requests received from the connection,
+    /// including this one.
+    #[inline]
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Returns `true` if the connection has received the other requests before
+    /// this one, such as a keep-alive HTTP/1.1 connection or a HTTP/2
+    /// connection.
+    #[inline]
+    pub fn is_reused(&self) -> bool {
+        self.requests > 1
+    }
+
+    /// Returns the local address of the connection.
+    #[inline]
+    pub fn local_addr(&self) -> &LocalAddr {
+        &self.local_addr
+    }
+
+    /// Returns the remote address of the connection.
+    #[inline]
+    pub fn remote_addr(&self) -> &RemoteAddr {
+        &self.remote_addr
+    }
+
+    /// Returns the HTTP version of the connection.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, test::TestClient};
+
+    #[tokio::test]
+    async fn connection_info() {
+        #[handler(internal)]
+        fn index(conn: ConnectionInfo) -> String {
+            format!(
+                "{} {} {} {:?}",
+                conn.id(),
+                conn.requests(),
+                conn.is_reused(),
+                conn.version()
+            )
+        }
+
+        let cli = TestClient::new(index);
+        cli.get("/")
+            .send()
+            .await
+            .assert_text("0 1 false HTTP/1.1")
+            .await;
+        cli.get("/")
+            .data(ConnectionMeta { id: 3, requests: 2 })
+            .send()
+            .await
+            .assert_text("3 2 true HTTP/1.1")
+            .await;
+    }
+}
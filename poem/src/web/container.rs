@@ -0,0 +1,389 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    future::Future,
+    ops::Deref,
+    sync::Arc,
+};
+
+use futures_util::future::BoxFuture;
+use parking_lot::Mutex;
+
+use crate::{error::ResolveError, FromRequest, Request, RequestBody, Result};
+
+type BoxAny = Box<dyn Any + Send + Sync>;
+type SingletonFactory = Box<dyn Fn() -> BoxAny + Send + Sync>;
+type ScopedFactory = Box<dyn Fn(&Request) -> BoxFuture<'static, Result<BoxAny>> + Send + Sync>;
+
+enum Provider {
+    Singleton(BoxAny),
+    SingletonFactory {
+        factory: SingletonFactory,
+        value: Mutex<Option<Arc<BoxAny>>>,
+    },
+    Scoped(ScopedFactory),
+}
+
+struct Registration {
+    type_name: &'static str,
+    provider: Provider,
+}
+
+/// A dependency injection container, the registered values can be extracted
+/// with [`Inject`].
+///
+/// The values are stored as `Arc<T>`, and `T` can be a trait object, so the
+/// handlers can depend on the abstractions instead of the implementations.
+///
+/// - [`Container::singleton`] and [`Container::singleton_arc`] register an
+///   app-scoped value, which can also be extracted with
+///   [`Data`](crate::web::Data).
+/// - [`Container::singleton_factory`] registers an app-scoped value which is
+///   created on the first use.
+/// - [`Container::scoped`] registers a request-scoped value which is created
+///   asynchronously on the first use in each request.
+///
+/// Add the container to the endpoint with the
+/// [`AddContainer`](crate::middleware::AddContainer) middleware.
+///
+/// # Errors
+///
+/// - [`ResolveError`]
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use poem::{
+///     handler,
+///     middleware::AddContainer,
+///     test::TestClient,
+///     web::{Container, Inject},
+///     EndpointExt, Request,
+/// };
+///
+/// trait Greeter: Send + Sync {
+///     fn greet(&self, name: &str) -> String;
+/// }
+///
+/// struct English;
+///
+/// impl Greeter for English {
+///     fn greet(&self, name: &str) -> String {
+///         format!("hello, {}!", name)
+///     }
+/// }
+///
+/// struct CurrentUser {
+///     name: String,
+/// }
+///
+/// #[handler]
+/// fn index(greeter: Inject<dyn Greeter>, user: Inject<CurrentUser>) -> String {
+///     greeter.greet(&user.name)
+/// }
+///
+/// let container = Container::new()
+///     .singleton_arc::<dyn Greeter>(Arc::new(English))
+///     .scoped(|req: &Request| {
+///         let name = req.header("x-user").unwrap_or("guest").to_string();
+///         async move { Ok(Arc::new(CurrentUser { name })) }
+///     });
+/// let cli = TestClient::new(index.with(AddContainer::new(container)));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("x-user", "sunli").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("hello, sunli!").await;
+/// # });
+/// ```
+#[derive(Default, Clone)]
+pub struct Container {
+    registrations: Arc<HashMap<TypeId, Registration>>,
+}
+
+impl Container {
+    /// Create a new empty container.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn register<T: ?Sized + 'static>(mut self, provider: Provider) -> Self {
+        Arc::get_mut(&mut self.registrations)
+            .expect("the container is shared")
+            .insert(
+                TypeId::of::<T>(),
+                Registration {
+                    type_name: std::any::type_name::<T>(),
+                    provider,
+                },
+            );
+        self
+    }
+
+    /// Registers an app-scoped value.
+    #[must_use]
+    pub fn singleton<T: Send + Sync + 'static>(self, value: T) -> Self {
+        self.singleton_arc(Arc::new(value))
+    }
+
+    /// Registers an app-scoped value, `T` can be a trait object.
+    #[must_use]
+    pub fn singleton_arc<T: ?Sized + Send + Sync + 'static>(self, value: Arc<T>) -> Self {
+        self.register::<T>(Provider::Singleton(Box::new(value)))
+    }
+
+    /// Registers an app-scoped value which is created with the factory on the
+    /// first use.
+    #[must_use]
+    pub fn singleton_factory<T, F>(self, factory: F) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+        F: Fn() -> Arc<T> + Send + Sync + 'static,
+    {
+        self.register::<T>(Provider::SingletonFactory {
+            factory: Box::new(move || Box::new(factory())),
+            value: Mutex::new(None),
+        })
+    }
+
+    /// Registers a request-scoped value which is created with the factory on
+    /// the first use in each request, the errors returned by the factory are
+    /// returned by the extractor.
+    ///
+    /// The factory takes the request and returns a future, so the parts of
+    /// the request which are needed must be copied before the future.
+    #[must_use]
+    pub fn scoped<T, F, Fut>(self, factory: F) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+        F: Fn(&Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Arc<T>>> + Send + 'static,
+    {
+        self.register::<T>(Provider::Scoped(Box::new(move |req| {
+            let fut = factory(req);
+            Box::pin(async move { fut.await.map(|value| Box::new(value) as BoxAny) })
+        })))
+    }
+
+    /// Returns `true` if the type is registered.
+    pub fn is_registered<T: ?Sized + 'static>(&self) -> bool {
+        self.registrations.contains_key(&TypeId::of::<T>())
+    }
+
+    fn not_registered<T: ?Sized>(&self) -> ResolveError {
+        let mut registered = self
+            .registrations
+            .values()
+            .map(|registration| registration.type_name)
+            .collect::<Vec<_>>();
+        registered.sort_unstable();
+        ResolveError::NotRegistered {
+            type_name: std::any::type_name::<T>(),
+            registered,
+        }
+    }
+}
+
+/// The container with the request-scoped values, which is added to the
+/// request extensions by the `AddContainer` middleware.
+pub(crate) struct RequestScope {
+    container: Container,
+    values: Mutex<HashMap<TypeId, Arc<BoxAny>>>,
+}
+
+impl RequestScope {
+    pub(crate) fn new(container: Container) -> Self {
+        Self {
+            container,
+            values: Default::default(),
+        }
+    }
+
+    /// Returns the app-scoped value registered with `Container::singleton`.
+    pub(crate) fn singleton<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        match &self
+            .container
+            .registrations
+            .get(&TypeId::of::<T>())?
+            .provider
+        {
+            Provider::Singleton(value) => value.downcast_ref::<Arc<T>>().map(|value| &**value),
+            _ => None,
+        }
+    }
+
+    async fn resolve<T: ?Sized + Send + Sync + 'static>(&self, req: &Request) -> Result<Arc<T>> {
+        let registration = self
+            .container
+            .registrations
+            .get(&TypeId::of::<T>())
+            .ok_or_else(|| self.container.not_registered::<T>())?;
+
+        let value = match &registration.provider {
+            Provider::Singleton(value) => return Ok(downcast::<T>(value)),
+            Provider::SingletonFactory { factory, value } => value
+                .lock()
+                .get_or_insert_with(|| Arc::new(factory()))
+                .clone(),
+            Provider::Scoped(factory) => {
+                if let Some(value) = self.values.lock().get(&TypeId::of::<T>()) {
+                    return Ok(downcast::<T>(value));
+                }
+                let value = Arc::new(factory(req).await?);
+                self.values
+                    .lock()
+                    .entry(TypeId::of::<T>())
+                    .or_insert(value)
+                    .clone()
+            }
+        };
+        Ok(downcast::<T>(&value))
+    }
+}
+
+fn downcast<T: ?Sized + 'static>(value: &BoxAny) -> Arc<T> {
+    value
+        .downcast_ref::<Arc<T>>()
+        .expect("the value has the registered type")
+        .clone()
+}
+
+/// An extractor that resolves the value from the [`Container`].
+///
+/// # Errors
+///
+/// - [`ResolveError`]
+/// - The errors returned by the factories registered with
+///   [`Container::scoped`].
+pub struct Inject<T: ?Sized>(pub Arc<T>);
+
+impl<T: ?Sized> Clone for Inject<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Deref for Inject<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, T: ?Sized + Send + Sync + 'static> FromRequest<'a> for Inject<T> {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        req.extensions()
+            .get::<RequestScope>()
+            .ok_or(ResolveError::ContainerNotFound)?
+            .resolve(req)
+            .await
+            .map(Inject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        handler, http::StatusCode, middleware::AddContainer, test::TestClient, web::Data,
+        EndpointExt,
+    };
+
+    trait Counter: Send + Sync {
+        fn next(&self) -> usize;
+    }
+
+    #[derive(Default)]
+    struct AtomicCounter(AtomicUsize);
+
+    impl Counter for AtomicCounter {
+        fn next(&self) -> usize {
+            self.0.fetch_add(1, Ordering::SeqCst)
+        }
+    }
+
+    struct RequestId(usize);
+
+    #[tokio::test]
+    async fn inject() {
+        #[handler(internal)]
+        fn index(
+            a: Inject<RequestId>,
+            b: Inject<RequestId>,
+            name: Inject<String>,
+            value: Data<&i32>,
+        ) -> String {
+            assert!(Arc::ptr_eq(&a.0, &b.0));
+            format!("{} {} {}", name.as_str(), a.0 .0, value.0)
+        }
+
+        let factory_calls = Arc::new(AtomicUsize::new(0));
+        let counter: Arc<dyn Counter> = Arc::new(AtomicCounter::default());
+        let container = Container::new()
+            .singleton(100i32)
+            .singleton_arc(counter.clone())
+            .singleton_factory({
+                let factory_calls = factory_calls.clone();
+                move || {
+                    factory_calls.fetch_add(1, Ordering::SeqCst);
+                    Arc::new("poem".to_string())
+                }
+            })
+            .scoped(move |_| {
+                let id = counter.next();
+                async move { Ok(Arc::new(RequestId(id))) }
+            });
+        assert!(container.is_registered::<dyn Counter>());
+        assert!(!container.is_registered::<u8>());
+
+        let cli = TestClient::new(index.with(AddContainer::new(container)));
+        cli.get("/").send().await.assert_text("poem 0 100").await;
+        cli.get("/").send().await.assert_text("poem 1 100").await;
+        assert_eq!(factory_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn resolve_error() {
+        #[handler(internal)]
+        fn index(_value: Inject<dyn Counter>) {}
+
+        let resp = TestClient::new(index).get("/").send().await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        resp.assert_text("the container was not found, add it with the `AddContainer` middleware")
+            .await;
+
+        let container = Container::new().singleton(1u8).singleton(1i32);
+        let resp = TestClient::new(index.with(AddContainer::new(container)))
+            .get("/")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        resp.assert_text(format!(
+            "type `{}` is not registered in the container, registered types: [i32, u8]",
+            std::any::type_name::<dyn Counter>()
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    async fn scoped_error() {
+        #[handler(internal)]
+        fn index(_value: Inject<RequestId>) {}
+
+        let container = Container::new().scoped::<RequestId, _, _>(|_| async {
+            Err(crate::Error::from_status(StatusCode::UNAUTHORIZED))
+        });
+        TestClient::new(index.with(AddContainer::new(container)))
+            .get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+}
@@ -11,7 +11,7 @@ use std::{
 use chrono::{DateTime, TimeZone, Utc};
 use http::HeaderValue;
 use parking_lot::Mutex;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     error::ParseCookieError,
@@ -351,20 +351,38 @@ impl<'a> FromRequest<'a> for Cookie {
 pub struct CookieJar {
     jar: Arc<Mutex<libcookie::CookieJar>>,
     pub(crate) key: Option<Arc<CookieKey>>,
+    pub(crate) fallback_keys: Arc<Vec<CookieKey>>,
+    pub(crate) config: Option<Arc<CookieConfig>>,
 }
 
 impl CookieJar {
     /// Adds cookie to this jar. If a cookie with the same name already exists,
     /// it is replaced with cookie.
+    ///
+    /// The attributes which are not set are filled with the
+    /// [`CookieConfig`] specified by the `CookieJarManager::config`.
     pub fn add(&self, cookie: Cookie) {
-        self.jar.lock().add(cookie.0);
+        self.jar.lock().add(self.prepare(cookie));
     }
 
     /// Removes cookie from this jar.
     pub fn remove(&self, name: impl AsRef<str>) {
-        self.jar
-            .lock()
-            .remove(libcookie::Cookie::named(name.as_ref().to_string()));
+        self.jar.lock().remove(self.removal(name.as_ref()));
+    }
+
+    fn prepare(&self, mut cookie: Cookie) -> libcookie::Cookie<'static> {
+        if let Some(config) = &self.config {
+            config.apply(&mut cookie);
+        }
+        cookie.0
+    }
+
+    fn removal(&self, name: &str) -> libcookie::Cookie<'static> {
+        let mut cookie = libcookie::Cookie::named(name.to_string());
+        if let Some(path) = self.config.as_ref().and_then(|config| config.path.clone()) {
+            cookie.set_path(path);
+        }
+        cookie
     }
 
     /// Returns a reference to the [`Cookie`] inside this jar with the `name`.
@@ -496,7 +514,7 @@ impl FromStr for CookieJar {
 
         Ok(CookieJar {
             jar: Arc::new(Mutex::new(cookie_jar)),
-            key: None,
+            ..Default::default()
         })
     }
 }
@@ -524,7 +542,7 @@ impl CookieJar {
 
         CookieJar {
             jar: Arc::new(Mutex::new(cookie_jar)),
-            key: None,
+            ..Default::default()
         }
     }
 
@@ -542,6 +560,99 @@ impl CookieJar {
 /// A cryptographic master key for use with Signed and/or Private jars.
 pub type CookieKey = libcookie::Key;
 
+/// The default attributes of the cookies added to the [`CookieJar`], see
+/// `CookieJarManager::config`.
+///
+/// The attributes are only applied to the cookies that do not set them
+/// explicitly.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     middleware::CookieJarManager,
+///     web::cookie::{CookieConfig, SameSite},
+/// };
+///
+/// let manager = CookieJarManager::new().config(
+///     CookieConfig::new()
+///         .same_site(SameSite::Lax)
+///         .secure(true)
+///         .http_only(true)
+///         .path("/"),
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CookieConfig {
+    same_site: Option<SameSite>,
+    secure: Option<bool>,
+    http_only: Option<bool>,
+    path: Option<String>,
+}
+
+impl CookieConfig {
+    /// Create a new `CookieConfig` without any defaults.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the default `SameSite` attribute.
+    #[must_use]
+    pub fn same_site(self, value: SameSite) -> Self {
+        Self {
+            same_site: Some(value),
+            ..self
+        }
+    }
+
+    /// Sets the default `Secure` attribute.
+    #[must_use]
+    pub fn secure(self, value: bool) -> Self {
+        Self {
+            secure: Some(value),
+            ..self
+        }
+    }
+
+    /// Sets the default `HttpOnly` attribute.
+    #[must_use]
+    pub fn http_only(self, value: bool) -> Self {
+        Self {
+            http_only: Some(value),
+            ..self
+        }
+    }
+
+    /// Sets the default `Path` attribute, which is also used to remove the
+    /// cookies.
+    #[must_use]
+    pub fn path(self, path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+
+    fn apply(&self, cookie: &mut Cookie) {
+        let cookie = &mut cookie.0;
+        if cookie.same_site().is_none() {
+            cookie.set_same_site(self.same_site);
+        }
+        if cookie.secure().is_none() {
+            cookie.set_secure(self.secure);
+        }
+        if cookie.http_only().is_none() {
+            cookie.set_http_only(self.http_only);
+        }
+        if cookie.path().is_none() {
+            if let Some(path) = &self.path {
+                cookie.set_path(path.clone());
+            }
+        }
+    }
+}
+
 /// A child cookie jar that provides authenticated encryption for its cookies.
 pub struct PrivateCookieJar<'a> {
     key: &'a CookieKey,
@@ -553,26 +664,45 @@ impl<'a> PrivateCookieJar<'a> {
     /// authenticated encryption assuring confidentiality, integrity, and
     /// authenticity.
     pub fn add(&self, cookie: Cookie) {
+        let cookie = self.cookie_jar.prepare(cookie);
         let mut cookie_jar = self.cookie_jar.jar.lock();
         let mut private_cookie_jar = cookie_jar.private_mut(self.key);
-        private_cookie_jar.add(cookie.0);
+        private_cookie_jar.add(cookie);
+    }
+
+    /// Serializes the value to JSON and adds it as an encrypted cookie
+    /// with the name.
+    pub fn add_value(&self, name: impl Into<String>, value: impl Serialize) {
+        self.add(Cookie::new(name, value));
     }
 
     /// Removes cookie from the parent jar.
     pub fn remove(&self, name: impl AsRef<str>) {
+        let cookie = self.cookie_jar.removal(name.as_ref());
         let mut cookie_jar = self.cookie_jar.jar.lock();
         let mut private_cookie_jar = cookie_jar.private_mut(self.key);
-        private_cookie_jar.remove(libcookie::Cookie::named(name.as_ref().to_string()));
+        private_cookie_jar.remove(cookie);
     }
 
     /// Returns cookie inside this jar with the name and authenticates and
     /// decrypts the cookie’s value, returning a Cookie with the decrypted
     /// value. If the cookie cannot be found, or the cookie fails to
     /// authenticate or decrypt, None is returned.
+    ///
+    /// The fallback keys specified by the `CookieJarManager::fallback_keys`
+    /// are tried if the cookie fails to decrypt with the key.
     pub fn get(&self, name: &str) -> Option<Cookie> {
         let cookie_jar = self.cookie_jar.jar.lock();
-        let private_cookie_jar = cookie_jar.private(self.key);
-        private_cookie_jar.get(name).map(Cookie)
+        std::iter::once(self.key)
+            .chain(self.cookie_jar.fallback_keys.iter())
+            .find_map(|key| cookie_jar.private(key).get(name))
+            .map(Cookie)
+    }
+
+    /// Returns the deserialized value of the cookie with the name, or `None`
+    /// if the cookie cannot be found, decrypted or deserialized.
+    pub fn get_value<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.get(name)?.value().ok()
     }
 }
 
@@ -586,26 +716,45 @@ impl<'a> SignedCookieJar<'a> {
     /// Adds cookie to the parent jar. The cookie’s value is signed assuring
     /// integrity and authenticity.
     pub fn add(&self, cookie: Cookie) {
+        let cookie = self.cookie_jar.prepare(cookie);
         let mut cookie_jar = self.cookie_jar.jar.lock();
         let mut signed_cookie_jar = cookie_jar.signed_mut(self.key);
-        signed_cookie_jar.add(cookie.0);
+        signed_cookie_jar.add(cookie);
+    }
+
+    /// Serializes the value to JSON and adds it as a signed cookie
+    /// with the name.
+    pub fn add_value(&self, name: impl Into<String>, value: impl Serialize) {
+        self.add(Cookie::new(name, value));
     }
 
     /// Removes cookie from the parent jar.
     pub fn remove(&self, name: impl AsRef<str>) {
+        let cookie = self.cookie_jar.removal(name.as_ref());
         let mut cookie_jar = self.cookie_jar.jar.lock();
         let mut signed_cookie_jar = cookie_jar.signed_mut(self.key);
-        signed_cookie_jar.remove(libcookie::Cookie::named(name.as_ref().to_string()));
+        signed_cookie_jar.remove(cookie);
     }
 
     /// Returns cookie inside this jar with the name and authenticates and
     /// decrypts the cookie’s value, returning a Cookie with the decrypted
     /// value. If the cookie cannot be found, or the cookie fails to
     /// authenticate or decrypt, None is returned.
+    ///
+    /// The fallback keys specified by the `CookieJarManager::fallback_keys`
+    /// are tried if the cookie fails to verify with the key.
     pub fn get(&self, name: &str) -> Option<Cookie> {
         let cookie_jar = self.cookie_jar.jar.lock();
-        let signed_cookie_jar = cookie_jar.signed(self.key);
-        signed_cookie_jar.get(name).map(Cookie)
+        std::iter::once(self.key)
+            .chain(self.cookie_jar.fallback_keys.iter())
+            .find_map(|key| cookie_jar.signed(key).get(name))
+            .map(Cookie)
+    }
+
+    /// Returns the deserialized value of the cookie with the name, or `None`
+    /// if the cookie cannot be found, verified or deserialized.
+    pub fn get_value<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.get(name)?.value().ok()
     }
 }
 
@@ -732,4 +881,114 @@ mod tests {
             vec![String::from("a"), String::from("b"), String::from("c")]
         );
     }
+
+    #[test]
+    fn typed_values() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Session {
+            user: String,
+            admin: bool,
+        }
+
+        let key = CookieKey::generate();
+        let cookie_jar = CookieJar::default();
+        let session = Session {
+            user: "sunli".to_string(),
+            admin: true,
+        };
+
+        cookie_jar.signed_with_key(&key).add_value("a", &session);
+        cookie_jar.private_with_key(&key).add_value("b", &session);
+        assert_eq!(
+            cookie_jar.signed_with_key(&key).get_value::<Session>("a"),
+            Some(Session {
+                user: "sunli".to_string(),
+                admin: true,
+            })
+        );
+        assert_eq!(
+            cookie_jar.private_with_key(&key).get_value::<Session>("b"),
+            Some(session)
+        );
+        assert_eq!(cookie_jar.signed_with_key(&key).get_value::<i32>("a"), None);
+        assert_eq!(cookie_jar.signed_with_key(&key).get_value::<i32>("c"), None);
+    }
+
+    #[test]
+    fn fallback_keys() {
+        let old_key = CookieKey::generate();
+        let key = CookieKey::generate();
+        let cookie_jar = CookieJar::default();
+        cookie_jar
+            .signed_with_key(&old_key)
+            .add(Cookie::new_with_str("a", "1"));
+        cookie_jar
+            .private_with_key(&old_key)
+            .add(Cookie::new_with_str("b", "2"));
+
+        assert!(cookie_jar.signed_with_key(&key).get("a").is_none());
+        assert!(cookie_jar.private_with_key(&key).get("b").is_none());
+
+        let cookie_jar = CookieJar {
+            fallback_keys: Arc::new(vec![CookieKey::generate(), old_key]),
+            ..cookie_jar
+        };
+        assert_eq!(
+            cookie_jar
+                .signed_with_key(&key)
+                .get("a")
+                .unwrap()
+                .value_str(),
+            "1"
+        );
+        assert_eq!(
+            cookie_jar
+                .private_with_key(&key)
+                .get("b")
+                .unwrap()
+                .value_str(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn config() {
+        let cookie_jar = CookieJar {
+            config: Some(Arc::new(
+                CookieConfig::new()
+                    .same_site(SameSite::Strict)
+                    .secure(true)
+                    .http_only(true)
+                    .path("/app"),
+            )),
+            ..CookieJar::default()
+        };
+
+        let mut cookie = Cookie::new_with_str("a", "1");
+        cookie.set_secure(false);
+        cookie_jar.add(cookie);
+        cookie_jar
+            .signed_with_key(&CookieKey::generate())
+            .add(Cookie::new_with_str("b", "2"));
+
+        let cookie = cookie_jar.get("a").unwrap();
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert!(!cookie.secure());
+        assert!(cookie.http_only());
+        assert_eq!(cookie.path(), Some("/app"));
+
+        let cookie = cookie_jar.get("b").unwrap();
+        assert!(cookie.secure());
+        assert_eq!(cookie.path(), Some("/app"));
+
+        let cookie_jar = CookieJar {
+            config: cookie_jar.config,
+            .."a=1".parse().unwrap()
+        };
+        cookie_jar.remove("a");
+        let mut headers = HeaderMap::new();
+        cookie_jar.append_delta_to_headers(&mut headers);
+        let removal = Cookie::parse(headers[header::SET_COOKIE].to_str().unwrap()).unwrap();
+        assert_eq!(removal.path(), Some("/app"));
+    }
 }
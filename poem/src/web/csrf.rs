@@ -1,8 +1,18 @@
-use std::{ops::Deref, sync::Arc};
+use std::{
+    ops::Deref,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use libcsrf::{AesGcmCsrfProtection, CsrfProtection, UnencryptedCsrfCookie};
+use rand::RngCore;
 
-use crate::{FromRequest, Request, RequestBody, Result};
+use crate::{
+    error::CsrfError,
+    http::{header, HeaderName},
+    web::RequestBody,
+    FromRequest, Request, Result,
+};
 
 /// A CSRF Token for the next request.
 ///
@@ -19,6 +29,67 @@ impl Deref for CsrfToken {
     }
 }
 
+impl CsrfToken {
+    /// Returns a hidden `<input>` element containing the token, to be
+    /// injected into the HTML forms.
+    ///
+    /// The `name` should be the form field of the
+    /// [`Csrf`](crate::middleware::Csrf) middleware, default is `csrf_token`.
+    ///
+    /// ```
+    /// use poem::web::CsrfToken;
+    ///
+    /// let token = CsrfToken("abc".to_string());
+    /// assert_eq!(
+    ///     token.form_field("csrf_token"),
+    ///     r#"<input type="hidden" name="csrf_token" value="abc">"#
+    /// );
+    /// ```
+    pub fn form_field(&self, name: &str) -> String {
+        format!(
+            r#"<input type="hidden" name="{}" value="{}">"#,
+            escape_attr(name),
+            escape_attr(&self.0)
+        )
+    }
+
+    /// Returns a `<meta>` element containing the token, to be read by the
+    /// scripts and sent in the header of the
+    /// [`Csrf`](crate::middleware::Csrf) middleware.
+    ///
+    /// ```
+    /// use poem::web::CsrfToken;
+    ///
+    /// let token = CsrfToken("abc".to_string());
+    /// assert_eq!(
+    ///     token.meta_tag("csrf-token"),
+    ///     r#"<meta name="csrf-token" content="abc">"#
+    /// );
+    /// ```
+    pub fn meta_tag(&self, name: &str) -> String {
+        format!(
+            r#"<meta name="{}" content="{}">"#,
+            escape_attr(name),
+            escape_attr(&self.0)
+        )
+    }
+}
+
+fn escape_attr(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#x27;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
 #[async_trait::async_trait]
 impl<'a> FromRequest<'a> for &'a CsrfToken {
     async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
@@ -29,21 +100,75 @@ impl<'a> FromRequest<'a> for &'a CsrfToken {
     }
 }
 
+const DOUBLE_SUBMIT_TOKEN_LEN: usize = 40;
+
+/// Generates a random token for the double-submit cookie mode, it contains
+/// the expiration time in seconds since the Unix epoch after the random
+/// bytes.
+pub(crate) fn generate_double_submit_token(ttl_secs: u64) -> String {
+    let mut data = [0; DOUBLE_SUBMIT_TOKEN_LEN];
+    rand::thread_rng().fill_bytes(&mut data[..32]);
+    data[32..].copy_from_slice(&(unix_now() + ttl_secs).to_be_bytes());
+    base64::encode(data)
+}
+
+/// Returns `true` if the token of the double-submit cookie mode is well
+/// formed and not expired.
+pub(crate) fn is_valid_double_submit_token(token: &str) -> bool {
+    match base64::decode(token) {
+        Ok(data) if data.len() == DOUBLE_SUBMIT_TOKEN_LEN => {
+            let mut expires = [0; 8];
+            expires.copy_from_slice(&data[32..]);
+            u64::from_be_bytes(expires) > unix_now()
+        }
+        _ => false,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// The CSRF cookie sent with the request.
+pub(crate) enum CsrfCookie {
+    Encrypted(UnencryptedCsrfCookie),
+    DoubleSubmit(String),
+}
+
+/// Where the [`VerifiedCsrfToken`] extractor reads the token from.
+pub(crate) struct CsrfTokenSource {
+    pub(crate) header_name: HeaderName,
+    pub(crate) form_field: String,
+}
+
 /// A verifier for CSRF Token.
 ///
 /// See also [`Csrf`](crate::middleware::Csrf)
 #[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
 pub struct CsrfVerifier {
-    cookie: Option<UnencryptedCsrfCookie>,
+    cookie: Option<CsrfCookie>,
     protect: Arc<AesGcmCsrfProtection>,
+    source: Arc<CsrfTokenSource>,
 }
 
 impl CsrfVerifier {
     pub(crate) fn new(
-        cookie: Option<UnencryptedCsrfCookie>,
+        cookie: Option<CsrfCookie>,
         protect: Arc<AesGcmCsrfProtection>,
+        source: Arc<CsrfTokenSource>,
     ) -> Self {
-        Self { cookie, protect }
+        Self {
+            cookie,
+            protect,
+            source,
+        }
     }
 }
 
@@ -60,21 +185,132 @@ impl<'a> FromRequest<'a> for &'a CsrfVerifier {
 impl CsrfVerifier {
     /// Return `true` if the token is valid.
     pub fn is_valid(&self, token: &str) -> bool {
-        let cookie = match &self.cookie {
-            Some(cookie) => cookie,
-            None => return false,
-        };
+        match &self.cookie {
+            Some(CsrfCookie::Encrypted(cookie)) => {
+                let token_data = match base64::decode(token) {
+                    Ok(data) => data,
+                    Err(_) => return false,
+                };
 
-        let token_data = match base64::decode(token) {
-            Ok(data) => data,
-            Err(_) => return false,
-        };
+                let token = match self.protect.parse_token(&token_data) {
+                    Ok(token) => token,
+                    Err(_) => return false,
+                };
+
+                self.protect.verify_token_pair(&token, cookie)
+            }
+            Some(CsrfCookie::DoubleSubmit(cookie)) => {
+                constant_time_eq(token.as_bytes(), cookie.as_bytes())
+                    && is_valid_double_submit_token(token)
+            }
+            None => false,
+        }
+    }
+}
+
+/// An extractor that verifies the CSRF token of the request, and rejects the
+/// request if the token is missing or invalid.
+///
+/// The token is read from the header, default is `X-CSRF-Token`, or from the
+/// field of the `application/x-www-form-urlencoded` body, default is
+/// `csrf_token`, see
+/// [`Csrf::header_name`](crate::middleware::Csrf::header_name)
+/// and [`Csrf::form_field`](crate::middleware::Csrf::form_field). The body is
+/// still available to the following extractors, such as
+/// [`Form`](crate::web::Form).
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`CsrfError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     middleware::Csrf,
+///     web::{CsrfToken, Form, Html, VerifiedCsrfToken},
+///     EndpointExt, Route,
+/// };
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Comment {
+///     content: String,
+/// }
+///
+/// #[handler]
+/// fn comment_ui(token: &CsrfToken) -> Html<String> {
+///     Html(format!(
+///         r#"<form method="post">{}<textarea name="content"></textarea></form>"#,
+///         token.form_field("csrf_token")
+///     ))
+/// }
+///
+/// #[handler]
+/// fn comment(_: VerifiedCsrfToken, Form(comment): Form<Comment>) -> String {
+///     comment.content
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(comment_ui).post(comment))
+///     .with(Csrf::new());
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VerifiedCsrfToken(pub String);
 
-        let token = match self.protect.parse_token(&token_data) {
-            Ok(token) => token,
-            Err(_) => return false,
+impl Deref for VerifiedCsrfToken {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for VerifiedCsrfToken {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let verifier = req
+            .extensions()
+            .get::<CsrfVerifier>()
+            .expect("To use the `VerifiedCsrfToken` extractor, the `Csrf` middleware is required.");
+
+        let token = match req
+            .headers()
+            .get(&verifier.source.header_name)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(token) => Some(token.to_string()),
+            None if is_form(req) => {
+                let data = body.take()?.into_bytes().await?;
+                *body = RequestBody::new(data.clone().into());
+                serde_urlencoded::from_bytes::<Vec<(String, String)>>(&data)
+                    .ok()
+                    .and_then(|fields| {
+                        fields
+                            .into_iter()
+                            .find(|(name, _)| name == &verifier.source.form_field)
+                            .map(|(_, value)| value)
+                    })
+            }
+            None => None,
         };
 
-        self.protect.verify_token_pair(&token, cookie)
+        match token {
+            Some(token) if verifier.is_valid(&token) => Ok(VerifiedCsrfToken(token)),
+            Some(_) => Err(CsrfError::InvalidToken.into()),
+            None => Err(CsrfError::MissingToken.into()),
+        }
     }
 }
+
+fn is_form(req: &Request) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .map(|mime| mime.essence_str() == "application/x-www-form-urlencoded")
+        .unwrap_or_default()
+}
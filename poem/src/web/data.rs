@@ -1,9 +1,15 @@
 use std::ops::Deref;
 
-use crate::{error::GetDataError, FromRequest, Request, RequestBody, Result};
+use crate::{
+    error::GetDataError, web::container::RequestScope, FromRequest, Request, RequestBody, Result,
+};
 
 /// An extractor that can extract data from the request extension.
 ///
+/// The values registered with
+/// [`Container::singleton`](crate::web::Container::singleton) can also be
+/// extracted if the container is added to the request.
+///
 /// # Errors
 ///
 /// - [`GetDataError`]
@@ -43,6 +49,11 @@ impl<'a, T: Send + Sync + 'static> FromRequest<'a> for Data<&'a T> {
         Ok(Data(
             req.extensions()
                 .get::<T>()
+                .or_else(|| {
+                    req.extensions()
+                        .get::<RequestScope>()
+                        .and_then(RequestScope::singleton::<T>)
+                })
                 .ok_or_else(|| GetDataError(std::any::type_name::<T>()))?,
         ))
     }
@@ -2,6 +2,8 @@ use std::ops::{Deref, DerefMut};
 
 use serde::de::DeserializeOwned;
 
+#[cfg(feature = "serde-qs")]
+use crate::web::UrlEncodedConfig;
 use crate::{
     error::ParseFormError,
     http::{
@@ -24,6 +26,8 @@ use crate::{
 ///
 /// - [`ReadBodyError`](crate::error::ReadBodyError)
 /// - [`ParseFormError`]
+/// - `ParseQsError` if the nested keys are enabled by `UrlEncodedConfig` with
+///   the `serde-qs` feature
 ///
 /// # Example
 ///
@@ -89,12 +93,18 @@ impl<T> DerefMut for Form<T> {
 #[async_trait::async_trait]
 impl<'a, T: DeserializeOwned> FromRequest<'a> for Form<T> {
     async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        #[cfg(feature = "serde-qs")]
+        let nested = UrlEncodedConfig::nested_of(req);
+
         if req.method() == Method::GET {
-            Ok(
-                serde_urlencoded::from_str(req.uri().query().unwrap_or_default())
-                    .map_err(ParseFormError::UrlDecode)
-                    .map(Self)?,
-            )
+            let query = req.uri().query().unwrap_or_default();
+            #[cfg(feature = "serde-qs")]
+            if let Some(config) = nested {
+                return Ok(config.deserialize(query.as_bytes()).map(Self)?);
+            }
+            Ok(serde_urlencoded::from_str(query)
+                .map_err(ParseFormError::UrlDecode)
+                .map(Self)?)
         } else {
             let content_type = req.headers().get(header::CONTENT_TYPE);
             if content_type
@@ -108,9 +118,13 @@ impl<'a, T: DeserializeOwned> FromRequest<'a> for Form<T> {
                 };
             }
 
+            let data = body.take()?.into_vec().await?;
+            #[cfg(feature = "serde-qs")]
+            if let Some(config) = nested {
+                return Ok(config.deserialize(&data).map(Self)?);
+            }
             Ok(Self(
-                serde_urlencoded::from_bytes(&body.take()?.into_vec().await?)
-                    .map_err(ParseFormError::UrlDecode)?,
+                serde_urlencoded::from_bytes(&data).map_err(ParseFormError::UrlDecode)?,
             ))
         }
     }
@@ -160,4 +174,41 @@ mod tests {
             .await
             .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
     }
+
+    #[cfg(feature = "serde-qs")]
+    #[tokio::test]
+    async fn test_nested_form() {
+        use crate::EndpointExt;
+
+        #[derive(Deserialize)]
+        struct Address {
+            city: String,
+        }
+
+        #[derive(Deserialize)]
+        struct User {
+            name: String,
+            address: Address,
+            tags: Vec<String>,
+        }
+
+        #[handler(internal)]
+        async fn index(Form(user): Form<User>) -> String {
+            format!("{} {} {:?}", user.name, user.address.city, user.tags)
+        }
+
+        let cli = TestClient::new(index.data(UrlEncodedConfig::new().nested(true)));
+        let resp = cli
+            .post("/")
+            .content_type("application/x-www-form-urlencoded")
+            .body("name=abc&address%5Bcity%5D=x&tags%5B%5D=a&tags%5B%5D=b")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text(r#"abc x ["a", "b"]"#).await;
+
+        let resp = cli.get("/?name=abc&address[city]=x&tags[]=a").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text(r#"abc x ["a"]"#).await;
+    }
 }
@@ -0,0 +1,72 @@
+use std::ops::Deref;
+
+use crate::{error::GetDataError, FromRequest, Request, RequestBody, Result};
+
+/// An extractor that gets the path pattern of the route matched by
+/// [`Route`](crate::Route), such as `/users/:id`.
+///
+/// The routes nested with [`Route::nest`](crate::Route::nest) include the
+/// prefix of the nesting. It is also inserted into the extensions of the
+/// response and the error, so the middlewares outside the router can label the
+/// requests by the route pattern instead of the raw paths.
+///
+/// # Errors
+///
+/// - [`GetDataError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{get, handler, test::TestClient, web::MatchedPath, Endpoint, EndpointExt, Route};
+///
+/// #[handler]
+/// fn user(matched_path: MatchedPath) -> String {
+///     matched_path.to_string()
+/// }
+///
+/// let app = Route::new()
+///     .nest("/api", Route::new().at("/users/:id", get(user)))
+///     .around(|ep, req| async move {
+///         let resp = ep.get_response(req).await;
+///         let matched_path = resp.data::<MatchedPath>().unwrap();
+///         assert_eq!(matched_path.as_str(), "/api/users/:id");
+///         Ok(resp)
+///     });
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/api/users/1")
+///     .send()
+///     .await
+///     .assert_text("/api/users/:id")
+///     .await;
+/// # });
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MatchedPath(pub(crate) String);
+
+impl MatchedPath {
+    /// Returns the path pattern as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for MatchedPath {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for MatchedPath {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<MatchedPath>()
+            .cloned()
+            .ok_or_else(|| GetDataError(std::any::type_name::<MatchedPath>()))?)
+    }
+}
@@ -2,18 +2,38 @@
 
 mod accept;
 mod addr;
+mod alpn_protocol;
+mod api_version;
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "protobuf"))]
+mod body_limit;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod client_cert;
+pub(crate) mod client_ip;
 #[cfg(feature = "compression")]
 mod compress;
+mod conditional;
+pub(crate) mod connection_info;
+pub(crate) mod container;
 #[cfg(feature = "cookie")]
 #[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
 pub mod cookie;
 mod data;
 mod form;
 mod json;
+mod matched_path;
+#[cfg(feature = "msgpack")]
+mod msgpack;
 #[cfg(feature = "multipart")]
 mod multipart;
+#[cfg(feature = "negotiate")]
+mod negotiate;
+mod pagination;
 mod path;
+#[cfg(feature = "protobuf")]
+mod protobuf;
 mod query;
+mod range;
 mod real_ip;
 mod redirect;
 #[cfg(feature = "sse")]
@@ -23,13 +43,19 @@ pub mod sse;
 mod static_file;
 #[cfg(feature = "tempfile")]
 mod tempfile;
+mod tls_info;
 #[cfg(feature = "xml")]
 mod xml;
 #[doc(inline)]
 pub use headers;
 #[cfg(feature = "csrf")]
-mod csrf;
+pub(crate) mod csrf;
 mod typed_header;
+#[cfg(feature = "serde-qs")]
+mod url_encoded;
+mod url_for;
+#[cfg(feature = "validator")]
+mod valid;
 #[cfg(feature = "websocket")]
 #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
 pub mod websocket;
@@ -39,31 +65,57 @@ use std::{convert::Infallible, fmt::Debug};
 use bytes::Bytes;
 use http::header;
 
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "protobuf"))]
+pub use self::body_limit::BodyLimit;
+#[cfg(feature = "cbor")]
+pub use self::cbor::Cbor;
 #[cfg(feature = "compression")]
-pub use self::compress::{Compress, CompressionAlgo};
+pub use self::compress::{Compress, CompressionAlgo, CompressionLevel};
 #[cfg(feature = "csrf")]
-pub use self::csrf::{CsrfToken, CsrfVerifier};
+pub use self::csrf::{CsrfToken, CsrfVerifier, VerifiedCsrfToken};
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::MsgPack;
 #[cfg(feature = "multipart")]
-pub use self::multipart::{Field, Multipart};
-pub(crate) use self::path::PathDeserializer;
+pub use self::multipart::{Field, FromMultipartField, Multipart, MultipartConfig, Upload};
+#[cfg(feature = "negotiate")]
+pub use self::negotiate::{MediaType, Negotiate};
+#[cfg(feature = "protobuf")]
+pub use self::protobuf::Protobuf;
 #[cfg(feature = "static-files")]
 pub use self::static_file::{StaticFileRequest, StaticFileResponse};
 #[cfg(feature = "tempfile")]
 pub use self::tempfile::TempFile;
+#[cfg(feature = "serde-qs")]
+pub use self::url_encoded::UrlEncodedConfig;
+#[cfg(feature = "validator")]
+pub use self::valid::Valid;
 #[cfg(feature = "xml")]
 pub use self::xml::Xml;
 pub use self::{
     accept::Accept,
     addr::{LocalAddr, RemoteAddr},
+    alpn_protocol::AlpnProtocol,
+    api_version::ApiVersion,
+    client_cert::ClientCert,
+    client_ip::{ClientIp, ForwardedHeader, TrustedProxies},
+    conditional::{Conditional, ConditionalHeaders, Precondition},
+    connection_info::ConnectionInfo,
+    container::{Container, Inject},
     data::Data,
     form::Form,
     json::Json,
+    matched_path::MatchedPath,
+    pagination::{Paginated, Pagination, PaginationConfig},
     path::Path,
     query::Query,
+    range::{Range, RangedBody},
     real_ip::RealIp,
     redirect::Redirect,
+    tls_info::{TlsInfo, TlsVersion},
     typed_header::TypedHeader,
+    url_for::UrlFor,
 };
+pub(crate) use self::{conditional::generate_etag, path::PathDeserializer};
 use crate::{
     body::Body,
     error::{ReadBodyError, Result},
@@ -129,6 +181,11 @@ impl RequestBody {
 ///
 ///    Extracts the remote peer's real ip address from request.
 ///
+/// - **ClientIp**
+///
+///    Extracts the client ip address from request, which is aware of the
+/// [`TrustedProxies`].
+///
 /// - **Method**
 ///
 ///    Extracts the [`Method`] from the incoming request.
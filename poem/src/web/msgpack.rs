@@ -0,0 +1,213 @@
+use std::ops::{Deref, DerefMut};
+
+use http::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::{MissingMsgPackContentTypeError, ParseMsgPackError},
+    http::header,
+    web::{BodyLimit, RequestBody},
+    FromRequest, IntoResponse, Request, Response, Result,
+};
+
+/// MessagePack extractor and response.
+///
+/// To extract the specified type of MessagePack from the body, `T` must
+/// implement [`serde::Deserialize`]. The `Content-Type` of the request must
+/// be `application/msgpack`, `application/x-msgpack` or
+/// `application/vnd.msgpack`, and the size of the body is limited by
+/// [`BodyLimit`].
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`MissingMsgPackContentTypeError`]
+/// - [`ParseMsgPackError`]
+///
+/// ```
+/// use poem::{handler, post, test::TestClient, web::MsgPack, Route};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[handler]
+/// async fn index(MsgPack(user): MsgPack<User>) -> String {
+///     format!("welcome {}!", user.name)
+/// }
+///
+/// let app = Route::new().at("/", post(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .body_msgpack(&User {
+///         name: "foo".to_string(),
+///     })
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("welcome foo!").await;
+/// # });
+/// ```
+///
+/// # Response
+///
+/// To serialize the specified type to MessagePack, `T` must implement
+/// [`serde::Serialize`]. The structs are serialized as maps with the field
+/// names.
+///
+/// ```
+/// use poem::{get, handler, test::TestClient, web::MsgPack, Route};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[handler]
+/// async fn index() -> MsgPack<User> {
+///     MsgPack(User {
+///         name: "foo".to_string(),
+///     })
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_content_type("application/msgpack");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct MsgPack<T>(pub T);
+
+impl<T> Deref for MsgPack<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for MsgPack<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, T: DeserializeOwned> FromRequest<'a> for MsgPack<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        if is_msgpack_content_type(req) {
+            let data = body.take()?.into_bytes_limit(BodyLimit::of(req)).await?;
+            Ok(Self(
+                rmp_serde::from_slice(&data).map_err(ParseMsgPackError)?,
+            ))
+        } else {
+            Err(MissingMsgPackContentTypeError.into())
+        }
+    }
+}
+
+fn is_msgpack_content_type(req: &Request) -> bool {
+    matches!(
+        req
+            .header(header::CONTENT_TYPE)
+            .and_then(|value| value.parse::<mime::Mime>().ok()),
+        Some(content_type)
+            if content_type.type_() == "application"
+                && matches!(content_type.subtype().as_str(), "msgpack" | "x-msgpack" | "vnd.msgpack"))
+}
+
+impl<T: Serialize + Send> IntoResponse for MsgPack<T> {
+    fn into_response(self) -> Response {
+        let data = match rmp_serde::to_vec_named(&self.0) {
+            Ok(data) => data,
+            Err(err) => {
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(err.to_string())
+            }
+        };
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/msgpack")
+            .body(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[derive(Deserialize, Serialize, Debug, Eq, PartialEq)]
+    struct CreateResource {
+        name: String,
+        value: i32,
+    }
+
+    fn resource() -> CreateResource {
+        CreateResource {
+            name: "abc".to_string(),
+            value: 100,
+        }
+    }
+
+    #[handler(internal)]
+    async fn echo(data: MsgPack<CreateResource>) -> MsgPack<CreateResource> {
+        data
+    }
+
+    #[tokio::test]
+    async fn test_msgpack() {
+        let cli = TestClient::new(echo);
+        let resp = cli.post("/").body_msgpack(&resource()).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("application/msgpack");
+        let data = resp.0.into_body().into_vec().await.unwrap();
+        assert_eq!(
+            rmp_serde::from_slice::<CreateResource>(&data).unwrap(),
+            resource()
+        );
+
+        let resp = cli
+            .post("/")
+            .content_type("application/x-msgpack")
+            .body(rmp_serde::to_vec(&resource()).unwrap())
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_extractor_fail() {
+        let cli = TestClient::new(echo.data(BodyLimit(8)));
+        cli.post("/")
+            .content_type("application/json")
+            .body(r#"{"name":"abc","value":100}"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        cli.post("/")
+            .content_type("application/msgpack")
+            .body("abc")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.post("/")
+            .body_msgpack(&resource())
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     fmt::{self, Debug, Formatter},
+    path::Path,
     str::FromStr,
 };
 
@@ -7,7 +8,7 @@ use futures_util::TryStreamExt;
 use mime::Mime;
 #[cfg(feature = "tempfile")]
 use tokio::fs::File;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 #[cfg(feature = "tempfile")]
 use tokio::io::{AsyncSeekExt, SeekFrom};
 
@@ -90,15 +91,167 @@ impl Field {
         Ok(file)
     }
 
+    /// Stream the field data to the file at `path` and return the number of
+    /// bytes written.
+    ///
+    /// The data is written in chunks, so the whole file is never buffered in
+    /// memory. If an error occurs, e.g. the field exceeds the
+    /// [`MultipartConfig::field_size_limit`], the partially written file is
+    /// removed.
+    pub async fn persist_to(self, path: impl AsRef<Path>) -> Result<u64, ParseMultipartError> {
+        let path = path.as_ref();
+        let mut reader = self.into_async_read();
+        let mut file = tokio::fs::File::create(path).await?;
+        let res = async {
+            let size = tokio::io::copy(&mut reader, &mut file).await?;
+            file.flush().await?;
+            Ok::<_, std::io::Error>(size)
+        }
+        .await;
+
+        match res {
+            Ok(size) => Ok(size),
+            Err(err) => {
+                drop(file);
+                let _ = tokio::fs::remove_file(path).await;
+                Err(err.into())
+            }
+        }
+    }
+
     /// Consume this field to return a reader.
     pub fn into_async_read(self) -> impl AsyncRead + Send {
         tokio_util::io::StreamReader::new(
             self.0
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string())),
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err)),
         )
     }
 }
 
+/// Configuration for the [`Multipart`] extractor.
+///
+/// Add it to the request data with
+/// [`EndpointExt::data`](crate::EndpointExt::data) to limit the uploads, no
+/// limits are applied by default.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler, post,
+///     web::{Multipart, MultipartConfig},
+///     EndpointExt, Result, Route,
+/// };
+///
+/// #[handler]
+/// async fn upload(mut multipart: Multipart) -> Result<()> {
+///     while let Some(field) = multipart.next_field().await? {
+///         if let Some(file_name) = field.file_name().map(ToString::to_string) {
+///             field
+///                 .persist_to(std::env::temp_dir().join(file_name))
+///                 .await?;
+///         }
+///     }
+///     Ok(())
+/// }
+///
+/// let app = Route::new().at("/upload", post(upload)).data(
+///     MultipartConfig::new()
+///         .size_limit(100 * 1024 * 1024)
+///         .field_size_limit(10 * 1024 * 1024)
+///         .max_files(10)
+///         .allowed_content_types(["image/*", "application/pdf"]),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+#[derive(Debug, Clone, Default)]
+pub struct MultipartConfig {
+    size_limit: Option<u64>,
+    field_size_limit: Option<u64>,
+    max_files: Option<usize>,
+    allowed_content_types: Vec<String>,
+}
+
+impl MultipartConfig {
+    /// Create a new `MultipartConfig` without any limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the maximum size of the whole multipart stream in bytes.
+    #[must_use]
+    pub fn size_limit(self, limit: u64) -> Self {
+        Self {
+            size_limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of each field in bytes.
+    #[must_use]
+    pub fn field_size_limit(self, limit: u64) -> Self {
+        Self {
+            field_size_limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of files, a file is a field with a file name.
+    #[must_use]
+    pub fn max_files(self, max_files: usize) -> Self {
+        Self {
+            max_files: Some(max_files),
+            ..self
+        }
+    }
+
+    /// Sets the allowed content types of the files, wildcards such as
+    /// `image/*` are supported.
+    ///
+    /// A file without the `Content-Type` is treated as
+    /// `application/octet-stream`.
+    #[must_use]
+    pub fn allowed_content_types<I, T>(self, content_types: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        Self {
+            allowed_content_types: content_types
+                .into_iter()
+                .map(|content_type| content_type.as_ref().to_ascii_lowercase())
+                .collect(),
+            ..self
+        }
+    }
+
+    fn constraints(&self) -> multer::Constraints {
+        let mut size_limit = multer::SizeLimit::new();
+        if let Some(limit) = self.size_limit {
+            size_limit = size_limit.whole_stream(limit);
+        }
+        if let Some(limit) = self.field_size_limit {
+            size_limit = size_limit.per_field(limit);
+        }
+        multer::Constraints::new().size_limit(size_limit)
+    }
+
+    fn is_allowed(&self, content_type: &str) -> bool {
+        if self.allowed_content_types.is_empty() {
+            return true;
+        }
+        let content_type = content_type.to_ascii_lowercase();
+        self.allowed_content_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some("*") => true,
+                Some(ty) => content_type.split('/').next() == Some(ty),
+                None => *allowed == content_type,
+            })
+    }
+}
+
 /// An extractor that parses `multipart/form-data` requests commonly used with
 /// file uploads.
 ///
@@ -107,6 +260,8 @@ impl Field {
 /// - [`ReadBodyError`](crate::error::ReadBodyError)
 /// - [`ParseMultipartError`]
 ///
+/// The uploads can be limited with [`MultipartConfig`].
+///
 /// # Example
 ///
 /// ```
@@ -124,9 +279,50 @@ impl Field {
 ///     Ok(())
 /// }
 /// ```
+///
+/// # Typed forms
+///
+/// Use [`FromMultipart`](crate::FromMultipart) to extract a struct from the
+/// multipart body, the errors are reported with the names of the fields.
+///
+/// ```
+/// use poem::{handler, post, test::TestClient, web::Upload, FromMultipart, Route};
+///
+/// #[derive(FromMultipart)]
+/// struct UploadForm {
+///     title: String,
+///     description: Option<String>,
+///     files: Vec<Upload>,
+/// }
+///
+/// #[handler]
+/// async fn upload(form: UploadForm) -> String {
+///     format!("{}: {} files", form.title, form.files.len())
+/// }
+///
+/// let app = Route::new().at("/", post(upload));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .header("content-type", "multipart/form-data; boundary=X")
+///     .body(
+///         "--X\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n\
+///          --X\r\nContent-Disposition: form-data; name=\"files\"; filename=\"a.txt\"\r\n\r\nabc\r\n\
+///          --X--\r\n",
+///     )
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("hello: 1 files").await;
+/// # });
+/// ```
 #[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
 pub struct Multipart {
     inner: multer::Multipart<'static>,
+    config: MultipartConfig,
+    files: usize,
 }
 
 #[async_trait::async_trait]
@@ -148,11 +344,15 @@ impl<'a> FromRequest<'a> for Multipart {
 
         let boundary = multer::parse_boundary(content_type.as_ref())
             .map_err(ParseMultipartError::Multipart)?;
+        let config = req.data::<MultipartConfig>().cloned().unwrap_or_default();
         Ok(Self {
-            inner: multer::Multipart::new(
+            inner: multer::Multipart::with_constraints(
                 tokio_util::io::ReaderStream::new(body.take()?.into_async_read()),
                 boundary,
+                config.constraints(),
             ),
+            config,
+            files: 0,
         })
     }
 }
@@ -160,17 +360,209 @@ impl<'a> FromRequest<'a> for Multipart {
 impl Multipart {
     /// Yields the next [`Field`] if available.
     pub async fn next_field(&mut self) -> Result<Option<Field>, ParseMultipartError> {
-        match self.inner.next_field().await? {
-            Some(field) => Ok(Some(Field(field))),
-            None => Ok(None),
+        let field = match self.inner.next_field().await? {
+            Some(field) => Field(field),
+            None => return Ok(None),
+        };
+
+        if field.file_name().is_some() {
+            self.files += 1;
+            if let Some(max_files) = self.config.max_files {
+                if self.files > max_files {
+                    return Err(ParseMultipartError::TooManyFiles(max_files));
+                }
+            }
+
+            let content_type = field
+                .content_type()
+                .unwrap_or_else(|| mime::APPLICATION_OCTET_STREAM.essence_str());
+            if !self.config.is_allowed(content_type) {
+                return Err(ParseMultipartError::ContentTypeNotAllowed(
+                    content_type.to_string(),
+                ));
+            }
+        }
+
+        Ok(Some(field))
+    }
+}
+
+/// A file uploaded in a multipart form, see
+/// [`FromMultipart`](crate::FromMultipart).
+///
+/// The data of the file is read into memory, use [`MultipartConfig`] to limit
+/// the size of it.
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Upload {
+    file_name: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+impl Upload {
+    /// Get the content type of the file.
+    #[inline]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Get the file name of the file.
+    #[inline]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Get the size of the file in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Get the data of the file.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes this file to return the data.
+    #[inline]
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Write the data of the file to `path`.
+    pub async fn persist_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        tokio::fs::write(path, &self.data).await
+    }
+}
+
+/// Represents a type that can be parsed from a multipart field, used by
+/// [`FromMultipart`](crate::FromMultipart).
+#[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+#[async_trait::async_trait]
+pub trait FromMultipartField: Sized + Send {
+    /// Parse from the field, `prev` is the value parsed from the previous
+    /// fields with the same name.
+    async fn from_multipart_field(
+        field: Field,
+        prev: Option<Self>,
+    ) -> Result<Self, ParseMultipartError>;
+
+    /// Returns the value if the field is missing, `None` means that the field
+    /// is required.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl FromMultipartField for String {
+    async fn from_multipart_field(
+        field: Field,
+        _prev: Option<Self>,
+    ) -> Result<Self, ParseMultipartError> {
+        field.text().await
+    }
+}
+
+macro_rules! impl_from_multipart_field_for_from_str {
+    ($($ty:ty),*) => {
+        $(
+        #[async_trait::async_trait]
+        impl FromMultipartField for $ty {
+            async fn from_multipart_field(
+                field: Field,
+                _prev: Option<Self>,
+            ) -> Result<Self, ParseMultipartError> {
+                field
+                    .text()
+                    .await?
+                    .parse()
+                    .map_err(|err: <$ty as FromStr>::Err| {
+                        ParseMultipartError::InvalidValue(err.to_string())
+                    })
+            }
         }
+        )*
+    };
+}
+
+impl_from_multipart_field_for_from_str!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+#[async_trait::async_trait]
+impl FromMultipartField for Upload {
+    async fn from_multipart_field(
+        field: Field,
+        _prev: Option<Self>,
+    ) -> Result<Self, ParseMultipartError> {
+        let file_name = field.file_name().map(ToString::to_string);
+        let content_type = field.content_type().map(ToString::to_string);
+        Ok(Self {
+            file_name,
+            content_type,
+            data: field.bytes().await?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: FromMultipartField> FromMultipartField for Option<T> {
+    async fn from_multipart_field(
+        field: Field,
+        prev: Option<Self>,
+    ) -> Result<Self, ParseMultipartError> {
+        Ok(Some(T::from_multipart_field(field, prev.flatten()).await?))
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: FromMultipartField> FromMultipartField for Vec<T> {
+    async fn from_multipart_field(
+        field: Field,
+        prev: Option<Self>,
+    ) -> Result<Self, ParseMultipartError> {
+        let mut values = prev.unwrap_or_default();
+        values.push(T::from_multipart_field(field, None).await?);
+        Ok(values)
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(Vec::new())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{handler, http::StatusCode, test::TestClient};
+    use crate::{handler, http::StatusCode, test::TestClient, EndpointExt};
+
+    const FILES: &str = "--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"a\"; filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\n0123456789\r\n--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"b\"; filename=\"b.txt\"\r\nContent-Type: text/plain\r\n\r\nabc\r\n--X-BOUNDARY--\r\n";
+
+    #[handler(internal)]
+    async fn read_all(mut multipart: Multipart) -> Result<()> {
+        while let Some(field) = multipart.next_field().await? {
+            field.bytes().await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_files(config: MultipartConfig) -> StatusCode {
+        TestClient::new(read_all.data(config))
+            .post("/")
+            .header("content-type", "multipart/form-data; boundary=X-BOUNDARY")
+            .body(FILES)
+            .send()
+            .await
+            .0
+            .status()
+    }
 
     #[tokio::test]
     async fn test_multipart_extractor_content_type() {
@@ -218,4 +610,149 @@ mod tests {
             .await;
         resp.assert_status_is_ok();
     }
+
+    #[tokio::test]
+    async fn test_multipart_size_limit() {
+        assert_eq!(upload_files(MultipartConfig::new()).await, StatusCode::OK);
+        assert_eq!(
+            upload_files(MultipartConfig::new().field_size_limit(10)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            upload_files(MultipartConfig::new().field_size_limit(5)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            upload_files(MultipartConfig::new().size_limit(64)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multipart_max_files() {
+        assert_eq!(
+            upload_files(MultipartConfig::new().max_files(2)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            upload_files(MultipartConfig::new().max_files(1)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multipart_allowed_content_types() {
+        assert_eq!(
+            upload_files(MultipartConfig::new().allowed_content_types(["image/*", "text/plain"]))
+                .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            upload_files(MultipartConfig::new().allowed_content_types(["*/*"])).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            upload_files(MultipartConfig::new().allowed_content_types(["image/*"])).await,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multipart_persist_to() {
+        let dir = std::env::temp_dir().join(format!("poem-multipart-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        #[handler(internal)]
+        async fn index(
+            mut multipart: Multipart,
+            dir: crate::web::Data<&std::path::PathBuf>,
+        ) -> Result<()> {
+            let field = multipart.next_field().await?.unwrap();
+            assert_eq!(field.persist_to(dir.join("a.png")).await?, 10);
+            Ok(())
+        }
+
+        for (config, status) in [
+            (MultipartConfig::new(), StatusCode::OK),
+            (
+                MultipartConfig::new().field_size_limit(5),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+        ] {
+            TestClient::new(index.data(dir.clone()).data(config))
+                .post("/")
+                .header("content-type", "multipart/form-data; boundary=X-BOUNDARY")
+                .body(FILES)
+                .send()
+                .await
+                .assert_status(status);
+
+            if status == StatusCode::OK {
+                assert_eq!(std::fs::read(dir.join("a.png")).unwrap(), b"0123456789");
+            } else {
+                // the partially written file is removed
+                assert!(!dir.join("a.png").exists());
+            }
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_derive_from_multipart() {
+        use crate as poem;
+
+        #[derive(crate::FromMultipart)]
+        struct UploadForm {
+            title: String,
+            #[multipart(rename = "desc")]
+            description: Option<String>,
+            count: Option<u32>,
+            files: Vec<Upload>,
+        }
+
+        #[handler(internal)]
+        async fn index(form: UploadForm) -> String {
+            let files = form
+                .files
+                .iter()
+                .map(|file| {
+                    format!(
+                        "{}:{}:{}",
+                        file.file_name().unwrap(),
+                        file.content_type().unwrap(),
+                        file.size()
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "{} {:?} {:?} {:?}",
+                form.title, form.description, form.count, files
+            )
+        }
+
+        let cli = TestClient::new(index);
+        let send = |data: &'static str| {
+            cli.post("/")
+                .header("content-type", "multipart/form-data; boundary=X-BOUNDARY")
+                .body(data)
+                .send()
+        };
+
+        send("--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"desc\"\r\n\r\nworld\r\n--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"files\"; filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\n0123456789\r\n--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"files\"; filename=\"b.txt\"\r\nContent-Type: text/plain\r\n\r\nabc\r\n--X-BOUNDARY--\r\n")
+            .await
+            .assert_text(r#"hello Some("world") None ["a.png:image/png:10", "b.txt:text/plain:3"]"#)
+            .await;
+
+        let resp = send("--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"desc\"\r\n\r\nworld\r\n--X-BOUNDARY--\r\n").await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        resp.assert_text("the field `title` is required").await;
+
+        let resp = send("--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"count\"\r\n\r\nabc\r\n--X-BOUNDARY--\r\n").await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        resp.assert_text(
+            "failed to parse the field `count`: invalid value: invalid digit found in string",
+        )
+        .await;
+    }
 }
@@ -0,0 +1,253 @@
+use mime::Mime;
+use serde::Serialize;
+
+use crate::{
+    error::NotAcceptableError,
+    http::{header, HeaderValue},
+    web::{accept::parse_accept, Cbor, Json, MsgPack, Xml},
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+/// The media type of the response chosen by the content negotiation.
+///
+/// As an extractor, it is chosen by the `Accept` header of the request, the
+/// first acceptable media type with the highest quality is used. It is
+/// [`MediaType::Json`] if the header is missing or accepts any media types.
+///
+/// # Errors
+///
+/// - [`NotAcceptableError`]
+///
+/// See also [`Negotiate`].
+#[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MediaType {
+    /// `application/json`
+    Json,
+    /// `application/xml`
+    Xml,
+    /// `application/msgpack`
+    MsgPack,
+    /// `application/cbor`
+    Cbor,
+}
+
+impl MediaType {
+    /// Returns the value of the `Content-Type` header for this media type.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MediaType::Json => "application/json; charset=utf-8",
+            MediaType::Xml => "application/xml; charset=utf-8",
+            MediaType::MsgPack => "application/msgpack",
+            MediaType::Cbor => "application/cbor",
+        }
+    }
+
+    /// Chooses the media type from the media types of the `Accept` header,
+    /// which are sorted by the quality.
+    ///
+    /// Returns `None` if none of them is supported.
+    pub fn from_accept(accept: &[Mime]) -> Option<Self> {
+        if accept.is_empty() {
+            return Some(MediaType::Json);
+        }
+        accept.iter().find_map(
+            |mime| match (mime.type_().as_str(), mime.subtype().as_str()) {
+                ("*", "*") | ("application", "*") | ("application", "json") => {
+                    Some(MediaType::Json)
+                }
+                ("application", "xml") | ("text", "xml") => Some(MediaType::Xml),
+                ("application", "msgpack" | "x-msgpack" | "vnd.msgpack") => {
+                    Some(MediaType::MsgPack)
+                }
+                ("application", "cbor") => Some(MediaType::Cbor),
+                _ => match mime.suffix().map(|suffix| suffix.as_str()) {
+                    Some("json") => Some(MediaType::Json),
+                    Some("xml") => Some(MediaType::Xml),
+                    Some("cbor") => Some(MediaType::Cbor),
+                    _ => None,
+                },
+            },
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for MediaType {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(Self::from_accept(&parse_accept(req.headers())).ok_or(NotAcceptableError)?)
+    }
+}
+
+/// A response that serializes `T` as the negotiated [`MediaType`].
+///
+/// The `Vary: Accept` header is added to the response, so that the caches
+/// store the responses of the different media types separately.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::{header, StatusCode},
+///     test::TestClient,
+///     web::{MediaType, Negotiate},
+///     Route,
+/// };
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[handler]
+/// fn index(media_type: MediaType) -> Negotiate<User> {
+///     Negotiate(
+///         media_type,
+///         User {
+///             name: "foo".to_string(),
+///         },
+///     )
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .get("/")
+///     .header(header::ACCEPT, "application/json")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text(r#"{"name":"foo"}"#).await;
+///
+/// let resp = cli
+///     .get("/")
+///     .header(header::ACCEPT, "text/html, application/xml;q=0.9")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text(r#"<User name="foo"/>"#).await;
+///
+/// let resp = cli
+///     .get("/")
+///     .header(header::ACCEPT, "text/html")
+///     .send()
+///     .await;
+/// resp.assert_status(StatusCode::NOT_ACCEPTABLE);
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Negotiate<T>(pub MediaType, pub T);
+
+impl<T: Serialize + Send> IntoResponse for Negotiate<T> {
+    fn into_response(self) -> Response {
+        let Negotiate(media_type, value) = self;
+        let mut resp = match media_type {
+            MediaType::Json => Json(value).into_response(),
+            MediaType::Xml => Xml(value).into_response(),
+            MediaType::MsgPack => MsgPack(value).into_response(),
+            MediaType::Cbor => Cbor(value).into_response(),
+        };
+        resp.headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("accept"));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient};
+
+    #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+    struct User {
+        name: String,
+        age: i32,
+    }
+
+    fn user() -> User {
+        User {
+            name: "foo".to_string(),
+            age: 18,
+        }
+    }
+
+    #[test]
+    fn from_accept() {
+        let cases = [
+            (vec![], Some(MediaType::Json)),
+            (vec!["*/*"], Some(MediaType::Json)),
+            (vec!["text/html", "application/*"], Some(MediaType::Json)),
+            (vec!["application/vnd.api+json"], Some(MediaType::Json)),
+            (vec!["text/xml"], Some(MediaType::Xml)),
+            (vec!["application/atom+xml"], Some(MediaType::Xml)),
+            (vec!["application/x-msgpack"], Some(MediaType::MsgPack)),
+            (vec!["text/html", "application/cbor"], Some(MediaType::Cbor)),
+            (vec!["text/html", "image/*"], None),
+        ];
+        for (accept, media_type) in cases {
+            let accept = accept
+                .into_iter()
+                .map(|mime| mime.parse().unwrap())
+                .collect::<Vec<Mime>>();
+            assert_eq!(MediaType::from_accept(&accept), media_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn negotiate() {
+        #[handler(internal)]
+        fn index(media_type: MediaType) -> Negotiate<User> {
+            Negotiate(media_type, user())
+        }
+
+        let cli = TestClient::new(index);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("application/json; charset=utf-8");
+        resp.assert_header("vary", "accept");
+        resp.assert_json(&user()).await;
+
+        let resp = cli
+            .get("/")
+            .header(header::ACCEPT, "application/json;q=0.5, application/xml")
+            .send()
+            .await;
+        resp.assert_content_type("application/xml; charset=utf-8");
+        resp.assert_text(r#"<User name="foo" age="18"/>"#).await;
+
+        let resp = cli
+            .get("/")
+            .header(header::ACCEPT, "application/msgpack")
+            .send()
+            .await;
+        resp.assert_content_type("application/msgpack");
+        let data = resp.0.into_body().into_vec().await.unwrap();
+        assert_eq!(rmp_serde::from_slice::<User>(&data).unwrap(), user());
+
+        let resp = cli
+            .get("/")
+            .header(header::ACCEPT, "application/cbor")
+            .send()
+            .await;
+        resp.assert_content_type("application/cbor");
+        let data = resp.0.into_body().into_vec().await.unwrap();
+        assert_eq!(
+            ciborium::de::from_reader::<User, _>(data.as_slice()).unwrap(),
+            user()
+        );
+
+        cli.get("/")
+            .header(header::ACCEPT, "text/html")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_ACCEPTABLE);
+    }
+}
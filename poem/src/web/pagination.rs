@@ -0,0 +1,385 @@
+use crate::{
+    error::ParsePaginationError,
+    http::{header, HeaderValue},
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+const PAGE: &str = "page";
+const PER_PAGE: &str = "per_page";
+const CURSOR: &str = "cursor";
+
+/// Configuration for the [`Pagination`] extractor.
+///
+/// Add it to the request data with
+/// [`EndpointExt::data`](crate::EndpointExt::data), the default number of
+/// the items per page is `20` and the maximum is `100`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PaginationConfig {
+    default_per_page: u64,
+    max_per_page: u64,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            default_per_page: 20,
+            max_per_page: 100,
+        }
+    }
+}
+
+impl PaginationConfig {
+    /// Create a new `PaginationConfig`.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the number of the items per page if `per_page` is not specified.
+    #[must_use]
+    pub fn default_per_page(self, per_page: u64) -> Self {
+        Self {
+            default_per_page: per_page,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of the items per page, the larger `per_page`
+    /// is capped to it.
+    #[must_use]
+    pub fn max_per_page(self, per_page: u64) -> Self {
+        Self {
+            max_per_page: per_page,
+            ..self
+        }
+    }
+}
+
+/// An extractor for the pagination parameters in the query string.
+///
+/// - `page` - The page number starting from `1`, defaults to `1`.
+/// - `per_page` - The number of the items per page, defaults to and capped by
+///   [`PaginationConfig`].
+/// - `cursor` - The opaque cursor for the cursor-based pagination.
+///
+/// Respond with [`Paginated`] to generate the `Link` and `X-Total-Count`
+/// headers.
+///
+/// # Errors
+///
+/// - [`ParseQueryError`](crate::error::ParseQueryError)
+/// - [`ParsePaginationError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::header,
+///     test::TestClient,
+///     web::{Json, Paginated, Pagination},
+///     Route,
+/// };
+///
+/// #[handler]
+/// fn list(pagination: Pagination) -> Paginated<Json<Vec<u64>>> {
+///     let items = (1..=95)
+///         .skip(pagination.offset() as usize)
+///         .take(pagination.limit() as usize)
+///         .collect();
+///     Paginated::new(pagination, Json(items)).total(95)
+/// }
+///
+/// let app = Route::new().at("/items", get(list));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/items").query("page", &2).send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_header("x-total-count", "95");
+/// resp.assert_header(
+///     header::LINK,
+///     r#"</items?page=1&per_page=20>; rel="first", </items?page=1&per_page=20>; rel="prev", </items?page=3&per_page=20>; rel="next", </items?page=5&per_page=20>; rel="last""#,
+/// );
+/// # });
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pagination {
+    page: u64,
+    per_page: u64,
+    cursor: Option<String>,
+    path: String,
+    params: Vec<(String, String)>,
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for Pagination {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let config = req.data::<PaginationConfig>().copied().unwrap_or_default();
+        let mut page = 1;
+        let mut per_page = config.default_per_page;
+        let mut cursor = None;
+        let mut params = Vec::new();
+
+        for (name, value) in req.params::<Vec<(String, String)>>()? {
+            match name.as_str() {
+                PAGE => {
+                    page = value
+                        .parse()
+                        .ok()
+                        .filter(|page| *page > 0)
+                        .ok_or(ParsePaginationError::InvalidPage)?
+                }
+                PER_PAGE => {
+                    per_page = value
+                        .parse()
+                        .ok()
+                        .filter(|per_page| *per_page > 0)
+                        .ok_or(ParsePaginationError::InvalidPerPage)?
+                }
+                CURSOR => cursor = Some(value).filter(|cursor| !cursor.is_empty()),
+                _ => params.push((name, value)),
+            }
+        }
+
+        Ok(Self {
+            page,
+            per_page: per_page.min(config.max_per_page),
+            cursor,
+            path: req.original_uri().path().to_string(),
+            params,
+        })
+    }
+}
+
+impl Pagination {
+    /// Returns the page number starting from `1`.
+    #[inline]
+    pub fn page(&self) -> u64 {
+        self.page
+    }
+
+    /// Returns the number of the items per page.
+    #[inline]
+    pub fn per_page(&self) -> u64 {
+        self.per_page
+    }
+
+    /// Returns the cursor.
+    #[inline]
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Returns the number of the items to skip, which is
+    /// `(page - 1) * per_page`.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+
+    /// Returns the number of the items to take, which is `per_page`.
+    #[inline]
+    pub fn limit(&self) -> u64 {
+        self.per_page
+    }
+
+    fn link(&self, rel: &str, pagination: &[(&str, String)]) -> String {
+        let params = self
+            .params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(
+                pagination
+                    .iter()
+                    .map(|(name, value)| (*name, value.as_str())),
+            )
+            .collect::<Vec<_>>();
+        format!(
+            "<{}?{}>; rel=\"{}\"",
+            self.path,
+            serde_urlencoded::to_string(params).unwrap_or_default(),
+            rel
+        )
+    }
+
+    fn page_link(&self, rel: &str, page: u64) -> String {
+        self.link(
+            rel,
+            &[
+                (PAGE, page.to_string()),
+                (PER_PAGE, self.per_page.to_string()),
+            ],
+        )
+    }
+}
+
+/// A response for the paginated list, which adds the
+/// [RFC 5988](https://www.rfc-editor.org/rfc/rfc5988) `Link` header and the
+/// `X-Total-Count` header.
+///
+/// For the page-based pagination, the `first` and `prev` links are generated,
+/// and the `next` and `last` links are generated if the total count is
+/// specified with [`Paginated::total`].
+///
+/// For the cursor-based pagination, the `first` link and the `next` link
+/// with the cursor specified with [`Paginated::next_cursor`] are generated.
+///
+/// The other query parameters of the request are preserved in the links.
+pub struct Paginated<T> {
+    pagination: Pagination,
+    inner: T,
+    total: Option<u64>,
+    next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    /// Create a `Paginated` response.
+    pub fn new(pagination: Pagination, inner: T) -> Self {
+        Self {
+            pagination,
+            inner,
+            total: None,
+            next_cursor: None,
+        }
+    }
+
+    /// Sets the total count of the items.
+    #[must_use]
+    pub fn total(self, total: u64) -> Self {
+        Self {
+            total: Some(total),
+            ..self
+        }
+    }
+
+    /// Sets the cursor of the next page, which switches the links to the
+    /// cursor-based pagination.
+    #[must_use]
+    pub fn next_cursor(self, cursor: impl Into<String>) -> Self {
+        Self {
+            next_cursor: Some(cursor.into()),
+            ..self
+        }
+    }
+
+    fn links(&self) -> Vec<String> {
+        let pagination = &self.pagination;
+        let mut links = Vec::new();
+
+        if let Some(cursor) = &self.next_cursor {
+            let per_page = (PER_PAGE, pagination.per_page.to_string());
+            links.push(pagination.link("first", std::slice::from_ref(&per_page)));
+            links.push(pagination.link("next", &[(CURSOR, cursor.clone()), per_page]));
+            return links;
+        }
+
+        links.push(pagination.page_link("first", 1));
+        if pagination.page > 1 {
+            links.push(pagination.page_link("prev", pagination.page - 1));
+        }
+        if let Some(total) = self.total {
+            let per_page = pagination.per_page;
+            let last = match per_page {
+                0 => 1,
+                _ => ((total + per_page - 1) / per_page).max(1),
+            };
+            if pagination.page < last {
+                links.push(pagination.page_link("next", pagination.page + 1));
+            }
+            links.push(pagination.page_link("last", last));
+        }
+        links
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for Paginated<T> {
+    fn into_response(self) -> Response {
+        let links = self.links().join(", ");
+        let mut resp = self.inner.into_response();
+
+        if let Ok(value) = HeaderValue::from_str(&links) {
+            resp.headers_mut().insert(header::LINK, value);
+        }
+        if let Some(total) = self.total {
+            resp.headers_mut()
+                .insert("x-total-count", HeaderValue::from(total));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient, EndpointExt};
+
+    #[handler(internal)]
+    fn list(pagination: Pagination) -> Paginated<String> {
+        let body = format!(
+            "{} {} {:?}",
+            pagination.offset(),
+            pagination.limit(),
+            pagination.cursor()
+        );
+        match pagination.cursor() {
+            Some(_) => Paginated::new(pagination, body).next_cursor("abc"),
+            None => Paginated::new(pagination, body).total(45),
+        }
+    }
+
+    #[tokio::test]
+    async fn pagination() {
+        let cli = TestClient::new(list.data(PaginationConfig::new().default_per_page(10)));
+
+        let resp = cli.get("/items").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("x-total-count", "45");
+        resp.assert_header(
+            "link",
+            r#"</items?page=1&per_page=10>; rel="first", </items?page=2&per_page=10>; rel="next", </items?page=5&per_page=10>; rel="last""#,
+        );
+        resp.assert_text("0 10 None").await;
+
+        let resp = cli.get("/items?q=a+b&page=5&per_page=500").send().await;
+        resp.assert_header(
+            "link",
+            r#"</items?q=a+b&page=1&per_page=100>; rel="first", </items?q=a+b&page=4&per_page=100>; rel="prev", </items?q=a+b&page=1&per_page=100>; rel="last""#,
+        );
+        resp.assert_text("400 100 None").await;
+
+        cli.get("/items?page=0")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/items?per_page=x")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn zero_per_page() {
+        let cli = TestClient::new(list.data(PaginationConfig::new().max_per_page(0)));
+        let resp = cli.get("/items?page=2").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header(
+            "link",
+            r#"</items?page=1&per_page=0>; rel="first", </items?page=1&per_page=0>; rel="prev", </items?page=1&per_page=0>; rel="last""#,
+        );
+        resp.assert_text("0 0 None").await;
+    }
+
+    #[tokio::test]
+    async fn cursor_pagination() {
+        let cli = TestClient::new(list);
+        let resp = cli.get("/items?cursor=xyz&per_page=5").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("x-total-count");
+        resp.assert_header(
+            "link",
+            r#"</items?per_page=5>; rel="first", </items?cursor=abc&per_page=5>; rel="next""#,
+        );
+        resp.assert_text(r#"0 5 Some("xyz")"#).await;
+    }
+}
@@ -99,6 +99,39 @@ use crate::{error::ParsePathError, FromRequest, Request, RequestBody, Result};
 /// resp.assert_text("foo:100").await;
 /// # });
 /// ```
+///
+/// Use the [`path!`](crate::path) macro to define a typed path, the segments
+/// which can not be parsed into the types of the parameters are rejected by
+/// the router with `404 Not Found` instead of `400 Bad Request`.
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::{StatusCode, Uri},
+///     path,
+///     test::TestClient,
+///     Endpoint, Request, Route,
+/// };
+///
+/// path!(UserPostPath = "/users/{id: u64}/posts/{slug}");
+///
+/// #[handler]
+/// async fn user_post(path: UserPostPath) -> String {
+///     format!("{}:{}", path.id, path.slug)
+/// }
+///
+/// let app = Route::new().at(UserPostPath::PATTERN, get(user_post));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/users/100/posts/hello").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("100:hello").await;
+///
+/// let resp = cli.get("/users/foo/posts/hello").send().await;
+/// resp.assert_status(StatusCode::NOT_FOUND);
+/// # });
+/// ```
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Path<T>(pub T);
 
@@ -131,3 +164,46 @@ impl<'a, T: DeserializeOwned> FromRequest<'a> for Path<T> {
         Self::internal_from_request(req).await.map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use crate as poem;
+    use crate::{handler, path, test::TestClient, Route};
+
+    path!(TypedPath = "/a/{id: i32}/{enabled: bool}/{name}/{*rest}");
+
+    #[tokio::test]
+    async fn typed_path() {
+        assert_eq!(
+            TypedPath::PATTERN,
+            "/a/:id<^[+-]?\\d+>/:enabled<^(true|false)>/:name/*rest"
+        );
+
+        #[handler(internal)]
+        fn index(path: TypedPath) -> String {
+            format!("{} {} {} {}", path.id, path.enabled, path.name, path.rest)
+        }
+
+        let cli = TestClient::new(Route::new().at(TypedPath::PATTERN, index));
+        cli.get("/a/-10/true/foo/b/c")
+            .send()
+            .await
+            .assert_text("-10 true foo b/c")
+            .await;
+        cli.get("/a/10x/true/foo/b")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        cli.get("/a/10/yes/foo/b")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        // overflow
+        cli.get("/a/10000000000/true/foo/b")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
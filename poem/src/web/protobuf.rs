@@ -0,0 +1,206 @@
+use std::ops::{Deref, DerefMut};
+
+use prost::Message;
+
+use crate::{
+    error::{MissingProtobufContentTypeError, ParseProtobufError},
+    http::header,
+    web::{BodyLimit, RequestBody},
+    FromRequest, IntoResponse, Request, Response, Result,
+};
+
+/// Protocol Buffers extractor and response.
+///
+/// To extract the specified type of Protocol Buffers message from the body,
+/// `T` must implement [`prost::Message`] and [`Default`]. The `Content-Type`
+/// of the request must be `application/x-protobuf`, `application/protobuf`
+/// or `application/vnd.google.protobuf`, and the size of the body is limited
+/// by [`BodyLimit`].
+///
+/// Use it for the plain HTTP endpoints, see
+/// [`poem-grpc`](https://crates.io/crates/poem-grpc) for the gRPC services.
+///
+/// # Errors
+///
+/// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`MissingProtobufContentTypeError`]
+/// - [`ParseProtobufError`]
+///
+/// ```
+/// use poem::{handler, post, test::TestClient, web::Protobuf, Route};
+///
+/// #[derive(Clone, PartialEq, prost::Message)]
+/// struct User {
+///     #[prost(string, tag = "1")]
+///     name: String,
+/// }
+///
+/// #[handler]
+/// async fn index(Protobuf(user): Protobuf<User>) -> String {
+///     format!("welcome {}!", user.name)
+/// }
+///
+/// let app = Route::new().at("/", post(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .body_protobuf(&User {
+///         name: "foo".to_string(),
+///     })
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("welcome foo!").await;
+/// # });
+/// ```
+///
+/// # Response
+///
+/// To encode the specified type to Protocol Buffers, `T` must implement
+/// [`prost::Message`].
+///
+/// ```
+/// use poem::{get, handler, test::TestClient, web::Protobuf, Route};
+///
+/// #[derive(Clone, PartialEq, prost::Message)]
+/// struct User {
+///     #[prost(string, tag = "1")]
+///     name: String,
+/// }
+///
+/// #[handler]
+/// async fn index() -> Protobuf<User> {
+///     Protobuf(User {
+///         name: "foo".to_string(),
+///     })
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_content_type("application/x-protobuf");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "protobuf")))]
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Protobuf<T>(pub T);
+
+impl<T> Deref for Protobuf<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Protobuf<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, T: Message + Default> FromRequest<'a> for Protobuf<T> {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        if is_protobuf_content_type(req) {
+            let data = body.take()?.into_bytes_limit(BodyLimit::of(req)).await?;
+            Ok(Self(T::decode(data).map_err(ParseProtobufError)?))
+        } else {
+            Err(MissingProtobufContentTypeError.into())
+        }
+    }
+}
+
+fn is_protobuf_content_type(req: &Request) -> bool {
+    matches!(
+    req
+        .header(header::CONTENT_TYPE)
+        .and_then(|value| value.parse::<mime::Mime>().ok()),
+    Some(content_type)
+        if content_type.type_() == "application"
+            && matches!(
+                content_type.subtype().as_str(),
+                "x-protobuf" | "protobuf" | "vnd.google.protobuf"
+            ))
+}
+
+impl<T: Message> IntoResponse for Protobuf<T> {
+    fn into_response(self) -> Response {
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/x-protobuf")
+            .body(self.0.encode_to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{handler, test::TestClient, EndpointExt};
+
+    #[derive(Clone, PartialEq, Message)]
+    struct CreateResource {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(int32, tag = "2")]
+        value: i32,
+    }
+
+    fn resource() -> CreateResource {
+        CreateResource {
+            name: "abc".to_string(),
+            value: 100,
+        }
+    }
+
+    #[handler(internal)]
+    async fn echo(data: Protobuf<CreateResource>) -> Protobuf<CreateResource> {
+        data
+    }
+
+    #[tokio::test]
+    async fn test_protobuf() {
+        let cli = TestClient::new(echo);
+        let resp = cli.post("/").body_protobuf(&resource()).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("application/x-protobuf");
+        let data = resp.0.into_body().into_bytes().await.unwrap();
+        assert_eq!(CreateResource::decode(data).unwrap(), resource());
+
+        let resp = cli
+            .post("/")
+            .content_type("application/vnd.google.protobuf; proto=CreateResource")
+            .body(resource().encode_to_vec())
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_extractor_fail() {
+        let cli = TestClient::new(echo.data(BodyLimit(4)));
+        cli.post("/")
+            .content_type("application/json")
+            .body(r#"{"name":"abc","value":100}"#)
+            .send()
+            .await
+            .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        cli.post("/")
+            .content_type("application/x-protobuf")
+            .body(vec![0xff])
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.post("/")
+            .body_protobuf(&resource())
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
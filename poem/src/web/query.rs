@@ -2,13 +2,19 @@ use std::ops::{Deref, DerefMut};
 
 use serde::de::DeserializeOwned;
 
+#[cfg(feature = "serde-qs")]
+use crate::web::UrlEncodedConfig;
 use crate::{error::ParseQueryError, FromRequest, Request, RequestBody, Result};
 
 /// An extractor that can deserialize some type from query string.
 ///
+/// The nested keys, such as `filter[price][gte]=10`, can be enabled by
+/// `UrlEncodedConfig` with the `serde-qs` feature.
+///
 /// # Errors
 ///
 /// - [`ParseQueryError`]
+/// - `ParseQsError` if the nested keys are enabled
 ///
 /// # Example
 ///
@@ -73,6 +79,12 @@ impl<T: DeserializeOwned> Query<T> {
 #[async_trait::async_trait]
 impl<'a, T: DeserializeOwned> FromRequest<'a> for Query<T> {
     async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        #[cfg(feature = "serde-qs")]
+        if let Some(config) = UrlEncodedConfig::nested_of(req) {
+            let query = req.uri().query().unwrap_or_default();
+            return Ok(config.deserialize(query.as_bytes()).map(Self)?);
+        }
+
         Self::internal_from_request(req).await.map_err(Into::into)
     }
 }
@@ -106,4 +118,51 @@ mod tests {
             .await
             .assert_status_is_ok();
     }
+
+    #[cfg(feature = "serde-qs")]
+    #[tokio::test]
+    async fn test_nested_query() {
+        use std::collections::HashMap;
+
+        use crate::{http::StatusCode, EndpointExt};
+
+        #[derive(Deserialize)]
+        struct Params {
+            filter: HashMap<String, HashMap<String, u32>>,
+            ids: Vec<u32>,
+        }
+
+        #[handler(internal)]
+        async fn index(Query(params): Query<Params>) -> String {
+            format!("{} {:?}", params.filter["price"]["gte"], params.ids)
+        }
+
+        let cli = TestClient::new(index.data(UrlEncodedConfig::new().nested(true)));
+        let resp = cli
+            .get("/?filter[price][gte]=10&ids[]=1&ids[]=2")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("10 [1, 2]").await;
+
+        // the percent-encoded brackets
+        let resp = cli
+            .get("/?filter%5Bprice%5D%5Bgte%5D=10&ids%5B0%5D=3")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("10 [3]").await;
+
+        cli.get("/?filter[price][gte]=a&ids[]=1")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        // the nested keys are disabled
+        let cli = TestClient::new(index.data(UrlEncodedConfig::new()));
+        cli.get("/?filter[price][gte]=10&ids[]=1")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
 }
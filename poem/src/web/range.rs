@@ -0,0 +1,468 @@
+use std::{
+    collections::{hash_map::RandomState, VecDeque},
+    hash::{BuildHasher, Hasher},
+    io::SeekFrom,
+    ops,
+};
+
+use bytes::Bytes;
+use headers::ContentRange;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{
+    error::{RangeNotSatisfiableError, ResponseError},
+    http::{header, StatusCode},
+    Body, FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The maximum number of ranges after merging, the whole representation is
+/// served if more ranges are requested, so a request cannot amplify the
+/// response by repeating the ranges.
+///
+/// Reference: <https://www.rfc-editor.org/rfc/rfc7233#section-6.1>
+const MAX_RANGES: usize = 16;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum RangeSpec {
+    /// `first-last`
+    FromTo(u64, u64),
+    /// `first-`
+    AllFrom(u64),
+    /// `-suffix_length`
+    Last(u64),
+}
+
+impl RangeSpec {
+    fn parse(s: &str) -> Option<Self> {
+        let (first, last) = s.trim().split_once('-')?;
+        match (first.trim(), last.trim()) {
+            ("", last) => Some(RangeSpec::Last(last.parse().ok()?)),
+            (first, "") => Some(RangeSpec::AllFrom(first.parse().ok()?)),
+            (first, last) => {
+                let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+                (first <= last).then(|| RangeSpec::FromTo(first, last))
+            }
+        }
+    }
+
+    fn to_satisfiable_range(self, size: u64) -> Option<ops::Range<u64>> {
+        match self {
+            RangeSpec::FromTo(first, last) if first < size => {
+                Some(first..last.saturating_add(1).min(size))
+            }
+            RangeSpec::AllFrom(first) if first < size => Some(first..size),
+            RangeSpec::Last(len) if len > 0 && size > 0 => Some(size.saturating_sub(len)..size),
+            _ => None,
+        }
+    }
+}
+
+/// An extractor for the byte ranges requested by the `Range` header.
+///
+/// The syntactically invalid `Range` header is ignored as required by
+/// [RFC 7233](https://www.rfc-editor.org/rfc/rfc7233#section-3.1), use
+/// [`Range::satisfiable_ranges`] to resolve the ranges with the size of the
+/// representation, or respond with [`RangedBody`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     test::TestClient,
+///     web::{Range, RangedBody},
+///     Route,
+/// };
+///
+/// #[handler]
+/// fn download(range: Range) -> RangedBody<std::io::Cursor<&'static [u8]>> {
+///     let data: &'static [u8] = b"hello, world!";
+///     RangedBody::new(std::io::Cursor::new(data), data.len() as u64, range)
+///         .content_type("text/plain")
+/// }
+///
+/// let app = Route::new().at("/", get(download));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("range", "bytes=7-").send().await;
+/// resp.assert_status(StatusCode::PARTIAL_CONTENT);
+/// resp.assert_header("content-range", "bytes 7-12/13");
+/// resp.assert_text("world!").await;
+/// # });
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Range(Option<Vec<RangeSpec>>);
+
+impl Range {
+    /// Parse the value of the `Range` header, returns an empty range if the
+    /// value is invalid.
+    pub fn parse(value: &str) -> Self {
+        let specs = value
+            .trim()
+            .strip_prefix("bytes=")
+            .and_then(|value| value.split(',').map(RangeSpec::parse).collect());
+        Self(specs)
+    }
+
+    /// Returns `true` if the ranges are requested.
+    #[inline]
+    pub fn is_requested(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Resolves the requested ranges with the size of the representation.
+    ///
+    /// The ranges are sorted, and the overlapping or adjacent ranges are
+    /// merged. Returns `Ok(None)` if the ranges are not requested, cover the
+    /// whole representation, or there are more than 16 ranges after merging.
+    /// The unsatisfiable ranges are skipped, and [`RangeNotSatisfiableError`]
+    /// is returned if none of them can be satisfied.
+    pub fn satisfiable_ranges(
+        &self,
+        size: u64,
+    ) -> Result<Option<Vec<ops::Range<u64>>>, RangeNotSatisfiableError> {
+        let specs = match &self.0 {
+            Some(specs) => specs,
+            None => return Ok(None),
+        };
+        let mut ranges = specs
+            .iter()
+            .filter_map(|spec| spec.to_satisfiable_range(size))
+            .collect::<Vec<_>>();
+        ranges.sort_unstable_by_key(|range| range.start);
+        ranges.dedup_by(|range, prev| {
+            if range.start > prev.end {
+                return false;
+            }
+            prev.end = prev.end.max(range.end);
+            true
+        });
+
+        if ranges.is_empty() {
+            Err(RangeNotSatisfiableError { size })
+        } else if ranges.len() > MAX_RANGES || (ranges.len() == 1 && ranges[0] == (0..size)) {
+            Ok(None)
+        } else {
+            Ok(Some(ranges))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for Range {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .header(header::RANGE)
+            .map(Range::parse)
+            .unwrap_or_default())
+    }
+}
+
+enum Segment {
+    Bytes(Bytes),
+    Data(ops::Range<u64>),
+}
+
+/// A response that serves the ranges requested by [`Range`] from a seekable
+/// reader.
+///
+/// - Responds with `200 OK` and the whole content if the ranges are not
+///   requested.
+/// - Responds with `206 Partial Content` and the `Content-Range` header if a
+///   single range is requested.
+/// - Responds with `206 Partial Content` and a `multipart/byteranges` body if
+///   multiple ranges are requested.
+/// - Responds with `416 Range Not Satisfiable` if none of the ranges can be
+///   satisfied.
+///
+/// The content is streamed from the reader and never buffered in memory.
+pub struct RangedBody<R> {
+    reader: R,
+    size: u64,
+    range: Range,
+    content_type: Option<String>,
+}
+
+impl<R> RangedBody<R>
+where
+    R: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+{
+    /// Create a `RangedBody` with the reader and the size of its content.
+    pub fn new(reader: R, size: u64, range: Range) -> Self {
+        Self {
+            reader,
+            size,
+            range,
+            content_type: None,
+        }
+    }
+
+    /// Sets the content type of the content.
+    #[must_use]
+    pub fn content_type(self, content_type: impl Into<String>) -> Self {
+        Self {
+            content_type: Some(content_type.into()),
+            ..self
+        }
+    }
+}
+
+impl<R> IntoResponse for RangedBody<R>
+where
+    R: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+{
+    fn into_response(self) -> Response {
+        let ranges = match self.range.satisfiable_ranges(self.size) {
+            Ok(ranges) => ranges,
+            Err(err) => return err.as_response(),
+        };
+
+        let mut builder = Response::builder().header(header::ACCEPT_RANGES, "bytes");
+        match ranges.as_deref() {
+            None => {
+                if let Some(content_type) = &self.content_type {
+                    builder = builder.content_type(content_type);
+                }
+                builder
+                    .header(header::CONTENT_LENGTH, self.size)
+                    .body(ranged_stream(self.reader, [Segment::Data(0..self.size)]))
+            }
+            Some([range]) => {
+                let content_range = match ContentRange::bytes(range.clone(), self.size) {
+                    Ok(content_range) => content_range,
+                    Err(_) => return RangeNotSatisfiableError { size: self.size }.as_response(),
+                };
+                if let Some(content_type) = &self.content_type {
+                    builder = builder.content_type(content_type);
+                }
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .typed_header(content_range)
+                    .header(header::CONTENT_LENGTH, range.end - range.start)
+                    .body(ranged_stream(self.reader, [Segment::Data(range.clone())]))
+            }
+            Some(ranges) => {
+                let boundary = format!("{:016x}", RandomState::new().build_hasher().finish());
+                let mut segments = Vec::with_capacity(ranges.len() * 2 + 1);
+                for (idx, range) in ranges.iter().enumerate() {
+                    let mut part = String::new();
+                    if idx > 0 {
+                        part.push_str("\r\n");
+                    }
+                    part.push_str(&format!("--{}\r\n", boundary));
+                    if let Some(content_type) = &self.content_type {
+                        part.push_str(&format!("content-type: {}\r\n", content_type));
+                    }
+                    part.push_str(&format!(
+                        "content-range: bytes {}-{}/{}\r\n\r\n",
+                        range.start,
+                        range.end - 1,
+                        self.size
+                    ));
+                    segments.push(Segment::Bytes(part.into()));
+                    segments.push(Segment::Data(range.clone()));
+                }
+                segments.push(Segment::Bytes(format!("\r\n--{}--\r\n", boundary).into()));
+
+                let content_length: u64 = segments
+                    .iter()
+                    .map(|segment| match segment {
+                        Segment::Bytes(data) => data.len() as u64,
+                        Segment::Data(range) => range.end - range.start,
+                    })
+                    .sum();
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .content_type(format!("multipart/byteranges; boundary={}", boundary))
+                    .header(header::CONTENT_LENGTH, content_length)
+                    .body(ranged_stream(self.reader, segments))
+            }
+        }
+    }
+}
+
+/// Streams the segments, the data segments are read from `reader` in chunks.
+fn ranged_stream<R>(reader: R, segments: impl IntoIterator<Item = Segment>) -> Body
+where
+    R: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+{
+    let segments = segments.into_iter().collect::<VecDeque<_>>();
+    Body::from_bytes_stream(futures_util::stream::try_unfold(
+        (reader, segments),
+        |(mut reader, mut segments)| async move {
+            let data = match segments.pop_front() {
+                Some(Segment::Bytes(data)) => data,
+                Some(Segment::Data(range)) => {
+                    let len = (range.end - range.start).min(CHUNK_SIZE);
+                    let mut data = Vec::with_capacity(len as usize);
+                    reader.seek(SeekFrom::Start(range.start)).await?;
+                    (&mut reader).take(len).read_to_end(&mut data).await?;
+                    if data.is_empty() {
+                        return Err(std::io::ErrorKind::UnexpectedEof.into());
+                    }
+                    let next = range.start + data.len() as u64;
+                    if next < range.end {
+                        segments.push_front(Segment::Data(next..range.end));
+                    }
+                    Bytes::from(data)
+                }
+                None => return Ok::<_, std::io::Error>(None),
+            };
+            Ok(Some((data, (reader, segments))))
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{handler, test::TestClient};
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn satisfiable_ranges() {
+        let ranges = |value: &str, size: u64| Range::parse(value).satisfiable_ranges(size);
+
+        assert_eq!(Range::default().satisfiable_ranges(10), Ok(None));
+        assert_eq!(ranges("bytes=0-4", 10), Ok(Some(vec![0..5])));
+        assert_eq!(ranges("bytes=5-", 10), Ok(Some(vec![5..10])));
+        assert_eq!(ranges("bytes=-3", 10), Ok(Some(vec![7..10])));
+        assert_eq!(ranges("bytes=-30", 10), Ok(None));
+        assert_eq!(ranges("bytes=0-100", 10), Ok(None));
+        assert_eq!(
+            ranges("bytes=0-1, 4-5, 20-30", 10),
+            Ok(Some(vec![0..2, 4..6]))
+        );
+        assert_eq!(ranges("bytes=0-18446744073709551615", 10), Ok(None));
+        assert_eq!(
+            ranges("bytes=5-18446744073709551615", 10),
+            Ok(Some(vec![5..10]))
+        );
+        assert_eq!(
+            ranges("bytes=10-", 10),
+            Err(RangeNotSatisfiableError { size: 10 })
+        );
+        assert_eq!(
+            ranges("bytes=-0", 10),
+            Err(RangeNotSatisfiableError { size: 10 })
+        );
+
+        // the overlapping and adjacent ranges are merged
+        assert_eq!(
+            ranges("bytes=6-7, 0-1, 1-2, 3-4", 10),
+            Ok(Some(vec![0..5, 6..8]))
+        );
+        assert_eq!(ranges("bytes=0-,0-,0-,0-", 10), Ok(None));
+        assert_eq!(ranges("bytes=-5, 0-5", 10), Ok(None));
+        // too many ranges
+        let many = (0..17)
+            .map(|idx| format!("{}-{}", idx * 2, idx * 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(ranges(&format!("bytes={}", many), 100), Ok(None));
+        assert_eq!(
+            ranges(&format!("bytes={}", many), 32)
+                .unwrap()
+                .unwrap()
+                .len(),
+            16
+        );
+
+        // invalid ranges are ignored
+        assert!(!Range::parse("bytes=5-1").is_requested());
+        assert!(!Range::parse("items=0-1").is_requested());
+        assert!(!Range::parse("bytes=a-b").is_requested());
+    }
+
+    #[handler(internal)]
+    fn download(range: Range) -> RangedBody<Cursor<Vec<u8>>> {
+        let data = (0..200u8).collect::<Vec<_>>();
+        RangedBody::new(Cursor::new(data), 200, range).content_type("application/octet-stream")
+    }
+
+    #[tokio::test]
+    async fn ranged_body() {
+        let cli = TestClient::new(download);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("accept-ranges", "bytes");
+        resp.assert_header("content-length", "200");
+        resp.assert_bytes((0..200u8).collect::<Vec<_>>()).await;
+
+        let resp = cli.get("/").header("range", "bytes=10-19").send().await;
+        resp.assert_status(StatusCode::PARTIAL_CONTENT);
+        resp.assert_header("content-range", "bytes 10-19/200");
+        resp.assert_header("content-length", "10");
+        resp.assert_bytes((10..20u8).collect::<Vec<_>>()).await;
+
+        let resp = cli
+            .get("/")
+            .header("range", "bytes=190-18446744073709551615")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::PARTIAL_CONTENT);
+        resp.assert_header("content-range", "bytes 190-199/200");
+        resp.assert_bytes((190..200u8).collect::<Vec<_>>()).await;
+
+        // the repeated ranges are served once
+        let resp = cli
+            .get("/")
+            .header("range", format!("bytes={}", ["0-"; 64].join(",")))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("content-length", "200");
+
+        let resp = cli.get("/").header("range", "bytes=300-").send().await;
+        resp.assert_status(StatusCode::RANGE_NOT_SATISFIABLE);
+        resp.assert_header("content-range", "bytes */200");
+    }
+
+    #[tokio::test]
+    async fn ranged_body_multiple_ranges() {
+        let cli = TestClient::new(download);
+        let resp = cli.get("/").header("range", "bytes=0-1,-2").send().await;
+        resp.assert_status(StatusCode::PARTIAL_CONTENT);
+
+        let content_type = resp.0.content_type().unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let content_length: usize = resp
+            .0
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let body = resp.0.into_body().into_vec().await.unwrap();
+        let mut expected = format!(
+            "--{0}\r\ncontent-type: application/octet-stream\r\ncontent-range: bytes 0-1/200\r\n\r\n",
+            boundary
+        )
+        .into_bytes();
+        expected.extend([0, 1]);
+        expected.extend(
+            format!(
+                "\r\n--{0}\r\ncontent-type: application/octet-stream\r\ncontent-range: bytes 198-199/200\r\n\r\n",
+                boundary
+            )
+            .into_bytes(),
+        );
+        expected.extend([198, 199]);
+        expected.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+        assert_eq!(body, expected);
+        assert_eq!(content_length, expected.len());
+    }
+}
@@ -5,6 +5,11 @@ use rfc7239::{NodeIdentifier, NodeName};
 use crate::{Addr, FromRequest, Request, RequestBody, Result};
 
 /// An extractor that can extracts the real ip from request headers
+///
+/// NOTE: The headers can be forged by the clients, use [`ClientIp`] if the
+/// address is used for the access control.
+///
+/// [`ClientIp`]: crate::web::ClientIp
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct RealIp(pub Option<IpAddr>);
 
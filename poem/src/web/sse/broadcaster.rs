@@ -0,0 +1,290 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Weak,
+    },
+};
+
+use futures_util::{stream::BoxStream, StreamExt};
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+use super::{Event, SSE};
+
+/// What to do when the buffer of a client is full, see
+/// [`Broadcaster::overflow_policy`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest event in the buffer to make room for the new event.
+    DropOldest,
+    /// Drop the new event.
+    DropNewest,
+    /// Disconnect the client, the event stream of the client ends after the
+    /// buffered events.
+    Disconnect,
+}
+
+struct Client {
+    queue: Mutex<VecDeque<Event>>,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+impl Client {
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+}
+
+struct Inner {
+    clients: Mutex<Vec<Weak<Client>>>,
+    dropped_events: AtomicU64,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        for client in self.clients.get_mut().iter().filter_map(Weak::upgrade) {
+            client.close();
+        }
+    }
+}
+
+/// A helper that fans the events out to many SSE clients.
+///
+/// Each client has its own buffer, so a slow client does not block the
+/// others, and the [`OverflowPolicy`] decides what to do when the buffer of a
+/// client is full. The event streams of the clients end when all the clones
+/// of the broadcaster are dropped.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     web::{
+///         sse::{Broadcaster, Event, SSE},
+///         Data,
+///     },
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn events(broadcaster: Data<&Broadcaster>) -> SSE {
+///     broadcaster.sse()
+/// }
+///
+/// #[handler]
+/// fn publish(broadcaster: Data<&Broadcaster>, body: String) -> String {
+///     let received = broadcaster.send(Event::message(body));
+///     format!("sent to {} clients", received)
+/// }
+///
+/// let broadcaster = Broadcaster::new().buffer_size(16);
+/// let app = Route::new()
+///     .at("/events", get(events))
+///     .at("/publish", publish)
+///     .data(broadcaster);
+/// ```
+#[derive(Clone)]
+pub struct Broadcaster {
+    inner: Arc<Inner>,
+    buffer_size: usize,
+    overflow_policy: OverflowPolicy,
+}
+
+impl Default for Broadcaster {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                clients: Default::default(),
+                dropped_events: Default::default(),
+            }),
+            buffer_size: 64,
+            overflow_policy: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+impl Broadcaster {
+    /// Create a new `Broadcaster`, the buffer size of each client is `64` and
+    /// the oldest events are dropped when the buffer is full.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the number of the events that can be buffered for each client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is `0`.
+    #[must_use]
+    pub fn buffer_size(self, size: usize) -> Self {
+        assert!(size > 0, "the buffer size must be greater than 0");
+        Self {
+            buffer_size: size,
+            ..self
+        }
+    }
+
+    /// Sets what to do when the buffer of a client is full.
+    #[must_use]
+    pub fn overflow_policy(self, policy: OverflowPolicy) -> Self {
+        Self {
+            overflow_policy: policy,
+            ..self
+        }
+    }
+
+    /// Subscribes to the events sent after this call.
+    pub fn subscribe(&self) -> BoxStream<'static, Event> {
+        let client = Arc::new(Client {
+            queue: Mutex::new(VecDeque::with_capacity(self.buffer_size)),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+        });
+        self.inner.clients.lock().push(Arc::downgrade(&client));
+
+        futures_util::stream::unfold(client, |client| async move {
+            loop {
+                let event = client.queue.lock().pop_front();
+                if let Some(event) = event {
+                    return Some((event, client));
+                }
+                if client.closed.load(Ordering::SeqCst) {
+                    return None;
+                }
+                client.notify.notified().await;
+            }
+        })
+        .boxed()
+    }
+
+    /// Subscribes to the events sent after this call, and returns an SSE
+    /// response.
+    pub fn sse(&self) -> SSE {
+        SSE::new(self.subscribe())
+    }
+
+    /// Sends the event to all the subscribed clients, returns the number of
+    /// the clients that the event is buffered for.
+    pub fn send(&self, event: Event) -> usize {
+        let mut received = 0;
+        let mut dropped = 0;
+
+        self.inner.clients.lock().retain(|client| {
+            let client = match client.upgrade() {
+                Some(client) => client,
+                None => return false,
+            };
+            let mut queue = client.queue.lock();
+            if queue.len() >= self.buffer_size {
+                dropped += 1;
+                match self.overflow_policy {
+                    OverflowPolicy::DropOldest => {
+                        queue.pop_front();
+                    }
+                    OverflowPolicy::DropNewest => return true,
+                    OverflowPolicy::Disconnect => {
+                        drop(queue);
+                        client.close();
+                        return false;
+                    }
+                }
+            }
+            queue.push_back(event.clone());
+            received += 1;
+            drop(queue);
+            client.notify.notify_one();
+            true
+        });
+
+        self.inner
+            .dropped_events
+            .fetch_add(dropped, Ordering::Relaxed);
+        received
+    }
+
+    /// Returns the number of the subscribed clients.
+    pub fn subscriber_count(&self) -> usize {
+        let mut clients = self.inner.clients.lock();
+        clients.retain(|client| client.strong_count() > 0);
+        clients.len()
+    }
+
+    /// Returns the total number of the events dropped because the buffers of
+    /// the clients are full.
+    pub fn dropped_events(&self) -> u64 {
+        self.inner.dropped_events.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IntoResponse;
+
+    fn messages(events: Vec<Event>) -> Vec<String> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::Message { data, .. } => data,
+                Event::Retry { .. } => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn broadcast() {
+        let broadcaster = Broadcaster::new();
+        assert_eq!(broadcaster.send(Event::message("lost")), 0);
+
+        let a = broadcaster.subscribe();
+        let b = broadcaster.sse();
+        assert_eq!(broadcaster.subscriber_count(), 2);
+        assert_eq!(broadcaster.send(Event::message("1")), 2);
+        assert_eq!(broadcaster.send(Event::message("2")), 2);
+        drop(broadcaster);
+
+        assert_eq!(messages(a.collect().await), vec!["1", "2"]);
+        assert_eq!(
+            b.into_response().into_body().into_string().await.unwrap(),
+            "data: 1\n\ndata: 2\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn unsubscribe() {
+        let broadcaster = Broadcaster::new();
+        let a = broadcaster.subscribe();
+        let _b = broadcaster.subscribe();
+        drop(a);
+        assert_eq!(broadcaster.subscriber_count(), 1);
+        assert_eq!(broadcaster.send(Event::message("1")), 1);
+    }
+
+    #[tokio::test]
+    async fn overflow_policy() {
+        for (policy, expected, received, dropped) in [
+            (OverflowPolicy::DropOldest, vec!["3", "4"], 1, 2),
+            (OverflowPolicy::DropNewest, vec!["1", "2"], 0, 2),
+            (OverflowPolicy::Disconnect, vec!["1", "2"], 0, 1),
+        ] {
+            let broadcaster = Broadcaster::new().buffer_size(2).overflow_policy(policy);
+            let stream = broadcaster.subscribe();
+            broadcaster.send(Event::message("1"));
+            broadcaster.send(Event::message("2"));
+            broadcaster.send(Event::message("3"));
+            assert_eq!(broadcaster.send(Event::message("4")), received);
+            assert_eq!(broadcaster.dropped_events(), dropped);
+            assert_eq!(
+                broadcaster.subscriber_count(),
+                (policy != OverflowPolicy::Disconnect) as usize
+            );
+            drop(broadcaster);
+            assert_eq!(messages(stream.collect().await), expected);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// An extractor that extracts the `Last-Event-ID` header, which is sent by
+/// the reconnecting clients with the ID of the last received event, so that
+/// the stream can be resumed after it.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::stream;
+/// use poem::{
+///     handler,
+///     test::TestClient,
+///     web::sse::{Event, LastEventId, SSE},
+/// };
+///
+/// #[handler]
+/// fn index(last_event_id: LastEventId) -> SSE {
+///     let start = last_event_id
+///         .as_deref()
+///         .and_then(|id| id.parse::<u32>().ok())
+///         .map(|id| id + 1)
+///         .unwrap_or_default();
+///     SSE::new(stream::iter(
+///         (start..3).map(|id| Event::message(id.to_string()).id(id.to_string())),
+///     ))
+/// }
+///
+/// let cli = TestClient::new(index);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("last-event-id", "1").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("id: 2\ndata: 2\n\n").await;
+/// # });
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct LastEventId(pub Option<String>);
+
+impl LastEventId {
+    /// Returns the ID of the last received event, or `None` if the client is
+    /// not reconnecting.
+    #[inline]
+    pub fn as_deref(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for LastEventId {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(Self(
+            req.header("last-event-id")
+                .filter(|id| !id.is_empty())
+                .map(ToString::to_string),
+        ))
+    }
+}
@@ -1,19 +1,24 @@
 //! Server-Sent Events (SSE) types.
 
+mod broadcaster;
 mod event;
+mod last_event_id;
 mod response;
 
+pub use broadcaster::{Broadcaster, OverflowPolicy};
 pub use event::Event;
+pub use last_event_id::LastEventId;
 pub use response::SSE;
 
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
+    use futures_util::StreamExt;
     use tokio::{io::AsyncReadExt, time::Instant};
 
     use super::*;
-    use crate::IntoResponse;
+    use crate::{handler, test::TestClient, IntoResponse};
 
     #[tokio::test]
     async fn sse() {
@@ -71,4 +76,59 @@ data: 5
             s = now;
         }
     }
+
+    #[tokio::test]
+    async fn keep_alive_comment() {
+        let sse = SSE::new(futures_util::stream::pending())
+            .keep_alive(Duration::from_millis(50))
+            .keep_alive_comment("ping");
+        let mut body = sse.into_response().into_body().into_async_read();
+        let mut buf = [0; 16];
+        let n = body.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b":ping\n\n");
+    }
+
+    #[tokio::test]
+    async fn keep_alive_stops_with_stream() {
+        let stream = futures_util::stream::iter(vec![Event::message("a")]).chain(
+            futures_util::stream::once(async {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                Event::message("b")
+            }),
+        );
+        let sse = SSE::new(stream).keep_alive(Duration::from_millis(200));
+        let data = tokio::time::timeout(
+            Duration::from_secs(5),
+            sse.into_response().into_body().into_string(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(data, "data: a\n\n:\n\ndata: b\n\n");
+    }
+
+    #[tokio::test]
+    async fn retry() {
+        let sse = SSE::new(futures_util::stream::iter(vec![Event::message("a")]))
+            .retry(Duration::from_secs(3));
+        let data = sse.into_response().into_body().into_string().await.unwrap();
+        assert_eq!(data, "retry: 3000\n\ndata: a\n\n");
+    }
+
+    #[tokio::test]
+    async fn last_event_id() {
+        #[handler(internal)]
+        fn index(last_event_id: LastEventId) -> String {
+            format!("{:?}", last_event_id.0)
+        }
+
+        let cli = TestClient::new(index);
+        cli.get("/").send().await.assert_text("None").await;
+        cli.get("/")
+            .header("Last-Event-ID", "42")
+            .send()
+            .await
+            .assert_text(r#"Some("42")"#)
+            .await;
+    }
 }
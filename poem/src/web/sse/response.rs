@@ -1,12 +1,17 @@
 use bytes::Bytes;
 use futures_util::{stream::BoxStream, Stream, StreamExt};
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 
 use super::Event;
 use crate::{Body, IntoResponse, Response};
 
 /// An SSE response.
 ///
+/// Use [`SSE::keep_alive`] to send the comments periodically when there are
+/// no events, and [`SSE::retry`] to set the reconnection time of the client.
+/// The reconnecting clients send the ID of the last received event, which can
+/// be extracted with [`LastEventId`](super::LastEventId).
+///
 /// # Example
 ///
 /// ```
@@ -39,6 +44,8 @@ use crate::{Body, IntoResponse, Response};
 pub struct SSE {
     stream: BoxStream<'static, Event>,
     keep_alive: Option<Duration>,
+    keep_alive_comment: String,
+    retry: Option<Duration>,
 }
 
 impl SSE {
@@ -47,10 +54,15 @@ impl SSE {
         Self {
             stream: stream.boxed(),
             keep_alive: None,
+            keep_alive_comment: String::new(),
+            retry: None,
         }
     }
 
     /// Set the keep alive interval.
+    ///
+    /// A comment is sent if no event is sent within the interval, and the
+    /// keep alive is stopped when the event stream ends.
     #[must_use]
     pub fn keep_alive(self, duration: Duration) -> Self {
         Self {
@@ -58,25 +70,61 @@ impl SSE {
             ..self
         }
     }
+
+    /// Set the text of the keep alive comment, defaults to an empty comment.
+    #[must_use]
+    pub fn keep_alive_comment(self, comment: impl Into<String>) -> Self {
+        Self {
+            keep_alive_comment: comment.into(),
+            ..self
+        }
+    }
+
+    /// Set the reconnection time of the client, which is sent before the
+    /// events.
+    #[must_use]
+    pub fn retry(self, duration: Duration) -> Self {
+        Self {
+            retry: Some(duration),
+            ..self
+        }
+    }
 }
 
 impl IntoResponse for SSE {
     fn into_response(self) -> Response {
-        let mut stream = self
-            .stream
+        let retry = self
+            .retry
+            .map(|duration| Event::retry(duration.as_millis() as u64));
+        let mut stream = futures_util::stream::iter(retry)
+            .chain(self.stream)
             .map(|event| Ok::<_, std::io::Error>(Bytes::from(event.to_string())))
             .boxed();
         if let Some(duration) = self.keep_alive {
-            let comment = Bytes::from_static(b":\n\n");
-            stream = futures_util::stream::select(
-                stream,
-                tokio_stream::wrappers::IntervalStream::new(tokio::time::interval_at(
-                    tokio::time::Instant::now() + duration,
-                    duration,
-                ))
-                .map(move |_| Ok(comment.clone())),
-            )
-            .boxed();
+            let comment = if self.keep_alive_comment.is_empty() {
+                Bytes::from_static(b":\n\n")
+            } else {
+                let lines = self
+                    .keep_alive_comment
+                    .lines()
+                    .map(|line| format!(":{}\n", line))
+                    .collect::<String>();
+                Bytes::from(format!("{}\n", lines))
+            };
+            let sleep = Box::pin(tokio::time::sleep(duration));
+            stream =
+                futures_util::stream::unfold((stream, sleep), move |(mut stream, mut sleep)| {
+                    let comment = comment.clone();
+                    async move {
+                        let item = tokio::select! {
+                            item = stream.next() => item?,
+                            _ = sleep.as_mut() => Ok(comment),
+                        };
+                        sleep.as_mut().reset(Instant::now() + duration);
+                        Some((item, (stream, sleep)))
+                    }
+                })
+                .boxed();
         }
 
         Response::builder()
@@ -1,5 +1,4 @@
 use std::{
-    collections::Bound,
     fs::Metadata,
     io::{Seek, SeekFrom},
     path::Path,
@@ -9,7 +8,6 @@ use std::{
 
 use headers::{
     ContentRange, ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince,
-    Range,
 };
 use http::{header, StatusCode};
 use httpdate::HttpDate;
@@ -17,7 +15,9 @@ use mime::Mime;
 use tokio::{fs::File, io::AsyncReadExt};
 
 use crate::{
-    error::StaticFileError, Body, FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+    error::{ResponseError, StaticFileError},
+    web::Range,
+    Body, FromRequest, IntoResponse, Request, RequestBody, Response, Result,
 };
 
 /// A response for static file extractor.
@@ -68,9 +68,15 @@ impl IntoResponse for StaticFileResponse {
                 }
 
                 if let Some((range, size)) = content_range {
+                    let content_range = match ContentRange::bytes(range, size) {
+                        Ok(content_range) => content_range,
+                        Err(_) => {
+                            return StaticFileError::RangeNotSatisfiable { size }.as_response()
+                        }
+                    };
                     builder = builder
                         .status(StatusCode::PARTIAL_CONTENT)
-                        .typed_header(ContentRange::bytes(range, size).unwrap());
+                        .typed_header(content_range);
                 }
 
                 builder.body(body)
@@ -87,7 +93,7 @@ pub struct StaticFileRequest {
     if_unmodified_since: Option<IfUnmodifiedSince>,
     if_none_match: Option<IfNoneMatch>,
     if_modified_since: Option<IfModifiedSince>,
-    range: Option<Range>,
+    range: Range,
 }
 
 #[async_trait::async_trait]
@@ -98,7 +104,7 @@ impl<'a> FromRequest<'a> for StaticFileRequest {
             if_unmodified_since: req.headers().typed_get::<IfUnmodifiedSince>(),
             if_none_match: req.headers().typed_get::<IfNoneMatch>(),
             if_modified_since: req.headers().typed_get::<IfModifiedSince>(),
-            range: req.headers().typed_get::<Range>(),
+            range: Range::from_request_without_body(req).await?,
         })
     }
 }
@@ -168,30 +174,17 @@ impl StaticFileRequest {
 
         let mut content_range = None;
 
-        let body = if let Some((start, end)) = self.range.and_then(|range| range.iter().next()) {
-            let start = match start {
-                Bound::Included(n) => n,
-                Bound::Excluded(n) => n + 1,
-                Bound::Unbounded => 0,
-            };
-            let end = match end {
-                Bound::Included(n) => n + 1,
-                Bound::Excluded(n) => n,
-                Bound::Unbounded => metadata.len(),
-            };
-            if end < start || end > metadata.len() {
-                return Err(StaticFileError::RangeNotSatisfiable {
-                    size: metadata.len(),
-                });
-            }
-
-            if start != 0 || end != metadata.len() {
-                content_range = Some((start..end, metadata.len()));
-            }
-
-            content_length = end - start;
-            file.seek(SeekFrom::Start(start))?;
-            Body::from_async_read(File::from_std(file).take(end - start))
+        // only the first range is served, use `RangedBody` for the multiple ranges
+        let ranges = self
+            .range
+            .satisfiable_ranges(metadata.len())
+            .map_err(|err| StaticFileError::RangeNotSatisfiable { size: err.size })?;
+        let body = if let Some(range) = ranges.and_then(|ranges| ranges.into_iter().next()) {
+            content_length = range.end - range.start;
+            file.seek(SeekFrom::Start(range.start))?;
+            let body = Body::from_async_read(File::from_std(file).take(content_length));
+            content_range = Some((range, metadata.len()));
+            body
         } else {
             Body::from_async_read(File::from_std(file))
         };
@@ -273,6 +266,8 @@ fn etag(ino: u64, modified: &SystemTime, len: u64) -> String {
 mod tests {
     use std::{path::Path, time::Duration};
 
+    use headers::Range;
+
     use super::*;
 
     impl StaticFileResponse {
@@ -471,13 +466,60 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_range_suffix() {
+        let md = std::fs::metadata("Cargo.toml").unwrap();
+
+        let static_file = StaticFileRequest::from_request_without_body(
+            &Request::builder().header("range", "bytes=-10").finish(),
+        )
+        .await
+        .unwrap();
+        let resp = static_file
+            .create_response(Path::new("Cargo.toml"), false)
+            .unwrap();
+        match resp {
+            StaticFileResponse::Ok { content_range, .. } => {
+                assert_eq!(content_range.unwrap().0, md.len() - 10..md.len());
+            }
+            StaticFileResponse::NotModified => panic!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_last_overflow() {
+        let md = std::fs::metadata("Cargo.toml").unwrap();
+
+        let static_file = StaticFileRequest::from_request_without_body(
+            &Request::builder()
+                .header("range", "bytes=10-18446744073709551615")
+                .finish(),
+        )
+        .await
+        .unwrap();
+        let resp = static_file
+            .create_response(Path::new("Cargo.toml"), false)
+            .unwrap();
+        match resp {
+            StaticFileResponse::Ok {
+                content_length,
+                content_range,
+                ..
+            } => {
+                assert_eq!(content_range.unwrap().0, 10..md.len());
+                assert_eq!(content_length, md.len() - 10);
+            }
+            StaticFileResponse::NotModified => panic!(),
+        }
+    }
+
     #[tokio::test]
     async fn test_range_413() {
         let md = std::fs::metadata("Cargo.toml").unwrap();
 
         let static_file = StaticFileRequest::from_request_without_body(
             &Request::builder()
-                .typed_header(Range::bytes(0..md.len() + 1).unwrap())
+                .typed_header(Range::bytes(md.len()..md.len() + 1).unwrap())
                 .finish(),
         )
         .await
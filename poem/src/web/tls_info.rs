@@ -0,0 +1,212 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    error::MissingTlsInfoError,
+    web::{AlpnProtocol, ClientCert},
+    FromRequest, Request, RequestBody, Result,
+};
+
+/// The version of the TLS protocol.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TlsVersion {
+    /// TLS 1.0
+    Tls1_0,
+    /// TLS 1.1
+    Tls1_1,
+    /// TLS 1.2
+    Tls1_2,
+    /// TLS 1.3
+    Tls1_3,
+}
+
+impl Display for TlsVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TlsVersion::Tls1_0 => "TLSv1.0",
+            TlsVersion::Tls1_1 => "TLSv1.1",
+            TlsVersion::Tls1_2 => "TLSv1.2",
+            TlsVersion::Tls1_3 => "TLSv1.3",
+        })
+    }
+}
+
+/// An extractor that can extracts the parameters negotiated in the TLS
+/// handshake of the connection.
+///
+/// It is only available if the connection is accepted by a TLS listener, use
+/// `Option<TlsInfo>` if the endpoint also accepts the plain connections.
+///
+/// Which parameters are available depends on the TLS listener, the
+/// `native-tls` listener only reports the peer certificate.
+///
+/// # Errors
+///
+/// - [`MissingTlsInfoError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     web::{TlsInfo, TlsVersion},
+///     Result,
+/// };
+///
+/// #[handler]
+/// fn index(tls_info: TlsInfo) -> Result<String> {
+///     if tls_info.version() < Some(TlsVersion::Tls1_3) {
+///         return Err(StatusCode::UPGRADE_REQUIRED.into());
+///     }
+///     Ok(format!(
+///         "{} {}",
+///         tls_info.server_name().unwrap_or("-"),
+///         tls_info.cipher_suite().unwrap_or("-")
+///     ))
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct TlsInfo {
+    version: Option<TlsVersion>,
+    cipher_suite: Option<String>,
+    server_name: Option<String>,
+    alpn_protocol: Option<AlpnProtocol>,
+    peer_cert: Option<ClientCert>,
+}
+
+impl TlsInfo {
+    /// Create an empty `TlsInfo`.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the negotiated protocol version.
+    #[must_use]
+    pub fn with_version(self, version: TlsVersion) -> Self {
+        Self {
+            version: Some(version),
+            ..self
+        }
+    }
+
+    /// Sets the IANA name of the negotiated cipher suite.
+    #[must_use]
+    pub fn with_cipher_suite(self, cipher_suite: impl Into<String>) -> Self {
+        Self {
+            cipher_suite: Some(cipher_suite.into()),
+            ..self
+        }
+    }
+
+    /// Sets the server name sent by the client with SNI.
+    #[must_use]
+    pub fn with_server_name(self, server_name: impl Into<String>) -> Self {
+        Self {
+            server_name: Some(server_name.into()),
+            ..self
+        }
+    }
+
+    /// Sets the application protocol negotiated with ALPN.
+    #[must_use]
+    pub fn with_alpn_protocol(self, alpn_protocol: AlpnProtocol) -> Self {
+        Self {
+            alpn_protocol: Some(alpn_protocol),
+            ..self
+        }
+    }
+
+    /// Sets the certificate chain presented by the client.
+    #[must_use]
+    pub fn with_peer_cert(self, peer_cert: ClientCert) -> Self {
+        Self {
+            peer_cert: Some(peer_cert),
+            ..self
+        }
+    }
+
+    /// Returns the negotiated protocol version.
+    #[inline]
+    pub fn version(&self) -> Option<TlsVersion> {
+        self.version
+    }
+
+    /// Returns the IANA name of the negotiated cipher suite, such as
+    /// `TLS_AES_128_GCM_SHA256`.
+    #[inline]
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite.as_deref()
+    }
+
+    /// Returns the server name sent by the client with SNI.
+    #[inline]
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Returns the application protocol negotiated with ALPN.
+    #[inline]
+    pub fn alpn_protocol(&self) -> Option<&AlpnProtocol> {
+        self.alpn_protocol.as_ref()
+    }
+
+    /// Returns the certificate chain presented by the client, which is only
+    /// available if the client authentication is enabled.
+    #[inline]
+    pub fn peer_cert(&self) -> Option<&ClientCert> {
+        self.peer_cert.as_ref()
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for TlsInfo {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        req.extensions()
+            .get::<TlsInfo>()
+            .cloned()
+            .ok_or_else(|| MissingTlsInfoError.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::StatusCode, test::TestClient};
+
+    #[tokio::test]
+    async fn extract_tls_info() {
+        #[handler(internal)]
+        fn index(tls_info: TlsInfo) -> String {
+            format!(
+                "{} {:?} {:?}",
+                tls_info.version().unwrap(),
+                tls_info.server_name(),
+                tls_info.peer_cert().map(|cert| cert.leaf().len())
+            )
+        }
+
+        let cli = TestClient::new(index);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        let tls_info = TlsInfo::new()
+            .with_version(TlsVersion::Tls1_2)
+            .with_server_name("example.com")
+            .with_peer_cert(ClientCert::new(vec![vec![1, 2, 3]]));
+        cli.get("/")
+            .data(tls_info)
+            .send()
+            .await
+            .assert_text(r#"TLSv1.2 Some("example.com") Some(3)"#)
+            .await;
+    }
+
+    #[test]
+    fn version_order() {
+        assert!(TlsVersion::Tls1_3 > TlsVersion::Tls1_2);
+        assert!(Some(TlsVersion::Tls1_0) > None);
+    }
+}
@@ -6,6 +6,12 @@ use crate::{error::ParseTypedHeaderError, FromRequest, Request, RequestBody, Res
 
 /// An extractor that extracts a typed header value.
 ///
+/// The header can be any type implementing [`Header`] of the
+/// [`headers`](crate::web::headers) crate, such as
+/// `Authorization<Bearer>` and `Range`. If the header is missing or invalid,
+/// the request is rejected with `400 Bad Request`, use
+/// `Option<TypedHeader<T>>` for the optional headers.
+///
 /// # Errors
 ///
 /// - [`ParseTypedHeaderError`]
@@ -77,8 +83,9 @@ mod tests {
     use super::*;
     use crate::{
         handler,
+        http::StatusCode,
         test::TestClient,
-        web::headers::{ContentLength, Host},
+        web::headers::{authorization::Bearer, Authorization, ContentLength, Host, Range},
     };
 
     #[tokio::test]
@@ -98,6 +105,62 @@ mod tests {
         resp.assert_status_is_ok();
     }
 
+    #[tokio::test]
+    async fn test_typed_header_authorization() {
+        #[handler(internal)]
+        fn index(TypedHeader(auth): TypedHeader<Authorization<Bearer>>) -> String {
+            auth.token().to_string()
+        }
+
+        let cli = TestClient::new(index);
+        let resp = cli
+            .get("/")
+            .header("authorization", "Bearer abc")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("abc").await;
+
+        cli.get("/")
+            .header("authorization", "Basic YTpi")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_typed_header_range() {
+        #[handler(internal)]
+        fn index(range: Option<TypedHeader<Range>>) -> String {
+            match range {
+                Some(TypedHeader(range)) => format!("{:?}", range.iter().collect::<Vec<_>>()),
+                None => "none".to_string(),
+            }
+        }
+
+        let cli = TestClient::new(index);
+        let resp = cli.get("/").header("range", "bytes=0-9").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("[(Included(0), Included(9))]").await;
+
+        let resp = cli.get("/").send().await;
+        resp.assert_text("none").await;
+
+        let (req, mut body) = Request::builder()
+            .header("range", "lines=a-b")
+            .body("")
+            .split();
+        let err = TypedHeader::<Range>::from_request(&req, &mut body)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert!(err.is::<ParseTypedHeaderError>());
+    }
+
     #[tokio::test]
     async fn test_typed_header_extractor_error() {
         let (req, mut body) = Request::builder().body("abc").split();
@@ -0,0 +1,106 @@
+use serde::de::DeserializeOwned;
+
+use crate::{error::ParseQsError, Request};
+
+/// The configuration of parsing the query strings and the form bodies by
+/// [`Query`](crate::web::Query) and [`Form`](crate::web::Form).
+///
+/// The flat `key=value` pairs are parsed by default. If the nested keys are
+/// enabled, the bracketed keys are parsed into the nested structs, maps and
+/// sequences with [`serde_qs`](https://crates.io/crates/serde_qs), such as
+/// `filter[price][gte]=10` and `ids[]=1&ids[]=2`.
+///
+/// Add it to the requests with [`EndpointExt::data`](crate::EndpointExt::data).
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     test::TestClient,
+///     web::{Query, UrlEncodedConfig},
+///     EndpointExt, Route,
+/// };
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Price {
+///     gte: u32,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Filter {
+///     price: Price,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Params {
+///     filter: Filter,
+///     ids: Vec<u32>,
+/// }
+///
+/// #[handler]
+/// fn index(Query(params): Query<Params>) -> String {
+///     format!("{} {:?}", params.filter.price.gte, params.ids)
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .data(UrlEncodedConfig::new().nested(true));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = TestClient::new(app)
+///     .get("/?filter[price][gte]=10&ids[]=1&ids[]=2")
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("10 [1, 2]").await;
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-qs")))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UrlEncodedConfig {
+    nested: bool,
+    max_depth: usize,
+}
+
+impl Default for UrlEncodedConfig {
+    fn default() -> Self {
+        Self {
+            nested: false,
+            max_depth: 5,
+        }
+    }
+}
+
+impl UrlEncodedConfig {
+    /// Create a `UrlEncodedConfig`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Parses the bracketed keys into the nested values. Default is `false`.
+    #[must_use]
+    pub fn nested(self, nested: bool) -> Self {
+        Self { nested, ..self }
+    }
+
+    /// Sets the maximum depth of the nested keys, the deeper brackets are
+    /// parsed as a part of the key. Default is `5`.
+    #[must_use]
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// Returns the configuration of the request if the nested keys are
+    /// enabled.
+    pub(crate) fn nested_of(req: &Request) -> Option<&Self> {
+        req.data::<Self>().filter(|config| config.nested)
+    }
+
+    pub(crate) fn deserialize<T: DeserializeOwned>(&self, input: &[u8]) -> Result<T, ParseQsError> {
+        // the brackets are percent-encoded by the browsers, so the strict mode
+        // is disabled
+        Ok(serde_qs::Config::new(self.max_depth, false).deserialize_bytes(input)?)
+    }
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{error::UrlForError, route::build_path, FromRequest, Request, RequestBody, Result};
+
+/// An extractor that generates the URLs of the routes named by
+/// [`Route::at_named`](crate::Route::at_named).
+///
+/// The names of the routes which are nested directly with
+/// [`Route::nest`](crate::Route::nest) or
+/// [`Route::nest_no_strip`](crate::Route::nest_no_strip) are also available,
+/// and the paths include the prefix of the nesting.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::StatusCode,
+///     test::TestClient,
+///     web::{Path, Redirect, UrlFor},
+///     Route,
+/// };
+///
+/// #[handler]
+/// fn user_detail(Path(id): Path<String>) -> String {
+///     format!("user: {}", id)
+/// }
+///
+/// #[handler]
+/// fn me(url_for: UrlFor) -> poem::Result<Redirect> {
+///     Ok(Redirect::see_other(
+///         url_for.url("user_detail", &[("id", "1")])?,
+///     ))
+/// }
+///
+/// let app = Route::new().nest(
+///     "/api",
+///     Route::new()
+///         .at_named("/users/:id", get(user_detail), "user_detail")
+///         .at("/me", get(me)),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// let resp = cli.get("/api/me").send().await;
+/// resp.assert_status(StatusCode::SEE_OTHER);
+/// resp.assert_header("location", "/api/users/1");
+/// # });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UrlFor(pub(crate) Arc<HashMap<String, String>>);
+
+impl UrlFor {
+    /// Returns the path of the route with the specified name, the parameters
+    /// in the path are replaced with the values in `params`.
+    pub fn url(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlForError> {
+        let pattern = self
+            .0
+            .get(name)
+            .ok_or_else(|| UrlForError::NotFound(name.to_string()))?;
+        build_path(name, pattern, params)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for UrlFor {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req
+            .extensions()
+            .get::<UrlFor>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url() {
+        let url_for = UrlFor(Arc::new(
+            [
+                ("a", "/a/:id"),
+                ("b", "/b/:name<\\d+>/*path"),
+                ("c", "/c/<\\d+>"),
+            ]
+            .into_iter()
+            .map(|(name, path)| (name.to_string(), path.to_string()))
+            .collect(),
+        ));
+
+        assert_eq!(url_for.url("a", &[("id", "1")]).unwrap(), "/a/1");
+        assert_eq!(
+            url_for.url("a", &[("id", "a b/c")]).unwrap(),
+            "/a/a%20b%2Fc"
+        );
+        assert_eq!(
+            url_for
+                .url("b", &[("name", "10"), ("path", "x/y z")])
+                .unwrap(),
+            "/b/10/x/y%20z"
+        );
+        assert_eq!(
+            url_for.url("a", &[]).unwrap_err(),
+            UrlForError::MissingParam {
+                name: "a".to_string(),
+                param: "id".to_string()
+            }
+        );
+        assert_eq!(
+            url_for.url("c", &[]).unwrap_err(),
+            UrlForError::UnnamedParam("c".to_string())
+        );
+        assert_eq!(
+            url_for.url("d", &[]).unwrap_err(),
+            UrlForError::NotFound("d".to_string())
+        );
+    }
+}
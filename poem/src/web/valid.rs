@@ -0,0 +1,209 @@
+use std::ops::{Deref, DerefMut};
+
+use validator::Validate;
+
+use crate::{error::ValidateError, FromRequest, Request, RequestBody, Result};
+
+/// An extractor that validates the value extracted by the inner extractor
+/// with the [`validator`](https://crates.io/crates/validator) crate.
+///
+/// The inner extractor can be any extractor dereferencing to a type which
+/// implements [`Validate`], such as [`Json`](crate::web::Json),
+/// [`Query`](crate::web::Query) and [`Form`](crate::web::Form). The errors
+/// of the inner extractor are returned as they are.
+///
+/// # Errors
+///
+/// - The errors of the inner extractor
+/// - [`ValidateError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     http::StatusCode,
+///     post,
+///     test::TestClient,
+///     web::{Json, Valid},
+///     Route,
+/// };
+/// use serde::Deserialize;
+/// use validator::Validate;
+///
+/// #[derive(Deserialize, Validate)]
+/// struct User {
+///     #[validate(length(min = 1, max = 16))]
+///     name: String,
+///     #[validate(email)]
+///     email: String,
+/// }
+///
+/// #[handler]
+/// async fn index(Valid(Json(user)): Valid<Json<User>>) -> String {
+///     format!("welcome {}!", user.name)
+/// }
+///
+/// let app = Route::new().at("/", post(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli
+///     .post("/")
+///     .body_json(&serde_json::json!({ "name": "foo", "email": "foo@example.com" }))
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("welcome foo!").await;
+///
+/// let resp = cli
+///     .post("/")
+///     .body_json(&serde_json::json!({ "name": "", "email": "foo" }))
+///     .send()
+///     .await;
+/// resp.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "validator")))]
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Valid<T>(pub T);
+
+impl<T> Deref for Valid<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Valid<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, T> FromRequest<'a> for Valid<T>
+where
+    T: FromRequest<'a> + Deref + Send,
+    T::Target: Validate,
+{
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let value = T::from_request(req, body).await?;
+        value.deref().validate().map_err(ValidateError)?;
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        handler,
+        http::StatusCode,
+        test::TestClient,
+        web::{Json, Query},
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Address {
+        #[validate(length(min = 1))]
+        city: String,
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct User {
+        #[validate(length(min = 1, max = 8))]
+        name: String,
+        #[validate(range(min = 18, message = "too young"))]
+        age: u8,
+        #[validate]
+        address: Address,
+    }
+
+    #[tokio::test]
+    async fn valid_json() {
+        #[handler(internal)]
+        fn index(Valid(Json(user)): Valid<Json<User>>) -> String {
+            user.name
+        }
+
+        let cli = TestClient::new(index);
+
+        let resp = cli
+            .post("/")
+            .body_json(&json!({ "name": "foo", "age": 18, "address": { "city": "a" } }))
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("foo").await;
+
+        let resp = cli
+            .post("/")
+            .body_json(&json!({ "name": "", "age": 17, "address": { "city": "" } }))
+            .send()
+            .await;
+        resp.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        resp.assert_content_type("application/json; charset=utf-8");
+        resp.assert_json(json!({
+            "errors": [
+                {
+                    "field": "address.city",
+                    "code": "length",
+                    "message": null,
+                    "params": { "min": 1, "value": "" },
+                },
+                {
+                    "field": "age",
+                    "code": "range",
+                    "message": "too young",
+                    "params": { "min": 18.0, "value": 17 },
+                },
+                {
+                    "field": "name",
+                    "code": "length",
+                    "message": null,
+                    "params": { "min": 1, "max": 8, "value": "" },
+                },
+            ]
+        }))
+        .await;
+
+        // the errors of the inner extractor
+        cli.post("/")
+            .body_json(&json!({ "name": "foo" }))
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn valid_query() {
+        #[derive(Debug, Deserialize, Validate)]
+        struct Params {
+            #[validate(range(max = 100))]
+            limit: u32,
+        }
+
+        #[handler(internal)]
+        fn index(params: Valid<Query<Params>>) -> String {
+            params.limit.to_string()
+        }
+
+        let cli = TestClient::new(index);
+        let resp = cli.get("/").query("limit", &10).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("10").await;
+
+        let (req, mut body) = Request::builder().uri_str("/?limit=1000").finish().split();
+        let err = Valid::<Query<Params>>::from_request(&req, &mut body)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let err = err.downcast_ref::<ValidateError>().unwrap();
+        assert!(err.0.field_errors().contains_key("limit"));
+    }
+}
@@ -13,11 +13,14 @@ use crate::{
 /// XML extractor and response.
 ///
 /// To extract the specified type of XML from the body, `T` must implement
-/// [`serde::Deserialize`].
+/// [`serde::Deserialize`]. The `Content-Type` of the request must be
+/// `application/xml`, `text/xml` or `application/*+xml`, such as
+/// `application/soap+xml`.
 ///
 /// # Errors
 ///
 /// - [`ReadBodyError`](crate::error::ReadBodyError)
+/// - [`MissingXmlContentTypeError`]
 /// - [`ParseXmlError`]
 ///
 /// ```
@@ -126,7 +129,7 @@ fn is_xml_content_type(req: &Request) -> bool {
             .header(header::CONTENT_TYPE)
             .and_then(|value| value.parse::<mime::Mime>().ok()),
         Some(content_type)
-            if content_type.type_() == "application"
+            if (content_type.type_() == "application" || content_type.type_() == "text")
                 && (content_type.subtype() == "xml"
                     || content_type.suffix().map_or(false, |v| v == "xml"))
     )
@@ -200,6 +203,46 @@ mod tests {
             .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
     }
 
+    #[tokio::test]
+    async fn test_xml_content_type() {
+        #[handler(internal)]
+        async fn index(query: Xml<CreateResource>) -> String {
+            query.0.name
+        }
+
+        let cli = TestClient::new(index);
+        for content_type in [
+            "application/xml",
+            "text/xml; charset=utf-8",
+            "application/soap+xml",
+        ] {
+            let resp = cli
+                .post("/")
+                .content_type(content_type)
+                .body(r#"<CreateResource name="abc" value="100"/>"#)
+                .send()
+                .await;
+            resp.assert_status_is_ok();
+            resp.assert_text("abc").await;
+        }
+
+        for content_type in ["application/json", "text/plain"] {
+            cli.post("/")
+                .content_type(content_type)
+                .body(r#"<CreateResource name="abc" value="100"/>"#)
+                .send()
+                .await
+                .assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+
+        cli.post("/")
+            .content_type("application/xml")
+            .body(r#"<CreateResource name="abc" value="a"/>"#)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_xml_response() {
         #[handler(internal)]